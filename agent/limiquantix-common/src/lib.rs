@@ -16,8 +16,14 @@
 //! log_success!("vm", "VM created successfully");
 //! log_vm!("start", "vm-123", "Starting VM with 4GB RAM");
 //! ```
+//!
+//! ## Tree Archives
+//!
+//! [`tree_archive`] streams directory trees with per-file checksums for
+//! transfers through the guest agent.
 
 pub mod logging;
+pub mod tree_archive;
 
 // Re-export logging functions
 pub use logging::{
@@ -0,0 +1,1169 @@
+//! # Directory Tree Archives
+//!
+//! A small streaming archive format (QTA1) used to move whole directory trees
+//! between the host and the guest agent in a single ordered byte stream.
+//!
+//! ## Layout
+//! ```text
+//! "QTA1"
+//! entry*            kind u8 (1 = dir, 2 = file, 3 = symlink)
+//!                   path_len u16 + path (relative, '/'-separated, UTF-8)
+//!                   mode u32, mtime_secs i64, mtime_nanos u32, size u64
+//!                   link_len u16 + link target
+//!                   file entries: `size` bytes of content + SHA256 (32 bytes)
+//! 0u8               end of archive
+//! ```
+//! All integers are big-endian. The SHA256 of the whole byte stream is
+//! exchanged out of band and checked by the receiver once the end marker has
+//! been processed.
+//!
+//! [`ArchiveWriter`] produces the stream deterministically (entries sorted by
+//! name), so a sender that lost its position can rebuild the archive and
+//! [`ArchiveWriter::skip_to`] the offset the receiver acknowledged.
+//! [`ArchiveReader`] consumes the stream in arbitrarily sized pieces and
+//! extracts it below a destination directory without following symlinks.
+//! Only the rwx permission bits of entries are restored.
+
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use sha2::{Digest, Sha256};
+
+use fsops::Dir;
+
+/// Magic bytes at the start of every archive.
+pub const MAGIC: &[u8; 4] = b"QTA1";
+
+/// Suffix of files that are still being received.
+pub const PARTIAL_SUFFIX: &str = ".qtpart";
+
+const KIND_END: u8 = 0;
+const KIND_DIR: u8 = 1;
+const KIND_FILE: u8 = 2;
+const KIND_SYMLINK: u8 = 3;
+
+/// Fixed part of an entry header after the path: mode, mtime, size, link_len.
+const FIXED_HEADER_LEN: usize = 4 + 8 + 4 + 8 + 2;
+const DIGEST_LEN: usize = 32;
+
+/// Counts describing a tree, and the archive digest once it is known.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TreeSummary {
+    pub files: u64,
+    pub directories: u64,
+    pub symlinks: u64,
+    /// File content bytes, excluding archive framing
+    pub bytes: u64,
+    /// Hex SHA256 of the whole archive (empty until the stream is complete)
+    pub archive_sha256: String,
+}
+
+#[derive(Debug, Clone)]
+struct Header {
+    kind: u8,
+    path: String,
+    mode: u32,
+    mtime: (i64, u32),
+    size: u64,
+    link: String,
+}
+
+impl Header {
+    fn encode(&self) -> io::Result<Vec<u8>> {
+        let path_len = u16::try_from(self.path.len())
+            .map_err(|_| invalid(format!("path too long: {}", self.path)))?;
+        let link_len = u16::try_from(self.link.len())
+            .map_err(|_| invalid(format!("symlink target too long: {}", self.path)))?;
+
+        let mut buf = Vec::with_capacity(1 + 2 + self.path.len() + FIXED_HEADER_LEN + self.link.len());
+        buf.push(self.kind);
+        buf.extend_from_slice(&path_len.to_be_bytes());
+        buf.extend_from_slice(self.path.as_bytes());
+        buf.extend_from_slice(&self.mode.to_be_bytes());
+        buf.extend_from_slice(&self.mtime.0.to_be_bytes());
+        buf.extend_from_slice(&self.mtime.1.to_be_bytes());
+        buf.extend_from_slice(&self.size.to_be_bytes());
+        buf.extend_from_slice(&link_len.to_be_bytes());
+        buf.extend_from_slice(self.link.as_bytes());
+        Ok(buf)
+    }
+
+    /// Parse a header from the start of `buf`.
+    ///
+    /// Returns `None` if more bytes are needed, otherwise the header and the
+    /// number of bytes it occupied.
+    fn decode(buf: &[u8]) -> io::Result<Option<(Header, usize)>> {
+        if buf.len() < 3 {
+            return Ok(None);
+        }
+        let kind = buf[0];
+        if !matches!(kind, KIND_DIR | KIND_FILE | KIND_SYMLINK) {
+            return Err(invalid(format!("unknown archive entry kind {}", kind)));
+        }
+        let path_len = u16::from_be_bytes([buf[1], buf[2]]) as usize;
+        let fixed_start = 3 + path_len;
+        if buf.len() < fixed_start + FIXED_HEADER_LEN {
+            return Ok(None);
+        }
+        let fixed = &buf[fixed_start..fixed_start + FIXED_HEADER_LEN];
+        let link_len = u16::from_be_bytes([fixed[24], fixed[25]]) as usize;
+        let total = fixed_start + FIXED_HEADER_LEN + link_len;
+        if buf.len() < total {
+            return Ok(None);
+        }
+
+        let path = String::from_utf8(buf[3..fixed_start].to_vec())
+            .map_err(|_| invalid("archive path is not UTF-8"))?;
+        let link = String::from_utf8(buf[fixed_start + FIXED_HEADER_LEN..total].to_vec())
+            .map_err(|_| invalid("symlink target is not UTF-8"))?;
+
+        let header = Header {
+            kind,
+            path,
+            mode: u32::from_be_bytes(fixed[0..4].try_into().unwrap()),
+            mtime: (
+                i64::from_be_bytes(fixed[4..12].try_into().unwrap()),
+                u32::from_be_bytes(fixed[12..16].try_into().unwrap()),
+            ),
+            size: u64::from_be_bytes(fixed[16..24].try_into().unwrap()),
+            link,
+        };
+        Ok(Some((header, total)))
+    }
+}
+
+// ============================================================================
+// Writer
+// ============================================================================
+
+struct PlannedEntry {
+    header: Header,
+    source: PathBuf,
+}
+
+struct FileBody {
+    file: File,
+    remaining: u64,
+    hasher: Sha256,
+    path: String,
+}
+
+/// Streams a file or directory tree as a QTA1 archive through [`Read`].
+///
+/// The tree is walked when the writer is created; file contents are read
+/// lazily. A file that shrinks while it is being archived fails the read.
+pub struct ArchiveWriter {
+    entries: Vec<PlannedEntry>,
+    next: usize,
+    staged: Vec<u8>,
+    staged_pos: usize,
+    body: Option<FileBody>,
+    ended: bool,
+    offset: u64,
+    hasher: Sha256,
+    summary: TreeSummary,
+}
+
+impl ArchiveWriter {
+    /// Plan an archive of `root`.
+    ///
+    /// A directory contributes its contents (not itself); a single file is
+    /// archived under its own name.
+    pub fn new(root: &Path) -> io::Result<Self> {
+        let meta = fs::symlink_metadata(root)?;
+        let mut entries = Vec::new();
+
+        if meta.is_dir() {
+            walk(root, "", &mut entries)?;
+        } else {
+            let name = root
+                .file_name()
+                .and_then(|n| n.to_str())
+                .ok_or_else(|| invalid(format!("unsupported file name: {}", root.display())))?;
+            entries.push(plan_entry(root, name.to_string(), &meta)?);
+        }
+
+        let mut summary = TreeSummary::default();
+        for entry in &entries {
+            match entry.header.kind {
+                KIND_DIR => summary.directories += 1,
+                KIND_FILE => {
+                    summary.files += 1;
+                    summary.bytes += entry.header.size;
+                }
+                _ => summary.symlinks += 1,
+            }
+        }
+
+        Ok(Self {
+            entries,
+            next: 0,
+            staged: MAGIC.to_vec(),
+            staged_pos: 0,
+            body: None,
+            ended: false,
+            offset: 0,
+            hasher: Sha256::new(),
+            summary,
+        })
+    }
+
+    /// Totals for the planned tree; `archive_sha256` is filled in once the
+    /// whole archive has been read.
+    pub fn summary(&self) -> TreeSummary {
+        let mut summary = self.summary.clone();
+        if let Some(digest) = self.archive_sha256() {
+            summary.archive_sha256 = digest;
+        }
+        summary
+    }
+
+    /// Number of archive bytes produced so far.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Hex SHA256 of the archive, available after the end has been read.
+    pub fn archive_sha256(&self) -> Option<String> {
+        if self.ended && self.staged_pos == self.staged.len() {
+            Some(to_hex(&self.hasher.clone().finalize()))
+        } else {
+            None
+        }
+    }
+
+    /// Advance to `offset` by generating and discarding archive bytes.
+    pub fn skip_to(&mut self, offset: u64) -> io::Result<()> {
+        if offset < self.offset {
+            return Err(invalid(format!(
+                "cannot rewind archive from {} to {}",
+                self.offset, offset
+            )));
+        }
+        let mut scratch = vec![0u8; 64 * 1024];
+        while self.offset < offset {
+            let want = (offset - self.offset).min(scratch.len() as u64) as usize;
+            if self.read(&mut scratch[..want])? == 0 {
+                return Err(invalid(format!(
+                    "offset {} is beyond the end of the archive ({})",
+                    offset, self.offset
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn stage(&mut self, bytes: Vec<u8>) {
+        self.staged = bytes;
+        self.staged_pos = 0;
+    }
+
+    fn emit(&mut self, n: usize, out: &[u8]) -> usize {
+        self.hasher.update(&out[..n]);
+        self.offset += n as u64;
+        n
+    }
+}
+
+impl Read for ArchiveWriter {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if out.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            if self.staged_pos < self.staged.len() {
+                let n = (self.staged.len() - self.staged_pos).min(out.len());
+                out[..n].copy_from_slice(&self.staged[self.staged_pos..self.staged_pos + n]);
+                self.staged_pos += n;
+                return Ok(self.emit(n, out));
+            }
+
+            if let Some(body) = self.body.as_mut() {
+                if body.remaining > 0 {
+                    let want = body.remaining.min(out.len() as u64) as usize;
+                    let n = body.file.read(&mut out[..want])?;
+                    if n == 0 {
+                        return Err(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            format!("{} shrank while it was being archived", body.path),
+                        ));
+                    }
+                    body.hasher.update(&out[..n]);
+                    body.remaining -= n as u64;
+                    return Ok(self.emit(n, out));
+                }
+                let body = self.body.take().unwrap();
+                self.stage(body.hasher.finalize().to_vec());
+                continue;
+            }
+
+            if self.ended {
+                return Ok(0);
+            }
+
+            if self.next < self.entries.len() {
+                let entry = &self.entries[self.next];
+                self.next += 1;
+                let header = entry.header.encode()?;
+                if entry.header.kind == KIND_FILE {
+                    self.body = Some(FileBody {
+                        file: File::open(&entry.source)?,
+                        remaining: entry.header.size,
+                        hasher: Sha256::new(),
+                        path: entry.header.path.clone(),
+                    });
+                }
+                self.stage(header);
+            } else {
+                self.ended = true;
+                self.stage(vec![KIND_END]);
+            }
+        }
+    }
+}
+
+/// Collect entries below `dir` in sorted pre-order.
+fn walk(dir: &Path, prefix: &str, entries: &mut Vec<PlannedEntry>) -> io::Result<()> {
+    let mut children = Vec::new();
+    for child in fs::read_dir(dir)? {
+        let child = child?;
+        let name = child
+            .file_name()
+            .into_string()
+            .map_err(|n| invalid(format!("unsupported file name: {:?}", n)))?;
+        children.push((name, child.path()));
+    }
+    children.sort();
+
+    for (name, path) in children {
+        let rel = if prefix.is_empty() {
+            name
+        } else {
+            format!("{}/{}", prefix, name)
+        };
+        let meta = fs::symlink_metadata(&path)?;
+        entries.push(plan_entry(&path, rel.clone(), &meta)?);
+        if meta.is_dir() {
+            walk(&path, &rel, entries)?;
+        }
+    }
+    Ok(())
+}
+
+fn plan_entry(path: &Path, rel: String, meta: &fs::Metadata) -> io::Result<PlannedEntry> {
+    let file_type = meta.file_type();
+    let (kind, size, link) = if file_type.is_symlink() {
+        let target = fs::read_link(path)?;
+        let target = target
+            .to_str()
+            .ok_or_else(|| invalid(format!("unsupported symlink target: {}", path.display())))?
+            .to_string();
+        (KIND_SYMLINK, 0, target)
+    } else if file_type.is_dir() {
+        (KIND_DIR, 0, String::new())
+    } else if file_type.is_file() {
+        (KIND_FILE, meta.len(), String::new())
+    } else {
+        return Err(invalid(format!(
+            "unsupported file type (device, socket or fifo): {}",
+            path.display()
+        )));
+    };
+
+    Ok(PlannedEntry {
+        header: Header {
+            kind,
+            path: rel,
+            mode: file_mode(meta),
+            mtime: meta.modified().map(split_time).unwrap_or((0, 0)),
+            size,
+            link,
+        },
+        source: path.to_path_buf(),
+    })
+}
+
+// ============================================================================
+// Reader
+// ============================================================================
+
+enum ReadState {
+    Magic,
+    Header,
+    Body {
+        file: File,
+        dir: Dir,
+        partial: OsString,
+        name: OsString,
+        header: Header,
+        remaining: u64,
+        hasher: Sha256,
+    },
+    Trailer {
+        file: File,
+        dir: Dir,
+        partial: OsString,
+        name: OsString,
+        header: Header,
+        digest: [u8; DIGEST_LEN],
+    },
+    End,
+}
+
+/// Extracts a QTA1 archive below a destination directory.
+///
+/// Bytes are pushed with [`ArchiveReader::write`] in pieces of any size.
+/// Files are written to a `.qtpart` sibling and only renamed into place once
+/// their SHA256 trailer matches, so an interrupted transfer never leaves a
+/// truncated file under its real name. Symlinks and directory metadata are
+/// applied by [`ArchiveReader::finish`].
+///
+/// Paths below the destination are resolved one component at a time without
+/// following symlinks, so links already present there (or created by an
+/// earlier archive) cannot redirect writes out of it.
+pub struct ArchiveReader {
+    dest: PathBuf,
+    root: Dir,
+    overwrite: bool,
+    create_symlinks: bool,
+    state: ReadState,
+    pending: Vec<u8>,
+    offset: u64,
+    hasher: Sha256,
+    summary: TreeSummary,
+    directories: Vec<(PathBuf, Header)>,
+    symlinks: Vec<(PathBuf, String)>,
+}
+
+impl ArchiveReader {
+    /// Prepare to extract into `dest`, creating it if necessary.
+    pub fn new(dest: &Path, overwrite: bool) -> io::Result<Self> {
+        fs::create_dir_all(dest)?;
+        Ok(Self {
+            dest: dest.to_path_buf(),
+            root: fsops::open_root(dest)?,
+            overwrite,
+            create_symlinks: true,
+            state: ReadState::Magic,
+            pending: Vec::new(),
+            offset: 0,
+            hasher: Sha256::new(),
+            summary: TreeSummary::default(),
+            directories: Vec::new(),
+            symlinks: Vec::new(),
+        })
+    }
+
+    /// Don't create the symlinks of the archive, for archives from a sender
+    /// that must not place links on this side. Skipped links are not counted.
+    pub fn skip_symlinks(mut self) -> Self {
+        self.create_symlinks = false;
+        self
+    }
+
+    /// Number of archive bytes consumed so far.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Whether the end-of-archive marker has been processed.
+    pub fn is_complete(&self) -> bool {
+        matches!(self.state, ReadState::End)
+    }
+
+    /// Consume the next piece of the archive.
+    pub fn write(&mut self, mut data: &[u8]) -> io::Result<()> {
+        while !data.is_empty() {
+            let used = self.consume(data)?;
+            self.hasher.update(&data[..used]);
+            self.offset += used as u64;
+            data = &data[used..];
+        }
+        Ok(())
+    }
+
+    /// Apply deferred metadata and return what was extracted.
+    pub fn finish(mut self) -> io::Result<TreeSummary> {
+        if !self.is_complete() {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                format!("archive ended early at offset {}", self.offset),
+            ));
+        }
+
+        for (rel, target) in std::mem::take(&mut self.symlinks) {
+            let (dir, name) = self.parent_dir(&rel)?;
+            if fsops::entry_is_dir(&dir, name)? == Some(false) && self.overwrite {
+                fsops::remove_file(&dir, name)?;
+            }
+            fsops::symlink(&target, &dir, name)?;
+        }
+
+        // Deepest directories first so setting a parent's mtime is not undone
+        // by changes to its children.
+        let directories = std::mem::take(&mut self.directories);
+        for (rel, header) in directories.iter().rev() {
+            let dir = fsops::open_dirs(&self.root, rel, false)?;
+            fsops::set_dir_metadata(&dir, header)?;
+        }
+
+        let mut summary = std::mem::take(&mut self.summary);
+        summary.archive_sha256 = to_hex(&self.hasher.clone().finalize());
+        Ok(summary)
+    }
+
+    /// Process bytes from `data`, returning how many were used.
+    fn consume(&mut self, data: &[u8]) -> io::Result<usize> {
+        match &mut self.state {
+            ReadState::Magic => {
+                let need = MAGIC.len() - self.pending.len();
+                let used = need.min(data.len());
+                self.pending.extend_from_slice(&data[..used]);
+                if self.pending.len() == MAGIC.len() {
+                    if self.pending != MAGIC {
+                        return Err(invalid("not a QTA1 archive"));
+                    }
+                    self.pending.clear();
+                    self.state = ReadState::Header;
+                }
+                Ok(used)
+            }
+            ReadState::Header => {
+                if self.pending.is_empty() && data[0] == KIND_END {
+                    self.state = ReadState::End;
+                    return Ok(1);
+                }
+                let before = self.pending.len();
+                self.pending.extend_from_slice(data);
+                match Header::decode(&self.pending)? {
+                    None => Ok(data.len()),
+                    Some((header, len)) => {
+                        self.pending.clear();
+                        self.begin_entry(header)?;
+                        Ok(len - before)
+                    }
+                }
+            }
+            ReadState::Body {
+                file,
+                remaining,
+                hasher,
+                ..
+            } => {
+                let used = (*remaining).min(data.len() as u64) as usize;
+                file.write_all(&data[..used])?;
+                hasher.update(&data[..used]);
+                *remaining -= used as u64;
+                if *remaining == 0 {
+                    let ReadState::Body {
+                        file,
+                        dir,
+                        partial,
+                        name,
+                        header,
+                        hasher,
+                        ..
+                    } = std::mem::replace(&mut self.state, ReadState::Header)
+                    else {
+                        unreachable!()
+                    };
+                    file.sync_all()?;
+                    file.set_modified(join_time(header.mtime))?;
+                    self.state = ReadState::Trailer {
+                        file,
+                        dir,
+                        partial,
+                        name,
+                        header,
+                        digest: hasher.finalize().into(),
+                    };
+                }
+                Ok(used)
+            }
+            ReadState::Trailer { digest, .. } => {
+                let need = DIGEST_LEN - self.pending.len();
+                let used = need.min(data.len());
+                self.pending.extend_from_slice(&data[..used]);
+                if self.pending.len() == DIGEST_LEN {
+                    if self.pending[..] != digest[..] {
+                        return Err(invalid(format!(
+                            "checksum mismatch for {}",
+                            self.entry_path().unwrap_or_default()
+                        )));
+                    }
+                    self.pending.clear();
+                    let ReadState::Trailer {
+                        file,
+                        dir,
+                        partial,
+                        name,
+                        header,
+                        ..
+                    } = std::mem::replace(&mut self.state, ReadState::Header)
+                    else {
+                        unreachable!()
+                    };
+                    self.complete_file(&file, &dir, &partial, &name, &header)?;
+                }
+                Ok(used)
+            }
+            ReadState::End => Err(invalid("data after end of archive")),
+        }
+    }
+
+    fn entry_path(&self) -> Option<String> {
+        match &self.state {
+            ReadState::Body { header, .. } | ReadState::Trailer { header, .. } => {
+                Some(header.path.clone())
+            }
+            _ => None,
+        }
+    }
+
+    fn begin_entry(&mut self, header: Header) -> io::Result<()> {
+        let rel = safe_relative(&header.path)?;
+
+        match header.kind {
+            KIND_DIR => {
+                fsops::open_dirs(&self.root, &rel, true)?;
+                self.summary.directories += 1;
+                self.directories.push((rel, header));
+            }
+            KIND_SYMLINK => {
+                if !self.create_symlinks {
+                    tracing::warn!(path = %header.path, target = %header.link, "Skipping symlink from archive");
+                    return Ok(());
+                }
+                let (dir, name) = self.parent_dir(&rel)?;
+                self.check_overwrite(&dir, name, &rel)?;
+                self.summary.symlinks += 1;
+                self.symlinks.push((rel, header.link));
+            }
+            _ => {
+                let (dir, name) = self.parent_dir(&rel)?;
+                self.check_overwrite(&dir, name, &rel)?;
+                let name = name.to_os_string();
+                let mut partial = name.clone();
+                partial.push(PARTIAL_SUFFIX);
+                let file = fsops::create_file(&dir, &partial)?;
+
+                self.summary.files += 1;
+                self.summary.bytes += header.size;
+                if header.size == 0 {
+                    file.set_modified(join_time(header.mtime))?;
+                    self.state = ReadState::Trailer {
+                        file,
+                        dir,
+                        partial,
+                        name,
+                        header,
+                        digest: Sha256::new().finalize().into(),
+                    };
+                } else {
+                    self.state = ReadState::Body {
+                        file,
+                        dir,
+                        partial,
+                        name,
+                        remaining: header.size,
+                        header,
+                        hasher: Sha256::new(),
+                    };
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Open (creating) the directory that holds `rel`, and the entry name.
+    fn parent_dir<'a>(&self, rel: &'a Path) -> io::Result<(Dir, &'a OsStr)> {
+        let name = rel.file_name().ok_or_else(|| invalid("empty path in archive"))?;
+        let dir = fsops::open_dirs(&self.root, rel.parent().unwrap_or(Path::new("")), true)?;
+        Ok((dir, name))
+    }
+
+    fn check_overwrite(&self, dir: &Dir, name: &OsStr, rel: &Path) -> io::Result<()> {
+        if !self.overwrite && fsops::entry_is_dir(dir, name)?.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} already exists", self.dest.join(rel).display()),
+            ));
+        }
+        Ok(())
+    }
+
+    fn complete_file(&self, file: &File, dir: &Dir, partial: &OsStr, name: &OsStr, header: &Header) -> io::Result<()> {
+        set_mode(file, header.mode)?;
+        if fsops::entry_is_dir(dir, name)? == Some(true) {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                format!("{} is a directory", Path::new(name).display()),
+            ));
+        }
+        fsops::rename(dir, partial, name)
+    }
+}
+
+impl Drop for ArchiveReader {
+    fn drop(&mut self) {
+        // Don't leave half-received files behind on abort.
+        if let ReadState::Body { dir, partial, .. } | ReadState::Trailer { dir, partial, .. } = &self.state {
+            let _ = fsops::remove_file(dir, partial);
+        }
+    }
+}
+
+/// Validate an archive path and convert it to a relative filesystem path.
+fn safe_relative(path: &str) -> io::Result<PathBuf> {
+    let mut rel = PathBuf::new();
+    for part in path.split('/') {
+        let mut components = Path::new(part).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(name)), None) => rel.push(name),
+            _ => return Err(invalid(format!("unsafe path in archive: {}", path))),
+        }
+    }
+    if rel.as_os_str().is_empty() {
+        return Err(invalid("empty path in archive"));
+    }
+    Ok(rel)
+}
+
+/// Directory operations relative to an open directory that never follow
+/// symlinks, so nothing below the destination can point writes elsewhere.
+#[cfg(unix)]
+mod fsops {
+    use super::{invalid, join_time, Header};
+    use std::ffi::{CString, OsStr};
+    use std::fs::{self, File, OpenOptions};
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+    use std::os::unix::io::{AsRawFd, FromRawFd};
+    use std::path::Path;
+
+    /// An open directory below (or at) the destination.
+    pub type Dir = File;
+
+    fn c_name(name: &OsStr) -> io::Result<CString> {
+        CString::new(name.as_bytes()).map_err(|_| invalid("NUL byte in archive path"))
+    }
+
+    fn check(ret: libc::c_int) -> io::Result<()> {
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Open the destination itself (chosen by the caller, so links are allowed).
+    pub fn open_root(path: &Path) -> io::Result<Dir> {
+        OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_DIRECTORY | libc::O_CLOEXEC)
+            .open(path)
+    }
+
+    /// Open `rel` below `root` one component at a time, creating missing
+    /// directories if `create` is set. Symlinks and other non-directories
+    /// on the way are refused.
+    pub fn open_dirs(root: &Dir, rel: &Path, create: bool) -> io::Result<Dir> {
+        let mut dir = root.try_clone()?;
+        for component in rel.components() {
+            let name = c_name(component.as_os_str())?;
+            if create {
+                // SAFETY: valid directory fd and NUL-terminated name
+                let ret = unsafe { libc::mkdirat(dir.as_raw_fd(), name.as_ptr(), 0o777) };
+                if let Err(e) = check(ret) {
+                    if e.kind() != io::ErrorKind::AlreadyExists {
+                        return Err(e);
+                    }
+                }
+            }
+            // SAFETY: as above; the returned fd is owned by the File
+            let fd = unsafe {
+                libc::openat(
+                    dir.as_raw_fd(),
+                    name.as_ptr(),
+                    libc::O_RDONLY | libc::O_DIRECTORY | libc::O_NOFOLLOW | libc::O_CLOEXEC,
+                )
+            };
+            if fd < 0 {
+                let e = io::Error::last_os_error();
+                return Err(match e.raw_os_error() {
+                    Some(libc::ELOOP) | Some(libc::ENOTDIR) => invalid(format!(
+                        "{} is not a directory (symlinks are not followed)",
+                        rel.display()
+                    )),
+                    _ => e,
+                });
+            }
+            // SAFETY: fd was just opened and is not owned elsewhere
+            dir = unsafe { File::from_raw_fd(fd) };
+        }
+        Ok(dir)
+    }
+
+    /// Whether `name` exists in `dir` (without following it) and is a directory.
+    pub fn entry_is_dir(dir: &Dir, name: &OsStr) -> io::Result<Option<bool>> {
+        let name = c_name(name)?;
+        // SAFETY: stat is plain data, filled in by fstatat
+        let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+        let ret = unsafe { libc::fstatat(dir.as_raw_fd(), name.as_ptr(), &mut stat, libc::AT_SYMLINK_NOFOLLOW) };
+        match check(ret) {
+            Ok(()) => Ok(Some(stat.st_mode & libc::S_IFMT == libc::S_IFDIR)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Create a new, empty file (replacing a leftover one, never through a link).
+    pub fn create_file(dir: &Dir, name: &OsStr) -> io::Result<File> {
+        match remove_file(dir, name) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+        let c = c_name(name)?;
+        // SAFETY: valid directory fd and NUL-terminated name
+        let fd = unsafe {
+            libc::openat(
+                dir.as_raw_fd(),
+                c.as_ptr(),
+                libc::O_WRONLY | libc::O_CREAT | libc::O_EXCL | libc::O_NOFOLLOW | libc::O_CLOEXEC,
+                0o600 as libc::c_uint,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: fd was just opened and is not owned elsewhere
+        Ok(unsafe { File::from_raw_fd(fd) })
+    }
+
+    pub fn remove_file(dir: &Dir, name: &OsStr) -> io::Result<()> {
+        let name = c_name(name)?;
+        // SAFETY: valid directory fd and NUL-terminated name
+        check(unsafe { libc::unlinkat(dir.as_raw_fd(), name.as_ptr(), 0) })
+    }
+
+    pub fn rename(dir: &Dir, from: &OsStr, to: &OsStr) -> io::Result<()> {
+        let (from, to) = (c_name(from)?, c_name(to)?);
+        // SAFETY: valid directory fd and NUL-terminated names
+        check(unsafe { libc::renameat(dir.as_raw_fd(), from.as_ptr(), dir.as_raw_fd(), to.as_ptr()) })
+    }
+
+    pub fn symlink(target: &str, dir: &Dir, name: &OsStr) -> io::Result<()> {
+        let target = CString::new(target).map_err(|_| invalid("NUL byte in symlink target"))?;
+        let name = c_name(name)?;
+        // SAFETY: valid directory fd and NUL-terminated strings
+        check(unsafe { libc::symlinkat(target.as_ptr(), dir.as_raw_fd(), name.as_ptr()) })
+    }
+
+    /// Apply the permission bits and mtime of a directory entry.
+    pub fn set_dir_metadata(dir: &Dir, header: &Header) -> io::Result<()> {
+        dir.set_permissions(fs::Permissions::from_mode(header.mode & 0o777))?;
+        let _ = dir.set_modified(join_time(header.mtime));
+        Ok(())
+    }
+}
+
+/// Path based fallback: symlinks are not created on Windows, so there are
+/// none from earlier archives to follow.
+#[cfg(not(unix))]
+mod fsops {
+    use super::{join_time, Header};
+    use std::ffi::OsStr;
+    use std::fs::{self, File};
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    pub type Dir = PathBuf;
+
+    pub fn open_root(path: &Path) -> io::Result<Dir> {
+        Ok(path.to_path_buf())
+    }
+
+    pub fn open_dirs(root: &Dir, rel: &Path, create: bool) -> io::Result<Dir> {
+        let path = root.join(rel);
+        if create {
+            fs::create_dir_all(&path)?;
+        } else if !path.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} is not a directory", path.display()),
+            ));
+        }
+        Ok(path)
+    }
+
+    pub fn entry_is_dir(dir: &Dir, name: &OsStr) -> io::Result<Option<bool>> {
+        match dir.join(name).symlink_metadata() {
+            Ok(meta) => Ok(Some(meta.is_dir())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn create_file(dir: &Dir, name: &OsStr) -> io::Result<File> {
+        File::create(dir.join(name))
+    }
+
+    pub fn remove_file(dir: &Dir, name: &OsStr) -> io::Result<()> {
+        fs::remove_file(dir.join(name))
+    }
+
+    pub fn rename(dir: &Dir, from: &OsStr, to: &OsStr) -> io::Result<()> {
+        fs::rename(dir.join(from), dir.join(to))
+    }
+
+    pub fn symlink(target: &str, dir: &Dir, name: &OsStr) -> io::Result<()> {
+        // Creating symlinks needs extra privileges on Windows; skip them.
+        let path = dir.join(name);
+        tracing::warn!(path = %path.display(), target = %target, "Skipping symlink from archive");
+        Ok(())
+    }
+
+    pub fn set_dir_metadata(dir: &Dir, header: &Header) -> io::Result<()> {
+        let mut permissions = fs::metadata(dir)?.permissions();
+        permissions.set_readonly(header.mode & 0o200 == 0);
+        fs::set_permissions(dir, permissions)?;
+        if let Ok(file) = File::open(dir) {
+            let _ = file.set_modified(join_time(header.mtime));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn file_mode(meta: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    meta.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn file_mode(meta: &fs::Metadata) -> u32 {
+    match (meta.is_dir(), meta.permissions().readonly()) {
+        (true, _) => 0o755,
+        (false, true) => 0o444,
+        (false, false) => 0o644,
+    }
+}
+
+/// Apply the permission bits of an archive entry. Set-id and sticky bits are
+/// never restored: extracting as root must not create setuid binaries.
+#[cfg(unix)]
+fn set_mode(file: &File, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    file.set_permissions(fs::Permissions::from_mode(mode & 0o777))
+}
+
+#[cfg(not(unix))]
+fn set_mode(file: &File, mode: u32) -> io::Result<()> {
+    let mut permissions = file.metadata()?.permissions();
+    permissions.set_readonly(mode & 0o200 == 0);
+    file.set_permissions(permissions)
+}
+
+fn split_time(time: SystemTime) -> (i64, u32) {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => (d.as_secs() as i64, d.subsec_nanos()),
+        Err(e) => (-(e.duration().as_secs() as i64), 0),
+    }
+}
+
+fn join_time((secs, nanos): (i64, u32)) -> SystemTime {
+    if secs >= 0 {
+        UNIX_EPOCH + Duration::new(secs as u64, nanos.min(999_999_999))
+    } else {
+        UNIX_EPOCH - Duration::from_secs(secs.unsigned_abs())
+    }
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+/// Lowercase hex encoding of a digest.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_tree() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("etc/app")).unwrap();
+        fs::write(dir.path().join("etc/app/config.yaml"), b"listen: 8080\n").unwrap();
+        fs::write(dir.path().join("empty"), b"").unwrap();
+        let big: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        fs::write(dir.path().join("data.bin"), &big).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(
+                dir.path().join("etc/app/config.yaml"),
+                fs::Permissions::from_mode(0o600),
+            )
+            .unwrap();
+            fs::set_permissions(dir.path().join("data.bin"), fs::Permissions::from_mode(0o4755))
+                .unwrap();
+            std::os::unix::fs::symlink("etc/app/config.yaml", dir.path().join("link")).unwrap();
+        }
+        File::options()
+            .write(true)
+            .open(dir.path().join("data.bin"))
+            .unwrap()
+            .set_modified(UNIX_EPOCH + Duration::from_secs(1_600_000_000))
+            .unwrap();
+        dir
+    }
+
+    fn archive(root: &Path) -> (Vec<u8>, TreeSummary) {
+        let mut writer = ArchiveWriter::new(root).unwrap();
+        let mut bytes = Vec::new();
+        writer.read_to_end(&mut bytes).unwrap();
+        (bytes, writer.summary())
+    }
+
+    #[test]
+    fn test_roundtrip_preserves_content_and_metadata() {
+        let src = sample_tree();
+        let (bytes, sent) = archive(src.path());
+        assert_eq!(sent.files, 3);
+        assert_eq!(sent.directories, 2);
+
+        let dest = tempfile::tempdir().unwrap();
+        let mut reader = ArchiveReader::new(dest.path(), false).unwrap();
+        for chunk in bytes.chunks(7_777) {
+            reader.write(chunk).unwrap();
+        }
+        let received = reader.finish().unwrap();
+        assert_eq!(received, sent);
+
+        for name in ["etc/app/config.yaml", "empty", "data.bin"] {
+            assert_eq!(
+                fs::read(src.path().join(name)).unwrap(),
+                fs::read(dest.path().join(name)).unwrap()
+            );
+        }
+        let mtime = fs::metadata(dest.path().join("data.bin")).unwrap().modified().unwrap();
+        assert_eq!(mtime, UNIX_EPOCH + Duration::from_secs(1_600_000_000));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(dest.path().join("etc/app/config.yaml"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+            // The setuid bit is dropped
+            let mode = fs::metadata(dest.path().join("data.bin")).unwrap().permissions().mode();
+            assert_eq!(mode & 0o7777, 0o755);
+            assert_eq!(
+                fs::read_link(dest.path().join("link")).unwrap(),
+                Path::new("etc/app/config.yaml")
+            );
+        }
+    }
+
+    #[test]
+    fn test_skip_to_resumes_identical_stream() {
+        let src = sample_tree();
+        let (bytes, _) = archive(src.path());
+
+        let resume_at = 100_123;
+        let mut writer = ArchiveWriter::new(src.path()).unwrap();
+        writer.skip_to(resume_at).unwrap();
+        let mut rest = Vec::new();
+        writer.read_to_end(&mut rest).unwrap();
+        assert_eq!(&bytes[resume_at as usize..], &rest[..]);
+        assert!(writer.skip_to(0).is_err());
+    }
+
+    #[test]
+    fn test_corrupted_file_is_rejected() {
+        let src = sample_tree();
+        let (mut bytes, _) = archive(src.path());
+        let pos = bytes.windows(12).position(|w| w == b"listen: 8080").unwrap();
+        bytes[pos] ^= 0xff;
+
+        let dest = tempfile::tempdir().unwrap();
+        let mut reader = ArchiveReader::new(dest.path(), false).unwrap();
+        let err = reader.write(&bytes).unwrap_err();
+        assert!(err.to_string().contains("checksum mismatch"));
+        drop(reader);
+        assert!(!dest.path().join("etc/app/config.yaml").exists());
+        assert!(!dest.path().join("etc/app/config.yaml.qtpart").exists());
+    }
+
+    #[test]
+    fn test_unsafe_paths_are_rejected() {
+        assert!(safe_relative("a/b/c").is_ok());
+        assert!(safe_relative("../etc/passwd").is_err());
+        assert!(safe_relative("a/../../b").is_err());
+        assert!(safe_relative("/etc/passwd").is_err());
+        assert!(safe_relative("a//b").is_err());
+        assert!(safe_relative("").is_err());
+    }
+
+    #[test]
+    fn test_existing_files_need_overwrite() {
+        let src = sample_tree();
+        let (bytes, _) = archive(src.path());
+        let dest = tempfile::tempdir().unwrap();
+        fs::write(dest.path().join("empty"), b"keep").unwrap();
+
+        let mut reader = ArchiveReader::new(dest.path(), false).unwrap();
+        assert!(reader.write(&bytes).is_err());
+        drop(reader);
+        assert_eq!(fs::read(dest.path().join("empty")).unwrap(), b"keep");
+
+        let mut reader = ArchiveReader::new(dest.path(), true).unwrap();
+        reader.write(&bytes).unwrap();
+        reader.finish().unwrap();
+        assert!(fs::read(dest.path().join("empty")).unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_links_below_destination_are_not_followed() {
+        let src = sample_tree();
+        let (bytes, _) = archive(src.path());
+
+        // A link left by an earlier archive must not redirect "etc/app/..."
+        let outside = tempfile::tempdir().unwrap();
+        let dest = tempfile::tempdir().unwrap();
+        std::os::unix::fs::symlink(outside.path(), dest.path().join("etc")).unwrap();
+        let mut reader = ArchiveReader::new(dest.path(), true).unwrap();
+        let err = reader.write(&bytes).unwrap_err();
+        assert!(err.to_string().contains("not a directory"), "{}", err);
+        drop(reader);
+        assert!(fs::read_dir(outside.path()).unwrap().next().is_none());
+
+        // Nor may a file entry be written through a link in its place
+        let dest = tempfile::tempdir().unwrap();
+        let victim = outside.path().join("victim");
+        fs::write(&victim, b"keep").unwrap();
+        std::os::unix::fs::symlink(&victim, dest.path().join("data.bin")).unwrap();
+        let mut reader = ArchiveReader::new(dest.path(), true).unwrap();
+        reader.write(&bytes).unwrap();
+        reader.finish().unwrap();
+        assert_eq!(fs::read(&victim).unwrap(), b"keep");
+        assert!(!dest.path().join("data.bin").symlink_metadata().unwrap().is_symlink());
+
+        // Links of the archive itself can be left out
+        let dest = tempfile::tempdir().unwrap();
+        let mut reader = ArchiveReader::new(dest.path(), false).unwrap().skip_symlinks();
+        reader.write(&bytes).unwrap();
+        let summary = reader.finish().unwrap();
+        assert_eq!(summary.symlinks, 0);
+        assert!(dest.path().join("link").symlink_metadata().is_err());
+        assert!(dest.path().join("etc/app/config.yaml").is_file());
+    }
+}
@@ -66,6 +66,11 @@ log_error() {
 build_binary() {
     log_info "Building quantix-kvm-agent binary..."
     
+    # Ed25519 keys (hex, comma separated) that agent updates must be signed with
+    if [ -z "${QUANTIX_UPDATE_PUBLIC_KEYS:-}" ]; then
+        log_warn "QUANTIX_UPDATE_PUBLIC_KEYS is not set; this agent will refuse self-updates"
+    fi
+    
     cd "${ROOT_DIR}"
     cargo build --release -p limiquantix-guest-agent
     
@@ -89,6 +94,7 @@ build_deb() {
     rm -rf "${DEB_DIR}"
     mkdir -p "${DEB_DIR}/DEBIAN"
     mkdir -p "${DEB_DIR}/usr/local/bin"
+    mkdir -p "${DEB_DIR}/usr/local/lib/quantix-kvm-agent"
     mkdir -p "${DEB_DIR}/lib/systemd/system"
     mkdir -p "${DEB_DIR}/etc/quantix-kvm"
     mkdir -p "${DEB_DIR}/etc/quantix-kvm/pre-freeze.d"
@@ -96,8 +102,9 @@ build_deb() {
     mkdir -p "${DEB_DIR}/var/log/quantix-kvm"
     
     # Copy binary
-    cp "${ROOT_DIR}/target/release/quantix-kvm-agent" "${DEB_DIR}/usr/local/bin/"
-    chmod 755 "${DEB_DIR}/usr/local/bin/quantix-kvm-agent"
+    cp "${ROOT_DIR}/target/release/quantix-kvm-agent" "${DEB_DIR}/usr/local/lib/quantix-kvm-agent/"
+    chmod 755 "${DEB_DIR}/usr/local/lib/quantix-kvm-agent/quantix-kvm-agent"
+    ln -s /usr/local/lib/quantix-kvm-agent/quantix-kvm-agent "${DEB_DIR}/usr/local/bin/quantix-kvm-agent"
     
     # Copy systemd service
     cp "${SCRIPT_DIR}/systemd/quantix-kvm-agent.service" "${DEB_DIR}/lib/systemd/system/"
@@ -112,6 +119,7 @@ Version: ${VERSION}
 Architecture: ${ARCH}
 Maintainer: Quantix KVM Team <team@quantix-kvm.io>
 Depends: libc6
+Recommends: cloud-guest-utils
 Description: Quantix KVM Guest Agent for VM Integration
  The Quantix KVM Guest Agent is a lightweight daemon that runs inside
  guest VMs to enable deep integration with the Quantix KVM hypervisor.
@@ -179,8 +187,10 @@ Similar to VMware Tools but for KVM/QEMU environments.
 
 %install
 mkdir -p %{buildroot}/usr/local/bin
+mkdir -p %{buildroot}/usr/local/lib/quantix-kvm-agent
 mkdir -p %{buildroot}/lib/systemd/system
-install -m 755 ${ROOT_DIR}/target/release/quantix-kvm-agent %{buildroot}/usr/local/bin/
+install -m 755 ${ROOT_DIR}/target/release/quantix-kvm-agent %{buildroot}/usr/local/lib/quantix-kvm-agent/
+ln -s /usr/local/lib/quantix-kvm-agent/quantix-kvm-agent %{buildroot}/usr/local/bin/quantix-kvm-agent
 install -m 644 ${SCRIPT_DIR}/systemd/quantix-kvm-agent.service %{buildroot}/lib/systemd/system/
 
 %post
@@ -195,6 +205,8 @@ if [ \$1 -eq 0 ]; then
 fi
 
 %files
+%dir /usr/local/lib/quantix-kvm-agent
+/usr/local/lib/quantix-kvm-agent/quantix-kvm-agent
 /usr/local/bin/quantix-kvm-agent
 /lib/systemd/system/quantix-kvm-agent.service
 EOF
@@ -233,7 +245,8 @@ set -e
 echo "Installing Quantix KVM Guest Agent..."
 
 # Copy binary
-sudo install -m 755 quantix-kvm-agent /usr/local/bin/
+sudo install -D -m 755 quantix-kvm-agent /usr/local/lib/quantix-kvm-agent/quantix-kvm-agent
+sudo ln -sf /usr/local/lib/quantix-kvm-agent/quantix-kvm-agent /usr/local/bin/quantix-kvm-agent
 
 # Copy systemd service
 sudo install -m 644 quantix-kvm-agent.service /etc/systemd/system/
@@ -329,17 +329,19 @@ install_quantix_agent() {
         fi
         
         # Copy binary
-        cp "${binary_file}" /usr/local/bin/quantix-kvm-agent
-        chmod 755 /usr/local/bin/quantix-kvm-agent
+        # The binary lives in its own directory, the only one the agent may
+        # write to for self-update
+        install -D -m 755 "${binary_file}" /usr/local/lib/quantix-kvm-agent/quantix-kvm-agent
+        ln -sf /usr/local/lib/quantix-kvm-agent/quantix-kvm-agent /usr/local/bin/quantix-kvm-agent
         
         # Fix SELinux context if SELinux is enabled
         if command -v getenforce &> /dev/null && [ "$(getenforce)" != "Disabled" ]; then
             log_info "Fixing SELinux context..."
             if command -v chcon &> /dev/null; then
-                chcon -t bin_t /usr/local/bin/quantix-kvm-agent 2>/dev/null || true
+                chcon -t bin_t /usr/local/lib/quantix-kvm-agent/quantix-kvm-agent 2>/dev/null || true
             fi
             if command -v restorecon &> /dev/null; then
-                restorecon -v /usr/local/bin/quantix-kvm-agent 2>/dev/null || true
+                restorecon -v /usr/local/lib/quantix-kvm-agent/quantix-kvm-agent 2>/dev/null || true
             fi
         fi
         
@@ -366,7 +368,7 @@ ConditionVirtualization=vm
 
 [Service]
 Type=simple
-ExecStart=/usr/local/bin/quantix-kvm-agent
+ExecStart=/usr/local/lib/quantix-kvm-agent/quantix-kvm-agent
 Restart=always
 RestartSec=5
 Environment=RUST_LOG=info
@@ -376,7 +378,7 @@ NoNewPrivileges=false
 ProtectSystem=strict
 ProtectHome=read-only
 PrivateTmp=true
-ReadWritePaths=/var/log/quantix-kvm /etc/quantix-kvm /dev
+ReadWritePaths=/var/log/quantix-kvm /etc/quantix-kvm /dev /usr/local/lib/quantix-kvm-agent
 
 [Install]
 WantedBy=multi-user.target
@@ -446,6 +448,7 @@ uninstall_agent() {
     
     # Remove binary
     rm -f /usr/local/bin/quantix-kvm-agent
+    rm -rf /usr/local/lib/quantix-kvm-agent
     
     # Optionally remove config (keep logs)
     # rm -rf /etc/quantix-kvm
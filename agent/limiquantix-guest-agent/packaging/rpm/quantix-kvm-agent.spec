@@ -5,6 +5,8 @@ Summary:        Quantix KVM Guest Agent for VM Integration
 License:        Apache-2.0
 URL:            https://github.com/Quantix-KVM/LimiQuantix
 BuildArch:      x86_64
+# growpart, used to grow the root partition after a disk resize
+Recommends:     cloud-utils-growpart
 
 %description
 The Quantix KVM Guest Agent is a lightweight daemon that runs inside
@@ -18,6 +20,7 @@ Features:
 - Graceful shutdown/reboot
 - Password reset
 - Filesystem quiescing for snapshots
+- Partition/filesystem growth after disk resize
 - Time synchronization
 - Display resize (desktop VMs)
 - Clipboard sharing
@@ -27,6 +30,7 @@ Features:
 
 %install
 mkdir -p %{buildroot}/usr/local/bin
+mkdir -p %{buildroot}/usr/local/lib/quantix-kvm-agent
 mkdir -p %{buildroot}/usr/lib/systemd/system
 mkdir -p %{buildroot}/etc/quantix-kvm
 mkdir -p %{buildroot}/etc/quantix-kvm/pre-freeze.d
@@ -34,7 +38,8 @@ mkdir -p %{buildroot}/etc/quantix-kvm/post-thaw.d
 mkdir -p %{buildroot}/var/log/quantix-kvm
 
 # Binary will be copied during build
-install -m 755 %{_sourcedir}/quantix-kvm-agent %{buildroot}/usr/local/bin/
+install -m 755 %{_sourcedir}/quantix-kvm-agent %{buildroot}/usr/local/lib/quantix-kvm-agent/
+ln -s /usr/local/lib/quantix-kvm-agent/quantix-kvm-agent %{buildroot}/usr/local/bin/quantix-kvm-agent
 install -m 644 %{_sourcedir}/quantix-kvm-agent.service %{buildroot}/usr/lib/systemd/system/
 install -m 644 %{_sourcedir}/agent.yaml %{buildroot}/etc/quantix-kvm/
 
@@ -66,7 +71,9 @@ if [ $1 -eq 0 ]; then
 fi
 
 %files
-%attr(755, root, root) /usr/local/bin/quantix-kvm-agent
+%dir %attr(755, root, root) /usr/local/lib/quantix-kvm-agent
+%attr(755, root, root) /usr/local/lib/quantix-kvm-agent/quantix-kvm-agent
+/usr/local/bin/quantix-kvm-agent
 %attr(644, root, root) /usr/lib/systemd/system/quantix-kvm-agent.service
 %config(noreplace) %attr(644, root, root) /etc/quantix-kvm/agent.yaml
 %dir %attr(755, root, root) /etc/quantix-kvm
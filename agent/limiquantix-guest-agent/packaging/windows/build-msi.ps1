@@ -89,9 +89,12 @@ if (-not $wixPath) {
     $env:Path = [System.Environment]::GetEnvironmentVariable("Path", "Machine") + ";" + [System.Environment]::GetEnvironmentVariable("Path", "User")
 }
 
+# Util extension provides the service recovery options
+wix extension add -g WixToolset.Util.wixext | Out-Null
+
 # Build the MSI
 $MsiName = "quantix-kvm-agent-$Version-x64.msi"
-wix build main.wxs -o "$OutputDir\$MsiName" -define Version=$Version
+wix build main.wxs -ext WixToolset.Util.wixext -o "$OutputDir\$MsiName" -define Version=$Version
 if ($LASTEXITCODE -ne 0) {
     Write-Error "WiX build failed"
     Pop-Location
@@ -79,6 +79,9 @@ pub struct AgentConfig {
 
     /// Health check configuration
     pub health: HealthConfig,
+
+    /// Application quiesce providers run before filesystems are frozen
+    pub quiesce: QuiesceConfig,
 }
 
 /// Log format options
@@ -111,6 +114,9 @@ pub struct SecurityConfig {
     /// Paths where file reads are denied
     pub deny_file_read_paths: Vec<String>,
 
+    /// Guest ports the host may open port forwards to (empty = all allowed)
+    pub forward_port_allowlist: Vec<u16>,
+
     /// Maximum commands per minute (0 = unlimited)
     pub max_commands_per_minute: u32,
 
@@ -145,6 +151,132 @@ impl Default for HealthConfig {
     }
 }
 
+/// Built-in application quiesce providers.
+///
+/// Enabled providers bring their application to a consistent on-disk state
+/// before the filesystems are frozen and release it after the thaw, so
+/// snapshots are application-consistent without pre-freeze scripts.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
+pub struct QuiesceConfig {
+    /// PostgreSQL (`pg_backup_start` / `pg_backup_stop`)
+    pub postgresql: PostgresQuiesceConfig,
+
+    /// MySQL/MariaDB (`FLUSH TABLES WITH READ LOCK` held during the freeze)
+    pub mysql: MysqlQuiesceConfig,
+
+    /// Redis (`BGSAVE`, waiting for the RDB dump to finish)
+    pub redis: RedisQuiesceConfig,
+}
+
+/// PostgreSQL quiesce provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PostgresQuiesceConfig {
+    /// Run the provider on quiesce
+    pub enabled: bool,
+
+    /// Abort the quiesce if the provider fails (otherwise only reported)
+    pub required: bool,
+
+    /// Maximum time for the backup start checkpoint in seconds
+    pub timeout_secs: u64,
+
+    /// Path to the psql client
+    pub psql_path: String,
+
+    /// OS user psql runs as when the agent runs as root (empty = agent user)
+    pub os_user: String,
+
+    /// libpq connection string (empty = local socket, default database)
+    pub connection: String,
+}
+
+impl Default for PostgresQuiesceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            required: false,
+            timeout_secs: 30,
+            psql_path: "psql".to_string(),
+            os_user: "postgres".to_string(),
+            connection: String::new(),
+        }
+    }
+}
+
+/// MySQL/MariaDB quiesce provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MysqlQuiesceConfig {
+    /// Run the provider on quiesce
+    pub enabled: bool,
+
+    /// Abort the quiesce if the provider fails (otherwise only reported)
+    pub required: bool,
+
+    /// Maximum time to acquire the global read lock in seconds
+    pub timeout_secs: u64,
+
+    /// Path to the mysql client
+    pub client_path: String,
+
+    /// Option file with the connection credentials (empty = client defaults)
+    pub defaults_file: String,
+}
+
+impl Default for MysqlQuiesceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            required: false,
+            timeout_secs: 30,
+            client_path: "mysql".to_string(),
+            defaults_file: String::new(),
+        }
+    }
+}
+
+/// Redis quiesce provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RedisQuiesceConfig {
+    /// Run the provider on quiesce
+    pub enabled: bool,
+
+    /// Abort the quiesce if the provider fails (otherwise only reported)
+    pub required: bool,
+
+    /// Maximum time for the background save in seconds
+    pub timeout_secs: u64,
+
+    /// Path to redis-cli
+    pub cli_path: String,
+
+    /// Redis host
+    pub host: String,
+
+    /// Redis port
+    pub port: u16,
+
+    /// Redis password (empty = no AUTH)
+    pub password: String,
+}
+
+impl Default for RedisQuiesceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            required: false,
+            timeout_secs: 60,
+            cli_path: "redis-cli".to_string(),
+            host: "127.0.0.1".to_string(),
+            port: 6379,
+            password: String::new(),
+        }
+    }
+}
+
 impl Default for AgentConfig {
     fn default() -> Self {
         Self {
@@ -161,6 +293,7 @@ impl Default for AgentConfig {
             post_thaw_script_dir: DEFAULT_POST_THAW_DIR.to_string(),
             security: SecurityConfig::default(),
             health: HealthConfig::default(),
+            quiesce: QuiesceConfig::default(),
         }
     }
 }
@@ -261,6 +394,21 @@ impl AgentConfig {
             });
         }
 
+        // Validate quiesce provider timeouts
+        let providers = [
+            ("quiesce.postgresql", self.quiesce.postgresql.enabled, self.quiesce.postgresql.timeout_secs),
+            ("quiesce.mysql", self.quiesce.mysql.enabled, self.quiesce.mysql.timeout_secs),
+            ("quiesce.redis", self.quiesce.redis.enabled, self.quiesce.redis.timeout_secs),
+        ];
+        for (name, enabled, timeout_secs) in providers {
+            if enabled && (timeout_secs == 0 || timeout_secs > 600) {
+                return Err(ConfigError::InvalidValue {
+                    field: format!("{}.timeout_secs", name),
+                    message: "must be between 1 and 600".to_string(),
+                });
+            }
+        }
+
         Ok(())
     }
 
@@ -333,6 +481,21 @@ impl AgentConfig {
         debug!(path = %path, "File write path not in allowlist");
         false
     }
+
+    /// Check if a port forward to the given guest port is allowed
+    pub fn is_forward_port_allowed(&self, port: u16) -> bool {
+        // If allowlist is empty, all ports are allowed
+        if self.security.forward_port_allowlist.is_empty() {
+            return true;
+        }
+
+        if self.security.forward_port_allowlist.contains(&port) {
+            return true;
+        }
+
+        debug!(port = port, "Forward port not in allowlist");
+        false
+    }
 }
 
 /// Configuration errors
@@ -394,6 +557,33 @@ mod tests {
         assert!(!config.is_command_allowed("/bin/ls")); // Not in allowlist
     }
 
+    #[test]
+    fn test_forward_port_allowlist() {
+        let mut config = AgentConfig::default();
+        assert!(config.is_forward_port_allowed(5432));
+
+        config.security.forward_port_allowlist = vec![22];
+        assert!(config.is_forward_port_allowed(22));
+        assert!(!config.is_forward_port_allowed(5432));
+    }
+
+    #[test]
+    fn test_quiesce_config() {
+        let config: AgentConfig = serde_yaml::from_str(
+            "quiesce:\n  mysql:\n    enabled: true\n    defaults_file: /root/.my.cnf\n",
+        )
+        .unwrap();
+        assert!(config.quiesce.mysql.enabled);
+        assert_eq!(config.quiesce.mysql.timeout_secs, 30);
+        assert!(!config.quiesce.postgresql.enabled);
+        assert_eq!(config.quiesce.redis.port, 6379);
+        assert!(config.validate().is_ok());
+
+        let mut config = config;
+        config.quiesce.mysql.timeout_secs = 0;
+        assert!(config.validate().is_err());
+    }
+
     #[test]
     fn test_yaml_serialization() {
         let config = AgentConfig::default();
@@ -0,0 +1,524 @@
+//! Application quiesce providers for application-consistent snapshots.
+//!
+//! Filesystem freezing alone gives crash-consistent snapshots: databases
+//! recover from them like after a power loss. The built-in providers bring
+//! common databases to a clean on-disk state first:
+//!
+//! - **PostgreSQL**: `pg_backup_start` (checkpoint + backup mode) in a psql
+//!   session that stays open until the thaw, then `pg_backup_stop`
+//! - **MySQL/MariaDB**: `FLUSH TABLES WITH READ LOCK` in a mysql session
+//!   that holds the lock during the freeze, then `UNLOCK TABLES`
+//! - **Redis**: `BGSAVE`, waiting until the RDB dump is written
+//!
+//! Providers are enabled in the `quiesce` section of the agent config. Each
+//! step has a timeout; a failing provider aborts the quiesce only when it is
+//! marked `required`, otherwise the failure is just reported.
+
+use crate::config::{MysqlQuiesceConfig, PostgresQuiesceConfig, QuiesceConfig, RedisQuiesceConfig};
+use limiquantix_proto::agent::ApplicationQuiesceResult;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::Mutex;
+use tracing::{error, info, warn};
+
+/// Selected after each statement batch to find the end of its output
+const DONE_MARKER: &str = "__QUANTIX_DONE__";
+
+/// Label of the PostgreSQL backup
+const PG_BACKUP_LABEL: &str = "quantix-snapshot";
+
+/// Maximum time for releasing a provider after the thaw
+const RELEASE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Interval between Redis background save checks
+const REDIS_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Maximum stderr kept per client session
+const MAX_STDERR: usize = 4096;
+
+/// Providers currently holding their application quiesced
+static HELD_PROVIDERS: Lazy<Mutex<Vec<HeldProvider>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// Result of quiescing the enabled applications.
+pub struct ApplicationQuiesce {
+    /// One result per enabled provider
+    pub results: Vec<ApplicationQuiesceResult>,
+    /// Set when a required provider failed (nothing is held then)
+    pub error: Option<String>,
+}
+
+/// A provider whose client session must stay open until the thaw.
+struct HeldProvider {
+    provider: &'static str,
+    session: SqlSession,
+    /// Statement releasing the quiesce
+    release_sql: String,
+}
+
+/// Quiesce every enabled application provider, in the order Redis,
+/// PostgreSQL, MySQL so the global read lock is held for the shortest time.
+pub async fn quiesce_applications(config: &QuiesceConfig) -> ApplicationQuiesce {
+    // A previous quiesce that was never thawed must not keep its locks
+    if !HELD_PROVIDERS.lock().await.is_empty() {
+        warn!("Application providers still held from a previous quiesce, releasing them");
+        release_applications().await;
+    }
+
+    let mut results = Vec::new();
+    let mut error = None;
+
+    if config.redis.enabled {
+        let start = Instant::now();
+        let outcome = quiesce_redis(&config.redis).await;
+        results.push(step_result("redis", start, outcome));
+        if config.redis.required {
+            error = required_failure(&results);
+        }
+    }
+
+    if error.is_none() && config.postgresql.enabled {
+        let start = Instant::now();
+        let outcome = quiesce_postgres(&config.postgresql).await;
+        results.push(step_result("postgresql", start, outcome));
+        if config.postgresql.required {
+            error = required_failure(&results);
+        }
+    }
+
+    if error.is_none() && config.mysql.enabled {
+        let start = Instant::now();
+        let outcome = quiesce_mysql(&config.mysql).await;
+        results.push(step_result("mysql", start, outcome));
+        if config.mysql.required {
+            error = required_failure(&results);
+        }
+    }
+
+    if error.is_some() {
+        release_applications().await;
+    }
+
+    ApplicationQuiesce { results, error }
+}
+
+/// Release every held provider (newest first) and report how it went.
+pub async fn release_applications() -> Vec<ApplicationQuiesceResult> {
+    let held: Vec<HeldProvider> = std::mem::take(&mut *HELD_PROVIDERS.lock().await);
+    let mut results = Vec::new();
+
+    for mut held in held.into_iter().rev() {
+        let start = Instant::now();
+        let outcome = held
+            .session
+            .query(&held.release_sql, RELEASE_TIMEOUT)
+            .await
+            .map(|rows| released_detail(held.provider, &rows));
+        held.session.close().await;
+        results.push(step_result(held.provider, start, outcome));
+    }
+
+    results
+}
+
+/// Build the result of one provider step and log it.
+fn step_result(
+    provider: &str,
+    start: Instant,
+    outcome: Result<String, String>,
+) -> ApplicationQuiesceResult {
+    let duration_ms = start.elapsed().as_millis() as u64;
+    match outcome {
+        Ok(detail) => {
+            info!(provider = %provider, duration_ms, detail = %detail, "Application quiesce step succeeded");
+            ApplicationQuiesceResult {
+                provider: provider.to_string(),
+                success: true,
+                error: String::new(),
+                duration_ms,
+                detail,
+            }
+        }
+        Err(e) => {
+            error!(provider = %provider, duration_ms, error = %e, "Application quiesce step failed");
+            ApplicationQuiesceResult {
+                provider: provider.to_string(),
+                success: false,
+                error: e,
+                duration_ms,
+                detail: String::new(),
+            }
+        }
+    }
+}
+
+/// Error for the quiesce if the last (required) provider failed.
+fn required_failure(results: &[ApplicationQuiesceResult]) -> Option<String> {
+    results
+        .last()
+        .filter(|r| !r.success)
+        .map(|r| format!("Required {} quiesce failed: {}", r.provider, r.error))
+}
+
+/// Detail reported after a provider was released.
+fn released_detail(provider: &str, rows: &[String]) -> String {
+    match (provider, rows.first()) {
+        ("postgresql", Some(lsn)) => format!("backup stopped at LSN {}", lsn),
+        ("mysql", _) => "read lock released".to_string(),
+        _ => "released".to_string(),
+    }
+}
+
+// =============================================================================
+// PostgreSQL
+// =============================================================================
+
+async fn quiesce_postgres(config: &PostgresQuiesceConfig) -> Result<String, String> {
+    let mut command = command_as_user(&config.psql_path, &config.os_user);
+    command.args(["-X", "-q", "-A", "-t", "-v", "ON_ERROR_STOP=1"]);
+    if !config.connection.is_empty() {
+        command.arg("-d").arg(&config.connection);
+    }
+
+    let timeout = Duration::from_secs(config.timeout_secs);
+    let mut session = SqlSession::spawn("psql", command)?;
+
+    let version = session
+        .query("SELECT current_setting('server_version_num');", timeout)
+        .await?;
+    let version: u32 = version
+        .first()
+        .and_then(|v| v.trim().parse().ok())
+        .ok_or_else(|| format!("Unexpected server_version_num: {:?}", version))?;
+    let (start_sql, stop_sql) = postgres_backup_sql(version)?;
+
+    // Fast checkpoint, so the backup starts within the timeout
+    let lsn = session.query(&start_sql, timeout).await?;
+    let lsn = lsn.first().cloned().unwrap_or_default();
+
+    HELD_PROVIDERS.lock().await.push(HeldProvider {
+        provider: "postgresql",
+        session,
+        release_sql: stop_sql,
+    });
+
+    Ok(format!("backup started at LSN {} (server {})", lsn, version))
+}
+
+/// Backup start and stop statements for a `server_version_num`.
+///
+/// PostgreSQL 15 renamed the functions; both use a non-exclusive backup
+/// that is aborted if the session ends before the stop.
+fn postgres_backup_sql(version: u32) -> Result<(String, String), String> {
+    if version >= 150000 {
+        Ok((
+            format!("SELECT pg_backup_start('{}', true);", PG_BACKUP_LABEL),
+            "SELECT lsn FROM pg_backup_stop(false);".to_string(),
+        ))
+    } else if version >= 90600 {
+        Ok((
+            format!("SELECT pg_start_backup('{}', true, false);", PG_BACKUP_LABEL),
+            "SELECT lsn FROM pg_stop_backup(false, false);".to_string(),
+        ))
+    } else {
+        Err(format!("PostgreSQL {} is not supported (9.6 or later required)", version))
+    }
+}
+
+// =============================================================================
+// MySQL / MariaDB
+// =============================================================================
+
+async fn quiesce_mysql(config: &MysqlQuiesceConfig) -> Result<String, String> {
+    let mut command = Command::new(&config.client_path);
+    // --defaults-file must be the first option
+    if !config.defaults_file.is_empty() {
+        command.arg(format!("--defaults-file={}", config.defaults_file));
+    }
+    command.args(["--batch", "--skip-column-names", "--unbuffered"]);
+
+    let mut session = SqlSession::spawn("mysql", command)?;
+
+    // The server gives up waiting for the lock at the same time we do
+    let sql = format!(
+        "SET SESSION lock_wait_timeout = {}; FLUSH TABLES WITH READ LOCK;",
+        config.timeout_secs
+    );
+    session
+        .query(&sql, Duration::from_secs(config.timeout_secs))
+        .await?;
+
+    HELD_PROVIDERS.lock().await.push(HeldProvider {
+        provider: "mysql",
+        session,
+        release_sql: "UNLOCK TABLES;".to_string(),
+    });
+
+    Ok("global read lock held".to_string())
+}
+
+// =============================================================================
+// Redis
+// =============================================================================
+
+async fn quiesce_redis(config: &RedisQuiesceConfig) -> Result<String, String> {
+    let timeout = Duration::from_secs(config.timeout_secs);
+    let deadline = Instant::now() + timeout;
+
+    let last_save = redis_cli(config, &["LASTSAVE"], timeout).await?;
+    let last_save: i64 = last_save
+        .trim()
+        .parse()
+        .map_err(|_| format!("Unexpected LASTSAVE reply: {}", last_save.trim()))?;
+
+    let reply = redis_cli(config, &["BGSAVE"], timeout).await?;
+    let reply = reply.trim();
+    // "scheduled" (AOF rewrite running) or "already in progress" start no
+    // new save; only a save finished after LASTSAVE counts then
+    let started = reply.contains("started");
+    if !started && !reply.contains("scheduled") && !reply.contains("already in progress") {
+        return Err(format!("BGSAVE failed: {}", reply));
+    }
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(format!("Background save did not finish within {}s", config.timeout_secs));
+        }
+
+        let info = parse_redis_info(&redis_cli(config, &["INFO", "persistence"], remaining).await?);
+        let in_progress = info.get("rdb_bgsave_in_progress").map(String::as_str) != Some("0");
+        let saved_at: i64 = info
+            .get("rdb_last_save_time")
+            .and_then(|t| t.parse().ok())
+            .unwrap_or(0);
+
+        if !in_progress && (started || saved_at > last_save) {
+            return match info.get("rdb_last_bgsave_status").map(String::as_str) {
+                Some("ok") => Ok(format!(
+                    "RDB saved in {}s",
+                    info.get("rdb_last_bgsave_time_sec").map(String::as_str).unwrap_or("?")
+                )),
+                status => Err(format!("Background save failed (status {})", status.unwrap_or("unknown"))),
+            };
+        }
+
+        tokio::time::sleep(REDIS_POLL_INTERVAL.min(remaining)).await;
+    }
+}
+
+/// Run one redis-cli command and return its output.
+async fn redis_cli(config: &RedisQuiesceConfig, args: &[&str], timeout: Duration) -> Result<String, String> {
+    let mut command = Command::new(&config.cli_path);
+    command
+        .args(["-h", &config.host, "-p", &config.port.to_string()])
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    // Passed through the environment so it does not show up in ps
+    if !config.password.is_empty() {
+        command.env("REDISCLI_AUTH", &config.password);
+    }
+
+    let output = tokio::time::timeout(timeout, command.output())
+        .await
+        .map_err(|_| format!("redis-cli {} timed out", args.join(" ")))?
+        .map_err(|e| format!("Failed to run redis-cli: {}", e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    if !output.status.success() || stdout.starts_with("ERR") || stdout.starts_with("NOAUTH") {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("redis-cli {} failed: {}{}", args.join(" "), stdout.trim(), stderr.trim()));
+    }
+
+    Ok(stdout)
+}
+
+/// Parse `INFO` output into its `key:value` fields.
+fn parse_redis_info(info: &str) -> HashMap<String, String> {
+    info.lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+// =============================================================================
+// Client sessions
+// =============================================================================
+
+/// Build a command that runs as `os_user` when the agent runs as root.
+fn command_as_user(program: &str, os_user: &str) -> Command {
+    #[cfg(unix)]
+    {
+        if !os_user.is_empty() && nix::unistd::geteuid().is_root() {
+            let mut command = Command::new("runuser");
+            // The user usually cannot enter the agent's working directory
+            command.args(["-u", os_user, "--", program]).current_dir("/");
+            return command;
+        }
+    }
+
+    let _ = os_user;
+    Command::new(program)
+}
+
+/// An interactive SQL client (psql, mysql) fed statements over stdin.
+///
+/// The session, and with it the backup or lock it started, lives until it is
+/// closed or dropped (the client is killed on drop).
+struct SqlSession {
+    client: &'static str,
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stdout: Lines<BufReader<ChildStdout>>,
+    stderr: Arc<std::sync::Mutex<String>>,
+}
+
+impl SqlSession {
+    fn spawn(client: &'static str, mut command: Command) -> Result<Self, String> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("Failed to start {}: {}", client, e))?;
+
+        let stdin = child.stdin.take();
+        let stdout = child.stdout.take().ok_or("Failed to capture stdout")?;
+
+        // Drain stderr so notices never block the client
+        let stderr = Arc::new(std::sync::Mutex::new(String::new()));
+        if let Some(mut pipe) = child.stderr.take() {
+            let stderr = stderr.clone();
+            tokio::spawn(async move {
+                let mut buf = [0u8; 1024];
+                while let Ok(n) = pipe.read(&mut buf).await {
+                    if n == 0 {
+                        break;
+                    }
+                    let mut text = stderr.lock().unwrap();
+                    if text.len() < MAX_STDERR {
+                        text.push_str(&String::from_utf8_lossy(&buf[..n]));
+                    }
+                }
+            });
+        }
+
+        Ok(Self {
+            client,
+            child,
+            stdin,
+            stdout: BufReader::new(stdout).lines(),
+            stderr,
+        })
+    }
+
+    /// Run `sql` and return its output rows.
+    ///
+    /// The client stops at the first error (ON_ERROR_STOP / batch mode), so
+    /// its stdout closing before the marker means the statements failed.
+    async fn query(&mut self, sql: &str, timeout: Duration) -> Result<Vec<String>, String> {
+        let stdin = self.stdin.as_mut().ok_or("Session is closed")?;
+        let input = format!("{}\nSELECT '{}';\n", sql, DONE_MARKER);
+        stdin
+            .write_all(input.as_bytes())
+            .await
+            .map_err(|e| format!("Failed to write to {}: {}", self.client, e))?;
+        stdin
+            .flush()
+            .await
+            .map_err(|e| format!("Failed to write to {}: {}", self.client, e))?;
+
+        let read = async {
+            let mut rows = Vec::new();
+            loop {
+                match self.stdout.next_line().await {
+                    Ok(Some(line)) if line.trim() == DONE_MARKER => return Ok(rows),
+                    Ok(Some(line)) if !line.trim().is_empty() => rows.push(line.trim().to_string()),
+                    Ok(Some(_)) => {}
+                    Ok(None) | Err(_) => return Err(()),
+                }
+            }
+        };
+
+        match tokio::time::timeout(timeout, read).await {
+            Ok(Ok(rows)) => Ok(rows),
+            Ok(Err(())) => {
+                // Give the stderr reader a moment to catch the last message
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                Err(format!("{} failed: {}", self.client, self.stderr_text()))
+            }
+            Err(_) => {
+                let _ = self.child.start_kill();
+                Err(format!("{} timed out after {}s", self.client, timeout.as_secs()))
+            }
+        }
+    }
+
+    fn stderr_text(&self) -> String {
+        let text = self.stderr.lock().unwrap();
+        match text.trim() {
+            "" => "client exited".to_string(),
+            text => text.to_string(),
+        }
+    }
+
+    /// Close stdin so the client exits, killing it if it does not.
+    async fn close(mut self) {
+        drop(self.stdin.take());
+        if tokio::time::timeout(Duration::from_secs(5), self.child.wait()).await.is_err() {
+            warn!(client = %self.client, "Client did not exit, killing it");
+            let _ = self.child.kill().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_postgres_backup_sql() {
+        let (start, stop) = postgres_backup_sql(160002).unwrap();
+        assert_eq!(start, "SELECT pg_backup_start('quantix-snapshot', true);");
+        assert!(stop.contains("pg_backup_stop(false)"));
+
+        let (start, stop) = postgres_backup_sql(140011).unwrap();
+        assert!(start.contains("pg_start_backup('quantix-snapshot', true, false)"));
+        assert!(stop.contains("pg_stop_backup(false, false)"));
+
+        assert!(postgres_backup_sql(90500).is_err());
+    }
+
+    #[test]
+    fn test_parse_redis_info() {
+        let info = "# Persistence\r\nloading:0\r\nrdb_bgsave_in_progress:0\r\n\
+                    rdb_last_save_time:1760781600\r\nrdb_last_bgsave_status:ok\r\n";
+        let fields = parse_redis_info(info);
+        assert_eq!(fields.get("rdb_bgsave_in_progress").map(String::as_str), Some("0"));
+        assert_eq!(fields.get("rdb_last_save_time").map(String::as_str), Some("1760781600"));
+        assert_eq!(fields.get("rdb_last_bgsave_status").map(String::as_str), Some("ok"));
+        assert!(!fields.contains_key("# Persistence"));
+    }
+
+    #[test]
+    fn test_required_failure() {
+        let ok = step_result("redis", Instant::now(), Ok("RDB saved in 0s".to_string()));
+        assert_eq!(required_failure(&[ok.clone()]), None);
+
+        let failed = step_result("mysql", Instant::now(), Err("Access denied".to_string()));
+        assert_eq!(
+            required_failure(&[ok, failed]).as_deref(),
+            Some("Required mysql quiesce failed: Access denied")
+        );
+    }
+}
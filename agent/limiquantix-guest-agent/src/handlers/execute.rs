@@ -284,10 +284,10 @@ async fn wait_for_output(
 
 /// User credentials for running commands.
 #[cfg(unix)]
-struct UserCredentials {
-    uid: u32,
-    gid: u32,
-    supplementary_gids: Vec<u32>,
+pub(super) struct UserCredentials {
+    pub(super) uid: u32,
+    pub(super) gid: u32,
+    pub(super) supplementary_gids: Vec<u32>,
 }
 
 /// Get user credentials for running commands.
@@ -297,7 +297,7 @@ struct UserCredentials {
 /// * `group` - Optional group name (uses user's primary group if empty)
 /// * `include_supplementary` - Whether to include supplementary groups
 #[cfg(unix)]
-fn get_user_credentials(
+pub(super) fn get_user_credentials(
     username: &str,
     group: &str,
     include_supplementary: bool,
@@ -389,14 +389,14 @@ fn get_supplementary_groups(username: &str, primary_gid: u32) -> Vec<u32> {
 }
 
 #[cfg(windows)]
-struct UserCredentials {
-    uid: u32,
-    gid: u32,
-    supplementary_gids: Vec<u32>,
+pub(super) struct UserCredentials {
+    pub(super) uid: u32,
+    pub(super) gid: u32,
+    pub(super) supplementary_gids: Vec<u32>,
 }
 
 #[cfg(windows)]
-fn get_user_credentials(
+pub(super) fn get_user_credentials(
     _username: &str,
     _group: &str,
     _include_supplementary: bool,
@@ -0,0 +1,705 @@
+//! Filesystem maintenance handlers.
+//!
+//! ## Growing filesystems
+//!
+//! After the host grows a virtual disk the new space sits unused behind the
+//! existing partition table. `GrowFilesystems` extends every layer between
+//! the disk and the filesystem:
+//!
+//! - **Linux**: rescan SCSI disks, then for each mounted ext2/3/4, xfs or
+//!   btrfs filesystem walk its block device chain (`lsblk --inverse`) and run
+//!   `growpart` on the partition, `pvresize`/`lvextend` for LVM, and finally
+//!   `resize2fs`, `xfs_growfs` or `btrfs filesystem resize`.
+//! - **Windows**: `Update-HostStorageCache` and `Resize-Partition` to the
+//!   maximum supported size; NTFS/ReFS volumes grow with their partition.
+//!
+//! Only the last partition on a disk can grow; others are reported as
+//! unchanged rather than failed.
+//!
+//! ## Trimming filesystems
+//!
+//! `TrimFilesystems` tells each filesystem to discard its free blocks so a
+//! thin-provisioned disk (with `discard='unmap'` on the host) can shrink:
+//!
+//! - **Linux**: `fstrim -v` on every mounted filesystem that supports it.
+//! - **Windows**: `Optimize-Volume -ReTrim` on fixed NTFS/ReFS volumes. The
+//!   amount trimmed is not reported, so it is returned as 0.
+
+use limiquantix_proto::agent::{
+    agent_message, GrowFilesystemsRequest, GrowFilesystemsResponse, GrownFilesystem,
+    TrimFilesystemsRequest, TrimFilesystemsResponse, TrimmedFilesystem,
+};
+use std::process::Stdio;
+use tokio::process::Command;
+use tracing::{info, warn};
+
+/// Handle a request to grow partitions and filesystems.
+pub async fn handle_grow_filesystems(req: GrowFilesystemsRequest) -> agent_message::Payload {
+    info!(mount_points = ?req.mount_points, dry_run = req.dry_run, "Growing filesystems");
+
+    let response = match grow_filesystems(&req).await {
+        Ok(filesystems) => {
+            for fs in filesystems.iter().filter(|fs| !fs.error.is_empty()) {
+                warn!(mount_point = %fs.mount_point, error = %fs.error, "Failed to grow filesystem");
+            }
+            GrowFilesystemsResponse {
+                success: filesystems.iter().all(|fs| fs.error.is_empty()),
+                filesystems,
+                error: String::new(),
+            }
+        }
+        Err(error) => GrowFilesystemsResponse {
+            success: false,
+            filesystems: Vec::new(),
+            error,
+        },
+    };
+
+    agent_message::Payload::GrowFilesystemsResponse(response)
+}
+
+/// Handle a request to discard unused filesystem blocks.
+pub async fn handle_trim_filesystems(req: TrimFilesystemsRequest) -> agent_message::Payload {
+    info!(mount_points = ?req.mount_points, "Trimming filesystems");
+
+    let response = match trim_filesystems(&req).await {
+        Ok(filesystems) => {
+            for fs in filesystems.iter().filter(|fs| !fs.error.is_empty()) {
+                warn!(mount_point = %fs.mount_point, error = %fs.error, "Failed to trim filesystem");
+            }
+            let trimmed: u64 = filesystems.iter().map(|fs| fs.trimmed_bytes).sum();
+            info!(filesystems = filesystems.len(), trimmed_bytes = trimmed, "Filesystems trimmed");
+            TrimFilesystemsResponse {
+                success: filesystems.iter().all(|fs| fs.error.is_empty()),
+                filesystems,
+                error: String::new(),
+            }
+        }
+        Err(error) => TrimFilesystemsResponse {
+            success: false,
+            filesystems: Vec::new(),
+            error,
+        },
+    };
+
+    agent_message::Payload::TrimFilesystemsResponse(response)
+}
+
+/// Run a command, returning stdout or an error with its output.
+async fn run(program: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new(program)
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    if output.status.success() {
+        Ok(stdout)
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(format!("{} {} failed: {} {}", program, args.join(" "), stdout.trim(), stderr.trim()))
+    }
+}
+
+// =============================================================================
+// Linux
+// =============================================================================
+
+/// Filesystems that can be grown online.
+#[cfg(any(target_os = "linux", test))]
+const GROWABLE_FILESYSTEMS: &[&str] = &["ext2", "ext3", "ext4", "xfs", "btrfs"];
+
+/// Filesystems that support FITRIM (`fstrim`).
+#[cfg(any(target_os = "linux", test))]
+const TRIMMABLE_FILESYSTEMS: &[&str] = &["ext2", "ext3", "ext4", "xfs", "btrfs", "f2fs", "vfat"];
+
+/// A block device in the chain below a filesystem, from `lsblk -P`.
+#[cfg(any(target_os = "linux", test))]
+#[derive(Debug, Clone, PartialEq)]
+struct BlockDevice {
+    name: String,
+    kind: String,
+    fstype: String,
+    parent: String,
+    /// Capacity in bytes
+    size: u64,
+}
+
+/// One command in the plan to grow a filesystem.
+#[cfg(any(target_os = "linux", test))]
+#[derive(Debug, Clone, PartialEq)]
+enum GrowStep {
+    Partition { disk: String, number: u32 },
+    PhysicalVolume { device: String },
+    LogicalVolume { device: String },
+    Filesystem { program: &'static str, args: Vec<String> },
+}
+
+#[cfg(any(target_os = "linux", test))]
+impl GrowStep {
+    fn command(&self) -> (&'static str, Vec<String>) {
+        match self {
+            GrowStep::Partition { disk, number } => ("growpart", vec![disk.clone(), number.to_string()]),
+            GrowStep::PhysicalVolume { device } => ("pvresize", vec![device.clone()]),
+            GrowStep::LogicalVolume { device } => {
+                ("lvextend", vec!["-l".to_string(), "+100%FREE".to_string(), device.clone()])
+            }
+            GrowStep::Filesystem { program, args } => (program, args.clone()),
+        }
+    }
+
+    fn describe(&self) -> String {
+        let (program, args) = self.command();
+        format!("{} {}", program, args.join(" "))
+    }
+
+    /// Whether a failure only means there was nothing to grow.
+    fn is_no_change(&self, error: &str) -> bool {
+        match self {
+            // growpart exits 1 with NOCHANGE when the partition is not last
+            // or already fills the disk.
+            GrowStep::Partition { .. } => error.contains("NOCHANGE"),
+            GrowStep::LogicalVolume { .. } => {
+                error.contains("matches existing size") || error.contains("not larger than existing size")
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+async fn grow_filesystems(req: &GrowFilesystemsRequest) -> Result<Vec<GrownFilesystem>, String> {
+    // A rescan only refreshes disk capacities, so dry runs get one too and
+    // report the disk sizes the host just set
+    rescan_scsi_disks().await;
+
+    let mounts = std::fs::read_to_string("/proc/self/mounts")
+        .map_err(|e| format!("Failed to read mounts: {}", e))?;
+    let targets = block_mounts(&mounts, &req.mount_points, GROWABLE_FILESYSTEMS);
+    for wanted in &req.mount_points {
+        if !targets.iter().any(|(_, mount, _)| mount == wanted) {
+            return Err(format!("{} is not a mounted filesystem that can be grown", wanted));
+        }
+    }
+
+    let mut results = Vec::new();
+    for (device, mount_point, fstype) in targets {
+        results.push(grow_one(&device, &mount_point, &fstype, req.dry_run).await);
+    }
+    Ok(results)
+}
+
+#[cfg(target_os = "linux")]
+async fn grow_one(device: &str, mount_point: &str, fstype: &str, dry_run: bool) -> GrownFilesystem {
+    let mut result = GrownFilesystem {
+        mount_point: mount_point.to_string(),
+        device: device.to_string(),
+        filesystem: fstype.to_string(),
+        size_before_bytes: filesystem_size(mount_point),
+        ..Default::default()
+    };
+
+    let plan = match run("lsblk", &["-P", "-p", "-b", "-s", "-o", "NAME,TYPE,FSTYPE,PKNAME,SIZE", device]).await {
+        Ok(output) => {
+            let chain = parse_lsblk(&output);
+            result.disk_size_bytes = chain.iter().filter(|d| d.kind == "disk").map(|d| d.size).collect();
+            plan_growth(&chain, device, mount_point, fstype)
+        }
+        Err(e) => Err(e),
+    };
+    let plan = match plan {
+        Ok(plan) => plan,
+        Err(e) => {
+            result.error = e;
+            result.size_after_bytes = result.size_before_bytes;
+            return result;
+        }
+    };
+
+    for step in plan {
+        result.steps.push(step.describe());
+        if dry_run {
+            continue;
+        }
+        let (program, args) = step.command();
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        match run(program, &args).await {
+            Ok(_) => {}
+            Err(e) if step.is_no_change(&e) => {}
+            Err(e) => {
+                result.error = e;
+                break;
+            }
+        }
+    }
+
+    result.size_after_bytes = filesystem_size(mount_point);
+    result.grown = result.size_after_bytes > result.size_before_bytes;
+    if result.grown {
+        info!(
+            mount_point = %mount_point,
+            before = result.size_before_bytes,
+            after = result.size_after_bytes,
+            "Filesystem grown"
+        );
+    }
+    result
+}
+
+/// Ask SCSI disks to re-read their capacity (virtio-blk updates by itself).
+#[cfg(target_os = "linux")]
+async fn rescan_scsi_disks() {
+    let Ok(entries) = std::fs::read_dir("/sys/class/block") else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let rescan = entry.path().join("device/rescan");
+        if rescan.exists() {
+            if let Err(e) = tokio::fs::write(&rescan, b"1").await {
+                warn!(device = ?entry.file_name(), error = %e, "Failed to rescan block device");
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[allow(clippy::unnecessary_cast)] // field widths differ between 32- and 64-bit targets
+fn filesystem_size(mount_point: &str) -> u64 {
+    nix::sys::statvfs::statvfs(mount_point)
+        .map(|s| s.blocks() as u64 * s.fragment_size() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(target_os = "linux")]
+async fn trim_filesystems(req: &TrimFilesystemsRequest) -> Result<Vec<TrimmedFilesystem>, String> {
+    let mounts = std::fs::read_to_string("/proc/self/mounts")
+        .map_err(|e| format!("Failed to read mounts: {}", e))?;
+    let targets = block_mounts(&mounts, &req.mount_points, TRIMMABLE_FILESYSTEMS);
+    for wanted in &req.mount_points {
+        if !targets.iter().any(|(_, mount, _)| mount == wanted) {
+            return Err(format!("{} is not a mounted filesystem that can be trimmed", wanted));
+        }
+    }
+
+    let mut results = Vec::new();
+    for (_, mount_point, _) in targets {
+        let mut result = TrimmedFilesystem {
+            mount_point: mount_point.clone(),
+            ..Default::default()
+        };
+        match run("fstrim", &["-v", &mount_point]).await {
+            Ok(output) => result.trimmed_bytes = parse_fstrim(&output).unwrap_or(0),
+            Err(e) => result.error = e,
+        }
+        results.push(result);
+    }
+    Ok(results)
+}
+
+/// Bytes trimmed from `fstrim -v` output (`/: 1.2 GiB (1288490188 bytes) trimmed`).
+#[cfg(any(target_os = "linux", test))]
+fn parse_fstrim(output: &str) -> Option<u64> {
+    let end = output.find(" bytes)")?;
+    let start = output[..end].rfind('(')? + 1;
+    output[start..end].trim().parse().ok()
+}
+
+/// Mounted block-device filesystems of the given types, one entry per device.
+///
+/// Returns (device, mount point, filesystem type), limited to `wanted`
+/// mount points if any are given.
+#[cfg(any(target_os = "linux", test))]
+fn block_mounts(mounts: &str, wanted: &[String], filesystems: &[&str]) -> Vec<(String, String, String)> {
+    let mut result: Vec<(String, String, String)> = Vec::new();
+    for line in mounts.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 3 {
+            continue;
+        }
+        let (device, mount_point, fstype) = (unescape_mount(fields[0]), unescape_mount(fields[1]), fields[2]);
+        if !device.starts_with("/dev/") || !filesystems.contains(&fstype) {
+            continue;
+        }
+        if !wanted.is_empty() && !wanted.contains(&mount_point) {
+            continue;
+        }
+        // Bind mounts and btrfs subvolumes share a device; handle it once.
+        if result.iter().any(|(d, _, _)| *d == device) {
+            continue;
+        }
+        result.push((device, mount_point, fstype.to_string()));
+    }
+    result
+}
+
+/// Decode the octal escapes (`\040` for space) used in /proc/self/mounts.
+#[cfg(any(target_os = "linux", test))]
+fn unescape_mount(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 3 < bytes.len() && bytes[i + 1..i + 4].iter().all(|b| (b'0'..=b'7').contains(b)) {
+            let value = (bytes[i + 1] - b'0') * 64 + (bytes[i + 2] - b'0') * 8 + (bytes[i + 3] - b'0');
+            out.push(value);
+            i += 4;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&out).to_string()
+}
+
+/// Parse `lsblk -P` output (KEY="value" pairs per line).
+#[cfg(any(target_os = "linux", test))]
+fn parse_lsblk(output: &str) -> Vec<BlockDevice> {
+    output
+        .lines()
+        .map(|line| {
+            let mut device = BlockDevice {
+                name: String::new(),
+                kind: String::new(),
+                fstype: String::new(),
+                parent: String::new(),
+                size: 0,
+            };
+            let mut rest = line.trim();
+            while let Some(eq) = rest.find("=\"") {
+                let key = rest[..eq].trim();
+                let value_start = eq + 2;
+                let Some(len) = rest[value_start..].find('"') else {
+                    break;
+                };
+                let value = rest[value_start..value_start + len].to_string();
+                match key {
+                    "NAME" => device.name = value,
+                    "TYPE" => device.kind = value,
+                    "FSTYPE" => device.fstype = value,
+                    "PKNAME" => device.parent = value,
+                    "SIZE" => device.size = value.parse().unwrap_or(0),
+                    _ => {}
+                }
+                rest = &rest[value_start + len + 1..];
+            }
+            device
+        })
+        .filter(|d| !d.name.is_empty())
+        .collect()
+}
+
+/// Work out the commands that grow `device` and its filesystem, from the
+/// disk upwards.
+#[cfg(any(target_os = "linux", test))]
+fn plan_growth(
+    chain: &[BlockDevice],
+    device: &str,
+    mount_point: &str,
+    fstype: &str,
+) -> Result<Vec<GrowStep>, String> {
+    // Follow parents from the filesystem's device down to the disk.
+    let mut path = Vec::new();
+    let mut current = device.to_string();
+    while let Some(dev) = chain.iter().find(|d| d.name == current) {
+        if path.iter().any(|d: &&BlockDevice| d.name == dev.name) {
+            break;
+        }
+        path.push(dev);
+        if dev.parent.is_empty() {
+            break;
+        }
+        current = dev.parent.clone();
+    }
+    if path.is_empty() {
+        return Err(format!("Block device {} not found", device));
+    }
+
+    let mut steps = Vec::new();
+    for dev in path.iter().rev() {
+        match dev.kind.as_str() {
+            "disk" => {}
+            "part" => steps.push(GrowStep::Partition {
+                disk: dev.parent.clone(),
+                number: partition_number(&dev.name, &dev.parent)
+                    .ok_or_else(|| format!("Cannot determine partition number of {}", dev.name))?,
+            }),
+            "lvm" => steps.push(GrowStep::LogicalVolume { device: dev.name.clone() }),
+            other => {
+                return Err(format!("Growing {} devices is not supported ({})", other, dev.name));
+            }
+        }
+        if dev.fstype == "LVM2_member" {
+            steps.push(GrowStep::PhysicalVolume { device: dev.name.clone() });
+        }
+    }
+
+    let filesystem = match fstype {
+        "ext2" | "ext3" | "ext4" => GrowStep::Filesystem { program: "resize2fs", args: vec![device.to_string()] },
+        "xfs" => GrowStep::Filesystem { program: "xfs_growfs", args: vec![mount_point.to_string()] },
+        "btrfs" => GrowStep::Filesystem {
+            program: "btrfs",
+            args: vec!["filesystem".to_string(), "resize".to_string(), "max".to_string(), mount_point.to_string()],
+        },
+        other => return Err(format!("Growing {} filesystems is not supported", other)),
+    };
+    steps.push(filesystem);
+    Ok(steps)
+}
+
+/// Partition number from its device name (`/dev/vda3`, `/dev/nvme0n1p2`).
+#[cfg(any(target_os = "linux", test))]
+fn partition_number(partition: &str, disk: &str) -> Option<u32> {
+    let suffix = partition.strip_prefix(disk)?;
+    suffix.trim_start_matches('p').parse().ok()
+}
+
+// =============================================================================
+// Windows
+// =============================================================================
+
+#[cfg(windows)]
+async fn grow_filesystems(req: &GrowFilesystemsRequest) -> Result<Vec<GrownFilesystem>, String> {
+    let letters: Vec<String> = req
+        .mount_points
+        .iter()
+        .filter_map(|m| m.chars().next())
+        .filter(|c| c.is_ascii_alphabetic())
+        .map(|c| format!("'{}'", c.to_ascii_uppercase()))
+        .collect();
+
+    let script = format!(
+        r#"
+        $Letters = @({letters})
+        $DryRun = ${dry_run}
+        Update-HostStorageCache
+        $results = @()
+        foreach ($vol in Get-Volume | Where-Object {{ $_.DriveLetter -and $_.DriveType -eq 'Fixed' -and $_.FileSystem -in 'NTFS','ReFS' }}) {{
+            $letter = [string]$vol.DriveLetter
+            if ($Letters.Count -gt 0 -and $Letters -notcontains $letter) {{ continue }}
+            $part = Get-Partition -DriveLetter $letter
+            $max = (Get-PartitionSupportedSize -DriveLetter $letter).SizeMax
+            $err = ''
+            $steps = @()
+            if ($max -gt $part.Size + 1MB) {{
+                $steps += "Resize-Partition -DriveLetter $letter -Size $max"
+                if (-not $DryRun) {{
+                    try {{ Resize-Partition -DriveLetter $letter -Size $max -ErrorAction Stop }} catch {{ $err = $_.Exception.Message }}
+                }}
+            }}
+            $results += [pscustomobject]@{{
+                MountPoint = "$($letter):\"
+                Device = "Disk $($part.DiskNumber) Partition $($part.PartitionNumber)"
+                DiskSize = [uint64](Get-Disk -Number $part.DiskNumber).Size
+                FileSystem = $vol.FileSystem
+                Steps = $steps
+                Before = [uint64]$vol.Size
+                After = [uint64](Get-Volume -DriveLetter $letter).Size
+                Error = $err
+            }}
+        }}
+        ConvertTo-Json -InputObject @($results) -Compress
+        "#,
+        letters = letters.join(","),
+        dry_run = if req.dry_run { "true" } else { "false" },
+    );
+
+    let stdout = run("powershell", &["-NoProfile", "-Command", &script]).await?;
+    let json: serde_json::Value = serde_json::from_str(stdout.trim())
+        .map_err(|e| format!("Unexpected PowerShell output: {}", e))?;
+    let items = match json {
+        serde_json::Value::Array(items) => items,
+        item => vec![item],
+    };
+
+    Ok(items
+        .iter()
+        .map(|item| {
+            let before = item["Before"].as_u64().unwrap_or(0);
+            let after = item["After"].as_u64().unwrap_or(0);
+            let steps = match &item["Steps"] {
+                serde_json::Value::Array(steps) => steps.iter().filter_map(|s| s.as_str().map(String::from)).collect(),
+                serde_json::Value::String(step) => vec![step.clone()],
+                _ => Vec::new(),
+            };
+            GrownFilesystem {
+                mount_point: item["MountPoint"].as_str().unwrap_or_default().to_string(),
+                device: item["Device"].as_str().unwrap_or_default().to_string(),
+                filesystem: item["FileSystem"].as_str().unwrap_or_default().to_lowercase(),
+                steps,
+                size_before_bytes: before,
+                size_after_bytes: after,
+                grown: after > before,
+                error: item["Error"].as_str().unwrap_or_default().to_string(),
+                disk_size_bytes: item["DiskSize"].as_u64().into_iter().collect(),
+            }
+        })
+        .collect())
+}
+
+#[cfg(windows)]
+async fn trim_filesystems(req: &TrimFilesystemsRequest) -> Result<Vec<TrimmedFilesystem>, String> {
+    let letters: Vec<String> = req
+        .mount_points
+        .iter()
+        .filter_map(|m| m.chars().next())
+        .filter(|c| c.is_ascii_alphabetic())
+        .map(|c| format!("'{}'", c.to_ascii_uppercase()))
+        .collect();
+
+    let script = format!(
+        r#"
+        $Letters = @({letters})
+        $results = @()
+        foreach ($vol in Get-Volume | Where-Object {{ $_.DriveLetter -and $_.DriveType -eq 'Fixed' -and $_.FileSystem -in 'NTFS','ReFS' }}) {{
+            $letter = [string]$vol.DriveLetter
+            if ($Letters.Count -gt 0 -and $Letters -notcontains $letter) {{ continue }}
+            $err = ''
+            try {{ Optimize-Volume -DriveLetter $letter -ReTrim -ErrorAction Stop }} catch {{ $err = $_.Exception.Message }}
+            $results += [pscustomobject]@{{
+                MountPoint = "$($letter):\"
+                Error = $err
+            }}
+        }}
+        ConvertTo-Json -InputObject @($results) -Compress
+        "#,
+        letters = letters.join(","),
+    );
+
+    let stdout = run("powershell", &["-NoProfile", "-Command", &script]).await?;
+    let json: serde_json::Value = serde_json::from_str(stdout.trim())
+        .map_err(|e| format!("Unexpected PowerShell output: {}", e))?;
+    let items = match json {
+        serde_json::Value::Array(items) => items,
+        item => vec![item],
+    };
+
+    Ok(items
+        .iter()
+        .map(|item| TrimmedFilesystem {
+            mount_point: item["MountPoint"].as_str().unwrap_or_default().to_string(),
+            trimmed_bytes: 0,
+            error: item["Error"].as_str().unwrap_or_default().to_string(),
+        })
+        .collect())
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+async fn grow_filesystems(_req: &GrowFilesystemsRequest) -> Result<Vec<GrownFilesystem>, String> {
+    Err("Growing filesystems is not supported on this platform".to_string())
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+async fn trim_filesystems(_req: &TrimFilesystemsRequest) -> Result<Vec<TrimmedFilesystem>, String> {
+    Err("Trimming filesystems is not supported on this platform".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dev(name: &str, kind: &str, fstype: &str, parent: &str) -> BlockDevice {
+        BlockDevice {
+            name: name.to_string(),
+            kind: kind.to_string(),
+            fstype: fstype.to_string(),
+            parent: parent.to_string(),
+            size: 0,
+        }
+    }
+
+    #[test]
+    fn test_parse_lsblk_pairs() {
+        let output = "NAME=\"/dev/mapper/vg-root\" TYPE=\"lvm\" FSTYPE=\"xfs\" PKNAME=\"/dev/vda3\" SIZE=\"20396900352\"\n\
+                      NAME=\"/dev/vda3\" TYPE=\"part\" FSTYPE=\"LVM2_member\" PKNAME=\"/dev/vda\" SIZE=\"20398997504\"\n\
+                      NAME=\"/dev/vda\" TYPE=\"disk\" FSTYPE=\"\" PKNAME=\"\" SIZE=\"42949672960\"\n";
+        let devices = parse_lsblk(output);
+        assert_eq!(devices.len(), 3);
+        assert_eq!(devices[1].name, "/dev/vda3");
+        assert_eq!(devices[1].parent, "/dev/vda");
+        assert_eq!(devices[2].fstype, "");
+        assert_eq!(devices[2].size, 42949672960);
+    }
+
+    #[test]
+    fn test_plan_lvm_on_partition() {
+        let chain = vec![
+            dev("/dev/mapper/vg-root", "lvm", "xfs", "/dev/vda3"),
+            dev("/dev/vda3", "part", "LVM2_member", "/dev/vda"),
+            dev("/dev/vda", "disk", "", ""),
+        ];
+        let steps: Vec<String> = plan_growth(&chain, "/dev/mapper/vg-root", "/", "xfs")
+            .unwrap()
+            .iter()
+            .map(GrowStep::describe)
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                "growpart /dev/vda 3",
+                "pvresize /dev/vda3",
+                "lvextend -l +100%FREE /dev/mapper/vg-root",
+                "xfs_growfs /",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_plain_partition_and_whole_disk() {
+        let chain = vec![
+            dev("/dev/nvme0n1p2", "part", "ext4", "/dev/nvme0n1"),
+            dev("/dev/nvme0n1", "disk", "", ""),
+        ];
+        let steps = plan_growth(&chain, "/dev/nvme0n1p2", "/", "ext4").unwrap();
+        assert_eq!(steps[0], GrowStep::Partition { disk: "/dev/nvme0n1".to_string(), number: 2 });
+        assert_eq!(steps[1].describe(), "resize2fs /dev/nvme0n1p2");
+
+        let chain = vec![dev("/dev/vdb", "disk", "btrfs", "")];
+        let steps = plan_growth(&chain, "/dev/vdb", "/data", "btrfs").unwrap();
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].describe(), "btrfs filesystem resize max /data");
+
+        let chain = vec![
+            dev("/dev/mapper/luks", "crypt", "ext4", "/dev/vda2"),
+            dev("/dev/vda2", "part", "crypto_LUKS", "/dev/vda"),
+            dev("/dev/vda", "disk", "", ""),
+        ];
+        assert!(plan_growth(&chain, "/dev/mapper/luks", "/", "ext4").is_err());
+    }
+
+    #[test]
+    fn test_growable_mounts() {
+        let mounts = "/dev/vda1 / ext4 rw 0 0\n\
+                      proc /proc proc rw 0 0\n\
+                      /dev/vdb /srv/my\\040data xfs rw 0 0\n\
+                      /dev/vdc /home btrfs rw,subvol=/home 0 0\n\
+                      /dev/vdc /var btrfs rw,subvol=/var 0 0\n\
+                      /dev/vdd /boot/efi vfat rw 0 0\n";
+        let all = block_mounts(mounts, &[], GROWABLE_FILESYSTEMS);
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[1], ("/dev/vdb".to_string(), "/srv/my data".to_string(), "xfs".to_string()));
+
+        let root = block_mounts(mounts, &["/".to_string()], GROWABLE_FILESYSTEMS);
+        assert_eq!(root, vec![("/dev/vda1".to_string(), "/".to_string(), "ext4".to_string())]);
+
+        let trimmable = block_mounts(mounts, &[], TRIMMABLE_FILESYSTEMS);
+        assert_eq!(trimmable.len(), 4);
+        assert_eq!(trimmable[3].1, "/boot/efi");
+    }
+
+    #[test]
+    fn test_parse_fstrim() {
+        assert_eq!(parse_fstrim("/: 1.2 GiB (1288490188 bytes) trimmed\n"), Some(1288490188));
+        assert_eq!(parse_fstrim("/home: 0 B (0 bytes) trimmed on /dev/vdb\n"), Some(0));
+        assert_eq!(parse_fstrim("fstrim: /: FITRIM ioctl failed"), None);
+    }
+
+    #[test]
+    fn test_no_change_is_tolerated() {
+        let step = GrowStep::Partition { disk: "/dev/vda".to_string(), number: 1 };
+        assert!(step.is_no_change("NOCHANGE: partition 1 is size 41940959. it cannot be grown"));
+        assert!(!step.is_no_change("growpart: command not found"));
+    }
+}
@@ -8,30 +8,71 @@ use prost_types::Timestamp;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::{debug, info, warn};
 
+mod app_quiesce;
 mod clipboard;
 mod directory;
 mod display;
 mod execute;
 mod file;
+mod filesystem;
 mod inventory;
 mod lifecycle;
+mod patches;
 mod process;
 mod quiesce;
 mod service;
+mod session;
+mod stream;
 mod timesync;
+mod transfer;
 mod update;
+mod users;
+
+pub use patches::reboot_required;
+pub use update::{check_pending_update, confirm_update};
 
 use crate::AgentConfig;
 
 /// Message handler that routes messages to the appropriate handler.
 pub struct MessageHandler {
     config: AgentConfig,
+    sessions: session::SessionManager,
+    streams: stream::StreamManager,
 }
 
 impl MessageHandler {
     /// Create a new message handler with the given configuration.
-    pub fn new(config: AgentConfig) -> Self {
-        Self { config }
+    ///
+    /// `events` carries unsolicited messages (session output, stream data) to the host.
+    pub fn new(config: AgentConfig, events: tokio::sync::mpsc::Sender<AgentMessage>) -> Self {
+        Self {
+            config,
+            sessions: session::SessionManager::new(events.clone()),
+            streams: stream::StreamManager::new(events),
+        }
+    }
+
+    /// Kill all interactive sessions and close all streams (the host connection is gone).
+    pub async fn close_sessions(&self) {
+        self.sessions.close_all().await;
+        self.streams.close_all().await;
+    }
+
+    /// Whether a message must be handled in arrival order.
+    ///
+    /// Session input and stream frames carry ordered byte streams; they are
+    /// handled inline by the read loop instead of on their own task. Their
+    /// handlers only queue the data and never block for long.
+    pub fn is_ordered(message: &AgentMessage) -> bool {
+        matches!(
+            message.payload,
+            Some(agent_message::Payload::SessionInput(_))
+                | Some(agent_message::Payload::SessionResize(_))
+                | Some(agent_message::Payload::CloseSession(_))
+                | Some(agent_message::Payload::StreamData(_))
+                | Some(agent_message::Payload::StreamWindow(_))
+                | Some(agent_message::Payload::StreamClose(_))
+        )
     }
 
     /// Handle an incoming message and return an optional response.
@@ -59,6 +100,54 @@ impl MessageHandler {
                 Some(execute::handle_execute(req, &self.config).await)
             }
 
+            // =========================================================================
+            // Interactive Sessions (PTY shell / streaming exec)
+            // =========================================================================
+            agent_message::Payload::OpenSession(req) => {
+                info!(session_id = %req.session_id, command = %req.command, pty = req.pty, "Handling open session request");
+                Some(self.sessions.open(req, &self.config).await)
+            }
+
+            agent_message::Payload::SessionInput(req) => {
+                self.sessions.input(req).await;
+                None
+            }
+
+            agent_message::Payload::SessionResize(req) => {
+                debug!(session_id = %req.session_id, cols = req.cols, rows = req.rows, "Handling session resize");
+                self.sessions.resize(req).await;
+                None
+            }
+
+            agent_message::Payload::CloseSession(req) => {
+                info!(session_id = %req.session_id, force = req.force, "Handling close session request");
+                self.sessions.close(req).await;
+                None
+            }
+
+            // =========================================================================
+            // Multiplexed Streams (TCP port forwarding)
+            // =========================================================================
+            agent_message::Payload::OpenStream(req) => {
+                info!(stream_id = %req.stream_id, host = %req.target_host, port = req.target_port, "Handling open stream request");
+                Some(self.streams.open(req, &self.config).await)
+            }
+
+            agent_message::Payload::StreamData(frame) => {
+                self.streams.data(frame).await;
+                None
+            }
+
+            agent_message::Payload::StreamWindow(update) => {
+                self.streams.window(update).await;
+                None
+            }
+
+            agent_message::Payload::StreamClose(frame) => {
+                self.streams.close(frame).await;
+                None
+            }
+
             // =========================================================================
             // File Operations
             // =========================================================================
@@ -72,6 +161,24 @@ impl MessageHandler {
                 Some(file::handle_file_read(req, &self.config).await)
             }
 
+            agent_message::Payload::TreeUpload(req) => {
+                debug!(
+                    transfer_id = %req.transfer_id,
+                    offset = req.offset,
+                    "Handling tree upload chunk"
+                );
+                Some(transfer::handle_tree_upload(req, &self.config).await)
+            }
+
+            agent_message::Payload::TreeDownload(req) => {
+                debug!(
+                    transfer_id = %req.transfer_id,
+                    offset = req.offset,
+                    "Handling tree download chunk"
+                );
+                Some(transfer::handle_tree_download(req, &self.config).await)
+            }
+
             // =========================================================================
             // Directory Operations (Phase 2)
             // =========================================================================
@@ -126,6 +233,52 @@ impl MessageHandler {
                 Some(quiesce::handle_thaw(req, &self.config).await)
             }
 
+            agent_message::Payload::GrowFilesystems(req) => {
+                info!(mount_points = ?req.mount_points, "Handling grow filesystems request");
+                Some(filesystem::handle_grow_filesystems(req).await)
+            }
+
+            agent_message::Payload::TrimFilesystems(req) => {
+                info!(mount_points = ?req.mount_points, "Handling trim filesystems request");
+                Some(filesystem::handle_trim_filesystems(req).await)
+            }
+
+            // =========================================================================
+            // User Accounts
+            // =========================================================================
+            agent_message::Payload::ListUsers(req) => {
+                info!(include_system = req.include_system, "Handling list users request");
+                Some(users::handle_list_users(req).await)
+            }
+
+            agent_message::Payload::UserAccount(req) => {
+                info!(username = %req.username, action = req.action, "Handling user account request");
+                Some(users::handle_user_account(req, &message_id).await)
+            }
+
+            agent_message::Payload::ListLoginSessions(req) => {
+                info!(username = %req.username, "Handling list login sessions request");
+                Some(users::handle_list_login_sessions(req).await)
+            }
+
+            // =========================================================================
+            // OS Patching
+            // =========================================================================
+            agent_message::Payload::ListUpdates(req) => {
+                info!(refresh = req.refresh, security_only = req.security_only, "Handling list updates request");
+                Some(patches::handle_list_updates(req).await)
+            }
+
+            agent_message::Payload::ApplyUpdates(req) => {
+                info!(
+                    security_only = req.security_only,
+                    packages = req.packages.len(),
+                    reboot_policy = req.reboot_policy,
+                    "Handling apply updates request"
+                );
+                Some(patches::handle_apply_updates(req).await)
+            }
+
             // =========================================================================
             // Time Synchronization
             // =========================================================================
@@ -243,6 +396,19 @@ impl MessageHandler {
             | agent_message::Payload::ListInstalledSoftwareResponse(_)
             | agent_message::Payload::AgentUpdateResponse(_)
             | agent_message::Payload::GetCapabilitiesResponse(_)
+            | agent_message::Payload::OpenSessionResponse(_)
+            | agent_message::Payload::OpenStreamResponse(_)
+            | agent_message::Payload::TreeUploadResponse(_)
+            | agent_message::Payload::TreeDownloadResponse(_)
+            | agent_message::Payload::GrowFilesystemsResponse(_)
+            | agent_message::Payload::TrimFilesystemsResponse(_)
+            | agent_message::Payload::ListUsersResponse(_)
+            | agent_message::Payload::UserAccountResponse(_)
+            | agent_message::Payload::ListLoginSessionsResponse(_)
+            | agent_message::Payload::ListUpdatesResponse(_)
+            | agent_message::Payload::ApplyUpdatesResponse(_)
+            | agent_message::Payload::SessionOutput(_)
+            | agent_message::Payload::SessionExited(_)
             | agent_message::Payload::Telemetry(_)
             | agent_message::Payload::AgentReady(_)
             | agent_message::Payload::Error(_)
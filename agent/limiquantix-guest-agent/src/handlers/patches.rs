@@ -0,0 +1,1281 @@
+//! OS patch handlers.
+//!
+//! Lists pending updates and installs them with the guest's own package
+//! manager, so patch compliance no longer needs SSH access to every VM:
+//!
+//! - **apt**: a simulated `apt-get dist-upgrade`; updates coming from a
+//!   `*-security` suite are security fixes.
+//! - **dnf/yum**: `check-update`, classified with `updateinfo list`.
+//! - **zypper**: `list-updates` for packages, `list-patches --category security`
+//!   for security patches.
+//! - **apk**: `apk version -l '<'` (Alpine publishes no security metadata).
+//! - **Windows**: the Windows Update Agent COM API through PowerShell.
+//!
+//! An install run lists the pending updates, installs the selected ones and
+//! lists again: whatever is no longer pending was installed. Only one run
+//! happens at a time. Afterwards the reboot policy decides whether the
+//! guest restarts.
+
+use super::lifecycle;
+use limiquantix_proto::agent::{
+    agent_message, ApplyRebootPolicy, ApplyUpdatesRequest, ApplyUpdatesResponse, AvailableUpdate,
+    ListUpdatesRequest, ListUpdatesResponse, ShutdownRequest, ShutdownType,
+};
+use once_cell::sync::Lazy;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+use tracing::{error, info, warn};
+
+/// Listing may refresh the package metadata first
+const LIST_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Default limit of an install run
+const DEFAULT_APPLY_TIMEOUT_SECS: u32 = 3600;
+
+/// Package manager output kept in the response
+const OUTPUT_TAIL_BYTES: usize = 4096;
+
+/// Time for the response to reach the host before a policy reboot
+const REBOOT_GRACE: Duration = Duration::from_secs(5);
+
+/// How long a reboot-required check is reused by telemetry
+const REBOOT_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Set when an install run reported that a reboot is needed
+static REBOOT_PENDING: AtomicBool = AtomicBool::new(false);
+
+/// Last system reboot-required check
+static REBOOT_CHECK: Mutex<Option<(Instant, bool)>> = Mutex::new(None);
+
+/// Set while an install run that outlived its timeout is still going
+static DETACHED_RUN: AtomicBool = AtomicBool::new(false);
+
+/// Held for the duration of an install run
+static APPLY_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mutex::new(()));
+
+// Each platform only constructs its own variants
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PackageManager {
+    Apt,
+    Dnf,
+    Yum,
+    Zypper,
+    Apk,
+    WindowsUpdate,
+}
+
+impl PackageManager {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Apt => "apt",
+            Self::Dnf => "dnf",
+            Self::Yum => "yum",
+            Self::Zypper => "zypper",
+            Self::Apk => "apk",
+            Self::WindowsUpdate => "windows-update",
+        }
+    }
+
+    /// Binary that installs the updates
+    #[cfg(unix)]
+    fn program(self) -> &'static str {
+        match self {
+            Self::Apt => "apt-get",
+            other => other.name(),
+        }
+    }
+
+    fn classifies_security(self) -> bool {
+        self != Self::Apk
+    }
+
+    #[cfg(unix)]
+    fn detect() -> Result<Self, String> {
+        [
+            ("apt-get", Self::Apt),
+            ("dnf", Self::Dnf),
+            ("yum", Self::Yum),
+            ("zypper", Self::Zypper),
+            ("apk", Self::Apk),
+        ]
+        .into_iter()
+        .find(|(program, _)| on_path(program))
+        .map(|(_, manager)| manager)
+        .ok_or_else(|| "No supported package manager found (apt, dnf, yum, zypper, apk)".to_string())
+    }
+
+    #[cfg(windows)]
+    fn detect() -> Result<Self, String> {
+        Ok(Self::WindowsUpdate)
+    }
+}
+
+/// What an install run did.
+struct ApplyOutcome {
+    manager: PackageManager,
+    installed: Vec<AvailableUpdate>,
+    failed: Vec<String>,
+    reboot_required: bool,
+    output: String,
+    /// Set when the package manager failed; some updates may still be installed
+    error: Option<String>,
+}
+
+/// Handle a list updates request.
+pub async fn handle_list_updates(req: ListUpdatesRequest) -> agent_message::Payload {
+    let response = match list_updates(req.refresh).await {
+        Ok((manager, updates)) => {
+            let updates: Vec<AvailableUpdate> = updates
+                .into_iter()
+                .filter(|u| !req.security_only || u.security)
+                .collect();
+            info!(
+                package_manager = manager.name(),
+                updates = updates.len(),
+                security = updates.iter().filter(|u| u.security).count(),
+                "Listed pending updates"
+            );
+            ListUpdatesResponse {
+                success: true,
+                error: String::new(),
+                package_manager: manager.name().to_string(),
+                updates,
+                security_classified: manager.classifies_security(),
+                reboot_required: reboot_required(),
+            }
+        }
+        Err(e) => {
+            error!(error = %e, "Failed to list updates");
+            ListUpdatesResponse {
+                success: false,
+                error: e,
+                reboot_required: reboot_required(),
+                ..Default::default()
+            }
+        }
+    };
+
+    agent_message::Payload::ListUpdatesResponse(response)
+}
+
+/// Handle an apply updates request, then reboot according to its policy.
+pub async fn handle_apply_updates(req: ApplyUpdatesRequest) -> agent_message::Payload {
+    let policy = ApplyRebootPolicy::try_from(req.reboot_policy).unwrap_or(ApplyRebootPolicy::Never);
+
+    let guard = APPLY_LOCK.try_lock();
+    if guard.is_err() || DETACHED_RUN.load(Ordering::SeqCst) {
+        return agent_message::Payload::ApplyUpdatesResponse(ApplyUpdatesResponse {
+            success: false,
+            error: "An update run is already in progress".to_string(),
+            ..Default::default()
+        });
+    }
+
+    let start = Instant::now();
+    let outcome = match apply_updates(&req).await {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            error!(error = %e, "Update run failed");
+            return agent_message::Payload::ApplyUpdatesResponse(ApplyUpdatesResponse {
+                success: false,
+                error: e,
+                reboot_required: reboot_required(),
+                duration_ms: start.elapsed().as_millis() as u64,
+                ..Default::default()
+            });
+        }
+    };
+
+    if outcome.reboot_required {
+        REBOOT_PENDING.store(true, Ordering::SeqCst);
+    }
+    let reboot_required = outcome.reboot_required || reboot_required();
+    let reboot_scheduled = match policy {
+        ApplyRebootPolicy::Never => false,
+        ApplyRebootPolicy::IfRequired => reboot_required,
+        ApplyRebootPolicy::Always => true,
+    };
+
+    match &outcome.error {
+        None => info!(
+            package_manager = outcome.manager.name(),
+            installed = outcome.installed.len(),
+            reboot_required,
+            reboot_scheduled,
+            "Updates installed"
+        ),
+        Some(e) => warn!(
+            package_manager = outcome.manager.name(),
+            installed = outcome.installed.len(),
+            failed = outcome.failed.len(),
+            error = %e,
+            "Update run finished with errors"
+        ),
+    }
+
+    if reboot_scheduled {
+        schedule_reboot(req.reboot_delay_seconds);
+    }
+
+    agent_message::Payload::ApplyUpdatesResponse(ApplyUpdatesResponse {
+        success: outcome.error.is_none(),
+        error: outcome.error.unwrap_or_default(),
+        package_manager: outcome.manager.name().to_string(),
+        installed: outcome.installed,
+        failed: outcome.failed,
+        reboot_required,
+        reboot_scheduled,
+        output: outcome.output,
+        duration_ms: start.elapsed().as_millis() as u64,
+    })
+}
+
+/// Whether the guest needs a reboot to finish applying updates. Cheap
+/// enough for every telemetry report: system checks are cached for a minute.
+pub fn reboot_required() -> bool {
+    if REBOOT_PENDING.load(Ordering::SeqCst) {
+        return true;
+    }
+
+    let mut check = REBOOT_CHECK.lock().unwrap_or_else(|e| e.into_inner());
+    match *check {
+        Some((at, required)) if at.elapsed() < REBOOT_CHECK_INTERVAL => required,
+        _ => {
+            let required = system_reboot_required();
+            *check = Some((Instant::now(), required));
+            required
+        }
+    }
+}
+
+/// Reboot after the grace period, so the response is delivered first.
+fn schedule_reboot(delay_seconds: u32) {
+    info!(delay_seconds, "Scheduling reboot after update run");
+    tokio::spawn(async move {
+        tokio::time::sleep(REBOOT_GRACE).await;
+        let request = ShutdownRequest {
+            r#type: ShutdownType::Reboot as i32,
+            delay_seconds,
+            message: "Rebooting to finish installing updates".to_string(),
+        };
+        if let agent_message::Payload::ShutdownResponse(resp) = lifecycle::handle_shutdown(request).await {
+            if !resp.accepted {
+                error!(error = %resp.error, "Reboot after update run failed");
+            }
+        }
+    });
+}
+
+/// Detect the package manager and list its pending updates.
+async fn list_updates(refresh: bool) -> Result<(PackageManager, Vec<AvailableUpdate>), String> {
+    let manager = PackageManager::detect()?;
+    if refresh {
+        refresh_metadata(manager).await?;
+    }
+    Ok((manager, pending_updates(manager).await?))
+}
+
+/// Pick the pending updates an apply request asks for.
+fn select_updates<'a>(
+    pending: &'a [AvailableUpdate],
+    req: &ApplyUpdatesRequest,
+) -> Result<Vec<&'a AvailableUpdate>, String> {
+    if let Some(missing) = req.packages.iter().find(|p| !pending.iter().any(|u| &u.id == *p)) {
+        return Err(format!("No pending update for {}", missing));
+    }
+
+    Ok(pending
+        .iter()
+        .filter(|u| !req.security_only || u.security)
+        .filter(|u| req.packages.is_empty() || req.packages.contains(&u.id))
+        .collect())
+}
+
+/// Keep the last `max` bytes of the output, on a character boundary.
+fn tail(output: &str, max: usize) -> String {
+    let output = output.trim();
+    if output.len() <= max {
+        return output.to_string();
+    }
+    let mut start = output.len() - max;
+    while !output.is_char_boundary(start) {
+        start += 1;
+    }
+    output[start..].to_string()
+}
+
+// =============================================================================
+// Output parsing
+// =============================================================================
+
+/// Parse the `Inst` lines of `apt-get -s dist-upgrade`, e.g.
+/// `Inst bash [5.2.15-2+b8] (5.2.15-2+b13 Debian:12.14/oldstable [amd64])`.
+#[cfg(any(unix, test))]
+fn parse_apt_simulation(output: &str) -> Vec<AvailableUpdate> {
+    let mut updates = Vec::new();
+
+    for line in output.lines() {
+        let Some(rest) = line.strip_prefix("Inst ") else {
+            continue;
+        };
+        let Some((name, rest)) = rest.split_once(' ') else {
+            continue;
+        };
+
+        // New packages (e.g. a new kernel) have no installed version
+        let (current_version, rest) = match rest.strip_prefix('[') {
+            Some(rest) => match rest.split_once("] ") {
+                Some((version, rest)) => (version, rest),
+                None => continue,
+            },
+            None => ("", rest),
+        };
+
+        let Some(candidate) = rest.strip_prefix('(').and_then(|r| r.split(')').next()) else {
+            continue;
+        };
+        let Some((available_version, origins)) = candidate.split_once(' ') else {
+            continue;
+        };
+        let origins = origins.rsplit_once(" [").map_or(origins, |(origins, _arch)| origins);
+
+        updates.push(AvailableUpdate {
+            id: name.to_string(),
+            name: name.to_string(),
+            current_version: current_version.to_string(),
+            available_version: available_version.to_string(),
+            source: origins.to_string(),
+            security: origins.split(", ").any(|o| o.ends_with("-security") || o.contains("-Security:")),
+            ..Default::default()
+        });
+    }
+
+    updates
+}
+
+/// Split an RPM `name-[epoch:]version-release.arch` into name and arch.
+#[cfg(any(unix, test))]
+fn parse_nevra(nevra: &str) -> Option<(&str, &str)> {
+    let (nevr, arch) = nevra.rsplit_once('.')?;
+    let mut parts = nevr.rsplitn(3, '-');
+    let _release = parts.next()?;
+    let version = parts.next()?;
+    let name = parts.next()?;
+    if name.is_empty() || !version.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    Some((name, arch))
+}
+
+/// Parse `dnf check-update` / `yum check-update`: `name.arch version repo`,
+/// where yum wraps long package names onto their own line.
+#[cfg(any(unix, test))]
+fn parse_check_update(output: &str) -> Vec<AvailableUpdate> {
+    let mut updates = Vec::new();
+    let mut carry: Option<&str> = None;
+
+    for line in output.lines() {
+        if line.starts_with("Obsoleting") || line.starts_with("Security:") {
+            break;
+        }
+
+        let mut tokens: Vec<&str> = line.split_whitespace().collect();
+        if let Some(name) = carry.take() {
+            tokens.insert(0, name);
+        }
+        if tokens.len() == 1 && tokens[0].contains('.') {
+            carry = Some(tokens[0]);
+            continue;
+        }
+        if tokens.len() != 3 {
+            continue;
+        }
+
+        let Some((name, _arch)) = tokens[0].rsplit_once('.') else {
+            continue;
+        };
+        updates.push(AvailableUpdate {
+            id: tokens[0].to_string(),
+            name: name.to_string(),
+            available_version: tokens[1].to_string(),
+            source: tokens[2].to_string(),
+            ..Default::default()
+        });
+    }
+
+    updates
+}
+
+/// Mark updates covered by advisories from `updateinfo list`. dnf 4 and
+/// yum print `RHSA-2024:1234 Important/Sec. openssl-1:3.0.7-27.el9.x86_64`,
+/// dnf 5 prints `FEDORA-2024-1 security Moderate curl-8.6.0-7.fc40.x86_64 <date>`.
+#[cfg(any(unix, test))]
+fn apply_updateinfo(updates: &mut [AvailableUpdate], output: &str) {
+    for line in output.lines() {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        if tokens.len() < 3 || tokens[0] == "Name" {
+            continue;
+        }
+
+        let (security, severity) = match tokens[1].strip_suffix("/Sec.") {
+            Some(severity) => (true, severity),
+            None if tokens[1] == "security" => (true, tokens[2]),
+            None => (false, ""),
+        };
+        let Some((name, arch)) = tokens[2..]
+            .iter()
+            .filter(|t| t.contains('-') && t.contains('.'))
+            .find_map(|t| parse_nevra(t))
+        else {
+            continue;
+        };
+
+        let id = format!("{}.{}", name, arch);
+        for update in updates.iter_mut().filter(|u| u.id == id) {
+            if !update.advisories.iter().any(|a| a == tokens[0]) {
+                update.advisories.push(tokens[0].to_string());
+            }
+            if security {
+                update.security = true;
+                if update.severity.is_empty() && severity != "None" {
+                    update.severity = severity.to_string();
+                }
+            }
+        }
+    }
+}
+
+/// Parse a zypper table into rows keyed by the header names.
+#[cfg(any(unix, test))]
+fn parse_zypper_table(output: &str) -> Vec<std::collections::HashMap<String, String>> {
+    let mut header: Option<Vec<String>> = None;
+    let mut rows = Vec::new();
+
+    for line in output.lines().filter(|l| l.contains('|')) {
+        let cells: Vec<String> = line.split('|').map(|c| c.trim().to_string()).collect();
+        if cells.iter().all(|c| c.chars().all(|ch| ch == '-' || ch == '+')) {
+            continue;
+        }
+        match &header {
+            None => header = Some(cells),
+            Some(names) => rows.push(names.iter().cloned().zip(cells).collect()),
+        }
+    }
+
+    rows
+}
+
+/// Parse `zypper list-updates` (packages) and `zypper list-patches` (patches).
+#[cfg(any(unix, test))]
+fn parse_zypper(list_updates: &str, security_patches: &str) -> Vec<AvailableUpdate> {
+    let field = |row: &std::collections::HashMap<String, String>, name: &str| {
+        row.get(name).cloned().unwrap_or_default()
+    };
+
+    let patches = parse_zypper_table(security_patches).into_iter().map(|row| {
+        let name = field(&row, "Name");
+        AvailableUpdate {
+            id: format!("patch:{}", name),
+            source: field(&row, "Repository"),
+            security: true,
+            severity: field(&row, "Severity"),
+            advisories: vec![name.clone()],
+            name,
+            ..Default::default()
+        }
+    });
+    let packages = parse_zypper_table(list_updates).into_iter().map(|row| AvailableUpdate {
+        id: field(&row, "Name"),
+        name: field(&row, "Name"),
+        current_version: field(&row, "Current Version"),
+        available_version: field(&row, "Available Version"),
+        source: field(&row, "Repository"),
+        ..Default::default()
+    });
+
+    patches.chain(packages).filter(|u| !u.name.is_empty()).collect()
+}
+
+/// Parse `apk version -l '<'`: `busybox-1.36.1-r2   < 1.36.1-r5`.
+#[cfg(any(unix, test))]
+fn parse_apk_version(output: &str) -> Vec<AvailableUpdate> {
+    let mut updates = Vec::new();
+
+    for line in output.lines() {
+        let Some((installed, available)) = line.split_once('<') else {
+            continue;
+        };
+        let installed = installed.trim();
+        let mut parts = installed.rsplitn(3, '-');
+        let (Some(release), Some(version), Some(name)) = (parts.next(), parts.next(), parts.next()) else {
+            continue;
+        };
+
+        updates.push(AvailableUpdate {
+            id: name.to_string(),
+            name: name.to_string(),
+            current_version: format!("{}-{}", version, release),
+            available_version: available.trim().to_string(),
+            ..Default::default()
+        });
+    }
+
+    updates
+}
+
+// =============================================================================
+// Linux
+// =============================================================================
+
+/// Whether `program` is an executable on the PATH (or in the sbin dirs,
+/// which are missing from some service PATHs).
+#[cfg(unix)]
+fn on_path(program: &str) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = std::env::var("PATH").unwrap_or_default();
+    let found = path
+        .split(':')
+        .chain(["/usr/sbin", "/sbin", "/usr/bin", "/bin"])
+        .filter(|dir| !dir.is_empty())
+        .any(|dir| {
+            std::fs::metadata(std::path::Path::new(dir).join(program))
+                .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+                .unwrap_or(false)
+        });
+    found
+}
+
+/// Wait for the output of a command. A listing is killed at the timeout.
+/// An install run is left to finish instead, as killing a package manager
+/// mid-install can leave the package database half updated: the run is
+/// reported as timed out and no other run starts until it exits.
+async fn wait_output(
+    mut command: Command,
+    what: &str,
+    timeout: Duration,
+    detach_on_timeout: bool,
+) -> Result<std::process::Output, String> {
+    let child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(!detach_on_timeout)
+        .spawn()
+        .map_err(|e| format!("Failed to run {}: {}", what, e))?;
+
+    let mut output = Box::pin(child.wait_with_output());
+    match tokio::time::timeout(timeout, &mut output).await {
+        Ok(result) => result.map_err(|e| format!("Failed to run {}: {}", what, e)),
+        Err(_) if detach_on_timeout => {
+            DETACHED_RUN.store(true, Ordering::SeqCst);
+            let program = what.to_string();
+            tokio::spawn(async move {
+                match output.await {
+                    Ok(output) => info!(program = %program, status = %output.status, "Timed out update run finished"),
+                    Err(e) => warn!(program = %program, error = %e, "Timed out update run failed"),
+                }
+                DETACHED_RUN.store(false, Ordering::SeqCst);
+            });
+            Err(format!(
+                "{} did not finish within {}s, it keeps running in the background",
+                what,
+                timeout.as_secs()
+            ))
+        }
+        Err(_) => Err(format!("{} did not finish within {}s", what, timeout.as_secs())),
+    }
+}
+
+/// Run a package manager listing command. Exit codes in `ok_codes` count
+/// as success; returns the exit code and the combined output.
+#[cfg(unix)]
+async fn run_package_manager(
+    program: &str,
+    args: &[&str],
+    ok_codes: &[i32],
+    timeout: Duration,
+) -> Result<(i32, String), String> {
+    run_package_manager_with(program, args, ok_codes, timeout, false).await
+}
+
+/// Run a package manager install command, which outlives its timeout.
+#[cfg(unix)]
+async fn run_install(
+    program: &str,
+    args: &[&str],
+    ok_codes: &[i32],
+    timeout: Duration,
+) -> Result<(i32, String), String> {
+    run_package_manager_with(program, args, ok_codes, timeout, true).await
+}
+
+#[cfg(unix)]
+async fn run_package_manager_with(
+    program: &str,
+    args: &[&str],
+    ok_codes: &[i32],
+    timeout: Duration,
+    detach_on_timeout: bool,
+) -> Result<(i32, String), String> {
+    let mut command = Command::new(program);
+    command
+        .args(args)
+        .env("LC_ALL", "C")
+        .env("DEBIAN_FRONTEND", "noninteractive")
+        .env("NEEDRESTART_MODE", "a");
+    let output = wait_output(command, program, timeout, detach_on_timeout).await?;
+
+    let code = output.status.code().unwrap_or(-1);
+    let mut text = String::from_utf8_lossy(&output.stdout).to_string();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    if code == 0 || ok_codes.contains(&code) {
+        Ok((code, text))
+    } else {
+        Err(format!("{} {} exited with {}: {}", program, args.join(" "), code, tail(&text, 1024)))
+    }
+}
+
+/// zypper informational exit codes (updates/reboot needed, repos skipped)
+#[cfg(unix)]
+const ZYPPER_OK_CODES: &[i32] = &[100, 101, 102, 103, 106];
+
+/// zypper: the installed patches need a reboot
+#[cfg(unix)]
+const ZYPPER_EXIT_REBOOT_NEEDED: i32 = 102;
+
+/// zypper: zypper itself was updated and must run again
+#[cfg(unix)]
+const ZYPPER_EXIT_RESTART_NEEDED: i32 = 103;
+
+#[cfg(unix)]
+async fn refresh_metadata(manager: PackageManager) -> Result<(), String> {
+    let (program, args): (&str, &[&str]) = match manager {
+        PackageManager::Apt => ("apt-get", &["-q", "update"]),
+        PackageManager::Dnf => ("dnf", &["-q", "makecache", "--refresh"]),
+        PackageManager::Yum => ("yum", &["-q", "makecache"]),
+        PackageManager::Zypper => ("zypper", &["-n", "-q", "refresh"]),
+        PackageManager::Apk => ("apk", &["update"]),
+        PackageManager::WindowsUpdate => return Ok(()),
+    };
+    let ok_codes: &[i32] = if manager == PackageManager::Zypper { ZYPPER_OK_CODES } else { &[] };
+    run_package_manager(program, args, ok_codes, LIST_TIMEOUT).await.map(|_| ())
+}
+
+#[cfg(unix)]
+async fn pending_updates(manager: PackageManager) -> Result<Vec<AvailableUpdate>, String> {
+    match manager {
+        PackageManager::Apt => {
+            let (_, output) = run_package_manager(
+                "apt-get",
+                &["-s", "-o", "Debug::NoLocking=1", "dist-upgrade"],
+                &[],
+                LIST_TIMEOUT,
+            )
+            .await?;
+            Ok(parse_apt_simulation(&output))
+        }
+        PackageManager::Dnf | PackageManager::Yum => {
+            let program = manager.program();
+            // check-update exits with 100 when updates are available
+            let (_, output) = run_package_manager(program, &["-q", "check-update"], &[100], LIST_TIMEOUT).await?;
+            let mut updates = parse_check_update(&output);
+
+            let (_, installed) = run_package_manager(
+                "rpm",
+                &["-qa", "--queryformat", "%{NAME}.%{ARCH}\t%{VERSION}-%{RELEASE}\n"],
+                &[],
+                LIST_TIMEOUT,
+            )
+            .await?;
+            let installed: std::collections::HashMap<&str, &str> =
+                installed.lines().filter_map(|l| l.split_once('\t')).collect();
+            for update in &mut updates {
+                if let Some(version) = installed.get(update.id.as_str()) {
+                    update.current_version = version.to_string();
+                }
+            }
+
+            match run_package_manager(program, &["-q", "updateinfo", "list"], &[], LIST_TIMEOUT).await {
+                Ok((_, advisories)) => apply_updateinfo(&mut updates, &advisories),
+                Err(e) => warn!(error = %e, "No advisory metadata, updates are unclassified"),
+            }
+            Ok(updates)
+        }
+        PackageManager::Zypper => {
+            let (_, packages) =
+                run_package_manager("zypper", &["-n", "-q", "list-updates"], ZYPPER_OK_CODES, LIST_TIMEOUT).await?;
+            let (_, patches) = run_package_manager(
+                "zypper",
+                &["-n", "-q", "list-patches", "--category", "security"],
+                ZYPPER_OK_CODES,
+                LIST_TIMEOUT,
+            )
+            .await?;
+            Ok(parse_zypper(&packages, &patches))
+        }
+        PackageManager::Apk => {
+            let (_, output) = run_package_manager("apk", &["version", "-l", "<"], &[], LIST_TIMEOUT).await?;
+            Ok(parse_apk_version(&output))
+        }
+        PackageManager::WindowsUpdate => Ok(Vec::new()),
+    }
+}
+
+/// Install the selected updates (everything when the request has no filter).
+#[cfg(unix)]
+async fn apply_updates(req: &ApplyUpdatesRequest) -> Result<ApplyOutcome, String> {
+    let manager = PackageManager::detect()?;
+    if req.security_only && !manager.classifies_security() {
+        return Err(format!("{} does not classify updates as security fixes", manager.name()));
+    }
+
+    let pending = pending_updates(manager).await?;
+    let selected = select_updates(&pending, req)?;
+    if selected.is_empty() {
+        return Ok(ApplyOutcome {
+            manager,
+            installed: Vec::new(),
+            failed: Vec::new(),
+            reboot_required: false,
+            output: "No pending updates to install".to_string(),
+            error: None,
+        });
+    }
+
+    let full_upgrade = !req.security_only && req.packages.is_empty();
+    let ids: Vec<&str> = selected.iter().map(|u| u.id.as_str()).collect();
+    let mut args: Vec<&str> = match manager {
+        PackageManager::Apt => vec![
+            "-y",
+            "-q",
+            "-o",
+            "Dpkg::Options::=--force-confdef",
+            "-o",
+            "Dpkg::Options::=--force-confold",
+        ],
+        PackageManager::Dnf | PackageManager::Yum => vec!["-y"],
+        PackageManager::Zypper => vec!["-n", "--auto-agree-with-licenses"],
+        PackageManager::Apk | PackageManager::WindowsUpdate => vec![],
+    };
+    match (manager, full_upgrade) {
+        (PackageManager::Apt, true) => args.push("dist-upgrade"),
+        (PackageManager::Apt, false) => args.extend(["install", "--only-upgrade"]),
+        (PackageManager::Dnf, _) => args.push("upgrade"),
+        (PackageManager::Yum, _) => args.push("update"),
+        (PackageManager::Zypper, true) => args.push("update"),
+        (PackageManager::Zypper, false) => args.push("install"),
+        (PackageManager::Apk, _) | (PackageManager::WindowsUpdate, _) => args.push("upgrade"),
+    }
+    // zypper "update" would reject the patch: ids, so only pass ids to targeted runs
+    if !full_upgrade {
+        args.extend(&ids);
+    }
+
+    let timeout_secs = if req.timeout_seconds > 0 { req.timeout_seconds } else { DEFAULT_APPLY_TIMEOUT_SECS };
+    let timeout = Duration::from_secs(timeout_secs as u64);
+    let ok_codes: &[i32] = if manager == PackageManager::Zypper { ZYPPER_OK_CODES } else { &[] };
+
+    let mut result = run_install(manager.program(), &args, ok_codes, timeout).await;
+    if manager == PackageManager::Zypper && matches!(result, Ok((ZYPPER_EXIT_RESTART_NEEDED, _))) {
+        info!("zypper updated itself, running it again");
+        result = run_install(manager.program(), &args, ok_codes, timeout).await;
+    }
+    // Listing again would wait for the package manager lock of the running install
+    if let (true, Err(e)) = (DETACHED_RUN.load(Ordering::SeqCst), &result) {
+        return Err(e.clone());
+    }
+    let (exit_code, output, error) = match result {
+        Ok((code, output)) => (code, tail(&output, OUTPUT_TAIL_BYTES), None),
+        Err(e) => (-1, String::new(), Some(e)),
+    };
+
+    // Whatever is no longer pending was installed
+    let (installed, failed) = match pending_updates(manager).await {
+        Ok(remaining) => {
+            let (failed, installed): (Vec<&AvailableUpdate>, Vec<&AvailableUpdate>) =
+                selected.into_iter().partition(|u| remaining.iter().any(|r| r.id == u.id));
+            (
+                installed.into_iter().cloned().collect(),
+                failed.into_iter().map(|u| u.name.clone()).collect(),
+            )
+        }
+        Err(e) => {
+            warn!(error = %e, "Could not list the remaining updates");
+            if error.is_none() {
+                (selected.into_iter().cloned().collect(), Vec::new())
+            } else {
+                (Vec::new(), selected.into_iter().map(|u| u.name.clone()).collect())
+            }
+        }
+    };
+
+    let reboot_required = (manager == PackageManager::Zypper && exit_code == ZYPPER_EXIT_REBOOT_NEEDED)
+        || needs_restarting(manager).await
+        || system_reboot_required();
+
+    Ok(ApplyOutcome { manager, installed, failed, reboot_required, output, error })
+}
+
+/// Ask dnf/yum whether core packages were updated since boot
+/// (`needs-restarting -r` exits with 1 when a reboot is needed).
+#[cfg(unix)]
+async fn needs_restarting(manager: PackageManager) -> bool {
+    let (program, args): (&str, &[&str]) = match manager {
+        PackageManager::Dnf => ("dnf", &["-q", "needs-restarting", "-r"]),
+        PackageManager::Yum if on_path("needs-restarting") => ("needs-restarting", &["-r"]),
+        _ => return false,
+    };
+
+    let status = Command::new(program)
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await;
+    matches!(status.map(|s| s.code()), Ok(Some(1)))
+}
+
+/// Reboot markers left by the package managers, or an installed kernel
+/// newer than the running one (covers distributions that leave no marker).
+#[cfg(unix)]
+fn system_reboot_required() -> bool {
+    const MARKERS: &[&str] = &[
+        "/run/reboot-required",
+        "/var/run/reboot-required",
+        "/run/reboot-needed",
+        "/var/run/reboot-needed",
+    ];
+    if MARKERS.iter().any(|m| std::path::Path::new(m).exists()) {
+        return true;
+    }
+
+    let Ok(running) = std::fs::read_to_string("/proc/sys/kernel/osrelease") else {
+        return false;
+    };
+    let Ok(entries) = std::fs::read_dir("/lib/modules") else {
+        return false;
+    };
+    // depmod writes modules.dep for every installed kernel; directories a
+    // removed kernel leaves behind (DKMS builds and the like) have none
+    let installed: Vec<String> = entries
+        .flatten()
+        .filter(|entry| entry.path().join("modules.dep").is_file())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+    newer_kernel_installed(running.trim(), &installed)
+}
+
+/// Whether the newest of the installed kernels is newer than the running one.
+#[cfg(unix)]
+fn newer_kernel_installed(running: &str, installed: &[String]) -> bool {
+    installed
+        .iter()
+        .max_by(|a, b| compare_kernel_versions(a, b))
+        .is_some_and(|newest| compare_kernel_versions(newest, running) == std::cmp::Ordering::Greater)
+}
+
+/// Compare kernel releases (`6.8.0-45-generic`, `5.14.0-427.13.1.el9_4.x86_64`)
+/// segment by segment, like rpm: digit runs numerically, letter runs
+/// alphabetically, a digit run is newer than a letter run and the release
+/// with segments left over is newer.
+#[cfg(unix)]
+fn compare_kernel_versions(a: &str, b: &str) -> std::cmp::Ordering {
+    use std::cmp::Ordering;
+
+    fn segments(version: &str) -> Vec<&str> {
+        let mut segments = Vec::new();
+        let mut rest = version.trim_start_matches(|c: char| !c.is_ascii_alphanumeric());
+        while let Some(first) = rest.chars().next() {
+            let end = if first.is_ascii_digit() {
+                rest.find(|c: char| !c.is_ascii_digit())
+            } else {
+                rest.find(|c: char| !c.is_ascii_alphabetic())
+            }
+            .unwrap_or(rest.len());
+            segments.push(&rest[..end]);
+            rest = rest[end..].trim_start_matches(|c: char| !c.is_ascii_alphanumeric());
+        }
+        segments
+    }
+
+    let (a, b) = (segments(a), segments(b));
+    for (x, y) in a.iter().zip(&b) {
+        let x_numeric = x.starts_with(|c: char| c.is_ascii_digit());
+        let y_numeric = y.starts_with(|c: char| c.is_ascii_digit());
+        let ordering = match (x_numeric, y_numeric) {
+            (true, true) => {
+                let (x, y) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+            }
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => x.cmp(y),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    a.len().cmp(&b.len())
+}
+
+// =============================================================================
+// Windows
+// =============================================================================
+
+/// Shared PowerShell helpers: search pending updates and convert them to
+/// the JSON shape read by `windows_update`.
+#[cfg(windows)]
+const WU_COMMON: &str = r#"
+$SecurityCategory = '0fa1201d-4330-4fa8-8ae9-b877473b6441'
+function Find-QxUpdates($session) {
+    $searcher = $session.CreateUpdateSearcher()
+    $searcher.Search("IsInstalled=0 and IsHidden=0 and Type='Software'").Updates
+}
+function ConvertTo-QxUpdate($u) {
+    $categories = @($u.Categories | ForEach-Object { $_ })
+    [pscustomobject]@{
+        Id = $u.Identity.UpdateID
+        Title = $u.Title
+        Advisories = @(@($u.KBArticleIDs | ForEach-Object { "KB$_" }) + @($u.CveIDs | ForEach-Object { $_ }))
+        Severity = [string]$u.MsrcSeverity
+        Source = (@($categories | ForEach-Object { $_.Name }) -join ', ')
+        Security = [bool](@($categories | Where-Object { $_.CategoryID -eq $SecurityCategory }).Count -gt 0 -or $u.MsrcSeverity)
+        Reboot = [bool]($u.InstallationBehavior.RebootBehavior -ne 0)
+    }
+}
+"#;
+
+/// Run a PowerShell script, passing values through the environment. An
+/// install script keeps running past the timeout (see `wait_output`).
+#[cfg(windows)]
+async fn powershell(
+    script: &str,
+    env: &[(&str, &str)],
+    timeout: Duration,
+    detach_on_timeout: bool,
+) -> Result<serde_json::Value, String> {
+    let script = format!("$ErrorActionPreference = 'Stop'\n{}\n{}", WU_COMMON, script);
+    let mut command = Command::new("powershell");
+    command
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .envs(env.iter().copied());
+    let output = wait_output(command, "PowerShell", timeout, detach_on_timeout).await?;
+    if !output.status.success() {
+        return Err(format!("Windows Update failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    serde_json::from_slice(&output.stdout).map_err(|e| format!("Unexpected Windows Update output: {}", e))
+}
+
+/// `ConvertTo-Json` turns single-element arrays into objects.
+#[cfg(windows)]
+fn json_list(value: &serde_json::Value) -> Vec<serde_json::Value> {
+    match value {
+        serde_json::Value::Array(items) => items.clone(),
+        serde_json::Value::Null => Vec::new(),
+        other => vec![other.clone()],
+    }
+}
+
+#[cfg(windows)]
+fn windows_update(value: &serde_json::Value) -> AvailableUpdate {
+    let text = |key: &str| value[key].as_str().unwrap_or_default().to_string();
+    AvailableUpdate {
+        id: text("Id"),
+        name: text("Title"),
+        source: text("Source"),
+        security: value["Security"].as_bool().unwrap_or(false),
+        severity: text("Severity"),
+        advisories: json_list(&value["Advisories"])
+            .iter()
+            .filter_map(|a| a.as_str().map(str::to_string))
+            .collect(),
+        reboot_expected: value["Reboot"].as_bool().unwrap_or(false),
+        ..Default::default()
+    }
+}
+
+/// The Windows Update Agent always searches online, so there is no
+/// separate metadata refresh.
+#[cfg(windows)]
+async fn refresh_metadata(_manager: PackageManager) -> Result<(), String> {
+    Ok(())
+}
+
+#[cfg(windows)]
+async fn pending_updates(_manager: PackageManager) -> Result<Vec<AvailableUpdate>, String> {
+    let script = r#"
+        $session = New-Object -ComObject Microsoft.Update.Session
+        ConvertTo-Json -InputObject @(Find-QxUpdates $session | ForEach-Object { ConvertTo-QxUpdate $_ }) -Depth 4 -Compress
+    "#;
+    let json = powershell(script, &[], LIST_TIMEOUT, false).await?;
+    Ok(json_list(&json).iter().map(windows_update).collect())
+}
+
+/// Download and install the selected updates through the Windows Update Agent.
+#[cfg(windows)]
+async fn apply_updates(req: &ApplyUpdatesRequest) -> Result<ApplyOutcome, String> {
+    let manager = PackageManager::WindowsUpdate;
+    let pending = pending_updates(manager).await?;
+    let selected = select_updates(&pending, req)?;
+    if selected.is_empty() {
+        return Ok(ApplyOutcome {
+            manager,
+            installed: Vec::new(),
+            failed: Vec::new(),
+            reboot_required: false,
+            output: "No pending updates to install".to_string(),
+            error: None,
+        });
+    }
+
+    // Result codes: 2 succeeded, 3 succeeded with errors, 4 failed, 5 aborted
+    let script = r#"
+        $ids = $env:QX_UPDATE_IDS -split ','
+        $session = New-Object -ComObject Microsoft.Update.Session
+        $selected = New-Object -ComObject Microsoft.Update.UpdateColl
+        foreach ($u in Find-QxUpdates $session) {
+            if ($ids -notcontains $u.Identity.UpdateID) { continue }
+            if (-not $u.EulaAccepted) { $u.AcceptEula() }
+            [void]$selected.Add($u)
+        }
+        $downloader = $session.CreateUpdateDownloader()
+        $downloader.Updates = $selected
+        [void]$downloader.Download()
+        $installer = $session.CreateUpdateInstaller()
+        $installer.Updates = $selected
+        $install = $installer.Install()
+        $results = @(for ($i = 0; $i -lt $selected.Count; $i++) {
+            $update = ConvertTo-QxUpdate $selected.Item($i)
+            $code = $install.GetUpdateResult($i).ResultCode
+            $update | Add-Member -NotePropertyName Installed -NotePropertyValue ($code -eq 2 -or $code -eq 3)
+            $update
+        })
+        [pscustomobject]@{
+            Results = $results
+            RebootRequired = [bool]$install.RebootRequired
+            ResultCode = [int]$install.ResultCode
+        } | ConvertTo-Json -Depth 5 -Compress
+    "#;
+
+    let ids: Vec<&str> = selected.iter().map(|u| u.id.as_str()).collect();
+    let ids = ids.join(",");
+    let timeout_secs = if req.timeout_seconds > 0 { req.timeout_seconds } else { DEFAULT_APPLY_TIMEOUT_SECS };
+    let timeout = Duration::from_secs(timeout_secs as u64);
+    let json = powershell(script, &[("QX_UPDATE_IDS", &ids)], timeout, true).await?;
+
+    let mut installed = Vec::new();
+    let mut failed = Vec::new();
+    for result in json_list(&json["Results"]) {
+        let update = windows_update(&result);
+        if result["Installed"].as_bool().unwrap_or(false) {
+            installed.push(update);
+        } else {
+            failed.push(update.name);
+        }
+    }
+    let error = (!failed.is_empty()).then(|| format!("{} updates failed to install", failed.len()));
+
+    Ok(ApplyOutcome {
+        manager,
+        output: format!("Windows Update result code {}", json["ResultCode"].as_i64().unwrap_or(0)),
+        reboot_required: json["RebootRequired"].as_bool().unwrap_or(false),
+        installed,
+        failed,
+        error,
+    })
+}
+
+/// Pending reboot flags of Windows Update and Component Based Servicing,
+/// read from the registry directly (this runs on the async runtime).
+#[cfg(windows)]
+fn system_reboot_required() -> bool {
+    use windows::core::{w, PCWSTR};
+    use windows::Win32::Foundation::ERROR_SUCCESS;
+    use windows::Win32::System::Registry::{RegCloseKey, RegOpenKeyExW, HKEY, HKEY_LOCAL_MACHINE, KEY_READ};
+
+    const KEYS: &[PCWSTR] = &[
+        w!(r"SOFTWARE\Microsoft\Windows\CurrentVersion\WindowsUpdate\Auto Update\RebootRequired"),
+        w!(r"SOFTWARE\Microsoft\Windows\CurrentVersion\Component Based Servicing\RebootPending"),
+    ];
+    KEYS.iter().any(|key| unsafe {
+        let mut handle = HKEY::default();
+        let exists = RegOpenKeyExW(HKEY_LOCAL_MACHINE, *key, 0, KEY_READ, &mut handle) == ERROR_SUCCESS;
+        if exists {
+            let _ = RegCloseKey(handle);
+        }
+        exists
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_apt_simulation() {
+        let output = "\
+Reading package lists...
+Inst base-files [12.4+deb12u11] (12.4+deb12u14 Debian:12.14/oldstable [amd64])
+Inst libgnutls30 [3.7.9-2+deb12u6] (3.7.9-2+deb12u7 Debian-Security:12/oldstable-security [amd64]) []
+Inst libssl3 [3.0.2-0ubuntu1.10] (3.0.2-0ubuntu1.12 Ubuntu:22.04/jammy-updates, Ubuntu:22.04/jammy-security [amd64])
+Inst linux-image-6.1.0-30-amd64 (6.1.124-1 Debian:12.9/stable [amd64])
+Conf base-files (12.4+deb12u14 Debian:12.14/oldstable [amd64])
+";
+        let updates = parse_apt_simulation(output);
+        assert_eq!(updates.len(), 4);
+
+        assert_eq!(updates[0].name, "base-files");
+        assert_eq!(updates[0].current_version, "12.4+deb12u11");
+        assert_eq!(updates[0].available_version, "12.4+deb12u14");
+        assert_eq!(updates[0].source, "Debian:12.14/oldstable");
+        assert!(!updates[0].security);
+
+        assert!(updates[1].security);
+        assert!(updates[2].security);
+        assert_eq!(updates[2].source, "Ubuntu:22.04/jammy-updates, Ubuntu:22.04/jammy-security");
+
+        assert_eq!(updates[3].current_version, "");
+        assert_eq!(updates[3].available_version, "6.1.124-1");
+    }
+
+    #[test]
+    fn test_parse_dnf_check_update_and_updateinfo() {
+        let output = "
+kernel.x86_64                      5.14.0-362.8.1.el9_3                baseos
+openssl-libs.x86_64                1:3.0.7-25.el9_3                    baseos
+python3-some-very-long-package-name.noarch
+                                   2.1-3.el9                           appstream
+Obsoleting Packages
+grub2-tools.x86_64                 1:2.06-70.el9                       baseos
+";
+        let mut updates = parse_check_update(output);
+        assert_eq!(updates.len(), 3);
+        assert_eq!(updates[0].id, "kernel.x86_64");
+        assert_eq!(updates[0].name, "kernel");
+        assert_eq!(updates[1].available_version, "1:3.0.7-25.el9_3");
+        assert_eq!(updates[2].id, "python3-some-very-long-package-name.noarch");
+        assert_eq!(updates[2].source, "appstream");
+
+        let dnf4 = "\
+RHSA-2023:7549 Important/Sec. kernel-5.14.0-362.8.1.el9_3.x86_64
+RHBA-2023:7000 bugfix         openssl-libs-1:3.0.7-25.el9_3.x86_64
+";
+        apply_updateinfo(&mut updates, dnf4);
+        assert!(updates[0].security);
+        assert_eq!(updates[0].severity, "Important");
+        assert_eq!(updates[0].advisories, vec!["RHSA-2023:7549"]);
+        assert!(!updates[1].security);
+        assert_eq!(updates[1].advisories, vec!["RHBA-2023:7000"]);
+
+        let dnf5 = "\
+Name               Type     Severity Package                                         Issued
+FEDORA-2024-1a2b3c security Moderate openssl-libs-1:3.0.7-25.el9_3.x86_64 2024-05-01 01:23:45
+";
+        apply_updateinfo(&mut updates, dnf5);
+        assert!(updates[1].security);
+        assert_eq!(updates[1].severity, "Moderate");
+        assert!(!updates[2].security);
+    }
+
+    #[test]
+    fn test_parse_zypper() {
+        let list_updates = "\
+S | Repository                          | Name          | Current Version       | Available Version     | Arch
+--+-------------------------------------+---------------+-----------------------+-----------------------+-------
+v | SLE-Module-Basesystem15-SP5-Updates | libopenssl1_1 | 1.1.1l-150500.17.12.1 | 1.1.1l-150500.17.15.1 | x86_64
+";
+        let patches = "\
+Repository                          | Name                                        | Category | Severity  | Interactive | Status | Summary
+------------------------------------+---------------------------------------------+----------+-----------+-------------+--------+--------------------------------
+SLE-Module-Basesystem15-SP5-Updates | SUSE-SLE-Module-Basesystem-15-SP5-2023-4375 | security | important | ---         | needed | Security update for openssl-1_1
+";
+        let updates = parse_zypper(list_updates, patches);
+        assert_eq!(updates.len(), 2);
+
+        assert_eq!(updates[0].id, "patch:SUSE-SLE-Module-Basesystem-15-SP5-2023-4375");
+        assert!(updates[0].security);
+        assert_eq!(updates[0].severity, "important");
+
+        assert_eq!(updates[1].id, "libopenssl1_1");
+        assert_eq!(updates[1].current_version, "1.1.1l-150500.17.12.1");
+        assert_eq!(updates[1].available_version, "1.1.1l-150500.17.15.1");
+        assert!(!updates[1].security);
+    }
+
+    #[test]
+    fn test_parse_apk_version() {
+        let output = "\
+Installed:                                Available:
+busybox-1.36.1-r2                       < 1.36.1-r5
+py3-setuptools-68.0.0-r0                < 70.3.0-r0
+";
+        let updates = parse_apk_version(output);
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0].name, "busybox");
+        assert_eq!(updates[0].current_version, "1.36.1-r2");
+        assert_eq!(updates[0].available_version, "1.36.1-r5");
+        assert_eq!(updates[1].name, "py3-setuptools");
+    }
+
+    #[test]
+    fn test_select_updates() {
+        let update = |id: &str, security: bool| AvailableUpdate {
+            id: id.to_string(),
+            name: id.to_string(),
+            security,
+            ..Default::default()
+        };
+        let pending = vec![update("bash", false), update("openssl", true), update("curl", true)];
+
+        let all = select_updates(&pending, &ApplyUpdatesRequest::default()).unwrap();
+        assert_eq!(all.len(), 3);
+
+        let request = ApplyUpdatesRequest { security_only: true, ..Default::default() };
+        let security: Vec<&str> = select_updates(&pending, &request).unwrap().iter().map(|u| u.id.as_str()).collect();
+        assert_eq!(security, vec!["openssl", "curl"]);
+
+        let request = ApplyUpdatesRequest { packages: vec!["bash".to_string()], ..Default::default() };
+        assert_eq!(select_updates(&pending, &request).unwrap().len(), 1);
+
+        let request = ApplyUpdatesRequest { packages: vec!["vim".to_string()], ..Default::default() };
+        assert!(select_updates(&pending, &request).is_err());
+    }
+
+    #[test]
+    fn test_tail() {
+        assert_eq!(tail("  short \n", 100), "short");
+        assert_eq!(tail("abcdef", 3), "def");
+        assert_eq!(tail("aé", 1), "");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_newer_kernel_installed() {
+        use std::cmp::Ordering;
+
+        assert_eq!(compare_kernel_versions("6.8.0-47-generic", "6.8.0-45-generic"), Ordering::Greater);
+        assert_eq!(compare_kernel_versions("6.8.0-100-generic", "6.8.0-99-generic"), Ordering::Greater);
+        assert_eq!(
+            compare_kernel_versions("5.14.0-427.13.1.el9_4.x86_64", "5.14.0-427.13.1.el9_4.x86_64"),
+            Ordering::Equal
+        );
+        assert_eq!(
+            compare_kernel_versions("5.14.0-427.el9.x86_64", "5.14.0-427.13.1.el9_4.x86_64"),
+            Ordering::Less
+        );
+        assert_eq!(compare_kernel_versions("6.6.31-0-lts", "6.6.9-0-lts"), Ordering::Greater);
+
+        let installed = |versions: &[&str]| versions.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        // Installing any kernel after boot is not enough, it has to be newer
+        assert!(!newer_kernel_installed("6.8.0-47-generic", &installed(&["6.8.0-45-generic", "6.8.0-47-generic"])));
+        assert!(newer_kernel_installed("6.8.0-45-generic", &installed(&["6.8.0-45-generic", "6.8.0-47-generic"])));
+        // The running kernel was replaced by the upgrade
+        assert!(newer_kernel_installed("6.6.9-0-lts", &installed(&["6.6.31-0-lts"])));
+        assert!(!newer_kernel_installed("6.8.0-47-generic", &[]));
+    }
+}
@@ -15,8 +15,11 @@
 //!
 //! - Auto-thaw timeout: Filesystems are automatically thawed after timeout
 //! - Pre/post scripts: Optional hooks for database flush/resume
+//! - Application providers: Built-in PostgreSQL, MySQL/MariaDB and Redis
+//!   quiesce (see [`super::app_quiesce`]), released after the thaw
 //! - Partial failure handling: Reports which filesystems failed
 
+use super::app_quiesce;
 use crate::AgentConfig;
 use limiquantix_proto::agent::{
     agent_message, FrozenFilesystem, QuiesceFilesystemsRequest, QuiesceFilesystemsResponse,
@@ -52,7 +55,7 @@ const DEFAULT_POST_THAW_DIR: &str = "/etc/limiquantix/post-thaw.d";
 /// Handle a quiesce (freeze) request.
 pub async fn handle_quiesce(
     req: QuiesceFilesystemsRequest,
-    _config: &AgentConfig,
+    config: &AgentConfig,
 ) -> agent_message::Payload {
     info!(
         mount_points = ?req.mount_points,
@@ -80,6 +83,24 @@ pub async fn handle_quiesce(
         }
     }
 
+    // Bring databases to a consistent state before the freeze
+    let applications = if req.skip_applications {
+        Vec::new()
+    } else {
+        let quiesce = app_quiesce::quiesce_applications(&config.quiesce).await;
+        if let Some(error) = quiesce.error {
+            return agent_message::Payload::QuiesceResponse(QuiesceFilesystemsResponse {
+                success: false,
+                frozen: vec![],
+                error,
+                quiesce_token: String::new(),
+                auto_thaw_at: None,
+                applications: quiesce.results,
+            });
+        }
+        quiesce.results
+    };
+
     // Get list of mount points to freeze
     let mount_points = if req.mount_points.is_empty() {
         get_writable_filesystems().await
@@ -88,12 +109,14 @@ pub async fn handle_quiesce(
     };
 
     if mount_points.is_empty() {
+        app_quiesce::release_applications().await;
         return agent_message::Payload::QuiesceResponse(QuiesceFilesystemsResponse {
             success: false,
             frozen: vec![],
             error: "No filesystems to freeze".to_string(),
             quiesce_token: String::new(),
             auto_thaw_at: None,
+            applications,
         });
     }
 
@@ -141,6 +164,7 @@ pub async fn handle_quiesce(
                 frozen_set.remove(&fs.mount_point);
             }
         }
+        app_quiesce::release_applications().await;
 
         return agent_message::Payload::QuiesceResponse(QuiesceFilesystemsResponse {
             success: false,
@@ -148,6 +172,7 @@ pub async fn handle_quiesce(
             error: "Failed to freeze all filesystems".to_string(),
             quiesce_token: String::new(),
             auto_thaw_at: None,
+            applications,
         });
     }
 
@@ -184,6 +209,7 @@ pub async fn handle_quiesce(
         error: String::new(),
         quiesce_token: token,
         auto_thaw_at: auto_thaw_timestamp,
+        applications,
     })
 }
 
@@ -239,6 +265,10 @@ pub async fn handle_thaw(
         }
     }
 
+    // Release the application providers once writes are possible again
+    drop(frozen_set);
+    let applications = app_quiesce::release_applications().await;
+
     // Run post-thaw scripts if requested
     if req.run_post_thaw_scripts {
         if let Err(e) = run_hook_scripts(&post_thaw_dir).await {
@@ -259,6 +289,7 @@ pub async fn handle_thaw(
         thawed_mount_points: thawed,
         error: error_msg,
         frozen_duration_ms: duration_ms,
+        applications,
     })
 }
 
@@ -568,6 +599,8 @@ fn spawn_auto_thaw_timer(mount_points: Vec<String>, timeout_secs: u32) {
                     }
                 }
             }
+            drop(frozen_set);
+            app_quiesce::release_applications().await;
 
             AUTO_THAW_ACTIVE.store(false, Ordering::SeqCst);
         }
@@ -0,0 +1,669 @@
+//! Interactive session handler.
+//!
+//! Handles OpenSessionRequest and the messages that drive a running session
+//! (input, resize, close). Unlike `execute`, output is streamed to the host
+//! as unsolicited SessionOutput messages while the process runs, and a
+//! SessionExited message is sent once it ends.
+//!
+//! Sessions either run on a pseudo-terminal (interactive shells, Unix only)
+//! or with plain pipes (streaming exec). All sessions of a connection are
+//! killed when the connection to the host is lost.
+
+use crate::AgentConfig;
+use limiquantix_proto::agent::{
+    agent_message, AgentMessage, CloseSessionRequest, OpenSessionRequest, OpenSessionResponse,
+    SessionExited, SessionInput, SessionOutput, SessionResize, SessionStream,
+};
+use prost_types::Timestamp;
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::process::Command;
+use tokio::sync::{mpsc, Mutex};
+use tracing::{debug, info, warn};
+
+/// Maximum concurrent sessions per connection
+const MAX_SESSIONS: usize = 8;
+
+/// Bytes read from the process per SessionOutput message
+const OUTPUT_CHUNK_SIZE: usize = 16 * 1024;
+
+/// How long to wait for remaining output after the process exits
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Input queued for the process.
+enum InputEvent {
+    Data(Vec<u8>),
+    Eof,
+}
+
+/// A running session.
+struct SessionHandle {
+    /// Process ID (also the process group, the child calls setsid)
+    pid: u32,
+    input: mpsc::Sender<InputEvent>,
+    /// PTY master, used for resizing
+    #[cfg(unix)]
+    pty: Option<std::fs::File>,
+}
+
+/// Sessions of one host connection.
+pub struct SessionManager {
+    sessions: Arc<Mutex<HashMap<String, SessionHandle>>>,
+    /// Unsolicited messages to the host
+    events: mpsc::Sender<AgentMessage>,
+}
+
+impl SessionManager {
+    pub fn new(events: mpsc::Sender<AgentMessage>) -> Self {
+        Self {
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+            events,
+        }
+    }
+
+    /// Start a session process.
+    pub async fn open(&self, req: OpenSessionRequest, config: &AgentConfig) -> agent_message::Payload {
+        let session_id = req.session_id.clone();
+        let fail = |error: String| {
+            agent_message::Payload::OpenSessionResponse(OpenSessionResponse {
+                success: false,
+                session_id: session_id.clone(),
+                pid: 0,
+                error,
+            })
+        };
+
+        if session_id.is_empty() {
+            return fail("Session ID is required".to_string());
+        }
+
+        let mut sessions = self.sessions.lock().await;
+        if sessions.contains_key(&session_id) {
+            return fail(format!("Session {} already exists", session_id));
+        }
+        if sessions.len() >= MAX_SESSIONS {
+            return fail(format!("Too many sessions (maximum {})", MAX_SESSIONS));
+        }
+
+        let program = if req.command.is_empty() { login_shell(&req.run_as_user) } else { req.command.clone() };
+        if !config.is_command_allowed(&program) {
+            warn!(session_id = %session_id, command = %program, "Session command blocked by security policy");
+            return fail("Command not allowed by security policy".to_string());
+        }
+
+        let spawned = if req.pty {
+            spawn_pty(&req, self.events.clone())
+        } else {
+            spawn_piped(&req, self.events.clone())
+        };
+
+        match spawned {
+            Ok((handle, child, readers, input_rx, stdin)) => {
+                let pid = handle.pid;
+                info!(
+                    session_id = %session_id,
+                    pid = pid,
+                    command = %program,
+                    user = %if req.run_as_user.is_empty() { "root" } else { req.run_as_user.as_str() },
+                    pty = req.pty,
+                    "Session started"
+                );
+
+                tokio::spawn(write_input(stdin, input_rx));
+                tokio::spawn(wait_session(
+                    session_id.clone(),
+                    child,
+                    readers,
+                    self.sessions.clone(),
+                    self.events.clone(),
+                ));
+                sessions.insert(session_id.clone(), handle);
+
+                agent_message::Payload::OpenSessionResponse(OpenSessionResponse {
+                    success: true,
+                    session_id,
+                    pid,
+                    error: String::new(),
+                })
+            }
+            Err(e) => {
+                warn!(session_id = %session_id, error = %e, "Failed to start session");
+                fail(e)
+            }
+        }
+    }
+
+    /// Queue input for a session.
+    pub async fn input(&self, req: SessionInput) {
+        let input = match self.sessions.lock().await.get(&req.session_id) {
+            Some(s) => s.input.clone(),
+            None => {
+                debug!(session_id = %req.session_id, "Input for unknown session");
+                return;
+            }
+        };
+
+        if !req.data.is_empty() {
+            let _ = input.send(InputEvent::Data(req.data)).await;
+        }
+        if req.eof {
+            let _ = input.send(InputEvent::Eof).await;
+        }
+    }
+
+    /// Change the terminal size of a PTY session.
+    pub async fn resize(&self, req: SessionResize) {
+        #[cfg(unix)]
+        if let Some(Some(pty)) = self.sessions.lock().await.get(&req.session_id).map(|s| s.pty.as_ref()) {
+            if let Err(e) = set_window_size(pty, req.cols, req.rows) {
+                debug!(session_id = %req.session_id, error = %e, "Failed to resize terminal");
+            }
+        }
+
+        #[cfg(not(unix))]
+        let _ = req;
+    }
+
+    /// Signal a session to end. SessionExited follows when it has.
+    pub async fn close(&self, req: CloseSessionRequest) {
+        if let Some(s) = self.sessions.lock().await.get(&req.session_id) {
+            info!(session_id = %req.session_id, force = req.force, "Closing session");
+            signal_session(s.pid, req.force);
+        }
+    }
+
+    /// Kill all sessions (connection to the host lost).
+    pub async fn close_all(&self) {
+        let sessions = self.sessions.lock().await;
+        for (id, s) in sessions.iter() {
+            info!(session_id = %id, "Killing session after disconnect");
+            signal_session(s.pid, true);
+        }
+    }
+}
+
+type Spawned = (
+    SessionHandle,
+    tokio::process::Child,
+    Vec<tokio::task::JoinHandle<()>>,
+    mpsc::Receiver<InputEvent>,
+    Box<dyn AsyncWrite + Send + Unpin>,
+);
+
+/// Build the command for a session (without stdio).
+fn build_command(req: &OpenSessionRequest) -> Result<Command, String> {
+    #[cfg(unix)]
+    let account = if req.run_as_user.is_empty() {
+        passwd_entry("root").ok()
+    } else {
+        Some(passwd_entry(&req.run_as_user)?)
+    };
+
+    let mut cmd = if req.command.is_empty() {
+        // Login shell: argv[0] starting with '-' is the login convention
+        let shell = login_shell(&req.run_as_user);
+        #[cfg_attr(not(unix), allow(unused_mut))]
+        let mut c = Command::new(&shell);
+        #[cfg(unix)]
+        c.arg0(format!("-{}", shell.rsplit('/').next().unwrap_or("sh")));
+        c
+    } else if req.args.is_empty() {
+        #[cfg(unix)]
+        {
+            let mut c = Command::new("sh");
+            c.arg("-c").arg(&req.command);
+            c
+        }
+        #[cfg(windows)]
+        {
+            let mut c = Command::new("cmd");
+            c.arg("/C").arg(&req.command);
+            c
+        }
+    } else {
+        let mut c = Command::new(&req.command);
+        c.args(&req.args);
+        c
+    };
+
+    #[cfg(unix)]
+    if let Some(ref a) = account {
+        cmd.env("HOME", &a.home)
+            .env("USER", &a.name)
+            .env("LOGNAME", &a.name)
+            .env("SHELL", &a.shell);
+        if req.working_directory.is_empty() && std::path::Path::new(&a.home).is_dir() {
+            cmd.current_dir(&a.home);
+        }
+    }
+
+    if !req.working_directory.is_empty() {
+        cmd.current_dir(&req.working_directory);
+    }
+    for (key, value) in &req.environment {
+        cmd.env(key, value);
+    }
+    cmd.kill_on_drop(true);
+
+    #[cfg(unix)]
+    {
+        let creds = if req.run_as_user.is_empty() {
+            None
+        } else {
+            Some(super::execute::get_user_credentials(&req.run_as_user, "", true)?)
+        };
+        let pty = req.pty;
+
+        unsafe {
+            cmd.pre_exec(move || {
+                // New session so the whole job can be signalled and the PTY
+                // becomes the controlling terminal
+                if libc::setsid() < 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                if pty && libc::ioctl(0, libc::TIOCSCTTY as _, 0) != 0 {
+                    return Err(std::io::Error::last_os_error());
+                }
+                if let Some(ref creds) = creds {
+                    if !creds.supplementary_gids.is_empty() {
+                        let gids: Vec<libc::gid_t> = creds.supplementary_gids.iter().map(|&g| g as libc::gid_t).collect();
+                        if libc::setgroups(gids.len() as _, gids.as_ptr()) != 0 {
+                            return Err(std::io::Error::last_os_error());
+                        }
+                    }
+                    if libc::setgid(creds.gid) != 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    if libc::setuid(creds.uid) != 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                }
+                Ok(())
+            });
+        }
+    }
+
+    Ok(cmd)
+}
+
+/// Login shell of `username` (root when empty).
+fn login_shell(username: &str) -> String {
+    #[cfg(unix)]
+    {
+        passwd_entry(if username.is_empty() { "root" } else { username })
+            .map(|a| a.shell)
+            .ok()
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| "/bin/sh".to_string())
+    }
+    #[cfg(windows)]
+    {
+        let _ = username;
+        "cmd.exe".to_string()
+    }
+}
+
+/// Start a session on a new pseudo-terminal.
+#[cfg(unix)]
+fn spawn_pty(req: &OpenSessionRequest, events: mpsc::Sender<AgentMessage>) -> Result<Spawned, String> {
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+    let mut master: libc::c_int = -1;
+    let mut slave: libc::c_int = -1;
+    let size = window_size(req.cols, req.rows);
+    // SAFETY: openpty writes two new descriptors into master/slave
+    let rc = unsafe { libc::openpty(&mut master, &mut slave, std::ptr::null_mut(), std::ptr::null(), &size) };
+    if rc != 0 {
+        return Err(format!("Failed to allocate PTY: {}", std::io::Error::last_os_error()));
+    }
+    // SAFETY: both descriptors are owned by us from here on
+    let (master, slave) = unsafe { (OwnedFd::from_raw_fd(master), OwnedFd::from_raw_fd(slave)) };
+    // openpty does not set O_CLOEXEC; without it sessions spawned in parallel
+    // inherit each other's terminals (the child gets the slave via dup2)
+    for fd in [master.as_raw_fd(), slave.as_raw_fd()] {
+        // SAFETY: fd is a valid descriptor owned above
+        if unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) } != 0 {
+            return Err(format!("Failed to set FD_CLOEXEC on PTY: {}", std::io::Error::last_os_error()));
+        }
+    }
+    let master = std::fs::File::from(master);
+    let slave = std::fs::File::from(slave);
+
+    let mut cmd = build_command(req)?;
+    let term = if req.term.is_empty() { "xterm-256color" } else { req.term.as_str() };
+    cmd.env("TERM", term);
+
+    let stdio = |f: &std::fs::File| f.try_clone().map(Stdio::from).map_err(|e| e.to_string());
+    cmd.stdin(stdio(&slave)?).stdout(stdio(&slave)?).stderr(stdio(&slave)?);
+
+    let child = cmd.spawn().map_err(|e| format!("Failed to spawn: {}", e))?;
+    // Only the child keeps the slave open, so reads end with EIO once it exits
+    drop(slave);
+
+    let reader = master.try_clone().map_err(|e| e.to_string())?;
+    let writer = master.try_clone().map_err(|e| e.to_string())?;
+    let session_id = req.session_id.clone();
+    let output = tokio::task::spawn_blocking(move || read_pty(reader, session_id, events));
+
+    let (input_tx, input_rx) = mpsc::channel(64);
+    Ok((
+        SessionHandle {
+            pid: child.id().unwrap_or_default(),
+            input: input_tx,
+            pty: Some(master),
+        },
+        child,
+        vec![output],
+        input_rx,
+        Box::new(tokio::fs::File::from_std(writer)),
+    ))
+}
+
+#[cfg(not(unix))]
+fn spawn_pty(_req: &OpenSessionRequest, _events: mpsc::Sender<AgentMessage>) -> Result<Spawned, String> {
+    Err("PTY sessions are not supported on this platform".to_string())
+}
+
+/// Start a session with piped stdin/stdout/stderr.
+fn spawn_piped(req: &OpenSessionRequest, events: mpsc::Sender<AgentMessage>) -> Result<Spawned, String> {
+    let mut cmd = build_command(req)?;
+    cmd.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped());
+
+    let mut child = cmd.spawn().map_err(|e| format!("Failed to spawn: {}", e))?;
+    let stdin = child.stdin.take().ok_or("Failed to open stdin")?;
+    let stdout = child.stdout.take().ok_or("Failed to open stdout")?;
+    let stderr = child.stderr.take().ok_or("Failed to open stderr")?;
+
+    let readers = vec![
+        tokio::spawn(forward_output(stdout, req.session_id.clone(), SessionStream::Stdout, events.clone())),
+        tokio::spawn(forward_output(stderr, req.session_id.clone(), SessionStream::Stderr, events)),
+    ];
+
+    let (input_tx, input_rx) = mpsc::channel(64);
+    Ok((
+        SessionHandle {
+            pid: child.id().unwrap_or_default(),
+            input: input_tx,
+            #[cfg(unix)]
+            pty: None,
+        },
+        child,
+        readers,
+        input_rx,
+        Box::new(stdin),
+    ))
+}
+
+/// Write queued input to the process until EOF or the session ends.
+async fn write_input(mut stdin: Box<dyn AsyncWrite + Send + Unpin>, mut input: mpsc::Receiver<InputEvent>) {
+    while let Some(event) = input.recv().await {
+        match event {
+            InputEvent::Data(data) => {
+                if stdin.write_all(&data).await.is_err() || stdin.flush().await.is_err() {
+                    break;
+                }
+            }
+            InputEvent::Eof => break,
+        }
+    }
+    let _ = stdin.shutdown().await;
+}
+
+/// Read a PTY master until the terminal closes (blocking, runs on the blocking pool).
+#[cfg(unix)]
+fn read_pty(mut master: std::fs::File, session_id: String, events: mpsc::Sender<AgentMessage>) {
+    use std::io::Read;
+
+    let mut buf = vec![0u8; OUTPUT_CHUNK_SIZE];
+    loop {
+        match master.read(&mut buf) {
+            // EIO means the slave side is closed
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                let msg = output_message(&session_id, SessionStream::Pty, buf[..n].to_vec());
+                if events.blocking_send(msg).is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Forward a pipe to the host until EOF.
+async fn forward_output<R: AsyncRead + Unpin>(
+    mut reader: R,
+    session_id: String,
+    stream: SessionStream,
+    events: mpsc::Sender<AgentMessage>,
+) {
+    let mut buf = vec![0u8; OUTPUT_CHUNK_SIZE];
+    loop {
+        match reader.read(&mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                if events.send(output_message(&session_id, stream, buf[..n].to_vec())).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Wait for the process, flush its output and report the exit.
+async fn wait_session(
+    session_id: String,
+    mut child: tokio::process::Child,
+    readers: Vec<tokio::task::JoinHandle<()>>,
+    sessions: Arc<Mutex<HashMap<String, SessionHandle>>>,
+    events: mpsc::Sender<AgentMessage>,
+) {
+    let status = child.wait().await;
+
+    // Background jobs can keep the terminal open; don't wait for them forever
+    for reader in readers {
+        if tokio::time::timeout(OUTPUT_DRAIN_TIMEOUT, reader).await.is_err() {
+            debug!(session_id = %session_id, "Output still open after exit, detaching");
+        }
+    }
+
+    sessions.lock().await.remove(&session_id);
+
+    let (exit_code, signal, error) = match status {
+        Ok(status) => {
+            #[cfg(unix)]
+            let signal = std::os::unix::process::ExitStatusExt::signal(&status).unwrap_or(0);
+            #[cfg(not(unix))]
+            let signal = 0;
+            (status.code().unwrap_or(-1), signal, String::new())
+        }
+        Err(e) => (-1, 0, format!("Failed to wait for process: {}", e)),
+    };
+
+    info!(session_id = %session_id, exit_code = exit_code, signal = signal, "Session ended");
+
+    let msg = envelope(agent_message::Payload::SessionExited(SessionExited {
+        session_id,
+        exit_code,
+        signal,
+        error,
+    }));
+    let _ = events.send(msg).await;
+}
+
+/// Send SIGHUP (or SIGKILL) to the session's process group.
+fn signal_session(pid: u32, force: bool) {
+    #[cfg(unix)]
+    {
+        if pid == 0 {
+            return;
+        }
+        let sig = if force { libc::SIGKILL } else { libc::SIGHUP };
+        // SAFETY: plain kill(2) on the process group created by setsid
+        unsafe {
+            libc::kill(-(pid as libc::pid_t), sig);
+        }
+    }
+
+    #[cfg(windows)]
+    {
+        let _ = force;
+        let _ = std::process::Command::new("taskkill")
+            .args(["/T", "/F", "/PID", &pid.to_string()])
+            .output();
+    }
+}
+
+fn output_message(session_id: &str, stream: SessionStream, data: Vec<u8>) -> AgentMessage {
+    envelope(agent_message::Payload::SessionOutput(SessionOutput {
+        session_id: session_id.to_string(),
+        stream: stream as i32,
+        data,
+    }))
+}
+
+/// Wrap an unsolicited payload in an AgentMessage.
+pub(super) fn envelope(payload: agent_message::Payload) -> AgentMessage {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+
+    AgentMessage {
+        message_id: uuid::Uuid::new_v4().to_string(),
+        timestamp: Some(Timestamp {
+            seconds: now.as_secs() as i64,
+            nanos: now.subsec_nanos() as i32,
+        }),
+        payload: Some(payload),
+    }
+}
+
+#[cfg(unix)]
+fn window_size(cols: u32, rows: u32) -> libc::winsize {
+    libc::winsize {
+        ws_row: if rows == 0 { 24 } else { rows.min(u16::MAX as u32) as u16 },
+        ws_col: if cols == 0 { 80 } else { cols.min(u16::MAX as u32) as u16 },
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    }
+}
+
+#[cfg(unix)]
+fn set_window_size(pty: &std::fs::File, cols: u32, rows: u32) -> std::io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let size = window_size(cols, rows);
+    // SAFETY: TIOCSWINSZ reads a winsize from the pointer; the kernel sends
+    // SIGWINCH to the foreground process group
+    if unsafe { libc::ioctl(pty.as_raw_fd(), libc::TIOCSWINSZ as _, &size) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Account details needed to start a login shell.
+#[cfg(unix)]
+struct PasswdEntry {
+    name: String,
+    home: String,
+    shell: String,
+}
+
+#[cfg(unix)]
+fn passwd_entry(username: &str) -> Result<PasswdEntry, String> {
+    use std::ffi::{CStr, CString};
+
+    let c_username = CString::new(username).map_err(|_| "Invalid username".to_string())?;
+    // SAFETY: getpwnam returns a pointer to static storage; fields are copied
+    // out before any other passwd call
+    unsafe {
+        let pwd = libc::getpwnam(c_username.as_ptr());
+        if pwd.is_null() {
+            return Err(format!("User not found: {}", username));
+        }
+        let field = |p: *const libc::c_char| {
+            if p.is_null() {
+                String::new()
+            } else {
+                CStr::from_ptr(p).to_string_lossy().into_owned()
+            }
+        };
+        Ok(PasswdEntry {
+            name: field((*pwd).pw_name),
+            home: field((*pwd).pw_dir),
+            shell: field((*pwd).pw_shell),
+        })
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn request(command: &str, pty: bool) -> OpenSessionRequest {
+        OpenSessionRequest {
+            session_id: "test-session".to_string(),
+            command: command.to_string(),
+            pty,
+            ..Default::default()
+        }
+    }
+
+    /// Collect output until the session exits.
+    async fn run(manager: &SessionManager, rx: &mut mpsc::Receiver<AgentMessage>) -> (Vec<u8>, SessionExited) {
+        let mut output = Vec::new();
+        loop {
+            let msg = tokio::time::timeout(Duration::from_secs(10), rx.recv()).await
+                .expect("session timed out")
+                .expect("event channel closed");
+            match msg.payload {
+                Some(agent_message::Payload::SessionOutput(o)) => output.extend(o.data),
+                Some(agent_message::Payload::SessionExited(e)) => {
+                    assert!(manager.sessions.lock().await.is_empty());
+                    return (output, e);
+                }
+                other => panic!("unexpected message: {:?}", other),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_piped_session() {
+        let (tx, mut rx) = mpsc::channel(16);
+        let manager = SessionManager::new(tx);
+
+        let response = manager.open(request("cat; exit 3", false), &AgentConfig::default()).await;
+        match response {
+            agent_message::Payload::OpenSessionResponse(r) => assert!(r.success, "{}", r.error),
+            _ => panic!("unexpected response"),
+        }
+
+        manager.input(SessionInput {
+            session_id: "test-session".to_string(),
+            data: b"hello".to_vec(),
+            eof: true,
+        }).await;
+
+        let (output, exited) = run(&manager, &mut rx).await;
+        assert_eq!(output, b"hello");
+        assert_eq!(exited.exit_code, 3);
+    }
+
+    #[tokio::test]
+    async fn test_pty_session() {
+        let (tx, mut rx) = mpsc::channel(16);
+        let manager = SessionManager::new(tx);
+
+        let response = manager.open(request("test -t 0 && echo tty", true), &AgentConfig::default()).await;
+        match response {
+            agent_message::Payload::OpenSessionResponse(r) => assert!(r.success, "{}", r.error),
+            _ => panic!("unexpected response"),
+        }
+
+        let (output, exited) = run(&manager, &mut rx).await;
+        assert_eq!(String::from_utf8_lossy(&output).trim(), "tty");
+        assert_eq!(exited.exit_code, 0);
+    }
+}
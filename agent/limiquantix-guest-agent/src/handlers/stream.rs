@@ -0,0 +1,371 @@
+//! Multiplexed stream handler (TCP port forwarding).
+//!
+//! Handles OpenStreamRequest and the frames of an open stream (data, window
+//! updates, close). Each stream is a TCP connection from the guest to a local
+//! service, carried over the agent channel alongside all other messages.
+//!
+//! Flow control is credit based: data read from the socket is only sent to the
+//! host while the host has granted window, and the host is granted more window
+//! once its data has been written to the socket. All streams of a connection
+//! are closed when the connection to the host is lost.
+
+use super::session::envelope;
+use crate::AgentConfig;
+use limiquantix_proto::agent::{
+    agent_message, AgentMessage, OpenStreamRequest, OpenStreamResponse, StreamClose, StreamData,
+    StreamWindowUpdate,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, Mutex, Semaphore};
+use tracing::{debug, info, warn};
+
+/// Maximum concurrent streams per connection
+const MAX_STREAMS: usize = 64;
+
+/// Window granted to the host for each stream
+const STREAM_WINDOW: u32 = 256 * 1024;
+
+/// Largest window accepted from the host
+const MAX_PEER_WINDOW: u32 = 16 * 1024 * 1024;
+
+/// Bytes read from the socket per StreamData message
+const DATA_CHUNK_SIZE: usize = 16 * 1024;
+
+/// How long to wait for the target to accept the connection
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Data from the host, queued for the socket.
+enum StreamInput {
+    Data(Vec<u8>),
+    Eof,
+}
+
+/// An open stream.
+struct StreamHandle {
+    /// Unbounded: the host never sends more than the granted window
+    input: mpsc::UnboundedSender<StreamInput>,
+    /// Bytes the guest may still send to the host
+    credit: Arc<Semaphore>,
+    task: tokio::task::AbortHandle,
+}
+
+/// Streams of one host connection.
+pub struct StreamManager {
+    streams: Arc<Mutex<HashMap<String, StreamHandle>>>,
+    /// Unsolicited messages to the host
+    events: mpsc::Sender<AgentMessage>,
+}
+
+impl StreamManager {
+    pub fn new(events: mpsc::Sender<AgentMessage>) -> Self {
+        Self {
+            streams: Arc::new(Mutex::new(HashMap::new())),
+            events,
+        }
+    }
+
+    /// Connect to the target and start pumping data.
+    pub async fn open(&self, req: OpenStreamRequest, config: &AgentConfig) -> agent_message::Payload {
+        let stream_id = req.stream_id.clone();
+        let fail = |error: String| {
+            agent_message::Payload::OpenStreamResponse(OpenStreamResponse {
+                success: false,
+                stream_id: stream_id.clone(),
+                error,
+                initial_window: 0,
+            })
+        };
+
+        if stream_id.is_empty() {
+            return fail("Stream ID is required".to_string());
+        }
+        let port = match u16::try_from(req.target_port) {
+            Ok(port) if port != 0 => port,
+            _ => return fail(format!("Invalid target port: {}", req.target_port)),
+        };
+        if !config.is_forward_port_allowed(port) {
+            warn!(stream_id = %stream_id, port = port, "Port forward blocked by security policy");
+            return fail("Port not allowed by security policy".to_string());
+        }
+        // Streams reach services of this guest only, never other hosts
+        let host = if req.target_host.is_empty() { "127.0.0.1" } else { req.target_host.as_str() };
+        if host != "localhost" && !host.parse::<std::net::IpAddr>().is_ok_and(|ip| ip.is_loopback()) {
+            warn!(stream_id = %stream_id, host = %host, "Port forward to a non-local host blocked");
+            return fail(format!("Target host must be a loopback address, got {}", host));
+        }
+
+        {
+            let streams = self.streams.lock().await;
+            if streams.contains_key(&stream_id) {
+                return fail(format!("Stream {} already exists", stream_id));
+            }
+            if streams.len() >= MAX_STREAMS {
+                return fail(format!("Too many streams (maximum {})", MAX_STREAMS));
+            }
+        }
+
+        let socket = match tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect((host, port))).await {
+            Ok(Ok(socket)) => socket,
+            Ok(Err(e)) => return fail(format!("Failed to connect to {}:{}: {}", host, port, e)),
+            Err(_) => return fail(format!("Timed out connecting to {}:{}", host, port)),
+        };
+        let _ = socket.set_nodelay(true);
+
+        let peer_window = match req.initial_window {
+            0 => STREAM_WINDOW,
+            n => n.min(MAX_PEER_WINDOW),
+        };
+        let credit = Arc::new(Semaphore::new(peer_window as usize));
+        let (input_tx, input_rx) = mpsc::unbounded_channel();
+
+        let mut streams = self.streams.lock().await;
+        if streams.contains_key(&stream_id) {
+            return fail(format!("Stream {} already exists", stream_id));
+        }
+
+        let task = tokio::spawn(run_stream(
+            stream_id.clone(),
+            socket,
+            input_rx,
+            credit.clone(),
+            peer_window,
+            self.streams.clone(),
+            self.events.clone(),
+        ));
+        streams.insert(
+            stream_id.clone(),
+            StreamHandle {
+                input: input_tx,
+                credit,
+                task: task.abort_handle(),
+            },
+        );
+
+        info!(stream_id = %stream_id, target = %format!("{}:{}", host, port), "Stream opened");
+
+        agent_message::Payload::OpenStreamResponse(OpenStreamResponse {
+            success: true,
+            stream_id,
+            error: String::new(),
+            initial_window: STREAM_WINDOW,
+        })
+    }
+
+    /// Queue data (or the end of data) from the host for the socket.
+    pub async fn data(&self, frame: StreamData) {
+        let streams = self.streams.lock().await;
+        let Some(stream) = streams.get(&frame.stream_id) else {
+            debug!(stream_id = %frame.stream_id, "Data for unknown stream");
+            return;
+        };
+
+        if !frame.data.is_empty() {
+            let _ = stream.input.send(StreamInput::Data(frame.data));
+        }
+        if frame.eof {
+            let _ = stream.input.send(StreamInput::Eof);
+        }
+    }
+
+    /// Add send credit granted by the host.
+    pub async fn window(&self, update: StreamWindowUpdate) {
+        if let Some(stream) = self.streams.lock().await.get(&update.stream_id) {
+            stream.credit.add_permits(update.increment as usize);
+        }
+    }
+
+    /// Tear down a stream closed by the host.
+    pub async fn close(&self, frame: StreamClose) {
+        if let Some(stream) = self.streams.lock().await.remove(&frame.stream_id) {
+            if frame.error.is_empty() {
+                debug!(stream_id = %frame.stream_id, "Stream closed by host");
+            } else {
+                debug!(stream_id = %frame.stream_id, error = %frame.error, "Stream reset by host");
+            }
+            stream.task.abort();
+        }
+    }
+
+    /// Close all streams (connection to the host lost).
+    pub async fn close_all(&self) {
+        let mut streams = self.streams.lock().await;
+        for (id, stream) in streams.drain() {
+            debug!(stream_id = %id, "Closing stream after disconnect");
+            stream.task.abort();
+        }
+    }
+}
+
+/// Pump data between the socket and the host until both directions have
+/// ended, or send StreamClose if either fails.
+async fn run_stream(
+    stream_id: String,
+    socket: TcpStream,
+    mut input: mpsc::UnboundedReceiver<StreamInput>,
+    credit: Arc<Semaphore>,
+    peer_window: u32,
+    streams: Arc<Mutex<HashMap<String, StreamHandle>>>,
+    events: mpsc::Sender<AgentMessage>,
+) {
+    let (mut rd, mut wr) = socket.into_split();
+
+    // Socket -> host, limited by the credit the host has granted
+    let upstream = async {
+        let mut buf = vec![0u8; DATA_CHUNK_SIZE.min(peer_window as usize)];
+        loop {
+            let n = rd.read(&mut buf).await.map_err(|e| format!("Read failed: {}", e))?;
+            let eof = n == 0;
+            if !eof {
+                credit
+                    .acquire_many(n as u32)
+                    .await
+                    .map_err(|_| "Stream closed".to_string())?
+                    .forget();
+            }
+
+            let msg = envelope(agent_message::Payload::StreamData(StreamData {
+                stream_id: stream_id.clone(),
+                data: buf[..n].to_vec(),
+                eof,
+            }));
+            events.send(msg).await.map_err(|_| "Host connection closed".to_string())?;
+            if eof {
+                return Ok::<(), String>(());
+            }
+        }
+    };
+
+    // Host -> socket, granting the host more window as data is written
+    let downstream = async {
+        while let Some(item) = input.recv().await {
+            match item {
+                StreamInput::Data(data) => {
+                    wr.write_all(&data).await.map_err(|e| format!("Write failed: {}", e))?;
+                    let msg = envelope(agent_message::Payload::StreamWindow(StreamWindowUpdate {
+                        stream_id: stream_id.clone(),
+                        increment: data.len() as u32,
+                    }));
+                    events.send(msg).await.map_err(|_| "Host connection closed".to_string())?;
+                }
+                StreamInput::Eof => break,
+            }
+        }
+        let _ = wr.shutdown().await;
+        Ok::<(), String>(())
+    };
+
+    let result = tokio::try_join!(upstream, downstream);
+    streams.lock().await.remove(&stream_id);
+
+    match result {
+        Ok(_) => debug!(stream_id = %stream_id, "Stream finished"),
+        Err(error) => {
+            debug!(stream_id = %stream_id, error = %error, "Stream failed");
+            let msg = envelope(agent_message::Payload::StreamClose(StreamClose {
+                stream_id: stream_id.clone(),
+                error,
+            }));
+            let _ = events.send(msg).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn test_stream_echo_with_window() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 1024];
+            loop {
+                let n = socket.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                socket.write_all(&buf[..n]).await.unwrap();
+            }
+        });
+
+        let (events_tx, mut events_rx) = mpsc::channel(64);
+        let manager = StreamManager::new(events_tx);
+        let config = AgentConfig::default();
+
+        let resp = manager
+            .open(
+                OpenStreamRequest {
+                    stream_id: "s1".to_string(),
+                    target_host: String::new(),
+                    target_port: port as u32,
+                    initial_window: 4,
+                },
+                &config,
+            )
+            .await;
+        match resp {
+            agent_message::Payload::OpenStreamResponse(r) => assert!(r.success, "{}", r.error),
+            _ => panic!("unexpected response"),
+        }
+
+        manager
+            .data(StreamData { stream_id: "s1".to_string(), data: b"hello".to_vec(), eof: false })
+            .await;
+
+        // Only the 4 bytes of granted window come back until more is granted
+        let mut echoed = Vec::new();
+        let mut granted = 0;
+        while echoed.len() < 5 {
+            let msg = tokio::time::timeout(Duration::from_secs(5), events_rx.recv()).await.unwrap().unwrap();
+            match msg.payload {
+                Some(agent_message::Payload::StreamData(d)) => {
+                    echoed.extend_from_slice(&d.data);
+                    assert!(echoed.len() <= 4 + granted);
+                    if echoed.len() == 4 {
+                        granted = 4;
+                        manager
+                            .window(StreamWindowUpdate { stream_id: "s1".to_string(), increment: 4 })
+                            .await;
+                    }
+                }
+                Some(agent_message::Payload::StreamWindow(w)) => assert_eq!(w.increment, 5),
+                other => panic!("unexpected message: {:?}", other),
+            }
+        }
+        assert_eq!(echoed, b"hello");
+
+        manager.close(StreamClose { stream_id: "s1".to_string(), error: String::new() }).await;
+        assert!(manager.streams.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stream_blocked_port() {
+        let (events_tx, _events_rx) = mpsc::channel(1);
+        let manager = StreamManager::new(events_tx);
+        let mut config = AgentConfig::default();
+        config.security.forward_port_allowlist = vec![22];
+
+        let resp = manager
+            .open(
+                OpenStreamRequest {
+                    stream_id: "s1".to_string(),
+                    target_host: String::new(),
+                    target_port: 5432,
+                    initial_window: 0,
+                },
+                &config,
+            )
+            .await;
+        match resp {
+            agent_message::Payload::OpenStreamResponse(r) => assert!(!r.success),
+            _ => panic!("unexpected response"),
+        }
+    }
+}
@@ -0,0 +1,458 @@
+//! Directory tree transfer handlers.
+//!
+//! Uploads and downloads of whole directory trees use the QTA1 archive
+//! stream from `limiquantix_common::tree_archive`, which carries per-file
+//! SHA256 checksums, permissions and timestamps.
+//!
+//! ## Resume
+//!
+//! Transfers are kept in a global table keyed by the host's transfer ID, so
+//! they survive a reconnect of the agent channel:
+//!
+//! - **Upload**: every response carries the acknowledged archive offset. A
+//!   chunk for any other offset is ignored and answered with that offset so
+//!   the host can seek and resend.
+//! - **Download**: the archive is generated deterministically, so any offset
+//!   can be served again, even after an agent restart, by rebuilding the
+//!   stream and skipping ahead. The last chunk is kept for cheap resends.
+//!
+//! Idle transfers are discarded after [`TRANSFER_IDLE_TIMEOUT`].
+
+use crate::AgentConfig;
+use limiquantix_common::tree_archive::{ArchiveReader, ArchiveWriter, TreeSummary};
+use limiquantix_proto::agent::{
+    agent_message, TreeDownloadRequest, TreeDownloadResponse, TreeTransferSummary,
+    TreeUploadRequest, TreeUploadResponse,
+};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// How long an untouched transfer is kept for a resume.
+const TRANSFER_IDLE_TIMEOUT: Duration = Duration::from_secs(600);
+
+struct UploadTransfer {
+    destination: String,
+    /// None once the archive has been fully extracted
+    reader: Option<ArchiveReader>,
+    /// Final archive length and summary once finished
+    finished: Option<(u64, TreeSummary)>,
+    last_active: Instant,
+}
+
+struct DownloadTransfer {
+    source: String,
+    writer: ArchiveWriter,
+    /// Offset and contents of the last chunk sent, for resends
+    last_chunk: Option<(u64, Vec<u8>)>,
+    last_active: Instant,
+}
+
+type TransferTable<T> = Lazy<Mutex<HashMap<String, Arc<Mutex<T>>>>>;
+
+static UPLOADS: TransferTable<UploadTransfer> = Lazy::new(|| Mutex::new(HashMap::new()));
+static DOWNLOADS: TransferTable<DownloadTransfer> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Handle a chunk of a directory upload from the host.
+pub async fn handle_tree_upload(
+    req: TreeUploadRequest,
+    config: &AgentConfig,
+) -> agent_message::Payload {
+    let transfer_id = req.transfer_id.clone();
+    let result = match check_path(&req.transfer_id, &req.destination) {
+        Err(e) => Err(e),
+        Ok(()) if !config.is_file_write_allowed(&req.destination) => {
+            Err("Access denied by security policy".to_string())
+        }
+        Ok(()) => tokio::task::spawn_blocking(move || upload_chunk(req))
+            .await
+            .unwrap_or_else(|e| Err(format!("Upload task failed: {}", e))),
+    };
+
+    let response = match result {
+        Ok((acknowledged_offset, summary)) => TreeUploadResponse {
+            success: true,
+            transfer_id,
+            acknowledged_offset,
+            error: String::new(),
+            summary: summary.map(to_proto_summary),
+        },
+        Err(error) => {
+            warn!(transfer_id = %transfer_id, error = %error, "Tree upload failed");
+            TreeUploadResponse {
+                success: false,
+                transfer_id,
+                acknowledged_offset: 0,
+                error,
+                summary: None,
+            }
+        }
+    };
+    agent_message::Payload::TreeUploadResponse(response)
+}
+
+/// Handle a request for the next chunk of a directory download.
+pub async fn handle_tree_download(
+    req: TreeDownloadRequest,
+    config: &AgentConfig,
+) -> agent_message::Payload {
+    let transfer_id = req.transfer_id.clone();
+    let max_chunk = config.max_chunk_size;
+    let result = match check_path(&req.transfer_id, &req.source) {
+        Err(e) => Err(e),
+        Ok(()) if !config.is_file_read_allowed(&req.source) => {
+            Err("Access denied by security policy".to_string())
+        }
+        Ok(()) => tokio::task::spawn_blocking(move || download_chunk(req, max_chunk))
+            .await
+            .unwrap_or_else(|e| Err(format!("Download task failed: {}", e))),
+    };
+
+    let response = match result {
+        Ok(response) => response,
+        Err(error) => {
+            warn!(transfer_id = %transfer_id, error = %error, "Tree download failed");
+            TreeDownloadResponse {
+                success: false,
+                transfer_id,
+                error,
+                ..Default::default()
+            }
+        }
+    };
+    agent_message::Payload::TreeDownloadResponse(response)
+}
+
+fn upload_chunk(req: TreeUploadRequest) -> Result<(u64, Option<TreeSummary>), String> {
+    if req.abort {
+        if UPLOADS.lock().unwrap().remove(&req.transfer_id).is_some() {
+            info!(transfer_id = %req.transfer_id, "Tree upload aborted");
+        }
+        return Ok((0, None));
+    }
+
+    let transfer = {
+        let mut uploads = UPLOADS.lock().unwrap();
+        prune(&mut uploads, |t| t.last_active);
+        match uploads.get(&req.transfer_id) {
+            Some(transfer) => transfer.clone(),
+            None => {
+                if req.offset != 0 {
+                    return Err(format!(
+                        "Unknown transfer {} (the agent may have restarted); start again from offset 0",
+                        req.transfer_id
+                    ));
+                }
+                let reader = ArchiveReader::new(Path::new(&req.destination), req.overwrite)
+                    .map_err(|e| format!("Failed to prepare {}: {}", req.destination, e))?;
+                info!(
+                    transfer_id = %req.transfer_id,
+                    destination = %req.destination,
+                    "Tree upload started"
+                );
+                let transfer = Arc::new(Mutex::new(UploadTransfer {
+                    destination: req.destination.clone(),
+                    reader: Some(reader),
+                    finished: None,
+                    last_active: Instant::now(),
+                }));
+                uploads.insert(req.transfer_id.clone(), transfer.clone());
+                transfer
+            }
+        }
+    };
+
+    let mut upload = transfer.lock().unwrap();
+    upload.last_active = Instant::now();
+    if upload.destination != req.destination {
+        return Err(format!(
+            "Transfer {} targets {}, not {}",
+            req.transfer_id, upload.destination, req.destination
+        ));
+    }
+
+    // Already finished: repeat the outcome for a host that missed it.
+    if let Some((length, summary)) = &upload.finished {
+        return Ok((*length, Some(summary.clone())));
+    }
+
+    let reader = upload.reader.as_mut().expect("unfinished upload has a reader");
+    let acknowledged = reader.offset();
+    if req.offset != acknowledged {
+        return Ok((acknowledged, None));
+    }
+
+    if let Err(e) = reader.write(&req.data) {
+        drop(upload);
+        UPLOADS.lock().unwrap().remove(&req.transfer_id);
+        return Err(format!("Failed to extract archive at offset {}: {}", acknowledged, e));
+    }
+    let acknowledged = reader.offset();
+
+    if !req.finish {
+        return Ok((acknowledged, None));
+    }
+
+    let reader = upload.reader.take().unwrap();
+    let outcome = reader.finish().map_err(|e| e.to_string()).and_then(|summary| {
+        if !req.archive_sha256.is_empty() && !req.archive_sha256.eq_ignore_ascii_case(&summary.archive_sha256) {
+            Err(format!(
+                "Archive checksum mismatch: expected {}, got {}",
+                req.archive_sha256, summary.archive_sha256
+            ))
+        } else {
+            Ok(summary)
+        }
+    });
+
+    match outcome {
+        Ok(summary) => {
+            info!(
+                transfer_id = %req.transfer_id,
+                files = summary.files,
+                bytes = summary.bytes,
+                "Tree upload complete"
+            );
+            upload.finished = Some((acknowledged, summary.clone()));
+            Ok((acknowledged, Some(summary)))
+        }
+        Err(e) => {
+            drop(upload);
+            UPLOADS.lock().unwrap().remove(&req.transfer_id);
+            Err(e)
+        }
+    }
+}
+
+fn download_chunk(req: TreeDownloadRequest, max_chunk: usize) -> Result<TreeDownloadResponse, String> {
+    if req.abort {
+        DOWNLOADS.lock().unwrap().remove(&req.transfer_id);
+        return Ok(TreeDownloadResponse {
+            success: true,
+            transfer_id: req.transfer_id,
+            ..Default::default()
+        });
+    }
+
+    let transfer = {
+        let mut downloads = DOWNLOADS.lock().unwrap();
+        prune(&mut downloads, |t| t.last_active);
+        match downloads.get(&req.transfer_id) {
+            Some(transfer) => transfer.clone(),
+            None => {
+                let writer = open_archive(&req.source)?;
+                info!(
+                    transfer_id = %req.transfer_id,
+                    source = %req.source,
+                    offset = req.offset,
+                    "Tree download started"
+                );
+                let transfer = Arc::new(Mutex::new(DownloadTransfer {
+                    source: req.source.clone(),
+                    writer,
+                    last_chunk: None,
+                    last_active: Instant::now(),
+                }));
+                downloads.insert(req.transfer_id.clone(), transfer.clone());
+                transfer
+            }
+        }
+    };
+
+    let mut download = transfer.lock().unwrap();
+    download.last_active = Instant::now();
+    if download.source != req.source {
+        return Err(format!(
+            "Transfer {} reads {}, not {}",
+            req.transfer_id, download.source, req.source
+        ));
+    }
+
+    let data = match &download.last_chunk {
+        Some((offset, data)) if *offset == req.offset => data.clone(),
+        _ => {
+            if req.offset < download.writer.offset() {
+                download.writer = open_archive(&req.source)?;
+            }
+            download
+                .writer
+                .skip_to(req.offset)
+                .map_err(|e| format!("Failed to seek archive: {}", e))?;
+
+            let size = match req.chunk_size as usize {
+                0 => max_chunk,
+                n => n.min(max_chunk),
+            };
+            let mut data = vec![0u8; size];
+            let mut filled = 0;
+            while filled < size {
+                match download.writer.read(&mut data[filled..]) {
+                    Ok(0) => break,
+                    Ok(n) => filled += n,
+                    Err(e) => return Err(format!("Failed to read {}: {}", req.source, e)),
+                }
+            }
+            data.truncate(filled);
+            download.last_chunk = Some((req.offset, data.clone()));
+            data
+        }
+    };
+
+    let end = req.offset + data.len() as u64;
+    let archive_sha256 = if end == download.writer.offset() {
+        download.writer.archive_sha256()
+    } else {
+        None
+    };
+    let summary = download.writer.summary();
+
+    Ok(TreeDownloadResponse {
+        success: true,
+        transfer_id: req.transfer_id,
+        offset: req.offset,
+        data,
+        eof: archive_sha256.is_some(),
+        archive_sha256: archive_sha256.unwrap_or_default(),
+        error: String::new(),
+        total_files: summary.files,
+        total_bytes: summary.bytes,
+    })
+}
+
+fn open_archive(source: &str) -> Result<ArchiveWriter, String> {
+    ArchiveWriter::new(Path::new(source)).map_err(|e| format!("Failed to read {}: {}", source, e))
+}
+
+fn check_path(transfer_id: &str, path: &str) -> Result<(), String> {
+    if transfer_id.is_empty() {
+        return Err("transfer_id is required".to_string());
+    }
+    if !is_path_safe(Path::new(path)) {
+        return Err("Invalid path: must be absolute without '..'".to_string());
+    }
+    Ok(())
+}
+
+/// Drop transfers that have been idle too long (skipping ones in use).
+fn prune<T>(table: &mut HashMap<String, Arc<Mutex<T>>>, last_active: impl Fn(&T) -> Instant) {
+    table.retain(|id, transfer| match transfer.try_lock() {
+        Ok(t) if last_active(&t).elapsed() > TRANSFER_IDLE_TIMEOUT => {
+            info!(transfer_id = %id, "Discarding idle transfer");
+            false
+        }
+        _ => true,
+    });
+}
+
+fn to_proto_summary(summary: TreeSummary) -> TreeTransferSummary {
+    TreeTransferSummary {
+        files: summary.files,
+        directories: summary.directories,
+        symlinks: summary.symlinks,
+        bytes: summary.bytes,
+        archive_sha256: summary.archive_sha256,
+    }
+}
+
+/// Check if a path is safe (absolute, no directory traversal).
+fn is_path_safe(path: &Path) -> bool {
+    path.is_absolute()
+        && !path
+            .components()
+            .any(|c| matches!(c, std::path::Component::ParentDir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_download_then_upload_with_resume() {
+        let src = tempfile::tempdir().unwrap();
+        std::fs::create_dir(src.path().join("conf")).unwrap();
+        std::fs::write(src.path().join("conf/app.ini"), b"[main]\nport=80\n").unwrap();
+        std::fs::write(src.path().join("blob"), vec![7u8; 10_000]).unwrap();
+        let source = src.path().to_str().unwrap().to_string();
+
+        // Download in small chunks, re-requesting one chunk as after a reconnect.
+        let mut archive = Vec::new();
+        let mut offset = 0u64;
+        let mut requests = 0;
+        let sha = loop {
+            let resp = download_chunk(
+                TreeDownloadRequest {
+                    transfer_id: "dl-test".into(),
+                    source: source.clone(),
+                    offset,
+                    chunk_size: 4096,
+                    abort: false,
+                },
+                65536,
+            )
+            .unwrap();
+            requests += 1;
+            if requests == 2 {
+                // Pretend the response was lost and ask again.
+                continue;
+            }
+            assert_eq!(resp.offset, offset);
+            offset += resp.data.len() as u64;
+            archive.extend_from_slice(&resp.data);
+            if resp.eof {
+                break resp.archive_sha256;
+            }
+        };
+        assert_eq!(sha.len(), 64);
+
+        let dest = tempfile::tempdir().unwrap();
+        let destination = dest.path().join("restored").to_str().unwrap().to_string();
+        let upload = |offset: u64, data: &[u8], finish: bool| {
+            upload_chunk(TreeUploadRequest {
+                transfer_id: "ul-test".into(),
+                destination: destination.clone(),
+                offset,
+                data: data.to_vec(),
+                finish,
+                archive_sha256: sha.clone(),
+                overwrite: false,
+                abort: false,
+            })
+        };
+
+        let (ack, _) = upload(0, &archive[..100], false).unwrap();
+        assert_eq!(ack, 100);
+        // A stale resend is not applied twice; the guest reports its position.
+        let (ack, _) = upload(0, &archive[..100], false).unwrap();
+        assert_eq!(ack, 100);
+        let (ack, summary) = upload(100, &archive[100..], true).unwrap();
+        assert_eq!(ack, archive.len() as u64);
+        let summary = summary.unwrap();
+        assert_eq!(summary.files, 2);
+        assert_eq!(summary.archive_sha256, sha);
+        assert_eq!(
+            std::fs::read(dest.path().join("restored/conf/app.ini")).unwrap(),
+            b"[main]\nport=80\n"
+        );
+    }
+
+    #[test]
+    fn test_upload_rejects_unknown_resume_and_bad_paths() {
+        let err = upload_chunk(TreeUploadRequest {
+            transfer_id: "missing".into(),
+            destination: "/tmp/nowhere".into(),
+            offset: 512,
+            ..Default::default()
+        })
+        .unwrap_err();
+        assert!(err.contains("Unknown transfer"));
+
+        assert!(check_path("id", "relative/path").is_err());
+        assert!(check_path("id", "/var/../etc").is_err());
+        assert!(check_path("", "/var/tmp").is_err());
+        assert!(check_path("id", "/var/tmp").is_ok());
+    }
+}
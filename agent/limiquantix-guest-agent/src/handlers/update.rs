@@ -1,22 +1,56 @@
 //! Agent self-update handlers.
 //!
-//! Handles agent binary updates with chunked transfer, checksum verification,
-//! and atomic replacement.
+//! Handles agent binary updates with chunked transfer, checksum and signature
+//! verification, and atomic replacement.
+//!
+//! Updates must carry a detached Ed25519 signature made with one of the keys
+//! embedded at build time (`QUANTIX_UPDATE_PUBLIC_KEYS`, hex, comma separated).
+//! Only a binary reporting a newer version than the running agent is
+//! installed. The update is received next to the binary it replaces.
+//! The previous binary is kept next to the new one, and a pending-update
+//! marker records the deadline by which the new binary must have answered the
+//! host. A new binary that keeps crashing or misses the deadline is replaced
+//! by the previous one and the agent exits so the service manager restarts it.
 
 use limiquantix_proto::agent::{
     agent_message, AgentUpdateRequest, AgentUpdateResponse, GetCapabilitiesRequest,
     GetCapabilitiesResponse, UpdateState,
 };
+use ring::signature::{UnparsedPublicKey, ED25519};
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
+use std::time::Duration;
 use tracing::{debug, error, info, warn};
 
+/// Ed25519 public keys trusted to sign agent updates (hex, comma separated).
+const TRUSTED_UPDATE_KEYS: Option<&str> = option_env!("QUANTIX_UPDATE_PUBLIC_KEYS");
+
+/// Time the new binary has to answer the host before it is rolled back
+const HEALTH_DEADLINE: Duration = Duration::from_secs(120);
+
+/// Starts of a new binary allowed before it is considered crash-looping
+const MAX_UPDATE_STARTS: u32 = 3;
+
+/// Timeout for running `--version` on a received binary
+const PREFLIGHT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Delay before exiting to restart into the new binary (lets the response out)
+const RESTART_DELAY: Duration = Duration::from_secs(2);
+
+/// Exit code used to have the service manager restart the agent. Non-zero so
+/// restart-on-failure policies apply as well.
+const RESTART_EXIT_CODE: i32 = 75;
+
 /// Global state for tracking update progress.
 static UPDATE_STATE: Mutex<Option<UpdateProgress>> = Mutex::new(None);
 
+/// Marker of an update that has not yet been confirmed healthy.
+static PENDING_MARKER: Mutex<Option<PathBuf>> = Mutex::new(None);
+
 /// Tracks the progress of an ongoing update.
 struct UpdateProgress {
     target_version: String,
@@ -26,6 +60,33 @@ struct UpdateProgress {
     temp_file: PathBuf,
     hasher: Sha256,
     chunks_received: u32,
+    signature: Vec<u8>,
+}
+
+/// An installed update awaiting confirmation, persisted next to the binary.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct PendingUpdate {
+    /// Version that was replaced
+    previous_version: String,
+    /// Version reported by the installed binary
+    new_version: String,
+    binary: PathBuf,
+    backup: PathBuf,
+    /// Unix time by which the new binary must have answered the host
+    deadline: i64,
+    /// Times the new binary has been started
+    #[serde(default)]
+    starts: u32,
+}
+
+/// What to do with a pending update when the agent starts.
+#[derive(Debug, PartialEq)]
+enum StartupAction {
+    /// Not our update (already rolled back or reinstalled); drop the marker
+    Clear,
+    /// Run, and roll back unless the host is answered before the deadline
+    Probation(PendingUpdate),
+    RollBack(PendingUpdate, String),
 }
 
 /// Handle an agent update request.
@@ -49,21 +110,23 @@ pub async fn handle_agent_update(req: AgentUpdateRequest) -> agent_message::Payl
 
 /// Initialize a new update.
 async fn initialize_update(req: AgentUpdateRequest) -> agent_message::Payload {
-    // Create temp file for the update
-    let temp_dir = get_temp_dir();
-    if let Err(e) = std::fs::create_dir_all(&temp_dir) {
-        error!(error = %e, "Failed to create temp directory");
-        return agent_message::Payload::AgentUpdateResponse(AgentUpdateResponse {
-            success: false,
-            current_version: env!("CARGO_PKG_VERSION").to_string(),
-            error: format!("Failed to create temp directory: {}", e),
-            restart_required: false,
-            progress_percent: 0,
-            state: UpdateState::Failed as i32,
-        });
-    }
-
-    let temp_file = temp_dir.join(format!("agent-update-{}.tmp", req.target_version));
+    // Stage the update next to the binary so it can be renamed over it
+    // (a rename from another filesystem such as /tmp fails)
+    let temp_file = match std::env::current_exe() {
+        Ok(exe) => staging_path(&exe),
+        Err(e) => {
+            error!(error = %e, "Failed to get executable path");
+            return agent_message::Payload::AgentUpdateResponse(AgentUpdateResponse {
+                success: false,
+                current_version: env!("CARGO_PKG_VERSION").to_string(),
+                error: format!("Failed to get executable path: {}", e),
+                restart_required: false,
+                progress_percent: 0,
+                state: UpdateState::Failed as i32,
+                new_version: String::new(),
+            });
+        }
+    };
 
     // Create/truncate the temp file
     let mut file = match std::fs::File::create(&temp_file) {
@@ -77,6 +140,7 @@ async fn initialize_update(req: AgentUpdateRequest) -> agent_message::Payload {
                 restart_required: false,
                 progress_percent: 0,
                 state: UpdateState::Failed as i32,
+                new_version: String::new(),
             });
         }
     };
@@ -91,6 +155,7 @@ async fn initialize_update(req: AgentUpdateRequest) -> agent_message::Payload {
             restart_required: false,
             progress_percent: 0,
             state: UpdateState::Failed as i32,
+            new_version: String::new(),
         });
     }
 
@@ -125,6 +190,7 @@ async fn initialize_update(req: AgentUpdateRequest) -> agent_message::Payload {
             temp_file: temp_file.clone(),
             hasher,
             chunks_received: 1,
+            signature: req.signature.clone(),
         });
     } // Lock released here
 
@@ -146,6 +212,7 @@ async fn initialize_update(req: AgentUpdateRequest) -> agent_message::Payload {
         restart_required: false,
         progress_percent: progress,
         state: UpdateState::Downloading as i32,
+        new_version: String::new(),
     })
 }
 
@@ -165,6 +232,7 @@ async fn append_update_chunk(req: AgentUpdateRequest) -> agent_message::Payload
                     restart_required: false,
                     progress_percent: 0,
                     state: UpdateState::Failed as i32,
+                    new_version: String::new(),
                 });
             }
         };
@@ -195,6 +263,7 @@ async fn append_update_chunk(req: AgentUpdateRequest) -> agent_message::Payload
                     restart_required: false,
                     progress_percent: 0,
                     state: UpdateState::Failed as i32,
+                    new_version: String::new(),
                 });
             }
         };
@@ -208,9 +277,15 @@ async fn append_update_chunk(req: AgentUpdateRequest) -> agent_message::Payload
                 restart_required: false,
                 progress_percent: 0,
                 state: UpdateState::Failed as i32,
+                new_version: String::new(),
             });
         }
 
+        // The signature may be sent with any chunk
+        if !req.signature.is_empty() {
+            state.signature = req.signature.clone();
+        }
+
         // Update hasher
         state.hasher.update(&req.binary_data);
         state.received_bytes += req.binary_data.len() as u64;
@@ -245,10 +320,25 @@ async fn append_update_chunk(req: AgentUpdateRequest) -> agent_message::Payload
         restart_required: false,
         progress_percent: progress,
         state: UpdateState::Downloading as i32,
+        new_version: String::new(),
     })
 }
 
-/// Finalize the update by verifying checksum and replacing the binary.
+/// Build a failed update response.
+fn update_failed(error: String, progress_percent: u32) -> agent_message::Payload {
+    agent_message::Payload::AgentUpdateResponse(AgentUpdateResponse {
+        success: false,
+        current_version: env!("CARGO_PKG_VERSION").to_string(),
+        error,
+        restart_required: false,
+        progress_percent,
+        state: UpdateState::Failed as i32,
+        new_version: String::new(),
+    })
+}
+
+/// Finalize the update: verify checksum and signature, check that the new
+/// binary runs, replace the current binary and restart into it.
 async fn finalize_update() -> agent_message::Payload {
     let state = {
         let mut guard = UPDATE_STATE.lock().unwrap();
@@ -259,14 +349,7 @@ async fn finalize_update() -> agent_message::Payload {
         Some(s) => s,
         None => {
             error!("No update to finalize");
-            return agent_message::Payload::AgentUpdateResponse(AgentUpdateResponse {
-                success: false,
-                current_version: env!("CARGO_PKG_VERSION").to_string(),
-                error: "No update to finalize".to_string(),
-                restart_required: false,
-                progress_percent: 0,
-                state: UpdateState::Failed as i32,
-            });
+            return update_failed("No update to finalize".to_string(), 0);
         }
     };
 
@@ -276,50 +359,34 @@ async fn finalize_update() -> agent_message::Payload {
         "Finalizing update"
     );
 
-    // Verify checksum
-    let computed_hash = hex::encode(state.hasher.finalize());
-    if !state.expected_checksum.is_empty() && computed_hash != state.expected_checksum.to_lowercase()
-    {
-        error!(
-            expected = %state.expected_checksum,
-            computed = %computed_hash,
-            "Checksum mismatch"
-        );
-
-        // Clean up temp file
+    let fail = |error: String| {
+        error!(error = %error, "Update rejected");
         let _ = std::fs::remove_file(&state.temp_file);
+        update_failed(error, 100)
+    };
 
-        return agent_message::Payload::AgentUpdateResponse(AgentUpdateResponse {
-            success: false,
-            current_version: env!("CARGO_PKG_VERSION").to_string(),
-            error: format!(
-                "Checksum mismatch: expected {}, got {}",
-                state.expected_checksum, computed_hash
-            ),
-            restart_required: false,
-            progress_percent: 100,
-            state: UpdateState::Failed as i32,
-        });
+    if state.total_size > 0 && state.received_bytes != state.total_size {
+        return fail(format!(
+            "Size mismatch: expected {} bytes, received {}",
+            state.total_size, state.received_bytes
+        ));
     }
 
-    info!(checksum = %computed_hash, "Checksum verified");
+    // Verify checksum
+    let computed_hash = hex::encode(state.hasher.clone().finalize());
+    if !state.expected_checksum.is_empty() && computed_hash != state.expected_checksum.to_lowercase()
+    {
+        return fail(format!(
+            "Checksum mismatch: expected {}, got {}",
+            state.expected_checksum, computed_hash
+        ));
+    }
 
-    // Get current binary path
-    let current_exe = match std::env::current_exe() {
-        Ok(p) => p,
-        Err(e) => {
-            error!(error = %e, "Failed to get current executable path");
-            let _ = std::fs::remove_file(&state.temp_file);
-            return agent_message::Payload::AgentUpdateResponse(AgentUpdateResponse {
-                success: false,
-                current_version: env!("CARGO_PKG_VERSION").to_string(),
-                error: format!("Failed to get executable path: {}", e),
-                restart_required: false,
-                progress_percent: 100,
-                state: UpdateState::Failed as i32,
-            });
-        }
-    };
+    // Verify signature
+    if let Err(e) = verify_signature(&state.temp_file, &state.signature, &trusted_keys()) {
+        return fail(e);
+    }
+    info!(checksum = %computed_hash, "Update signature verified");
 
     // Make temp file executable (Unix)
     #[cfg(unix)]
@@ -331,79 +398,322 @@ async fn finalize_update() -> agent_message::Payload {
         }
     }
 
-    // Atomic replacement strategy
-    let backup_path = current_exe.with_extension("bak");
+    // A binary that does not run (wrong architecture, truncated) would never
+    // get to roll itself back, so check it before installing
+    let new_version = match preflight(&state.temp_file).await {
+        Ok(version) => version,
+        Err(e) => return fail(e),
+    };
+    if !state.target_version.is_empty() && new_version != state.target_version {
+        return fail(format!(
+            "Binary reports version {}, expected {}",
+            new_version, state.target_version
+        ));
+    }
+    // The version is reported by the signed binary. Only newer ones are
+    // accepted so an older signed release can't be installed again
+    if !is_newer_version(&new_version, env!("CARGO_PKG_VERSION")) {
+        return fail(format!(
+            "Binary version {} is not newer than the running version {}",
+            new_version,
+            env!("CARGO_PKG_VERSION")
+        ));
+    }
+
+    // Get current binary path
+    let current_exe = match std::env::current_exe() {
+        Ok(p) => p,
+        Err(e) => return fail(format!("Failed to get executable path: {}", e)),
+    };
+
+    let deadline = chrono::Utc::now().timestamp() + HEALTH_DEADLINE.as_secs() as i64;
+    if let Err(e) = install_update(&state.temp_file, &current_exe, &new_version, deadline) {
+        return fail(e);
+    }
+
+    info!(
+        new_version = %new_version,
+        deadline_secs = HEALTH_DEADLINE.as_secs(),
+        "Update installed, restarting"
+    );
+
+    tokio::spawn(async {
+        tokio::time::sleep(RESTART_DELAY).await;
+        info!("Restarting to run the updated agent");
+        std::process::exit(RESTART_EXIT_CODE);
+    });
+
+    agent_message::Payload::AgentUpdateResponse(AgentUpdateResponse {
+        success: true,
+        current_version: env!("CARGO_PKG_VERSION").to_string(),
+        error: String::new(),
+        restart_required: true,
+        progress_percent: 100,
+        state: UpdateState::Complete as i32,
+        new_version,
+    })
+}
+
+/// Keys embedded at build time.
+fn trusted_keys() -> Vec<[u8; 32]> {
+    parse_keys(TRUSTED_UPDATE_KEYS.unwrap_or_default())
+}
+
+/// Parse comma separated hex Ed25519 public keys, skipping invalid ones.
+fn parse_keys(keys: &str) -> Vec<[u8; 32]> {
+    keys.split(',')
+        .map(str::trim)
+        .filter(|k| !k.is_empty())
+        .filter_map(|k| match hex::decode(k).map(<[u8; 32]>::try_from) {
+            Some(Ok(key)) => Some(key),
+            _ => {
+                warn!(key = %k, "Ignoring invalid update signing key");
+                None
+            }
+        })
+        .collect()
+}
+
+/// Verify the detached signature of the file at `path` against `keys`.
+fn verify_signature(path: &Path, signature: &[u8], keys: &[[u8; 32]]) -> Result<(), String> {
+    if keys.is_empty() {
+        return Err("This agent build has no update signing key; updates are disabled".to_string());
+    }
+    if signature.is_empty() {
+        return Err("Update is not signed".to_string());
+    }
+
+    let data = std::fs::read(path).map_err(|e| format!("Failed to read update: {}", e))?;
+    if keys.iter().any(|key| UnparsedPublicKey::new(&ED25519, key).verify(&data, signature).is_ok()) {
+        Ok(())
+    } else {
+        Err("Update signature is not valid for any trusted key".to_string())
+    }
+}
+
+/// Run `<binary> --version` and return the version it reports.
+async fn preflight(binary: &Path) -> Result<String, String> {
+    let run = tokio::process::Command::new(binary)
+        .arg("--version")
+        .kill_on_drop(true)
+        .output();
+    let output = match tokio::time::timeout(PREFLIGHT_TIMEOUT, run).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) => return Err(format!("New binary failed to start: {}", e)),
+        Err(_) => return Err("New binary did not answer --version".to_string()),
+    };
+    if !output.status.success() {
+        return Err(format!("New binary exited with {} on --version", output.status));
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .last()
+        .map(str::to_string)
+        .ok_or_else(|| "New binary reported no version".to_string())
+}
+
+/// Whether dotted version `candidate` is newer than `current`. Pre-release
+/// and build suffixes are ignored.
+fn is_newer_version(candidate: &str, current: &str) -> bool {
+    fn parse(version: &str) -> Vec<u64> {
+        version
+            .trim_start_matches('v')
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    }
+    let (mut candidate, mut current) = (parse(candidate), parse(current));
+    let len = candidate.len().max(current.len());
+    candidate.resize(len, 0);
+    current.resize(len, 0);
+    candidate > current
+}
+
+/// File an update of `binary` is received into, in the same directory.
+fn staging_path(binary: &Path) -> PathBuf {
+    binary.with_extension("update")
+}
+
+/// Marker file of a pending update of `binary`.
+fn marker_path(binary: &Path) -> PathBuf {
+    binary.with_extension("pending")
+}
+
+/// Replace `binary` with `new_binary`, keeping the current one as backup,
+/// and record the pending update.
+fn install_update(new_binary: &Path, binary: &Path, new_version: &str, deadline: i64) -> Result<(), String> {
+    let backup = binary.with_extension("bak");
 
     // On Windows, we can't replace a running executable directly
     // We need to rename the current one first
     #[cfg(windows)]
     {
-        // Rename current to backup
-        if let Err(e) = std::fs::rename(&current_exe, &backup_path) {
-            error!(error = %e, "Failed to backup current executable");
-            let _ = std::fs::remove_file(&state.temp_file);
-            return agent_message::Payload::AgentUpdateResponse(AgentUpdateResponse {
-                success: false,
-                current_version: env!("CARGO_PKG_VERSION").to_string(),
-                error: format!("Failed to backup executable: {}", e),
-                restart_required: false,
-                progress_percent: 100,
-                state: UpdateState::Failed as i32,
-            });
-        }
+        let _ = std::fs::remove_file(&backup);
+        std::fs::rename(binary, &backup).map_err(|e| format!("Failed to backup executable: {}", e))?;
 
-        // Move new binary to current location
-        if let Err(e) = std::fs::rename(&state.temp_file, &current_exe) {
-            error!(error = %e, "Failed to install new executable");
+        if let Err(e) = std::fs::rename(new_binary, binary) {
             // Try to restore backup
-            let _ = std::fs::rename(&backup_path, &current_exe);
-            return agent_message::Payload::AgentUpdateResponse(AgentUpdateResponse {
-                success: false,
-                current_version: env!("CARGO_PKG_VERSION").to_string(),
-                error: format!("Failed to install new executable: {}", e),
-                restart_required: false,
-                progress_percent: 100,
-                state: UpdateState::Failed as i32,
-            });
+            let _ = std::fs::rename(&backup, binary);
+            let _ = std::fs::remove_file(new_binary);
+            return Err(format!("Failed to install new executable: {}", e));
         }
     }
 
     // On Unix, we can use atomic rename
     #[cfg(unix)]
     {
-        // Backup current executable
-        if let Err(e) = std::fs::copy(&current_exe, &backup_path) {
-            warn!(error = %e, "Failed to create backup (continuing anyway)");
+        // The backup is what a failed update is rolled back to
+        if let Err(e) = std::fs::copy(binary, &backup) {
+            let _ = std::fs::remove_file(new_binary);
+            return Err(format!("Failed to backup executable: {}", e));
         }
 
-        // Atomic rename
-        if let Err(e) = std::fs::rename(&state.temp_file, &current_exe) {
-            error!(error = %e, "Failed to install new executable");
-            let _ = std::fs::remove_file(&state.temp_file);
-            return agent_message::Payload::AgentUpdateResponse(AgentUpdateResponse {
-                success: false,
-                current_version: env!("CARGO_PKG_VERSION").to_string(),
-                error: format!("Failed to install new executable: {}", e),
-                restart_required: false,
-                progress_percent: 100,
-                state: UpdateState::Failed as i32,
-            });
+        if let Err(e) = std::fs::rename(new_binary, binary) {
+            let _ = std::fs::remove_file(new_binary);
+            return Err(format!("Failed to install new executable: {}", e));
         }
     }
 
-    info!(
-        target_version = %state.target_version,
-        "Update installed successfully, restart required"
+    let pending = PendingUpdate {
+        previous_version: env!("CARGO_PKG_VERSION").to_string(),
+        new_version: new_version.to_string(),
+        binary: binary.to_path_buf(),
+        backup,
+        deadline,
+        starts: 0,
+    };
+    if let Err(e) = write_marker(&marker_path(binary), &pending) {
+        // Without the marker the update can't be rolled back automatically
+        warn!(error = %e, "Failed to record pending update");
+    }
+    Ok(())
+}
+
+fn write_marker(marker: &Path, pending: &PendingUpdate) -> std::io::Result<()> {
+    let json = serde_json::to_vec_pretty(pending).map_err(std::io::Error::other)?;
+    std::fs::write(marker, json)
+}
+
+fn read_marker(marker: &Path) -> std::io::Result<Option<PendingUpdate>> {
+    match std::fs::read(marker) {
+        Ok(data) => serde_json::from_slice(&data).map(Some).map_err(std::io::Error::other),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Decide what to do with a pending update at startup.
+fn evaluate_pending(mut pending: PendingUpdate, running_version: &str, now: i64) -> StartupAction {
+    if pending.new_version != running_version {
+        return StartupAction::Clear;
+    }
+
+    pending.starts += 1;
+    if pending.starts > MAX_UPDATE_STARTS {
+        let reason = format!("new binary was started {} times without answering the host", pending.starts - 1);
+        StartupAction::RollBack(pending, reason)
+    } else if now >= pending.deadline {
+        StartupAction::RollBack(pending, "new binary missed its health deadline".to_string())
+    } else {
+        StartupAction::Probation(pending)
+    }
+}
+
+/// Put the previous binary back in place.
+fn restore_previous(pending: &PendingUpdate) -> std::io::Result<()> {
+    // A running executable can be renamed but not replaced on Windows
+    #[cfg(windows)]
+    {
+        let failed = pending.binary.with_extension("failed");
+        let _ = std::fs::remove_file(&failed);
+        std::fs::rename(&pending.binary, &failed)?;
+    }
+
+    std::fs::rename(&pending.backup, &pending.binary)
+}
+
+/// Restore the previous binary and exit so it gets restarted. If it can't be
+/// restored, keep running the new one.
+fn roll_back(marker: &Path, pending: &PendingUpdate, reason: &str) {
+    error!(
+        new_version = %pending.new_version,
+        previous_version = %pending.previous_version,
+        reason = %reason,
+        "Rolling back agent update"
     );
 
-    agent_message::Payload::AgentUpdateResponse(AgentUpdateResponse {
-        success: true,
-        current_version: env!("CARGO_PKG_VERSION").to_string(),
-        error: String::new(),
-        restart_required: true,
-        progress_percent: 100,
-        state: UpdateState::Complete as i32,
-    })
+    let restored = restore_previous(pending);
+    let _ = std::fs::remove_file(marker);
+    match restored {
+        Ok(()) => {
+            info!(version = %pending.previous_version, "Previous agent restored, restarting");
+            std::process::exit(RESTART_EXIT_CODE);
+        }
+        Err(e) => error!(error = %e, backup = %pending.backup.display(), "Failed to restore previous agent"),
+    }
+}
+
+/// Check for an unconfirmed update of this binary at startup.
+///
+/// Rolls back right away if the new binary keeps restarting or is past its
+/// deadline; otherwise starts a timer that rolls back unless
+/// [`confirm_update`] is called first.
+pub fn check_pending_update() {
+    let Ok(binary) = std::env::current_exe() else {
+        return;
+    };
+    let marker = marker_path(&binary);
+    let pending = match read_marker(&marker) {
+        Ok(Some(pending)) => pending,
+        Ok(None) => return,
+        Err(e) => {
+            warn!(error = %e, marker = %marker.display(), "Discarding unreadable update marker");
+            let _ = std::fs::remove_file(&marker);
+            return;
+        }
+    };
+
+    match evaluate_pending(pending, env!("CARGO_PKG_VERSION"), chrono::Utc::now().timestamp()) {
+        StartupAction::Clear => {
+            debug!("Discarding update marker of another version");
+            let _ = std::fs::remove_file(&marker);
+        }
+        StartupAction::RollBack(pending, reason) => roll_back(&marker, &pending, &reason),
+        StartupAction::Probation(pending) => {
+            if let Err(e) = write_marker(&marker, &pending) {
+                warn!(error = %e, "Failed to update pending update marker");
+            }
+            let remaining = (pending.deadline - chrono::Utc::now().timestamp()).max(0) as u64;
+            info!(
+                version = %pending.new_version,
+                start = pending.starts,
+                remaining_secs = remaining,
+                "Running updated agent, waiting for the host"
+            );
+
+            *PENDING_MARKER.lock().unwrap() = Some(marker.clone());
+            std::thread::spawn(move || {
+                std::thread::sleep(Duration::from_secs(remaining));
+                if PENDING_MARKER.lock().unwrap().take().is_some() {
+                    roll_back(&marker, &pending, "new binary did not answer the host before its deadline");
+                }
+            });
+        }
+    }
+}
+
+/// Mark a pending update as healthy once the host has been answered. The
+/// previous binary is kept for manual rollback.
+pub fn confirm_update() {
+    let Some(marker) = PENDING_MARKER.lock().ok().and_then(|mut m| m.take()) else {
+        return;
+    };
+    let _ = std::fs::remove_file(&marker);
+    info!(version = env!("CARGO_PKG_VERSION"), "Agent update confirmed healthy");
 }
 
 /// Handle a get capabilities request.
@@ -435,6 +745,15 @@ pub async fn handle_get_capabilities(_req: GetCapabilitiesRequest) -> agent_mess
         "hardware_info".to_string(),
         "software_list".to_string(),
         "self_update".to_string(),
+        "session".to_string(),
+        "port_forward".to_string(),
+        "signed_update".to_string(),
+        "tree_transfer".to_string(),
+        "grow_filesystems".to_string(),
+        "trim_filesystems".to_string(),
+        "app_quiesce".to_string(),
+        "user_management".to_string(),
+        "os_patching".to_string(),
     ];
 
     let mut features = HashMap::new();
@@ -443,6 +762,7 @@ pub async fn handle_get_capabilities(_req: GetCapabilitiesRequest) -> agent_mess
     #[cfg(unix)]
     {
         features.insert("user_context_exec".to_string(), "true".to_string());
+        features.insert("pty_session".to_string(), "true".to_string());
         features.insert("fsfreeze".to_string(), "true".to_string());
     }
 
@@ -466,19 +786,6 @@ pub async fn handle_get_capabilities(_req: GetCapabilitiesRequest) -> agent_mess
     })
 }
 
-/// Get the temp directory for updates.
-fn get_temp_dir() -> PathBuf {
-    #[cfg(unix)]
-    {
-        PathBuf::from("/tmp/limiquantix")
-    }
-
-    #[cfg(windows)]
-    {
-        std::env::temp_dir().join("limiquantix")
-    }
-}
-
 // Add hex encoding for checksum
 mod hex {
     pub fn encode(bytes: impl AsRef<[u8]>) -> String {
@@ -488,6 +795,16 @@ mod hex {
             .map(|b| format!("{:02x}", b))
             .collect()
     }
+
+    pub fn decode(s: &str) -> Option<Vec<u8>> {
+        if s.len() % 2 != 0 {
+            return None;
+        }
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -514,4 +831,90 @@ mod tests {
         assert_eq!(hex::encode([0xde, 0xad, 0xbe, 0xef]), "deadbeef");
         assert_eq!(hex::encode([0x00, 0xff]), "00ff");
     }
+
+    #[test]
+    fn test_hex_decode() {
+        assert_eq!(hex::decode("deadBEEF"), Some(vec![0xde, 0xad, 0xbe, 0xef]));
+        assert_eq!(hex::decode("abc"), None);
+        assert_eq!(hex::decode("zz"), None);
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        use ring::rand::SystemRandom;
+        use ring::signature::{Ed25519KeyPair, KeyPair};
+
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let keys = parse_keys(&format!("00ff, {}", hex::encode(pair.public_key())));
+        assert_eq!(keys.len(), 1);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("agent");
+        std::fs::write(&path, b"new agent binary").unwrap();
+        let signature = pair.sign(b"new agent binary");
+
+        assert!(verify_signature(&path, signature.as_ref(), &keys).is_ok());
+        assert!(verify_signature(&path, &[], &keys).is_err());
+        assert!(verify_signature(&path, signature.as_ref(), &[]).is_err());
+
+        std::fs::write(&path, b"tampered agent binary").unwrap();
+        assert!(verify_signature(&path, signature.as_ref(), &keys).is_err());
+    }
+
+    #[test]
+    fn test_is_newer_version() {
+        assert!(is_newer_version("1.2.0", "1.1.9"));
+        assert!(is_newer_version("1.10.0", "1.9.0"));
+        assert!(is_newer_version("v2.0", "1.9.9"));
+        assert!(!is_newer_version("1.1.0", "1.1.0"));
+        assert!(!is_newer_version("1.1", "1.1.0"));
+        assert!(!is_newer_version("1.0.9", "1.1.0"));
+        assert!(!is_newer_version("1.1.0-rc1", "1.1.0"));
+    }
+
+    #[test]
+    fn test_install_and_roll_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("quantix-kvm-agent");
+        let new_binary = dir.path().join("agent-update.tmp");
+        std::fs::write(&binary, b"old").unwrap();
+        std::fs::write(&new_binary, b"new").unwrap();
+
+        install_update(&new_binary, &binary, "9.9.9", 1000).unwrap();
+        assert_eq!(std::fs::read(&binary).unwrap(), b"new");
+        assert_eq!(std::fs::read(binary.with_extension("bak")).unwrap(), b"old");
+
+        let pending = read_marker(&marker_path(&binary)).unwrap().unwrap();
+        assert_eq!(pending.new_version, "9.9.9");
+        assert_eq!(pending.previous_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(pending.starts, 0);
+
+        restore_previous(&pending).unwrap();
+        assert_eq!(std::fs::read(&binary).unwrap(), b"old");
+    }
+
+    #[test]
+    fn test_evaluate_pending() {
+        let pending = PendingUpdate {
+            previous_version: "1.0.0".to_string(),
+            new_version: "1.1.0".to_string(),
+            binary: PathBuf::from("/usr/local/bin/quantix-kvm-agent"),
+            backup: PathBuf::from("/usr/local/bin/quantix-kvm-agent.bak"),
+            deadline: 1000,
+            starts: 0,
+        };
+
+        assert_eq!(evaluate_pending(pending.clone(), "1.0.0", 0), StartupAction::Clear);
+
+        let StartupAction::Probation(started) = evaluate_pending(pending.clone(), "1.1.0", 0) else {
+            panic!("expected probation");
+        };
+        assert_eq!(started.starts, 1);
+
+        assert!(matches!(evaluate_pending(pending.clone(), "1.1.0", 1000), StartupAction::RollBack(..)));
+
+        let crash_looping = PendingUpdate { starts: MAX_UPDATE_STARTS, ..pending };
+        assert!(matches!(evaluate_pending(crash_looping, "1.1.0", 0), StartupAction::RollBack(..)));
+    }
 }
@@ -0,0 +1,1274 @@
+//! User account handlers.
+//!
+//! Manages local accounts so operators can onboard or offboard people
+//! without logging into every VM:
+//!
+//! - **Linux**: `/etc/passwd`, `/etc/group` and `/etc/shadow` for listing,
+//!   `useradd`/`userdel`/`usermod` for changes, `~/.ssh/authorized_keys` for
+//!   SSH keys and `loginctl` (or `who`/`pkill` without systemd) for sessions.
+//!   Locking also expires the account so SSH key logins stop working too.
+//! - **Windows**: the LocalAccounts PowerShell module (`New-LocalUser`,
+//!   `Add-LocalGroupMember`, `Disable-LocalUser`, ...), the OpenSSH
+//!   `authorized_keys` files and `quser`/`logoff` for sessions.
+//!
+//! Every change is written to the audit log regardless of
+//! `security.audit_logging`, since account changes are rare and always
+//! security-relevant. Passwords are never logged.
+
+use crate::security::AuditLogEntry;
+use limiquantix_proto::agent::{
+    agent_message, GuestUser, ListLoginSessionsRequest, ListLoginSessionsResponse,
+    ListUsersRequest, ListUsersResponse, LoginSession, UserAccountAction, UserAccountRequest,
+    UserAccountResponse,
+};
+use std::process::Stdio;
+use tokio::process::Command;
+use tracing::{error, info, warn};
+
+/// First UID of regular accounts when login.defs does not set UID_MIN
+#[cfg(unix)]
+const DEFAULT_UID_MIN: u32 = 1000;
+
+/// Handle a list users request.
+pub async fn handle_list_users(req: ListUsersRequest) -> agent_message::Payload {
+    let response = match list_users(req.include_system || !req.username.is_empty()).await {
+        Ok(users) => ListUsersResponse {
+            success: true,
+            error: String::new(),
+            users: users
+                .into_iter()
+                .filter(|u| req.username.is_empty() || u.username == req.username)
+                .collect(),
+        },
+        Err(e) => {
+            error!(error = %e, "Failed to list users");
+            ListUsersResponse {
+                success: false,
+                error: e,
+                users: Vec::new(),
+            }
+        }
+    };
+
+    agent_message::Payload::ListUsersResponse(response)
+}
+
+/// Handle a user account change and record it in the audit log.
+pub async fn handle_user_account(req: UserAccountRequest, request_id: &str) -> agent_message::Payload {
+    let Ok(action) = UserAccountAction::try_from(req.action) else {
+        return agent_message::Payload::UserAccountResponse(UserAccountResponse {
+            success: false,
+            error: format!("Unknown user account action: {}", req.action),
+            user: None,
+            sessions_ended: 0,
+        });
+    };
+
+    info!(username = %req.username, action = ?action, "Handling user account request");
+
+    let result = user_account(&req, action).await;
+    audit(request_id, action, &req, &result);
+
+    let response = match result {
+        Ok((user, sessions_ended)) => UserAccountResponse {
+            success: true,
+            error: String::new(),
+            user,
+            sessions_ended,
+        },
+        Err(e) => {
+            error!(username = %req.username, action = ?action, error = %e, "User account action failed");
+            UserAccountResponse {
+                success: false,
+                error: e,
+                user: None,
+                sessions_ended: 0,
+            }
+        }
+    };
+
+    agent_message::Payload::UserAccountResponse(response)
+}
+
+/// Handle a list login sessions request.
+pub async fn handle_list_login_sessions(req: ListLoginSessionsRequest) -> agent_message::Payload {
+    let response = match list_sessions().await {
+        Ok(sessions) => ListLoginSessionsResponse {
+            success: true,
+            error: String::new(),
+            sessions: sessions
+                .into_iter()
+                .map(|s| s.info)
+                .filter(|s| req.username.is_empty() || s.username == req.username)
+                .collect(),
+        },
+        Err(e) => {
+            error!(error = %e, "Failed to list login sessions");
+            ListLoginSessionsResponse {
+                success: false,
+                error: e,
+                sessions: Vec::new(),
+            }
+        }
+    };
+
+    agent_message::Payload::ListLoginSessionsResponse(response)
+}
+
+/// Apply an account action. Returns the account afterwards (none after a
+/// delete) and the number of sessions that were ended.
+async fn user_account(
+    req: &UserAccountRequest,
+    action: UserAccountAction,
+) -> Result<(Option<GuestUser>, u32), String> {
+    validate_name(&req.username)?;
+    for group in &req.groups {
+        validate_name(group)?;
+    }
+    if !req.ssh_public_key.is_empty() {
+        validate_ssh_key(&req.ssh_public_key)?;
+    }
+    validate_password(&req.password)?;
+
+    if action == UserAccountAction::Create {
+        if find_user(&req.username).await.is_ok() {
+            return Err(format!("User {} already exists", req.username));
+        }
+        create_user(req).await?;
+        let user = find_user(&req.username).await?;
+        if !req.ssh_public_key.is_empty() {
+            update_authorized_keys(&user, &req.ssh_public_key, true).await?;
+        }
+        return Ok((Some(find_user(&req.username).await?), 0));
+    }
+
+    let user = find_user(&req.username).await?;
+    if matches!(action, UserAccountAction::Delete | UserAccountAction::Lock) && is_protected(&user) {
+        let verb = if action == UserAccountAction::Delete { "delete" } else { "lock" };
+        return Err(format!("Refusing to {} {}, the built-in administrator account", verb, user.username));
+    }
+
+    let mut sessions_ended = 0;
+    match action {
+        UserAccountAction::Create => unreachable!(),
+        UserAccountAction::Delete => {
+            // Sessions keep processes running as the user, which blocks the removal
+            sessions_ended = end_sessions(&user.username, "").await.unwrap_or_else(|e| {
+                warn!(username = %user.username, error = %e, "Failed to end sessions before delete");
+                0
+            });
+            delete_user(&user, req.remove_home).await?;
+            return Ok((None, sessions_ended));
+        }
+        UserAccountAction::SetGroups => set_groups(&user, &req.groups).await?,
+        UserAccountAction::Lock => set_locked(&user, true).await?,
+        UserAccountAction::Unlock => set_locked(&user, false).await?,
+        UserAccountAction::AddSshKey | UserAccountAction::RemoveSshKey => {
+            if req.ssh_public_key.is_empty() {
+                return Err("ssh_public_key is required".to_string());
+            }
+            let add = action == UserAccountAction::AddSshKey;
+            update_authorized_keys(&user, &req.ssh_public_key, add).await?;
+        }
+        UserAccountAction::Logoff => {
+            sessions_ended = end_sessions(&user.username, &req.session_id).await?;
+        }
+    }
+
+    Ok((Some(find_user(&req.username).await?), sessions_ended))
+}
+
+/// Look up one account (including system accounts).
+async fn find_user(username: &str) -> Result<GuestUser, String> {
+    list_users(true)
+        .await?
+        .into_iter()
+        .find(|u| u.username == username)
+        .ok_or_else(|| format!("User {} not found", username))
+}
+
+/// Root and the built-in Windows Administrator cannot be deleted or locked.
+fn is_protected(user: &GuestUser) -> bool {
+    user.id == "0" || user.id.ends_with("-500")
+}
+
+/// End the login sessions of a user (or only `session_id`).
+async fn end_sessions(username: &str, session_id: &str) -> Result<u32, String> {
+    let sessions: Vec<Session> = list_sessions()
+        .await?
+        .into_iter()
+        .filter(|s| s.info.username == username)
+        .filter(|s| session_id.is_empty() || s.info.session_id == session_id)
+        .collect();
+
+    if sessions.is_empty() && !session_id.is_empty() {
+        return Err(format!("Session {} of user {} not found", session_id, username));
+    }
+
+    let mut ended = 0;
+    for session in &sessions {
+        end_session(session).await?;
+        info!(username = %username, session_id = %session.info.session_id, "Ended login session");
+        ended += 1;
+    }
+    Ok(ended)
+}
+
+/// Write an audit log entry for an account change.
+fn audit(
+    request_id: &str,
+    action: UserAccountAction,
+    req: &UserAccountRequest,
+    result: &Result<(Option<GuestUser>, u32), String>,
+) {
+    let operation = match action {
+        UserAccountAction::Create => "user_create",
+        UserAccountAction::Delete => "user_delete",
+        UserAccountAction::SetGroups => "user_set_groups",
+        UserAccountAction::Lock => "user_lock",
+        UserAccountAction::Unlock => "user_unlock",
+        UserAccountAction::AddSshKey => "user_add_ssh_key",
+        UserAccountAction::RemoveSshKey => "user_remove_ssh_key",
+        UserAccountAction::Logoff => "user_logoff",
+    };
+
+    let mut entry = AuditLogEntry::new(operation, request_id).with_user(&req.username);
+    if matches!(action, UserAccountAction::Create | UserAccountAction::SetGroups) {
+        entry = entry.with_detail("groups", &req.groups.join(","));
+    }
+    if action == UserAccountAction::Create {
+        entry = entry.with_detail("password_set", &(!req.password.is_empty()).to_string());
+    }
+    if action == UserAccountAction::Delete {
+        entry = entry.with_detail("remove_home", &req.remove_home.to_string());
+    }
+    if !req.ssh_public_key.is_empty() {
+        entry = entry.with_detail("ssh_key", &ssh_key_summary(&req.ssh_public_key));
+    }
+    if !req.session_id.is_empty() {
+        entry = entry.with_detail("session_id", &req.session_id);
+    }
+    match result {
+        Ok((_, sessions_ended)) => {
+            entry = entry.with_detail("result", "ok");
+            if *sessions_ended > 0 {
+                entry = entry.with_detail("sessions_ended", &sessions_ended.to_string());
+            }
+        }
+        Err(e) => entry = entry.with_detail("result", "error").with_detail("error", e),
+    }
+    entry.log();
+}
+
+/// Run a command, returning stdout or an error with its output.
+async fn run(program: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new(program)
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    if output.status.success() {
+        Ok(stdout)
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(format!("{} failed: {} {}", program, stdout.trim(), stderr.trim()))
+    }
+}
+
+// =============================================================================
+// Validation and authorized_keys editing
+// =============================================================================
+
+/// Accept portable user and group names only (no option or path injection).
+fn validate_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name.len() <= 32
+        && !name.starts_with('-')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid user or group name: {:?}", name))
+    }
+}
+
+/// Key types accepted in authorized_keys.
+fn is_key_type(token: &str) -> bool {
+    ["ssh-", "ecdsa-sha2-", "sk-ssh-", "sk-ecdsa-sha2-"]
+        .iter()
+        .any(|prefix| token.starts_with(prefix))
+}
+
+/// Type and base64 blob of an authorized_keys line, skipping any options.
+fn parse_ssh_key(line: &str) -> Option<(&str, &str)> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let index = tokens.iter().position(|t| is_key_type(t))?;
+    let blob = tokens.get(index + 1)?;
+    let base64 = blob
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '/' | '='));
+    base64.then_some((tokens[index], *blob))
+}
+
+/// A single, well-formed OpenSSH public key line.
+fn validate_ssh_key(key: &str) -> Result<(), String> {
+    if key.contains('\n') || key.contains('\r') || parse_ssh_key(key).is_none() {
+        return Err("Invalid SSH public key".to_string());
+    }
+    Ok(())
+}
+
+/// chpasswd reads `user:password` lines, so a line break would set the
+/// password of another account.
+fn validate_password(password: &str) -> Result<(), String> {
+    if password.contains(['\n', '\r', '\0']) {
+        return Err("Password must not contain line breaks or NUL characters".to_string());
+    }
+    Ok(())
+}
+
+/// Key type and comment, for logs (the blob is not useful there).
+fn ssh_key_summary(key: &str) -> String {
+    match parse_ssh_key(key) {
+        Some((key_type, blob)) => {
+            let comment = key.split(blob).nth(1).unwrap_or_default().trim();
+            format!("{} {}", key_type, comment).trim().to_string()
+        }
+        None => "invalid".to_string(),
+    }
+}
+
+/// Append `key` to authorized_keys content (None if it is already present).
+fn add_authorized_key(content: &str, key: &str) -> Option<String> {
+    let wanted = parse_ssh_key(key)?;
+    if content.lines().any(|line| parse_ssh_key(line).map(|k| k.1) == Some(wanted.1)) {
+        return None;
+    }
+
+    let mut updated = content.to_string();
+    if !updated.is_empty() && !updated.ends_with('\n') {
+        updated.push('\n');
+    }
+    updated.push_str(key.trim());
+    updated.push('\n');
+    Some(updated)
+}
+
+/// Remove every line with the same key as `key` (None if there is none).
+fn remove_authorized_key(content: &str, key: &str) -> Option<String> {
+    let wanted = parse_ssh_key(key)?;
+    let kept: Vec<&str> = content
+        .lines()
+        .filter(|line| parse_ssh_key(line).map(|k| k.1) != Some(wanted.1))
+        .collect();
+    if kept.len() == content.lines().count() {
+        return None;
+    }
+
+    let mut updated = kept.join("\n");
+    if !updated.is_empty() {
+        updated.push('\n');
+    }
+    Some(updated)
+}
+
+/// Keys in authorized_keys content (comments and blank lines skipped).
+fn authorized_keys(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Apply an add/remove to the user's authorized_keys content.
+fn edit_authorized_keys(content: &str, key: &str, add: bool) -> Result<Option<String>, String> {
+    if add {
+        Ok(add_authorized_key(content, key))
+    } else {
+        remove_authorized_key(content, key)
+            .map(Some)
+            .ok_or_else(|| "SSH key is not authorized for this user".to_string())
+    }
+}
+
+// =============================================================================
+// Linux
+// =============================================================================
+
+/// A login session and how to end it.
+struct Session {
+    info: LoginSession,
+    /// Session managed by systemd-logind (otherwise a `who` terminal)
+    #[cfg_attr(windows, allow(dead_code))]
+    logind: bool,
+}
+
+/// Entry of /etc/passwd.
+#[cfg(any(unix, test))]
+#[derive(Debug, PartialEq)]
+struct PasswdEntry {
+    name: String,
+    uid: u32,
+    gid: u32,
+    gecos: String,
+    home: String,
+    shell: String,
+}
+
+#[cfg(any(unix, test))]
+fn parse_passwd(content: &str) -> Vec<PasswdEntry> {
+    content
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(':').collect();
+            if fields.len() < 7 {
+                return None;
+            }
+            Some(PasswdEntry {
+                name: fields[0].to_string(),
+                uid: fields[2].parse().ok()?,
+                gid: fields[3].parse().ok()?,
+                // GECOS holds "Full Name,room,phone,..."
+                gecos: fields[4].split(',').next().unwrap_or_default().to_string(),
+                home: fields[5].to_string(),
+                shell: fields[6].to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Entries of /etc/group as (name, gid, members).
+#[cfg(any(unix, test))]
+fn parse_group(content: &str) -> Vec<(String, u32, Vec<String>)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(':').collect();
+            if fields.len() < 4 {
+                return None;
+            }
+            let members = fields[3]
+                .split(',')
+                .filter(|m| !m.is_empty())
+                .map(str::to_string)
+                .collect();
+            Some((fields[0].to_string(), fields[2].parse().ok()?, members))
+        })
+        .collect()
+}
+
+/// Group names of a user, primary group first.
+#[cfg(any(unix, test))]
+fn user_groups(user: &PasswdEntry, groups: &[(String, u32, Vec<String>)]) -> Vec<String> {
+    let primary = groups.iter().find(|(_, gid, _)| *gid == user.gid);
+    primary
+        .iter()
+        .map(|(name, _, _)| name.clone())
+        .chain(
+            groups
+                .iter()
+                .filter(|(_, gid, members)| *gid != user.gid && members.contains(&user.name))
+                .map(|(name, _, _)| name.clone()),
+        )
+        .collect()
+}
+
+/// Whether a shadow entry is locked (a password hash prefixed with '!') or
+/// the account is expired (`expire` days since the epoch, 0 < expire <= today).
+/// A bare "!" or "!!" only means no password is set (key-only accounts).
+#[cfg(any(unix, test))]
+fn shadow_locked(entry: &str, today: i64) -> bool {
+    let fields: Vec<&str> = entry.split(':').collect();
+    let password_locked = fields
+        .get(1)
+        .is_some_and(|pw| pw.starts_with('!') && !pw.trim_start_matches('!').is_empty());
+    let expired = fields
+        .get(7)
+        .and_then(|e| e.parse::<i64>().ok())
+        .is_some_and(|expire| expire > 0 && expire <= today);
+    password_locked || expired
+}
+
+/// UID_MIN from login.defs.
+#[cfg(any(unix, test))]
+fn parse_uid_min(login_defs: &str) -> Option<u32> {
+    login_defs.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        (parts.next() == Some("UID_MIN")).then(|| parts.next()?.parse().ok())?
+    })
+}
+
+/// Properties printed by `loginctl show-session`.
+#[cfg(any(unix, test))]
+fn parse_loginctl_session(id: &str, output: &str) -> LoginSession {
+    let property = |key: &str| {
+        output
+            .lines()
+            .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+            .unwrap_or_default()
+            .to_string()
+    };
+
+    LoginSession {
+        session_id: id.to_string(),
+        username: property("Name"),
+        tty: property("TTY"),
+        remote_host: property("RemoteHost"),
+        login_time: parse_logind_timestamp(&property("Timestamp")),
+        state: property("State"),
+    }
+}
+
+/// Parse a logind timestamp like "Sat 2026-10-18 09:12:33 UTC".
+#[cfg(any(unix, test))]
+fn parse_logind_timestamp(value: &str) -> Option<prost_types::Timestamp> {
+    let tokens: Vec<&str> = value.split_whitespace().collect();
+    let naive = chrono::NaiveDateTime::parse_from_str(
+        &format!("{} {}", tokens.get(1)?, tokens.get(2)?),
+        "%Y-%m-%d %H:%M:%S",
+    )
+    .ok()?;
+    let seconds = match tokens.get(3) {
+        Some(&"UTC") | None => naive.and_utc().timestamp(),
+        Some(_) => naive.and_local_timezone(chrono::Local).single()?.timestamp(),
+    };
+    Some(prost_types::Timestamp { seconds, nanos: 0 })
+}
+
+/// Parse `who` output ("alice pts/0 2026-10-18 09:12 (10.0.0.5)").
+#[cfg(any(unix, test))]
+fn parse_who(output: &str) -> Vec<LoginSession> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            let (username, tty) = (tokens.first()?, tokens.get(1)?);
+            let login_time = tokens.get(2).zip(tokens.get(3)).and_then(|(date, time)| {
+                chrono::NaiveDateTime::parse_from_str(&format!("{} {}", date, time), "%Y-%m-%d %H:%M")
+                    .ok()?
+                    .and_local_timezone(chrono::Local)
+                    .single()
+                    .map(|t| prost_types::Timestamp { seconds: t.timestamp(), nanos: 0 })
+            });
+            let remote_host = tokens
+                .iter()
+                .find(|t| t.starts_with('('))
+                .map(|t| t.trim_matches(|c| c == '(' || c == ')').to_string())
+                .unwrap_or_default();
+            Some(LoginSession {
+                session_id: tty.to_string(),
+                username: username.to_string(),
+                tty: tty.to_string(),
+                remote_host,
+                login_time,
+                state: "active".to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(unix)]
+async fn list_users(include_system: bool) -> Result<Vec<GuestUser>, String> {
+    let passwd = tokio::fs::read_to_string("/etc/passwd")
+        .await
+        .map_err(|e| format!("Failed to read /etc/passwd: {}", e))?;
+    let groups = parse_group(&tokio::fs::read_to_string("/etc/group").await.unwrap_or_default());
+    // Only readable by root; without it lock status is unknown
+    let shadow = tokio::fs::read_to_string("/etc/shadow").await.unwrap_or_default();
+    let uid_min = tokio::fs::read_to_string("/etc/login.defs")
+        .await
+        .ok()
+        .and_then(|defs| parse_uid_min(&defs))
+        .unwrap_or(DEFAULT_UID_MIN);
+    let today = chrono::Utc::now().timestamp() / 86400;
+
+    let mut users = Vec::new();
+    for entry in parse_passwd(&passwd) {
+        let system = entry.uid < uid_min || entry.uid == 65534;
+        if system && !include_system {
+            continue;
+        }
+
+        let keys_path = std::path::Path::new(&entry.home).join(".ssh/authorized_keys");
+        let keys = tokio::fs::read_to_string(&keys_path).await.unwrap_or_default();
+        let locked = shadow
+            .lines()
+            .find(|line| line.split(':').next() == Some(entry.name.as_str()))
+            .is_some_and(|line| shadow_locked(line, today));
+
+        users.push(GuestUser {
+            username: entry.name.clone(),
+            id: entry.uid.to_string(),
+            full_name: entry.gecos.clone(),
+            groups: user_groups(&entry, &groups),
+            home: entry.home,
+            shell: entry.shell,
+            locked,
+            system,
+            ssh_authorized_keys: authorized_keys(&keys),
+        });
+    }
+    Ok(users)
+}
+
+#[cfg(unix)]
+async fn create_user(req: &UserAccountRequest) -> Result<(), String> {
+    let groups = req.groups.join(",");
+    let mut args = vec!["-m"];
+    if !req.full_name.is_empty() {
+        args.extend(["-c", req.full_name.as_str()]);
+    }
+    if !req.shell.is_empty() {
+        args.extend(["-s", req.shell.as_str()]);
+    }
+    if !groups.is_empty() {
+        args.extend(["-G", groups.as_str()]);
+    }
+    args.push(req.username.as_str());
+    run("useradd", &args).await?;
+
+    if !req.password.is_empty() {
+        if let Err(e) = set_password(&req.username, &req.password).await {
+            // Do not leave a half-created account behind
+            let _ = run("userdel", &["-r", &req.username]).await;
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// Set a password through chpasswd's stdin (never on the command line).
+#[cfg(unix)]
+async fn set_password(username: &str, password: &str) -> Result<(), String> {
+    use tokio::io::AsyncWriteExt;
+
+    let mut child = Command::new("chpasswd")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to run chpasswd: {}", e))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(format!("{}:{}\n", username, password).as_bytes())
+            .await
+            .map_err(|e| format!("Failed to write to chpasswd: {}", e))?;
+    }
+    let output = child
+        .wait_with_output()
+        .await
+        .map_err(|e| format!("Failed to run chpasswd: {}", e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(format!("chpasswd failed: {}", String::from_utf8_lossy(&output.stderr).trim()))
+    }
+}
+
+#[cfg(unix)]
+async fn delete_user(user: &GuestUser, remove_home: bool) -> Result<(), String> {
+    if remove_home {
+        run("userdel", &["-r", &user.username]).await?;
+    } else {
+        run("userdel", &[&user.username]).await?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+async fn set_groups(user: &GuestUser, groups: &[String]) -> Result<(), String> {
+    run("usermod", &["-G", &groups.join(","), &user.username]).await?;
+    Ok(())
+}
+
+#[cfg(unix)]
+async fn set_locked(user: &GuestUser, locked: bool) -> Result<(), String> {
+    if locked {
+        // Expiring the account also blocks SSH key logins, which -L alone does not
+        run("usermod", &["-L", "-e", "1", &user.username]).await?;
+        return Ok(());
+    }
+
+    // -U refuses to leave an empty password (accounts created without one)
+    let shadow = tokio::fs::read_to_string("/etc/shadow").await.unwrap_or_default();
+    let password = shadow
+        .lines()
+        .find(|line| line.split(':').next() == Some(user.username.as_str()))
+        .and_then(|line| line.split(':').nth(1))
+        .unwrap_or_default();
+    if password.len() > 1 && password.starts_with('!') {
+        run("usermod", &["-U", "-e", "", &user.username]).await?;
+    } else {
+        run("usermod", &["-e", "", &user.username]).await?;
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+async fn update_authorized_keys(user: &GuestUser, key: &str, add: bool) -> Result<(), String> {
+    let account = nix::unistd::User::from_name(&user.username)
+        .map_err(|e| format!("Failed to look up {}: {}", user.username, e))?
+        .ok_or_else(|| format!("User {} not found", user.username))?;
+    let key = key.to_string();
+    tokio::task::spawn_blocking(move || write_authorized_keys(&account, &key, add))
+        .await
+        .map_err(|e| format!("Failed to update authorized keys: {}", e))?
+}
+
+/// Edit `~/.ssh/authorized_keys` of `account`.
+///
+/// The user owns `.ssh` and can swap anything in it for a symlink or hard
+/// link at any time. Everything below the home directory is therefore opened
+/// relative to a verified directory descriptor with `O_NOFOLLOW`, ownership
+/// and permissions are set on descriptors, and the file is replaced with
+/// `renameat` rather than written in place.
+#[cfg(unix)]
+fn write_authorized_keys(account: &nix::unistd::User, key: &str, add: bool) -> Result<(), String> {
+    use nix::errno::Errno;
+    use nix::fcntl::{openat, renameat, OFlag};
+    use nix::sys::stat::{mkdirat, Mode};
+    use nix::unistd::{fchown, unlinkat, UnlinkatFlags};
+    use std::fs::File;
+    use std::io::{Read, Write};
+    use std::os::fd::{AsRawFd, FromRawFd};
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    const TEMP_NAME: &str = ".authorized_keys.tmp";
+
+    let open_at = |dir: &File, name: &str, flags: OFlag, mode: u32| {
+        let fd = openat(
+            Some(dir.as_raw_fd()),
+            name,
+            flags | OFlag::O_NOFOLLOW | OFlag::O_CLOEXEC,
+            Mode::from_bits_truncate(mode),
+        )?;
+        // SAFETY: openat returned a new descriptor that nothing else owns
+        Ok::<_, Errno>(unsafe { File::from_raw_fd(fd) })
+    };
+    let home_path = account.dir.display();
+
+    // The home directory comes from passwd; it must belong to the user (or root)
+    let home = File::open(&account.dir).map_err(|e| format!("Failed to open {}: {}", home_path, e))?;
+    let meta = home.metadata().map_err(|e| format!("Failed to inspect {}: {}", home_path, e))?;
+    if !meta.is_dir() || (meta.uid() != account.uid.as_raw() && meta.uid() != 0) {
+        return Err(format!("Home directory {} is not a directory owned by {}", home_path, account.name));
+    }
+
+    let dir_flags = OFlag::O_RDONLY | OFlag::O_DIRECTORY;
+    let ssh = match open_at(&home, ".ssh", dir_flags, 0) {
+        Ok(ssh) => ssh,
+        Err(Errno::ENOENT) => {
+            mkdirat(Some(home.as_raw_fd()), ".ssh", Mode::from_bits_truncate(0o700))
+                .map_err(|e| format!("Failed to create {}/.ssh: {}", home_path, e))?;
+            let ssh = open_at(&home, ".ssh", dir_flags, 0)
+                .map_err(|e| format!("Failed to open {}/.ssh: {}", home_path, e))?;
+            fchown(ssh.as_raw_fd(), Some(account.uid), Some(account.gid))
+                .map_err(|e| format!("Failed to chown {}/.ssh: {}", home_path, e))?;
+            ssh
+        }
+        Err(Errno::ELOOP) | Err(Errno::ENOTDIR) => {
+            return Err(format!("Refusing to use {}/.ssh: not a directory", home_path));
+        }
+        Err(e) => return Err(format!("Failed to open {}/.ssh: {}", home_path, e)),
+    };
+    let meta = ssh.metadata().map_err(|e| format!("Failed to inspect {}/.ssh: {}", home_path, e))?;
+    if meta.uid() != account.uid.as_raw() {
+        return Err(format!("{}/.ssh is not owned by {}", home_path, account.name));
+    }
+
+    // Only a regular file of the user's with no other names is read back;
+    // O_NONBLOCK keeps a planted FIFO from blocking the open
+    let content = match open_at(&ssh, "authorized_keys", OFlag::O_RDONLY | OFlag::O_NONBLOCK, 0) {
+        Ok(mut file) => {
+            let meta = file.metadata().map_err(|e| format!("Failed to inspect authorized_keys: {}", e))?;
+            if !meta.is_file() || meta.uid() != account.uid.as_raw() || meta.nlink() != 1 {
+                return Err(format!(
+                    "Refusing to use {}/.ssh/authorized_keys: not a regular file owned by {}",
+                    home_path, account.name
+                ));
+            }
+            let mut content = String::new();
+            file.read_to_string(&mut content)
+                .map_err(|e| format!("Failed to read {}/.ssh/authorized_keys: {}", home_path, e))?;
+            content
+        }
+        Err(Errno::ENOENT) => String::new(),
+        Err(Errno::ELOOP) => {
+            return Err(format!("Refusing to write through symlink {}/.ssh/authorized_keys", home_path));
+        }
+        Err(e) => return Err(format!("Failed to open {}/.ssh/authorized_keys: {}", home_path, e)),
+    };
+
+    let Some(updated) = edit_authorized_keys(&content, key, add)? else {
+        return Ok(());
+    };
+
+    let create = OFlag::O_WRONLY | OFlag::O_CREAT | OFlag::O_EXCL;
+    let mut temp = match open_at(&ssh, TEMP_NAME, create, 0o600) {
+        Err(Errno::EEXIST) => {
+            // Left over from an interrupted update (or planted): drop the name
+            let _ = unlinkat(Some(ssh.as_raw_fd()), TEMP_NAME, UnlinkatFlags::NoRemoveDir);
+            open_at(&ssh, TEMP_NAME, create, 0o600)
+        }
+        other => other,
+    }
+    .map_err(|e| format!("Failed to create {}/.ssh/{}: {}", home_path, TEMP_NAME, e))?;
+
+    let written = temp
+        .write_all(updated.as_bytes())
+        .and_then(|_| temp.set_permissions(std::fs::Permissions::from_mode(0o600)))
+        .map_err(|e| e.to_string())
+        .and_then(|_| fchown(temp.as_raw_fd(), Some(account.uid), Some(account.gid)).map_err(|e| e.to_string()))
+        .and_then(|_| temp.sync_all().map_err(|e| e.to_string()))
+        .and_then(|_| {
+            renameat(Some(ssh.as_raw_fd()), TEMP_NAME, Some(ssh.as_raw_fd()), "authorized_keys")
+                .map_err(|e| e.to_string())
+        });
+    if let Err(e) = written {
+        let _ = unlinkat(Some(ssh.as_raw_fd()), TEMP_NAME, UnlinkatFlags::NoRemoveDir);
+        return Err(format!("Failed to write {}/.ssh/authorized_keys: {}", home_path, e));
+    }
+    Ok(())
+}
+
+/// Login sessions from logind, or from utmp (`who`) without systemd.
+#[cfg(unix)]
+async fn list_sessions() -> Result<Vec<Session>, String> {
+    match run("loginctl", &["list-sessions", "--no-legend"]).await {
+        Ok(output) => {
+            let mut sessions = Vec::new();
+            for id in output.lines().filter_map(|line| line.split_whitespace().next()) {
+                let properties = run(
+                    "loginctl",
+                    &["show-session", id, "-p", "Name", "-p", "TTY", "-p", "RemoteHost", "-p", "Timestamp", "-p", "State"],
+                )
+                .await?;
+                sessions.push(Session {
+                    info: parse_loginctl_session(id, &properties),
+                    logind: true,
+                });
+            }
+            Ok(sessions)
+        }
+        Err(e) => {
+            warn!(error = %e, "loginctl unavailable, listing sessions with who");
+            let output = run("who", &[]).await?;
+            Ok(parse_who(&output)
+                .into_iter()
+                .map(|info| Session { info, logind: false })
+                .collect())
+        }
+    }
+}
+
+#[cfg(unix)]
+async fn end_session(session: &Session) -> Result<(), String> {
+    if session.logind {
+        run("loginctl", &["terminate-session", &session.info.session_id]).await?;
+    } else {
+        run("pkill", &["-KILL", "-t", &session.info.tty]).await?;
+    }
+    Ok(())
+}
+
+// =============================================================================
+// Windows
+// =============================================================================
+
+/// Parse `quser` output. Disconnected sessions have no session name, so the
+/// columns are found from the numeric session ID.
+#[cfg(any(windows, test))]
+fn parse_quser(output: &str) -> Vec<LoginSession> {
+    output
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let tokens: Vec<&str> = line.trim_start_matches('>').split_whitespace().collect();
+            let id_index = tokens.iter().skip(1).position(|t| t.parse::<u32>().is_ok())? + 1;
+            Some(LoginSession {
+                session_id: tokens[id_index].to_string(),
+                username: tokens[0].to_string(),
+                tty: if id_index == 2 { tokens[1].to_string() } else { String::new() },
+                remote_host: String::new(),
+                login_time: None,
+                state: tokens.get(id_index + 1).unwrap_or(&"").to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Run a PowerShell script, passing values through the environment so they
+/// are never parsed as script text.
+#[cfg(windows)]
+async fn powershell(script: &str, env: &[(&str, &str)]) -> Result<String, String> {
+    let script = format!("$ErrorActionPreference = 'Stop'\n{}", script);
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .envs(env.iter().copied())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run PowerShell: {}", e))?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    } else {
+        Err(format!("PowerShell failed: {}", String::from_utf8_lossy(&output.stderr).trim()))
+    }
+}
+
+#[cfg(windows)]
+async fn list_users(include_system: bool) -> Result<Vec<GuestUser>, String> {
+    let script = r#"
+        $admins = @(Get-LocalGroupMember -SID 'S-1-5-32-544' -ErrorAction SilentlyContinue | ForEach-Object { $_.SID.Value })
+        $groups = @(Get-LocalGroup | ForEach-Object {
+            [PSCustomObject]@{ Name = $_.Name; Members = @(Get-LocalGroupMember -Group $_ -ErrorAction SilentlyContinue | ForEach-Object { $_.SID.Value }) }
+        })
+        @(Get-LocalUser | ForEach-Object {
+            $sid = $_.SID.Value
+            [PSCustomObject]@{
+                Name = $_.Name
+                SID = $sid
+                FullName = $_.FullName
+                Enabled = $_.Enabled
+                Admin = $admins -contains $sid
+                Profile = (Get-CimInstance Win32_UserProfile -Filter "SID='$sid'" -ErrorAction SilentlyContinue).LocalPath
+                Groups = @($groups | Where-Object { $_.Members -contains $sid } | ForEach-Object { $_.Name })
+            }
+        }) | ConvertTo-Json -Compress -Depth 3
+    "#;
+
+    let stdout = powershell(script, &[]).await?;
+    let json: serde_json::Value = serde_json::from_str(stdout.trim())
+        .map_err(|e| format!("Unexpected PowerShell output: {}", e))?;
+    let items = match json {
+        serde_json::Value::Array(items) => items,
+        item => vec![item],
+    };
+
+    let mut users = Vec::new();
+    for item in &items {
+        let name = item["Name"].as_str().unwrap_or_default().to_string();
+        let sid = item["SID"].as_str().unwrap_or_default().to_string();
+        // Built-in accounts (Administrator, Guest, ...) have RIDs below 1000
+        let rid: u32 = sid.rsplit('-').next().and_then(|r| r.parse().ok()).unwrap_or(0);
+        let system = rid < 1000;
+        if system && !include_system {
+            continue;
+        }
+
+        let home = item["Profile"].as_str().map(str::to_string).unwrap_or_else(|| format!(r"C:\Users\{}", name));
+        let keys_path = windows_authorized_keys_path(&home, item["Admin"].as_bool().unwrap_or(false));
+        let keys = tokio::fs::read_to_string(&keys_path).await.unwrap_or_default();
+
+        users.push(GuestUser {
+            username: name,
+            id: sid,
+            full_name: item["FullName"].as_str().unwrap_or_default().to_string(),
+            home,
+            shell: String::new(),
+            groups: item["Groups"]
+                .as_array()
+                .map(|g| g.iter().filter_map(|g| g.as_str()).map(str::to_string).collect())
+                .unwrap_or_default(),
+            locked: !item["Enabled"].as_bool().unwrap_or(true),
+            system,
+            ssh_authorized_keys: authorized_keys(&keys),
+        });
+    }
+    Ok(users)
+}
+
+/// OpenSSH for Windows reads administrators' keys from a shared file.
+#[cfg(windows)]
+fn windows_authorized_keys_path(home: &str, admin: bool) -> std::path::PathBuf {
+    if admin {
+        let program_data = std::env::var("ProgramData").unwrap_or_else(|_| r"C:\ProgramData".to_string());
+        std::path::Path::new(&program_data).join(r"ssh\administrators_authorized_keys")
+    } else {
+        std::path::Path::new(home).join(r".ssh\authorized_keys")
+    }
+}
+
+#[cfg(windows)]
+async fn create_user(req: &UserAccountRequest) -> Result<(), String> {
+    let script = r#"
+        foreach ($g in @($env:QX_GROUPS -split "`n" | Where-Object { $_ })) { Get-LocalGroup -Name $g | Out-Null }
+        if ($env:QX_PASSWORD) {
+            $password = ConvertTo-SecureString $env:QX_PASSWORD -AsPlainText -Force
+            New-LocalUser -Name $env:QX_USER -FullName $env:QX_FULLNAME -Password $password | Out-Null
+        } else {
+            New-LocalUser -Name $env:QX_USER -FullName $env:QX_FULLNAME -NoPassword | Out-Null
+        }
+        foreach ($g in @($env:QX_GROUPS -split "`n" | Where-Object { $_ })) { Add-LocalGroupMember -Group $g -Member $env:QX_USER }
+    "#;
+    let groups = req.groups.join("\n");
+    powershell(
+        script,
+        &[
+            ("QX_USER", req.username.as_str()),
+            ("QX_FULLNAME", req.full_name.as_str()),
+            ("QX_PASSWORD", req.password.as_str()),
+            ("QX_GROUPS", groups.as_str()),
+        ],
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(windows)]
+async fn delete_user(user: &GuestUser, remove_home: bool) -> Result<(), String> {
+    let script = r#"
+        Remove-LocalUser -SID $env:QX_SID
+        if ($env:QX_REMOVE_HOME -eq 'true') {
+            Get-CimInstance Win32_UserProfile -Filter "SID='$env:QX_SID'" | Remove-CimInstance
+        }
+    "#;
+    let remove_home = remove_home.to_string();
+    powershell(script, &[("QX_SID", user.id.as_str()), ("QX_REMOVE_HOME", remove_home.as_str())]).await?;
+    Ok(())
+}
+
+#[cfg(windows)]
+async fn set_groups(user: &GuestUser, groups: &[String]) -> Result<(), String> {
+    let script = r#"
+        $want = @($env:QX_GROUPS -split "`n" | Where-Object { $_ })
+        foreach ($g in $want) { Get-LocalGroup -Name $g | Out-Null }
+        foreach ($g in Get-LocalGroup) {
+            $member = Get-LocalGroupMember -Group $g -ErrorAction SilentlyContinue | Where-Object { $_.SID.Value -eq $env:QX_SID }
+            if ($member -and $want -notcontains $g.Name) {
+                Remove-LocalGroupMember -Group $g -Member $env:QX_SID
+            } elseif (-not $member -and $want -contains $g.Name) {
+                Add-LocalGroupMember -Group $g -Member $env:QX_SID
+            }
+        }
+    "#;
+    let groups = groups.join("\n");
+    powershell(script, &[("QX_SID", user.id.as_str()), ("QX_GROUPS", groups.as_str())]).await?;
+    Ok(())
+}
+
+#[cfg(windows)]
+async fn set_locked(user: &GuestUser, locked: bool) -> Result<(), String> {
+    let script = if locked {
+        "Disable-LocalUser -SID $env:QX_SID"
+    } else {
+        "Enable-LocalUser -SID $env:QX_SID"
+    };
+    powershell(script, &[("QX_SID", user.id.as_str())]).await?;
+    Ok(())
+}
+
+#[cfg(windows)]
+async fn update_authorized_keys(user: &GuestUser, key: &str, add: bool) -> Result<(), String> {
+    let admin = powershell(
+        "@(Get-LocalGroupMember -SID 'S-1-5-32-544' | Where-Object { $_.SID.Value -eq $env:QX_SID }).Count",
+        &[("QX_SID", user.id.as_str())],
+    )
+    .await?
+    .trim()
+        != "0";
+    let path = windows_authorized_keys_path(&user.home, admin);
+
+    let existed = path.exists();
+    let content = tokio::fs::read_to_string(&path).await.unwrap_or_default();
+    let Some(updated) = edit_authorized_keys(&content, key, add)? else {
+        return Ok(());
+    };
+
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    tokio::fs::write(&path, updated)
+        .await
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+
+    // sshd ignores the shared file unless only Administrators and SYSTEM can write it
+    if admin && !existed {
+        let path = path.to_string_lossy().to_string();
+        run(
+            "icacls",
+            &[&path, "/inheritance:r", "/grant", "*S-1-5-32-544:F", "/grant", "*S-1-5-18:F"],
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+async fn list_sessions() -> Result<Vec<Session>, String> {
+    // quser exits with 1 when nobody is logged on
+    let output = Command::new("quser")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run quser: {}", e))?;
+    Ok(parse_quser(&String::from_utf8_lossy(&output.stdout))
+        .into_iter()
+        .map(|info| Session { info, logind: false })
+        .collect())
+}
+
+#[cfg(windows)]
+async fn end_session(session: &Session) -> Result<(), String> {
+    run("logoff", &[&session.info.session_id]).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAIOMqqnkVzrm0SdG6UOoqKLsabgH5C9okWi0dh2l9GKJl alice@laptop";
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("alice").is_ok());
+        assert!(validate_name("svc_backup-01").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("-rf").is_err());
+        assert!(validate_name("alice bob").is_err());
+        assert!(validate_name("../etc").is_err());
+    }
+
+    #[test]
+    fn test_ssh_key_parsing() {
+        assert!(validate_ssh_key(KEY).is_ok());
+        assert!(validate_ssh_key("not a key").is_err());
+        assert!(validate_ssh_key(&format!("{}\nssh-rsa AAAA", KEY)).is_err());
+        assert_eq!(
+            parse_ssh_key(&format!("from=\"10.0.0.0/8\",no-pty {}", KEY)).map(|k| k.0),
+            Some("ssh-ed25519")
+        );
+        assert_eq!(ssh_key_summary(KEY), "ssh-ed25519 alice@laptop");
+    }
+
+    #[tokio::test]
+    async fn test_password_line_breaks_rejected() {
+        assert!(validate_password("").is_ok());
+        assert!(validate_password("correct horse: battery").is_ok());
+        assert!(validate_password("pw\r").is_err());
+        assert!(validate_password("pw\0").is_err());
+
+        // Rejected before the account is looked up or created
+        let req = UserAccountRequest {
+            username: "alice".to_string(),
+            password: "secret\nroot:owned".to_string(),
+            ..Default::default()
+        };
+        let err = user_account(&req, UserAccountAction::Create).await.unwrap_err();
+        assert!(err.contains("line breaks"), "{}", err);
+    }
+
+    #[test]
+    fn test_authorized_keys_editing() {
+        let existing = "# managed\nssh-rsa AAAAB3NzaC1yc2E bob@desk";
+        let added = add_authorized_key(existing, KEY).unwrap();
+        assert_eq!(authorized_keys(&added).len(), 2);
+        assert!(added.ends_with("alice@laptop\n"));
+
+        // Same key with another comment or options is already present
+        let renamed = KEY.replace("alice@laptop", "alice@new");
+        assert_eq!(add_authorized_key(&added, &renamed), None);
+
+        let removed = remove_authorized_key(&added, &renamed).unwrap();
+        assert_eq!(removed, "# managed\nssh-rsa AAAAB3NzaC1yc2E bob@desk\n");
+        assert_eq!(remove_authorized_key(&removed, KEY), None);
+        assert!(edit_authorized_keys(&removed, KEY, false).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_authorized_keys_refuse_links() {
+        let home = tempfile::tempdir().unwrap();
+        let mut account = nix::unistd::User::from_uid(nix::unistd::getuid()).unwrap().unwrap();
+        account.dir = home.path().to_path_buf();
+
+        write_authorized_keys(&account, KEY, true).unwrap();
+        let path = home.path().join(".ssh/authorized_keys");
+        assert_eq!(authorized_keys(&std::fs::read_to_string(&path).unwrap()).len(), 1);
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(std::fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+
+        // A symlinked (or hard linked) authorized_keys is neither read nor replaced
+        let target = home.path().join("target");
+        std::fs::write(&target, "secret\n").unwrap();
+        std::fs::remove_file(&path).unwrap();
+        std::os::unix::fs::symlink(&target, &path).unwrap();
+        assert!(write_authorized_keys(&account, KEY, false).is_err());
+        std::fs::remove_file(&path).unwrap();
+        std::fs::hard_link(&target, &path).unwrap();
+        assert!(write_authorized_keys(&account, KEY, true).is_err());
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "secret\n");
+
+        // So is a symlinked .ssh
+        std::fs::remove_dir_all(home.path().join(".ssh")).unwrap();
+        std::os::unix::fs::symlink(home.path().join("elsewhere"), home.path().join(".ssh")).unwrap();
+        assert!(write_authorized_keys(&account, KEY, true).is_err());
+    }
+
+    #[test]
+    fn test_parse_passwd_and_groups() {
+        let passwd = "root:x:0:0:root:/root:/bin/bash\n\
+                      alice:x:1001:1001:Alice Smith,,,:/home/alice:/bin/zsh\n\
+                      broken line\n";
+        let group = "root:x:0:\nalice:x:1001:\nsudo:x:27:alice,bob\ndocker:x:998:bob\n";
+
+        let users = parse_passwd(passwd);
+        assert_eq!(users.len(), 2);
+        assert_eq!(users[1].gecos, "Alice Smith");
+        assert_eq!(users[1].shell, "/bin/zsh");
+        assert_eq!(user_groups(&users[1], &parse_group(group)), vec!["alice", "sudo"]);
+    }
+
+    #[test]
+    fn test_shadow_locked() {
+        let today = 20_000;
+        assert!(!shadow_locked("alice:$6$salt$hash:19000:0:99999:7:::", today));
+        assert!(shadow_locked("alice:!$6$salt$hash:19000:0:99999:7:::", today));
+        assert!(!shadow_locked("alice:!!:19000:0:99999:7:::", today));
+        assert!(shadow_locked("alice:$6$salt$hash:19000:0:99999:7::1:", today));
+        assert!(!shadow_locked("alice:$6$salt$hash:19000:0:99999:7::30000:", today));
+        assert_eq!(parse_uid_min("# comment\nUID_MIN\t\t\t 500\nUID_MAX 60000\n"), Some(500));
+    }
+
+    #[test]
+    fn test_parse_sessions() {
+        let session = parse_loginctl_session(
+            "7",
+            "Name=alice\nTTY=pts/1\nRemoteHost=10.0.0.5\nTimestamp=Sun 2026-10-18 09:12:33 UTC\nState=active\n",
+        );
+        assert_eq!(session.username, "alice");
+        assert_eq!(session.tty, "pts/1");
+        assert_eq!(session.remote_host, "10.0.0.5");
+        assert_eq!(session.login_time.map(|t| t.seconds), Some(1_792_314_753));
+
+        let who = parse_who("alice    pts/0        2026-10-18 09:12 (10.0.0.5)\nbob      tty1         2026-10-18 08:00\n");
+        assert_eq!(who.len(), 2);
+        assert_eq!(who[0].session_id, "pts/0");
+        assert_eq!(who[0].remote_host, "10.0.0.5");
+        assert!(who[1].login_time.is_some());
+
+        let quser = parse_quser(
+            " USERNAME              SESSIONNAME        ID  STATE   IDLE TIME  LOGON TIME\n\
+             >administrator         console             1  Active      none   10/18/2026 9:12 AM\n \
+             alice                                     2  Disc          5   10/18/2026 8:00 AM\n",
+        );
+        assert_eq!(quser.len(), 2);
+        assert_eq!((quser[0].session_id.as_str(), quser[0].tty.as_str()), ("1", "console"));
+        assert_eq!((quser[1].username.as_str(), quser[1].state.as_str()), ("alice", "Disc"));
+    }
+}
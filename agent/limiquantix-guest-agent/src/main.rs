@@ -6,6 +6,8 @@
 //! ## Features
 //! - **Telemetry**: Report real RAM/Disk usage, CPU, network interfaces
 //! - **Execution**: Run scripts/commands inside the VM
+//! - **Sessions**: Interactive PTY shells and streaming exec
+//! - **Port Forwarding**: Multiplexed TCP streams to guest services
 //! - **File Transfer**: Push/Pull files without SSH
 //! - **Lifecycle**: Clean shutdown, password reset, IP reporting
 //! - **Desktop Integration**: Display resize, clipboard sharing
@@ -180,12 +182,24 @@ fn get_capabilities() -> Vec<String> {
         "hardware_info".to_string(),
         "software_list".to_string(),
         "self_update".to_string(),
+        "session".to_string(),
+        "port_forward".to_string(),
+        "signed_update".to_string(),
+        "tree_transfer".to_string(),
+        "grow_filesystems".to_string(),
+        "trim_filesystems".to_string(),
+        "app_quiesce".to_string(),
+        "user_management".to_string(),
+        "os_patching".to_string(),
     ];
 
     // Platform-specific capabilities
     #[cfg(unix)]
     caps.push("user_context_exec".to_string());
 
+    #[cfg(unix)]
+    caps.push("pty_session".to_string());
+
     #[cfg(windows)]
     caps.push("vss_quiesce".to_string());
 
@@ -194,6 +208,12 @@ fn get_capabilities() -> Vec<String> {
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    // Used to check an update before it is installed
+    if std::env::args().nth(1).as_deref() == Some("--version") {
+        println!("quantix-kvm-agent {}", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    }
+
     // Load configuration first (before logging init)
     let config = AgentConfig::load();
 
@@ -214,6 +234,9 @@ async fn main() -> Result<()> {
 
     debug!(?config, "Configuration loaded");
 
+    // Roll back an update that keeps failing, or start its health deadline
+    handlers::check_pending_update();
+
     // Initialize health state ONCE (persists across reconnections)
     let health = Arc::new(HealthState::new());
 
@@ -287,7 +310,8 @@ async fn main() -> Result<()> {
         // 2. Setup resources for this connection
         let (reader, writer) = transport.split();
         let writer = Arc::new(Mutex::new(writer));
-        let handler = Arc::new(MessageHandler::new(config.clone()));
+        let (events_tx, events_rx) = tokio::sync::mpsc::channel(256);
+        let handler = Arc::new(MessageHandler::new(config.clone(), events_tx));
         let telemetry = TelemetryCollector::new();
 
         // 3. Send AgentReady event (handshake)
@@ -306,6 +330,7 @@ async fn main() -> Result<()> {
         
         let read_handler = handler.clone();
         let read_writer = writer.clone();
+        let events_writer = writer.clone();
         let read_health = health.clone();
         let shutdown_check = shutdown_requested.clone();
 
@@ -328,7 +353,15 @@ async fn main() -> Result<()> {
                 res.map(|_| "read_loop")
             }
 
-            // Task C: Periodic shutdown check (every 5 seconds)
+            // Task C: Session output (unsolicited messages from handlers)
+            res = forward_events(events_rx, events_writer) => {
+                if let Err(ref e) = res {
+                    error!(error = %e, "Failed to send session output - triggering reconnect");
+                }
+                res.map(|_| "events")
+            }
+
+            // Task D: Periodic shutdown check (every 5 seconds)
             _ = async {
                 loop {
                     tokio::time::sleep(Duration::from_secs(5)).await;
@@ -343,7 +376,9 @@ async fn main() -> Result<()> {
         };
 
         // 5. Connection ended - either failure or shutdown
-        // Resources (transport, writer, reader) are automatically dropped here
+        // Resources (transport, writer, reader) are automatically dropped here,
+        // interactive sessions must not outlive the connection
+        handler.close_sessions().await;
         
         match connection_result {
             Ok("shutdown") => {
@@ -649,6 +684,15 @@ where
 
                 health.record_message_processed();
 
+                // Stream frames must keep their order, handle them here
+                if MessageHandler::is_ordered(&message) {
+                    if let Err(e) = handler.handle(message).await {
+                        error!(error = %e, message_id = %msg_id, "Failed to handle message");
+                        health.record_error();
+                    }
+                    continue;
+                }
+
                 // Handle the message
                 let handler = handler.clone();
                 let writer = writer.clone();
@@ -658,9 +702,13 @@ where
                     match handler.handle(message).await {
                         Ok(Some(response)) => {
                             let mut guard = writer.lock().await;
-                            if let Err(e) = write_message(&mut *guard, &response).await {
-                                error!(error = %e, message_id = %msg_id, "Failed to send response");
-                                health.record_error();
+                            match write_message(&mut *guard, &response).await {
+                                // Answering the host is what confirms an update
+                                Ok(()) => handlers::confirm_update(),
+                                Err(e) => {
+                                    error!(error = %e, message_id = %msg_id, "Failed to send response");
+                                    health.record_error();
+                                }
                             }
                         }
                         Ok(None) => {
@@ -688,6 +736,18 @@ where
     }
 }
 
+/// Send unsolicited messages (session output) to the host.
+async fn forward_events<W: AsyncWriteExt + Unpin>(
+    mut events: tokio::sync::mpsc::Receiver<limiquantix_proto::agent::AgentMessage>,
+    writer: Arc<Mutex<W>>,
+) -> Result<()> {
+    while let Some(message) = events.recv().await {
+        let mut guard = writer.lock().await;
+        write_message(&mut *guard, &message).await?;
+    }
+    Ok(())
+}
+
 /// Wait for shutdown signal (SIGTERM, SIGINT, or Ctrl+C)
 async fn wait_for_shutdown_signal() -> Result<()> {
     #[cfg(unix)]
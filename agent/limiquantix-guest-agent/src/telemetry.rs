@@ -192,6 +192,9 @@ impl TelemetryCollector {
         // Hostname
         let hostname = System::host_name().unwrap_or_else(|| "unknown".to_string());
 
+        // Pending reboot after updates (cached, cheap)
+        let reboot_required = crate::handlers::reboot_required();
+
         debug!(
             cpu_usage = cpu_usage,
             memory_used = memory_used,
@@ -216,6 +219,7 @@ impl TelemetryCollector {
             process_count,
             uptime_seconds: uptime,
             hostname,
+            reboot_required,
         }
     }
 
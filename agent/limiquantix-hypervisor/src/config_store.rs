@@ -0,0 +1,392 @@
+//! Persistent store for VM configurations.
+//!
+//! Libvirt keeps the domain XML, but not the `VmConfig` it was generated
+//! from. Operations that need to re-create a VM from its configuration
+//! (templates, clones) read it from this store instead of reverse-parsing
+//! the XML. Configurations are stored as one JSON file per VM.
+//!
+//! VMs defined before the store existed have no stored configuration; it is
+//! backfilled from their domain XML (`config_from_domain_xml`). After a
+//! device change, `refresh_from_domain` re-reads the devices from the XML
+//! while keeping what the XML cannot express (guest OS, disk sizes, IDs).
+
+use std::path::{Path, PathBuf};
+
+use tracing::{debug, warn};
+
+use serde::de::DeserializeOwned;
+
+use crate::error::{HypervisorError, Result};
+use crate::types::{
+    BootDevice, CdromConfig, DiskConfig, Firmware, NicConfig, NicModel, VmConfig,
+};
+use crate::xml_tree::{parse_tree, Element};
+
+/// Default directory for stored VM configurations.
+pub const DEFAULT_CONFIG_STORE_PATH: &str = "/var/lib/limiquantix/vm-configs";
+
+/// JSON file store of `VmConfig`s keyed by VM ID.
+#[derive(Debug, Clone)]
+pub struct VmConfigStore {
+    dir: PathBuf,
+}
+
+impl VmConfigStore {
+    /// Create a store rooted at `dir` (created on first write).
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+    
+    /// Get the store directory.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+    
+    fn path(&self, vm_id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", vm_id))
+    }
+    
+    /// Save (or replace) a VM configuration.
+    pub fn save(&self, config: &VmConfig) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| HypervisorError::Internal(format!("Failed to create {}: {}", self.dir.display(), e)))?;
+        
+        let json = serde_json::to_string_pretty(config)
+            .map_err(|e| HypervisorError::Internal(format!("Failed to serialize VM config: {}", e)))?;
+        
+        // Write-then-rename so a crash never leaves a truncated config
+        let path = self.path(&config.id);
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, json)
+            .and_then(|_| std::fs::rename(&tmp, &path))
+            .map_err(|e| HypervisorError::Internal(format!("Failed to write {}: {}", path.display(), e)))?;
+        
+        debug!(vm_id = %config.id, "Stored VM config");
+        Ok(())
+    }
+    
+    /// Load a VM configuration.
+    pub fn load(&self, vm_id: &str) -> Result<VmConfig> {
+        let path = self.path(vm_id);
+        let json = std::fs::read_to_string(&path)
+            .map_err(|_| HypervisorError::VmNotFound(format!("No stored configuration for VM {}", vm_id)))?;
+        
+        serde_json::from_str(&json)
+            .map_err(|e| HypervisorError::Internal(format!("Failed to parse {}: {}", path.display(), e)))
+    }
+    
+    /// Remove a VM configuration (no-op if absent).
+    pub fn remove(&self, vm_id: &str) {
+        let path = self.path(vm_id);
+        if path.exists() {
+            if let Err(e) = std::fs::remove_file(&path) {
+                warn!(vm_id = %vm_id, error = %e, "Failed to remove stored VM config");
+            }
+        }
+    }
+}
+
+impl Default for VmConfigStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_CONFIG_STORE_PATH)
+    }
+}
+
+/// Map a libvirt attribute value to one of our lowercase-serialized enums.
+fn parse_enum<T: DeserializeOwned>(value: Option<&str>) -> Option<T> {
+    serde_json::from_value(serde_json::Value::String(value?.to_string())).ok()
+}
+
+/// Build a `VmConfig` from libvirt domain XML.
+///
+/// Covers what `DomainXmlBuilder` emits: CPU topology and model, memory,
+/// firmware and boot order, file disks, CD-ROMs and bridge/network/OVS
+/// interfaces. Disk IDs are the target device names and disk sizes are 0,
+/// since the XML carries neither.
+pub fn config_from_domain_xml(xml: &str) -> Result<VmConfig> {
+    let domain = parse_tree(xml)?;
+    if domain.name != "domain" {
+        return Err(HypervisorError::XmlError(format!("Expected <domain>, found <{}>", domain.name)));
+    }
+    
+    let mut config = VmConfig::new(domain.child_text("name").unwrap_or_default())
+        .with_id(domain.child_text("uuid").unwrap_or_default());
+    
+    // Memory (KiB unless a unit is given)
+    if let Some(memory) = domain.child("memory") {
+        let value: u64 = memory.text.trim().parse().unwrap_or(0);
+        let bytes = match memory.attr("unit").unwrap_or("KiB") {
+            "b" | "bytes" => value,
+            "KiB" | "k" => value * 1024,
+            "MiB" | "M" => value * 1024 * 1024,
+            "GiB" | "G" => value * 1024 * 1024 * 1024,
+            _ => value * 1024,
+        };
+        config.memory.size_mib = bytes / (1024 * 1024);
+    }
+    
+    // CPU topology, falling back to one socket with <vcpu> cores
+    let vcpus: u32 = domain.child_text("vcpu").and_then(|v| v.parse().ok()).unwrap_or(1);
+    config.cpu.sockets = 1;
+    config.cpu.cores = vcpus;
+    config.cpu.threads_per_core = 1;
+    if let Some(cpu) = domain.child("cpu") {
+        if let Some(topology) = cpu.child("topology") {
+            let count = |name| topology.attr(name).and_then(|v| v.parse().ok()).filter(|n: &u32| *n > 0);
+            config.cpu.sockets = count("sockets").unwrap_or(1);
+            config.cpu.cores = count("cores").unwrap_or(vcpus);
+            config.cpu.threads_per_core = count("threads").unwrap_or(1);
+        }
+        config.cpu.model = match cpu.attr("mode") {
+            Some("host-passthrough") | None => None,
+            Some("host-model") => Some("host-model".to_string()),
+            Some("maximum") => Some("max".to_string()),
+            Some(_) => cpu.child_text("model").map(str::to_string),
+        };
+    }
+    
+    // Firmware and boot order
+    if let Some(os) = domain.child("os") {
+        let uefi = os.attr("firmware") == Some("efi") || os.child("loader").is_some();
+        config.boot.firmware = if uefi { Firmware::Uefi } else { Firmware::Bios };
+        config.boot.secure_boot = os.child("loader").and_then(|l| l.attr("secure")) == Some("yes");
+        
+        let order: Vec<BootDevice> = os.children("boot")
+            .filter_map(|b| match b.attr("dev") {
+                Some("hd") => Some(BootDevice::Disk),
+                Some("cdrom") => Some(BootDevice::Cdrom),
+                Some("network") => Some(BootDevice::Network),
+                _ => None,
+            })
+            .collect();
+        if !order.is_empty() {
+            config.boot.order = order;
+        }
+    }
+    
+    let Some(devices) = domain.child("devices") else {
+        return Ok(config);
+    };
+    
+    for disk in devices.children("disk") {
+        let source = disk.child("source").and_then(|s| s.attr("file").or(s.attr("dev")));
+        let target = disk.child("target");
+        let dev = target.and_then(|t| t.attr("dev")).unwrap_or_default();
+        
+        match disk.attr("device") {
+            Some("cdrom") => config.cdroms.push(CdromConfig {
+                id: dev.to_string(),
+                iso_path: source.map(str::to_string),
+                bootable: false,
+            }),
+            Some("disk") | None => {
+                let Some(path) = source else {
+                    continue;
+                };
+                let driver = disk.child("driver");
+                let mut config_disk = DiskConfig {
+                    id: dev.to_string(),
+                    path: path.to_string(),
+                    size_gib: 0,
+                    bootable: config.disks.is_empty(),
+                    readonly: disk.child("readonly").is_some(),
+                    backing_file: disk.child("backingStore")
+                        .and_then(|b| b.child("source"))
+                        .and_then(|s| s.attr("file"))
+                        .map(str::to_string),
+                    ..Default::default()
+                };
+                if let Some(bus) = parse_enum(target.and_then(|t| t.attr("bus"))) {
+                    config_disk.bus = bus;
+                }
+                if let Some(driver) = driver {
+                    if let Some(format) = parse_enum(driver.attr("type")) {
+                        config_disk.format = format;
+                    }
+                    if let Some(cache) = parse_enum(driver.attr("cache")) {
+                        config_disk.cache = cache;
+                    }
+                    if let Some(io_mode) = parse_enum(driver.attr("io")) {
+                        config_disk.io_mode = io_mode;
+                    }
+                    if let Some(discard) = parse_enum(driver.attr("discard")) {
+                        config_disk.discard = discard;
+                    }
+                    if let Some(detect_zeroes) = parse_enum(driver.attr("detect_zeroes")) {
+                        config_disk.detect_zeroes = detect_zeroes;
+                    }
+                }
+                config.disks.push(config_disk);
+            }
+            Some(_) => {}
+        }
+    }
+    
+    for interface in devices.children("interface") {
+        config.nics.push(nic_from_interface(interface));
+    }
+    
+    Ok(config)
+}
+
+fn nic_from_interface(interface: &Element) -> NicConfig {
+    let source = interface.child("source");
+    let bridge = source.and_then(|s| s.attr("bridge")).map(str::to_string);
+    let network = source.and_then(|s| s.attr("network")).map(str::to_string);
+    let mac_address = interface.child("mac").and_then(|m| m.attr("address")).map(str::to_string);
+    let model = parse_enum(interface.child("model").and_then(|m| m.attr("type"))).unwrap_or(NicModel::Virtio);
+    
+    let ovn_port_name = interface.child("virtualport")
+        .filter(|v| v.attr("type") == Some("openvswitch"))
+        .and_then(|v| v.child("parameters"))
+        .and_then(|p| p.attr("interfaceid"))
+        .map(str::to_string);
+    
+    if ovn_port_name.is_some() {
+        return NicConfig {
+            id: mac_address.clone().unwrap_or_default(),
+            mac_address,
+            bridge: None,
+            network: None,
+            model,
+            ovn_port_name,
+            ovs_bridge: bridge,
+        };
+    }
+    
+    NicConfig {
+        id: mac_address.clone().unwrap_or_default(),
+        mac_address,
+        bridge,
+        network: if interface.attr("type") == Some("network") { network } else { None },
+        model,
+        ovn_port_name: None,
+        ovs_bridge: None,
+    }
+}
+
+/// Update a stored configuration with the devices of the live domain.
+///
+/// Hardware comes from `live`; for disks (matched by path), CD-ROMs (by
+/// position) and NICs (by MAC) the stored IDs and the fields the XML does
+/// not carry are kept.
+pub fn refresh_from_domain(stored: &VmConfig, live: VmConfig) -> VmConfig {
+    let mut config = stored.clone();
+    config.cpu = live.cpu;
+    config.memory.size_mib = live.memory.size_mib;
+    config.boot = live.boot;
+    
+    config.disks = live.disks.into_iter().map(|disk| {
+        match stored.disks.iter().find(|d| d.path == disk.path) {
+            Some(known) => DiskConfig {
+                bus: disk.bus,
+                format: disk.format,
+                readonly: disk.readonly,
+                cache: disk.cache,
+                io_mode: disk.io_mode,
+                discard: disk.discard,
+                detect_zeroes: disk.detect_zeroes,
+                ..known.clone()
+            },
+            None => disk,
+        }
+    }).collect();
+    
+    config.cdroms = live.cdroms.into_iter().enumerate().map(|(i, cdrom)| {
+        match stored.cdroms.get(i) {
+            Some(known) => CdromConfig { iso_path: cdrom.iso_path, ..known.clone() },
+            None => cdrom,
+        }
+    }).collect();
+    
+    config.nics = live.nics.into_iter().map(|nic| {
+        let known = stored.nics.iter()
+            .find(|n| n.mac_address.is_some() && n.mac_address == nic.mac_address);
+        match known {
+            Some(known) => NicConfig { model: nic.model, ..known.clone() },
+            None => nic,
+        }
+    }).collect();
+    
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_save_load_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = VmConfigStore::new(dir.path());
+        
+        let config = VmConfig::new("web-01").with_id("vm-1");
+        store.save(&config).unwrap();
+        
+        let loaded = store.load("vm-1").unwrap();
+        assert_eq!(loaded.name, "web-01");
+        
+        store.remove("vm-1");
+        assert!(matches!(store.load("vm-1"), Err(HypervisorError::VmNotFound(_))));
+    }
+    
+    #[test]
+    fn test_config_round_trips_through_domain_xml() {
+        use crate::types::{DiskBus, DiskDiscard, DiskFormat};
+        use crate::xml::DomainXmlBuilder;
+        
+        let mut config = VmConfig::new("web-01").with_id("11111111-2222-3333-4444-555555555555");
+        config.cpu.sockets = 2;
+        config.cpu.cores = 4;
+        config.memory.size_mib = 8192;
+        config.disks.push(DiskConfig {
+            id: "root".to_string(),
+            path: "/var/lib/limiquantix/vms/web-01/root.qcow2".to_string(),
+            size_gib: 40,
+            bus: DiskBus::Scsi,
+            discard: DiskDiscard::Unmap,
+            ..Default::default()
+        });
+        config.cdroms.push(CdromConfig {
+            iso_path: Some("/isos/ubuntu.iso".to_string()),
+            ..Default::default()
+        });
+        config.nics.push(NicConfig {
+            mac_address: Some("52:54:00:aa:bb:cc".to_string()),
+            bridge: Some("br0".to_string()),
+            ..Default::default()
+        });
+        config.nics.push(NicConfig {
+            mac_address: Some("52:54:00:aa:bb:cd".to_string()),
+            ovn_port_name: Some("lsp-1".to_string()),
+            ..Default::default()
+        });
+        
+        let parsed = config_from_domain_xml(&DomainXmlBuilder::new(&config).build()).unwrap();
+        assert_eq!(parsed.id, config.id);
+        assert_eq!(parsed.name, "web-01");
+        assert_eq!(parsed.cpu.total_vcpus(), 8);
+        assert_eq!(parsed.memory.size_mib, 8192);
+        assert_eq!(parsed.disks.len(), 1);
+        assert_eq!(parsed.disks[0].path, config.disks[0].path);
+        assert_eq!(parsed.disks[0].bus, DiskBus::Scsi);
+        assert_eq!(parsed.disks[0].format, DiskFormat::Qcow2);
+        assert_eq!(parsed.disks[0].discard, DiskDiscard::Unmap);
+        assert_eq!(parsed.cdroms[0].iso_path.as_deref(), Some("/isos/ubuntu.iso"));
+        assert_eq!(parsed.nics[0].bridge.as_deref(), Some("br0"));
+        assert_eq!(parsed.nics[1].ovn_port_name.as_deref(), Some("lsp-1"));
+        assert_eq!(parsed.nics[1].ovs_bridge.as_deref(), Some("br-int"));
+        
+        // Refreshing keeps stored IDs and sizes, and picks up device changes
+        let mut live = parsed;
+        live.cdroms[0].iso_path = None;
+        live.nics.remove(0);
+        let refreshed = refresh_from_domain(&config, live);
+        assert_eq!(refreshed.disks[0].id, "root");
+        assert_eq!(refreshed.disks[0].size_gib, 40);
+        assert_eq!(refreshed.cdroms[0].id, config.cdroms[0].id);
+        assert_eq!(refreshed.cdroms[0].iso_path, None);
+        assert_eq!(refreshed.nics.len(), 1);
+        assert_eq!(refreshed.nics[0].id, config.nics[1].id);
+    }
+}
@@ -22,6 +22,10 @@
 
 use serde::{Deserialize, Serialize};
 
+use crate::types::{
+    CrashAction, PanicConfig, PanicModel, RngConfig, WatchdogAction, WatchdogConfig, WatchdogModel,
+};
+
 /// Guest OS Family - major categories with distinct hardware requirements.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 #[serde(rename_all = "snake_case")]
@@ -358,6 +362,43 @@ pub struct PlatformRequirements {
     pub min_vcpus: u32,
 }
 
+/// Default platform devices (watchdog, RNG, panic notifier).
+///
+/// These apply unless the VM config overrides them explicitly.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct DeviceProfile {
+    /// Watchdog device (needs a guest driver to be useful).
+    pub watchdog: Option<WatchdogConfig>,
+    
+    /// Virtio RNG device (feeds guest entropy pool at boot).
+    pub rng: Option<RngConfig>,
+    
+    /// Guest panic notifier.
+    pub panic: Option<PanicConfig>,
+}
+
+impl DeviceProfile {
+    /// Linux guests: i6300esb watchdog, virtio-rng and ISA pvpanic.
+    /// A hung or panicked kernel is reset instead of sitting frozen.
+    fn linux() -> Self {
+        Self {
+            watchdog: Some(WatchdogConfig::new(WatchdogModel::I6300esb, WatchdogAction::Reset)),
+            rng: Some(RngConfig::default()),
+            panic: Some(PanicConfig::new(PanicModel::Isa, CrashAction::Reset)),
+        }
+    }
+    
+    /// Windows guests: no inbox i6300esb driver, so no watchdog.
+    /// Crashes are reported through the Hyper-V crash MSRs.
+    fn windows() -> Self {
+        Self {
+            watchdog: None,
+            rng: Some(RngConfig::default()),
+            panic: Some(PanicConfig::new(PanicModel::Hyperv, CrashAction::Reset)),
+        }
+    }
+}
+
 /// Complete Guest OS Profile with all settings.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GuestOSProfile {
@@ -385,6 +426,10 @@ pub struct GuestOSProfile {
     /// Platform requirements.
     pub platform: PlatformRequirements,
     
+    /// Default platform devices.
+    #[serde(default)]
+    pub devices: DeviceProfile,
+    
     /// Machine type (q35 recommended for modern OSes).
     pub machine_type: String,
     
@@ -448,6 +493,7 @@ impl GuestOSProfile {
                 min_memory_mib: 2048,
                 min_vcpus: 2,
             },
+            devices: DeviceProfile::linux(),
             machine_type: "q35".to_string(),
             firmware: "bios".to_string(),  // UEFI optional
         }
@@ -486,6 +532,7 @@ impl GuestOSProfile {
                 default_model: "virtio".to_string(),
             },
             platform: PlatformRequirements::default(),
+            devices: DeviceProfile::linux(),
             machine_type: "q35".to_string(),
             firmware: "bios".to_string(),
         }
@@ -559,6 +606,7 @@ impl GuestOSProfile {
                 min_memory_mib: 2048,
                 min_vcpus: 2,
             },
+            devices: DeviceProfile::windows(),
             machine_type: "q35".to_string(),
             firmware: "uefi".to_string(),
         }
@@ -612,6 +660,11 @@ impl GuestOSProfile {
                 default_model: "e1000".to_string(),  // Legacy Intel
             },
             platform: PlatformRequirements::default(),
+            devices: DeviceProfile {
+                watchdog: None,
+                rng: None,  // No virtio drivers on legacy Windows
+                panic: None,
+            },
             machine_type: "q35".to_string(),
             firmware: "bios".to_string(),  // Legacy Windows often doesn't support UEFI
         }
@@ -649,6 +702,11 @@ impl GuestOSProfile {
                 default_model: "virtio".to_string(),
             },
             platform: PlatformRequirements::default(),
+            devices: DeviceProfile {
+                watchdog: Some(WatchdogConfig::new(WatchdogModel::I6300esb, WatchdogAction::Reset)),  // ichwd(4)
+                rng: Some(RngConfig::default()),
+                panic: None,  // No pvpanic driver
+            },
             machine_type: "q35".to_string(),
             firmware: "bios".to_string(),
         }
@@ -660,6 +718,7 @@ impl GuestOSProfile {
         profile.family = family;
         // OpenBSD is more conservative
         profile.disk.default_bus = "scsi".to_string();
+        profile.devices.watchdog = None;
         profile
     }
     
@@ -695,6 +754,7 @@ impl GuestOSProfile {
                 default_model: "virtio".to_string(),
             },
             platform: PlatformRequirements::default(),
+            devices: DeviceProfile::linux(),
             machine_type: "q35".to_string(),
             firmware: "bios".to_string(),
         }
@@ -736,6 +796,18 @@ mod tests {
         assert_eq!(GuestOSFamily::from_str("win11"), GuestOSFamily::WindowsDesktop);
     }
     
+    #[test]
+    fn test_device_defaults() {
+        let linux = GuestOSProfile::for_family(GuestOSFamily::Rhel);
+        assert_eq!(linux.devices.watchdog.as_ref().map(|w| w.action), Some(WatchdogAction::Reset));
+        assert_eq!(linux.devices.panic.as_ref().map(|p| p.model), Some(PanicModel::Isa));
+        assert!(linux.devices.rng.is_some());
+        
+        let windows = GuestOSProfile::for_family(GuestOSFamily::WindowsServer);
+        assert!(windows.devices.watchdog.is_none(), "Windows has no i6300esb driver");
+        assert_eq!(windows.devices.panic.as_ref().map(|p| p.model), Some(PanicModel::Hyperv));
+    }
+    
     #[test]
     fn test_debian_hpet_enabled() {
         let profile = GuestOSProfile::for_family(GuestOSFamily::Debian);
@@ -49,7 +49,11 @@ pub mod storage;
 pub mod network;
 pub mod cloudinit;
 pub mod guest_os;
+pub mod config_store;
+pub mod template;
+pub mod ovf;
 mod xml;
+mod xml_tree;
 
 pub use error::HypervisorError;
 pub use traits::{Hypervisor, HypervisorCapabilities};
@@ -79,8 +83,30 @@ pub use network::{
     NetworkPortBindingType,
     NetworkPortQoS,
     OvsStatus,
+    HostNetworkConfig,
+    NetworkChange,
+    NetworkTransaction,
+    SecurityGroup,
+    SecurityGroupRule,
+    PortFilter,
+    DhcpConfig,
+    DhcpLease,
+    StaticLease,
+    CaptureOptions,
+    MirrorDirection,
+    MirrorSession,
+    MacAllocation,
+    MacAllocator,
+    MacConflict,
+    MacPoolConfig,
+    HostFirewallConfig,
+    ManagementService,
+    ServiceRule,
 };
 pub use cloudinit::{CloudInitConfig, CloudInitGenerator};
+pub use config_store::{config_from_domain_xml, refresh_from_domain, VmConfigStore};
+pub use template::{CloneOptions, CloneType, TemplateInfo, TemplateManager};
+pub use ovf::{OvaImportOptions, OvfDescriptor};
 
 // Re-export libvirt backend when available
 #[cfg(feature = "libvirt")]
@@ -932,6 +932,22 @@ impl Hypervisor for LibvirtBackend {
         Ok(())
     }
     
+    #[instrument(skip(self), fields(vm_id = %vm_id, disk = %disk))]
+    async fn resize_disk(&self, vm_id: &str, disk: &str, new_size_bytes: u64) -> Result<()> {
+        info!(new_size = new_size_bytes, "Resizing disk of running VM");
+        
+        let domain = self.get_domain(vm_id)?;
+        
+        // virDomainBlockResize grows the image through QEMU (the image is
+        // locked by the running VM) and raises a capacity change in the guest.
+        // BYTES: size is in bytes rather than KiB.
+        domain.block_resize(disk, new_size_bytes, sys::VIR_DOMAIN_BLOCK_RESIZE_BYTES)
+            .map_err(|e| HypervisorError::OperationFailed(format!("Failed to resize disk: {}", e)))?;
+        
+        info!("Disk resized");
+        Ok(())
+    }
+    
     #[instrument(skip(self), fields(vm_id = %vm_id, target = %target_uri))]
     async fn migrate_vm(&self, vm_id: &str, target_uri: &str, live: bool) -> Result<()> {
         info!(live = live, "Migrating VM");
@@ -1084,6 +1100,14 @@ fn parse_disks_from_xml(xml: &str) -> Vec<DiskConfig> {
                 bootable: false, // Hard to tell from just disk block
                 cache: DiskCache::None, // Default
                 io_mode: DiskIoMode::Native, // Default
+                discard: if part.contains("discard='unmap'") { DiskDiscard::Unmap } else { DiskDiscard::Ignore },
+                detect_zeroes: if part.contains("detect_zeroes='unmap'") {
+                    DiskDetectZeroes::Unmap
+                } else if part.contains("detect_zeroes='on'") {
+                    DiskDetectZeroes::On
+                } else {
+                    DiskDetectZeroes::Off
+                },
                 backing_file: None, // Would need to parse backing store from XML
             });
         }
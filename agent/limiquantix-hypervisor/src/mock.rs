@@ -524,6 +524,30 @@ impl Hypervisor for MockBackend {
         Ok(())
     }
     
+    async fn resize_disk(&self, vm_id: &str, disk: &str, new_size_bytes: u64) -> Result<()> {
+        info!(vm_id = %vm_id, disk = %disk, new_size = new_size_bytes, "Resizing disk");
+        
+        let mut vms = self.vms.write().map_err(|_| {
+            HypervisorError::Internal("Lock poisoned".to_string())
+        })?;
+        
+        let vm = vms.get_mut(vm_id)
+            .ok_or_else(|| HypervisorError::VmNotFound(vm_id.to_string()))?;
+        
+        let config = vm.config.disks.iter_mut()
+            .find(|d| d.path == disk || d.id == disk)
+            .ok_or_else(|| HypervisorError::InvalidConfig(format!("Disk {} not attached to VM", disk)))?;
+        
+        let new_size_gib = new_size_bytes.div_ceil(1024 * 1024 * 1024);
+        if new_size_gib < config.size_gib {
+            return Err(HypervisorError::InvalidConfig("Disks can only grow".to_string()));
+        }
+        config.size_gib = new_size_gib;
+        
+        info!("Disk resized (mock)");
+        Ok(())
+    }
+    
     async fn migrate_vm(&self, vm_id: &str, target_uri: &str, live: bool) -> Result<()> {
         info!(
             vm_id = %vm_id, 
@@ -0,0 +1,178 @@
+//! Bounded packet capture on VM interfaces.
+//!
+//! Captures run `tcpdump` on a VM's tap (or OVS port, which has the same
+//! name) and stream pcap data to stdout. Every capture is bounded:
+//! - `max_duration_secs`: wall-clock limit
+//! - `max_bytes`: pcap file size limit, enforced by the caller copying
+//!   whole records from the stream (tcpdump itself can only rotate, not stop)
+//! - `max_packets`: optional packet count limit (`tcpdump -c`)
+//!
+//! BPF filters are compiled with `tcpdump -d` before the capture starts, so
+//! syntax errors are reported to the caller instead of killing the capture.
+
+use std::process::Command;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Upper bound for `max_bytes`
+pub const MAX_CAPTURE_BYTES: u64 = 1024 * 1024 * 1024;
+/// Upper bound for `max_duration_secs`
+pub const MAX_CAPTURE_SECS: u32 = 3600;
+
+/// Capture limits and filter.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureOptions {
+    /// BPF filter expression, e.g. "tcp port 443"
+    #[serde(default)]
+    pub filter: Option<String>,
+    #[serde(default = "default_max_bytes")]
+    pub max_bytes: u64,
+    #[serde(default = "default_max_duration")]
+    pub max_duration_secs: u32,
+    #[serde(default)]
+    pub max_packets: Option<u64>,
+    /// Bytes captured per packet
+    #[serde(default = "default_snaplen")]
+    pub snaplen: u32,
+}
+
+fn default_max_bytes() -> u64 {
+    50 * 1024 * 1024
+}
+
+fn default_max_duration() -> u32 {
+    60
+}
+
+fn default_snaplen() -> u32 {
+    262144
+}
+
+impl Default for CaptureOptions {
+    fn default() -> Self {
+        Self {
+            filter: None,
+            max_bytes: default_max_bytes(),
+            max_duration_secs: default_max_duration(),
+            max_packets: None,
+            snaplen: default_snaplen(),
+        }
+    }
+}
+
+impl CaptureOptions {
+    /// Check limits (the filter is checked by [`compile_filter`]).
+    pub fn validate(&self) -> Result<()> {
+        if self.max_bytes == 0 || self.max_bytes > MAX_CAPTURE_BYTES {
+            bail!("maxBytes must be between 1 and {}", MAX_CAPTURE_BYTES);
+        }
+        if self.max_duration_secs == 0 || self.max_duration_secs > MAX_CAPTURE_SECS {
+            bail!("maxDurationSecs must be between 1 and {}", MAX_CAPTURE_SECS);
+        }
+        if self.max_packets == Some(0) {
+            bail!("maxPackets must be positive");
+        }
+        if !(64..=262144).contains(&self.snaplen) {
+            bail!("snaplen must be between 64 and 262144");
+        }
+        if let Some(ref filter) = self.filter {
+            if filter.len() > 1024 || filter.contains('\0') {
+                bail!("Filter is too long or contains invalid characters");
+            }
+        }
+        Ok(())
+    }
+
+    fn filter_args(&self) -> Vec<String> {
+        self.filter.as_deref()
+            .map(str::trim)
+            .filter(|f| !f.is_empty())
+            .map(|f| vec![f.to_string()])
+            .unwrap_or_default()
+    }
+}
+
+/// Arguments for `tcpdump` capturing `iface` as pcap on stdout.
+pub fn tcpdump_args(iface: &str, options: &CaptureOptions) -> Vec<String> {
+    let mut args = vec![
+        "-i".to_string(), iface.to_string(),
+        "-w".to_string(), "-".to_string(),
+        // Keep root instead of switching to the tcpdump user after opening the tap
+        "-Z".to_string(), "root".to_string(),
+        // Flush per packet so the record stream (and partial downloads) stay current
+        "-U".to_string(),
+        "-n".to_string(),
+        "-s".to_string(), options.snaplen.to_string(),
+        // Don't put the tap into promiscuous mode; it already sees all VM traffic
+        "-p".to_string(),
+    ];
+    if let Some(count) = options.max_packets {
+        args.push("-c".to_string());
+        args.push(count.to_string());
+    }
+    // Everything after "--" is the filter, even if it starts with '-'
+    let filter = options.filter_args();
+    if !filter.is_empty() {
+        args.push("--".to_string());
+        args.extend(filter);
+    }
+    args
+}
+
+/// Compile the filter for Ethernet without capturing anything.
+pub fn compile_filter(options: &CaptureOptions) -> Result<()> {
+    let filter = options.filter_args();
+    if filter.is_empty() {
+        return Ok(());
+    }
+
+    let output = Command::new("tcpdump")
+        .args(["-d", "-y", "EN10MB", "--"])
+        .args(&filter)
+        .output()
+        .context("Failed to run tcpdump (is it installed?)")?;
+    if !output.status.success() {
+        bail!("Invalid capture filter: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tcpdump_args() {
+        let options = CaptureOptions {
+            filter: Some(" tcp port 443 ".to_string()),
+            max_packets: Some(100),
+            ..Default::default()
+        };
+        assert!(options.validate().is_ok());
+
+        let args = tcpdump_args("vnet0", &options);
+        assert_eq!(args[..6], ["-i", "vnet0", "-w", "-", "-Z", "root"]);
+        assert!(args.windows(2).any(|w| w == ["-c", "100"]));
+        assert_eq!(args[args.len() - 2..], ["--", "tcp port 443"]);
+
+        let args = tcpdump_args("vnet0", &CaptureOptions::default());
+        assert!(!args.contains(&"--".to_string()));
+    }
+
+    #[test]
+    fn test_validate_limits() {
+        let options = CaptureOptions {
+            max_duration_secs: MAX_CAPTURE_SECS + 1,
+            ..Default::default()
+        };
+        assert!(options.validate().is_err());
+
+        let options = CaptureOptions {
+            max_bytes: 0,
+            ..Default::default()
+        };
+        assert!(options.validate().is_err());
+    }
+}
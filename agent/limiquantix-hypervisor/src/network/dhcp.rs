@@ -0,0 +1,786 @@
+//! DHCPv4 server logic and IPAM for standalone host bridges.
+//!
+//! Hosts without OVN have nobody handing out addresses on their Linux
+//! bridges. This module holds the transport-independent part of a small
+//! DHCP server: packet encoding (RFC 2131/2132) and a per-bridge address
+//! pool with static and dynamic leases. The node daemon owns the sockets
+//! and persistence.
+//!
+//! - Static leases are keyed on the VM NIC MAC and always win
+//! - Dynamic leases come from `range_start..=range_end` and stick to a MAC
+//!   for as long as they are renewed (and afterwards, until the address is
+//!   needed for somebody else)
+//! - Offers are not recorded; the address is only bound on REQUEST/ACK
+
+use std::collections::BTreeMap;
+use std::net::Ipv4Addr;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::firewall::validate_mac;
+use super::host::parse_cidr;
+
+/// Server port
+pub const SERVER_PORT: u16 = 67;
+/// Client port
+pub const CLIENT_PORT: u16 = 68;
+
+/// Magic cookie preceding the options (RFC 2131 section 3)
+const MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+/// Fixed BOOTP header length, without the cookie
+const HEADER_LEN: usize = 236;
+/// Broadcast bit in `flags`
+const FLAG_BROADCAST: u16 = 0x8000;
+
+const OPT_PAD: u8 = 0;
+const OPT_SUBNET_MASK: u8 = 1;
+const OPT_ROUTER: u8 = 3;
+const OPT_DNS: u8 = 6;
+const OPT_HOSTNAME: u8 = 12;
+const OPT_DOMAIN_NAME: u8 = 15;
+const OPT_REQUESTED_IP: u8 = 50;
+const OPT_LEASE_TIME: u8 = 51;
+const OPT_MESSAGE_TYPE: u8 = 53;
+const OPT_SERVER_ID: u8 = 54;
+const OPT_MESSAGE: u8 = 56;
+const OPT_RENEWAL_TIME: u8 = 58;
+const OPT_REBINDING_TIME: u8 = 59;
+const OPT_END: u8 = 255;
+
+/// DHCP message type (option 53).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageType {
+    Discover = 1,
+    Offer = 2,
+    Request = 3,
+    Decline = 4,
+    Ack = 5,
+    Nak = 6,
+    Release = 7,
+    Inform = 8,
+}
+
+impl MessageType {
+    fn from_u8(value: u8) -> Option<Self> {
+        Some(match value {
+            1 => MessageType::Discover,
+            2 => MessageType::Offer,
+            3 => MessageType::Request,
+            4 => MessageType::Decline,
+            5 => MessageType::Ack,
+            6 => MessageType::Nak,
+            7 => MessageType::Release,
+            8 => MessageType::Inform,
+            _ => return None,
+        })
+    }
+}
+
+/// A DHCP packet (only the fields a server needs).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DhcpPacket {
+    /// 1 = BOOTREQUEST, 2 = BOOTREPLY
+    pub op: u8,
+    pub xid: u32,
+    pub secs: u16,
+    pub flags: u16,
+    pub ciaddr: Ipv4Addr,
+    pub yiaddr: Ipv4Addr,
+    pub siaddr: Ipv4Addr,
+    pub giaddr: Ipv4Addr,
+    /// Client hardware address (Ethernet only)
+    pub chaddr: [u8; 6],
+    /// Options in wire order, without pad/end
+    pub options: Vec<(u8, Vec<u8>)>,
+}
+
+fn ipv4_at(data: &[u8], offset: usize) -> Ipv4Addr {
+    Ipv4Addr::new(data[offset], data[offset + 1], data[offset + 2], data[offset + 3])
+}
+
+impl DhcpPacket {
+    /// Decode a packet received on the server port.
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() < HEADER_LEN + MAGIC_COOKIE.len() {
+            bail!("DHCP packet too short ({} bytes)", data.len());
+        }
+        if data[1] != 1 || data[2] != 6 {
+            bail!("Unsupported hardware type {} / length {}", data[1], data[2]);
+        }
+        if data[HEADER_LEN..HEADER_LEN + 4] != MAGIC_COOKIE {
+            bail!("Missing DHCP magic cookie");
+        }
+
+        let mut options = Vec::new();
+        let mut pos = HEADER_LEN + 4;
+        while pos < data.len() {
+            let code = data[pos];
+            match code {
+                OPT_PAD => pos += 1,
+                OPT_END => break,
+                _ => {
+                    let len = *data.get(pos + 1).context("Truncated DHCP option")? as usize;
+                    let value = data.get(pos + 2..pos + 2 + len).context("Truncated DHCP option")?;
+                    options.push((code, value.to_vec()));
+                    pos += 2 + len;
+                }
+            }
+        }
+
+        let mut chaddr = [0u8; 6];
+        chaddr.copy_from_slice(&data[28..34]);
+
+        Ok(Self {
+            op: data[0],
+            xid: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+            secs: u16::from_be_bytes([data[8], data[9]]),
+            flags: u16::from_be_bytes([data[10], data[11]]),
+            ciaddr: ipv4_at(data, 12),
+            yiaddr: ipv4_at(data, 16),
+            siaddr: ipv4_at(data, 20),
+            giaddr: ipv4_at(data, 24),
+            chaddr,
+            options,
+        })
+    }
+
+    /// Encode the packet for the wire.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = vec![0u8; HEADER_LEN];
+        data[0] = self.op;
+        data[1] = 1;
+        data[2] = 6;
+        data[4..8].copy_from_slice(&self.xid.to_be_bytes());
+        data[8..10].copy_from_slice(&self.secs.to_be_bytes());
+        data[10..12].copy_from_slice(&self.flags.to_be_bytes());
+        data[12..16].copy_from_slice(&self.ciaddr.octets());
+        data[16..20].copy_from_slice(&self.yiaddr.octets());
+        data[20..24].copy_from_slice(&self.siaddr.octets());
+        data[24..28].copy_from_slice(&self.giaddr.octets());
+        data[28..34].copy_from_slice(&self.chaddr);
+        data.extend_from_slice(&MAGIC_COOKIE);
+        for (code, value) in &self.options {
+            // Longer values would need RFC 3396 splitting; nothing we send is that long
+            let len = value.len().min(255);
+            data.push(*code);
+            data.push(len as u8);
+            data.extend_from_slice(&value[..len]);
+        }
+        data.push(OPT_END);
+        // Some clients ignore replies shorter than a BOOTP packet
+        if data.len() < 300 {
+            data.resize(300, 0);
+        }
+        data
+    }
+
+    fn option(&self, code: u8) -> Option<&[u8]> {
+        self.options.iter().find(|(c, _)| *c == code).map(|(_, v)| v.as_slice())
+    }
+
+    fn option_ipv4(&self, code: u8) -> Option<Ipv4Addr> {
+        self.option(code)
+            .filter(|v| v.len() == 4)
+            .map(|v| ipv4_at(v, 0))
+    }
+
+    pub fn message_type(&self) -> Option<MessageType> {
+        self.option(OPT_MESSAGE_TYPE)
+            .and_then(|v| v.first())
+            .and_then(|t| MessageType::from_u8(*t))
+    }
+
+    pub fn requested_ip(&self) -> Option<Ipv4Addr> {
+        self.option_ipv4(OPT_REQUESTED_IP)
+    }
+
+    pub fn server_id(&self) -> Option<Ipv4Addr> {
+        self.option_ipv4(OPT_SERVER_ID)
+    }
+
+    pub fn hostname(&self) -> Option<String> {
+        self.option(OPT_HOSTNAME)
+            .map(|v| String::from_utf8_lossy(v).trim_end_matches('\0').to_string())
+            .filter(|h| !h.is_empty())
+    }
+
+    /// Client MAC as lowercase "aa:bb:cc:dd:ee:ff".
+    pub fn client_mac(&self) -> String {
+        self.chaddr.iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(":")
+    }
+
+    pub fn wants_broadcast(&self) -> bool {
+        self.flags & FLAG_BROADCAST != 0
+    }
+}
+
+/// A lease that is always handed to the same NIC.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StaticLease {
+    pub mac_address: String,
+    pub ip_address: Ipv4Addr,
+    /// Hostname sent to the client (option 12)
+    #[serde(default)]
+    pub hostname: Option<String>,
+    /// VM owning the NIC (informational)
+    #[serde(default)]
+    pub vm_id: Option<String>,
+}
+
+/// DHCP settings of one bridge.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DhcpConfig {
+    pub bridge: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Subnet served, e.g. "192.168.50.0/24"
+    pub subnet: String,
+    /// Server identifier; the host's address on the bridge (defaults to the gateway)
+    #[serde(default)]
+    pub server_address: Option<Ipv4Addr>,
+    /// First dynamically assigned address
+    pub range_start: Ipv4Addr,
+    /// Last dynamically assigned address
+    pub range_end: Ipv4Addr,
+    #[serde(default)]
+    pub gateway: Option<Ipv4Addr>,
+    #[serde(default)]
+    pub dns_servers: Vec<Ipv4Addr>,
+    #[serde(default)]
+    pub domain_name: Option<String>,
+    #[serde(default = "default_lease_time")]
+    pub lease_time_secs: u32,
+    #[serde(default)]
+    pub static_leases: Vec<StaticLease>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_lease_time() -> u32 {
+    3600
+}
+
+impl DhcpConfig {
+    /// Subnet address and prefix length.
+    pub fn network(&self) -> Result<(Ipv4Addr, u8)> {
+        match parse_cidr(&self.subnet)? {
+            (std::net::IpAddr::V4(addr), prefix) => Ok((addr, prefix)),
+            _ => bail!("DHCP subnet {} is not IPv4", self.subnet),
+        }
+    }
+
+    fn contains(&self, addr: Ipv4Addr) -> bool {
+        match self.network() {
+            Ok((network, prefix)) => {
+                let mask = prefix_mask(prefix);
+                u32::from(addr) & mask == u32::from(network) & mask
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Server identifier sent in every reply.
+    pub fn server_id(&self) -> Option<Ipv4Addr> {
+        self.server_address.or(self.gateway)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let (network, prefix) = self.network()?;
+        if prefix > 30 {
+            bail!("DHCP subnet {} is too small", self.subnet);
+        }
+        let mask = prefix_mask(prefix);
+        let broadcast = Ipv4Addr::from(u32::from(network) | !mask);
+        let usable = |addr: Ipv4Addr| {
+            self.contains(addr) && u32::from(addr) & !mask != 0 && addr != broadcast
+        };
+
+        if !usable(self.range_start) || !usable(self.range_end) {
+            bail!("DHCP range {}-{} is not inside {}", self.range_start, self.range_end, self.subnet);
+        }
+        if u32::from(self.range_start) > u32::from(self.range_end) {
+            bail!("DHCP range start {} is after range end {}", self.range_start, self.range_end);
+        }
+        let Some(server_id) = self.server_id() else {
+            bail!("DHCP on {} needs a server address or gateway", self.bridge);
+        };
+        if !self.contains(server_id) {
+            bail!("Server address {} is not inside {}", server_id, self.subnet);
+        }
+        if let Some(gateway) = self.gateway {
+            if !usable(gateway) {
+                bail!("Gateway {} is not a usable address in {}", gateway, self.subnet);
+            }
+        }
+        if self.lease_time_secs < 60 {
+            bail!("Lease time must be at least 60 seconds");
+        }
+
+        let mut macs = std::collections::HashSet::new();
+        let mut addresses = std::collections::HashSet::new();
+        for lease in &self.static_leases {
+            validate_mac(&lease.mac_address)?;
+            if !usable(lease.ip_address) {
+                bail!("Static lease {} is not a usable address in {}", lease.ip_address, self.subnet);
+            }
+            if Some(lease.ip_address) == self.gateway || lease.ip_address == server_id {
+                bail!("Static lease {} collides with the gateway/server address", lease.ip_address);
+            }
+            if !macs.insert(lease.mac_address.to_lowercase()) {
+                bail!("Duplicate static lease for {}", lease.mac_address);
+            }
+            if !addresses.insert(lease.ip_address) {
+                bail!("Address {} is assigned to more than one static lease", lease.ip_address);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn prefix_mask(prefix: u8) -> u32 {
+    if prefix == 0 {
+        0
+    } else {
+        u32::MAX << (32 - prefix)
+    }
+}
+
+/// A bound address.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DhcpLease {
+    pub mac_address: String,
+    pub ip_address: Ipv4Addr,
+    #[serde(default)]
+    pub hostname: Option<String>,
+    /// Unix time the lease expires
+    pub expires_at: i64,
+    #[serde(default)]
+    pub is_static: bool,
+}
+
+/// Lease table key: the client MAC, or the address for declined (ownerless) entries.
+fn lease_key(lease: &DhcpLease) -> String {
+    if lease.mac_address.is_empty() {
+        format!("declined-{}", lease.ip_address)
+    } else {
+        lease.mac_address.to_lowercase()
+    }
+}
+
+/// Address pool and lease table of one bridge.
+#[derive(Debug, Clone)]
+pub struct DhcpPool {
+    config: DhcpConfig,
+    /// MAC -> lease
+    leases: BTreeMap<String, DhcpLease>,
+}
+
+impl DhcpPool {
+    /// Create a pool, keeping previous leases that still fit the config.
+    pub fn new(config: DhcpConfig, leases: impl IntoIterator<Item = DhcpLease>) -> Self {
+        let mut pool = Self {
+            config,
+            leases: BTreeMap::new(),
+        };
+        for lease in leases {
+            let key = lease_key(&lease);
+            if pool.static_lease(&key).is_none() && pool.in_range(lease.ip_address) {
+                pool.leases.insert(key, lease);
+            }
+        }
+        pool
+    }
+
+    pub fn config(&self) -> &DhcpConfig {
+        &self.config
+    }
+
+    /// Dynamic leases (including expired ones still reserved for their MAC).
+    pub fn dynamic_leases(&self) -> impl Iterator<Item = &DhcpLease> {
+        self.leases.values()
+    }
+
+    /// Lease table: all static leases plus unexpired dynamic ones.
+    pub fn active_leases(&self, now: i64) -> Vec<DhcpLease> {
+        let mut leases: Vec<DhcpLease> = self.config.static_leases.iter()
+            .map(|s| DhcpLease {
+                mac_address: s.mac_address.to_lowercase(),
+                ip_address: s.ip_address,
+                hostname: s.hostname.clone(),
+                expires_at: 0,
+                is_static: true,
+            })
+            .collect();
+        leases.extend(self.leases.values()
+            .filter(|l| l.expires_at > now && !l.mac_address.is_empty())
+            .cloned());
+        leases.sort_by_key(|l| u32::from(l.ip_address));
+        leases
+    }
+
+    fn static_lease(&self, mac: &str) -> Option<&StaticLease> {
+        self.config.static_leases.iter().find(|s| s.mac_address.eq_ignore_ascii_case(mac))
+    }
+
+    fn in_range(&self, addr: Ipv4Addr) -> bool {
+        (u32::from(self.config.range_start)..=u32::from(self.config.range_end)).contains(&u32::from(addr))
+    }
+
+    /// Whether `addr` may be handed to `mac` right now.
+    fn available_for(&self, addr: Ipv4Addr, mac: &str, now: i64) -> bool {
+        if !self.in_range(addr)
+            || Some(addr) == self.config.gateway
+            || Some(addr) == self.config.server_id()
+            || self.config.static_leases.iter().any(|s| s.ip_address == addr)
+        {
+            return false;
+        }
+        !self.leases.iter().any(|(owner, lease)| {
+            lease.ip_address == addr && owner != mac && lease.expires_at > now
+        })
+    }
+
+    /// Address to offer to `mac`, or None when the pool is exhausted.
+    pub fn select_address(&self, mac: &str, requested: Option<Ipv4Addr>, now: i64) -> Option<Ipv4Addr> {
+        let mac = mac.to_lowercase();
+        if let Some(fixed) = self.static_lease(&mac) {
+            return Some(fixed.ip_address);
+        }
+        // Keep a client on its previous address, even after expiry, if nobody took it
+        if let Some(lease) = self.leases.get(&mac) {
+            if self.available_for(lease.ip_address, &mac, now) {
+                return Some(lease.ip_address);
+            }
+        }
+        if let Some(addr) = requested.filter(|a| self.available_for(*a, &mac, now)) {
+            return Some(addr);
+        }
+
+        let (start, end) = (u32::from(self.config.range_start), u32::from(self.config.range_end));
+        let free = (start..=end).map(Ipv4Addr::from).find(|a| {
+            self.available_for(*a, &mac, now) && !self.leases.values().any(|l| l.ip_address == *a)
+        });
+        // Fall back to reusing the longest-expired lease of another client
+        free.or_else(|| {
+            self.leases.iter()
+                .filter(|(owner, l)| **owner != mac && l.expires_at <= now && self.available_for(l.ip_address, &mac, now))
+                .min_by_key(|(_, l)| l.expires_at)
+                .map(|(_, l)| l.ip_address)
+        })
+    }
+
+    /// Bind `addr` to `mac`. Returns None when the address can't be given to that client.
+    pub fn bind(&mut self, mac: &str, addr: Ipv4Addr, hostname: Option<String>, now: i64) -> Option<DhcpLease> {
+        let mac = mac.to_lowercase();
+        if let Some(fixed) = self.static_lease(&mac) {
+            return (fixed.ip_address == addr).then(|| DhcpLease {
+                mac_address: mac.clone(),
+                ip_address: addr,
+                hostname: fixed.hostname.clone().or(hostname),
+                expires_at: now + i64::from(self.config.lease_time_secs),
+                is_static: true,
+            });
+        }
+        if !self.available_for(addr, &mac, now) {
+            return None;
+        }
+
+        // The address may still be recorded for an expired client
+        self.leases.retain(|owner, l| *owner == mac || l.ip_address != addr);
+        let lease = DhcpLease {
+            mac_address: mac.clone(),
+            ip_address: addr,
+            hostname,
+            expires_at: now + i64::from(self.config.lease_time_secs),
+            is_static: false,
+        };
+        self.leases.insert(mac, lease.clone());
+        Some(lease)
+    }
+
+    /// Client gave its address back.
+    pub fn release(&mut self, mac: &str, now: i64) -> bool {
+        match self.leases.get_mut(&mac.to_lowercase()) {
+            Some(lease) if lease.expires_at > now => {
+                // Keep the record so the client gets the same address next time
+                lease.expires_at = now;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Current address of a MAC (static or unexpired dynamic).
+    pub fn address_of(&self, mac: &str, now: i64) -> Option<Ipv4Addr> {
+        if let Some(fixed) = self.static_lease(mac) {
+            return Some(fixed.ip_address);
+        }
+        self.leases.get(&mac.to_lowercase())
+            .filter(|l| l.expires_at > now)
+            .map(|l| l.ip_address)
+    }
+
+    /// Handle a client message. Returns the reply to send (if any) and
+    /// whether the lease table changed.
+    pub fn handle(&mut self, request: &DhcpPacket, now: i64) -> (Option<DhcpPacket>, bool) {
+        let Some(server_id) = self.config.server_id() else {
+            return (None, false);
+        };
+        if request.op != 1 {
+            return (None, false);
+        }
+        let mac = request.client_mac();
+
+        match request.message_type() {
+            Some(MessageType::Discover) => {
+                let reply = self.select_address(&mac, request.requested_ip(), now)
+                    .map(|addr| self.reply(request, MessageType::Offer, addr, server_id));
+                (reply, false)
+            }
+            Some(MessageType::Request) => {
+                // The client picked another server's offer
+                if request.server_id().is_some_and(|id| id != server_id) {
+                    return (None, false);
+                }
+                // SELECTING/INIT-REBOOT carry option 50, RENEWING/REBINDING use ciaddr
+                let wanted = request.requested_ip()
+                    .or_else(|| (!request.ciaddr.is_unspecified()).then_some(request.ciaddr));
+                let Some(wanted) = wanted else {
+                    return (None, false);
+                };
+
+                match self.bind(&mac, wanted, request.hostname(), now) {
+                    Some(lease) => {
+                        let reply = self.reply(request, MessageType::Ack, lease.ip_address, server_id);
+                        (Some(reply), !lease.is_static)
+                    }
+                    None if request.server_id().is_some() || self.config.contains(wanted) => {
+                        (Some(self.nak(request, server_id)), false)
+                    }
+                    // INIT-REBOOT for another network: stay silent (RFC 2131 4.3.2)
+                    None => (None, false),
+                }
+            }
+            Some(MessageType::Release) => (None, self.release(&mac, now)),
+            Some(MessageType::Decline) => {
+                // Somebody else uses the address; park it so it isn't handed out again
+                let changed = match request.requested_ip() {
+                    Some(addr) if self.in_range(addr) => {
+                        self.leases.remove(&mac.to_lowercase());
+                        let parked = DhcpLease {
+                            mac_address: String::new(),
+                            ip_address: addr,
+                            hostname: None,
+                            expires_at: now + i64::from(self.config.lease_time_secs),
+                            is_static: false,
+                        };
+                        self.leases.insert(lease_key(&parked), parked);
+                        true
+                    }
+                    _ => false,
+                };
+                (None, changed)
+            }
+            Some(MessageType::Inform) => {
+                let mut reply = self.reply(request, MessageType::Ack, Ipv4Addr::UNSPECIFIED, server_id);
+                reply.options.retain(|(code, _)| {
+                    !matches!(*code, OPT_LEASE_TIME | OPT_RENEWAL_TIME | OPT_REBINDING_TIME)
+                });
+                (Some(reply), false)
+            }
+            _ => (None, false),
+        }
+    }
+
+    fn reply(&self, request: &DhcpPacket, kind: MessageType, yiaddr: Ipv4Addr, server_id: Ipv4Addr) -> DhcpPacket {
+        let lease_time = self.config.lease_time_secs;
+        let prefix = self.network_prefix();
+        let mut options = vec![
+            (OPT_MESSAGE_TYPE, vec![kind as u8]),
+            (OPT_SERVER_ID, server_id.octets().to_vec()),
+            (OPT_LEASE_TIME, lease_time.to_be_bytes().to_vec()),
+            (OPT_RENEWAL_TIME, (lease_time / 2).to_be_bytes().to_vec()),
+            (OPT_REBINDING_TIME, (lease_time / 8 * 7).to_be_bytes().to_vec()),
+            (OPT_SUBNET_MASK, prefix_mask(prefix).to_be_bytes().to_vec()),
+        ];
+        if let Some(gateway) = self.config.gateway {
+            options.push((OPT_ROUTER, gateway.octets().to_vec()));
+        }
+        if !self.config.dns_servers.is_empty() {
+            options.push((OPT_DNS, self.config.dns_servers.iter().flat_map(|d| d.octets()).collect()));
+        }
+        if let Some(ref domain) = self.config.domain_name {
+            options.push((OPT_DOMAIN_NAME, domain.as_bytes().to_vec()));
+        }
+        if let Some(hostname) = self.static_lease(&request.client_mac()).and_then(|s| s.hostname.as_ref()) {
+            options.push((OPT_HOSTNAME, hostname.as_bytes().to_vec()));
+        }
+
+        DhcpPacket {
+            op: 2,
+            xid: request.xid,
+            secs: 0,
+            flags: request.flags,
+            ciaddr: request.ciaddr,
+            yiaddr,
+            siaddr: Ipv4Addr::UNSPECIFIED,
+            giaddr: request.giaddr,
+            chaddr: request.chaddr,
+            options,
+        }
+    }
+
+    fn nak(&self, request: &DhcpPacket, server_id: Ipv4Addr) -> DhcpPacket {
+        DhcpPacket {
+            op: 2,
+            xid: request.xid,
+            secs: 0,
+            // NAKs are always broadcast when there is no relay
+            flags: request.flags | FLAG_BROADCAST,
+            ciaddr: Ipv4Addr::UNSPECIFIED,
+            yiaddr: Ipv4Addr::UNSPECIFIED,
+            siaddr: Ipv4Addr::UNSPECIFIED,
+            giaddr: request.giaddr,
+            chaddr: request.chaddr,
+            options: vec![
+                (OPT_MESSAGE_TYPE, vec![MessageType::Nak as u8]),
+                (OPT_SERVER_ID, server_id.octets().to_vec()),
+                (OPT_MESSAGE, b"requested address not available".to_vec()),
+            ],
+        }
+    }
+
+    fn network_prefix(&self) -> u8 {
+        self.config.network().map(|(_, p)| p).unwrap_or(24)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> DhcpConfig {
+        DhcpConfig {
+            bridge: "br0".to_string(),
+            enabled: true,
+            subnet: "192.168.50.0/24".to_string(),
+            server_address: None,
+            range_start: Ipv4Addr::new(192, 168, 50, 100),
+            range_end: Ipv4Addr::new(192, 168, 50, 102),
+            gateway: Some(Ipv4Addr::new(192, 168, 50, 1)),
+            dns_servers: vec![Ipv4Addr::new(192, 168, 50, 1)],
+            domain_name: Some("lab.local".to_string()),
+            lease_time_secs: 600,
+            static_leases: vec![StaticLease {
+                mac_address: "52:54:00:00:00:aa".to_string(),
+                ip_address: Ipv4Addr::new(192, 168, 50, 10),
+                hostname: Some("db".to_string()),
+                vm_id: None,
+            }],
+        }
+    }
+
+    fn request(kind: MessageType, mac_last: u8, requested: Option<Ipv4Addr>) -> DhcpPacket {
+        let mut options = vec![(OPT_MESSAGE_TYPE, vec![kind as u8])];
+        if let Some(addr) = requested {
+            options.push((OPT_REQUESTED_IP, addr.octets().to_vec()));
+        }
+        DhcpPacket {
+            op: 1,
+            xid: 0x1234,
+            secs: 0,
+            flags: 0,
+            ciaddr: Ipv4Addr::UNSPECIFIED,
+            yiaddr: Ipv4Addr::UNSPECIFIED,
+            siaddr: Ipv4Addr::UNSPECIFIED,
+            giaddr: Ipv4Addr::UNSPECIFIED,
+            chaddr: [0x52, 0x54, 0, 0, 0, mac_last],
+            options,
+        }
+    }
+
+    #[test]
+    fn test_packet_roundtrip() {
+        let packet = request(MessageType::Discover, 1, Some(Ipv4Addr::new(10, 0, 0, 5)));
+        let bytes = packet.to_bytes();
+        assert_eq!(bytes.len(), 300);
+
+        let parsed = DhcpPacket::parse(&bytes).unwrap();
+        assert_eq!(parsed, packet);
+        assert_eq!(parsed.message_type(), Some(MessageType::Discover));
+        assert_eq!(parsed.client_mac(), "52:54:00:00:00:01");
+        assert!(DhcpPacket::parse(&bytes[..100]).is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(config().validate().is_ok());
+
+        let mut bad = config();
+        bad.range_end = Ipv4Addr::new(192, 168, 51, 10);
+        assert!(bad.validate().is_err());
+
+        let mut bad = config();
+        bad.static_leases[0].ip_address = Ipv4Addr::new(192, 168, 50, 1);
+        assert!(bad.validate().is_err());
+
+        let mut bad = config();
+        bad.gateway = None;
+        assert!(bad.validate().is_err());
+    }
+
+    #[test]
+    fn test_dora_and_exhaustion() {
+        let mut pool = DhcpPool::new(config(), []);
+        let now = 1_000;
+
+        let (offer, changed) = pool.handle(&request(MessageType::Discover, 1, None), now);
+        let offer = offer.unwrap();
+        assert!(!changed);
+        assert_eq!(offer.message_type(), Some(MessageType::Offer));
+        assert_eq!(offer.yiaddr, Ipv4Addr::new(192, 168, 50, 100));
+        assert_eq!(offer.server_id(), Some(Ipv4Addr::new(192, 168, 50, 1)));
+
+        let mut req = request(MessageType::Request, 1, Some(offer.yiaddr));
+        req.options.push((OPT_SERVER_ID, vec![192, 168, 50, 1]));
+        let (ack, changed) = pool.handle(&req, now);
+        assert!(changed);
+        assert_eq!(ack.unwrap().message_type(), Some(MessageType::Ack));
+        assert_eq!(pool.address_of("52:54:00:00:00:01", now), Some(offer.yiaddr));
+
+        // Another client can't take a bound address
+        let (nak, _) = pool.handle(&request(MessageType::Request, 2, Some(offer.yiaddr)), now);
+        assert_eq!(nak.unwrap().message_type(), Some(MessageType::Nak));
+
+        for last in 2..=3 {
+            let addr = pool.select_address(&format!("52:54:00:00:00:0{}", last), None, now).unwrap();
+            pool.bind(&format!("52:54:00:00:00:0{}", last), addr, None, now).unwrap();
+        }
+        assert_eq!(pool.select_address("52:54:00:00:00:04", None, now), None);
+
+        // After expiry the oldest lease is reused, but the owner still gets it back first
+        let later = now + 601;
+        assert_eq!(pool.select_address("52:54:00:00:00:01", None, later), Some(offer.yiaddr));
+        assert!(pool.select_address("52:54:00:00:00:04", None, later).is_some());
+    }
+
+    #[test]
+    fn test_static_lease() {
+        let mut pool = DhcpPool::new(config(), []);
+        let (offer, _) = pool.handle(&request(MessageType::Discover, 0xaa, None), 0);
+        let offer = offer.unwrap();
+        assert_eq!(offer.yiaddr, Ipv4Addr::new(192, 168, 50, 10));
+        assert_eq!(offer.option(OPT_HOSTNAME), Some(b"db".as_slice()));
+
+        // A static client asking for a pool address is refused
+        let (nak, _) = pool.handle(&request(MessageType::Request, 0xaa, Some(Ipv4Addr::new(192, 168, 50, 100))), 0);
+        assert_eq!(nak.unwrap().message_type(), Some(MessageType::Nak));
+        assert_eq!(pool.active_leases(0).len(), 1);
+    }
+}
@@ -0,0 +1,618 @@
+//! Security group enforcement for bridge-attached VM NICs with nftables.
+//!
+//! OVN enforces security groups as ACLs on logical switch ports. VMs on plain
+//! Linux bridges bypass OVN, so this module renders the same security groups
+//! into a `bridge` family nftables table with one pair of chains per tap:
+//!
+//! ```text
+//! table bridge limiquantix_sg
+//!   prerouting     ── iifname "vnet0" ──► vnet0_egress   (traffic from the VM)
+//!   forward/output ── oifname "vnet0" ──► vnet0_ingress  (traffic to the VM)
+//! ```
+//!
+//! - Anti-spoofing: frames from the VM must carry its MAC, and ARP/IP/IPv6
+//!   packets one of its addresses (when `port_security_enabled`)
+//! - Stateful groups accept return traffic through conntrack
+//! - Ports with security groups drop anything no rule allows; ports without
+//!   groups only get anti-spoofing
+//! - The whole table is replaced in one `nft -f` transaction, so there is
+//!   never a window with partial rules
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::net::{IpAddr, Ipv6Addr};
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, instrument};
+
+use super::host::parse_cidr;
+
+/// nftables table holding all security group chains.
+pub const TABLE_NAME: &str = "limiquantix_sg";
+
+/// Traffic direction, seen from the VM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleDirection {
+    /// Traffic to the VM
+    Ingress,
+    /// Traffic from the VM
+    Egress,
+}
+
+/// What to do with matching traffic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleAction {
+    #[default]
+    Allow,
+    Drop,
+    /// Drop and send an ICMP error / TCP reset.
+    ///
+    /// The bridge family can only reject before forwarding, so ingress
+    /// reject rules drop silently.
+    Reject,
+}
+
+impl RuleAction {
+    fn verdict(self, direction: RuleDirection) -> &'static str {
+        match self {
+            RuleAction::Allow => "accept",
+            RuleAction::Drop => "drop",
+            RuleAction::Reject if direction == RuleDirection::Egress => "reject",
+            RuleAction::Reject => "drop",
+        }
+    }
+}
+
+/// A security group rule.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecurityGroupRule {
+    #[serde(default)]
+    pub id: String,
+    pub direction: RuleDirection,
+    /// "tcp", "udp", "sctp", "icmp", "icmpv6", "any" or an IP protocol number
+    #[serde(default = "default_protocol")]
+    pub protocol: String,
+    /// First port of the range (TCP/UDP/SCTP; None = all ports)
+    #[serde(default)]
+    pub port_min: Option<u16>,
+    /// Last port of the range (None = same as `port_min`)
+    #[serde(default)]
+    pub port_max: Option<u16>,
+    /// ICMP type (None = any)
+    #[serde(default)]
+    pub icmp_type: Option<u8>,
+    /// ICMP code (None = any)
+    #[serde(default)]
+    pub icmp_code: Option<u8>,
+    /// Remote address or CIDR (source for ingress, destination for egress)
+    #[serde(default)]
+    pub remote_ip_prefix: Option<String>,
+    /// Match the addresses of ports in this security group instead
+    #[serde(default)]
+    pub remote_security_group_id: Option<String>,
+    #[serde(default)]
+    pub action: RuleAction,
+    /// Evaluation order (lower first)
+    #[serde(default)]
+    pub priority: u32,
+    #[serde(default)]
+    pub description: String,
+}
+
+fn default_protocol() -> String {
+    "any".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// A named set of firewall rules.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SecurityGroup {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Track connections so replies to allowed traffic are accepted
+    #[serde(default = "default_true")]
+    pub stateful: bool,
+    #[serde(default)]
+    pub rules: Vec<SecurityGroupRule>,
+}
+
+/// Filtering for one VM tap.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PortFilter {
+    /// Host-side tap device (e.g. "vnet3")
+    pub tap: String,
+    /// Guest MAC address
+    pub mac_address: String,
+    /// Guest addresses (plain or CIDR); empty = no IP anti-spoofing
+    #[serde(default)]
+    pub ip_addresses: Vec<String>,
+    /// Enforce MAC/IP anti-spoofing
+    #[serde(default = "default_true")]
+    pub port_security_enabled: bool,
+    #[serde(default)]
+    pub security_group_ids: Vec<String>,
+}
+
+// =============================================================================
+// Validation
+// =============================================================================
+
+/// Parse an address or CIDR prefix.
+fn parse_prefix(prefix: &str) -> Result<IpAddr> {
+    if prefix.contains('/') {
+        Ok(parse_cidr(prefix)?.0)
+    } else {
+        prefix.parse().with_context(|| format!("Invalid address {}", prefix))
+    }
+}
+
+/// Check a MAC address ("52:54:00:12:34:56").
+pub fn validate_mac(mac: &str) -> Result<()> {
+    let octets: Vec<&str> = mac.split(':').collect();
+    if octets.len() != 6 || !octets.iter().all(|o| o.len() == 2 && o.chars().all(|c| c.is_ascii_hexdigit())) {
+        bail!("Invalid MAC address {}", mac);
+    }
+    Ok(())
+}
+
+impl SecurityGroupRule {
+    fn is_port_protocol(&self) -> bool {
+        matches!(self.protocol.as_str(), "tcp" | "udp" | "sctp")
+    }
+
+    fn is_icmp(&self) -> bool {
+        matches!(self.protocol.as_str(), "icmp" | "icmpv6")
+    }
+
+    /// Check the rule for consistency.
+    pub fn validate(&self) -> Result<()> {
+        let known = matches!(self.protocol.as_str(), "any" | "tcp" | "udp" | "sctp" | "icmp" | "icmpv6")
+            || self.protocol.parse::<u8>().is_ok();
+        if !known {
+            bail!("Unknown protocol '{}'", self.protocol);
+        }
+
+        if self.port_min.is_some() || self.port_max.is_some() {
+            if !self.is_port_protocol() {
+                bail!("Port ranges need protocol tcp, udp or sctp (got {})", self.protocol);
+            }
+            let min = self.port_min.unwrap_or(0);
+            if self.port_max.is_some_and(|max| max < min) {
+                bail!("Port range {}-{} is empty", min, self.port_max.unwrap_or(0));
+            }
+        }
+        if (self.icmp_type.is_some() || self.icmp_code.is_some()) && !self.is_icmp() {
+            bail!("ICMP type/code need protocol icmp or icmpv6 (got {})", self.protocol);
+        }
+        if self.icmp_code.is_some() && self.icmp_type.is_none() {
+            bail!("An ICMP code needs an ICMP type");
+        }
+
+        if self.remote_ip_prefix.is_some() && self.remote_security_group_id.is_some() {
+            bail!("Use either a remote IP prefix or a remote security group, not both");
+        }
+        if let Some(ref prefix) = self.remote_ip_prefix {
+            let addr = parse_prefix(prefix)?;
+            let mismatch = match self.protocol.as_str() {
+                "icmp" => addr.is_ipv6(),
+                "icmpv6" => addr.is_ipv4(),
+                _ => false,
+            };
+            if mismatch {
+                bail!("Protocol {} does not match remote prefix {}", self.protocol, prefix);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SecurityGroup {
+    /// Check the group and its rules.
+    pub fn validate(&self) -> Result<()> {
+        if self.id.is_empty() {
+            bail!("Security group ID is required");
+        }
+        for (i, rule) in self.rules.iter().enumerate() {
+            rule.validate().with_context(|| format!("Rule {} of security group {}", i + 1, self.name))?;
+        }
+        Ok(())
+    }
+}
+
+impl PortFilter {
+    /// Check the filter (group references are checked when rendering).
+    pub fn validate(&self) -> Result<()> {
+        if self.tap.is_empty() || self.tap.len() > 15 || self.tap.contains(['"', ' ', '/']) {
+            bail!("Invalid tap name '{}'", self.tap);
+        }
+        validate_mac(&self.mac_address)?;
+        for addr in &self.ip_addresses {
+            parse_prefix(addr)?;
+        }
+        Ok(())
+    }
+
+    /// Guest addresses without prefix lengths, by family.
+    fn addresses(&self) -> (Vec<IpAddr>, Vec<IpAddr>) {
+        self.ip_addresses.iter()
+            .filter_map(|a| parse_prefix(a).ok())
+            .partition(IpAddr::is_ipv4)
+    }
+}
+
+// =============================================================================
+// Rendering
+// =============================================================================
+
+/// EUI-64 link-local address derived from a MAC (fe80::/64 + modified EUI-64).
+pub fn eui64_link_local(mac: &str) -> Option<Ipv6Addr> {
+    let octets: Vec<u8> = mac.split(':')
+        .map(|o| u8::from_str_radix(o, 16))
+        .collect::<std::result::Result<_, _>>()
+        .ok()?;
+    let [a, b, c, d, e, f] = <[u8; 6]>::try_from(octets).ok()?;
+
+    Some(Ipv6Addr::new(
+        0xfe80, 0, 0, 0,
+        u16::from_be_bytes([a ^ 0x02, b]),
+        u16::from_be_bytes([c, 0xff]),
+        u16::from_be_bytes([0xfe, d]),
+        u16::from_be_bytes([e, f]),
+    ))
+}
+
+/// nftables chain name for a tap (identifiers cannot contain '-').
+fn chain_name(tap: &str, suffix: &str) -> String {
+    let base: String = tap.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("{}_{}", base, suffix)
+}
+
+fn set_of<T: std::fmt::Display>(items: impl IntoIterator<Item = T>) -> String {
+    let items: Vec<String> = items.into_iter().map(|i| i.to_string()).collect();
+    format!("{{ {} }}", items.join(", "))
+}
+
+/// Addresses of the local ports in each security group (for remote group rules).
+fn group_members(ports: &[PortFilter]) -> BTreeMap<&str, BTreeSet<IpAddr>> {
+    let mut members: BTreeMap<&str, BTreeSet<IpAddr>> = BTreeMap::new();
+    for port in ports {
+        let (v4, v6) = port.addresses();
+        for group in &port.security_group_ids {
+            members.entry(group.as_str()).or_default().extend(v4.iter().chain(v6.iter()).copied());
+        }
+    }
+    members
+}
+
+/// Render one rule as nftables statements (remote groups can need one per family).
+fn render_rule(rule: &SecurityGroupRule, members: &BTreeMap<&str, BTreeSet<IpAddr>>) -> Vec<String> {
+    let (addr_key, port_key) = match rule.direction {
+        RuleDirection::Ingress => ("saddr", "dport"),
+        RuleDirection::Egress => ("daddr", "dport"),
+    };
+
+    // Address matches, one per family
+    let mut address_matches: Vec<Option<String>> = Vec::new();
+    if let Some(ref prefix) = rule.remote_ip_prefix {
+        let family = if prefix.contains(':') { "ip6" } else { "ip" };
+        address_matches.push(Some(format!("{} {} {}", family, addr_key, prefix)));
+    } else if let Some(ref group) = rule.remote_security_group_id {
+        let addrs = members.get(group.as_str()).cloned().unwrap_or_default();
+        let (v4, v6): (Vec<IpAddr>, Vec<IpAddr>) = addrs.into_iter().partition(IpAddr::is_ipv4);
+        if !v4.is_empty() && rule.protocol != "icmpv6" {
+            address_matches.push(Some(format!("ip {} {}", addr_key, set_of(v4))));
+        }
+        if !v6.is_empty() && rule.protocol != "icmp" {
+            address_matches.push(Some(format!("ip6 {} {}", addr_key, set_of(v6))));
+        }
+        // No members on this host: the rule cannot match anything
+    } else {
+        address_matches.push(None);
+    }
+
+    let mut protocol = String::new();
+    match rule.protocol.as_str() {
+        "any" => {}
+        p @ ("tcp" | "udp" | "sctp") => match (rule.port_min, rule.port_max) {
+            (Some(min), Some(max)) if max != min => write!(protocol, "{} {} {}-{}", p, port_key, min, max).unwrap(),
+            (Some(port), _) | (None, Some(port)) => write!(protocol, "{} {} {}", p, port_key, port).unwrap(),
+            (None, None) => write!(protocol, "meta l4proto {}", p).unwrap(),
+        },
+        p @ ("icmp" | "icmpv6") => {
+            let l4 = if p == "icmp" { "icmp" } else { "ipv6-icmp" };
+            write!(protocol, "meta l4proto {}", l4).unwrap();
+            if let Some(t) = rule.icmp_type {
+                write!(protocol, " {} type {}", p, t).unwrap();
+            }
+            if let Some(c) = rule.icmp_code {
+                write!(protocol, " {} code {}", p, c).unwrap();
+            }
+        }
+        number => write!(protocol, "meta l4proto {}", number).unwrap(),
+    }
+
+    address_matches.into_iter()
+        .map(|addr| {
+            let mut parts: Vec<&str> = Vec::new();
+            if let Some(ref a) = addr {
+                parts.push(a);
+            }
+            if !protocol.is_empty() {
+                parts.push(&protocol);
+            }
+            parts.push(rule.action.verdict(rule.direction));
+            let mut line = parts.join(" ");
+            if !rule.id.is_empty() {
+                write!(line, " comment \"{}\"", rule.id.replace('"', "")).unwrap();
+            }
+            line
+        })
+        .collect()
+}
+
+/// Render the complete nftables script for `ports`.
+///
+/// The script replaces the table atomically. With no ports it only removes
+/// the table.
+pub fn render_ruleset(groups: &BTreeMap<String, SecurityGroup>, ports: &[PortFilter]) -> Result<String> {
+    let mut out = String::new();
+    // Declaring the table first makes the delete succeed when it does not exist
+    writeln!(out, "table bridge {}", TABLE_NAME)?;
+    writeln!(out, "delete table bridge {}", TABLE_NAME)?;
+    if ports.is_empty() {
+        return Ok(out);
+    }
+
+    for port in ports {
+        port.validate()?;
+        for id in &port.security_group_ids {
+            if !groups.contains_key(id) {
+                bail!("Tap {} references unknown security group {}", port.tap, id);
+            }
+        }
+    }
+    let members = group_members(ports);
+
+    writeln!(out, "table bridge {} {{", TABLE_NAME)?;
+
+    // Egress is filtered on prerouting, the only hook (with input) that can reject
+    let mut prerouting = Vec::new();
+    let mut forward = Vec::new();
+    for port in ports {
+        prerouting.push(format!("iifname \"{}\" jump {}", port.tap, chain_name(&port.tap, "egress")));
+        forward.push(format!("oifname \"{}\" jump {}", port.tap, chain_name(&port.tap, "ingress")));
+    }
+    for (hook, rules) in [("prerouting", &prerouting), ("forward", &forward), ("output", &forward)] {
+        writeln!(out, "  chain {} {{", hook)?;
+        writeln!(out, "    type filter hook {} priority 0; policy accept;", hook)?;
+        for rule in rules {
+            writeln!(out, "    {}", rule)?;
+        }
+        writeln!(out, "  }}")?;
+    }
+
+    for port in ports {
+        let port_groups: Vec<&SecurityGroup> = port.security_group_ids.iter()
+            .filter_map(|id| groups.get(id))
+            .collect();
+        let filtered = !port_groups.is_empty();
+        let stateful = port_groups.iter().any(|g| g.stateful);
+
+        // Rules of all groups, in priority order
+        let mut rules: Vec<(&SecurityGroup, &SecurityGroupRule)> = port_groups.iter()
+            .flat_map(|g| g.rules.iter().map(move |r| (*g, r)))
+            .collect();
+        rules.sort_by(|(ga, a), (gb, b)| (a.priority, &ga.id, &a.id).cmp(&(b.priority, &gb.id, &b.id)));
+
+        for direction in [RuleDirection::Egress, RuleDirection::Ingress] {
+            let mut lines: Vec<String> = Vec::new();
+
+            if direction == RuleDirection::Egress {
+                if port.port_security_enabled {
+                    lines.extend(anti_spoofing(port));
+                }
+                // DHCP client and neighbor discovery always work
+                lines.push("udp sport 68 udp dport 67 accept".to_string());
+                lines.push("ether type arp accept".to_string());
+                lines.push("icmpv6 type { nd-router-solicit, nd-neighbor-solicit, nd-neighbor-advert } accept".to_string());
+            } else {
+                lines.push("udp sport 67 udp dport 68 accept".to_string());
+                lines.push("ether type arp accept".to_string());
+                lines.push("icmpv6 type { nd-router-advert, nd-neighbor-solicit, nd-neighbor-advert } accept".to_string());
+            }
+
+            if filtered {
+                if stateful {
+                    lines.push("ct state established,related accept".to_string());
+                    lines.push("ct state invalid drop".to_string());
+                }
+                for (_, rule) in rules.iter().filter(|(_, r)| r.direction == direction) {
+                    lines.extend(render_rule(rule, &members));
+                }
+                lines.push("drop".to_string());
+            }
+
+            let suffix = if direction == RuleDirection::Egress { "egress" } else { "ingress" };
+            writeln!(out, "  chain {} {{", chain_name(&port.tap, suffix))?;
+            for line in lines {
+                writeln!(out, "    {}", line)?;
+            }
+            writeln!(out, "  }}")?;
+        }
+    }
+
+    writeln!(out, "}}")?;
+    Ok(out)
+}
+
+/// Anti-spoofing statements for traffic leaving a VM.
+fn anti_spoofing(port: &PortFilter) -> Vec<String> {
+    let mac = port.mac_address.to_lowercase();
+    let mut lines = vec![
+        format!("ether saddr != {} drop", mac),
+        format!("arp saddr ether != {} drop", mac),
+        // Rogue DHCP servers and routers
+        "udp sport 67 udp dport 68 drop".to_string(),
+        "icmpv6 type nd-router-advert drop".to_string(),
+    ];
+
+    let (v4, v6) = port.addresses();
+    if !v4.is_empty() {
+        lines.push(format!("arp saddr ip != {} drop", set_of(&v4)));
+        // DHCP discovery is sent from 0.0.0.0
+        lines.push("ip saddr 0.0.0.0 udp sport 68 udp dport 67 accept".to_string());
+        lines.push(format!("ip saddr != {} drop", set_of(&v4)));
+    }
+    if !v6.is_empty() {
+        // The unspecified address (duplicate address detection) and the
+        // NIC's own EUI-64 link-local address, not the whole fe80::/10
+        let mut allowed: Vec<String> = vec!["::".to_string()];
+        allowed.extend(eui64_link_local(&mac).map(|a| a.to_string()));
+        allowed.extend(v6.iter().map(|a| a.to_string()));
+        lines.push(format!("ip6 saddr != {} drop", set_of(allowed)));
+    }
+    lines
+}
+
+// =============================================================================
+// Applying
+// =============================================================================
+
+/// Load a ruleset produced by `render_ruleset` (or the host firewall's
+/// `render_host_ruleset`) in a single transaction.
+#[instrument(skip(ruleset))]
+pub fn apply_ruleset(ruleset: &str) -> Result<()> {
+    debug!(ruleset = %ruleset, "Loading nftables ruleset");
+
+    let mut child = Command::new("nft")
+        .args(["-f", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to execute nft")?;
+
+    child.stdin.take()
+        .context("nft has no stdin")?
+        .write_all(ruleset.as_bytes())
+        .context("Failed to write ruleset to nft")?;
+
+    let output = child.wait_with_output().context("Failed to wait for nft")?;
+    if !output.status.success() {
+        bail!("nft rejected the ruleset: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    info!("nftables ruleset loaded");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(direction: RuleDirection, protocol: &str) -> SecurityGroupRule {
+        SecurityGroupRule {
+            id: String::new(),
+            direction,
+            protocol: protocol.to_string(),
+            port_min: None,
+            port_max: None,
+            icmp_type: None,
+            icmp_code: None,
+            remote_ip_prefix: None,
+            remote_security_group_id: None,
+            action: RuleAction::Allow,
+            priority: 0,
+            description: String::new(),
+        }
+    }
+
+    fn web_group() -> SecurityGroup {
+        SecurityGroup {
+            id: "web".into(),
+            name: "web".into(),
+            description: String::new(),
+            stateful: true,
+            rules: vec![
+                SecurityGroupRule { port_min: Some(443), remote_ip_prefix: Some("0.0.0.0/0".into()), ..rule(RuleDirection::Ingress, "tcp") },
+                SecurityGroupRule { port_min: Some(8000), port_max: Some(8080), remote_security_group_id: Some("web".into()), ..rule(RuleDirection::Ingress, "tcp") },
+                SecurityGroupRule { icmp_type: Some(8), ..rule(RuleDirection::Ingress, "icmp") },
+                SecurityGroupRule { priority: 1, ..rule(RuleDirection::Egress, "any") },
+                SecurityGroupRule { action: RuleAction::Reject, port_min: Some(25), ..rule(RuleDirection::Egress, "tcp") },
+            ],
+        }
+    }
+
+    fn port(tap: &str, ip: &str) -> PortFilter {
+        PortFilter {
+            tap: tap.into(),
+            mac_address: "52:54:00:AA:BB:01".into(),
+            ip_addresses: vec![ip.into(), "2001:db8::5/64".into()],
+            port_security_enabled: true,
+            security_group_ids: vec!["web".into()],
+        }
+    }
+
+    #[test]
+    fn test_render_ruleset() {
+        let groups = BTreeMap::from([("web".to_string(), web_group())]);
+        let ports = vec![port("vnet0", "10.0.0.5/24"), port("vnet-1", "10.0.0.6")];
+        let ruleset = render_ruleset(&groups, &ports).unwrap();
+
+        assert!(ruleset.starts_with("table bridge limiquantix_sg\ndelete table bridge limiquantix_sg\n"));
+        assert!(ruleset.contains("iifname \"vnet-1\" jump vnet_1_egress"));
+        assert!(ruleset.contains("ether saddr != 52:54:00:aa:bb:01 drop"));
+        assert!(ruleset.contains("ip saddr != { 10.0.0.5 } drop"));
+        assert!(ruleset.contains("ip6 saddr != { ::, fe80::5054:ff:feaa:bb01, 2001:db8::5 } drop"));
+        assert!(ruleset.contains("ip saddr 0.0.0.0/0 tcp dport 443 accept"));
+        assert!(ruleset.contains("ip saddr { 10.0.0.5, 10.0.0.6 } tcp dport 8000-8080 accept"));
+        assert!(ruleset.contains("meta l4proto icmp icmp type 8 accept"));
+        assert!(ruleset.contains("ct state established,related accept"));
+
+        // Priority 0 reject comes before the priority 1 allow-all
+        let chain = &ruleset[ruleset.find("chain vnet0_egress").unwrap()..];
+        let chain = &chain[..chain.find("\n  }").unwrap()];
+        let reject = chain.find("tcp dport 25 reject").unwrap();
+        let allow = chain.find("\n    accept").unwrap();
+        assert!(reject < allow && chain.trim_end().ends_with("drop"));
+    }
+
+    #[test]
+    fn test_render_without_groups() {
+        let mut open = port("vnet0", "10.0.0.5");
+        open.security_group_ids.clear();
+        open.port_security_enabled = false;
+        let ruleset = render_ruleset(&BTreeMap::new(), &[open]).unwrap();
+        assert!(!ruleset.contains("ether saddr"));
+        assert!(!ruleset.contains("ct state"));
+        assert!(!ruleset.contains("    drop\n"));
+
+        assert_eq!(render_ruleset(&BTreeMap::new(), &[]).unwrap().lines().count(), 2);
+        assert!(render_ruleset(&BTreeMap::new(), &[port("vnet0", "10.0.0.5")]).is_err());
+    }
+
+    #[test]
+    fn test_validate_rules() {
+        assert!(SecurityGroupRule { port_min: Some(22), ..rule(RuleDirection::Ingress, "icmp") }.validate().is_err());
+        assert!(SecurityGroupRule { port_min: Some(90), port_max: Some(80), ..rule(RuleDirection::Ingress, "tcp") }.validate().is_err());
+        assert!(SecurityGroupRule { remote_ip_prefix: Some("::/0".into()), ..rule(RuleDirection::Ingress, "icmp") }.validate().is_err());
+        assert!(rule(RuleDirection::Ingress, "gre").validate().is_err());
+        assert!(rule(RuleDirection::Ingress, "47").validate().is_ok());
+        assert!(validate_mac("52:54:00:aa:bb").is_err());
+    }
+}
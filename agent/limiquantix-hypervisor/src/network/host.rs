@@ -0,0 +1,1576 @@
+//! Declarative host network configuration.
+//!
+//! The host network (physical links, Linux bridges, bonds, VLANs, static
+//! routes, DNS, and DHCP/SLAAC/DHCPv6 addressing) is described as a `HostNetworkConfig`. Changes are applied
+//! as a transaction:
+//! 1. Capture the current state as a `HostNetworkConfig` (the snapshot)
+//! 2. Diff it against the desired state (`plan`)
+//! 3. Apply the changes in dependency order, stopping at the first failure
+//! 4. Verify connectivity (the default gateway answers)
+//!
+//! If a step or the verification fails, the snapshot is re-applied with the
+//! same machinery. Callers can keep the snapshot around and roll back later
+//! (e.g. when a remote client never confirms the change).
+//!
+//! The model is declarative for the objects it manages: a managed bridge,
+//! bond or VLAN missing from the desired state is deleted, and a managed link
+//! missing from `links` loses its addresses. Links owned by other components
+//! (libvirt `virbr*`, OVS, VM taps) are never touched.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::net::IpAddr;
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, instrument, warn};
+
+/// Path of the resolver configuration.
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+
+/// Linux bonding modes.
+pub const BOND_MODES: &[&str] = &[
+    "balance-rr", "active-backup", "balance-xor", "broadcast", "802.3ad", "balance-tlb", "balance-alb",
+];
+
+/// Transmit hash policies for balance-xor and 802.3ad bonds.
+const XMIT_HASH_POLICIES: &[&str] = &["layer2", "layer2+3", "layer3+4", "encap2+3", "encap3+4", "vlan+srcmac"];
+
+/// Link name prefixes owned by other components (libvirt, OVS/OVN, containers, VMs).
+const UNMANAGED_PREFIXES: &[&str] = &[
+    "lo", "virbr", "vnet", "tap", "docker", "veth", "br-int", "ovs-system",
+    "genev_sys", "vxlan_sys", "gre_sys", "cni", "flannel",
+];
+
+/// Addressing and link settings for one link (physical NIC, bridge, bond or VLAN).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkConfig {
+    /// Link name
+    pub name: String,
+    /// Static addresses in CIDR notation (IPv4 or IPv6)
+    #[serde(default)]
+    pub addresses: Vec<String>,
+    /// Obtain an IPv4 address with DHCP
+    #[serde(default)]
+    pub dhcp: bool,
+    /// IPv6 autoconfiguration (None = leave the kernel settings unchanged)
+    #[serde(default)]
+    pub ipv6_autoconf: Option<Ipv6Autoconf>,
+    /// MTU (None = leave unchanged)
+    #[serde(default)]
+    pub mtu: Option<u32>,
+    /// Administrative state
+    #[serde(default = "default_true")]
+    pub up: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl LinkConfig {
+    /// A link with no addressing.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            addresses: Vec::new(),
+            dhcp: false,
+            ipv6_autoconf: None,
+            mtu: None,
+            up: true,
+        }
+    }
+}
+
+/// How a link obtains IPv6 addresses besides its static ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ipv6Autoconf {
+    /// Static addresses only; router advertisements are ignored
+    Off,
+    /// Addresses and default route from router advertisements
+    Slaac,
+    /// Addresses from a DHCPv6 server, default route from router advertisements
+    Dhcpv6,
+}
+
+impl fmt::Display for Ipv6Autoconf {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ipv6Autoconf::Off => write!(f, "off"),
+            Ipv6Autoconf::Slaac => write!(f, "SLAAC"),
+            Ipv6Autoconf::Dhcpv6 => write!(f, "DHCPv6"),
+        }
+    }
+}
+
+/// A Linux bridge.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BridgeConfig {
+    /// Bridge name
+    pub name: String,
+    /// Member links (NICs, bonds or VLANs)
+    #[serde(default)]
+    pub ports: Vec<String>,
+    /// Enable spanning tree
+    #[serde(default)]
+    pub stp: bool,
+}
+
+/// A Linux bond.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BondConfig {
+    /// Bond name
+    pub name: String,
+    /// Bonding mode ("active-backup", "802.3ad", "balance-alb", ...)
+    pub mode: String,
+    /// Member NICs
+    #[serde(default)]
+    pub members: Vec<String>,
+    /// MII link monitoring interval in milliseconds
+    #[serde(default)]
+    pub miimon: Option<u32>,
+    /// LACPDU rate for 802.3ad ("slow" or "fast")
+    #[serde(default)]
+    pub lacp_rate: Option<String>,
+    /// Transmit hash policy for balance-xor and 802.3ad ("layer2", "layer3+4", ...)
+    #[serde(default)]
+    pub xmit_hash_policy: Option<String>,
+    /// Preferred member for active-backup, balance-tlb and balance-alb
+    #[serde(default)]
+    pub primary: Option<String>,
+}
+
+impl BondConfig {
+    /// A bond with the default options for `mode`.
+    pub fn new(name: impl Into<String>, mode: impl Into<String>, members: Vec<String>) -> Self {
+        Self {
+            name: name.into(),
+            mode: mode.into(),
+            members,
+            miimon: None,
+            lacp_rate: None,
+            xmit_hash_policy: None,
+            primary: None,
+        }
+    }
+
+    /// Whether `current` must be recreated to get these options.
+    ///
+    /// Options left unset here are not compared.
+    fn needs_recreate(&self, current: &BondConfig) -> bool {
+        let differs = |want: &Option<String>, have: &Option<String>| want.is_some() && want != have;
+        self.mode != current.mode
+            || (self.miimon.is_some() && self.miimon != current.miimon)
+            || differs(&self.lacp_rate, &current.lacp_rate)
+            || differs(&self.xmit_hash_policy, &current.xmit_hash_policy)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if !BOND_MODES.contains(&self.mode.as_str()) {
+            bail!("Unknown bonding mode '{}' on {} (expected one of {})", self.mode, self.name, BOND_MODES.join(", "));
+        }
+        if let Some(miimon) = self.miimon {
+            if miimon == 0 {
+                bail!("miimon on {} must be greater than 0", self.name);
+            }
+        }
+        if let Some(ref rate) = self.lacp_rate {
+            if self.mode != "802.3ad" {
+                bail!("lacpRate is only valid for 802.3ad bonds ({} is {})", self.name, self.mode);
+            }
+            if !matches!(rate.as_str(), "slow" | "fast") {
+                bail!("Invalid LACP rate '{}' on {} (expected slow or fast)", rate, self.name);
+            }
+        }
+        if let Some(ref policy) = self.xmit_hash_policy {
+            if !matches!(self.mode.as_str(), "balance-xor" | "802.3ad") {
+                bail!("xmitHashPolicy is only valid for balance-xor and 802.3ad bonds ({} is {})", self.name, self.mode);
+            }
+            if !XMIT_HASH_POLICIES.contains(&policy.as_str()) {
+                bail!("Invalid transmit hash policy '{}' on {}", policy, self.name);
+            }
+        }
+        if let Some(ref primary) = self.primary {
+            if !matches!(self.mode.as_str(), "active-backup" | "balance-tlb" | "balance-alb") {
+                bail!("A primary member is only valid for active-backup, balance-tlb and balance-alb bonds");
+            }
+            if !self.members.contains(primary) {
+                bail!("Primary {} is not a member of {}", primary, self.name);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// An 802.1Q VLAN sub-interface.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VlanConfig {
+    /// VLAN link name (e.g. "eth0.100")
+    pub name: String,
+    /// Parent link
+    pub parent: String,
+    /// VLAN ID (1-4094)
+    pub id: u16,
+}
+
+/// A static route.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RouteConfig {
+    /// Destination in CIDR notation, or "default"
+    pub destination: String,
+    /// Next hop
+    #[serde(default)]
+    pub gateway: Option<String>,
+    /// Output link
+    #[serde(default)]
+    pub dev: Option<String>,
+    /// Route metric
+    #[serde(default)]
+    pub metric: Option<u32>,
+}
+
+/// Resolver settings.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DnsSettings {
+    #[serde(default)]
+    pub nameservers: Vec<String>,
+    #[serde(default)]
+    pub search_domains: Vec<String>,
+}
+
+/// Complete host network configuration.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HostNetworkConfig {
+    #[serde(default)]
+    pub links: Vec<LinkConfig>,
+    #[serde(default)]
+    pub bridges: Vec<BridgeConfig>,
+    #[serde(default)]
+    pub bonds: Vec<BondConfig>,
+    #[serde(default)]
+    pub vlans: Vec<VlanConfig>,
+    #[serde(default)]
+    pub routes: Vec<RouteConfig>,
+    #[serde(default)]
+    pub dns: DnsSettings,
+}
+
+/// A single step of a network transaction.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum NetworkChange {
+    CreateBond {
+        name: String,
+        mode: String,
+        miimon: Option<u32>,
+        lacp_rate: Option<String>,
+        xmit_hash_policy: Option<String>,
+    },
+    SetBondPrimary { name: String, primary: String },
+    CreateVlan { name: String, parent: String, id: u16 },
+    CreateBridge { name: String, stp: bool },
+    SetBridgeStp { name: String, stp: bool },
+    ReleaseLink { link: String, master: String },
+    EnslaveLink { link: String, master: String },
+    SetMtu { link: String, mtu: u32 },
+    SetLinkState { link: String, up: bool },
+    StopDhcp { link: String },
+    RemoveAddress { link: String, address: String },
+    AddAddress { link: String, address: String },
+    StartDhcp { link: String },
+    SetIpv6Autoconf { link: String, mode: Ipv6Autoconf },
+    DeleteRoute { route: RouteConfig },
+    AddRoute { route: RouteConfig },
+    DeleteLink { name: String },
+    SetDns { dns: DnsSettings },
+}
+
+impl fmt::Display for NetworkChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkChange::CreateBond { name, mode, .. } => write!(f, "create bond {} (mode {})", name, mode),
+            NetworkChange::SetBondPrimary { name, primary } => write!(f, "set primary of {} to {}", name, primary),
+            NetworkChange::CreateVlan { name, parent, id } => write!(f, "create VLAN {} on {} (id {})", name, parent, id),
+            NetworkChange::CreateBridge { name, .. } => write!(f, "create bridge {}", name),
+            NetworkChange::SetBridgeStp { name, stp } => write!(f, "set STP {} on {}", if *stp { "on" } else { "off" }, name),
+            NetworkChange::ReleaseLink { link, master } => write!(f, "remove {} from {}", link, master),
+            NetworkChange::EnslaveLink { link, master } => write!(f, "add {} to {}", link, master),
+            NetworkChange::SetMtu { link, mtu } => write!(f, "set MTU {} on {}", mtu, link),
+            NetworkChange::SetLinkState { link, up } => write!(f, "set {} {}", link, if *up { "up" } else { "down" }),
+            NetworkChange::StopDhcp { link } => write!(f, "stop DHCP on {}", link),
+            NetworkChange::RemoveAddress { link, address } => write!(f, "remove address {} from {}", address, link),
+            NetworkChange::AddAddress { link, address } => write!(f, "add address {} to {}", address, link),
+            NetworkChange::StartDhcp { link } => write!(f, "start DHCP on {}", link),
+            NetworkChange::SetIpv6Autoconf { link, mode } => write!(f, "set IPv6 autoconfiguration on {} to {}", link, mode),
+            NetworkChange::DeleteRoute { route } => write!(f, "delete route {}", route_description(route)),
+            NetworkChange::AddRoute { route } => write!(f, "add route {}", route_description(route)),
+            NetworkChange::DeleteLink { name } => write!(f, "delete {}", name),
+            NetworkChange::SetDns { dns } => write!(f, "set DNS servers {}", dns.nameservers.join(", ")),
+        }
+    }
+}
+
+fn route_description(route: &RouteConfig) -> String {
+    let mut s = route.destination.clone();
+    if let Some(ref gw) = route.gateway {
+        s.push_str(&format!(" via {}", gw));
+    }
+    if let Some(ref dev) = route.dev {
+        s.push_str(&format!(" dev {}", dev));
+    }
+    if let Some(metric) = route.metric {
+        s.push_str(&format!(" metric {}", metric));
+    }
+    s
+}
+
+// =============================================================================
+// Validation
+// =============================================================================
+
+/// Parse a CIDR address ("10.0.0.5/24", "fd00::5/64").
+pub(crate) fn parse_cidr(cidr: &str) -> Result<(IpAddr, u8)> {
+    let (addr, prefix) = cidr.split_once('/')
+        .with_context(|| format!("Address {} is missing a prefix length", cidr))?;
+    let addr: IpAddr = addr.parse().with_context(|| format!("Invalid address in {}", cidr))?;
+    let prefix: u8 = prefix.parse().with_context(|| format!("Invalid prefix length in {}", cidr))?;
+    let max = if addr.is_ipv4() { 32 } else { 128 };
+    if prefix > max {
+        bail!("Prefix length {} is out of range for {}", prefix, addr);
+    }
+    Ok((addr, prefix))
+}
+
+/// Convert a dotted netmask ("255.255.255.0") or prefix length ("24") to a prefix length.
+pub fn netmask_to_prefix(netmask: &str) -> Result<u8> {
+    if let Ok(prefix) = netmask.parse::<u8>() {
+        if prefix <= 32 {
+            return Ok(prefix);
+        }
+    }
+
+    let mask: std::net::Ipv4Addr = netmask.parse()
+        .with_context(|| format!("Invalid netmask {}", netmask))?;
+    let bits = u32::from(mask);
+    let prefix = bits.leading_ones();
+    if bits.checked_shl(prefix).unwrap_or(0) != 0 {
+        bail!("Netmask {} is not contiguous", netmask);
+    }
+    Ok(prefix as u8)
+}
+
+impl HostNetworkConfig {
+    /// Check the configuration for internal consistency.
+    pub fn validate(&self) -> Result<()> {
+        let mut names = BTreeSet::new();
+        for name in self.bridges.iter().map(|b| &b.name)
+            .chain(self.bonds.iter().map(|b| &b.name))
+            .chain(self.vlans.iter().map(|v| &v.name))
+        {
+            if !valid_link_name(name) {
+                bail!("Invalid link name '{}'", name);
+            }
+            if !names.insert(name.as_str()) {
+                bail!("Link '{}' is declared more than once", name);
+            }
+        }
+
+        let mut seen_links = BTreeSet::new();
+        for link in &self.links {
+            if !seen_links.insert(link.name.as_str()) {
+                bail!("Link '{}' appears more than once in links", link.name);
+            }
+            for address in &link.addresses {
+                parse_cidr(address)?;
+            }
+            if let Some(mtu) = link.mtu {
+                if !(68..=65535).contains(&mtu) {
+                    bail!("MTU {} on {} is out of range", mtu, link.name);
+                }
+            }
+        }
+
+        for vlan in &self.vlans {
+            if !(1..=4094).contains(&vlan.id) {
+                bail!("VLAN ID {} on {} is out of range (1-4094)", vlan.id, vlan.name);
+            }
+            if vlan.parent == vlan.name {
+                bail!("VLAN {} cannot be its own parent", vlan.name);
+            }
+        }
+
+        for bond in &self.bonds {
+            bond.validate()?;
+        }
+
+        // A link can have at most one master
+        let mut masters: BTreeMap<&str, &str> = BTreeMap::new();
+        let memberships = self.bridges.iter()
+            .flat_map(|b| b.ports.iter().map(move |p| (p.as_str(), b.name.as_str())))
+            .chain(self.bonds.iter().flat_map(|b| b.members.iter().map(move |m| (m.as_str(), b.name.as_str()))));
+        for (link, master) in memberships {
+            if link == master {
+                bail!("{} cannot be a member of itself", link);
+            }
+            if let Some(other) = masters.insert(link, master) {
+                bail!("{} is a member of both {} and {}", link, other, master);
+            }
+        }
+
+        // Enslaved links cannot carry addresses
+        for link in &self.links {
+            if let Some(master) = masters.get(link.name.as_str()) {
+                let autoconf = matches!(link.ipv6_autoconf, Some(Ipv6Autoconf::Slaac | Ipv6Autoconf::Dhcpv6));
+                if !link.addresses.is_empty() || link.dhcp || autoconf {
+                    bail!("{} is a member of {} and cannot have its own addresses", link.name, master);
+                }
+            }
+        }
+
+        for route in &self.routes {
+            if route.destination != "default" {
+                parse_cidr(&route.destination)?;
+            }
+            if let Some(ref gw) = route.gateway {
+                gw.parse::<IpAddr>().with_context(|| format!("Invalid gateway {}", gw))?;
+            }
+            if route.gateway.is_none() && route.dev.is_none() {
+                bail!("Route {} needs a gateway or a device", route.destination);
+            }
+        }
+
+        for ns in &self.dns.nameservers {
+            ns.parse::<IpAddr>().with_context(|| format!("Invalid nameserver {}", ns))?;
+        }
+
+        Ok(())
+    }
+
+    /// Links declared as virtual devices (bridges, bonds, VLANs).
+    fn virtual_links(&self) -> BTreeSet<&str> {
+        self.bridges.iter().map(|b| b.name.as_str())
+            .chain(self.bonds.iter().map(|b| b.name.as_str()))
+            .chain(self.vlans.iter().map(|v| v.name.as_str()))
+            .collect()
+    }
+
+    /// Desired master of each enslaved link.
+    fn masters(&self) -> BTreeMap<&str, &str> {
+        self.bridges.iter()
+            .flat_map(|b| b.ports.iter().map(move |p| (p.as_str(), b.name.as_str())))
+            .chain(self.bonds.iter().flat_map(|b| b.members.iter().map(move |m| (m.as_str(), b.name.as_str()))))
+            .collect()
+    }
+
+    /// Check that every referenced link exists in `current` or is declared here.
+    pub fn validate_against(&self, current: &HostNetworkConfig) -> Result<()> {
+        let existing: BTreeSet<&str> = current.links.iter().map(|l| l.name.as_str()).collect();
+        let declared = self.virtual_links();
+        let known = |name: &str| existing.contains(name) || declared.contains(name);
+
+        for link in &self.links {
+            if !known(&link.name) {
+                bail!("Link '{}' does not exist or is not managed by the host network configuration", link.name);
+            }
+        }
+        for (link, master) in self.masters() {
+            if !known(link) {
+                bail!("{} member '{}' does not exist", master, link);
+            }
+        }
+        for vlan in &self.vlans {
+            if !known(&vlan.parent) {
+                bail!("VLAN {} parent '{}' does not exist", vlan.name, vlan.parent);
+            }
+        }
+        for route in &self.routes {
+            if let Some(ref dev) = route.dev {
+                if !known(dev) {
+                    bail!("Route {} uses unknown device '{}'", route.destination, dev);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn link(&self, name: &str) -> Option<&LinkConfig> {
+        self.links.iter().find(|l| l.name == name)
+    }
+
+    /// Move the addresses, DHCP, MTU and routes of `from` to the link `to`.
+    ///
+    /// Used when links are enslaved to a new bridge or bond, so the host stays
+    /// reachable over the same addresses.
+    pub fn move_addressing(&mut self, from: &[String], to: &str) {
+        let mut target = self.links.iter()
+            .position(|l| l.name == to)
+            .map(|i| self.links.remove(i))
+            .unwrap_or_else(|| LinkConfig::new(to));
+
+        for name in from {
+            if let Some(link) = self.links.iter_mut().find(|l| &l.name == name) {
+                target.addresses.append(&mut link.addresses);
+                target.dhcp |= std::mem::take(&mut link.dhcp);
+                if let Some(mode) = link.ipv6_autoconf.take() {
+                    target.ipv6_autoconf.get_or_insert(mode);
+                }
+                if target.mtu.is_none() {
+                    target.mtu = link.mtu;
+                }
+            }
+            for route in self.routes.iter_mut().filter(|r| r.dev.as_deref() == Some(name.as_str())) {
+                route.dev = Some(to.to_string());
+            }
+        }
+
+        target.up = true;
+        self.links.push(target);
+    }
+
+    /// Remove a bridge, bond or VLAN.
+    ///
+    /// The addressing of a bridge or bond goes back to its first port or
+    /// member; a VLAN's addresses and routes are dropped. Fails if another
+    /// link still uses it.
+    pub fn remove_virtual_link(&mut self, name: &str) -> Result<()> {
+        if let Some((user, _)) = self.masters().into_iter().find(|(link, _)| *link == name) {
+            bail!("{} is a member of {}", name, user);
+        }
+        if let Some(vlan) = self.vlans.iter().find(|v| v.parent == name) {
+            bail!("{} is the parent of VLAN {}", name, vlan.name);
+        }
+
+        let heir = if let Some(i) = self.bridges.iter().position(|b| b.name == name) {
+            self.bridges.remove(i).ports.into_iter().next()
+        } else if let Some(i) = self.bonds.iter().position(|b| b.name == name) {
+            self.bonds.remove(i).members.into_iter().next()
+        } else if let Some(i) = self.vlans.iter().position(|v| v.name == name) {
+            self.vlans.remove(i);
+            None
+        } else {
+            bail!("{} is not a bridge, bond or VLAN", name);
+        };
+
+        match heir {
+            Some(heir) => self.move_addressing(&[name.to_string()], &heir),
+            None => self.routes.retain(|r| r.dev.as_deref() != Some(name)),
+        }
+        self.links.retain(|l| l.name != name);
+        Ok(())
+    }
+}
+
+fn valid_link_name(name: &str) -> bool {
+    // IFNAMSIZ is 16 including the terminator
+    !name.is_empty()
+        && name.len() <= 15
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+fn is_managed_name(name: &str) -> bool {
+    !UNMANAGED_PREFIXES.iter().any(|p| name == *p || (p.len() > 2 && name.starts_with(p)))
+}
+
+// =============================================================================
+// Planning
+// =============================================================================
+
+/// Compute the changes that turn `current` into `desired`, in apply order.
+pub fn plan(current: &HostNetworkConfig, desired: &HostNetworkConfig) -> Vec<NetworkChange> {
+    let mut changes = Vec::new();
+
+    // Virtual links whose definition changed are recreated
+    let mut recreate: BTreeSet<String> = BTreeSet::new();
+    for bond in &desired.bonds {
+        if let Some(cur) = current.bonds.iter().find(|b| b.name == bond.name) {
+            if bond.needs_recreate(cur) {
+                recreate.insert(bond.name.clone());
+            }
+        }
+    }
+    for vlan in &desired.vlans {
+        if let Some(cur) = current.vlans.iter().find(|v| v.name == vlan.name) {
+            if cur.parent != vlan.parent || cur.id != vlan.id {
+                recreate.insert(vlan.name.clone());
+            }
+        }
+    }
+
+    let current_virtual = current.virtual_links();
+    let desired_virtual = desired.virtual_links();
+    let exists = |name: &str| current_virtual.contains(name) && !recreate.contains(name);
+
+    let deleted: Vec<&str> = current.bridges.iter().map(|b| b.name.as_str())
+        .chain(current.vlans.iter().map(|v| v.name.as_str()))
+        .chain(current.bonds.iter().map(|b| b.name.as_str()))
+        .filter(|n| !desired_virtual.contains(n) || recreate.contains(*n))
+        .collect();
+
+    // Memberships: release first so links are free to move between masters
+    let current_masters = current.masters();
+    let desired_masters = desired.masters();
+    for (link, master) in &current_masters {
+        if desired_masters.get(link) != Some(master) || recreate.contains(*master) || recreate.contains(*link) {
+            changes.push(NetworkChange::ReleaseLink { link: link.to_string(), master: master.to_string() });
+        }
+    }
+
+    // Recreated links are removed before their replacements are created
+    for name in &recreate {
+        changes.push(NetworkChange::DeleteLink { name: name.clone() });
+    }
+
+    for bond in &desired.bonds {
+        if !exists(&bond.name) {
+            changes.push(NetworkChange::CreateBond {
+                name: bond.name.clone(),
+                mode: bond.mode.clone(),
+                miimon: bond.miimon,
+                lacp_rate: bond.lacp_rate.clone(),
+                xmit_hash_policy: bond.xmit_hash_policy.clone(),
+            });
+        }
+    }
+    for vlan in &desired.vlans {
+        if !exists(&vlan.name) {
+            changes.push(NetworkChange::CreateVlan {
+                name: vlan.name.clone(),
+                parent: vlan.parent.clone(),
+                id: vlan.id,
+            });
+        }
+    }
+    for bridge in &desired.bridges {
+        match current.bridges.iter().find(|b| b.name == bridge.name) {
+            Some(cur) if cur.stp != bridge.stp => {
+                changes.push(NetworkChange::SetBridgeStp { name: bridge.name.clone(), stp: bridge.stp });
+            }
+            Some(_) => {}
+            None => changes.push(NetworkChange::CreateBridge { name: bridge.name.clone(), stp: bridge.stp }),
+        }
+    }
+
+    for (link, master) in &desired_masters {
+        let unchanged = current_masters.get(link) == Some(master) && exists(master) && !recreate.contains(*link);
+        if !unchanged {
+            changes.push(NetworkChange::EnslaveLink { link: link.to_string(), master: master.to_string() });
+        }
+    }
+
+    // The primary can only be set once it is a member
+    for bond in &desired.bonds {
+        let Some(ref primary) = bond.primary else { continue };
+        let cur = current.bonds.iter().find(|b| b.name == bond.name);
+        if !exists(&bond.name) || cur.and_then(|c| c.primary.as_ref()) != Some(primary) {
+            changes.push(NetworkChange::SetBondPrimary { name: bond.name.clone(), primary: primary.clone() });
+        }
+    }
+
+    // Link settings. Links only in `current` keep their MTU and state but lose addresses.
+    let mut link_names: BTreeSet<&str> = current.links.iter().map(|l| l.name.as_str()).collect();
+    link_names.extend(desired.links.iter().map(|l| l.name.as_str()));
+    link_names.extend(desired_virtual.iter().copied());
+    link_names.retain(|n| !deleted.contains(n) || desired_virtual.contains(n));
+
+    let mut readdressed: BTreeSet<String> = BTreeSet::new();
+    let mut removals = Vec::new();
+    let mut additions = Vec::new();
+    for name in &link_names {
+        let fresh = desired_virtual.contains(name) && !exists(name);
+        let cur = if fresh { None } else { current.link(name) };
+        let unconfigured = LinkConfig::new(*name);
+        let want = desired.link(name).unwrap_or(&unconfigured);
+        let explicit = desired.link(name).is_some();
+
+        if let Some(mtu) = want.mtu {
+            if cur.and_then(|c| c.mtu) != Some(mtu) {
+                changes.push(NetworkChange::SetMtu { link: name.to_string(), mtu });
+            }
+        }
+        if explicit || fresh {
+            let is_up = cur.map(|c| c.up).unwrap_or(false);
+            if is_up != want.up {
+                changes.push(NetworkChange::SetLinkState { link: name.to_string(), up: want.up });
+            }
+        }
+
+        let cur_dhcp = cur.map(|c| c.dhcp).unwrap_or(false);
+        if cur_dhcp && !want.dhcp {
+            removals.push(NetworkChange::StopDhcp { link: name.to_string() });
+            readdressed.insert(name.to_string());
+        }
+
+        let cur_addrs: BTreeSet<&str> = cur.map(|c| c.addresses.iter().map(String::as_str).collect()).unwrap_or_default();
+        let want_addrs: BTreeSet<&str> = want.addresses.iter().map(String::as_str).collect();
+        for address in cur_addrs.difference(&want_addrs) {
+            removals.push(NetworkChange::RemoveAddress { link: name.to_string(), address: address.to_string() });
+            readdressed.insert(name.to_string());
+        }
+        for address in want_addrs.difference(&cur_addrs) {
+            additions.push(NetworkChange::AddAddress { link: name.to_string(), address: address.to_string() });
+            readdressed.insert(name.to_string());
+        }
+
+        if want.dhcp && !cur_dhcp {
+            additions.push(NetworkChange::StartDhcp { link: name.to_string() });
+            readdressed.insert(name.to_string());
+        }
+
+        if let Some(mode) = want.ipv6_autoconf {
+            if cur.and_then(|c| c.ipv6_autoconf) != Some(mode) {
+                let change = NetworkChange::SetIpv6Autoconf { link: name.to_string(), mode };
+                if mode == Ipv6Autoconf::Off {
+                    removals.push(change);
+                } else {
+                    additions.push(change);
+                }
+                readdressed.insert(name.to_string());
+            }
+        }
+    }
+
+    // Stale routes go before the addresses they depend on. Address changes can
+    // make the kernel drop routes through a link, so routes on re-addressed
+    // links are always (re)installed afterwards.
+    let cur_routes: BTreeSet<&RouteConfig> = current.routes.iter().collect();
+    let want_routes: BTreeSet<&RouteConfig> = desired.routes.iter().collect();
+    for route in cur_routes.difference(&want_routes) {
+        changes.push(NetworkChange::DeleteRoute { route: (*route).clone() });
+    }
+    changes.extend(removals);
+    changes.extend(additions);
+
+    for route in &desired.routes {
+        let touched = match route.dev {
+            Some(ref dev) => readdressed.contains(dev) || recreate.contains(dev),
+            None => !readdressed.is_empty(),
+        };
+        if !cur_routes.contains(route) || touched {
+            changes.push(NetworkChange::AddRoute { route: route.clone() });
+        }
+    }
+
+    // Delete virtual links no longer wanted (bridges before the links under them)
+    for name in deleted {
+        if !recreate.contains(name) {
+            changes.push(NetworkChange::DeleteLink { name: name.to_string() });
+        }
+    }
+
+    if current.dns != desired.dns {
+        changes.push(NetworkChange::SetDns { dns: desired.dns.clone() });
+    }
+
+    changes
+}
+
+// =============================================================================
+// Capturing the current state
+// =============================================================================
+
+fn run_json(args: &[&str]) -> Result<serde_json::Value> {
+    let output = Command::new("ip")
+        .args(args)
+        .output()
+        .context("Failed to execute ip")?;
+    if !output.status.success() {
+        bail!("ip {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    serde_json::from_slice(&output.stdout).context("Failed to parse ip JSON output")
+}
+
+/// Capture the current host network configuration.
+#[instrument]
+pub fn capture() -> Result<HostNetworkConfig> {
+    let links = run_json(&["-j", "-d", "link", "show"])?;
+    let addrs = run_json(&["-j", "addr", "show"])?;
+    // `ip route` lists IPv4 routes only
+    let mut routes = run_json(&["-j", "route", "show"])?;
+    if let (Some(v4), Ok(serde_json::Value::Array(v6))) = (routes.as_array_mut(), run_json(&["-6", "-j", "route", "show"])) {
+        v4.extend(v6);
+    }
+    let resolv = std::fs::read_to_string(RESOLV_CONF).unwrap_or_default();
+
+    let mut config = parse_state(&links, &addrs, &routes, &resolv);
+    let enslaved: BTreeSet<String> = config.masters().keys().map(|l| l.to_string()).collect();
+    for link in config.links.iter_mut().filter(|l| !enslaved.contains(&l.name)) {
+        link.ipv6_autoconf = ipv6_autoconf_state(&link.name);
+    }
+    Ok(config)
+}
+
+/// Effective IPv6 autoconfiguration of a link (None if IPv6 is disabled on it).
+fn ipv6_autoconf_state(link: &str) -> Option<Ipv6Autoconf> {
+    let sysctl = |key: &str| {
+        std::fs::read_to_string(format!("/proc/sys/net/ipv6/conf/{}/{}", link, key))
+            .ok()
+            .map(|v| v.trim().to_string())
+    };
+    if sysctl("disable_ipv6")? == "1" {
+        return None;
+    }
+    if dhcpv6_client_running(link) {
+        return Some(Ipv6Autoconf::Dhcpv6);
+    }
+    // accept_ra=1 only takes effect while forwarding is off
+    let accept_ra = match sysctl("accept_ra").as_deref() {
+        Some("2") => true,
+        Some("1") => sysctl("forwarding").as_deref() != Some("1"),
+        _ => false,
+    };
+    if accept_ra && sysctl("autoconf").as_deref() == Some("1") {
+        Some(Ipv6Autoconf::Slaac)
+    } else {
+        Some(Ipv6Autoconf::Off)
+    }
+}
+
+/// Build a `HostNetworkConfig` from `ip -j -d link`, `ip -j addr`, `ip -j route` (both families)
+/// output and the resolver configuration.
+fn parse_state(
+    links: &serde_json::Value,
+    addrs: &serde_json::Value,
+    routes: &serde_json::Value,
+    resolv: &str,
+) -> HostNetworkConfig {
+    let mut config = HostNetworkConfig::default();
+    let empty = Vec::new();
+
+    let mut kinds: BTreeMap<String, Option<String>> = BTreeMap::new();
+    let mut bridge_ports: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    let mut bond_members: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for link in links.as_array().unwrap_or(&empty) {
+        let Some(name) = link["ifname"].as_str() else { continue };
+        let kind = link["linkinfo"]["info_kind"].as_str().map(str::to_string);
+        if !is_managed_name(name) || !matches!(kind.as_deref(), None | Some("bridge") | Some("bond") | Some("vlan")) {
+            continue;
+        }
+        if link["link_type"].as_str() == Some("loopback") {
+            continue;
+        }
+
+        let info = &link["linkinfo"]["info_data"];
+        match kind.as_deref() {
+            Some("bridge") => config.bridges.push(BridgeConfig {
+                name: name.to_string(),
+                ports: Vec::new(),
+                stp: info["stp_state"].as_u64().unwrap_or(0) != 0,
+            }),
+            Some("bond") => {
+                let mode = info["mode"].as_str().unwrap_or("balance-rr").to_string();
+                // The kernel reports every option; keep the ones that apply to the mode
+                let lacp_rate = info["ad_lacp_rate"].as_str()
+                    .filter(|_| mode == "802.3ad")
+                    .map(str::to_string);
+                let xmit_hash_policy = info["xmit_hash_policy"].as_str()
+                    .filter(|_| matches!(mode.as_str(), "balance-xor" | "802.3ad"))
+                    .map(str::to_string);
+                config.bonds.push(BondConfig {
+                    name: name.to_string(),
+                    members: Vec::new(),
+                    miimon: info["miimon"].as_u64().filter(|&m| m > 0).map(|m| m as u32),
+                    lacp_rate,
+                    xmit_hash_policy,
+                    primary: info["primary"].as_str().map(str::to_string),
+                    mode,
+                })
+            }
+            Some("vlan") => config.vlans.push(VlanConfig {
+                name: name.to_string(),
+                parent: link["link"].as_str().unwrap_or_default().to_string(),
+                id: info["id"].as_u64().unwrap_or(0) as u16,
+            }),
+            _ => {}
+        }
+
+        if let Some(master) = link["master"].as_str() {
+            match link["linkinfo"]["info_slave_kind"].as_str() {
+                Some("bridge") => bridge_ports.entry(master.to_string()).or_default().push(name.to_string()),
+                Some("bond") => bond_members.entry(master.to_string()).or_default().push(name.to_string()),
+                _ => {}
+            }
+        }
+
+        let up = link["flags"].as_array()
+            .map(|flags| flags.iter().any(|f| f.as_str() == Some("UP")))
+            .unwrap_or(false);
+        config.links.push(LinkConfig {
+            name: name.to_string(),
+            addresses: Vec::new(),
+            dhcp: false,
+            ipv6_autoconf: None,
+            mtu: link["mtu"].as_u64().map(|m| m as u32),
+            up,
+        });
+        kinds.insert(name.to_string(), kind);
+    }
+
+    for bridge in &mut config.bridges {
+        bridge.ports = bridge_ports.remove(&bridge.name).unwrap_or_default();
+    }
+    for bond in &mut config.bonds {
+        bond.members = bond_members.remove(&bond.name).unwrap_or_default();
+    }
+
+    for entry in addrs.as_array().unwrap_or(&empty) {
+        let Some(name) = entry["ifname"].as_str() else { continue };
+        let Some(link) = config.links.iter_mut().find(|l| l.name == name) else { continue };
+
+        for addr in entry["addr_info"].as_array().unwrap_or(&empty) {
+            if addr["scope"].as_str() != Some("global") {
+                continue;
+            }
+            let (Some(local), Some(prefix)) = (addr["local"].as_str(), addr["prefixlen"].as_u64()) else { continue };
+            if addr["dynamic"].as_bool().unwrap_or(false) {
+                // DHCP leases (IPv4) and SLAAC addresses (IPv6) are not static config
+                if addr["family"].as_str() == Some("inet") {
+                    link.dhcp = true;
+                }
+                continue;
+            }
+            link.addresses.push(format!("{}/{}", local, prefix));
+        }
+    }
+
+    for route in routes.as_array().unwrap_or(&empty) {
+        // Kernel, DHCP and RA routes follow from addresses and leases
+        if !matches!(route["protocol"].as_str(), Some("boot") | Some("static")) {
+            continue;
+        }
+        let Some(dst) = route["dst"].as_str() else { continue };
+        let dev = route["dev"].as_str();
+        if dev.map(|d| !kinds.contains_key(d)).unwrap_or(false) {
+            continue;
+        }
+        config.routes.push(RouteConfig {
+            destination: dst.to_string(),
+            gateway: route["gateway"].as_str().map(str::to_string),
+            dev: dev.map(str::to_string),
+            metric: route["metric"].as_u64().map(|m| m as u32),
+        });
+    }
+
+    config.dns = parse_resolv_conf(resolv);
+    config
+}
+
+fn parse_resolv_conf(content: &str) -> DnsSettings {
+    let mut dns = DnsSettings::default();
+    for line in content.lines() {
+        let mut parts = line.split_whitespace();
+        match parts.next() {
+            Some("nameserver") => dns.nameservers.extend(parts.next().map(str::to_string)),
+            Some("search") => dns.search_domains = parts.map(str::to_string).collect(),
+            _ => {}
+        }
+    }
+    dns
+}
+
+// =============================================================================
+// Applying changes
+// =============================================================================
+
+fn ip(args: &[&str]) -> Result<()> {
+    debug!(args = ?args, "ip");
+    let output = Command::new("ip")
+        .args(args)
+        .output()
+        .context("Failed to execute ip")?;
+    if !output.status.success() {
+        bail!("ip {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+fn route_args<'a>(route: &'a RouteConfig, metric: &'a str) -> Vec<&'a str> {
+    let mut args = Vec::new();
+    if route.destination.contains(':') || route.gateway.as_deref().map(|g| g.contains(':')).unwrap_or(false) {
+        args.push("-6");
+    }
+    args.extend(["route", "replace", route.destination.as_str()]);
+    if let Some(ref gw) = route.gateway {
+        args.extend(["via", gw.as_str()]);
+    }
+    if let Some(ref dev) = route.dev {
+        args.extend(["dev", dev.as_str()]);
+    }
+    if route.metric.is_some() {
+        args.extend(["metric", metric]);
+    }
+    args
+}
+
+fn stop_dhcp_client(link: &str) {
+    // Leave the DHCPv6 clients (udhcpc6, dhclient -6) running
+    for pattern in [format!("udhcpc .*-i {}", link), format!("dhclient (-[^6 ][^ ]* )*{}$", link)] {
+        let _ = Command::new("pkill").args(["-f", &pattern]).output();
+    }
+    // Drop the lease address; static addresses are handled by the plan
+    let _ = Command::new("ip").args(["-4", "addr", "flush", "dev", link, "dynamic"]).output();
+}
+
+fn start_dhcp_client(link: &str) -> Result<()> {
+    let pidfile = format!("/run/udhcpc.{}.pid", link);
+    match Command::new("udhcpc").args(["-b", "-i", link, "-p", &pidfile]).output() {
+        Ok(output) if output.status.success() => return Ok(()),
+        Ok(output) => warn!(link = %link, stderr = %String::from_utf8_lossy(&output.stderr).trim(), "udhcpc failed"),
+        Err(_) => debug!("udhcpc not available, trying dhclient"),
+    }
+
+    let output = Command::new("dhclient")
+        .args(["-nw", link])
+        .output()
+        .context("No DHCP client (udhcpc or dhclient) available")?;
+    if !output.status.success() {
+        bail!("dhclient failed on {}: {}", link, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+fn dhcpv6_client_patterns(link: &str) -> [String; 2] {
+    [format!("udhcpc6 .*-i {}", link), format!("dhclient -6 .*{}$", link)]
+}
+
+fn dhcpv6_client_running(link: &str) -> bool {
+    dhcpv6_client_patterns(link).iter().any(|pattern| {
+        Command::new("pgrep").args(["-f", pattern]).output().is_ok_and(|o| o.status.success())
+    })
+}
+
+fn start_dhcpv6_client(link: &str) -> Result<()> {
+    let pidfile = format!("/run/udhcpc6.{}.pid", link);
+    match Command::new("udhcpc6").args(["-b", "-i", link, "-p", &pidfile]).output() {
+        Ok(output) if output.status.success() => return Ok(()),
+        Ok(output) => warn!(link = %link, stderr = %String::from_utf8_lossy(&output.stderr).trim(), "udhcpc6 failed"),
+        Err(_) => debug!("udhcpc6 not available, trying dhclient"),
+    }
+
+    let output = Command::new("dhclient")
+        .args(["-6", "-nw", link])
+        .output()
+        .context("No DHCPv6 client (udhcpc6 or dhclient) available")?;
+    if !output.status.success() {
+        bail!("dhclient -6 failed on {}: {}", link, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+fn set_ipv6_autoconf(link: &str, mode: Ipv6Autoconf) -> Result<()> {
+    let sysctl = |key: &str, value: &str| {
+        std::fs::write(format!("/proc/sys/net/ipv6/conf/{}/{}", link, key), value)
+            .with_context(|| format!("Failed to set IPv6 {} on {}", key, link))
+    };
+
+    for pattern in dhcpv6_client_patterns(link) {
+        let _ = Command::new("pkill").args(["-f", &pattern]).output();
+    }
+    match mode {
+        Ipv6Autoconf::Off => {
+            sysctl("accept_ra", "0")?;
+            sysctl("autoconf", "0")?;
+            let _ = Command::new("ip").args(["-6", "addr", "flush", "dev", link, "dynamic"]).output();
+            Ok(())
+        }
+        // accept_ra=2 keeps router advertisements working on hosts that forward
+        Ipv6Autoconf::Slaac => {
+            sysctl("accept_ra", "2")?;
+            sysctl("autoconf", "1")
+        }
+        Ipv6Autoconf::Dhcpv6 => {
+            sysctl("accept_ra", "2")?;
+            sysctl("autoconf", "1")?;
+            start_dhcpv6_client(link)
+        }
+    }
+}
+
+/// Apply a single change.
+pub fn apply_change(change: &NetworkChange) -> Result<()> {
+    match change {
+        NetworkChange::CreateBond { name, mode, miimon, lacp_rate, xmit_hash_policy } => {
+            let miimon = miimon.unwrap_or(100).to_string();
+            let mut args = vec!["link", "add", "name", name, "type", "bond", "mode", mode, "miimon", &miimon];
+            if let Some(rate) = lacp_rate {
+                args.extend(["ad_lacp_rate", rate]);
+            }
+            if let Some(policy) = xmit_hash_policy {
+                args.extend(["xmit_hash_policy", policy]);
+            }
+            ip(&args)
+        }
+        NetworkChange::SetBondPrimary { name, primary } => {
+            ip(&["link", "set", "dev", name, "type", "bond", "primary", primary])
+        }
+        NetworkChange::CreateVlan { name, parent, id } => {
+            ip(&["link", "add", "link", parent, "name", name, "type", "vlan", "id", &id.to_string()])
+        }
+        NetworkChange::CreateBridge { name, stp } => {
+            ip(&["link", "add", "name", name, "type", "bridge", "stp_state", if *stp { "1" } else { "0" }])
+        }
+        NetworkChange::SetBridgeStp { name, stp } => {
+            ip(&["link", "set", "dev", name, "type", "bridge", "stp_state", if *stp { "1" } else { "0" }])
+        }
+        NetworkChange::ReleaseLink { link, .. } => ip(&["link", "set", "dev", link, "nomaster"]),
+        NetworkChange::EnslaveLink { link, master } => {
+            // Bond members must be down while being enslaved
+            ip(&["link", "set", "dev", link, "down"])?;
+            ip(&["link", "set", "dev", link, "master", master])?;
+            ip(&["link", "set", "dev", link, "up"])
+        }
+        NetworkChange::SetMtu { link, mtu } => ip(&["link", "set", "dev", link, "mtu", &mtu.to_string()]),
+        NetworkChange::SetLinkState { link, up } => {
+            ip(&["link", "set", "dev", link, if *up { "up" } else { "down" }])
+        }
+        NetworkChange::StopDhcp { link } => {
+            stop_dhcp_client(link);
+            Ok(())
+        }
+        NetworkChange::RemoveAddress { link, address } => {
+            // Keep secondary addresses when the primary is removed
+            let sysctl = format!("/proc/sys/net/ipv4/conf/{}/promote_secondaries", link);
+            let _ = std::fs::write(sysctl, "1");
+            ip(&["addr", "del", address, "dev", link])
+        }
+        NetworkChange::AddAddress { link, address } => ip(&["addr", "add", address, "dev", link]),
+        NetworkChange::StartDhcp { link } => start_dhcp_client(link),
+        NetworkChange::SetIpv6Autoconf { link, mode } => set_ipv6_autoconf(link, *mode),
+        NetworkChange::DeleteRoute { route } => {
+            let metric = route.metric.unwrap_or(0).to_string();
+            let mut args = route_args(route, &metric);
+            let pos = args.iter().position(|a| *a == "replace").unwrap_or(0);
+            args[pos] = "del";
+            // The kernel drops routes when their link goes down; that is not a failure
+            match ip(&args) {
+                Err(e) if e.to_string().contains("No such process") => Ok(()),
+                result => result,
+            }
+        }
+        NetworkChange::AddRoute { route } => {
+            let metric = route.metric.unwrap_or(0).to_string();
+            ip(&route_args(route, &metric))
+        }
+        NetworkChange::DeleteLink { name } => ip(&["link", "del", "dev", name]),
+        NetworkChange::SetDns { dns } => {
+            let mut content = String::new();
+            if !dns.search_domains.is_empty() {
+                content.push_str(&format!("search {}\n", dns.search_domains.join(" ")));
+            }
+            for ns in &dns.nameservers {
+                content.push_str(&format!("nameserver {}\n", ns));
+            }
+            std::fs::write(RESOLV_CONF, content).context("Failed to write /etc/resolv.conf")
+        }
+    }
+}
+
+/// Apply changes in order, stopping at the first failure.
+///
+/// On failure returns the number of changes that were applied and the error.
+pub fn apply_changes(changes: &[NetworkChange]) -> std::result::Result<(), (usize, anyhow::Error)> {
+    for (i, change) in changes.iter().enumerate() {
+        info!(change = %change, "Applying network change");
+        apply_change(change).map_err(|e| (i, e.context(format!("Failed to {}", change))))?;
+    }
+    Ok(())
+}
+
+/// Restore a previously captured configuration (best effort).
+///
+/// Every change is attempted; failures are collected and returned.
+#[instrument(skip(snapshot))]
+pub fn restore(snapshot: &HostNetworkConfig) -> Vec<String> {
+    let current = match capture() {
+        Ok(c) => c,
+        Err(e) => return vec![format!("Failed to capture network state: {}", e)],
+    };
+
+    let mut errors = Vec::new();
+    for change in plan(&current, snapshot) {
+        warn!(change = %change, "Rolling back network change");
+        if let Err(e) = apply_change(&change) {
+            errors.push(format!("Failed to {}: {}", change, e));
+        }
+    }
+    errors
+}
+
+/// Default gateways currently installed (any protocol, including DHCP).
+fn default_gateways() -> Vec<String> {
+    let mut gateways = Vec::new();
+    for family in ["-4", "-6"] {
+        if let Ok(routes) = run_json(&[family, "-j", "route", "show", "default"]) {
+            gateways.extend(routes.as_array().into_iter().flatten()
+                .filter_map(|r| r["gateway"].as_str().map(str::to_string)));
+        }
+    }
+    gateways
+}
+
+/// Whether `address` is assigned to a link that is up.
+fn address_assigned(address: IpAddr) -> bool {
+    let Ok(links) = run_json(&["-j", "addr", "show", "up"]) else {
+        return false;
+    };
+    links.as_array().into_iter().flatten()
+        .flat_map(|l| l["addr_info"].as_array().into_iter().flatten())
+        .filter(|a| a["tentative"].as_bool() != Some(true) && a["dadfailed"].as_bool() != Some(true))
+        .filter_map(|a| a["local"].as_str()?.parse::<IpAddr>().ok())
+        .any(|a| a == address)
+}
+
+/// Wait until the management address is usable and a default gateway
+/// answers ping, up to `timeout`.
+///
+/// The management address check is skipped when `management` is None; the
+/// gateway check succeeds immediately when the host has no default route.
+pub fn verify_connectivity(timeout: Duration, management: Option<IpAddr>) -> Result<()> {
+    let deadline = Instant::now() + timeout;
+
+    if let Some(address) = management {
+        while !address_assigned(address) {
+            if Instant::now() >= deadline {
+                bail!("Management address {} is no longer configured on an active link", address);
+            }
+            std::thread::sleep(Duration::from_millis(500));
+        }
+        debug!(address = %address, "Management address configured");
+    }
+
+    loop {
+        let gateways = default_gateways();
+        if gateways.is_empty() {
+            debug!("No default gateway, skipping connectivity check");
+            return Ok(());
+        }
+
+        for gw in &gateways {
+            let ok = Command::new("ping")
+                .args(["-c", "1", "-W", "1", gw])
+                .output()
+                .map(|o| o.status.success())
+                .unwrap_or(false);
+            if ok {
+                debug!(gateway = %gw, "Gateway reachable");
+                return Ok(());
+            }
+        }
+
+        if Instant::now() >= deadline {
+            bail!("Default gateway ({}) did not answer within {}s", gateways.join(", "), timeout.as_secs());
+        }
+        std::thread::sleep(Duration::from_millis(500));
+    }
+}
+
+// =============================================================================
+// Persistence
+// =============================================================================
+
+impl HostNetworkConfig {
+    /// Load a configuration written by `save` (None if the file does not exist).
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        match std::fs::read(path) {
+            Ok(data) => serde_json::from_slice(&data)
+                .map(Some)
+                .with_context(|| format!("Failed to parse {}", path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        }
+    }
+
+    /// Write the configuration as JSON, replacing `path` atomically.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", tmp.display()))?;
+        std::fs::rename(&tmp, path)
+            .with_context(|| format!("Failed to replace {}", path.display()))
+    }
+}
+
+/// An applied network transaction that can still be rolled back.
+#[derive(Debug, Clone)]
+pub struct NetworkTransaction {
+    /// State before the transaction
+    pub snapshot: HostNetworkConfig,
+    /// Changes that were applied
+    pub changes: Vec<NetworkChange>,
+}
+
+impl NetworkTransaction {
+    /// Apply `desired` transactionally.
+    ///
+    /// If a change fails, the management address disappears or the gateway
+    /// stops answering within `verify_timeout`, the previous state is
+    /// restored before returning the error.
+    #[instrument(skip(desired))]
+    pub fn apply(desired: &HostNetworkConfig, verify_timeout: Duration, management: Option<IpAddr>) -> Result<Self> {
+        desired.validate()?;
+
+        let snapshot = capture()?;
+        desired.validate_against(&snapshot)?;
+        let changes = plan(&snapshot, desired);
+        if changes.is_empty() {
+            info!("Host network already matches the desired configuration");
+            return Ok(Self { snapshot, changes });
+        }
+
+        let failure = match apply_changes(&changes) {
+            Err((applied, e)) => Some(e.context(format!("{} of {} changes applied", applied, changes.len()))),
+            Ok(()) => verify_connectivity(verify_timeout, management).err(),
+        };
+
+        if let Some(e) = failure {
+            let rollback_errors = restore(&snapshot);
+            if rollback_errors.is_empty() {
+                return Err(e.context("Network configuration rolled back"));
+            }
+            return Err(e.context(format!("Rollback incomplete: {}", rollback_errors.join("; "))));
+        }
+
+        info!(changes = changes.len(), "Host network configuration applied");
+        Ok(Self { snapshot, changes })
+    }
+
+    /// Restore the state from before this transaction.
+    pub fn rollback(&self) -> Result<()> {
+        let errors = restore(&self.snapshot);
+        if !errors.is_empty() {
+            bail!("Rollback incomplete: {}", errors.join("; "));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host() -> HostNetworkConfig {
+        HostNetworkConfig {
+            links: vec![
+                LinkConfig { addresses: vec!["10.0.0.5/24".into()], mtu: Some(1500), ..LinkConfig::new("eth0") },
+                LinkConfig { mtu: Some(1500), up: false, ..LinkConfig::new("eth1") },
+            ],
+            routes: vec![RouteConfig {
+                destination: "default".into(),
+                gateway: Some("10.0.0.1".into()),
+                dev: Some("eth0".into()),
+                metric: None,
+            }],
+            dns: DnsSettings { nameservers: vec!["10.0.0.1".into()], search_domains: vec![] },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_plan_move_address_to_bridge() {
+        let current = host();
+        let mut desired = host();
+        desired.bridges.push(BridgeConfig { name: "br0".into(), ports: vec!["eth0".into()], stp: false });
+        desired.links[0].addresses.clear();
+        desired.links.push(LinkConfig { addresses: vec!["10.0.0.5/24".into()], ..LinkConfig::new("br0") });
+        desired.routes[0].dev = Some("br0".into());
+        desired.validate().unwrap();
+
+        let changes = plan(&current, &desired);
+        let pos = |c: &NetworkChange| changes.iter().position(|x| x == c).unwrap();
+
+        let create = pos(&NetworkChange::CreateBridge { name: "br0".into(), stp: false });
+        let enslave = pos(&NetworkChange::EnslaveLink { link: "eth0".into(), master: "br0".into() });
+        let remove = pos(&NetworkChange::RemoveAddress { link: "eth0".into(), address: "10.0.0.5/24".into() });
+        let add = pos(&NetworkChange::AddAddress { link: "br0".into(), address: "10.0.0.5/24".into() });
+        let route = pos(&NetworkChange::AddRoute { route: desired.routes[0].clone() });
+        assert!(create < enslave && enslave < remove && remove < add && add < route);
+        assert!(changes.contains(&NetworkChange::SetLinkState { link: "br0".into(), up: true }));
+
+        // Applying the inverse plan to the new state gets back to the original
+        let rollback = plan(&desired, &current);
+        assert!(rollback.contains(&NetworkChange::DeleteLink { name: "br0".into() }));
+        assert!(rollback.contains(&NetworkChange::AddAddress { link: "eth0".into(), address: "10.0.0.5/24".into() }));
+    }
+
+    #[test]
+    fn test_plan_ipv6() {
+        let mut current = host();
+        current.links[0].ipv6_autoconf = Some(Ipv6Autoconf::Slaac);
+
+        // Unset autoconfiguration leaves the kernel settings alone
+        let mut desired = current.clone();
+        desired.links[0].ipv6_autoconf = None;
+        assert!(plan(&current, &desired).is_empty());
+
+        desired.links[0].ipv6_autoconf = Some(Ipv6Autoconf::Off);
+        desired.links[0].addresses.push("2001:db8::5/64".into());
+        desired.routes.push(RouteConfig {
+            destination: "default".into(),
+            gateway: Some("2001:db8::1".into()),
+            dev: Some("eth0".into()),
+            metric: None,
+        });
+        desired.validate().unwrap();
+
+        let changes = plan(&current, &desired);
+        let pos = |c: &NetworkChange| changes.iter().position(|x| x == c).unwrap();
+        let off = pos(&NetworkChange::SetIpv6Autoconf { link: "eth0".into(), mode: Ipv6Autoconf::Off });
+        let add = pos(&NetworkChange::AddAddress { link: "eth0".into(), address: "2001:db8::5/64".into() });
+        let route = pos(&NetworkChange::AddRoute { route: desired.routes[1].clone() });
+        assert!(off < add && add < route);
+        assert_eq!(route_args(&desired.routes[1], "0")[0], "-6");
+
+        // Autoconfiguration belongs to the bridge once the link is enslaved
+        let mut bridged = current.clone();
+        bridged.bridges.push(BridgeConfig { name: "br0".into(), ports: vec!["eth0".into()], stp: false });
+        bridged.move_addressing(&["eth0".into()], "br0");
+        bridged.validate().unwrap();
+        assert_eq!(bridged.link("br0").unwrap().ipv6_autoconf, Some(Ipv6Autoconf::Slaac));
+    }
+
+    #[test]
+    fn test_plan_noop_and_recreate() {
+        let mut current = host();
+        current.vlans.push(VlanConfig { name: "eth1.10".into(), parent: "eth1".into(), id: 10 });
+        assert!(plan(&current, &current).is_empty());
+
+        let mut desired = current.clone();
+        desired.vlans[0].id = 20;
+        let changes = plan(&current, &desired);
+        assert_eq!(changes[0], NetworkChange::DeleteLink { name: "eth1.10".into() });
+        assert_eq!(changes[1], NetworkChange::CreateVlan { name: "eth1.10".into(), parent: "eth1".into(), id: 20 });
+    }
+
+    #[test]
+    fn test_validate_rejects_bad_config() {
+        let mut config = host();
+        config.links[0].addresses = vec!["10.0.0.5/33".into()];
+        assert!(config.validate().is_err());
+
+        let mut config = host();
+        config.bridges.push(BridgeConfig { name: "br0".into(), ports: vec!["eth1".into()], stp: false });
+        config.bonds.push(BondConfig::new("bond0", "active-backup", vec!["eth1".into()]));
+        assert!(config.validate().is_err());
+
+        assert_eq!(netmask_to_prefix("255.255.255.0").unwrap(), 24);
+        assert_eq!(netmask_to_prefix("20").unwrap(), 20);
+        assert!(netmask_to_prefix("255.0.255.0").is_err());
+    }
+
+    #[test]
+    fn test_plan_bridge_on_bond() {
+        let current = host();
+        let mut desired = host();
+        let mut bond = BondConfig::new("bond0", "active-backup", vec!["eth0".into(), "eth1".into()]);
+        bond.primary = Some("eth0".into());
+        desired.bonds.push(bond);
+        desired.move_addressing(&["eth0".into(), "eth1".into()], "bond0");
+        desired.bridges.push(BridgeConfig { name: "br0".into(), ports: vec!["bond0".into()], stp: false });
+        desired.move_addressing(&["bond0".into()], "br0");
+        desired.validate().unwrap();
+        desired.validate_against(&current).unwrap();
+        assert_eq!(desired.routes[0].dev.as_deref(), Some("br0"));
+
+        let changes = plan(&current, &desired);
+        let pos = |c: &NetworkChange| changes.iter().position(|x| x == c).unwrap();
+        let bond = pos(&NetworkChange::CreateBond {
+            name: "bond0".into(),
+            mode: "active-backup".into(),
+            miimon: None,
+            lacp_rate: None,
+            xmit_hash_policy: None,
+        });
+        let member = pos(&NetworkChange::EnslaveLink { link: "eth0".into(), master: "bond0".into() });
+        let primary = pos(&NetworkChange::SetBondPrimary { name: "bond0".into(), primary: "eth0".into() });
+        let port = pos(&NetworkChange::EnslaveLink { link: "bond0".into(), master: "br0".into() });
+        let add = pos(&NetworkChange::AddAddress { link: "br0".into(), address: "10.0.0.5/24".into() });
+        assert!(bond < member && member < primary && bond < port && port < add);
+
+        // Changing LACP options recreates the bond; unset options are ignored
+        let mut lacp = desired.clone();
+        lacp.bonds[0] = BondConfig { primary: None, ..BondConfig::new("bond0", "802.3ad", vec!["eth0".into(), "eth1".into()]) };
+        assert!(plan(&desired, &lacp).contains(&NetworkChange::DeleteLink { name: "bond0".into() }));
+        let mut same = desired.clone();
+        same.bonds[0].miimon = None;
+        assert!(plan(&desired, &same).is_empty());
+
+        // Removing the bridge and bond hands the address back to eth0
+        let mut removed = desired.clone();
+        assert!(removed.remove_virtual_link("bond0").is_err());
+        removed.remove_virtual_link("br0").unwrap();
+        removed.remove_virtual_link("bond0").unwrap();
+        assert_eq!(removed.link("eth0").unwrap().addresses, vec!["10.0.0.5/24"]);
+        assert_eq!(removed.routes[0].dev.as_deref(), Some("eth0"));
+    }
+
+    #[test]
+    fn test_validate_bond_options() {
+        let mut config = host();
+        let mut bond = BondConfig::new("bond0", "802.3ad", vec!["eth1".into()]);
+        bond.lacp_rate = Some("fast".into());
+        bond.xmit_hash_policy = Some("layer3+4".into());
+        config.bonds.push(bond);
+        config.validate().unwrap();
+
+        config.bonds[0].mode = "active-backup".into();
+        assert!(config.validate().is_err());
+
+        config.bonds[0] = BondConfig::new("bond0", "balance-alb", vec!["eth1".into()]);
+        config.bonds[0].primary = Some("eth0".into());
+        assert!(config.validate().is_err());
+
+        config.bonds[0] = BondConfig::new("bond0", "lacp", vec!["eth1".into()]);
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_state() {
+        let links = serde_json::json!([
+            {"ifname": "lo", "flags": ["LOOPBACK", "UP"], "mtu": 65536, "link_type": "loopback"},
+            {"ifname": "eth0", "flags": ["UP"], "mtu": 1500, "master": "br0",
+             "linkinfo": {"info_slave_kind": "bridge"}},
+            {"ifname": "br0", "flags": ["UP"], "mtu": 1500,
+             "linkinfo": {"info_kind": "bridge", "info_data": {"stp_state": 0}}},
+            {"ifname": "vnet0", "flags": ["UP"], "mtu": 1500, "master": "br0",
+             "linkinfo": {"info_kind": "tun", "info_slave_kind": "bridge"}},
+            {"ifname": "virbr0", "flags": ["UP"], "mtu": 1500, "linkinfo": {"info_kind": "bridge"}}
+        ]);
+        let addrs = serde_json::json!([
+            {"ifname": "br0", "addr_info": [
+                {"family": "inet", "local": "10.0.0.5", "prefixlen": 24, "scope": "global"},
+                {"family": "inet6", "local": "fe80::1", "prefixlen": 64, "scope": "link"}
+            ]},
+            {"ifname": "eth0", "addr_info": [
+                {"family": "inet", "local": "192.168.1.9", "prefixlen": 24, "scope": "global", "dynamic": true}
+            ]}
+        ]);
+        let routes = serde_json::json!([
+            {"dst": "default", "gateway": "10.0.0.1", "dev": "br0", "protocol": "boot", "flags": []},
+            {"dst": "10.0.0.0/24", "dev": "br0", "protocol": "kernel", "scope": "link"}
+        ]);
+
+        let state = parse_state(&links, &addrs, &routes, "search lab\nnameserver 10.0.0.1\n");
+
+        assert_eq!(state.bridges, vec![BridgeConfig { name: "br0".into(), ports: vec!["eth0".into()], stp: false }]);
+        assert_eq!(state.links.iter().map(|l| l.name.as_str()).collect::<Vec<_>>(), vec!["eth0", "br0"]);
+        assert_eq!(state.links[1].addresses, vec!["10.0.0.5/24"]);
+        assert!(state.links[0].dhcp && state.links[0].addresses.is_empty());
+        assert_eq!(state.routes.len(), 1);
+        assert_eq!(state.dns.search_domains, vec!["lab"]);
+    }
+}
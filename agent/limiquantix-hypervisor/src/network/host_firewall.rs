@@ -0,0 +1,308 @@
+//! nftables firewall for the host's management-plane services.
+//!
+//! The node's own services (web UI, gRPC, SSH, VNC consoles, live migration)
+//! listen on all interfaces. This module restricts who may connect to them
+//! with an `inet` table hooked on input:
+//!
+//! ```text
+//! table inet limiquantix_host
+//!   input ── lo / established ──► accept
+//!         ── tcp dport <service ports> saddr <allowed sources> ──► accept
+//!         ── tcp dport <service ports> ──► drop
+//! ```
+//!
+//! Only the listed service ports are filtered; everything else (VM traffic,
+//! overlay tunnels, storage) is left alone. A service with no allowed
+//! sources is open to all networks; a disabled service is closed to all.
+
+use std::fmt::Write as _;
+use std::net::IpAddr;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use super::host::parse_cidr;
+
+/// nftables table holding the host firewall.
+pub const HOST_TABLE_NAME: &str = "limiquantix_host";
+
+/// A management-plane service protected by the host firewall.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ManagementService {
+    /// Web UI and REST API (HTTP redirect, HTTP, HTTPS)
+    WebUi,
+    /// gRPC API used by the control plane
+    Grpc,
+    Ssh,
+    /// VNC/SPICE consoles of VMs
+    Vnc,
+    /// libvirt remote access and QEMU live migration
+    Migration,
+}
+
+impl ManagementService {
+    pub const ALL: [ManagementService; 5] = [
+        ManagementService::WebUi,
+        ManagementService::Grpc,
+        ManagementService::Ssh,
+        ManagementService::Vnc,
+        ManagementService::Migration,
+    ];
+
+    /// Ports the node and libvirt use by default.
+    pub fn default_ports(&self) -> Vec<String> {
+        let ports: &[&str] = match self {
+            ManagementService::WebUi => &["80", "8080", "8443"],
+            ManagementService::Grpc => &["9090"],
+            ManagementService::Ssh => &["22"],
+            ManagementService::Vnc => &["5900-5999"],
+            ManagementService::Migration => &["16509", "16514", "49152-49215"],
+        };
+        ports.iter().map(|p| p.to_string()).collect()
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ManagementService::WebUi => "web-ui",
+            ManagementService::Grpc => "grpc",
+            ManagementService::Ssh => "ssh",
+            ManagementService::Vnc => "vnc",
+            ManagementService::Migration => "migration",
+        }
+    }
+}
+
+/// Access rule for one service.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServiceRule {
+    pub service: ManagementService,
+    /// TCP ports or ranges ("8443", "5900-5999")
+    pub ports: Vec<String>,
+    /// false closes the service to all networks
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Source addresses or CIDRs allowed to connect (empty = any)
+    #[serde(default)]
+    pub allowed_sources: Vec<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl ServiceRule {
+    /// Rule that leaves `service` open on its default ports.
+    pub fn new(service: ManagementService) -> Self {
+        Self {
+            service,
+            ports: service.default_ports(),
+            enabled: true,
+            allowed_sources: Vec::new(),
+        }
+    }
+
+    /// Whether a connection from `source` to `port` is allowed.
+    pub fn allows(&self, source: IpAddr, port: u16) -> bool {
+        if !self.ports.iter().filter_map(|p| parse_port_range(p).ok()).any(|(lo, hi)| (lo..=hi).contains(&port)) {
+            return true;
+        }
+        self.enabled && (self.allowed_sources.is_empty()
+            || self.allowed_sources.iter()
+                .filter_map(|s| parse_source(s).ok())
+                .any(|(net, prefix)| contains(net, prefix, source)))
+    }
+}
+
+/// Host firewall configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HostFirewallConfig {
+    /// false removes the table (all ports open)
+    pub enabled: bool,
+    pub services: Vec<ServiceRule>,
+}
+
+impl Default for HostFirewallConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            services: ManagementService::ALL.iter().map(|s| ServiceRule::new(*s)).collect(),
+        }
+    }
+}
+
+impl HostFirewallConfig {
+    /// Check ports, sources, and that each service appears once.
+    pub fn validate(&self) -> Result<()> {
+        let mut seen = Vec::new();
+        for rule in &self.services {
+            if seen.contains(&rule.service) {
+                bail!("Service {} is listed twice", rule.service.label());
+            }
+            seen.push(rule.service);
+            if rule.ports.is_empty() {
+                bail!("Service {} has no ports", rule.service.label());
+            }
+            for port in &rule.ports {
+                parse_port_range(port).with_context(|| format!("Service {}", rule.service.label()))?;
+            }
+            for source in &rule.allowed_sources {
+                parse_source(source).with_context(|| format!("Service {}", rule.service.label()))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Rule for `service`, if configured.
+    pub fn service(&self, service: ManagementService) -> Option<&ServiceRule> {
+        self.services.iter().find(|r| r.service == service)
+    }
+
+    /// Whether `source` may connect to `port` under this configuration.
+    pub fn allows(&self, source: IpAddr, port: u16) -> bool {
+        !self.enabled || self.services.iter().all(|r| r.allows(source, port))
+    }
+}
+
+/// Render the nftables script for `config`.
+///
+/// Like the security group ruleset, the script replaces the table
+/// atomically; a disabled firewall only removes it.
+pub fn render_host_ruleset(config: &HostFirewallConfig) -> Result<String> {
+    config.validate()?;
+
+    let mut out = String::new();
+    writeln!(out, "table inet {}", HOST_TABLE_NAME)?;
+    writeln!(out, "delete table inet {}", HOST_TABLE_NAME)?;
+    if !config.enabled {
+        return Ok(out);
+    }
+
+    writeln!(out, "table inet {} {{", HOST_TABLE_NAME)?;
+    writeln!(out, "  chain input {{")?;
+    writeln!(out, "    type filter hook input priority 0; policy accept;")?;
+    writeln!(out, "    iif \"lo\" accept")?;
+    // Sessions opened before a restriction keep working until they close
+    writeln!(out, "    ct state established,related accept")?;
+
+    for rule in &config.services {
+        if rule.enabled && rule.allowed_sources.is_empty() {
+            continue;
+        }
+        let ports = format!("{{ {} }}", rule.ports.iter()
+            .map(|p| p.trim().to_string())
+            .collect::<Vec<_>>()
+            .join(", "));
+        let comment = format!("comment \"{}\"", rule.service.label());
+
+        if rule.enabled {
+            let (v4, v6): (Vec<&String>, Vec<&String>) = rule.allowed_sources.iter()
+                .partition(|s| parse_source(s).map(|(a, _)| a.is_ipv4()).unwrap_or(false));
+            if !v4.is_empty() {
+                writeln!(out, "    ip saddr {{ {} }} tcp dport {} accept {}", join(&v4), ports, comment)?;
+            }
+            if !v6.is_empty() {
+                writeln!(out, "    ip6 saddr {{ {} }} tcp dport {} accept {}", join(&v6), ports, comment)?;
+            }
+        }
+        writeln!(out, "    tcp dport {} drop {}", ports, comment)?;
+    }
+
+    writeln!(out, "  }}")?;
+    writeln!(out, "}}")?;
+    Ok(out)
+}
+
+fn join(items: &[&String]) -> String {
+    items.iter().map(|s| s.trim()).collect::<Vec<_>>().join(", ")
+}
+
+/// Parse "22" or "5900-5999".
+fn parse_port_range(port: &str) -> Result<(u16, u16)> {
+    let port = port.trim();
+    let (lo, hi) = port.split_once('-').unwrap_or((port, port));
+    let lo: u16 = lo.parse().with_context(|| format!("Invalid port '{}'", port))?;
+    let hi: u16 = hi.parse().with_context(|| format!("Invalid port '{}'", port))?;
+    if lo == 0 || hi < lo {
+        bail!("Invalid port range '{}'", port);
+    }
+    Ok((lo, hi))
+}
+
+/// Parse an address or CIDR (a bare address is a host route).
+fn parse_source(source: &str) -> Result<(IpAddr, u8)> {
+    let source = source.trim();
+    if source.contains('/') {
+        return parse_cidr(source);
+    }
+    let addr: IpAddr = source.parse().with_context(|| format!("Invalid source address '{}'", source))?;
+    Ok((addr, if addr.is_ipv4() { 32 } else { 128 }))
+}
+
+fn contains(net: IpAddr, prefix: u8, addr: IpAddr) -> bool {
+    match (net, addr) {
+        (IpAddr::V4(n), IpAddr::V4(a)) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            u32::from(n) & mask == u32::from(a) & mask
+        }
+        (IpAddr::V6(n), IpAddr::V6(a)) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            u128::from(n) & mask == u128::from(a) & mask
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn restricted() -> HostFirewallConfig {
+        let mut config = HostFirewallConfig { enabled: true, ..Default::default() };
+        config.services[0].allowed_sources = vec!["10.0.0.0/8".to_string(), "fd00::/8".to_string()];
+        config.services[2].enabled = false;
+        config
+    }
+
+    #[test]
+    fn test_render_host_ruleset() {
+        let ruleset = render_host_ruleset(&restricted()).unwrap();
+        assert!(ruleset.starts_with("table inet limiquantix_host\ndelete table inet limiquantix_host\n"));
+        assert!(ruleset.contains("ip saddr { 10.0.0.0/8 } tcp dport { 80, 8080, 8443 } accept comment \"web-ui\""));
+        assert!(ruleset.contains("ip6 saddr { fd00::/8 } tcp dport { 80, 8080, 8443 } accept"));
+        assert!(ruleset.contains("tcp dport { 80, 8080, 8443 } drop"));
+        assert!(ruleset.contains("tcp dport { 22 } drop comment \"ssh\""));
+        // Unrestricted services get no rules
+        assert!(!ruleset.contains("9090"));
+
+        let disabled = render_host_ruleset(&HostFirewallConfig::default()).unwrap();
+        assert_eq!(disabled.lines().count(), 2);
+    }
+
+    #[test]
+    fn test_allows() {
+        let config = restricted();
+        assert!(config.allows("10.1.2.3".parse().unwrap(), 8443));
+        assert!(!config.allows("192.168.1.10".parse().unwrap(), 8443));
+        assert!(!config.allows("10.1.2.3".parse().unwrap(), 22));
+        assert!(config.allows("192.168.1.10".parse().unwrap(), 9090));
+        assert!(config.allows("192.168.1.10".parse().unwrap(), 3260));
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut config = restricted();
+        config.services[1].ports = vec!["9090-80".to_string()];
+        assert!(config.validate().is_err());
+
+        let mut config = restricted();
+        config.services[0].allowed_sources.push("10.0.0.0/33".to_string());
+        assert!(config.validate().is_err());
+
+        let mut config = restricted();
+        config.services.push(ServiceRule::new(ManagementService::Ssh));
+        assert!(config.validate().is_err());
+    }
+}
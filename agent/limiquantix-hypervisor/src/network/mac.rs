@@ -0,0 +1,419 @@
+//! MAC address allocation for VM NICs.
+//!
+//! Every MAC handed to a VM on this host is recorded in a registry so new
+//! addresses never collide with existing ones, including MACs of clones,
+//! imported machines and domains defined outside the node daemon (adopted
+//! by [`MacAllocator::reconcile`]).
+//!
+//! - Addresses come from a configurable pool: an OUI-style prefix plus an
+//!   optional start/end range (default `52:54:00`, the QEMU prefix)
+//! - Allocations are persisted as JSON and survive restarts; the registry is
+//!   read on first use
+//! - Explicitly requested MACs are recorded too; if another VM already uses
+//!   one, the collision is reported instead of silently accepted
+//! - Clones always get fresh addresses ([`MacAllocator::regenerate`])
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, MutexGuard, OnceCell};
+use tracing::{info, warn};
+
+use super::firewall::validate_mac;
+use crate::types::VmConfig;
+
+/// Default location of the allocation registry.
+pub const DEFAULT_MAC_REGISTRY_PATH: &str = "/var/lib/limiquantix/mac-allocations.json";
+
+/// Random attempts before falling back to a linear scan of the pool.
+const RANDOM_ATTEMPTS: usize = 64;
+
+/// Address pool for generated MACs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MacPoolConfig {
+    /// Leading octets shared by all generated MACs (1-5 octets), e.g. "52:54:00"
+    pub prefix: String,
+    /// First address of the pool (default: lowest address under the prefix)
+    #[serde(default)]
+    pub range_start: Option<String>,
+    /// Last address of the pool (default: highest address under the prefix)
+    #[serde(default)]
+    pub range_end: Option<String>,
+}
+
+impl Default for MacPoolConfig {
+    fn default() -> Self {
+        Self {
+            prefix: "52:54:00".to_string(),
+            range_start: None,
+            range_end: None,
+        }
+    }
+}
+
+impl MacPoolConfig {
+    /// Check the prefix and range, returning the pool as an inclusive range of
+    /// 48-bit integers.
+    pub fn validate(&self) -> Result<(u64, u64)> {
+        let octets: Vec<&str> = self.prefix.split(':').collect();
+        if octets.is_empty() || octets.len() > 5
+            || !octets.iter().all(|o| o.len() == 2 && o.chars().all(|c| c.is_ascii_hexdigit()))
+        {
+            bail!("MAC prefix must be 1-5 octets like 52:54:00, got '{}'", self.prefix);
+        }
+        let first = u8::from_str_radix(octets[0], 16)?;
+        if first & 0x01 != 0 {
+            bail!("MAC prefix {} is a multicast address", self.prefix);
+        }
+
+        let host_bits = 8 * (6 - octets.len() as u32);
+        let prefix = octets.iter().try_fold(0u64, |acc, o| u64::from_str_radix(o, 16).map(|v| (acc << 8) | v))?;
+        let (lowest, highest) = (prefix << host_bits, (prefix << host_bits) | ((1u64 << host_bits) - 1));
+
+        let parse_bound = |mac: &Option<String>, default: u64| -> Result<u64> {
+            let Some(mac) = mac else { return Ok(default) };
+            let value = mac_to_u64(mac)?;
+            if !(lowest..=highest).contains(&value) {
+                bail!("{} is outside the prefix {}", mac, self.prefix);
+            }
+            Ok(value)
+        };
+        let start = parse_bound(&self.range_start, lowest)?;
+        let end = parse_bound(&self.range_end, highest)?;
+        if start > end {
+            bail!("MAC range start is after range end");
+        }
+        Ok((start, end))
+    }
+}
+
+/// A MAC recorded in the registry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MacAllocation {
+    pub mac: String,
+    pub vm_id: String,
+    /// NIC ID within the VM (empty for adopted domains)
+    #[serde(default)]
+    pub nic_id: String,
+    pub allocated_at: DateTime<Utc>,
+}
+
+/// A MAC used by more than one VM.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MacConflict {
+    pub mac: String,
+    pub vm_ids: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Registry {
+    #[serde(default)]
+    pool: MacPoolConfig,
+    /// Keyed by (lowercase) MAC
+    #[serde(default)]
+    allocations: BTreeMap<String, MacAllocation>,
+}
+
+/// Persistent MAC allocation registry.
+pub struct MacAllocator {
+    /// Registry file (None: in-memory only)
+    path: Option<PathBuf>,
+    registry: OnceCell<Mutex<Registry>>,
+}
+
+impl MacAllocator {
+    /// Registry persisted at `path`, loaded on first use (starting empty if
+    /// the file does not exist).
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+            registry: OnceCell::new(),
+        }
+    }
+
+    /// Registry that is not persisted.
+    pub fn in_memory(pool: MacPoolConfig) -> Self {
+        Self {
+            path: None,
+            registry: OnceCell::new_with(Some(Mutex::new(Registry {
+                pool,
+                allocations: BTreeMap::new(),
+            }))),
+        }
+    }
+
+    /// Current pool configuration.
+    pub async fn pool(&self) -> MacPoolConfig {
+        self.lock().await.pool.clone()
+    }
+
+    /// Change the pool for future allocations (existing MACs are kept).
+    pub async fn set_pool(&self, pool: MacPoolConfig) -> Result<()> {
+        pool.validate()?;
+        let mut registry = self.lock().await;
+        info!(prefix = %pool.prefix, "MAC pool updated");
+        registry.pool = pool;
+        self.save(&registry).await
+    }
+
+    /// All recorded allocations.
+    pub async fn allocations(&self) -> Vec<MacAllocation> {
+        self.lock().await.allocations.values().cloned().collect()
+    }
+
+    /// Allocate a free MAC from the pool for a NIC.
+    pub async fn allocate(&self, vm_id: &str, nic_id: &str) -> Result<String> {
+        let mut registry = self.lock().await;
+        let mac = next_free(&registry)?;
+        record(&mut registry, &mac, vm_id, nic_id);
+        self.save(&registry).await?;
+        Ok(mac)
+    }
+
+    /// Record an explicitly chosen MAC. Returns the conflict if another VM
+    /// already holds it; the registry keeps the original owner.
+    pub async fn reserve(&self, mac: &str, vm_id: &str, nic_id: &str) -> Result<Option<MacConflict>> {
+        validate_mac(mac)?;
+        let mac = mac.to_lowercase();
+        let mut registry = self.lock().await;
+        let conflict = registry.allocations.get(&mac)
+            .filter(|existing| existing.vm_id != vm_id)
+            .map(|existing| MacConflict {
+                mac: mac.clone(),
+                vm_ids: vec![existing.vm_id.clone(), vm_id.to_string()],
+            });
+        if conflict.is_none() {
+            record(&mut registry, &mac, vm_id, nic_id);
+            self.save(&registry).await?;
+        }
+        Ok(conflict)
+    }
+
+    /// Give every NIC of `config` a MAC: missing ones are allocated, given
+    /// ones are reserved. Returns conflicts with other VMs.
+    pub async fn assign(&self, config: &mut VmConfig) -> Result<Vec<MacConflict>> {
+        let mut conflicts = Vec::new();
+        for nic in config.nics.iter_mut() {
+            match nic.mac_address {
+                Some(ref mac) => conflicts.extend(self.reserve(mac, &config.id, &nic.id).await?),
+                None => nic.mac_address = Some(self.allocate(&config.id, &nic.id).await?),
+            }
+        }
+        Ok(conflicts)
+    }
+
+    /// Replace every NIC's MAC with a freshly allocated one (clones, imports
+    /// that don't keep their addresses).
+    pub async fn regenerate(&self, config: &mut VmConfig) -> Result<()> {
+        for nic in config.nics.iter_mut() {
+            nic.mac_address = Some(self.allocate(&config.id, &nic.id).await?);
+        }
+        Ok(())
+    }
+
+    /// Forget all MACs of a VM.
+    pub async fn release_vm(&self, vm_id: &str) -> Result<()> {
+        let mut registry = self.lock().await;
+        let before = registry.allocations.len();
+        registry.allocations.retain(|_, a| a.vm_id != vm_id);
+        if registry.allocations.len() != before {
+            self.save(&registry).await?;
+        }
+        Ok(())
+    }
+
+    /// Compare the registry with the MACs of the local domains.
+    ///
+    /// Unknown MACs are adopted so they are never handed out again. Returns
+    /// MACs used by more than one domain.
+    pub async fn reconcile(&self, domains: &[(String, Vec<String>)]) -> Result<Vec<MacConflict>> {
+        let mut registry = self.lock().await;
+        let mut adopted = 0;
+        for (vm_id, macs) in domains {
+            for mac in macs {
+                let mac = mac.to_lowercase();
+                if !registry.allocations.contains_key(&mac) {
+                    record(&mut registry, &mac, vm_id, "");
+                    adopted += 1;
+                }
+            }
+        }
+        if adopted > 0 {
+            info!(adopted, "Adopted MACs of existing domains");
+            self.save(&registry).await?;
+        }
+        Ok(find_duplicates(domains))
+    }
+
+    async fn lock(&self) -> MutexGuard<'_, Registry> {
+        self.registry.get_or_init(|| async {
+            let Some(ref path) = self.path else {
+                return Mutex::default();
+            };
+            let registry = match tokio::fs::read_to_string(path).await {
+                Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                    warn!(path = %path.display(), error = %e, "Ignoring invalid MAC registry");
+                    Registry::default()
+                }),
+                Err(_) => Registry::default(),
+            };
+            Mutex::new(registry)
+        }).await.lock().await
+    }
+
+    async fn save(&self, registry: &Registry) -> Result<()> {
+        let Some(ref path) = self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let json = serde_json::to_string_pretty(registry)?;
+        let tmp = path.with_extension("json.tmp");
+        tokio::fs::write(&tmp, json).await.with_context(|| format!("Failed to write {}", tmp.display()))?;
+        tokio::fs::rename(&tmp, path).await.with_context(|| format!("Failed to write {}", path.display()))
+    }
+}
+
+impl Default for MacAllocator {
+    fn default() -> Self {
+        Self::open(DEFAULT_MAC_REGISTRY_PATH)
+    }
+}
+
+/// MACs that appear on more than one VM (a VM may list a MAC only once).
+pub fn find_duplicates(domains: &[(String, Vec<String>)]) -> Vec<MacConflict> {
+    let mut owners: BTreeMap<String, BTreeSet<&str>> = BTreeMap::new();
+    for (vm_id, macs) in domains {
+        for mac in macs {
+            owners.entry(mac.to_lowercase()).or_default().insert(vm_id);
+        }
+    }
+    owners.into_iter()
+        .filter(|(_, vms)| vms.len() > 1)
+        .map(|(mac, vms)| MacConflict {
+            mac,
+            vm_ids: vms.into_iter().map(String::from).collect(),
+        })
+        .collect()
+}
+
+fn record(registry: &mut Registry, mac: &str, vm_id: &str, nic_id: &str) {
+    registry.allocations.insert(mac.to_string(), MacAllocation {
+        mac: mac.to_string(),
+        vm_id: vm_id.to_string(),
+        nic_id: nic_id.to_string(),
+        allocated_at: Utc::now(),
+    });
+}
+
+/// Pick an unused address: random first (so hosts sharing a prefix rarely
+/// collide), then a linear scan when the pool is nearly full.
+fn next_free(registry: &Registry) -> Result<String> {
+    let (start, end) = registry.pool.validate()?;
+    let is_free = |value: u64| !registry.allocations.contains_key(&u64_to_mac(value));
+
+    for _ in 0..RANDOM_ATTEMPTS {
+        let value = start + rand::random::<u64>() % (end - start + 1);
+        if is_free(value) {
+            return Ok(u64_to_mac(value));
+        }
+    }
+    (start..=end)
+        .find(|&v| is_free(v))
+        .map(u64_to_mac)
+        .with_context(|| format!("MAC pool {} is exhausted", registry.pool.prefix))
+}
+
+fn mac_to_u64(mac: &str) -> Result<u64> {
+    validate_mac(mac)?;
+    Ok(u64::from_str_radix(&mac.replace(':', ""), 16)?)
+}
+
+fn u64_to_mac(value: u64) -> String {
+    let bytes = value.to_be_bytes();
+    bytes[2..].iter().map(|b| format!("{:02x}", b)).collect::<Vec<_>>().join(":")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::NicConfig;
+
+    #[test]
+    fn test_pool_validation() {
+        let pool = MacPoolConfig {
+            prefix: "02:00:00:aa".to_string(),
+            range_start: Some("02:00:00:aa:00:10".to_string()),
+            range_end: None,
+        };
+        assert_eq!(pool.validate().unwrap(), (0x0200_00aa_0010, 0x0200_00aa_ffff));
+
+        let multicast = MacPoolConfig { prefix: "01:00:5e".to_string(), ..Default::default() };
+        assert!(multicast.validate().is_err());
+
+        let outside = MacPoolConfig { range_end: Some("52:54:01:00:00:00".to_string()), ..Default::default() };
+        assert!(outside.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_allocate_and_exhaust() {
+        let pool = MacPoolConfig {
+            prefix: "52:54:00:00:00".to_string(),
+            range_start: Some("52:54:00:00:00:01".to_string()),
+            range_end: Some("52:54:00:00:00:03".to_string()),
+        };
+        let allocator = MacAllocator::in_memory(pool);
+        assert_eq!(allocator.reserve("52:54:00:00:00:02", "vm-a", "nic0").await.unwrap(), None);
+
+        let mut macs = Vec::new();
+        for i in 0..2 {
+            macs.push(allocator.allocate("vm-b", &format!("nic{}", i)).await.unwrap());
+        }
+        macs.sort();
+        assert_eq!(macs, ["52:54:00:00:00:01", "52:54:00:00:00:03"]);
+        assert!(allocator.allocate("vm-c", "nic0").await.is_err());
+
+        allocator.release_vm("vm-b").await.unwrap();
+        assert_eq!(allocator.allocations().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_assign_regenerate_and_conflicts() {
+        let allocator = MacAllocator::in_memory(MacPoolConfig::default());
+        let mut config = VmConfig::new("web");
+        config.nics.push(NicConfig::default());
+        config.nics.push(NicConfig { mac_address: Some("52:54:00:AA:BB:CC".to_string()), ..Default::default() });
+
+        assert!(allocator.assign(&mut config).await.unwrap().is_empty());
+        assert!(config.nics[0].mac_address.as_deref().unwrap().starts_with("52:54:00:"));
+
+        // A clone keeping the source's MACs would collide
+        let mut clone = config.clone();
+        clone.id = "clone".to_string();
+        let conflicts = allocator.assign(&mut clone).await.unwrap();
+        assert_eq!(conflicts.len(), 2);
+
+        allocator.regenerate(&mut clone).await.unwrap();
+        assert_ne!(clone.nics[1].mac_address, config.nics[1].mac_address);
+
+        let domains = vec![
+            ("a".to_string(), vec!["52:54:00:00:00:01".to_string()]),
+            ("b".to_string(), vec!["52:54:00:00:00:01".to_string(), "52:54:00:00:00:02".to_string()]),
+        ];
+        let duplicates = allocator.reconcile(&domains).await.unwrap();
+        assert_eq!(duplicates, vec![MacConflict {
+            mac: "52:54:00:00:00:01".to_string(),
+            vm_ids: vec!["a".to_string(), "b".to_string()],
+        }]);
+        assert!(allocator.allocations().await.iter().any(|a| a.mac == "52:54:00:00:00:02"));
+    }
+}
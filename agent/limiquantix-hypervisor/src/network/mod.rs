@@ -4,9 +4,27 @@
 //! - OVS port management (creating/binding ports on br-int)
 //! - OVN integration (iface-id binding for OVN controller)
 //! - Libvirt interface XML generation for OVS
+//! - Port mirroring and bounded packet capture
+//! - Declarative host network configuration with rollback
+//! - DHCP/IPAM for standalone host bridges
+//! - nftables security groups for bridge-attached VM NICs
+//! - MAC address allocation with conflict detection
+//! - nftables firewall for the host's management services
 
+pub mod capture;
+pub mod dhcp;
+pub mod firewall;
+pub mod host;
+pub mod host_firewall;
+pub mod mac;
 mod ovs;
 mod types;
 
+pub use capture::CaptureOptions;
+pub use dhcp::{DhcpConfig, DhcpLease, DhcpPool, StaticLease};
+pub use firewall::{PortFilter, SecurityGroup, SecurityGroupRule};
+pub use host::{HostNetworkConfig, NetworkChange, NetworkTransaction};
+pub use host_firewall::{HostFirewallConfig, ManagementService, ServiceRule};
+pub use mac::{MacAllocation, MacAllocator, MacConflict, MacPoolConfig};
 pub use ovs::OvsPortManager;
 pub use types::*;
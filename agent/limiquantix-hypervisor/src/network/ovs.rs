@@ -5,13 +5,17 @@
 //! - Binding VM interfaces to the OVS integration bridge (br-int)
 //! - Setting interface-id external_ids for OVN controller
 //! - Generating libvirt interface XML for OVS virtualport
+//! - Port mirror sessions for traffic analysis
+//! - Port QoS: egress policing and ingress shaping, updatable on running ports
+//!   and kept in the persistent domain definition
 
 use std::process::Command;
 use anyhow::{Context, Result, bail};
 use tracing::{info, debug, warn, instrument};
 
 use super::types::{
-    NetworkPortConfig, NetworkPortInfo, NetworkPortPhase, OvsStatus,
+    MirrorDirection, MirrorSession, NetworkPortConfig, NetworkPortInfo, NetworkPortPhase,
+    NetworkPortQoS, OvsStatus,
 };
 
 /// OVS port manager for connecting VMs to OVN.
@@ -139,9 +143,16 @@ impl OvsPortManager {
             "Configuring network port for OVN"
         );
 
-        // Generate libvirt interface XML
+        // Generate libvirt interface XML (carries the QoS for the next boot)
         let interface_xml = self.generate_interface_xml(config)?;
 
+        // If the VM is already running, enforce the limits right away
+        let qos = config.qos.clone().unwrap_or_default();
+        let iface = self.find_interface(&config.ovn_port_name).unwrap_or(None);
+        if let Some(ref iface) = iface {
+            self.apply_qos(iface, &qos)?;
+        }
+
         let port_info = NetworkPortInfo {
             port_id: config.port_id.clone(),
             vm_id: config.vm_id.clone(),
@@ -150,13 +161,17 @@ impl OvsPortManager {
             ip_addresses: config.ip_addresses.clone(),
             phase: NetworkPortPhase::Pending,
             error_message: None,
-            ovs_port_name: None, // Set when VM starts
+            ovs_port_name: iface.clone(), // Set when VM starts
             ovn_port_name: config.ovn_port_name.clone(),
             interface_xml,
             rx_bytes: 0,
             tx_bytes: 0,
             rx_packets: 0,
             tx_packets: 0,
+            effective_qos: iface.map(|_| qos),
+            rx_dropped: 0,
+            tx_dropped: 0,
+            qos_dropped: 0,
         };
 
         Ok(port_info)
@@ -236,7 +251,7 @@ impl OvsPortManager {
         // Find interface by iface-id
         let output = Command::new("ovs-vsctl")
             .args([
-                "--columns=name,external_ids,statistics",
+                "--columns=name,statistics,ingress_policing_rate,ingress_policing_burst",
                 "--format=json",
                 "find", "Interface",
                 &format!("external_ids:iface-id={}", ovn_port_name),
@@ -248,13 +263,25 @@ impl OvsPortManager {
             return Ok(None);
         }
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        if stdout.contains("[]") {
+        let rows = json_rows(&String::from_utf8_lossy(&output.stdout))?;
+        let Some(row) = rows.first() else {
             return Ok(None);
+        };
+        let iface = row.first().and_then(|n| n.as_str()).unwrap_or_default().to_string();
+        let stats = row.get(1).map(json_map).unwrap_or_default();
+        let stat = |key: &str| stats.get(key).and_then(|v| v.parse().ok()).unwrap_or(0);
+
+        let mut qos = NetworkPortQoS {
+            egress_rate_kbps: row.get(2).and_then(|v| v.as_u64()).unwrap_or(0),
+            // OVS stores the policing burst in kilobits
+            egress_burst_kb: row.get(3).and_then(|v| v.as_u64()).unwrap_or(0) / 8,
+            ..Default::default()
+        };
+        if let Some((rate_kbps, burst_kb)) = self.ingress_shaping(&iface)? {
+            qos.ingress_rate_kbps = rate_kbps;
+            qos.ingress_burst_kb = burst_kb;
         }
 
-        // Parse the interface info
-        // For now, return a basic status
         let port_info = NetworkPortInfo {
             port_id: port_id.to_string(),
             vm_id: String::new(),
@@ -263,13 +290,17 @@ impl OvsPortManager {
             ip_addresses: vec![],
             phase: NetworkPortPhase::Active,
             error_message: None,
-            ovs_port_name: Some(ovn_port_name.to_string()),
+            ovs_port_name: Some(iface.clone()),
             ovn_port_name: ovn_port_name.to_string(),
             interface_xml: String::new(),
-            rx_bytes: 0,
-            tx_bytes: 0,
-            rx_packets: 0,
-            tx_packets: 0,
+            rx_bytes: stat("rx_bytes"),
+            tx_bytes: stat("tx_bytes"),
+            rx_packets: stat("rx_packets"),
+            tx_packets: stat("tx_packets"),
+            effective_qos: Some(qos),
+            rx_dropped: stat("rx_dropped"),
+            tx_dropped: stat("tx_dropped"),
+            qos_dropped: qdisc_drops(&iface),
         };
 
         Ok(Some(port_info))
@@ -318,11 +349,12 @@ impl OvsPortManager {
     <parameters interfaceid='{ovn_port}'/>
   </virtualport>
   <mac address='{mac}'/>
-  <model type='virtio'/>
+  <model type='virtio'/>{bandwidth}
 </interface>"#,
             bridge = self.integration_bridge,
             ovn_port = config.ovn_port_name,
             mac = config.mac_address,
+            bandwidth = bandwidth_xml(config.qos.as_ref()),
         );
 
         Ok(xml)
@@ -344,12 +376,13 @@ impl OvsPortManager {
   </virtualport>
   <target dev='{target}'/>
   <mac address='{mac}'/>
-  <model type='virtio'/>
+  <model type='virtio'/>{bandwidth}
 </interface>"#,
             bridge = self.integration_bridge,
             ovn_port = config.ovn_port_name,
             target = target_dev,
             mac = config.mac_address,
+            bandwidth = bandwidth_xml(config.qos.as_ref()),
         );
 
         Ok(xml)
@@ -367,6 +400,396 @@ impl OvsPortManager {
     }
 }
 
+/// Run ovs-vsctl, returning stdout.
+fn vsctl(args: &[&str]) -> Result<String> {
+    let output = Command::new("ovs-vsctl")
+        .args(args)
+        .output()
+        .context("Failed to execute ovs-vsctl")?;
+
+    if !output.status.success() {
+        bail!("ovs-vsctl {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+impl OvsPortManager {
+    /// Mirror the traffic of `source_port` to the analyzer `output_port`.
+    ///
+    /// Both ports must be on the same bridge. The analyzer port only
+    /// receives mirrored traffic while the session exists (OVS stops
+    /// forwarding normal traffic to output ports).
+    #[instrument(skip(self))]
+    pub fn create_mirror(
+        &self,
+        name: &str,
+        source_port: &str,
+        output_port: &str,
+        direction: MirrorDirection,
+    ) -> Result<MirrorSession> {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c)) {
+            bail!("Invalid mirror name '{}'", name);
+        }
+        if source_port == output_port {
+            bail!("Source and output port must differ");
+        }
+        if self.list_mirrors()?.iter().any(|m| m.name == name) {
+            bail!("Mirror {} already exists", name);
+        }
+
+        let bridge = vsctl(&["port-to-br", source_port])
+            .with_context(|| format!("Port {} is not on an OVS bridge", source_port))?
+            .trim()
+            .to_string();
+        let output_bridge = vsctl(&["port-to-br", output_port])
+            .with_context(|| format!("Port {} is not on an OVS bridge", output_port))?;
+        if output_bridge.trim() != bridge {
+            bail!("Output port {} is not on bridge {}", output_port, bridge);
+        }
+
+        info!(mirror = %name, bridge = %bridge, source = %source_port, output = %output_port, ?direction, "Creating OVS mirror");
+
+        let name_arg = format!("name={}", name);
+        let mut args = vec![
+            "--", "--id=@src", "get", "Port", source_port,
+            "--", "--id=@out", "get", "Port", output_port,
+            "--", "--id=@m", "create", "Mirror", &name_arg, "output-port=@out",
+        ];
+        // select-src-port: packets received on the port (sent by the VM)
+        // select-dst-port: packets sent out of the port (to the VM)
+        match direction {
+            MirrorDirection::Both => args.extend(["select-src-port=@src", "select-dst-port=@src"]),
+            MirrorDirection::Egress => args.push("select-src-port=@src"),
+            MirrorDirection::Ingress => args.push("select-dst-port=@src"),
+        }
+        args.extend(["--", "add", "Bridge", &bridge, "mirrors", "@m"]);
+        vsctl(&args)?;
+
+        Ok(MirrorSession {
+            name: name.to_string(),
+            bridge,
+            source_port: source_port.to_string(),
+            output_port: output_port.to_string(),
+            direction,
+            tx_packets: 0,
+            tx_bytes: 0,
+        })
+    }
+
+    /// Remove a mirror session.
+    #[instrument(skip(self))]
+    pub fn delete_mirror(&self, name: &str) -> Result<()> {
+        let mirror = self.list_mirrors()?
+            .into_iter()
+            .find(|m| m.name == name)
+            .with_context(|| format!("Mirror {} not found", name))?;
+
+        info!(mirror = %name, bridge = %mirror.bridge, "Deleting OVS mirror");
+        vsctl(&[
+            "--", "--id=@m", "get", "Mirror", name,
+            "--", "remove", "Bridge", &mirror.bridge, "mirrors", "@m",
+        ])?;
+        Ok(())
+    }
+
+    /// List mirror sessions on all bridges.
+    #[instrument(skip(self))]
+    pub fn list_mirrors(&self) -> Result<Vec<MirrorSession>> {
+        let ports = vsctl(&["--format=json", "--columns=_uuid,name", "list", "Port"])?;
+        let bridges = vsctl(&["--format=json", "--columns=name,mirrors", "list", "Bridge"])?;
+        let mirrors = vsctl(&[
+            "--format=json",
+            "--columns=_uuid,name,select_src_port,select_dst_port,output_port,statistics",
+            "list", "Mirror",
+        ])?;
+        parse_mirrors(&ports, &bridges, &mirrors)
+    }
+}
+
+impl OvsPortManager {
+    /// Name of the OVS interface bound to an OVN logical port, if the VM is running.
+    pub fn find_interface(&self, ovn_port_name: &str) -> Result<Option<String>> {
+        let output = vsctl(&[
+            "--columns=name", "--format=json",
+            "find", "Interface", &format!("external_ids:iface-id={}", ovn_port_name),
+        ])?;
+        Ok(json_rows(&output)?
+            .first()
+            .and_then(|row| row.first())
+            .and_then(|name| name.as_str())
+            .map(String::from))
+    }
+
+    /// Enforce `qos` on a live interface, replacing any previous limits.
+    ///
+    /// Egress (VM to network) is policed on the interface; ingress (network
+    /// to VM) is shaped with an HTB queue on the port. Zero rates remove the
+    /// respective limit.
+    #[instrument(skip(self))]
+    pub fn apply_qos(&self, iface: &str, qos: &NetworkPortQoS) -> Result<()> {
+        info!(iface = %iface, ?qos, "Applying port QoS");
+
+        let old_qos = vsctl(&["get", "Port", iface, "qos"])?;
+        for args in qos_commands(iface, qos) {
+            let args: Vec<&str> = args.iter().map(String::as_str).collect();
+            vsctl(&args)?;
+        }
+
+        // QoS and Queue rows are root rows in OVSDB; drop the ones we replaced
+        let old_qos = old_qos.trim().trim_matches(['[', ']']);
+        if !old_qos.is_empty() {
+            let queues = vsctl(&["get", "QoS", old_qos, "queues"]).unwrap_or_default();
+            if let Err(e) = vsctl(&["--if-exists", "destroy", "QoS", old_qos]) {
+                warn!(iface = %iface, error = %e, "Failed to remove old QoS row");
+            }
+            for queue in parse_ovs_map(&queues).into_values() {
+                let _ = vsctl(&["--if-exists", "destroy", "Queue", &queue]);
+            }
+        }
+        Ok(())
+    }
+
+    /// Store `qos` as the `<bandwidth>` of a VM interface in the persistent
+    /// domain definition, so the limits come back when the VM starts again
+    /// (reboot, migration, node restart). libvirt then sets the OVS QoS itself.
+    #[instrument(skip(self))]
+    pub fn persist_qos(&self, vm_id: &str, mac_address: &str, qos: Option<&NetworkPortQoS>) -> Result<()> {
+        let output = Command::new("virsh")
+            .args(domiftune_args(vm_id, mac_address, qos))
+            .output()
+            .context("Failed to execute virsh domiftune")?;
+
+        if !output.status.success() {
+            bail!("virsh domiftune failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+        Ok(())
+    }
+
+    /// Ingress shaping (rate Kbps, burst KB) on a port, if any.
+    fn ingress_shaping(&self, iface: &str) -> Result<Option<(u64, u64)>> {
+        let qos = vsctl(&["get", "Port", iface, "qos"])?;
+        let qos = qos.trim().trim_matches(['[', ']']);
+        if qos.is_empty() {
+            return Ok(None);
+        }
+        let config = parse_ovs_map(&vsctl(&["get", "QoS", qos, "other_config"])?);
+        let rate = config.get("max-rate").and_then(|r| r.parse::<u64>().ok()).unwrap_or(0) / 1000;
+        let queues = parse_ovs_map(&vsctl(&["get", "QoS", qos, "queues"])?);
+        let burst = match queues.get("0") {
+            Some(queue) => parse_ovs_map(&vsctl(&["get", "Queue", queue, "other_config"])?)
+                .get("burst")
+                .and_then(|b| b.parse::<u64>().ok())
+                .unwrap_or(0) / 8000,
+            None => 0,
+        };
+        Ok(Some((rate, burst)))
+    }
+}
+
+/// ovs-vsctl invocations that set `qos` on `iface`.
+fn qos_commands(iface: &str, qos: &NetworkPortQoS) -> Vec<Vec<String>> {
+    // Policing is in kbps with the burst in kilobits; 0 disables it
+    let mut commands = vec![vec![
+        "set".to_string(), "Interface".to_string(), iface.to_string(),
+        format!("ingress_policing_rate={}", qos.egress_rate_kbps),
+        format!("ingress_policing_burst={}", if qos.egress_rate_kbps > 0 { qos.egress_burst_kb * 8 } else { 0 }),
+    ]];
+
+    if qos.ingress_rate_kbps == 0 {
+        commands.push(["--if-exists", "clear", "Port", iface, "qos"].map(String::from).to_vec());
+        return commands;
+    }
+
+    // HTB rates are in bit/s and the burst in bits
+    let rate = qos.ingress_rate_kbps * 1000;
+    let mut queue = vec![
+        "--".to_string(), "--id=@q0".to_string(), "create".to_string(), "Queue".to_string(),
+        format!("other_config:max-rate={}", rate),
+    ];
+    if qos.ingress_burst_kb > 0 {
+        queue.push(format!("other_config:burst={}", qos.ingress_burst_kb * 8000));
+    }
+    let mut shaper = vec![
+        "--".to_string(), "set".to_string(), "Port".to_string(), iface.to_string(), "qos=@q".to_string(),
+        "--".to_string(), "--id=@q".to_string(), "create".to_string(), "QoS".to_string(),
+        "type=linux-htb".to_string(),
+        format!("other_config:max-rate={}", rate),
+        "queues:0=@q0".to_string(),
+        format!("external_ids:limiquantix-port={}", iface),
+    ];
+    shaper.extend(queue);
+    commands.push(shaper);
+    commands
+}
+
+/// libvirt `<bandwidth>` element for `qos` (empty when unlimited).
+///
+/// libvirt takes averages in KB/s and bursts in KiB; inbound is traffic to
+/// the guest.
+fn bandwidth_xml(qos: Option<&NetworkPortQoS>) -> String {
+    let Some(qos) = qos.filter(|q| !q.is_unlimited()) else {
+        return String::new();
+    };
+    let limit = |element: &str, rate_kbps: u64, burst_kb: u64| {
+        if rate_kbps == 0 {
+            return String::new();
+        }
+        let burst = if burst_kb > 0 { format!(" burst='{}'", burst_kb) } else { String::new() };
+        format!("\n    <{} average='{}'{}/>", element, rate_kbps.div_ceil(8), burst)
+    };
+    format!(
+        "\n  <bandwidth>{}{}\n  </bandwidth>",
+        limit("inbound", qos.ingress_rate_kbps, qos.ingress_burst_kb),
+        limit("outbound", qos.egress_rate_kbps, qos.egress_burst_kb),
+    )
+}
+
+/// `virsh domiftune` arguments that store `qos` in the persistent definition
+/// only (the live port is configured through OVS). Rates are in KB/s like
+/// `bandwidth_xml`; a zero average clears a direction.
+fn domiftune_args(vm_id: &str, mac_address: &str, qos: Option<&NetworkPortQoS>) -> Vec<String> {
+    let qos = qos.cloned().unwrap_or_default();
+    // virsh takes average[,peak[,burst]]; the peak equals the average like the OVS limits
+    let rate = |rate_kbps: u64, burst_kb: u64| {
+        let average = rate_kbps.div_ceil(8);
+        if rate_kbps > 0 && burst_kb > 0 {
+            format!("{},{},{}", average, average, burst_kb)
+        } else {
+            average.to_string()
+        }
+    };
+    vec![
+        "domiftune".to_string(),
+        vm_id.to_string(),
+        mac_address.to_string(),
+        "--config".to_string(),
+        "--inbound".to_string(),
+        rate(qos.ingress_rate_kbps, qos.ingress_burst_kb),
+        "--outbound".to_string(),
+        rate(qos.egress_rate_kbps, qos.egress_burst_kb),
+    ]
+}
+
+/// Packets dropped by the qdiscs on `iface` (HTB shaper and ingress policer).
+fn qdisc_drops(iface: &str) -> u64 {
+    let Ok(output) = Command::new("tc").args(["-s", "-j", "qdisc", "show", "dev", iface]).output() else {
+        return 0;
+    };
+    serde_json::from_slice::<serde_json::Value>(&output.stdout)
+        .ok()
+        .and_then(|qdiscs| qdiscs.as_array().cloned())
+        .unwrap_or_default()
+        .iter()
+        // tc prints the counters at the top level, older versions under "stats"
+        .filter_map(|q| q.get("drops").or_else(|| q.get("stats").and_then(|s| s.get("drops"))))
+        .filter_map(|d| d.as_u64())
+        .sum()
+}
+
+/// Parse `ovs-vsctl get` map output: `{0=uuid, max-rate="1000"}`.
+fn parse_ovs_map(output: &str) -> std::collections::HashMap<String, String> {
+    output.trim()
+        .trim_start_matches('{')
+        .trim_end_matches('}')
+        .split(", ")
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(k, v)| (k.trim_matches('"').to_string(), v.trim_matches('"').to_string()))
+        .collect()
+}
+
+/// OVSDB map in JSON output (`["map", [[k, v], ...]]`) with values as strings.
+fn json_map(value: &serde_json::Value) -> std::collections::HashMap<String, String> {
+    value.get(1)
+        .and_then(|pairs| pairs.as_array())
+        .map(|pairs| {
+            pairs.iter()
+                .filter_map(|pair| {
+                    let value = pair.get(1)?;
+                    let value = value.as_str().map(String::from).unwrap_or_else(|| value.to_string());
+                    Some((pair.get(0)?.as_str()?.to_string(), value))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Rows of `ovs-vsctl --format=json list` output.
+fn json_rows(output: &str) -> Result<Vec<Vec<serde_json::Value>>> {
+    let table: serde_json::Value = serde_json::from_str(output).context("Invalid ovs-vsctl JSON")?;
+    Ok(table["data"].as_array()
+        .map(|rows| rows.iter().filter_map(|r| r.as_array().cloned()).collect())
+        .unwrap_or_default())
+}
+
+/// UUIDs in an OVSDB value: `["uuid", "x"]` or `["set", [["uuid", "x"], ...]]`.
+fn json_uuids(value: &serde_json::Value) -> Vec<String> {
+    match value.as_array().map(|v| (v.first().and_then(|t| t.as_str()), v.get(1))) {
+        Some((Some("uuid"), Some(id))) => id.as_str().map(|s| vec![s.to_string()]).unwrap_or_default(),
+        Some((Some("set"), Some(items))) => items.as_array()
+            .map(|items| items.iter().flat_map(json_uuids).collect())
+            .unwrap_or_default(),
+        _ => vec![],
+    }
+}
+
+fn parse_mirrors(ports: &str, bridges: &str, mirrors: &str) -> Result<Vec<MirrorSession>> {
+    let port_names: std::collections::HashMap<String, String> = json_rows(ports)?
+        .into_iter()
+        .filter_map(|row| {
+            let uuid = json_uuids(row.first()?).pop()?;
+            Some((uuid, row.get(1)?.as_str()?.to_string()))
+        })
+        .collect();
+    let mut bridge_of = std::collections::HashMap::new();
+    for row in json_rows(bridges)? {
+        let Some(name) = row.first().and_then(|n| n.as_str()) else { continue };
+        for mirror in row.get(1).map(json_uuids).unwrap_or_default() {
+            bridge_of.insert(mirror, name.to_string());
+        }
+    }
+    let port_name = |value: Option<&serde_json::Value>| {
+        value.map(json_uuids)
+            .unwrap_or_default()
+            .first()
+            .and_then(|uuid| port_names.get(uuid).cloned())
+    };
+
+    let mut sessions = Vec::new();
+    for row in json_rows(mirrors)? {
+        let Some(uuid) = row.first().map(json_uuids).and_then(|mut u| u.pop()) else { continue };
+        let Some(name) = row.get(1).and_then(|n| n.as_str()) else { continue };
+        let src = port_name(row.get(2));
+        let dst = port_name(row.get(3));
+        let direction = match (&src, &dst) {
+            (Some(_), Some(_)) => MirrorDirection::Both,
+            (Some(_), None) => MirrorDirection::Egress,
+            _ => MirrorDirection::Ingress,
+        };
+
+        // statistics: ["map", [["tx_bytes", 10], ["tx_packets", 1]]]
+        let stat = |key: &str| -> u64 {
+            row.get(5)
+                .and_then(|s| s.get(1))
+                .and_then(|pairs| pairs.as_array())
+                .and_then(|pairs| pairs.iter().find(|p| p.get(0).and_then(|k| k.as_str()) == Some(key)))
+                .and_then(|p| p.get(1))
+                .and_then(|v| v.as_u64())
+                .unwrap_or(0)
+        };
+
+        sessions.push(MirrorSession {
+            name: name.to_string(),
+            bridge: bridge_of.get(&uuid).cloned().unwrap_or_default(),
+            source_port: src.or(dst).unwrap_or_default(),
+            output_port: port_name(row.get(4)).unwrap_or_default(),
+            direction,
+            tx_packets: stat("tx_packets"),
+            tx_bytes: stat("tx_bytes"),
+        });
+    }
+    Ok(sessions)
+}
+
 impl Default for OvsPortManager {
     fn default() -> Self {
         Self::new()
@@ -398,5 +821,58 @@ mod tests {
         assert!(xml.contains("interfaceid='lsp-port-123'"));
         assert!(xml.contains("address='fa:16:3e:aa:bb:cc'"));
         assert!(xml.contains("type='virtio'"));
+        assert!(!xml.contains("bandwidth"));
+    }
+
+    #[test]
+    fn test_qos() {
+        let qos = NetworkPortQoS {
+            ingress_rate_kbps: 100_000,
+            egress_rate_kbps: 50_000,
+            ingress_burst_kb: 1000,
+            egress_burst_kb: 0,
+        };
+        let commands = qos_commands("vnet0", &qos);
+        assert_eq!(commands[0][3..], ["ingress_policing_rate=50000", "ingress_policing_burst=0"]);
+        assert!(commands[1].contains(&"other_config:max-rate=100000000".to_string()));
+        assert!(commands[1].contains(&"other_config:burst=8000000".to_string()));
+
+        let unlimited = qos_commands("vnet0", &NetworkPortQoS::default());
+        assert_eq!(unlimited[1], ["--if-exists", "clear", "Port", "vnet0", "qos"]);
+
+        let xml = bandwidth_xml(Some(&qos));
+        assert!(xml.contains("<inbound average='12500' burst='1000'/>"));
+        assert!(xml.contains("<outbound average='6250'/>"));
+        assert_eq!(bandwidth_xml(Some(&NetworkPortQoS::default())), "");
+
+        let args = domiftune_args("vm-1", "fa:16:3e:00:00:01", Some(&qos));
+        assert_eq!(
+            args,
+            ["domiftune", "vm-1", "fa:16:3e:00:00:01", "--config", "--inbound", "12500,12500,1000", "--outbound", "6250"]
+        );
+        let cleared = domiftune_args("vm-1", "fa:16:3e:00:00:01", None);
+        assert_eq!(cleared[4..], ["--inbound", "0", "--outbound", "0"]);
+
+        let map = parse_ovs_map("{0=\"5d3c\", max-rate=\"1000\"}\n");
+        assert_eq!(map.get("0").map(String::as_str), Some("5d3c"));
+        assert_eq!(map.get("max-rate").map(String::as_str), Some("1000"));
+    }
+
+    #[test]
+    fn test_parse_mirrors() {
+        let ports = r#"{"data":[[["uuid","p1"],"vnet0"],[["uuid","p2"],"tap-analyzer"]],"headings":["_uuid","name"]}"#;
+        let bridges = r#"{"data":[["br-int",["set",[["uuid","m1"]]]],["br0",["set",[]]]],"headings":["name","mirrors"]}"#;
+        let mirrors = r#"{"data":[[["uuid","m1"],"debug-web",["set",[["uuid","p1"]]],["set",[]],["uuid","p2"],["map",[["tx_bytes",1500],["tx_packets",3]]]]],"headings":[]}"#;
+
+        let sessions = parse_mirrors(ports, bridges, mirrors).unwrap();
+        assert_eq!(sessions, vec![MirrorSession {
+            name: "debug-web".to_string(),
+            bridge: "br-int".to_string(),
+            source_port: "vnet0".to_string(),
+            output_port: "tap-analyzer".to_string(),
+            direction: MirrorDirection::Egress,
+            tx_packets: 3,
+            tx_bytes: 1500,
+        }]);
     }
 }
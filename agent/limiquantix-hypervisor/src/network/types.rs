@@ -17,7 +17,10 @@ pub enum NetworkPortBindingType {
 }
 
 /// Network port QoS settings.
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+///
+/// Directions are from the VM's point of view: ingress is traffic delivered
+/// to the VM, egress is traffic sent by the VM. A rate of 0 means unlimited.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct NetworkPortQoS {
     /// Ingress rate limit in Kbps
     pub ingress_rate_kbps: u64,
@@ -29,6 +32,13 @@ pub struct NetworkPortQoS {
     pub egress_burst_kb: u64,
 }
 
+impl NetworkPortQoS {
+    /// Whether no limit is set in either direction.
+    pub fn is_unlimited(&self) -> bool {
+        self.ingress_rate_kbps == 0 && self.egress_rate_kbps == 0
+    }
+}
+
 /// Network port configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NetworkPortConfig {
@@ -97,6 +107,14 @@ pub struct NetworkPortInfo {
     pub rx_packets: u64,
     /// TX packets
     pub tx_packets: u64,
+    /// Limits currently enforced by OVS (None if the port has no interface yet)
+    pub effective_qos: Option<NetworkPortQoS>,
+    /// Packets dropped on receive from the VM
+    pub rx_dropped: u64,
+    /// Packets dropped on transmit to the VM
+    pub tx_dropped: u64,
+    /// Packets dropped by rate limiting (egress policer and ingress shaper)
+    pub qos_dropped: u64,
 }
 
 /// OVS status information.
@@ -131,3 +149,36 @@ impl Default for OvsStatus {
         }
     }
 }
+
+/// Which traffic of the source port an OVS mirror copies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MirrorDirection {
+    /// Both directions
+    #[default]
+    Both,
+    /// Traffic sent to the VM (leaving the port)
+    Ingress,
+    /// Traffic sent by the VM (entering the port)
+    Egress,
+}
+
+/// An OVS port mirror session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MirrorSession {
+    /// Mirror name (unique per host)
+    pub name: String,
+    /// Bridge the mirror is attached to
+    pub bridge: String,
+    /// Port whose traffic is copied
+    pub source_port: String,
+    /// Analyzer port receiving the copies
+    pub output_port: String,
+    pub direction: MirrorDirection,
+    /// Packets sent to the analyzer so far
+    #[serde(default)]
+    pub tx_packets: u64,
+    #[serde(default)]
+    pub tx_bytes: u64,
+}
@@ -0,0 +1,1198 @@
+//! OVF descriptors and OVA packages.
+//!
+//! Imports VMs exported by other hypervisors (VMware, VirtualBox, Hyper-V
+//! tooling) and exports stopped VMs in the same format:
+//! - **Import**: unpack the OVA, parse the OVF descriptor (CPU, memory,
+//!   disks, NICs, firmware, guest OS), verify the manifest, convert each
+//!   disk to qcow2 and map the result to a `VmConfig`
+//! - **Export**: convert each disk to a streamOptimized VMDK, generate the
+//!   OVF descriptor and a SHA256 manifest, and pack them as an OVA
+//!
+//! Archives are handled with `tar` and disks with `qemu-img`, like the rest
+//! of the storage code.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{debug, info, instrument, warn};
+use uuid::Uuid;
+
+use crate::error::{HypervisorError, Result};
+use crate::guest_os::GuestOSFamily;
+use crate::template::clone_folder_name;
+use crate::xml_tree::{parse_tree, Element};
+use crate::types::{DiskBus, DiskConfig, DiskFormat, Firmware, NicConfig, NicModel, VmConfig};
+
+const GIB: u64 = 1024 * 1024 * 1024;
+
+/// A disk declared in an OVF descriptor.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OvfDisk {
+    /// Disk ID (`ovf:diskId`)
+    pub disk_id: String,
+    /// File in the package backing this disk (None = blank disk)
+    pub file_href: Option<String>,
+    /// Virtual capacity in bytes
+    pub capacity_bytes: u64,
+    /// Controller the disk is attached to
+    pub bus: DiskBus,
+}
+
+/// A network adapter declared in an OVF descriptor.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OvfNic {
+    /// Source network name (`rasd:Connection`)
+    pub network: Option<String>,
+    /// Adapter type (`rasd:ResourceSubType`, e.g. "VmxNet3", "E1000")
+    pub adapter_type: Option<String>,
+    /// MAC address, if the source recorded one
+    pub mac_address: Option<String>,
+}
+
+/// The parts of an OVF descriptor needed to recreate a VM.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OvfDescriptor {
+    /// Virtual system name
+    pub name: String,
+    /// VMware guest ID (`vmw:osType`, e.g. "rhel8_64Guest")
+    pub os_type: Option<String>,
+    /// CIM operating system ID (`OperatingSystemSection ovf:id`)
+    pub os_id: Option<u32>,
+    /// Operating system description
+    pub os_description: Option<String>,
+    /// Total virtual CPUs
+    pub vcpus: u32,
+    /// Cores per socket (1 if not specified)
+    pub cores_per_socket: u32,
+    /// Memory in MiB
+    pub memory_mib: u64,
+    /// Firmware type
+    pub firmware: Firmware,
+    /// Disks in controller order
+    pub disks: Vec<OvfDisk>,
+    /// Network adapters
+    pub nics: Vec<OvfNic>,
+}
+
+/// Options for importing an OVA.
+#[derive(Debug, Clone, Default)]
+pub struct OvaImportOptions {
+    /// Name for the new VM (defaults to the OVF virtual system name)
+    pub name: Option<String>,
+    /// ID for the new VM (generated if None)
+    pub vm_id: Option<String>,
+    /// Bridge to connect all NICs to (defaults to `NicConfig::default()`)
+    pub bridge: Option<String>,
+    /// Keep the MAC addresses from the descriptor instead of allocating new ones
+    pub keep_mac_addresses: bool,
+}
+
+// =============================================================================
+// Descriptor parsing
+// =============================================================================
+
+/// Parse a CIM allocation unit string ("byte", "byte * 2^20", "MegaBytes", ...)
+/// into a multiplier in bytes.
+fn parse_allocation_units(units: &str) -> Option<u64> {
+    let normalized: String = units.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_lowercase();
+    
+    match normalized.as_str() {
+        "" | "byte" | "bytes" => return Some(1),
+        "kilobytes" | "kb" => return Some(1 << 10),
+        "megabytes" | "mb" => return Some(1 << 20),
+        "gigabytes" | "gb" => return Some(1 << 30),
+        _ => {}
+    }
+    
+    let factor = normalized.strip_prefix("byte*")?;
+    match factor.split_once('^') {
+        Some((base, exp)) => base.parse::<u64>().ok()?.checked_pow(exp.parse().ok()?),
+        None => factor.parse().ok(),
+    }
+}
+
+/// Parse an OVF descriptor.
+pub fn parse_ovf(xml: &str) -> Result<OvfDescriptor> {
+    let root = parse_tree(xml)?;
+    if root.name != "Envelope" {
+        return Err(HypervisorError::XmlError(format!("Not an OVF envelope: <{}>", root.name)));
+    }
+    
+    // File references: ovf:id -> ovf:href
+    let mut files: HashMap<&str, &str> = HashMap::new();
+    if let Some(references) = root.child("References") {
+        for file in references.children("File") {
+            if let Some(compression) = file.attr("compression") {
+                return Err(HypervisorError::InvalidConfig(format!(
+                    "Compressed OVF files ({}) are not supported", compression
+                )));
+            }
+            if let (Some(id), Some(href)) = (file.attr("id"), file.attr("href")) {
+                files.insert(id, href);
+            }
+        }
+    }
+    
+    // Disk declarations: ovf:diskId -> (file, capacity)
+    let mut disk_decls: HashMap<&str, (Option<String>, u64)> = HashMap::new();
+    if let Some(section) = root.find("DiskSection") {
+        for disk in section.children("Disk") {
+            let Some(disk_id) = disk.attr("diskId") else { continue };
+            let capacity: u64 = disk.attr("capacity").and_then(|c| c.parse().ok()).unwrap_or(0);
+            let units = disk.attr("capacityAllocationUnits").unwrap_or("byte");
+            let multiplier = parse_allocation_units(units).ok_or_else(|| {
+                HypervisorError::InvalidConfig(format!("Unsupported disk capacity units: {}", units))
+            })?;
+            let file = disk.attr("fileRef").and_then(|r| files.get(r)).map(|h| h.to_string());
+            disk_decls.insert(disk_id, (file, capacity.saturating_mul(multiplier)));
+        }
+    }
+    
+    let system = root.find("VirtualSystem")
+        .ok_or_else(|| HypervisorError::InvalidConfig("OVF has no VirtualSystem".into()))?;
+    
+    let name = system.child_text("Name")
+        .or_else(|| system.attr("id"))
+        .unwrap_or("imported-vm")
+        .to_string();
+    
+    let os_section = system.child("OperatingSystemSection");
+    let os_type = os_section.and_then(|s| s.attr("osType")).map(str::to_string);
+    let os_id = os_section.and_then(|s| s.attr("id")).and_then(|id| id.parse().ok());
+    let os_description = os_section.and_then(|s| s.child_text("Description")).map(str::to_string);
+    
+    let hardware = system.child("VirtualHardwareSection")
+        .ok_or_else(|| HypervisorError::InvalidConfig("OVF has no VirtualHardwareSection".into()))?;
+    
+    let mut descriptor = OvfDescriptor {
+        name,
+        os_type,
+        os_id,
+        os_description,
+        vcpus: 1,
+        cores_per_socket: 1,
+        memory_mib: 1024,
+        firmware: Firmware::Bios,
+        disks: Vec::new(),
+        nics: Vec::new(),
+    };
+    
+    // OVF 1.x uses <Item>; OVF 2.x adds <StorageItem> and <EthernetPortItem>
+    let items: Vec<&Element> = hardware.children.iter()
+        .filter(|c| c.name.ends_with("Item"))
+        .collect();
+    
+    // Controllers by InstanceID
+    let controllers: HashMap<&str, DiskBus> = items.iter()
+        .filter_map(|item| {
+            let id = item.child_text("InstanceID")?;
+            let bus = match item.child_text("ResourceType")? {
+                "5" => DiskBus::Ide,
+                "6" => DiskBus::Scsi,
+                "20" => DiskBus::Sata,
+                _ => return None,
+            };
+            Some((id, bus))
+        })
+        .collect();
+    
+    for item in &items {
+        match item.child_text("ResourceType").unwrap_or("") {
+            // Processor
+            "3" => {
+                descriptor.vcpus = item.child_text("VirtualQuantity")
+                    .and_then(|q| q.parse().ok())
+                    .unwrap_or(1)
+                    .max(1);
+                descriptor.cores_per_socket = item.child_text("CoresPerSocket")
+                    .and_then(|q| q.parse().ok())
+                    .filter(|&c: &u32| c > 0 && descriptor.vcpus.is_multiple_of(c))
+                    .unwrap_or(1);
+            }
+            // Memory
+            "4" => {
+                let quantity: u64 = item.child_text("VirtualQuantity")
+                    .and_then(|q| q.parse().ok())
+                    .unwrap_or(0);
+                let units = item.child_text("AllocationUnits").unwrap_or("byte * 2^20");
+                let multiplier = parse_allocation_units(units).ok_or_else(|| {
+                    HypervisorError::InvalidConfig(format!("Unsupported memory units: {}", units))
+                })?;
+                let mib = quantity.saturating_mul(multiplier) / (1024 * 1024);
+                if mib > 0 {
+                    descriptor.memory_mib = mib;
+                }
+            }
+            // Ethernet adapter
+            "10" => {
+                descriptor.nics.push(OvfNic {
+                    network: item.child_text("Connection").map(str::to_string),
+                    adapter_type: item.child_text("ResourceSubType").map(str::to_string),
+                    mac_address: item.child_text("Address").map(str::to_lowercase),
+                });
+            }
+            // Disk drive
+            "17" => {
+                let Some(host_resource) = item.child_text("HostResource") else { continue };
+                // "ovf:/disk/vmdisk1" (also seen as "/disk/vmdisk1")
+                let disk_id = host_resource.rsplit('/').next().unwrap_or(host_resource);
+                let (file_href, capacity_bytes) = disk_decls.get(disk_id).cloned().ok_or_else(|| {
+                    HypervisorError::InvalidConfig(format!("Disk item references unknown disk {}", host_resource))
+                })?;
+                let bus = item.child_text("Parent")
+                    .and_then(|p| controllers.get(p).copied())
+                    .unwrap_or(DiskBus::Scsi);
+                descriptor.disks.push(OvfDisk {
+                    disk_id: disk_id.to_string(),
+                    file_href,
+                    capacity_bytes,
+                    bus,
+                });
+            }
+            _ => {}
+        }
+    }
+    
+    // VMware records firmware as <vmw:Config vmw:key="firmware" vmw:value="efi"/>
+    let efi = hardware.children("Config")
+        .any(|c| c.attr("key") == Some("firmware") && c.attr("value") == Some("efi"));
+    if efi {
+        descriptor.firmware = Firmware::Uefi;
+    }
+    
+    Ok(descriptor)
+}
+
+/// Map OVF guest OS information to a Guest OS family.
+///
+/// The VMware guest ID is the most specific source, then the free-text
+/// description, then the CIM operating system ID.
+pub fn guest_os_from_ovf(os_type: Option<&str>, description: Option<&str>, os_id: Option<u32>) -> GuestOSFamily {
+    for hint in [os_type, description].into_iter().flatten() {
+        let family = guest_os_from_hint(&hint.to_lowercase());
+        if family != GuestOSFamily::Unspecified {
+            return family;
+        }
+    }
+    
+    match os_id {
+        Some(79) | Some(80) => GuestOSFamily::Rhel,
+        Some(82..=85) => GuestOSFamily::Suse,
+        Some(93..=96) => GuestOSFamily::Debian,
+        Some(36) | Some(101) => GuestOSFamily::GenericLinux,
+        Some(42) | Some(78) => GuestOSFamily::FreeBsd,
+        Some(65) => GuestOSFamily::OpenBsd,
+        Some(64) => GuestOSFamily::NetBsd,
+        Some(29) | Some(81) => GuestOSFamily::Solaris,
+        Some(67..=77) | Some(105) => GuestOSFamily::WindowsLegacy,
+        Some(103) => GuestOSFamily::WindowsServer,
+        _ => GuestOSFamily::Unspecified,
+    }
+}
+
+fn guest_os_from_hint(hint: &str) -> GuestOSFamily {
+    // Order matters: "darwin" contains "win", and server/desktop IDs share a prefix
+    if hint.contains("darwin") || hint.contains("macos") {
+        GuestOSFamily::MacOs
+    } else if hint.contains("win") {
+        if hint.contains("srv") || hint.contains("server") {
+            GuestOSFamily::WindowsServer
+        } else if ["windows9", "windows10", "windows11", "windows 10", "windows 11"].iter().any(|w| hint.contains(w)) {
+            GuestOSFamily::WindowsDesktop
+        } else {
+            GuestOSFamily::WindowsLegacy
+        }
+    } else if ["rhel", "red hat", "centos", "rocky", "alma", "oracle"].iter().any(|w| hint.contains(w)) {
+        GuestOSFamily::Rhel
+    } else if hint.contains("fedora") {
+        GuestOSFamily::Fedora
+    } else if ["ubuntu", "debian"].iter().any(|w| hint.contains(w)) {
+        GuestOSFamily::Debian
+    } else if ["sles", "suse"].iter().any(|w| hint.contains(w)) {
+        GuestOSFamily::Suse
+    } else if hint.contains("freebsd") {
+        GuestOSFamily::FreeBsd
+    } else if hint.contains("openbsd") {
+        GuestOSFamily::OpenBsd
+    } else if hint.contains("netbsd") {
+        GuestOSFamily::NetBsd
+    } else if hint.contains("solaris") {
+        GuestOSFamily::Solaris
+    } else if hint.contains("linux") {
+        GuestOSFamily::GenericLinux
+    } else {
+        GuestOSFamily::Unspecified
+    }
+}
+
+impl OvfDescriptor {
+    /// Guest OS family for this descriptor.
+    pub fn guest_os(&self) -> GuestOSFamily {
+        guest_os_from_ovf(self.os_type.as_deref(), self.os_description.as_deref(), self.os_id)
+    }
+    
+    /// Build a `VmConfig` using already-converted disk images (one per OVF disk).
+    pub fn to_vm_config(&self, disk_paths: &[PathBuf], options: &OvaImportOptions) -> VmConfig {
+        let guest_os = self.guest_os();
+        
+        let mut config = VmConfig::new(options.name.clone().unwrap_or_else(|| self.name.clone()))
+            .with_guest_os(guest_os)
+            .with_memory(self.memory_mib);
+        if let Some(ref id) = options.vm_id {
+            config = config.with_id(id.clone());
+        }
+        
+        config.cpu.sockets = self.vcpus / self.cores_per_socket;
+        config.cpu.cores = self.cores_per_socket;
+        config.boot.firmware = self.firmware;
+        
+        for (i, (disk, path)) in self.disks.iter().zip(disk_paths).enumerate() {
+            config.disks.push(DiskConfig {
+                id: format!("disk{}", i),
+                path: path.to_string_lossy().into_owned(),
+                size_gib: disk.capacity_bytes.div_ceil(GIB).max(1),
+                // Keep the source controller type: the guest may not have virtio drivers
+                bus: disk.bus,
+                format: DiskFormat::Qcow2,
+                bootable: i == 0,
+                ..Default::default()
+            });
+        }
+        
+        for nic in &self.nics {
+            let mut nic_config = NicConfig {
+                model: nic_model(nic.adapter_type.as_deref(), guest_os),
+                // Left empty, the MAC allocator assigns one when the VM is created
+                mac_address: nic.mac_address.clone().filter(|_| options.keep_mac_addresses),
+                ..Default::default()
+            };
+            if let Some(ref bridge) = options.bridge {
+                nic_config.bridge = Some(bridge.clone());
+            }
+            config.nics.push(nic_config);
+        }
+        
+        config
+    }
+}
+
+/// Pick a NIC model the guest already has a driver for.
+fn nic_model(adapter_type: Option<&str>, guest_os: GuestOSFamily) -> NicModel {
+    match adapter_type.map(str::to_lowercase).as_deref() {
+        Some("e1000") | Some("pcnet32") => NicModel::E1000,
+        Some("e1000e") => NicModel::E1000e,
+        Some("virtio") => NicModel::Virtio,
+        // VMXNET3 and unknown adapters: Linux ships virtio drivers, Windows ships e1000e
+        _ if guest_os.is_windows() => NicModel::E1000e,
+        _ => NicModel::Virtio,
+    }
+}
+
+// =============================================================================
+// Descriptor generation
+// =============================================================================
+
+/// A disk image included in an exported OVA.
+#[derive(Debug, Clone)]
+pub struct OvfExportDisk {
+    /// File name inside the OVA
+    pub file_name: String,
+    /// Size of the file in bytes
+    pub file_size: u64,
+    /// Virtual capacity in bytes
+    pub capacity_bytes: u64,
+    /// Controller bus
+    pub bus: DiskBus,
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// CIM operating system ID and VMware guest ID for a Guest OS family.
+fn ovf_os_ids(guest_os: GuestOSFamily) -> (u32, &'static str) {
+    match guest_os {
+        GuestOSFamily::Rhel => (80, "rhel8_64Guest"),
+        GuestOSFamily::Debian => (96, "debian10_64Guest"),
+        GuestOSFamily::Suse => (85, "sles15_64Guest"),
+        GuestOSFamily::Fedora => (101, "fedora64Guest"),
+        GuestOSFamily::Arch | GuestOSFamily::GenericLinux | GuestOSFamily::Unspecified => {
+            (101, "other4xLinux64Guest")
+        }
+        GuestOSFamily::WindowsServer => (1, "windows2019srv_64Guest"),
+        GuestOSFamily::WindowsDesktop => (1, "windows9_64Guest"),
+        GuestOSFamily::WindowsLegacy => (105, "windows7_64Guest"),
+        GuestOSFamily::FreeBsd => (78, "freebsd12_64Guest"),
+        GuestOSFamily::OpenBsd => (65, "otherGuest64"),
+        GuestOSFamily::NetBsd => (64, "otherGuest64"),
+        GuestOSFamily::MacOs => (1, "darwin19_64Guest"),
+        GuestOSFamily::Solaris => (81, "solaris11_64Guest"),
+        GuestOSFamily::Other => (1, "otherGuest64"),
+    }
+}
+
+/// Generate an OVF 1.0 descriptor for a VM.
+///
+/// `disks` must be in the same order as `config.disks`.
+pub fn build_ovf(config: &VmConfig, disks: &[OvfExportDisk]) -> String {
+    let name = xml_escape(&config.name);
+    let (os_id, os_type) = ovf_os_ids(config.guest_os);
+    
+    let mut references = String::new();
+    let mut disk_section = String::new();
+    for (i, disk) in disks.iter().enumerate() {
+        references.push_str(&format!(
+            "    <File ovf:id=\"file{n}\" ovf:href=\"{href}\" ovf:size=\"{size}\"/>\n",
+            n = i + 1,
+            href = xml_escape(&disk.file_name),
+            size = disk.file_size,
+        ));
+        disk_section.push_str(&format!(
+            "    <Disk ovf:diskId=\"vmdisk{n}\" ovf:fileRef=\"file{n}\" ovf:capacity=\"{cap}\" \
+ovf:capacityAllocationUnits=\"byte\" ovf:format=\"http://www.vmware.com/interfaces/specifications/vmdk.html#streamOptimized\"/>\n",
+            n = i + 1,
+            cap = disk.capacity_bytes,
+        ));
+    }
+    
+    let mut items = String::new();
+    let mut instance_id = 1;
+    let mut next_id = || {
+        let id = instance_id;
+        instance_id += 1;
+        id
+    };
+    
+    items.push_str(&format!(
+        r#"      <Item>
+        <rasd:AllocationUnits>hertz * 10^6</rasd:AllocationUnits>
+        <rasd:ElementName>{vcpus} virtual CPU(s)</rasd:ElementName>
+        <rasd:InstanceID>{id}</rasd:InstanceID>
+        <rasd:ResourceType>3</rasd:ResourceType>
+        <rasd:VirtualQuantity>{vcpus}</rasd:VirtualQuantity>
+        <vmw:CoresPerSocket ovf:required="false">{cores}</vmw:CoresPerSocket>
+      </Item>
+"#,
+        vcpus = config.cpu.total_vcpus(),
+        cores = config.cpu.cores * config.cpu.threads_per_core,
+        id = next_id(),
+    ));
+    
+    items.push_str(&format!(
+        r#"      <Item>
+        <rasd:AllocationUnits>byte * 2^20</rasd:AllocationUnits>
+        <rasd:ElementName>{mib}MB of memory</rasd:ElementName>
+        <rasd:InstanceID>{id}</rasd:InstanceID>
+        <rasd:ResourceType>4</rasd:ResourceType>
+        <rasd:VirtualQuantity>{mib}</rasd:VirtualQuantity>
+      </Item>
+"#,
+        mib = config.memory.size_mib,
+        id = next_id(),
+    ));
+    
+    // One controller per bus type in use; virtio disks are exported on SCSI
+    let export_bus = |bus: DiskBus| if bus == DiskBus::Virtio { DiskBus::Scsi } else { bus };
+    let mut controller_ids: Vec<(DiskBus, u32)> = Vec::new();
+    for disk in disks {
+        let bus = export_bus(disk.bus);
+        if controller_ids.iter().any(|(b, _)| *b == bus) {
+            continue;
+        }
+        let id = next_id();
+        let (resource_type, sub_type, label) = match bus {
+            DiskBus::Ide => ("5", "PIIX4", "IDE Controller 0"),
+            DiskBus::Sata => ("20", "vmware.sata.ahci", "SATA Controller 0"),
+            _ => ("6", "lsilogic", "SCSI Controller 0"),
+        };
+        items.push_str(&format!(
+            r#"      <Item>
+        <rasd:Address>0</rasd:Address>
+        <rasd:ElementName>{label}</rasd:ElementName>
+        <rasd:InstanceID>{id}</rasd:InstanceID>
+        <rasd:ResourceSubType>{sub_type}</rasd:ResourceSubType>
+        <rasd:ResourceType>{resource_type}</rasd:ResourceType>
+      </Item>
+"#
+        ));
+        controller_ids.push((bus, id));
+    }
+    
+    let mut unit_on_bus: HashMap<u32, u32> = HashMap::new();
+    for (i, disk) in disks.iter().enumerate() {
+        let bus = export_bus(disk.bus);
+        let parent = controller_ids.iter().find(|(b, _)| *b == bus).map(|(_, id)| *id).unwrap_or(0);
+        let unit = unit_on_bus.entry(parent).or_insert(0);
+        items.push_str(&format!(
+            r#"      <Item>
+        <rasd:AddressOnParent>{unit}</rasd:AddressOnParent>
+        <rasd:ElementName>Hard disk {n}</rasd:ElementName>
+        <rasd:HostResource>ovf:/disk/vmdisk{n}</rasd:HostResource>
+        <rasd:InstanceID>{id}</rasd:InstanceID>
+        <rasd:Parent>{parent}</rasd:Parent>
+        <rasd:ResourceType>17</rasd:ResourceType>
+      </Item>
+"#,
+            n = i + 1,
+            unit = *unit,
+            id = next_id(),
+        ));
+        *unit += 1;
+    }
+    
+    let mut networks: Vec<String> = Vec::new();
+    for (i, nic) in config.nics.iter().enumerate() {
+        let network = nic.network.clone()
+            .or_else(|| nic.bridge.clone())
+            .unwrap_or_else(|| "VM Network".to_string());
+        if !networks.contains(&network) {
+            networks.push(network.clone());
+        }
+        let sub_type = match nic.model {
+            NicModel::E1000 => "E1000",
+            NicModel::E1000e => "E1000e",
+            // VMware has no virtio-net; VMXNET3 is the paravirtual equivalent
+            NicModel::Virtio | NicModel::Rtl8139 => "VmxNet3",
+        };
+        let address = nic.mac_address.as_ref()
+            .map(|mac| format!("        <rasd:Address>{}</rasd:Address>\n", xml_escape(mac)))
+            .unwrap_or_default();
+        items.push_str(&format!(
+            r#"      <Item>
+{address}        <rasd:AddressOnParent>{slot}</rasd:AddressOnParent>
+        <rasd:AutomaticAllocation>true</rasd:AutomaticAllocation>
+        <rasd:Connection>{network}</rasd:Connection>
+        <rasd:ElementName>Network adapter {n}</rasd:ElementName>
+        <rasd:InstanceID>{id}</rasd:InstanceID>
+        <rasd:ResourceSubType>{sub_type}</rasd:ResourceSubType>
+        <rasd:ResourceType>10</rasd:ResourceType>
+      </Item>
+"#,
+            slot = 7 + i,
+            network = xml_escape(&network),
+            n = i + 1,
+            id = next_id(),
+        ));
+    }
+    
+    let network_section: String = networks.iter()
+        .map(|n| format!(
+            "    <Network ovf:name=\"{name}\">\n      <Description>The {name} network</Description>\n    </Network>\n",
+            name = xml_escape(n)
+        ))
+        .collect();
+    
+    let firmware = match config.boot.firmware {
+        Firmware::Uefi => "efi",
+        Firmware::Bios => "bios",
+    };
+    
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<Envelope xmlns="http://schemas.dmtf.org/ovf/envelope/1" xmlns:ovf="http://schemas.dmtf.org/ovf/envelope/1" xmlns:rasd="http://schemas.dmtf.org/wbem/wscim/1/cim-schema/2/CIM_ResourceAllocationSettingData" xmlns:vssd="http://schemas.dmtf.org/wbem/wscim/1/cim-schema/2/CIM_VirtualSystemSettingData" xmlns:vmw="http://www.vmware.com/schema/ovf">
+  <References>
+{references}  </References>
+  <DiskSection>
+    <Info>Virtual disk information</Info>
+{disk_section}  </DiskSection>
+  <NetworkSection>
+    <Info>The list of logical networks</Info>
+{network_section}  </NetworkSection>
+  <VirtualSystem ovf:id="{name}">
+    <Info>A virtual machine</Info>
+    <Name>{name}</Name>
+    <OperatingSystemSection ovf:id="{os_id}" vmw:osType="{os_type}">
+      <Info>The kind of installed guest operating system</Info>
+    </OperatingSystemSection>
+    <VirtualHardwareSection>
+      <Info>Virtual hardware requirements</Info>
+      <System>
+        <vssd:ElementName>Virtual Hardware Family</vssd:ElementName>
+        <vssd:InstanceID>0</vssd:InstanceID>
+        <vssd:VirtualSystemIdentifier>{name}</vssd:VirtualSystemIdentifier>
+        <vssd:VirtualSystemType>vmx-13</vssd:VirtualSystemType>
+      </System>
+{items}      <vmw:Config ovf:required="false" vmw:key="firmware" vmw:value="{firmware}"/>
+    </VirtualHardwareSection>
+  </VirtualSystem>
+</Envelope>
+"#
+    )
+}
+
+// =============================================================================
+// Manifests
+// =============================================================================
+
+/// Compute the SHA256 of a file as lowercase hex.
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = std::fs::File::open(path)
+        .map_err(|e| HypervisorError::Internal(format!("Failed to open {}: {}", path.display(), e)))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)
+        .map_err(|e| HypervisorError::Internal(format!("Failed to read {}: {}", path.display(), e)))?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Parse manifest lines of the form `SHA256(file.vmdk)= <hex>`.
+fn parse_manifest(contents: &str) -> Vec<(String, String, String)> {
+    contents.lines()
+        .filter_map(|line| {
+            let (algorithm, rest) = line.trim().split_once('(')?;
+            let (file, digest) = rest.split_once(")=")?;
+            Some((algorithm.trim().to_uppercase(), file.to_string(), digest.trim().to_lowercase()))
+        })
+        .collect()
+}
+
+/// Verify the files listed in a manifest.
+///
+/// SHA256 entries are checked; other algorithms (SHA1 from older exporters)
+/// are skipped with a warning.
+fn verify_manifest(dir: &Path, manifest: &Path) -> Result<()> {
+    let contents = std::fs::read_to_string(manifest)
+        .map_err(|e| HypervisorError::Internal(format!("Failed to read {}: {}", manifest.display(), e)))?;
+    
+    for (algorithm, file, expected) in parse_manifest(&contents) {
+        if algorithm != "SHA256" {
+            warn!(file = %file, algorithm = %algorithm, "Skipping unsupported manifest digest");
+            continue;
+        }
+        let actual = sha256_file(&safe_join(dir, &file)?)?;
+        if actual != expected {
+            return Err(HypervisorError::InvalidConfig(format!(
+                "Checksum mismatch for {} (manifest {}, actual {})", file, expected, actual
+            )));
+        }
+        debug!(file = %file, "Manifest digest verified");
+    }
+    
+    Ok(())
+}
+
+/// Join a package-relative file name, rejecting paths that escape `dir`.
+fn safe_join(dir: &Path, file: &str) -> Result<PathBuf> {
+    let relative = Path::new(file);
+    if relative.is_absolute() || relative.components().any(|c| matches!(c, std::path::Component::ParentDir)) {
+        return Err(HypervisorError::InvalidConfig(format!("Invalid file name in OVF package: {}", file)));
+    }
+    Ok(dir.join(relative))
+}
+
+// =============================================================================
+// Import / export
+// =============================================================================
+
+fn run_tool(program: &str, args: &[&str]) -> Result<()> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| HypervisorError::Internal(format!("{} failed: {}", program, e)))?;
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(HypervisorError::Internal(format!("{} {} failed: {}", program, args[0], stderr.trim())));
+    }
+    
+    Ok(())
+}
+
+/// Unpack an OVA into `dest`.
+///
+/// Only regular files and directories are accepted: a symlink or hard link
+/// member could point the disk conversion (or a later member) outside the
+/// staging directory.
+fn unpack_ova(source: &Path, dest: &Path) -> Result<()> {
+    let output = Command::new("tar")
+        .args(["-tvf"])
+        .arg(source)
+        .output()
+        .map_err(|e| HypervisorError::Internal(format!("tar failed: {}", e)))?;
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(HypervisorError::InvalidConfig(format!("Invalid OVA archive: {}", stderr.trim())));
+    }
+    
+    // Verbose listings start with the `ls -l` style mode, e.g. "-rw-r--r--"
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        if !matches!(line.chars().next(), Some('-') | Some('d')) {
+            return Err(HypervisorError::InvalidConfig(format!(
+                "OVA contains an unsupported member (only regular files are allowed): {}", line
+            )));
+        }
+    }
+    
+    run_tool("tar", &["-xf", &source.to_string_lossy(), "-C", &dest.to_string_lossy(), "--no-same-owner"])?;
+    check_regular_files(dest)
+}
+
+/// Recursively check that `dir` holds only regular files and directories.
+fn check_regular_files(dir: &Path) -> Result<()> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| HypervisorError::Internal(format!("Failed to read {}: {}", dir.display(), e)))?;
+    
+    for entry in entries.flatten() {
+        let file_type = entry.file_type()
+            .map_err(|e| HypervisorError::Internal(format!("Failed to stat {}: {}", entry.path().display(), e)))?;
+        if file_type.is_dir() {
+            check_regular_files(&entry.path())?;
+        } else if !file_type.is_file() {
+            return Err(HypervisorError::InvalidConfig(format!(
+                "OVA contains a non-regular file: {}", entry.path().display()
+            )));
+        }
+    }
+    
+    Ok(())
+}
+
+/// qemu-img format of a packaged disk, from its file name.
+fn disk_format(href: &str) -> Result<&'static str> {
+    match Path::new(href).extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase()) {
+        Some(ext) if ext == "vmdk" => Ok("vmdk"),
+        Some(ext) if ext == "qcow2" => Ok("qcow2"),
+        _ => Err(HypervisorError::InvalidConfig(format!(
+            "Unsupported disk format in OVF package: {} (expected .vmdk or .qcow2)", href
+        ))),
+    }
+}
+
+/// Check a packaged disk before converting it.
+///
+/// The image must be a regular file inside `package_dir` with no backing file,
+/// and every VMDK extent must also live inside `package_dir`; otherwise a
+/// crafted descriptor could make `qemu-img` read arbitrary host files or
+/// devices into the imported disk.
+fn inspect_disk(package_dir: &Path, src: &Path, format: &str) -> Result<()> {
+    let metadata = std::fs::symlink_metadata(src)
+        .map_err(|e| HypervisorError::InvalidConfig(format!("Disk {} not found: {}", src.display(), e)))?;
+    if !metadata.is_file() {
+        return Err(HypervisorError::InvalidConfig(format!("Disk {} is not a regular file", src.display())));
+    }
+    
+    let output = Command::new("qemu-img")
+        .args(["info", "-f", format, "--output=json"])
+        .arg(src)
+        .output()
+        .map_err(|e| HypervisorError::Internal(format!("qemu-img failed: {}", e)))?;
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(HypervisorError::InvalidConfig(format!(
+            "Invalid disk image {}: {}", src.display(), stderr.trim()
+        )));
+    }
+    
+    let info: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| HypervisorError::Internal(format!("Invalid qemu-img info output: {}", e)))?;
+    
+    if let Some(backing) = info["backing-filename"].as_str() {
+        return Err(HypervisorError::InvalidConfig(format!(
+            "Disk {} references a backing file ({}), which is not allowed in an OVF package",
+            src.display(), backing
+        )));
+    }
+    
+    let root = package_dir.canonicalize()
+        .map_err(|e| HypervisorError::Internal(format!("Failed to resolve {}: {}", package_dir.display(), e)))?;
+    let extents = info["format-specific"]["data"]["extents"].as_array().cloned().unwrap_or_default();
+    for extent in extents {
+        let Some(filename) = extent["filename"].as_str() else { continue };
+        let inside = Path::new(filename).canonicalize()
+            .map(|path| path.starts_with(&root))
+            .unwrap_or(false);
+        if !inside {
+            return Err(HypervisorError::InvalidConfig(format!(
+                "Disk {} has an extent outside the package: {}", src.display(), filename
+            )));
+        }
+    }
+    
+    Ok(())
+}
+
+/// Convert (or create, for blank disks) one OVF disk at `dest`.
+fn import_disk(package_dir: &Path, disk: &OvfDisk, dest: &Path) -> Result<()> {
+    let dest_str = dest.to_string_lossy();
+    
+    match disk.file_href {
+        Some(ref href) => {
+            let src = safe_join(package_dir, href)?;
+            let format = disk_format(href)?;
+            inspect_disk(package_dir, &src, format)?;
+            info!(disk = %disk.disk_id, source = %src.display(), dest = %dest.display(), "Converting disk");
+            run_tool("qemu-img", &["convert", "-f", format, "-O", "qcow2", &src.to_string_lossy(), &dest_str])
+        }
+        None => {
+            let size = disk.capacity_bytes.to_string();
+            run_tool("qemu-img", &["create", "-f", "qcow2", &dest_str, &size])
+        }
+    }
+}
+
+fn find_with_extension(dir: &Path, extension: &str) -> Option<PathBuf> {
+    std::fs::read_dir(dir).ok()?
+        .flatten()
+        .map(|e| e.path())
+        .find(|p| p.extension().map(|e| e.eq_ignore_ascii_case(extension)).unwrap_or(false))
+}
+
+/// Import an OVA (or an unpacked `.ovf` with its disks alongside).
+///
+/// Disks are converted to qcow2 in `<vms_root>/<name>_<uuid8>/`. The returned
+/// `VmConfig` is ready to be passed to `Hypervisor::create_vm`. This is a
+/// blocking operation (archive extraction and disk conversion).
+#[instrument(skip(options), fields(source = %source.display()))]
+pub fn import_ova(source: &Path, vms_root: &Path, options: &OvaImportOptions) -> Result<VmConfig> {
+    if !source.exists() {
+        return Err(HypervisorError::InvalidConfig(format!("{} does not exist", source.display())));
+    }
+    
+    // Unpack next to the destination so large disks are not copied across filesystems
+    std::fs::create_dir_all(vms_root)
+        .map_err(|e| HypervisorError::Internal(format!("Failed to create {}: {}", vms_root.display(), e)))?;
+    let staging = tempfile::Builder::new()
+        .prefix(".ova-import-")
+        .tempdir_in(vms_root)
+        .map_err(|e| HypervisorError::Internal(format!("Failed to create staging directory: {}", e)))?;
+    
+    let (package_dir, ovf_path) = if source.extension().map(|e| e.eq_ignore_ascii_case("ovf")).unwrap_or(false) {
+        (source.parent().unwrap_or(Path::new(".")).to_path_buf(), source.to_path_buf())
+    } else {
+        info!("Unpacking OVA");
+        unpack_ova(source, staging.path())?;
+        let ovf = find_with_extension(staging.path(), "ovf")
+            .ok_or_else(|| HypervisorError::InvalidConfig("OVA contains no .ovf descriptor".into()))?;
+        (staging.path().to_path_buf(), ovf)
+    };
+    
+    let xml = std::fs::read_to_string(&ovf_path)
+        .map_err(|e| HypervisorError::Internal(format!("Failed to read {}: {}", ovf_path.display(), e)))?;
+    let descriptor = parse_ovf(&xml)?;
+    
+    if let Some(manifest) = find_with_extension(&package_dir, "mf") {
+        verify_manifest(&package_dir, &manifest)?;
+    }
+    
+    let vm_id = options.vm_id.clone().unwrap_or_else(|| Uuid::new_v4().to_string());
+    let name = options.name.clone().unwrap_or_else(|| descriptor.name.clone());
+    let vm_dir = vms_root.join(clone_folder_name(&name, &vm_id));
+    std::fs::create_dir_all(&vm_dir)
+        .map_err(|e| HypervisorError::Internal(format!("Failed to create {}: {}", vm_dir.display(), e)))?;
+    
+    let mut disk_paths = Vec::new();
+    for (i, disk) in descriptor.disks.iter().enumerate() {
+        let dest = vm_dir.join(format!("disk{}.qcow2", i));
+        
+        if let Err(e) = import_disk(&package_dir, disk, &dest) {
+            let _ = std::fs::remove_dir_all(&vm_dir);
+            return Err(e);
+        }
+        disk_paths.push(dest);
+    }
+    
+    let options = OvaImportOptions {
+        name: Some(name),
+        vm_id: Some(vm_id),
+        ..options.clone()
+    };
+    let config = descriptor.to_vm_config(&disk_paths, &options);
+    
+    info!(
+        vm_id = %config.id,
+        name = %config.name,
+        guest_os = ?config.guest_os,
+        disks = config.disks.len(),
+        nics = config.nics.len(),
+        "OVA imported"
+    );
+    Ok(config)
+}
+
+/// Virtual size of a disk image in bytes.
+fn image_virtual_size(path: &str) -> Result<u64> {
+    let output = Command::new("qemu-img")
+        .args(["info", "--output=json", path])
+        .output()
+        .map_err(|e| HypervisorError::Internal(format!("qemu-img failed: {}", e)))?;
+    
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(HypervisorError::Internal(format!("qemu-img info failed: {}", stderr.trim())));
+    }
+    
+    let info: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| HypervisorError::Internal(format!("Invalid qemu-img info output: {}", e)))?;
+    info["virtual-size"].as_u64()
+        .ok_or_else(|| HypervisorError::Internal("qemu-img info reported no virtual size".into()))
+}
+
+/// Export a (stopped) VM as an OVA at `dest`.
+///
+/// The package contains the OVF descriptor first (as the OVA format
+/// requires), then a SHA256 manifest, then one streamOptimized VMDK per disk.
+/// This is a blocking operation.
+#[instrument(skip(config), fields(vm_id = %config.id, dest = %dest.display()))]
+pub fn export_ova(config: &VmConfig, dest: &Path) -> Result<()> {
+    let parent = dest.parent().unwrap_or(Path::new("."));
+    std::fs::create_dir_all(parent)
+        .map_err(|e| HypervisorError::Internal(format!("Failed to create {}: {}", parent.display(), e)))?;
+    let staging = tempfile::Builder::new()
+        .prefix(".ova-export-")
+        .tempdir_in(parent)
+        .map_err(|e| HypervisorError::Internal(format!("Failed to create staging directory: {}", e)))?;
+    
+    let base_name = clone_folder_name(&config.name, &config.id);
+    
+    let mut disks = Vec::new();
+    for (i, disk) in config.disks.iter().enumerate() {
+        let file_name = format!("{}-disk{}.vmdk", base_name, i + 1);
+        let vmdk = staging.path().join(&file_name);
+        
+        info!(disk = %disk.id, source = %disk.path, "Converting disk to VMDK");
+        run_tool("qemu-img", &[
+            "convert", "-O", "vmdk", "-o", "subformat=streamOptimized",
+            &disk.path,
+            &vmdk.to_string_lossy(),
+        ])?;
+        
+        let file_size = std::fs::metadata(&vmdk)
+            .map_err(|e| HypervisorError::Internal(format!("Failed to stat {}: {}", vmdk.display(), e)))?
+            .len();
+        disks.push(OvfExportDisk {
+            file_name,
+            file_size,
+            capacity_bytes: image_virtual_size(&disk.path)?,
+            bus: disk.bus,
+        });
+    }
+    
+    let ovf_name = format!("{}.ovf", base_name);
+    std::fs::write(staging.path().join(&ovf_name), build_ovf(config, &disks))
+        .map_err(|e| HypervisorError::Internal(format!("Failed to write OVF descriptor: {}", e)))?;
+    
+    let mut manifest = String::new();
+    for file in std::iter::once(&ovf_name).chain(disks.iter().map(|d| &d.file_name)) {
+        let digest = sha256_file(&staging.path().join(file))?;
+        manifest.push_str(&format!("SHA256({})= {}\n", file, digest));
+    }
+    let mf_name = format!("{}.mf", base_name);
+    std::fs::write(staging.path().join(&mf_name), manifest)
+        .map_err(|e| HypervisorError::Internal(format!("Failed to write manifest: {}", e)))?;
+    
+    // Write to a temporary name so a failed export never leaves a partial OVA
+    let partial = dest.with_extension("ova.partial");
+    let partial_str = partial.to_string_lossy().into_owned();
+    let staging_str = staging.path().to_string_lossy().into_owned();
+    let mut args: Vec<&str> = vec!["-cf", &partial_str, "--format=ustar", "-C", &staging_str, &ovf_name, &mf_name];
+    args.extend(disks.iter().map(|d| d.file_name.as_str()));
+    
+    run_tool("tar", &args)
+        .and_then(|_| std::fs::rename(&partial, dest)
+            .map_err(|e| HypervisorError::Internal(format!("Failed to write {}: {}", dest.display(), e))))
+        .inspect_err(|_| {
+            let _ = std::fs::remove_file(&partial);
+        })?;
+    
+    info!(disks = disks.len(), "VM exported as OVA");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const VMWARE_OVF: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<Envelope xmlns="http://schemas.dmtf.org/ovf/envelope/1" xmlns:ovf="http://schemas.dmtf.org/ovf/envelope/1" xmlns:rasd="http://schemas.dmtf.org/wbem/wscim/1/cim-schema/2/CIM_ResourceAllocationSettingData" xmlns:vmw="http://www.vmware.com/schema/ovf">
+  <References>
+    <File ovf:href="web-01-disk1.vmdk" ovf:id="file1" ovf:size="1048576"/>
+  </References>
+  <DiskSection>
+    <Info>Virtual disk information</Info>
+    <Disk ovf:capacity="40" ovf:capacityAllocationUnits="byte * 2^30" ovf:diskId="vmdisk1" ovf:fileRef="file1"/>
+    <Disk ovf:capacity="10737418240" ovf:diskId="vmdisk2"/>
+  </DiskSection>
+  <VirtualSystem ovf:id="web-01">
+    <Info>A virtual machine</Info>
+    <Name>web-01</Name>
+    <OperatingSystemSection ovf:id="80" vmw:osType="rhel8_64Guest">
+      <Info>The kind of installed guest operating system</Info>
+      <Description>Red Hat Enterprise Linux 8 (64-bit)</Description>
+    </OperatingSystemSection>
+    <VirtualHardwareSection>
+      <Item>
+        <rasd:InstanceID>1</rasd:InstanceID>
+        <rasd:ResourceType>3</rasd:ResourceType>
+        <rasd:VirtualQuantity>4</rasd:VirtualQuantity>
+        <vmw:CoresPerSocket ovf:required="false">2</vmw:CoresPerSocket>
+      </Item>
+      <Item>
+        <rasd:AllocationUnits>byte * 2^20</rasd:AllocationUnits>
+        <rasd:InstanceID>2</rasd:InstanceID>
+        <rasd:ResourceType>4</rasd:ResourceType>
+        <rasd:VirtualQuantity>8192</rasd:VirtualQuantity>
+      </Item>
+      <Item>
+        <rasd:InstanceID>3</rasd:InstanceID>
+        <rasd:ResourceSubType>VirtualSCSI</rasd:ResourceSubType>
+        <rasd:ResourceType>6</rasd:ResourceType>
+      </Item>
+      <Item>
+        <rasd:InstanceID>4</rasd:InstanceID>
+        <rasd:ResourceType>20</rasd:ResourceType>
+      </Item>
+      <Item>
+        <rasd:HostResource>ovf:/disk/vmdisk1</rasd:HostResource>
+        <rasd:InstanceID>5</rasd:InstanceID>
+        <rasd:Parent>3</rasd:Parent>
+        <rasd:ResourceType>17</rasd:ResourceType>
+      </Item>
+      <Item>
+        <rasd:HostResource>ovf:/disk/vmdisk2</rasd:HostResource>
+        <rasd:InstanceID>6</rasd:InstanceID>
+        <rasd:Parent>4</rasd:Parent>
+        <rasd:ResourceType>17</rasd:ResourceType>
+      </Item>
+      <Item>
+        <rasd:Address>00:50:56:AB:CD:EF</rasd:Address>
+        <rasd:Connection>VM Network</rasd:Connection>
+        <rasd:InstanceID>7</rasd:InstanceID>
+        <rasd:ResourceSubType>VmxNet3</rasd:ResourceSubType>
+        <rasd:ResourceType>10</rasd:ResourceType>
+      </Item>
+      <vmw:Config ovf:required="false" vmw:key="firmware" vmw:value="efi"/>
+    </VirtualHardwareSection>
+  </VirtualSystem>
+</Envelope>
+"#;
+
+    #[test]
+    fn test_parse_vmware_ovf() {
+        let ovf = parse_ovf(VMWARE_OVF).unwrap();
+        
+        assert_eq!(ovf.name, "web-01");
+        assert_eq!(ovf.vcpus, 4);
+        assert_eq!(ovf.cores_per_socket, 2);
+        assert_eq!(ovf.memory_mib, 8192);
+        assert_eq!(ovf.firmware, Firmware::Uefi);
+        assert_eq!(ovf.guest_os(), GuestOSFamily::Rhel);
+        
+        assert_eq!(ovf.disks.len(), 2);
+        assert_eq!(ovf.disks[0].file_href.as_deref(), Some("web-01-disk1.vmdk"));
+        assert_eq!(ovf.disks[0].capacity_bytes, 40 * GIB);
+        assert_eq!(ovf.disks[0].bus, DiskBus::Scsi);
+        assert_eq!(ovf.disks[1].file_href, None);
+        assert_eq!(ovf.disks[1].bus, DiskBus::Sata);
+        
+        assert_eq!(ovf.nics.len(), 1);
+        assert_eq!(ovf.nics[0].network.as_deref(), Some("VM Network"));
+        assert_eq!(ovf.nics[0].mac_address.as_deref(), Some("00:50:56:ab:cd:ef"));
+    }
+    
+    #[test]
+    fn test_descriptor_to_vm_config() {
+        let ovf = parse_ovf(VMWARE_OVF).unwrap();
+        let options = OvaImportOptions {
+            name: Some("imported".into()),
+            bridge: Some("br0".into()),
+            keep_mac_addresses: true,
+            ..Default::default()
+        };
+        let config = ovf.to_vm_config(&[PathBuf::from("/p/disk0.qcow2"), PathBuf::from("/p/disk1.qcow2")], &options);
+        
+        assert_eq!(config.name, "imported");
+        assert_eq!(config.cpu.total_vcpus(), 4);
+        assert_eq!(config.cpu.sockets, 2);
+        assert_eq!(config.memory.size_mib, 8192);
+        assert_eq!(config.boot.firmware, Firmware::Uefi);
+        assert_eq!(config.guest_os, GuestOSFamily::Rhel);
+        assert_eq!(config.disks[0].size_gib, 40);
+        assert!(config.disks[0].bootable && !config.disks[1].bootable);
+        assert_eq!(config.nics[0].model, NicModel::Virtio);
+        assert_eq!(config.nics[0].bridge.as_deref(), Some("br0"));
+        assert_eq!(config.nics[0].mac_address.as_deref(), Some("00:50:56:ab:cd:ef"));
+    }
+    
+    #[test]
+    fn test_guest_os_mapping() {
+        assert_eq!(guest_os_from_ovf(Some("windows2019srv_64Guest"), None, None), GuestOSFamily::WindowsServer);
+        assert_eq!(guest_os_from_ovf(Some("windows9_64Guest"), None, None), GuestOSFamily::WindowsDesktop);
+        assert_eq!(guest_os_from_ovf(Some("windows7_64Guest"), None, None), GuestOSFamily::WindowsLegacy);
+        assert_eq!(guest_os_from_ovf(Some("ubuntu64Guest"), None, None), GuestOSFamily::Debian);
+        assert_eq!(guest_os_from_ovf(None, Some("Debian GNU/Linux 12"), None), GuestOSFamily::Debian);
+        assert_eq!(guest_os_from_ovf(None, None, Some(85)), GuestOSFamily::Suse);
+        assert_eq!(guest_os_from_ovf(None, None, None), GuestOSFamily::Unspecified);
+        
+        // Windows guests without virtio drivers get an emulated NIC
+        assert_eq!(nic_model(Some("VmxNet3"), GuestOSFamily::WindowsServer), NicModel::E1000e);
+    }
+    
+    #[test]
+    fn test_export_descriptor_round_trip() {
+        let config = VmConfig::new("db & cache")
+            .with_cpu(2)
+            .with_memory(4096)
+            .with_guest_os(GuestOSFamily::Debian)
+            .with_disk(DiskConfig::new("/vms/db/disk0.qcow2"))
+            .with_nic(NicConfig {
+                mac_address: Some("52:54:00:12:34:56".into()),
+                model: NicModel::E1000,
+                ..Default::default()
+            });
+        let disks = vec![OvfExportDisk {
+            file_name: "db-disk1.vmdk".into(),
+            file_size: 1234,
+            capacity_bytes: 20 * GIB,
+            bus: DiskBus::Virtio,
+        }];
+        
+        let ovf = parse_ovf(&build_ovf(&config, &disks)).unwrap();
+        
+        assert_eq!(ovf.name, "db & cache");
+        assert_eq!(ovf.vcpus, 2);
+        assert_eq!(ovf.memory_mib, 4096);
+        assert_eq!(ovf.firmware, Firmware::Bios);
+        assert_eq!(ovf.guest_os(), GuestOSFamily::Debian);
+        assert_eq!(ovf.disks[0].capacity_bytes, 20 * GIB);
+        assert_eq!(ovf.disks[0].bus, DiskBus::Scsi);
+        assert_eq!(ovf.nics[0].adapter_type.as_deref(), Some("E1000"));
+        assert_eq!(ovf.nics[0].mac_address.as_deref(), Some("52:54:00:12:34:56"));
+    }
+    
+    #[test]
+    fn test_manifest_and_allocation_units() {
+        let entries = parse_manifest("SHA256(a.ovf)= ABCDEF\nSHA1(b.vmdk)= 0123\n");
+        assert_eq!(entries[0], ("SHA256".into(), "a.ovf".into(), "abcdef".into()));
+        assert_eq!(entries[1].0, "SHA1");
+        
+        assert_eq!(parse_allocation_units("byte * 2^20"), Some(1 << 20));
+        assert_eq!(parse_allocation_units("byte * 1024"), Some(1024));
+        assert_eq!(parse_allocation_units("MegaBytes"), Some(1 << 20));
+        assert_eq!(parse_allocation_units("furlongs"), None);
+        
+        assert!(safe_join(Path::new("/tmp"), "../etc/passwd").is_err());
+        
+        assert_eq!(disk_format("disk1.VMDK").unwrap(), "vmdk");
+        assert_eq!(disk_format("disk1.qcow2").unwrap(), "qcow2");
+        assert!(disk_format("disk1.img").is_err());
+    }
+    
+    #[test]
+    fn test_rejects_non_regular_members() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("vm.ovf"), "<Envelope/>").unwrap();
+        assert!(check_regular_files(dir.path()).is_ok());
+        
+        std::os::unix::fs::symlink("/etc/shadow", dir.path().join("disk1.vmdk")).unwrap();
+        assert!(check_regular_files(dir.path()).is_err());
+        assert!(inspect_disk(dir.path(), &dir.path().join("disk1.vmdk"), "vmdk").is_err());
+    }
+}
@@ -738,6 +738,45 @@ impl StorageBackend for CephBackend {
         ).await
     }
     
+    async fn copy_volume(
+        &self,
+        pool_id: &str,
+        source_volume_id: &str,
+        dest_volume_id: &str,
+    ) -> Result<()> {
+        let state = self.get_pool_state(pool_id).await?;
+        let source_spec = self.image_spec(&state, source_volume_id);
+        let dest_spec = self.image_spec(&state, dest_volume_id);
+        
+        info!(source = %source_spec, dest = %dest_spec, "Copying RBD volume");
+        
+        // `rbd cp` writes a flat image with no parent
+        self.run_rbd(&["cp", &source_spec, &dest_spec], &state)?;
+        
+        info!(volume_id = %dest_volume_id, "RBD volume copied");
+        Ok(())
+    }
+    
+    async fn list_clones(&self, pool_id: &str, volume_id: &str) -> Result<Vec<String>> {
+        let state = self.get_pool_state(pool_id).await?;
+        let image_spec = self.image_spec(&state, volume_id);
+        
+        let snaps = self.run_rbd(&["snap", "ls", &image_spec, "--format", "json"], &state)?;
+        let snaps: Vec<serde_json::Value> = serde_json::from_str(&snaps).unwrap_or_default();
+        
+        let mut clones = Vec::new();
+        for snap in snaps {
+            let Some(name) = snap["name"].as_str() else { continue };
+            let children = self.run_rbd(
+                &["children", &format!("{}@{}", image_spec, name), "--format", "json"],
+                &state,
+            )?;
+            clones.extend(parse_rbd_children(&children));
+        }
+        
+        Ok(clones)
+    }
+    
     async fn create_snapshot(
         &self,
         pool_id: &str,
@@ -766,6 +805,24 @@ impl StorageBackend for CephBackend {
     }
 }
 
+/// Parse `rbd children --format json` output into image names.
+///
+/// Newer releases emit objects (`{"pool": .., "image": ..}`), older ones
+/// emit plain `pool/image` strings.
+fn parse_rbd_children(output: &str) -> Vec<String> {
+    let children: Vec<serde_json::Value> = serde_json::from_str(output).unwrap_or_default();
+    
+    children.iter()
+        .filter_map(|child| match child {
+            serde_json::Value::String(spec) => {
+                Some(spec.rsplit('/').next().unwrap_or(spec).to_string())
+            }
+            serde_json::Value::Object(_) => child["image"].as_str().map(String::from),
+            _ => None,
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -855,4 +912,15 @@ mod tests {
         assert_eq!(ceph.monitors.len(), 2);
         assert_eq!(ceph.user, "admin");
     }
+    
+    #[test]
+    fn test_parse_rbd_children() {
+        let json = r#"[{"pool":"vms","pool_namespace":"","image":"clone-a"},{"pool":"vms","image":"clone-b"}]"#;
+        assert_eq!(parse_rbd_children(json), vec!["clone-a", "clone-b"]);
+        
+        let legacy = r#"["vms/clone-a"]"#;
+        assert_eq!(parse_rbd_children(legacy), vec!["clone-a"]);
+        
+        assert!(parse_rbd_children("[]").is_empty());
+    }
 }
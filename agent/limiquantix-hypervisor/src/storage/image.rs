@@ -0,0 +1,64 @@
+//! qemu-img helpers shared by the file-based backends (local, NFS).
+
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+use crate::error::{HypervisorError, Result};
+use super::types::VolumeInfo;
+
+/// Copy `source` into a standalone qcow2 at `dest`, flattening any backing chain.
+pub(super) async fn copy_image(qemu_img: &str, source: &Path, dest: &Path) -> Result<()> {
+    if !source.exists() {
+        return Err(HypervisorError::InvalidConfig(
+            format!("Volume {} not found", source.display())
+        ));
+    }
+    if dest.exists() {
+        return Err(HypervisorError::InvalidConfig(
+            format!("Volume {} already exists", dest.display())
+        ));
+    }
+
+    let output = Command::new(qemu_img)
+        .args(["convert", "-O", "qcow2"])
+        .arg(source)
+        .arg(dest)
+        .output()
+        .await
+        .map_err(|e| HypervisorError::Internal(format!("qemu-img convert failed: {}", e)))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(HypervisorError::Internal(format!("qemu-img convert failed: {}", stderr)));
+    }
+
+    Ok(())
+}
+
+/// Names of the volumes whose backing file is `source`.
+pub(super) async fn clones_of(qemu_img: &str, source: &Path, volumes: Vec<VolumeInfo>) -> Vec<String> {
+    let mut clones = Vec::new();
+
+    for volume in volumes {
+        if backing_file(qemu_img, Path::new(&volume.path)).await.as_deref() == Some(source) {
+            clones.push(volume.name);
+        }
+    }
+
+    clones
+}
+
+/// Backing file of an image, if it has one.
+async fn backing_file(qemu_img: &str, path: &Path) -> Option<PathBuf> {
+    let output = Command::new(qemu_img)
+        // Running VMs hold a write lock on their images
+        .args(["info", "--output=json", "--force-share"])
+        .arg(path)
+        .output()
+        .await
+        .ok()
+        .filter(|output| output.status.success())?;
+
+    let info: serde_json::Value = serde_json::from_slice(&output.stdout).ok()?;
+    info["backing-filename"].as_str().map(PathBuf::from)
+}
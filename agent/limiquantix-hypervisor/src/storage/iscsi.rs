@@ -642,6 +642,39 @@ impl StorageBackend for IscsiBackend {
         ).await
     }
     
+    async fn copy_volume(
+        &self,
+        _pool_id: &str,
+        _source_volume_id: &str,
+        _dest_volume_id: &str,
+    ) -> Result<()> {
+        Err(HypervisorError::OperationFailed(
+            "Full volume copies are not supported on iSCSI pools".into()
+        ))
+    }
+    
+    async fn list_clones(&self, pool_id: &str, volume_id: &str) -> Result<Vec<String>> {
+        let state = self.get_pool_state(pool_id).await?;
+        
+        // LVM snapshots report their source LV in the origin column
+        let output = self.run_cmd("lvs", &[
+            "--noheadings",
+            "-o", "lv_name,origin",
+            &state.volume_group,
+        ])?;
+        
+        let clones = output.lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let name = fields.next()?;
+                let origin = fields.next()?;
+                (origin == volume_id).then(|| name.to_string())
+            })
+            .collect();
+        
+        Ok(clones)
+    }
+    
     async fn create_snapshot(
         &self,
         pool_id: &str,
@@ -11,6 +11,7 @@ use tracing::{debug, info, instrument, warn};
 use crate::error::{HypervisorError, Result};
 use super::types::{DiskInfo, PoolConfig, PoolInfo, PoolType, VolumeAttachInfo, VolumeSource, VolumeInfo};
 use super::traits::StorageBackend;
+use super::image;
 
 /// Default storage base path for disk images.
 pub const DEFAULT_STORAGE_PATH: &str = "/var/lib/limiquantix/images";
@@ -159,6 +160,8 @@ impl LocalBackend {
             .args([
                 "info",
                 "--output=json",
+                // Running VMs hold a write lock on their images
+                "--force-share",
                 path.to_str().unwrap_or_default(),
             ])
             .output()
@@ -447,6 +450,28 @@ impl StorageBackend for LocalBackend {
         ).await
     }
     
+    async fn copy_volume(
+        &self,
+        pool_id: &str,
+        source_volume_id: &str,
+        dest_volume_id: &str,
+    ) -> Result<()> {
+        let source_path = self.volume_path(pool_id, source_volume_id);
+        let dest_path = self.volume_path(pool_id, dest_volume_id);
+        
+        image::copy_image(&self.qemu_img_path, &source_path, &dest_path).await?;
+        
+        info!(pool_id = %pool_id, source = %source_volume_id, dest = %dest_volume_id, "Volume copied");
+        Ok(())
+    }
+    
+    async fn list_clones(&self, pool_id: &str, volume_id: &str) -> Result<Vec<String>> {
+        let source_path = self.volume_path(pool_id, volume_id);
+        let volumes = self.list_volumes(pool_id).await?;
+        
+        Ok(image::clones_of(&self.qemu_img_path, &source_path, volumes).await)
+    }
+    
     async fn create_snapshot(
         &self,
         pool_id: &str,
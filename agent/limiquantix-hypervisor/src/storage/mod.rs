@@ -27,6 +27,7 @@ mod local;
 mod nfs;
 mod ceph;
 mod iscsi;
+mod image;
 mod types;
 mod traits;
 
@@ -279,6 +280,45 @@ impl StorageManager {
         Ok(())
     }
     
+    /// Copy a volume (independent of the source).
+    #[instrument(skip(self), fields(pool_id = %pool_id, source_id = %source_volume_id, dest_id = %dest_volume_id))]
+    pub async fn copy_volume(
+        &self,
+        pool_id: &str,
+        source_volume_id: &str,
+        dest_volume_id: &str,
+    ) -> Result<()> {
+        let pool_type = {
+            let pools = self.pools.read().await;
+            pools.get(pool_id)
+                .map(|p| p.pool_type)
+                .ok_or_else(|| HypervisorError::Internal(
+                    format!("Pool {} not found", pool_id)
+                ))?
+        };
+        
+        let backend = self.get_backend(pool_type)?;
+        backend.copy_volume(pool_id, source_volume_id, dest_volume_id).await?;
+        
+        info!("Volume copied");
+        Ok(())
+    }
+    
+    /// List copy-on-write clones that depend on a volume.
+    pub async fn list_clones(&self, pool_id: &str, volume_id: &str) -> Result<Vec<String>> {
+        let pool_type = {
+            let pools = self.pools.read().await;
+            pools.get(pool_id)
+                .map(|p| p.pool_type)
+                .ok_or_else(|| HypervisorError::Internal(
+                    format!("Pool {} not found", pool_id)
+                ))?
+        };
+        
+        let backend = self.get_backend(pool_type)?;
+        backend.list_clones(pool_id, volume_id).await
+    }
+    
     /// Create a snapshot of a volume.
     #[instrument(skip(self), fields(pool_id = %pool_id, volume_id = %volume_id, snapshot_id = %snapshot_id))]
     pub async fn create_snapshot(
@@ -29,6 +29,7 @@ use tracing::{debug, info, instrument, warn};
 use crate::error::{HypervisorError, Result};
 use super::types::{PoolConfig, PoolInfo, PoolType, VolumeAttachInfo, VolumeSource, VolumeInfo};
 use super::traits::StorageBackend;
+use super::image;
 
 /// Base path for NFS mount points.
 /// Must match the pattern used by QvDC: /var/lib/limiquantix/mnt/nfs-{poolId}
@@ -89,6 +90,8 @@ impl NfsBackend {
             .args([
                 "info",
                 "--output=json",
+                // Running VMs hold a write lock on their images
+                "--force-share",
                 path.to_str().unwrap_or_default(),
             ])
             .output()
@@ -592,6 +595,28 @@ impl StorageBackend for NfsBackend {
         ).await
     }
     
+    async fn copy_volume(
+        &self,
+        pool_id: &str,
+        source_volume_id: &str,
+        dest_volume_id: &str,
+    ) -> Result<()> {
+        let source_path = self.volume_path(pool_id, source_volume_id);
+        let dest_path = self.volume_path(pool_id, dest_volume_id);
+        
+        image::copy_image(&self.qemu_img_path, &source_path, &dest_path).await?;
+        
+        info!(pool_id = %pool_id, source = %source_volume_id, dest = %dest_volume_id, "Volume copied");
+        Ok(())
+    }
+    
+    async fn list_clones(&self, pool_id: &str, volume_id: &str) -> Result<Vec<String>> {
+        let source_path = self.volume_path(pool_id, volume_id);
+        let volumes = self.list_volumes(pool_id).await?;
+        
+        Ok(image::clones_of(&self.qemu_img_path, &source_path, volumes).await)
+    }
+    
     async fn create_snapshot(
         &self,
         pool_id: &str,
@@ -70,6 +70,22 @@ pub trait StorageBackend: Send + Sync {
         dest_volume_id: &str,
     ) -> Result<()>;
     
+    /// Copy a volume.
+    ///
+    /// Unlike `clone_volume`, the copy is fully independent of the source
+    /// (no backing chain or parent snapshot), so the source can be deleted.
+    async fn copy_volume(
+        &self,
+        pool_id: &str,
+        source_volume_id: &str,
+        dest_volume_id: &str,
+    ) -> Result<()>;
+    
+    /// List volumes that depend on a volume (copy-on-write clones).
+    ///
+    /// A volume with dependent clones cannot be safely deleted.
+    async fn list_clones(&self, pool_id: &str, volume_id: &str) -> Result<Vec<String>>;
+    
     /// Create a snapshot of a volume.
     async fn create_snapshot(
         &self,
@@ -0,0 +1,793 @@
+//! VM templates and clones.
+//!
+//! A template is a read-only snapshot of a stopped VM: its `VmConfig` plus a
+//! read-only copy of each disk. VMs are created from templates as either:
+//! - **Full clones**: independent copies of the template disks
+//! - **Linked clones**: copy-on-write overlays backed by the template disks
+//!
+//! Disk storage:
+//! - File disks (local directory and NFS pools) are handled with `qemu-img`.
+//!   Template images live in `<pool>/templates/<template_id>/`, next to the
+//!   VMs that use them, so linked clones on shared storage work on any host.
+//! - Block volumes (Ceph RBD) go through `StorageManager::copy_volume` and
+//!   `clone_volume` (RBD layering).
+//!
+//! A template cannot be deleted while linked clones still depend on it.
+//!
+//! Generalization: Linux file disks are run through `virt-sysprep` (if
+//! installed) so clones do not share the machine-id, SSH host keys or
+//! persistent NIC names of the source. Windows guests and block volumes
+//! cannot be generalized offline; run sysprep inside the guest before
+//! converting it. `TemplateInfo::generalized` records which case applies.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+use tokio::process::Command;
+use tracing::{info, instrument, warn};
+use uuid::Uuid;
+
+use crate::error::{HypervisorError, Result};
+use crate::network::MacAllocator;
+use crate::storage::{PoolType, StorageManager};
+use crate::types::VmConfig;
+
+/// Default directory for template metadata (and disks not on a pool).
+pub const DEFAULT_TEMPLATE_PATH: &str = "/var/lib/limiquantix/templates";
+
+/// `virt-sysprep` operations that reset a Linux guest's identity.
+const SYSPREP_OPERATIONS: &str = "machine-id,ssh-hostkeys,net-hwaddr,udev-persistent-net,\
+dhcp-client-state,random-seed,logfiles,bash-history,tmp-files";
+
+/// How a clone's disks relate to the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CloneType {
+    /// Independent copy of every disk
+    #[default]
+    Full,
+    /// Copy-on-write overlays on the template disks
+    Linked,
+}
+
+/// Where a disk image is stored.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum DiskLocation {
+    /// Image file on a local or NFS filesystem
+    File { path: String },
+    /// Volume in a block storage pool (Ceph RBD)
+    Volume { pool_id: String, volume_id: String },
+}
+
+/// A read-only template disk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateDisk {
+    /// Disk ID in the template `VmConfig`
+    pub disk_id: String,
+    /// Template image location
+    pub location: DiskLocation,
+}
+
+/// A VM created as a linked clone of a template.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkedClone {
+    /// Clone VM ID
+    pub vm_id: String,
+    /// Clone VM name
+    pub name: String,
+    /// Overlay disks backed by the template
+    pub disks: Vec<DiskLocation>,
+}
+
+/// A VM template.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateInfo {
+    /// Template ID (UUID)
+    pub id: String,
+    /// Display name
+    pub name: String,
+    /// Free-form description
+    #[serde(default)]
+    pub description: String,
+    /// VM the template was created from
+    pub source_vm_id: String,
+    /// Creation time
+    pub created_at: DateTime<Utc>,
+    /// VM configuration used for clones
+    pub config: VmConfig,
+    /// Template disks
+    pub disks: Vec<TemplateDisk>,
+    /// Every disk was generalized (machine-id, SSH host keys, ... reset)
+    #[serde(default)]
+    pub generalized: bool,
+    /// Linked clones depending on this template
+    #[serde(default)]
+    pub linked_clones: Vec<LinkedClone>,
+}
+
+/// Options for cloning a VM.
+#[derive(Debug, Clone, Default)]
+pub struct CloneOptions {
+    /// Name of the new VM
+    pub name: String,
+    /// Full or linked clone
+    pub clone_type: CloneType,
+    /// ID for the new VM (generated if None)
+    pub vm_id: Option<String>,
+}
+
+/// Manages VM templates and clones.
+pub struct TemplateManager {
+    /// Metadata directory
+    dir: PathBuf,
+    /// Storage manager for pool lookups and block volumes
+    storage: Arc<StorageManager>,
+    /// qemu-img binary path
+    qemu_img_path: String,
+    /// Loaded templates
+    templates: RwLock<HashMap<String, TemplateInfo>>,
+}
+
+impl TemplateManager {
+    /// Create a template manager and load existing templates from `dir`.
+    pub fn new(dir: impl Into<PathBuf>, storage: Arc<StorageManager>) -> Self {
+        let dir = dir.into();
+        let templates = load_templates(&dir);
+        
+        Self {
+            dir,
+            storage,
+            qemu_img_path: "qemu-img".to_string(),
+            templates: RwLock::new(templates),
+        }
+    }
+    
+    /// List all templates.
+    pub async fn list_templates(&self) -> Vec<TemplateInfo> {
+        let mut templates: Vec<_> = self.templates.read().await.values().cloned().collect();
+        templates.sort_by(|a, b| a.name.cmp(&b.name));
+        templates
+    }
+    
+    /// Get a template by ID.
+    pub async fn get_template(&self, template_id: &str) -> Result<TemplateInfo> {
+        self.templates.read().await
+            .get(template_id)
+            .cloned()
+            .ok_or_else(|| HypervisorError::InvalidConfig(format!("Template {} not found", template_id)))
+    }
+    
+    /// Create a template from a stopped VM.
+    ///
+    /// Each disk is flattened into a standalone image (so the template does
+    /// not depend on the source VM's backing chain), generalized where
+    /// possible and made read-only. The caller must ensure the source VM is
+    /// stopped.
+    #[instrument(skip(self, source), fields(source_vm_id = %source.id, name = %name))]
+    pub async fn create_template(
+        &self,
+        source: &VmConfig,
+        name: &str,
+        description: &str,
+    ) -> Result<TemplateInfo> {
+        let template_id = Uuid::new_v4().to_string();
+        let mut disks = Vec::new();
+        let mut generalized = !source.guest_os.is_windows();
+        
+        for disk in source.disks.iter().filter(|d| !d.path.is_empty()) {
+            let source_location = self.resolve_location(&disk.path).await;
+            let copied = match &source_location {
+                DiskLocation::File { path } => {
+                    let dest = self.template_file_path(path, &template_id, &disk.id).await;
+                    let mut result = self.convert_image(Path::new(path), &dest).await;
+                    if result.is_ok() && generalized {
+                        generalized = self.generalize(&dest).await;
+                    }
+                    result = result.and_then(|_| set_readonly(&dest, true));
+                    if let Err(e) = result {
+                        self.remove_disks(&disks).await;
+                        return Err(e);
+                    }
+                    DiskLocation::File { path: dest.to_string_lossy().to_string() }
+                }
+                DiskLocation::Volume { pool_id, volume_id } => {
+                    let dest = format!("tmpl-{}-{}", &template_id[..8], disk.id);
+                    if let Err(e) = self.storage.copy_volume(pool_id, volume_id, &dest).await {
+                        self.remove_disks(&disks).await;
+                        return Err(e);
+                    }
+                    generalized = false;
+                    DiskLocation::Volume { pool_id: pool_id.clone(), volume_id: dest }
+                }
+            };
+            
+            disks.push(TemplateDisk {
+                disk_id: disk.id.clone(),
+                location: copied,
+            });
+        }
+        
+        if !generalized {
+            warn!(
+                template_id = %template_id,
+                "Template disks were not generalized; clones share the source's machine-id and SSH host keys \
+                 unless the guest was prepared (sysprep / cloud-init clean) before conversion"
+            );
+        }
+        
+        let template = TemplateInfo {
+            id: template_id.clone(),
+            name: name.to_string(),
+            description: description.to_string(),
+            source_vm_id: source.id.clone(),
+            created_at: Utc::now(),
+            config: source.clone(),
+            disks,
+            generalized: generalized && !source.disks.is_empty(),
+            linked_clones: Vec::new(),
+        };
+        
+        self.save(&template)?;
+        self.templates.write().await.insert(template_id.clone(), template.clone());
+        
+        info!(template_id = %template_id, disks = template.disks.len(), "Template created");
+        Ok(template)
+    }
+    
+    /// Prepare a new VM from a template.
+    ///
+    /// Creates the clone's disks and returns its `VmConfig` (new UUID and
+    /// MAC addresses from `macs`) ready for `Hypervisor::create_vm`. If VM
+    /// creation fails, call `discard_clone` to remove the disks again and
+    /// release the MACs.
+    #[instrument(skip(self, options, macs), fields(template_id = %template_id, name = %options.name))]
+    pub async fn clone_from_template(
+        &self,
+        template_id: &str,
+        options: &CloneOptions,
+        macs: &MacAllocator,
+    ) -> Result<VmConfig> {
+        let template = self.get_template(template_id).await?;
+        let mut config = prepare_clone_config(&template.config, options, macs).await?;
+        
+        let sources: HashMap<&str, &DiskLocation> = template.disks.iter()
+            .map(|d| (d.disk_id.as_str(), &d.location))
+            .collect();
+        
+        let created = match self.create_clone_disks(&mut config, &sources, options.clone_type).await {
+            Ok(created) => created,
+            Err(e) => {
+                let _ = macs.release_vm(&config.id).await;
+                return Err(e);
+            }
+        };
+        
+        if options.clone_type == CloneType::Linked {
+            let mut templates = self.templates.write().await;
+            if let Some(template) = templates.get_mut(template_id) {
+                template.linked_clones.push(LinkedClone {
+                    vm_id: config.id.clone(),
+                    name: config.name.clone(),
+                    disks: created,
+                });
+                self.save(template)?;
+            }
+        }
+        
+        info!(vm_id = %config.id, clone_type = ?options.clone_type, "Clone prepared from template");
+        Ok(config)
+    }
+    
+    /// Prepare a full clone of a stopped VM.
+    ///
+    /// Linked clones are only supported from templates: the source VM's
+    /// disks would otherwise change underneath the clone.
+    #[instrument(skip(self, source, options, macs), fields(source_vm_id = %source.id, name = %options.name))]
+    pub async fn clone_from_vm(
+        &self,
+        source: &VmConfig,
+        options: &CloneOptions,
+        macs: &MacAllocator,
+    ) -> Result<VmConfig> {
+        if options.clone_type == CloneType::Linked {
+            return Err(HypervisorError::InvalidConfig(
+                "Linked clones can only be created from templates".into()
+            ));
+        }
+        
+        let mut config = prepare_clone_config(source, options, macs).await?;
+        
+        let mut locations = Vec::new();
+        for disk in source.disks.iter().filter(|d| !d.path.is_empty()) {
+            locations.push((disk.id.clone(), self.resolve_location(&disk.path).await));
+        }
+        let sources: HashMap<&str, &DiskLocation> = locations.iter()
+            .map(|(id, location)| (id.as_str(), location))
+            .collect();
+        
+        if let Err(e) = self.create_clone_disks(&mut config, &sources, CloneType::Full).await {
+            let _ = macs.release_vm(&config.id).await;
+            return Err(e);
+        }
+        
+        info!(vm_id = %config.id, "Full clone prepared from VM");
+        Ok(config)
+    }
+    
+    /// Remove the disks of a clone whose VM could not be created.
+    pub async fn discard_clone(&self, config: &VmConfig) {
+        let mut locations = Vec::new();
+        for disk in config.disks.iter().filter(|d| !d.path.is_empty()) {
+            locations.push(self.resolve_location(&disk.path).await);
+        }
+        for location in &locations {
+            self.remove_location(location).await;
+        }
+        
+        self.forget_clone(&config.id).await;
+    }
+    
+    /// Drop a VM from the linked clone records (call after the VM is deleted).
+    pub async fn forget_clone(&self, vm_id: &str) {
+        let mut templates = self.templates.write().await;
+        for template in templates.values_mut() {
+            let before = template.linked_clones.len();
+            template.linked_clones.retain(|c| c.vm_id != vm_id);
+            if template.linked_clones.len() != before {
+                if let Err(e) = self.save(template) {
+                    warn!(template_id = %template.id, error = %e, "Failed to update template metadata");
+                }
+            }
+        }
+    }
+    
+    /// Delete a template and its disks.
+    ///
+    /// Fails with `InvalidState` while linked clones depend on it.
+    #[instrument(skip(self), fields(template_id = %template_id))]
+    pub async fn delete_template(&self, template_id: &str) -> Result<()> {
+        let dependents = self.linked_clones(template_id).await?;
+        if !dependents.is_empty() {
+            return Err(HypervisorError::InvalidState(format!(
+                "Template {} has {} linked clone(s): {}",
+                template_id,
+                dependents.len(),
+                dependents.join(", ")
+            )));
+        }
+        
+        let template = self.templates.write().await
+            .remove(template_id)
+            .ok_or_else(|| HypervisorError::InvalidConfig(format!("Template {} not found", template_id)))?;
+        
+        self.remove_disks(&template.disks).await;
+        
+        let metadata = self.dir.join(format!("{}.json", template_id));
+        if let Err(e) = std::fs::remove_file(&metadata) {
+            warn!(path = %metadata.display(), error = %e, "Failed to remove template metadata");
+        }
+        
+        info!("Template deleted");
+        Ok(())
+    }
+    
+    /// List the clones that still depend on a template.
+    ///
+    /// File clones come from the template's records (pruning clones whose
+    /// overlays no longer exist); block volumes are asked for their children.
+    pub async fn linked_clones(&self, template_id: &str) -> Result<Vec<String>> {
+        let template = self.get_template(template_id).await?;
+        let mut dependents = Vec::new();
+        
+        let live: Vec<LinkedClone> = template.linked_clones.iter()
+            .filter(|clone| clone.disks.iter().any(|d| match d {
+                DiskLocation::File { path } => Path::new(path).exists(),
+                // Block clones are checked against the storage below
+                DiskLocation::Volume { .. } => false,
+            }))
+            .cloned()
+            .collect();
+        dependents.extend(live.iter().map(|c| c.vm_id.clone()));
+        
+        for disk in &template.disks {
+            if let DiskLocation::Volume { pool_id, volume_id } = &disk.location {
+                for child in self.storage.list_clones(pool_id, volume_id).await? {
+                    let owner = template.linked_clones.iter()
+                        .find(|c| c.disks.iter().any(|d| matches!(
+                            d, DiskLocation::Volume { volume_id, .. } if *volume_id == child
+                        )))
+                        .map(|c| c.vm_id.clone())
+                        .unwrap_or(child);
+                    if !dependents.contains(&owner) {
+                        dependents.push(owner);
+                    }
+                }
+            }
+        }
+        
+        // Prune records of file clones that are gone
+        let has_file_disks = template.disks.iter()
+            .any(|d| matches!(d.location, DiskLocation::File { .. }));
+        if has_file_disks && live.len() != template.linked_clones.len() {
+            let mut templates = self.templates.write().await;
+            if let Some(template) = templates.get_mut(template_id) {
+                template.linked_clones.retain(|c| dependents.contains(&c.vm_id));
+                self.save(template)?;
+            }
+        }
+        
+        Ok(dependents)
+    }
+    
+    /// Create clone disks for every disk in `config` and rewrite their paths.
+    async fn create_clone_disks(
+        &self,
+        config: &mut VmConfig,
+        sources: &HashMap<&str, &DiskLocation>,
+        clone_type: CloneType,
+    ) -> Result<Vec<DiskLocation>> {
+        let folder = clone_folder_name(&config.name, &config.id);
+        let mut created = Vec::new();
+        
+        for disk in config.disks.iter_mut() {
+            let Some(source) = sources.get(disk.id.as_str()) else {
+                continue;
+            };
+            
+            let result = match source {
+                DiskLocation::File { path } => {
+                    let dest = self.clone_file_path(path, &folder, &disk.id).await;
+                    let created_file = match clone_type {
+                        CloneType::Full => self.convert_image(Path::new(path), &dest).await,
+                        CloneType::Linked => self.create_overlay(Path::new(path), &dest).await,
+                    };
+                    created_file.map(|_| {
+                        disk.backing_file = match clone_type {
+                            CloneType::Full => None,
+                            CloneType::Linked => Some(path.clone()),
+                        };
+                        DiskLocation::File { path: dest.to_string_lossy().to_string() }
+                    })
+                }
+                DiskLocation::Volume { pool_id, volume_id } => {
+                    let dest = format!("vm-{}-{}", &config.id[..config.id.len().min(8)], disk.id);
+                    let copied = match clone_type {
+                        CloneType::Full => self.storage.copy_volume(pool_id, volume_id, &dest).await,
+                        CloneType::Linked => self.storage.clone_volume(pool_id, volume_id, &dest).await,
+                    };
+                    match copied {
+                        Ok(()) => Ok(DiskLocation::Volume { pool_id: pool_id.clone(), volume_id: dest }),
+                        Err(e) => Err(e),
+                    }
+                }
+            };
+            
+            let location = match result {
+                Ok(location) => location,
+                Err(e) => {
+                    for location in &created {
+                        self.remove_location(location).await;
+                    }
+                    return Err(e);
+                }
+            };
+            
+            disk.path = match &location {
+                DiskLocation::File { path } => path.clone(),
+                DiskLocation::Volume { pool_id, volume_id } => {
+                    self.storage.get_attach_info(pool_id, volume_id).await
+                        .map(|info| info.path)
+                        .unwrap_or_else(|_| volume_id.clone())
+                }
+            };
+            created.push(location);
+        }
+        
+        Ok(created)
+    }
+    
+    /// Map a disk path to its storage location.
+    ///
+    /// Paths are RBD image specs (`<rbd_pool>/<image>`) for Ceph pools and
+    /// plain files otherwise.
+    async fn resolve_location(&self, path: &str) -> DiskLocation {
+        if !path.starts_with('/') {
+            for pool in self.storage.list_pools().await {
+                if pool.pool_type != PoolType::CephRbd {
+                    continue;
+                }
+                if let Some(volume_id) = pool.rbd_pool.as_ref()
+                    .and_then(|rbd_pool| path.strip_prefix(&format!("{}/", rbd_pool)))
+                {
+                    return DiskLocation::Volume {
+                        pool_id: pool.pool_id.clone(),
+                        volume_id: volume_id.rsplit('/').next().unwrap_or(volume_id).to_string(),
+                    };
+                }
+            }
+        }
+        
+        DiskLocation::File { path: path.to_string() }
+    }
+    
+    /// Root directory of the pool holding `path`, if any.
+    async fn pool_root(&self, path: &str) -> Option<PathBuf> {
+        self.storage.list_pools().await
+            .into_iter()
+            .filter_map(|pool| pool.mount_path)
+            .filter(|mount| Path::new(path).starts_with(mount))
+            .max_by_key(|mount| mount.len())
+            .map(PathBuf::from)
+    }
+    
+    /// Image path for a template disk.
+    async fn template_file_path(&self, source_path: &str, template_id: &str, disk_id: &str) -> PathBuf {
+        let dir = match self.pool_root(source_path).await {
+            Some(root) => root.join("templates").join(template_id),
+            None => self.dir.join(template_id),
+        };
+        dir.join(format!("{}.qcow2", disk_id))
+    }
+    
+    /// Disk path for a clone, in the `vms/` folder of the source's pool.
+    async fn clone_file_path(&self, source_path: &str, folder: &str, disk_id: &str) -> PathBuf {
+        let root = match self.pool_root(source_path).await {
+            Some(root) => root,
+            None => self.dir.parent().map(Path::to_path_buf).unwrap_or_else(|| self.dir.clone()),
+        };
+        root.join("vms").join(folder).join(format!("{}.qcow2", disk_id))
+    }
+    
+    /// Copy an image into a standalone qcow2 (flattening any backing chain).
+    async fn convert_image(&self, source: &Path, dest: &Path) -> Result<()> {
+        ensure_parent(dest)?;
+        info!(source = %source.display(), dest = %dest.display(), "Copying disk image");
+        self.run_qemu_img(&[
+            "convert", "-O", "qcow2",
+            &source.to_string_lossy(),
+            &dest.to_string_lossy(),
+        ]).await
+    }
+    
+    /// Create a qcow2 overlay backed by `backing`.
+    async fn create_overlay(&self, backing: &Path, dest: &Path) -> Result<()> {
+        ensure_parent(dest)?;
+        info!(backing = %backing.display(), dest = %dest.display(), "Creating linked clone overlay");
+        self.run_qemu_img(&[
+            "create", "-f", "qcow2", "-F", "qcow2",
+            "-b", &backing.to_string_lossy(),
+            &dest.to_string_lossy(),
+        ]).await
+    }
+    
+    /// Reset the guest identity in a template image with `virt-sysprep`.
+    /// Returns false (and logs why) if the image was left as is.
+    async fn generalize(&self, image: &Path) -> bool {
+        info!(image = %image.display(), "Generalizing template disk");
+        let output = Command::new("virt-sysprep")
+            .args(["--quiet", "--format", "qcow2", "--operations", SYSPREP_OPERATIONS, "-a"])
+            .arg(image)
+            .output()
+            .await;
+        
+        match output {
+            Ok(output) if output.status.success() => true,
+            Ok(output) => {
+                warn!(
+                    image = %image.display(),
+                    stderr = %String::from_utf8_lossy(&output.stderr).trim(),
+                    "virt-sysprep failed"
+                );
+                false
+            }
+            Err(e) => {
+                warn!(error = %e, "virt-sysprep not available, template disk not generalized");
+                false
+            }
+        }
+    }
+    
+    async fn run_qemu_img(&self, args: &[&str]) -> Result<()> {
+        let output = Command::new(&self.qemu_img_path)
+            .args(args)
+            .output()
+            .await
+            .map_err(|e| HypervisorError::Internal(format!("qemu-img failed: {}", e)))?;
+        
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(HypervisorError::Internal(format!("qemu-img {} failed: {}", args[0], stderr.trim())));
+        }
+        
+        Ok(())
+    }
+    
+    async fn remove_disks(&self, disks: &[TemplateDisk]) {
+        for disk in disks {
+            self.remove_location(&disk.location).await;
+        }
+    }
+    
+    async fn remove_location(&self, location: &DiskLocation) {
+        match location {
+            DiskLocation::File { path } => {
+                let path = Path::new(path);
+                let _ = set_readonly(path, false);
+                if let Err(e) = std::fs::remove_file(path) {
+                    warn!(path = %path.display(), error = %e, "Failed to remove disk image");
+                }
+                // Drop the per-template / per-VM folder once empty
+                if let Some(parent) = path.parent() {
+                    let _ = std::fs::remove_dir(parent);
+                }
+            }
+            DiskLocation::Volume { pool_id, volume_id } => {
+                if let Err(e) = self.storage.delete_volume(pool_id, volume_id).await {
+                    warn!(pool_id = %pool_id, volume_id = %volume_id, error = %e, "Failed to remove volume");
+                }
+            }
+        }
+    }
+    
+    fn save(&self, template: &TemplateInfo) -> Result<()> {
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| HypervisorError::Internal(format!("Failed to create {}: {}", self.dir.display(), e)))?;
+        
+        let json = serde_json::to_string_pretty(template)
+            .map_err(|e| HypervisorError::Internal(format!("Failed to serialize template: {}", e)))?;
+        
+        let path = self.dir.join(format!("{}.json", template.id));
+        std::fs::write(&path, json)
+            .map_err(|e| HypervisorError::Internal(format!("Failed to write {}: {}", path.display(), e)))
+    }
+}
+
+/// Load template metadata files from a directory.
+fn load_templates(dir: &Path) -> HashMap<String, TemplateInfo> {
+    let mut templates = HashMap::new();
+    
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return templates;
+    };
+    
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().map(|e| e == "json").unwrap_or(false) {
+            match std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|json| serde_json::from_str::<TemplateInfo>(&json).map_err(|e| e.to_string()))
+            {
+                Ok(template) => {
+                    templates.insert(template.id.clone(), template);
+                }
+                Err(e) => warn!(path = %path.display(), error = %e, "Skipping invalid template metadata"),
+            }
+        }
+    }
+    
+    templates
+}
+
+/// Build the configuration of a clone: new identity, same hardware.
+///
+/// Every NIC gets a fresh MAC registered in `macs`, never the source's.
+pub async fn prepare_clone_config(
+    source: &VmConfig,
+    options: &CloneOptions,
+    macs: &MacAllocator,
+) -> Result<VmConfig> {
+    let mut config = source.clone();
+    
+    config.id = options.vm_id.clone().unwrap_or_else(|| Uuid::new_v4().to_string());
+    config.name = options.name.clone();
+    
+    for nic in config.nics.iter_mut() {
+        nic.id = Uuid::new_v4().to_string();
+        // OVN ports are per-VM; the caller binds new ones
+        nic.ovn_port_name = None;
+    }
+    
+    macs.regenerate(&mut config).await
+        .map_err(|e| HypervisorError::Internal(format!("Failed to allocate MAC address: {:#}", e)))?;
+    
+    Ok(config)
+}
+
+/// Folder name for a clone's disks: `{name}_{uuid_short}` like VM creation.
+pub(crate) fn clone_folder_name(name: &str, vm_id: &str) -> String {
+    let safe_name: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    format!("{}_{}", safe_name, &vm_id[..vm_id.len().min(8)])
+}
+
+fn ensure_parent(path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| HypervisorError::Internal(format!("Failed to create {}: {}", parent.display(), e)))?;
+    }
+    Ok(())
+}
+
+/// Make an image file read-only (or writable again for removal).
+fn set_readonly(path: &Path, readonly: bool) -> Result<()> {
+    let mut permissions = std::fs::metadata(path)
+        .map_err(|e| HypervisorError::Internal(format!("Failed to stat {}: {}", path.display(), e)))?
+        .permissions();
+    permissions.set_readonly(readonly);
+    std::fs::set_permissions(path, permissions)
+        .map_err(|e| HypervisorError::Internal(format!("Failed to set permissions on {}: {}", path.display(), e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::MacPoolConfig;
+    use crate::types::NicConfig;
+    
+    #[tokio::test]
+    async fn test_prepare_clone_config() {
+        let mut source = VmConfig::new("golden").with_id("11111111-2222-3333-4444-555555555555");
+        source.nics.push(NicConfig {
+            mac_address: Some("52:54:00:aa:bb:cc".to_string()),
+            ..Default::default()
+        });
+        
+        let macs = MacAllocator::in_memory(MacPoolConfig::default());
+        let clone = prepare_clone_config(&source, &CloneOptions {
+            name: "web-01".to_string(),
+            ..Default::default()
+        }, &macs).await.unwrap();
+        
+        assert_eq!(clone.name, "web-01");
+        assert_ne!(clone.id, source.id);
+        assert_ne!(clone.nics[0].id, source.nics[0].id);
+        let mac = clone.nics[0].mac_address.as_deref().unwrap();
+        assert!(mac.starts_with("52:54:00:"));
+        assert_ne!(mac, "52:54:00:aa:bb:cc");
+        assert_eq!(macs.allocations().await[0].vm_id, clone.id);
+    }
+    
+    #[test]
+    fn test_clone_folder_name() {
+        assert_eq!(clone_folder_name("web 01", "abcdef12-3456"), "web_01_abcdef12");
+    }
+    
+    #[tokio::test]
+    async fn test_delete_blocked_by_linked_clone() {
+        let dir = tempfile::tempdir().unwrap();
+        let overlay = dir.path().join("overlay.qcow2");
+        std::fs::write(&overlay, b"").unwrap();
+        
+        let manager = TemplateManager::new(dir.path().join("meta"), Arc::new(StorageManager::new()));
+        let template = TemplateInfo {
+            id: "tmpl-1".to_string(),
+            name: "golden".to_string(),
+            description: String::new(),
+            source_vm_id: "vm-1".to_string(),
+            created_at: Utc::now(),
+            config: VmConfig::new("golden"),
+            disks: Vec::new(),
+            generalized: false,
+            linked_clones: vec![LinkedClone {
+                vm_id: "vm-2".to_string(),
+                name: "web-01".to_string(),
+                disks: vec![DiskLocation::File { path: overlay.to_string_lossy().to_string() }],
+            }],
+        };
+        manager.templates.write().await.insert(template.id.clone(), template);
+        
+        let err = manager.delete_template("tmpl-1").await.unwrap_err();
+        assert!(matches!(err, HypervisorError::InvalidState(_)));
+        
+        // Once the clone's overlay is gone the template can be deleted
+        std::fs::remove_file(&overlay).unwrap();
+        manager.delete_template("tmpl-1").await.unwrap();
+        assert!(manager.list_templates().await.is_empty());
+    }
+}
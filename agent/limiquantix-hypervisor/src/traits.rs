@@ -168,6 +168,13 @@ pub trait Hypervisor: Send + Sync {
     /// If `iso_path` is None, ejects the current media.
     async fn change_media(&self, vm_id: &str, device: &str, iso_path: Option<&str>) -> Result<()>;
     
+    /// Grow a disk of a running VM to `new_size_bytes`.
+    ///
+    /// `disk` is the image path or target device. The hypervisor resizes the
+    /// image and notifies the guest of the new capacity; partitions and
+    /// filesystems are left to the guest.
+    async fn resize_disk(&self, vm_id: &str, disk: &str, new_size_bytes: u64) -> Result<()>;
+    
     // =========================================================================
     // Migration
     // =========================================================================
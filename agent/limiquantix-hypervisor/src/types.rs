@@ -34,6 +34,18 @@ pub struct VmConfig {
     /// Similar to VMware's Guest OS selection.
     #[serde(default)]
     pub guest_os: GuestOSFamily,
+    /// Watchdog device (None = use the Guest OS profile default)
+    #[serde(default)]
+    pub watchdog: Option<WatchdogConfig>,
+    /// Virtio RNG device (None = use the Guest OS profile default)
+    #[serde(default)]
+    pub rng: Option<RngConfig>,
+    /// Guest panic notifier (None = use the Guest OS profile default)
+    #[serde(default)]
+    pub panic: Option<PanicConfig>,
+    /// Additional serial ports (port 0 is always the PTY console)
+    #[serde(default)]
+    pub serial_ports: Vec<SerialPortConfig>,
 }
 
 impl VmConfig {
@@ -50,6 +62,10 @@ impl VmConfig {
             boot: BootConfig::default(),
             console: ConsoleConfig::default(),
             guest_os: GuestOSFamily::default(),
+            watchdog: None,
+            rng: None,
+            panic: None,
+            serial_ports: Vec::new(),
         }
     }
     
@@ -94,6 +110,30 @@ impl VmConfig {
         self.cdroms.push(cdrom);
         self
     }
+    
+    /// Set the watchdog device, overriding the Guest OS profile default.
+    pub fn with_watchdog(mut self, watchdog: WatchdogConfig) -> Self {
+        self.watchdog = Some(watchdog);
+        self
+    }
+    
+    /// Set the RNG device, overriding the Guest OS profile default.
+    pub fn with_rng(mut self, rng: RngConfig) -> Self {
+        self.rng = Some(rng);
+        self
+    }
+    
+    /// Set the guest panic device, overriding the Guest OS profile default.
+    pub fn with_panic(mut self, panic: PanicConfig) -> Self {
+        self.panic = Some(panic);
+        self
+    }
+    
+    /// Add an additional serial port.
+    pub fn with_serial_port(mut self, serial: SerialPortConfig) -> Self {
+        self.serial_ports.push(serial);
+        self
+    }
 }
 
 /// CPU configuration.
@@ -169,6 +209,12 @@ pub struct DiskConfig {
     pub cache: DiskCache,
     /// IO mode
     pub io_mode: DiskIoMode,
+    /// Whether guest discard (TRIM/UNMAP) requests reach the image
+    #[serde(default)]
+    pub discard: DiskDiscard,
+    /// Whether QEMU detects zero writes and turns them into zero/unmap requests
+    #[serde(default)]
+    pub detect_zeroes: DiskDetectZeroes,
     /// Backing file path (for copy-on-write cloud images)
     pub backing_file: Option<String>,
 }
@@ -185,6 +231,8 @@ impl Default for DiskConfig {
             bootable: true,
             cache: DiskCache::None,
             io_mode: DiskIoMode::Native,
+            discard: DiskDiscard::Ignore,
+            detect_zeroes: DiskDetectZeroes::Off,
             backing_file: None,
         }
     }
@@ -292,6 +340,49 @@ impl DiskIoMode {
     }
 }
 
+/// Disk discard mode.
+///
+/// With `Unmap`, TRIM/UNMAP requests issued by the guest punch holes in the
+/// image, so thin-provisioned volumes shrink when the guest deletes data.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiskDiscard {
+    #[default]
+    Ignore,
+    Unmap,
+}
+
+impl DiskDiscard {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiskDiscard::Ignore => "ignore",
+            DiskDiscard::Unmap => "unmap",
+        }
+    }
+}
+
+/// Zero-write detection mode.
+///
+/// `Unmap` only takes effect together with [`DiskDiscard::Unmap`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiskDetectZeroes {
+    #[default]
+    Off,
+    On,
+    Unmap,
+}
+
+impl DiskDetectZeroes {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DiskDetectZeroes::Off => "off",
+            DiskDetectZeroes::On => "on",
+            DiskDetectZeroes::Unmap => "unmap",
+        }
+    }
+}
+
 /// CD-ROM configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CdromConfig {
@@ -448,6 +539,246 @@ impl Default for ConsoleConfig {
     }
 }
 
+// =============================================================================
+// PLATFORM DEVICES
+// =============================================================================
+
+/// Watchdog device configuration.
+///
+/// The guest must periodically ping the watchdog; if it stops (hung kernel,
+/// deadlocked userspace), QEMU performs `action`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchdogConfig {
+    /// Set to false to remove a watchdog the Guest OS profile would add
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Emulated watchdog model
+    #[serde(default)]
+    pub model: WatchdogModel,
+    /// Action when the watchdog fires
+    #[serde(default)]
+    pub action: WatchdogAction,
+}
+
+impl WatchdogConfig {
+    /// Create an enabled watchdog with the given model and action.
+    pub fn new(model: WatchdogModel, action: WatchdogAction) -> Self {
+        Self { enabled: true, model, action }
+    }
+    
+    /// A watchdog override that removes the device.
+    pub fn disabled() -> Self {
+        Self { enabled: false, model: WatchdogModel::default(), action: WatchdogAction::default() }
+    }
+}
+
+/// Watchdog device model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum WatchdogModel {
+    /// Intel 6300ESB PCI watchdog (Linux i6300esb, FreeBSD ichwd)
+    #[default]
+    I6300esb,
+    /// Intel TCO watchdog built into the q35 ICH9 chipset
+    Itco,
+    /// ISA iBase IB700 (legacy)
+    Ib700,
+}
+
+impl WatchdogModel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WatchdogModel::I6300esb => "i6300esb",
+            WatchdogModel::Itco => "itco",
+            WatchdogModel::Ib700 => "ib700",
+        }
+    }
+}
+
+/// Action taken when the watchdog fires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum WatchdogAction {
+    /// Hard reset the guest
+    #[default]
+    Reset,
+    /// Power off the guest immediately
+    Poweroff,
+    /// Send an ACPI shutdown request
+    Shutdown,
+    /// Pause the guest for inspection
+    Pause,
+    /// Write a guest memory dump, then keep running
+    Dump,
+    /// Inject an NMI (guest kernel decides what to do)
+    InjectNmi,
+    /// Only report the event
+    None,
+}
+
+impl WatchdogAction {
+    /// Get the libvirt action name.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WatchdogAction::Reset => "reset",
+            WatchdogAction::Poweroff => "poweroff",
+            WatchdogAction::Shutdown => "shutdown",
+            WatchdogAction::Pause => "pause",
+            WatchdogAction::Dump => "dump",
+            WatchdogAction::InjectNmi => "inject-nmi",
+            WatchdogAction::None => "none",
+        }
+    }
+}
+
+/// Virtio RNG device configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RngConfig {
+    /// Set to false to remove an RNG the Guest OS profile would add
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Host entropy source
+    #[serde(default = "default_rng_source")]
+    pub source: String,
+    /// Rate limit: maximum bytes per period (None = unlimited)
+    #[serde(default)]
+    pub rate_bytes: Option<u32>,
+    /// Rate limit period in milliseconds
+    #[serde(default)]
+    pub rate_period_ms: Option<u32>,
+}
+
+impl Default for RngConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            source: default_rng_source(),
+            rate_bytes: None,
+            rate_period_ms: None,
+        }
+    }
+}
+
+impl RngConfig {
+    /// An RNG override that removes the device.
+    pub fn disabled() -> Self {
+        Self { enabled: false, ..Default::default() }
+    }
+}
+
+/// Guest panic notifier configuration.
+///
+/// The guest kernel signals a panic to the host through this device, so the
+/// host can react immediately instead of waiting for a watchdog.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PanicConfig {
+    /// Set to false to remove a panic device the Guest OS profile would add
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Panic device model
+    #[serde(default)]
+    pub model: PanicModel,
+    /// Action when the guest reports a panic
+    #[serde(default)]
+    pub action: CrashAction,
+}
+
+impl PanicConfig {
+    /// Create an enabled panic device with the given model and action.
+    pub fn new(model: PanicModel, action: CrashAction) -> Self {
+        Self { enabled: true, model, action }
+    }
+    
+    /// A panic device override that removes the device.
+    pub fn disabled() -> Self {
+        Self { enabled: false, model: PanicModel::default(), action: CrashAction::default() }
+    }
+}
+
+/// Guest panic device model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PanicModel {
+    /// pvpanic ISA device (Linux pvpanic driver)
+    #[default]
+    Isa,
+    /// pvpanic PCI device (newer QEMU/libvirt)
+    Pvpanic,
+    /// Hyper-V crash MSRs (Windows)
+    Hyperv,
+}
+
+impl PanicModel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PanicModel::Isa => "isa",
+            PanicModel::Pvpanic => "pvpanic",
+            PanicModel::Hyperv => "hyperv",
+        }
+    }
+}
+
+/// Action taken when the guest crashes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CrashAction {
+    /// Restart the guest
+    #[default]
+    Reset,
+    /// Power off the guest
+    Poweroff,
+    /// Write a core dump, then power off
+    Dump,
+    /// Write a core dump, then restart
+    DumpAndReset,
+    /// Leave the guest in the crashed state for inspection
+    Preserve,
+}
+
+impl CrashAction {
+    /// Get the libvirt `<on_crash>` value.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CrashAction::Reset => "restart",
+            CrashAction::Poweroff => "destroy",
+            CrashAction::Dump => "coredump-destroy",
+            CrashAction::DumpAndReset => "coredump-restart",
+            CrashAction::Preserve => "preserve",
+        }
+    }
+}
+
+/// Additional serial port configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SerialPortConfig {
+    /// Guest serial port index (ttyS1 = 1); port 0 is reserved for the console
+    pub port: u32,
+    /// Host-side backend
+    pub source: SerialSource,
+}
+
+/// Host-side backend for a serial port.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SerialSource {
+    /// Host pseudo-terminal
+    Pty,
+    /// Listening TCP socket (raw protocol)
+    Tcp { host: String, port: u16 },
+    /// Listening UNIX socket
+    Unix { path: String },
+    /// Append guest output to a file
+    File { path: String },
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_rng_source() -> String {
+    "/dev/urandom".to_string()
+}
+
 // =============================================================================
 // VM STATUS
 // =============================================================================
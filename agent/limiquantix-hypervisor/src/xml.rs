@@ -60,11 +60,14 @@ impl<'a> DomainXmlBuilder<'a> {
         // Clock (OS-specific timer configuration)
         xml.push_str(&self.build_clock_section());
         
-        // Power management
-        xml.push_str(r#"  <on_poweroff>destroy</on_poweroff>
+        // Power management (crash action follows the panic device)
+        let on_crash = self.effective_panic()
+            .map(|p| p.action.as_str())
+            .unwrap_or("destroy");
+        xml.push_str(&format!(r#"  <on_poweroff>destroy</on_poweroff>
   <on_reboot>restart</on_reboot>
-  <on_crash>destroy</on_crash>
-"#);
+  <on_crash>{}</on_crash>
+"#, on_crash));
         
         // Devices
         xml.push_str("  <devices>\n");
@@ -76,6 +79,9 @@ impl<'a> DomainXmlBuilder<'a> {
         xml.push_str(&self.build_console());
         xml.push_str(&self.build_graphics());
         xml.push_str(&self.build_channels());
+        xml.push_str(&self.build_watchdog());
+        xml.push_str(&self.build_rng());
+        xml.push_str(&self.build_panic());
         xml.push_str("  </devices>\n");
         
         xml.push_str("</domain>\n");
@@ -277,9 +283,18 @@ impl<'a> DomainXmlBuilder<'a> {
         for (i, disk) in self.config.disks.iter().enumerate() {
             let dev = format!("{}{}", disk.bus.device_prefix(), (b'a' + i as u8) as char);
             
+            // Only emit discard attributes when enabled so libvirt keeps its defaults
+            let mut discard = String::new();
+            if disk.discard != DiskDiscard::Ignore {
+                discard.push_str(&format!(" discard='{}'", disk.discard.as_str()));
+            }
+            if disk.detect_zeroes != DiskDetectZeroes::Off {
+                discard.push_str(&format!(" detect_zeroes='{}'", disk.detect_zeroes.as_str()));
+            }
+            
             xml.push_str(&format!(
                 r#"    <disk type='file' device='disk'>
-      <driver name='qemu' type='{}' cache='{}' io='{}'/>
+      <driver name='qemu' type='{}' cache='{}' io='{}'{}/>
       <source file='{}'/>
       <target dev='{}' bus='{}'/>
 {}    </disk>
@@ -287,6 +302,7 @@ impl<'a> DomainXmlBuilder<'a> {
                 disk.format.as_str(),
                 disk.cache.as_str(),
                 disk.io_mode.as_str(),
+                discard,
                 disk.path,
                 dev,
                 disk.bus.as_str(),
@@ -381,13 +397,105 @@ impl<'a> DomainXmlBuilder<'a> {
     }
     
     fn build_console(&self) -> String {
-        r#"    <serial type='pty'>
+        let mut xml = String::from(r#"    <serial type='pty'>
       <target port='0'/>
     </serial>
     <console type='pty'>
       <target type='serial' port='0'/>
     </console>
-"#.to_string()
+"#);
+        
+        // Additional serial ports (port 0 is the console above)
+        for serial in self.config.serial_ports.iter().filter(|s| s.port > 0) {
+            let (serial_type, source) = match &serial.source {
+                SerialSource::Pty => ("pty", String::new()),
+                SerialSource::Tcp { host, port } => (
+                    "tcp",
+                    format!(
+                        "      <source mode='bind' host='{}' service='{}'/>\n      <protocol type='raw'/>\n",
+                        host, port
+                    ),
+                ),
+                SerialSource::Unix { path } => (
+                    "unix",
+                    format!("      <source mode='bind' path='{}'/>\n", path),
+                ),
+                SerialSource::File { path } => (
+                    "file",
+                    format!("      <source path='{}' append='on'/>\n", path),
+                ),
+            };
+            
+            xml.push_str(&format!(
+                "    <serial type='{}'>\n{}      <target port='{}'/>\n    </serial>\n",
+                serial_type,
+                source,
+                serial.port
+            ));
+        }
+        
+        xml
+    }
+    
+    /// Watchdog from the VM config, falling back to the Guest OS profile.
+    fn effective_watchdog(&self) -> Option<&WatchdogConfig> {
+        self.config.watchdog.as_ref()
+            .or(self.profile.devices.watchdog.as_ref())
+            .filter(|w| w.enabled)
+    }
+    
+    /// RNG from the VM config, falling back to the Guest OS profile.
+    fn effective_rng(&self) -> Option<&RngConfig> {
+        self.config.rng.as_ref()
+            .or(self.profile.devices.rng.as_ref())
+            .filter(|r| r.enabled)
+    }
+    
+    /// Panic device from the VM config, falling back to the Guest OS profile.
+    fn effective_panic(&self) -> Option<&PanicConfig> {
+        self.config.panic.as_ref()
+            .or(self.profile.devices.panic.as_ref())
+            .filter(|p| p.enabled)
+    }
+    
+    fn build_watchdog(&self) -> String {
+        match self.effective_watchdog() {
+            Some(watchdog) => format!(
+                "    <watchdog model='{}' action='{}'/>\n",
+                watchdog.model.as_str(),
+                watchdog.action.as_str()
+            ),
+            None => String::new(),
+        }
+    }
+    
+    fn build_rng(&self) -> String {
+        let rng = match self.effective_rng() {
+            Some(rng) => rng,
+            None => return String::new(),
+        };
+        
+        let rate = match rng.rate_bytes {
+            Some(bytes) => format!(
+                "      <rate bytes='{}' period='{}'/>\n",
+                bytes,
+                rng.rate_period_ms.unwrap_or(1000)
+            ),
+            None => String::new(),
+        };
+        
+        format!(
+            "    <rng model='virtio'>\n{}      <backend model='random'>{}</backend>\n    </rng>\n",
+            rate,
+            rng.source
+        )
+    }
+    
+    fn build_panic(&self) -> String {
+        match self.effective_panic() {
+            Some(panic) => format!("    <panic model='{}'/>\n", panic.model.as_str()),
+            None => String::new(),
+        }
     }
     
     fn build_graphics(&self) -> String {
@@ -484,6 +592,23 @@ mod tests {
         assert!(xml.contains("/var/lib/vms/test.qcow2"));
     }
     
+    #[test]
+    fn test_disk_discard() {
+        let config = VmConfig::new("trim-vm")
+            .with_disk(DiskConfig {
+                path: "/var/lib/vms/thin.qcow2".to_string(),
+                discard: DiskDiscard::Unmap,
+                detect_zeroes: DiskDetectZeroes::Unmap,
+                ..Default::default()
+            })
+            .with_disk(DiskConfig::new("/var/lib/vms/data.qcow2"));
+        
+        let xml = DomainXmlBuilder::new(&config).build();
+        
+        assert!(xml.contains("io='native' discard='unmap' detect_zeroes='unmap'/>"));
+        assert_eq!(xml.matches("discard=").count(), 1);
+    }
+    
     #[test]
     fn test_uefi_firmware() {
         let mut config = VmConfig::new("uefi-vm");
@@ -583,5 +708,46 @@ mod tests {
         assert!(!xml.contains("virtualport"));
         assert!(xml.contains("address='52:54:00:12:34:56'"));
     }
+    
+    #[test]
+    fn test_profile_platform_devices() {
+        let config = VmConfig::new("linux-vm");
+        let xml = DomainXmlBuilder::new(&config).build();
+        
+        // Generic Linux gets watchdog, RNG and pvpanic by default
+        assert!(xml.contains("<watchdog model='i6300esb' action='reset'/>"));
+        assert!(xml.contains("<backend model='random'>/dev/urandom</backend>"));
+        assert!(xml.contains("<panic model='isa'/>"));
+        assert!(xml.contains("<on_crash>restart</on_crash>"));
+        
+        let config = VmConfig::new("legacy-vm")
+            .with_guest_os(crate::guest_os::GuestOSFamily::WindowsLegacy);
+        let xml = DomainXmlBuilder::new(&config).build();
+        
+        assert!(!xml.contains("<watchdog"));
+        assert!(!xml.contains("<rng"));
+        assert!(!xml.contains("<panic"));
+        assert!(xml.contains("<on_crash>destroy</on_crash>"));
+    }
+    
+    #[test]
+    fn test_platform_device_overrides() {
+        let config = VmConfig::new("override-vm")
+            .with_watchdog(WatchdogConfig::new(WatchdogModel::Itco, WatchdogAction::Dump))
+            .with_rng(RngConfig::disabled())
+            .with_panic(PanicConfig::new(PanicModel::Isa, CrashAction::Poweroff))
+            .with_serial_port(SerialPortConfig {
+                port: 1,
+                source: SerialSource::Tcp { host: "127.0.0.1".to_string(), port: 4555 },
+            });
+        
+        let xml = DomainXmlBuilder::new(&config).build();
+        
+        assert!(xml.contains("<watchdog model='itco' action='dump'/>"));
+        assert!(!xml.contains("<rng"));
+        assert!(xml.contains("<on_crash>destroy</on_crash>"));
+        assert!(xml.contains("<source mode='bind' host='127.0.0.1' service='4555'/>"));
+        assert!(xml.contains("<target port='1'/>"));
+    }
 }
 
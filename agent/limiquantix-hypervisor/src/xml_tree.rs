@@ -0,0 +1,106 @@
+//! Minimal XML element tree for reading documents we did not generate
+//! (OVF descriptors, libvirt domain XML).
+
+use std::collections::HashMap;
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use crate::error::{HypervisorError, Result};
+
+/// Minimal XML element tree (namespace prefixes stripped).
+#[derive(Debug, Default)]
+pub(crate) struct Element {
+    pub name: String,
+    pub attrs: HashMap<String, String>,
+    pub children: Vec<Element>,
+    pub text: String,
+}
+
+impl Element {
+    fn from_start(e: &BytesStart) -> Result<Self> {
+        let mut element = Element {
+            name: String::from_utf8_lossy(e.local_name().as_ref()).into_owned(),
+            ..Default::default()
+        };
+        
+        for attr in e.attributes() {
+            let attr = attr.map_err(|e| HypervisorError::XmlError(format!("Invalid XML attribute: {}", e)))?;
+            let key = String::from_utf8_lossy(attr.key.local_name().as_ref()).into_owned();
+            let value = attr.unescape_value()
+                .map_err(|e| HypervisorError::XmlError(format!("Invalid XML attribute value: {}", e)))?;
+            element.attrs.insert(key, value.into_owned());
+        }
+        
+        Ok(element)
+    }
+    
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs.get(name).map(String::as_str)
+    }
+    
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|c| c.name == name)
+    }
+    
+    pub fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+    
+    pub fn child_text(&self, name: &str) -> Option<&str> {
+        self.child(name).map(|c| c.text.trim()).filter(|t| !t.is_empty())
+    }
+    
+    /// Depth-first search for the first element with this name.
+    pub fn find(&self, name: &str) -> Option<&Element> {
+        if self.name == name {
+            return Some(self);
+        }
+        self.children.iter().find_map(|c| c.find(name))
+    }
+}
+
+/// Parse a document into its root element.
+pub(crate) fn parse_tree(xml: &str) -> Result<Element> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+    
+    let mut stack: Vec<Element> = Vec::new();
+    
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(e)) => stack.push(Element::from_start(&e)?),
+            Ok(Event::Empty(e)) => {
+                let element = Element::from_start(&e)?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            }
+            Ok(Event::Text(t)) => {
+                if let Some(current) = stack.last_mut() {
+                    let text = t.unescape()
+                        .map_err(|e| HypervisorError::XmlError(format!("Invalid XML text: {}", e)))?;
+                    current.text.push_str(&text);
+                }
+            }
+            Ok(Event::End(_)) => {
+                let element = stack.pop()
+                    .ok_or_else(|| HypervisorError::XmlError("Unbalanced XML document".into()))?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            }
+            Ok(Event::Eof) => {
+                return Err(HypervisorError::XmlError("Unexpected end of XML document".into()));
+            }
+            Ok(_) => {}
+            Err(e) => {
+                return Err(HypervisorError::XmlError(format!(
+                    "Invalid XML at position {}: {}", reader.buffer_position(), e
+                )));
+            }
+        }
+    }
+}
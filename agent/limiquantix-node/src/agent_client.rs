@@ -3,6 +3,10 @@
 //! This module provides a client for communicating with the LimiQuantix Guest Agent
 //! running inside VMs via virtio-serial (Unix sockets on the host).
 //!
+//! Besides request/response, the channel carries interactive sessions and
+//! multiplexed byte streams (`AgentStream`), which back the TCP port forwards
+//! managed by `PortForwardRegistry`.
+//!
 //! ## Platform Support
 //!
 //! - **Unix**: Full implementation using Unix sockets for virtio-serial communication.
@@ -26,7 +30,21 @@
 //! - Example: `\\.\pipe\org.quantix.agent.{vm_id}`
 
 use anyhow::{anyhow, Result};
-use limiquantix_proto::agent::{AgentReadyEvent, ExecuteRequest, ExecuteResponse, TelemetryReport};
+use limiquantix_proto::agent::{
+    AgentReadyEvent, ClipboardGetRequest, ClipboardGetResponse, ClipboardUpdateRequest,
+    ConfigureNetworkRequest, CreateDirectoryRequest, DisplayResizeRequest, DisplayResizeResponse,
+    ExecuteRequest, ExecuteResponse, FileDeleteRequest, FileStatResponse, GetCapabilitiesResponse,
+    GetHardwareInfoRequest, GetHardwareInfoResponse, KillProcessRequest, ListInstalledSoftwareRequest,
+    ListInstalledSoftwareResponse, ListProcessesRequest, ListProcessesResponse, ListServicesRequest,
+    ListServicesResponse, OpenSessionRequest, ResetPasswordRequest, ServiceControlRequest,
+    ServiceControlResponse, SessionExited, SessionOutput, TelemetryReport, AgentUpdateResponse,
+    TreeDownloadRequest, TreeDownloadResponse, TreeUploadRequest, TreeUploadResponse,
+    GrowFilesystemsRequest, GrowFilesystemsResponse, TrimFilesystemsRequest, TrimFilesystemsResponse,
+    ListUsersRequest, ListUsersResponse, UserAccountRequest, UserAccountResponse,
+    ListLoginSessionsRequest, ListLoginSessionsResponse, ListUpdatesRequest, ListUpdatesResponse,
+    ApplyUpdatesRequest, ApplyUpdatesResponse,
+};
+use serde::Serialize;
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::time::Duration;
@@ -42,6 +60,106 @@ const SOCKET_BASE_PATH: &str = "/var/run/quantix-kvm/vms";
 /// Default timeout for agent operations
 const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
 
+/// Output or exit of an interactive session.
+#[derive(Debug, Clone)]
+pub enum SessionEvent {
+    Output(SessionOutput),
+    Exited(SessionExited),
+}
+
+/// The guest agent lacks a capability (older agent or unsupported platform).
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Guest agent {agent_version} does not support '{capability}'")]
+pub struct UnsupportedCapability {
+    pub capability: String,
+    pub agent_version: String,
+}
+
+/// Data or end of a multiplexed stream.
+#[derive(Debug, Clone)]
+pub enum StreamEvent {
+    Data(Vec<u8>),
+    /// The guest will send no more data
+    Eof,
+    /// The guest reset the stream (or the agent connection was lost)
+    Closed(String),
+}
+
+/// A node-local listener forwarding connections to a port inside a guest.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PortForwardInfo {
+    pub id: String,
+    pub vm_id: String,
+    /// Bound address ("127.0.0.1:40123") or Unix socket path
+    pub listen: String,
+    pub target_host: String,
+    pub target_port: u16,
+    pub active_connections: u32,
+    pub total_connections: u64,
+    pub bytes_to_guest: u64,
+    pub bytes_from_guest: u64,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Requested port forward.
+#[derive(Debug, Clone)]
+pub struct PortForwardSpec {
+    /// Loopback "address:port" to listen on (port 0 picks a free port) or a
+    /// Unix socket path in `PORT_FORWARD_SOCKET_DIR`
+    pub listen: String,
+    /// Host to connect to from inside the guest (loopback only)
+    pub target_host: String,
+    pub target_port: u16,
+}
+
+/// Directory for Unix socket port forward listeners
+pub const PORT_FORWARD_SOCKET_DIR: &str = "/run/limiquantix/port-forwards";
+
+/// Where a port forward listens.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ForwardListen {
+    Tcp(std::net::SocketAddr),
+    Unix(PathBuf),
+}
+
+impl PortForwardSpec {
+    /// Check the spec: listeners are node-local (loopback TCP or a socket in
+    /// `socket_dir`) and the target is the guest itself.
+    fn validate(&self, socket_dir: &std::path::Path) -> Result<ForwardListen> {
+        if self.target_port == 0 {
+            return Err(anyhow!("Target port is required"));
+        }
+        let local_target = self.target_host == "localhost"
+            || self.target_host.parse::<std::net::IpAddr>().is_ok_and(|ip| ip.is_loopback());
+        if !local_target {
+            return Err(anyhow!("Target host must be a loopback address in the guest, got {}", self.target_host));
+        }
+
+        if self.listen.starts_with('/') {
+            let path = PathBuf::from(&self.listen);
+            let in_dir = path.parent() == Some(socket_dir)
+                && path.file_name().is_some_and(|n| n != ".." && n != ".");
+            if !in_dir {
+                return Err(anyhow!("Socket path must be a file in {}", socket_dir.display()));
+            }
+            return Ok(ForwardListen::Unix(path));
+        }
+
+        let addr: std::net::SocketAddr = self.listen.parse()
+            .map_err(|_| anyhow!("Invalid listen address: {}", self.listen))?;
+        if !addr.ip().is_loopback() {
+            return Err(anyhow!("Listen address must be a loopback address, got {}", addr.ip()));
+        }
+        Ok(ForwardListen::Tcp(addr))
+    }
+}
+
+/// Resolves a stream opener for each forwarded connection, so port forwards
+/// keep working across agent reconnects.
+pub type StreamOpenerSource =
+    std::sync::Arc<dyn Fn() -> futures::future::BoxFuture<'static, Result<AgentStreamOpener>> + Send + Sync>;
+
 // ============================================================================
 // UNIX IMPLEMENTATION
 // ============================================================================
@@ -54,22 +172,58 @@ mod unix_impl {
         agent_message, AgentMessage, FileReadRequest, FileWriteRequest, PingRequest, PongResponse,
         QuiesceFilesystemsRequest, QuiesceFilesystemsResponse, ShutdownRequest, ShutdownResponse,
         SyncTimeRequest, SyncTimeResponse, ThawFilesystemsRequest, ThawFilesystemsResponse,
-        ListDirectoryRequest, ListDirectoryResponse,
+        ListDirectoryRequest, ListDirectoryResponse, CloseSessionRequest, SessionInput, SessionResize,
+        OpenStreamRequest, StreamClose, StreamData, StreamWindowUpdate, GetCapabilitiesRequest,
+        FileStatRequest, AgentUpdateRequest,
     };
+    use sha2::{Digest, Sha256};
     use prost::Message;
     use prost_types::Timestamp;
     use std::sync::Arc;
-    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
     use std::time::{SystemTime, UNIX_EPOCH};
-    use tokio::io::{AsyncReadExt, AsyncWriteExt};
-    use tokio::net::UnixStream;
-    use tokio::sync::oneshot;
+    use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+    use tokio::net::{TcpListener, UnixListener, UnixStream};
+    use tokio::sync::{oneshot, Semaphore};
     use tracing::{debug, error, info, warn};
     use uuid::Uuid;
 
     /// Maximum message size (16 MB)
     const MAX_MESSAGE_SIZE: usize = 16 * 1024 * 1024;
 
+    /// Session output buffered per session before the reader waits
+    const SESSION_BUFFER: usize = 256;
+
+    /// How long a full session buffer may block the reader before the
+    /// session is dropped (a stalled viewer must not stall the whole agent)
+    const SESSION_SEND_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Open sessions, by session ID
+    type SessionMap = Arc<std::sync::Mutex<HashMap<String, mpsc::Sender<SessionEvent>>>>;
+
+    /// Window granted to the guest for each stream
+    const STREAM_WINDOW: u32 = 256 * 1024;
+
+    /// Bytes read from a local socket per StreamData message
+    const STREAM_CHUNK_SIZE: usize = 16 * 1024;
+
+    /// Maximum port forwards per VM
+    const MAX_PORT_FORWARDS_PER_VM: usize = 32;
+
+    /// Agents older than GetCapabilities never answer it; don't wait long
+    const CAPABILITIES_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Delivery of an open stream's frames
+    struct StreamRoute {
+        /// Unbounded: the guest never sends more than the granted window
+        events: mpsc::UnboundedSender<StreamEvent>,
+        /// Bytes the node may still send to the guest
+        credit: Arc<Semaphore>,
+    }
+
+    /// Open streams, by stream ID
+    type StreamMap = Arc<std::sync::Mutex<HashMap<String, StreamRoute>>>;
+
     /// Pending request waiting for a response
     struct PendingRequest {
         response_tx: oneshot::Sender<AgentMessage>,
@@ -82,12 +236,19 @@ mod unix_impl {
     /// The client maintains a connection to the guest agent via virtio-serial.
     /// It tracks both the writer (for sending) and whether the response handler
     /// task is still alive (for receiving).
+    #[derive(Clone)]
     pub struct AgentClient {
         vm_id: String,
         socket_path: PathBuf,
         /// Writer half of the stream - used for sending requests
         writer: Option<Arc<Mutex<WriteHalf<UnixStream>>>>,
         pending: Arc<Mutex<HashMap<String, PendingRequest>>>,
+        /// Output channels of open interactive sessions
+        sessions: SessionMap,
+        /// Routes of open multiplexed streams
+        streams: StreamMap,
+        /// Capabilities reported by the agent on this connection
+        capabilities: Arc<Mutex<Option<GetCapabilitiesResponse>>>,
         telemetry_tx: Option<mpsc::Sender<TelemetryReport>>,
         /// Channel for forwarding AgentReadyEvent to the service
         agent_ready_tx: Option<mpsc::Sender<AgentReadyEvent>>,
@@ -112,6 +273,9 @@ mod unix_impl {
                 socket_path,
                 writer: None,
                 pending: Arc::new(Mutex::new(HashMap::new())),
+                sessions: Arc::new(std::sync::Mutex::new(HashMap::new())),
+                streams: Arc::new(std::sync::Mutex::new(HashMap::new())),
+                capabilities: Arc::new(Mutex::new(None)),
                 telemetry_tx: None,
                 agent_ready_tx: None,
                 response_handler_alive: Arc::new(AtomicBool::new(false)),
@@ -202,6 +366,9 @@ mod unix_impl {
                 socket_path,
                 writer: None,
                 pending: Arc::new(Mutex::new(HashMap::new())),
+                sessions: Arc::new(std::sync::Mutex::new(HashMap::new())),
+                streams: Arc::new(std::sync::Mutex::new(HashMap::new())),
+                capabilities: Arc::new(Mutex::new(None)),
                 telemetry_tx: None,
                 agent_ready_tx: None,
                 response_handler_alive: Arc::new(AtomicBool::new(false)),
@@ -353,9 +520,14 @@ mod unix_impl {
             let (reader, writer) = tokio::io::split(stream);
             let writer = Arc::new(Mutex::new(writer));
             self.writer = Some(writer.clone());
+            
+            // The agent may have been upgraded (or replaced) since the last connection
+            *self.capabilities.lock().await = None;
 
             // Start the response handler with just the reader half
             let pending = self.pending.clone();
+            let sessions = self.sessions.clone();
+            let streams = self.streams.clone();
             let telemetry_tx = self.telemetry_tx.clone();
             let agent_ready_tx = self.agent_ready_tx.clone();
             let vm_id = self.vm_id.clone();
@@ -366,7 +538,21 @@ mod unix_impl {
 
             tokio::spawn(async move {
                 info!(vm_id = %vm_id, "Response handler task starting");
-                let result = response_handler(reader, pending, telemetry_tx, agent_ready_tx, vm_id.clone()).await;
+                let result = response_handler(
+                    reader,
+                    pending,
+                    sessions.clone(),
+                    streams.clone(),
+                    telemetry_tx,
+                    agent_ready_tx,
+                    vm_id.clone(),
+                ).await;
+                
+                // End open sessions and streams, their output can no longer arrive
+                sessions.lock().unwrap_or_else(|e| e.into_inner()).clear();
+                for (_, route) in streams.lock().unwrap_or_else(|e| e.into_inner()).drain() {
+                    route.credit.close();
+                }
                 
                 // Mark handler as dead when exiting (for any reason)
                 handler_alive.store(false, Ordering::SeqCst);
@@ -596,14 +782,26 @@ mod unix_impl {
             }
         }
 
-        /// Quiesce (freeze) filesystems for safe snapshots.
+        /// Quiesce (freeze) filesystems for safe snapshots. Unless
+        /// `skip_applications` is set, the guest first quiesces the database
+        /// providers enabled in its config.
         pub async fn quiesce_filesystems(
             &self,
             mount_points: Vec<String>,
             timeout_seconds: u32,
             run_pre_freeze_scripts: bool,
+            skip_applications: bool,
         ) -> Result<QuiesceFilesystemsResponse> {
-            let timeout = Duration::from_secs(timeout_seconds as u64 + 10);
+            // The application providers run first, each within its own timeout
+            const APPLICATION_TIMEOUT: Duration = Duration::from_secs(120);
+
+            self.require_capability("quiesce").await?;
+
+            let timeout = if skip_applications {
+                Duration::from_secs(timeout_seconds as u64 + 10)
+            } else {
+                Duration::from_secs(timeout_seconds as u64 + 10) + APPLICATION_TIMEOUT
+            };
 
             let request = AgentMessage {
                 message_id: Uuid::new_v4().to_string(),
@@ -613,6 +811,7 @@ mod unix_impl {
                     timeout_seconds,
                     run_pre_freeze_scripts,
                     pre_freeze_script_dir: String::new(),
+                    skip_applications,
                 })),
             };
 
@@ -630,6 +829,8 @@ mod unix_impl {
             quiesce_token: Option<String>,
             run_post_thaw_scripts: bool,
         ) -> Result<ThawFilesystemsResponse> {
+            self.require_capability("quiesce").await?;
+
             let request = AgentMessage {
                 message_id: Uuid::new_v4().to_string(),
                 timestamp: Some(current_timestamp()),
@@ -651,6 +852,8 @@ mod unix_impl {
 
         /// Synchronize the guest's system clock.
         pub async fn sync_time(&self, force: bool) -> Result<SyncTimeResponse> {
+            self.require_capability("sync_time").await?;
+
             let request = AgentMessage {
                 message_id: Uuid::new_v4().to_string(),
                 timestamp: Some(current_timestamp()),
@@ -696,226 +899,1022 @@ mod unix_impl {
             }
         }
 
-        /// Send a request and wait for a response.
-        async fn send_request(
-            &self,
-            request: AgentMessage,
-            timeout: Duration,
-        ) -> Result<AgentMessage> {
-            let writer = self
-                .writer
-                .as_ref()
-                .ok_or_else(|| anyhow!("Not connected to agent"))?;
-
-            let message_id = request.message_id.clone();
-            debug!(vm_id = %self.vm_id, message_id = %message_id, "Sending request to agent");
-
-            let (tx, rx) = oneshot::channel();
-            {
-                let mut pending = self.pending.lock().await;
-                pending.insert(message_id.clone(), PendingRequest { response_tx: tx });
+        /// Capabilities of the agent, queried once per connection.
+        ///
+        /// Agents that predate GetCapabilities don't answer it; they are
+        /// reported as unsupported rather than as a timeout.
+        pub async fn get_capabilities(&self) -> Result<GetCapabilitiesResponse> {
+            let mut cached = self.capabilities.lock().await;
+            if let Some(caps) = cached.as_ref() {
+                return Ok(caps.clone());
             }
 
-            // Write the request using only the writer half (no contention with reader)
-            {
-                let mut writer = writer.lock().await;
-                write_message(&mut *writer, &request).await?;
-                debug!(vm_id = %self.vm_id, message_id = %message_id, "Request sent, waiting for response");
-            }
+            let request = AgentMessage {
+                message_id: Uuid::new_v4().to_string(),
+                timestamp: Some(current_timestamp()),
+                payload: Some(agent_message::Payload::GetCapabilities(GetCapabilitiesRequest {})),
+            };
 
-            match tokio::time::timeout(timeout, rx).await {
-                Ok(Ok(response)) => {
-                    debug!(vm_id = %self.vm_id, message_id = %message_id, "Response received");
-                    Ok(response)
-                }
-                Ok(Err(_)) => {
-                    error!(vm_id = %self.vm_id, message_id = %message_id, "Response channel closed");
-                    Err(anyhow!("Response channel closed"))
+            match self.send_request(request, CAPABILITIES_TIMEOUT).await {
+                Ok(AgentMessage { payload: Some(agent_message::Payload::GetCapabilitiesResponse(caps)), .. }) => {
+                    debug!(vm_id = %self.vm_id, version = %caps.version, count = caps.capabilities.len(), "Agent capabilities");
+                    *cached = Some(caps.clone());
+                    Ok(caps)
                 }
-                Err(_) => {
-                    let mut pending = self.pending.lock().await;
-                    pending.remove(&message_id);
-                    error!(vm_id = %self.vm_id, message_id = %message_id, timeout_secs = timeout.as_secs(), "Request timed out");
-                    Err(anyhow!("Request timed out"))
+                Err(e) if !self.is_connected() => Err(e),
+                // Older agents drop the unknown request (timeout) or answer with an error
+                _ => Err(UnsupportedCapability {
+                    capability: "capabilities".to_string(),
+                    agent_version: "(unknown version)".to_string(),
                 }
+                .into()),
             }
         }
-    }
 
-    /// Handle incoming responses from the agent.
-    /// Takes ownership of the reader half of the split stream.
-    /// 
-    /// This task runs continuously, reading messages from the agent and either:
-    /// - Processing AgentReady/Telemetry/Error events
-    /// - Routing responses to pending requests
-    /// 
-    /// The task exits when:
-    /// - The socket is closed (Ok(None) from read)
-    /// - A read error occurs
-    /// - The socket EOF is reached
-    async fn response_handler(
-        mut reader: ReadHalf<UnixStream>,
-        pending: Arc<Mutex<HashMap<String, PendingRequest>>>,
-        telemetry_tx: Option<mpsc::Sender<TelemetryReport>>,
-        agent_ready_tx: Option<mpsc::Sender<AgentReadyEvent>>,
-        vm_id: String,
-    ) -> Result<()> {
-        info!(vm_id = %vm_id, "Response handler started - now listening for messages from guest agent");
-        
-        let mut message_count: u64 = 0;
-        
-        loop {
-            // Read directly from the reader - no mutex needed since we own this half
-            debug!(vm_id = %vm_id, message_count = message_count, "Waiting for next message from agent");
-            
-            let message = match read_message::<_, AgentMessage>(&mut reader).await {
-                Ok(Some(msg)) => {
-                    message_count += 1;
-                    debug!(vm_id = %vm_id, message_count = message_count, "Read message successfully");
-                    msg
-                }
-                Ok(None) => {
-                    info!(vm_id = %vm_id, message_count = message_count, 
-                          "Agent connection closed by peer (EOF) after {} messages", message_count);
-                    break;
+        /// Fail with `UnsupportedCapability` unless the agent reports `capability`.
+        pub async fn require_capability(&self, capability: &str) -> Result<()> {
+            let caps = self.get_capabilities().await.map_err(|e| match e.downcast::<UnsupportedCapability>() {
+                Ok(unsupported) => UnsupportedCapability {
+                    capability: capability.to_string(),
+                    agent_version: unsupported.agent_version,
                 }
-                Err(e) => {
-                    error!(vm_id = %vm_id, message_count = message_count, error = %e, 
-                           "Failed to read message from agent - handler exiting");
-                    return Err(e);
+                .into(),
+                Err(e) => e,
+            })?;
+
+            if caps.capabilities.iter().any(|c| c == capability) {
+                Ok(())
+            } else {
+                Err(UnsupportedCapability {
+                    capability: capability.to_string(),
+                    agent_version: caps.version,
                 }
+                .into())
+            }
+        }
+
+        /// Send a request that needs `capability` and return the response payload.
+        async fn call(&self, capability: &str, payload: agent_message::Payload) -> Result<agent_message::Payload> {
+            self.require_capability(capability).await?;
+
+            let request = AgentMessage {
+                message_id: Uuid::new_v4().to_string(),
+                timestamp: Some(current_timestamp()),
+                payload: Some(payload),
             };
+            let response = self.send_request(request, DEFAULT_TIMEOUT).await?;
+            response.payload.ok_or_else(|| anyhow!("Empty response from agent"))
+        }
 
-            let message_id = message.message_id.clone();
-            debug!(vm_id = %vm_id, message_id = %message_id, "Received message from agent");
+        /// List processes in the guest.
+        pub async fn list_processes(&self, req: ListProcessesRequest) -> Result<ListProcessesResponse> {
+            match self.call("process_list", agent_message::Payload::ListProcesses(req)).await? {
+                agent_message::Payload::ListProcessesResponse(resp) if resp.success => Ok(resp),
+                agent_message::Payload::ListProcessesResponse(resp) => Err(anyhow!("Process listing failed: {}", resp.error)),
+                _ => Err(anyhow!("Unexpected response type")),
+            }
+        }
 
-            match &message.payload {
-                Some(agent_message::Payload::Telemetry(report)) => {
-                    debug!(vm_id = %vm_id, "Received telemetry report");
-                    if let Some(ref tx) = telemetry_tx {
-                        let _ = tx.send(report.clone()).await;
-                    }
-                    continue;
-                }
-                Some(agent_message::Payload::AgentReady(ready)) => {
-                    info!(
-                        vm_id = %vm_id,
-                        version = %ready.version,
-                        hostname = %ready.hostname,
-                        os = %ready.os_name,
-                        kernel = %ready.kernel_version,
-                        ips = ?ready.ip_addresses,
-                        "Agent ready event received"
-                    );
-                    // Forward to service to update agent cache
-                    if let Some(ref tx) = agent_ready_tx {
-                        if let Err(e) = tx.send(ready.clone()).await {
-                            warn!(vm_id = %vm_id, error = %e, "Failed to forward AgentReadyEvent");
-                        }
-                    }
-                    continue;
-                }
-                Some(agent_message::Payload::Error(err)) => {
-                    error!(
-                        vm_id = %vm_id,
-                        code = %err.code,
-                        message = %err.message,
-                        "Agent error"
-                    );
-                    continue;
-                }
-                Some(agent_message::Payload::Pong(pong)) => {
-                    debug!(
-                        vm_id = %vm_id,
-                        message_id = %message_id,
-                        version = %pong.version,
-                        "Received pong response"
-                    );
-                }
-                _ => {
-                    debug!(vm_id = %vm_id, message_id = %message_id, "Received response message");
-                }
+        /// Send a signal to a guest process.
+        pub async fn kill_process(&self, pid: u32, signal: i32) -> Result<()> {
+            match self.call("process_kill", agent_message::Payload::KillProcess(KillProcessRequest { pid, signal })).await? {
+                agent_message::Payload::KillProcessResponse(resp) if resp.success => Ok(()),
+                agent_message::Payload::KillProcessResponse(resp) => Err(anyhow!("Kill failed: {}", resp.error)),
+                _ => Err(anyhow!("Unexpected response type")),
             }
+        }
 
-            // Route response to waiting request
-            let pending_req = {
-                let mut pending = pending.lock().await;
-                pending.remove(&message_id)
-            };
+        /// List services (systemd units or Windows services) in the guest.
+        pub async fn list_services(&self, req: ListServicesRequest) -> Result<ListServicesResponse> {
+            match self.call("service_list", agent_message::Payload::ListServices(req)).await? {
+                agent_message::Payload::ListServicesResponse(resp) if resp.success => Ok(resp),
+                agent_message::Payload::ListServicesResponse(resp) => Err(anyhow!("Service listing failed: {}", resp.error)),
+                _ => Err(anyhow!("Unexpected response type")),
+            }
+        }
 
-            if let Some(req) = pending_req {
-                debug!(vm_id = %vm_id, message_id = %message_id, "Routing response to pending request");
-                let _ = req.response_tx.send(message);
-            } else {
-                debug!(vm_id = %vm_id, message_id = %message_id, "No pending request for message");
+        /// Start, stop, restart, enable or disable a guest service.
+        pub async fn service_control(&self, req: ServiceControlRequest) -> Result<ServiceControlResponse> {
+            match self.call("service_control", agent_message::Payload::ServiceControl(req)).await? {
+                agent_message::Payload::ServiceControlResponse(resp) if resp.success => Ok(resp),
+                agent_message::Payload::ServiceControlResponse(resp) => Err(anyhow!("Service control failed: {}", resp.error)),
+                _ => Err(anyhow!("Unexpected response type")),
             }
         }
 
-        Ok(())
-    }
+        /// Hardware inventory of the guest.
+        pub async fn get_hardware_info(&self, req: GetHardwareInfoRequest) -> Result<GetHardwareInfoResponse> {
+            match self.call("hardware_info", agent_message::Payload::GetHardwareInfo(req)).await? {
+                agent_message::Payload::HardwareInfoResponse(resp) if resp.success => Ok(resp),
+                agent_message::Payload::HardwareInfoResponse(resp) => Err(anyhow!("Hardware inventory failed: {}", resp.error)),
+                _ => Err(anyhow!("Unexpected response type")),
+            }
+        }
 
-    /// Magic header for protocol framing: "QTX1" (Quantix Protocol v1)
-    /// This allows the reader to resync if it receives garbage/stale data.
-    const MAGIC_HEADER: [u8; 4] = [0x51, 0x54, 0x58, 0x01]; // Q=0x51, T=0x54, X=0x58, 1=0x01
-    
-    /// Maximum bytes to scan when resyncing before giving up
-    const MAX_RESYNC_BYTES: usize = 64 * 1024; // 64KB
+        /// Installed software (packages or Windows programs) in the guest.
+        pub async fn list_installed_software(&self, req: ListInstalledSoftwareRequest) -> Result<ListInstalledSoftwareResponse> {
+            match self.call("software_list", agent_message::Payload::ListInstalledSoftware(req)).await? {
+                agent_message::Payload::ListInstalledSoftwareResponse(resp) if resp.success => Ok(resp),
+                agent_message::Payload::ListInstalledSoftwareResponse(resp) => Err(anyhow!("Software inventory failed: {}", resp.error)),
+                _ => Err(anyhow!("Unexpected response type")),
+            }
+        }
 
-    /// Read a magic-header-prefixed protobuf message from any async reader.
-    /// 
-    /// The protocol format is:
-    /// - 4 bytes: Magic header "QTX1" (0x51 0x54 0x58 0x01)
-    /// - 4 bytes: Message length (big-endian u32)
-    /// - N bytes: Protobuf payload
-    ///
-    /// If garbage data is encountered, this function will scan for the magic header,
-    /// discarding invalid bytes until a valid message is found. This prevents the
-    /// deadlock scenario where:
-    /// 1. Host connects after guest sent data
-    /// 2. Host reads garbage as a huge "length" (e.g., 50,000)
-    /// 3. Host blocks forever waiting for 50,000 bytes
-    /// 4. Guest's buffer fills up and writes timeout
-    async fn read_message<R, M>(reader: &mut R) -> Result<Option<M>>
-    where
-        R: AsyncReadExt + Unpin,
-        M: Message + Default,
-    {
-        // Track total bytes scanned across ALL resync attempts in this function call
-        let mut total_resync_bytes: usize = 0;
-        
-        loop {
-            // Reset per-iteration resync counter (CRITICAL FIX: was not being reset before)
-            let mut resync_bytes_this_iteration: usize = 0;
-            
-            // 1. Scan for Magic Header (Resync Mechanism)
-            let mut header_byte = [0u8; 1];
-            let mut match_count: usize = 0;
+        /// Set the password of a guest user.
+        pub async fn reset_password(&self, req: ResetPasswordRequest) -> Result<()> {
+            match self.call("reset_password", agent_message::Payload::ResetPassword(req)).await? {
+                agent_message::Payload::ResetPasswordResponse(resp) if resp.success => Ok(()),
+                agent_message::Payload::ResetPasswordResponse(resp) => Err(anyhow!("Password reset failed: {}", resp.error)),
+                _ => Err(anyhow!("Unexpected response type")),
+            }
+        }
 
-            while match_count < 4 {
-                match reader.read(&mut header_byte).await {
-                    Ok(0) => {
-                        // EOF
-                        if total_resync_bytes > 0 {
-                            debug!(total_bytes_scanned = total_resync_bytes, "EOF reached during resync");
-                        }
-                        return Ok(None);
-                    }
-                    Ok(_) => {}
-                    Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
-                        return Ok(None);
-                    }
-                    Err(e) => {
-                        return Err(e).context("Failed to read magic header");
-                    }
-                }
+        /// Local user accounts of the guest.
+        pub async fn list_users(&self, req: ListUsersRequest) -> Result<ListUsersResponse> {
+            match self.call("user_management", agent_message::Payload::ListUsers(req)).await? {
+                agent_message::Payload::ListUsersResponse(resp) if resp.success => Ok(resp),
+                agent_message::Payload::ListUsersResponse(resp) => Err(anyhow!("User listing failed: {}", resp.error)),
+                _ => Err(anyhow!("Unexpected response type")),
+            }
+        }
 
-                // Count EVERY byte we read (CRITICAL FIX: was only counting mismatches)
-                resync_bytes_this_iteration += 1;
+        /// Create, delete, lock or unlock a guest account, change its groups
+        /// or SSH keys, or end its sessions.
+        pub async fn user_account(&self, req: UserAccountRequest) -> Result<UserAccountResponse> {
+            match self.call("user_management", agent_message::Payload::UserAccount(req)).await? {
+                agent_message::Payload::UserAccountResponse(resp) if resp.success => Ok(resp),
+                agent_message::Payload::UserAccountResponse(resp) => Err(anyhow!("User account change failed: {}", resp.error)),
+                _ => Err(anyhow!("Unexpected response type")),
+            }
+        }
 
-                if header_byte[0] == MAGIC_HEADER[match_count] {
-                    match_count += 1;
-                } else {
-                    // Mismatch - log progress periodically
+        /// Logged-in sessions in the guest.
+        pub async fn list_login_sessions(&self, req: ListLoginSessionsRequest) -> Result<ListLoginSessionsResponse> {
+            match self.call("user_management", agent_message::Payload::ListLoginSessions(req)).await? {
+                agent_message::Payload::ListLoginSessionsResponse(resp) if resp.success => Ok(resp),
+                agent_message::Payload::ListLoginSessionsResponse(resp) => Err(anyhow!("Session listing failed: {}", resp.error)),
+                _ => Err(anyhow!("Unexpected response type")),
+            }
+        }
+
+        /// Pending OS updates in the guest.
+        pub async fn list_updates(&self, req: ListUpdatesRequest) -> Result<ListUpdatesResponse> {
+            // Refreshing the package metadata can take minutes on slow mirrors
+            const LIST_UPDATES_TIMEOUT: Duration = Duration::from_secs(660);
+
+            self.require_capability("os_patching").await?;
+
+            let request = AgentMessage {
+                message_id: Uuid::new_v4().to_string(),
+                timestamp: Some(current_timestamp()),
+                payload: Some(agent_message::Payload::ListUpdates(req)),
+            };
+
+            match self.send_request(request, LIST_UPDATES_TIMEOUT).await?.payload {
+                Some(agent_message::Payload::ListUpdatesResponse(resp)) if resp.success => Ok(resp),
+                Some(agent_message::Payload::ListUpdatesResponse(resp)) => Err(anyhow!("Update listing failed: {}", resp.error)),
+                _ => Err(anyhow!("Unexpected response type")),
+            }
+        }
+
+        /// Install OS updates in the guest. A run that fails part-way is
+        /// returned as-is (success = false) since some updates may be
+        /// installed; only transport errors are `Err`.
+        pub async fn apply_updates(&self, req: ApplyUpdatesRequest) -> Result<ApplyUpdatesResponse> {
+            // The agent lists the pending updates before and after the run
+            const LISTING_OVERHEAD: Duration = Duration::from_secs(1260);
+            const DEFAULT_RUN_TIMEOUT_SECS: u64 = 3600;
+
+            self.require_capability("os_patching").await?;
+
+            let run_timeout = if req.timeout_seconds > 0 { req.timeout_seconds as u64 } else { DEFAULT_RUN_TIMEOUT_SECS };
+            let request = AgentMessage {
+                message_id: Uuid::new_v4().to_string(),
+                timestamp: Some(current_timestamp()),
+                payload: Some(agent_message::Payload::ApplyUpdates(req)),
+            };
+
+            match self.send_request(request, Duration::from_secs(run_timeout) + LISTING_OVERHEAD).await?.payload {
+                Some(agent_message::Payload::ApplyUpdatesResponse(resp)) => Ok(resp),
+                _ => Err(anyhow!("Unexpected response type")),
+            }
+        }
+
+        /// Apply a network configuration (Netplan YAML) in the guest.
+        pub async fn configure_network(&self, req: ConfigureNetworkRequest) -> Result<()> {
+            match self.call("configure_network", agent_message::Payload::ConfigureNetwork(req)).await? {
+                agent_message::Payload::ConfigureNetworkResponse(resp) if resp.success => Ok(()),
+                agent_message::Payload::ConfigureNetworkResponse(resp) => Err(anyhow!("Network configuration failed: {}", resp.error)),
+                _ => Err(anyhow!("Unexpected response type")),
+            }
+        }
+
+        /// Read the guest clipboard.
+        pub async fn clipboard_get(&self, req: ClipboardGetRequest) -> Result<ClipboardGetResponse> {
+            match self.call("clipboard", agent_message::Payload::ClipboardGet(req)).await? {
+                agent_message::Payload::ClipboardGetResponse(resp) if resp.success => Ok(resp),
+                agent_message::Payload::ClipboardGetResponse(resp) => Err(anyhow!("Clipboard read failed: {}", resp.error)),
+                _ => Err(anyhow!("Unexpected response type")),
+            }
+        }
+
+        /// Replace the guest clipboard.
+        pub async fn clipboard_update(&self, req: ClipboardUpdateRequest) -> Result<()> {
+            match self.call("clipboard", agent_message::Payload::ClipboardUpdate(req)).await? {
+                agent_message::Payload::ClipboardUpdateResponse(resp) if resp.success => Ok(()),
+                agent_message::Payload::ClipboardUpdateResponse(resp) => Err(anyhow!("Clipboard update failed: {}", resp.error)),
+                _ => Err(anyhow!("Unexpected response type")),
+            }
+        }
+
+        /// Change the guest display resolution.
+        pub async fn display_resize(&self, req: DisplayResizeRequest) -> Result<DisplayResizeResponse> {
+            match self.call("display_resize", agent_message::Payload::DisplayResize(req)).await? {
+                agent_message::Payload::DisplayResizeResponse(resp) if resp.success => Ok(resp),
+                agent_message::Payload::DisplayResizeResponse(resp) => Err(anyhow!("Display resize failed: {}", resp.error)),
+                _ => Err(anyhow!("Unexpected response type")),
+            }
+        }
+
+        /// Create a directory in the guest.
+        pub async fn create_directory(&self, req: CreateDirectoryRequest) -> Result<()> {
+            match self.call("directory_create", agent_message::Payload::CreateDirectory(req)).await? {
+                agent_message::Payload::CreateDirectoryResponse(resp) if resp.success => Ok(()),
+                agent_message::Payload::CreateDirectoryResponse(resp) => Err(anyhow!("Create directory failed: {}", resp.error)),
+                _ => Err(anyhow!("Unexpected response type")),
+            }
+        }
+
+        /// Delete a file or directory in the guest.
+        pub async fn delete_file(&self, req: FileDeleteRequest) -> Result<()> {
+            match self.call("file_delete", agent_message::Payload::FileDelete(req)).await? {
+                agent_message::Payload::FileDeleteResponse(resp) if resp.success => Ok(()),
+                agent_message::Payload::FileDeleteResponse(resp) => Err(anyhow!("Delete failed: {}", resp.error)),
+                _ => Err(anyhow!("Unexpected response type")),
+            }
+        }
+
+        /// Stat a path in the guest.
+        pub async fn stat_file(&self, path: &str) -> Result<FileStatResponse> {
+            let req = FileStatRequest { path: path.to_string() };
+            match self.call("file_stat", agent_message::Payload::FileStat(req)).await? {
+                agent_message::Payload::FileStatResponse(resp) if resp.success => Ok(resp),
+                agent_message::Payload::FileStatResponse(resp) => Err(anyhow!("Stat failed: {}", resp.error)),
+                _ => Err(anyhow!("Unexpected response type")),
+            }
+        }
+
+        /// Send one chunk of a directory tree upload. Failures reported by
+        /// the guest come back as a response with `success == false`.
+        pub async fn tree_upload(&self, req: TreeUploadRequest) -> Result<TreeUploadResponse> {
+            match self.call("tree_transfer", agent_message::Payload::TreeUpload(req)).await? {
+                agent_message::Payload::TreeUploadResponse(resp) => Ok(resp),
+                _ => Err(anyhow!("Unexpected response type")),
+            }
+        }
+
+        /// Fetch one chunk of a directory tree download.
+        pub async fn tree_download(&self, req: TreeDownloadRequest) -> Result<TreeDownloadResponse> {
+            match self.call("tree_transfer", agent_message::Payload::TreeDownload(req)).await? {
+                agent_message::Payload::TreeDownloadResponse(resp) => Ok(resp),
+                _ => Err(anyhow!("Unexpected response type")),
+            }
+        }
+
+        /// Grow partitions, LVM volumes and filesystems to fill resized disks.
+        /// Per-filesystem failures are reported in the response.
+        pub async fn grow_filesystems(&self, req: GrowFilesystemsRequest) -> Result<GrowFilesystemsResponse> {
+            match self.call("grow_filesystems", agent_message::Payload::GrowFilesystems(req)).await? {
+                agent_message::Payload::GrowFilesystemsResponse(resp) if resp.error.is_empty() => Ok(resp),
+                agent_message::Payload::GrowFilesystemsResponse(resp) => Err(anyhow!("Grow filesystems failed: {}", resp.error)),
+                _ => Err(anyhow!("Unexpected response type")),
+            }
+        }
+
+        /// Discard unused blocks on guest filesystems (fstrim / ReTrim).
+        /// Per-filesystem failures are reported in the response.
+        pub async fn trim_filesystems(&self, req: TrimFilesystemsRequest) -> Result<TrimFilesystemsResponse> {
+            // Trimming a large, never-trimmed filesystem can take minutes
+            const TRIM_TIMEOUT: Duration = Duration::from_secs(900);
+
+            self.require_capability("trim_filesystems").await?;
+
+            let request = AgentMessage {
+                message_id: Uuid::new_v4().to_string(),
+                timestamp: Some(current_timestamp()),
+                payload: Some(agent_message::Payload::TrimFilesystems(req)),
+            };
+
+            match self.send_request(request, TRIM_TIMEOUT).await?.payload {
+                Some(agent_message::Payload::TrimFilesystemsResponse(resp)) if resp.error.is_empty() => Ok(resp),
+                Some(agent_message::Payload::TrimFilesystemsResponse(resp)) => Err(anyhow!("Trim filesystems failed: {}", resp.error)),
+                _ => Err(anyhow!("Unexpected response type")),
+            }
+        }
+
+        /// Push a signed agent binary. The agent verifies the signature
+        /// against its embedded keys, installs the binary and restarts; the
+        /// response carries the version of the new binary.
+        pub async fn update_agent(&self, binary: &[u8], signature: &[u8], target_version: &str) -> Result<AgentUpdateResponse> {
+            const CHUNK_SIZE: usize = 1024 * 1024;
+
+            if binary.is_empty() {
+                return Err(anyhow!("Agent binary is empty"));
+            }
+            self.require_capability("signed_update").await?;
+
+            let checksum = hex::encode(Sha256::digest(binary));
+            let total_chunks = binary.len().div_ceil(CHUNK_SIZE);
+            let mut last = None;
+
+            for (i, chunk) in binary.chunks(CHUNK_SIZE).enumerate() {
+                let first = i == 0;
+                let request = AgentMessage {
+                    message_id: Uuid::new_v4().to_string(),
+                    timestamp: Some(current_timestamp()),
+                    payload: Some(agent_message::Payload::AgentUpdate(AgentUpdateRequest {
+                        target_version: target_version.to_string(),
+                        binary_data: chunk.to_vec(),
+                        chunk_number: i as u32,
+                        is_last_chunk: i == total_chunks - 1,
+                        checksum_sha256: if first { checksum.clone() } else { String::new() },
+                        total_size: binary.len() as u64,
+                        signature: if first { signature.to_vec() } else { Vec::new() },
+                    })),
+                };
+
+                match self.send_request(request, DEFAULT_TIMEOUT).await?.payload {
+                    Some(agent_message::Payload::AgentUpdateResponse(resp)) if resp.success => last = Some(resp),
+                    Some(agent_message::Payload::AgentUpdateResponse(resp)) => {
+                        return Err(anyhow!("Agent update failed: {}", resp.error));
+                    }
+                    _ => return Err(anyhow!("Unexpected response type")),
+                }
+            }
+
+            last.ok_or_else(|| anyhow!("Agent did not acknowledge the update"))
+        }
+
+        /// Open an interactive session (PTY shell or streaming exec) in the guest.
+        ///
+        /// A session ID is generated when the request has none. Output and
+        /// the exit status are received through the returned `AgentSession`.
+        pub async fn open_session(&self, mut req: OpenSessionRequest) -> Result<AgentSession> {
+            self.require_capability("session").await?;
+            let writer = self
+                .writer
+                .clone()
+                .ok_or_else(|| anyhow!("Not connected to agent"))?;
+            if req.session_id.is_empty() {
+                req.session_id = Uuid::new_v4().to_string();
+            }
+            let session_id = req.session_id.clone();
+            
+            // Register before sending: output can arrive ahead of the response
+            let (tx, rx) = mpsc::channel(SESSION_BUFFER);
+            self.sessions.lock().unwrap_or_else(|e| e.into_inner()).insert(session_id.clone(), tx);
+            
+            let session = AgentSession {
+                session_id: session_id.clone(),
+                vm_id: self.vm_id.clone(),
+                writer,
+                events: rx,
+                sessions: self.sessions.clone(),
+                exited: false,
+            };
+            
+            let request = AgentMessage {
+                message_id: Uuid::new_v4().to_string(),
+                timestamp: Some(current_timestamp()),
+                payload: Some(agent_message::Payload::OpenSession(req)),
+            };
+            
+            // On error the session is dropped, which unregisters it
+            let response = self.send_request(request, DEFAULT_TIMEOUT).await?;
+            match response.payload {
+                Some(agent_message::Payload::OpenSessionResponse(resp)) if resp.success => {
+                    info!(vm_id = %self.vm_id, session_id = %session_id, pid = resp.pid, "Agent session opened");
+                    Ok(session)
+                }
+                Some(agent_message::Payload::OpenSessionResponse(resp)) => {
+                    Err(anyhow!("Failed to open session: {}", resp.error))
+                }
+                _ => Err(anyhow!("Unexpected response type")),
+            }
+        }
+        
+        /// Handle for opening streams on the current connection.
+        ///
+        /// The handle does not borrow the client, so long-lived users such as
+        /// port forwards don't keep the agent map locked. It stops working
+        /// when this connection is lost.
+        pub fn stream_opener(&self) -> Result<AgentStreamOpener> {
+            let writer = self
+                .writer
+                .clone()
+                .ok_or_else(|| anyhow!("Not connected to agent"))?;
+            Ok(AgentStreamOpener {
+                vm_id: self.vm_id.clone(),
+                writer,
+                pending: self.pending.clone(),
+                streams: self.streams.clone(),
+                alive: self.response_handler_alive.clone(),
+            })
+        }
+
+        /// Open a TCP stream to `target_host:target_port` inside the guest.
+        pub async fn open_stream(&self, target_host: &str, target_port: u16) -> Result<AgentStream> {
+            self.require_capability("port_forward").await?;
+            self.stream_opener()?.open(target_host, target_port).await
+        }
+        
+        /// Send a request and wait for a response.
+        async fn send_request(
+            &self,
+            request: AgentMessage,
+            timeout: Duration,
+        ) -> Result<AgentMessage> {
+            let writer = self
+                .writer
+                .as_ref()
+                .ok_or_else(|| anyhow!("Not connected to agent"))?;
+
+            send_request_on(&self.vm_id, writer, &self.pending, request, timeout).await
+        }
+    }
+
+    /// Send a request on a connection and wait for its response.
+    async fn send_request_on(
+        vm_id: &str,
+        writer: &Arc<Mutex<WriteHalf<UnixStream>>>,
+        pending: &Arc<Mutex<HashMap<String, PendingRequest>>>,
+        request: AgentMessage,
+        timeout: Duration,
+    ) -> Result<AgentMessage> {
+        let message_id = request.message_id.clone();
+        debug!(vm_id = %vm_id, message_id = %message_id, "Sending request to agent");
+
+        let (tx, rx) = oneshot::channel();
+        {
+            let mut pending = pending.lock().await;
+            pending.insert(message_id.clone(), PendingRequest { response_tx: tx });
+        }
+
+        // Write the request using only the writer half (no contention with reader)
+        {
+            let mut writer = writer.lock().await;
+            write_message(&mut *writer, &request).await?;
+            debug!(vm_id = %vm_id, message_id = %message_id, "Request sent, waiting for response");
+        }
+
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(response)) => {
+                debug!(vm_id = %vm_id, message_id = %message_id, "Response received");
+                Ok(response)
+            }
+            Ok(Err(_)) => {
+                error!(vm_id = %vm_id, message_id = %message_id, "Response channel closed");
+                Err(anyhow!("Response channel closed"))
+            }
+            Err(_) => {
+                let mut pending = pending.lock().await;
+                pending.remove(&message_id);
+                error!(vm_id = %vm_id, message_id = %message_id, timeout_secs = timeout.as_secs(), "Request timed out");
+                Err(anyhow!("Request timed out"))
+            }
+        }
+    }
+
+    /// Opens multiplexed streams on an agent connection.
+    #[derive(Clone)]
+    pub struct AgentStreamOpener {
+        vm_id: String,
+        writer: Arc<Mutex<WriteHalf<UnixStream>>>,
+        pending: Arc<Mutex<HashMap<String, PendingRequest>>>,
+        streams: StreamMap,
+        alive: Arc<AtomicBool>,
+    }
+    
+    impl AgentStreamOpener {
+        /// Whether the connection this opener belongs to is still up.
+        pub fn is_connected(&self) -> bool {
+            self.alive.load(Ordering::SeqCst)
+        }
+        
+        /// Open a TCP stream to `target_host:target_port` inside the guest.
+        pub async fn open(&self, target_host: &str, target_port: u16) -> Result<AgentStream> {
+            let stream_id = Uuid::new_v4().to_string();
+            
+            // Register before sending: data can arrive ahead of the response.
+            // The guest grants its window in the response, until then nothing may be sent.
+            let (tx, rx) = mpsc::unbounded_channel();
+            let credit = Arc::new(Semaphore::new(0));
+            self.streams.lock().unwrap_or_else(|e| e.into_inner()).insert(
+                stream_id.clone(),
+                StreamRoute { events: tx, credit: credit.clone() },
+            );
+            
+            let mut stream = AgentStream {
+                stream_id: stream_id.clone(),
+                vm_id: self.vm_id.clone(),
+                writer: self.writer.clone(),
+                events: rx,
+                credit,
+                streams: self.streams.clone(),
+                closed: false,
+            };
+            
+            let request = AgentMessage {
+                message_id: Uuid::new_v4().to_string(),
+                timestamp: Some(current_timestamp()),
+                payload: Some(agent_message::Payload::OpenStream(OpenStreamRequest {
+                    stream_id: stream_id.clone(),
+                    target_host: target_host.to_string(),
+                    target_port: target_port as u32,
+                    initial_window: STREAM_WINDOW,
+                })),
+            };
+            
+            // On error the stream is dropped, which unregisters it
+            let response = send_request_on(&self.vm_id, &self.writer, &self.pending, request, DEFAULT_TIMEOUT).await?;
+            match response.payload {
+                Some(agent_message::Payload::OpenStreamResponse(resp)) if resp.success => {
+                    stream.credit.add_permits(resp.initial_window as usize);
+                    debug!(vm_id = %self.vm_id, stream_id = %stream_id, target_port = target_port, "Agent stream opened");
+                    Ok(stream)
+                }
+                Some(agent_message::Payload::OpenStreamResponse(resp)) => {
+                    stream.closed = true;
+                    Err(anyhow!("Failed to open stream: {}", resp.error))
+                }
+                _ => Err(anyhow!("Unexpected response type")),
+            }
+        }
+    }
+
+    /// A TCP connection made by the guest, carried over the agent channel.
+    ///
+    /// Dropping a stream that has not finished resets it in the guest.
+    pub struct AgentStream {
+        stream_id: String,
+        vm_id: String,
+        writer: Arc<Mutex<WriteHalf<UnixStream>>>,
+        events: mpsc::UnboundedReceiver<StreamEvent>,
+        credit: Arc<Semaphore>,
+        streams: StreamMap,
+        closed: bool,
+    }
+    
+    impl AgentStream {
+        /// Stream ID (as known to the guest).
+        pub fn id(&self) -> &str {
+            &self.stream_id
+        }
+        
+        /// Copy data between `socket` and the guest until both directions have
+        /// ended. Returns the bytes sent to and received from the guest.
+        pub async fn forward<S>(mut self, socket: S) -> Result<(u64, u64)>
+        where
+            S: AsyncRead + AsyncWrite + Unpin,
+        {
+            let (mut rd, mut wr) = tokio::io::split(socket);
+            let mut buf = vec![0u8; STREAM_CHUNK_SIZE];
+            let (mut sent, mut received) = (0u64, 0u64);
+            let (mut local_eof, mut remote_eof) = (false, false);
+            
+            while !(local_eof && remote_eof) {
+                tokio::select! {
+                    read = rd.read(&mut buf), if !local_eof => {
+                        let n = read.context("Local read failed")?;
+                        if n == 0 {
+                            local_eof = true;
+                            self.send(agent_message::Payload::StreamData(StreamData {
+                                stream_id: self.stream_id.clone(),
+                                data: Vec::new(),
+                                eof: true,
+                            })).await?;
+                        }
+                        
+                        // Send as much as the guest's window allows, then wait for more
+                        let mut offset = 0;
+                        while offset < n {
+                            let Ok(permit) = self.credit.acquire().await else {
+                                self.closed = true;
+                                return Err(anyhow!("Stream closed by guest"));
+                            };
+                            permit.forget();
+                            let extra = (n - offset - 1).min(self.credit.available_permits());
+                            if let Ok(permits) = self.credit.try_acquire_many(extra as u32) {
+                                permits.forget();
+                            }
+                            let end = offset + 1 + extra;
+                            self.send(agent_message::Payload::StreamData(StreamData {
+                                stream_id: self.stream_id.clone(),
+                                data: buf[offset..end].to_vec(),
+                                eof: false,
+                            })).await?;
+                            offset = end;
+                        }
+                        sent += n as u64;
+                    }
+                    event = self.events.recv(), if !remote_eof => {
+                        match event {
+                            Some(StreamEvent::Data(data)) => {
+                                wr.write_all(&data).await.context("Local write failed")?;
+                                received += data.len() as u64;
+                                self.send(agent_message::Payload::StreamWindow(StreamWindowUpdate {
+                                    stream_id: self.stream_id.clone(),
+                                    increment: data.len() as u32,
+                                })).await?;
+                            }
+                            Some(StreamEvent::Eof) => {
+                                remote_eof = true;
+                                let _ = wr.shutdown().await;
+                            }
+                            Some(StreamEvent::Closed(error)) => {
+                                self.closed = true;
+                                return Err(if error.is_empty() {
+                                    anyhow!("Stream closed by guest")
+                                } else {
+                                    anyhow!("Stream reset by guest: {}", error)
+                                });
+                            }
+                            None => {
+                                self.closed = true;
+                                return Err(anyhow!("Agent connection lost"));
+                            }
+                        }
+                    }
+                }
+            }
+
+            // The guest drops its side once both directions have ended
+            self.closed = true;
+            debug!(vm_id = %self.vm_id, stream_id = %self.stream_id, sent = sent, received = received, "Agent stream finished");
+            Ok((sent, received))
+        }
+        
+        async fn send(&self, payload: agent_message::Payload) -> Result<()> {
+            let message = AgentMessage {
+                message_id: Uuid::new_v4().to_string(),
+                timestamp: Some(current_timestamp()),
+                payload: Some(payload),
+            };
+            let mut writer = self.writer.lock().await;
+            write_message(&mut *writer, &message).await
+        }
+    }
+    
+    impl Drop for AgentStream {
+        fn drop(&mut self) {
+            self.streams.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.stream_id);
+            if self.closed {
+                return;
+            }
+
+            debug!(vm_id = %self.vm_id, stream_id = %self.stream_id, "Resetting abandoned agent stream");
+            let writer = self.writer.clone();
+            let message = AgentMessage {
+                message_id: Uuid::new_v4().to_string(),
+                timestamp: Some(current_timestamp()),
+                payload: Some(agent_message::Payload::StreamClose(StreamClose {
+                    stream_id: self.stream_id.clone(),
+                    error: String::new(),
+                })),
+            };
+            if let Ok(handle) = tokio::runtime::Handle::try_current() {
+                handle.spawn(async move {
+                    let mut writer = writer.lock().await;
+                    let _ = write_message(&mut *writer, &message).await;
+                });
+            }
+        }
+    }
+
+    /// An interactive session in the guest.
+    ///
+    /// Input, resize and close are fire-and-forget messages; the guest reports
+    /// the end of the session with `SessionEvent::Exited`. Dropping an active
+    /// session kills the process in the guest.
+    pub struct AgentSession {
+        session_id: String,
+        vm_id: String,
+        writer: Arc<Mutex<WriteHalf<UnixStream>>>,
+        events: mpsc::Receiver<SessionEvent>,
+        sessions: SessionMap,
+        exited: bool,
+    }
+    
+    impl AgentSession {
+        /// Session ID (as known to the guest).
+        pub fn id(&self) -> &str {
+            &self.session_id
+        }
+        
+        /// Next output chunk or the exit status. `None` once the session has
+        /// exited or the agent connection was lost.
+        pub async fn recv(&mut self) -> Option<SessionEvent> {
+            if self.exited {
+                return None;
+            }
+            let event = self.events.recv().await;
+            if matches!(event, Some(SessionEvent::Exited(_)) | None) {
+                self.exited = true;
+            }
+            event
+        }
+        
+        /// Write to the terminal or stdin; `eof` closes stdin (pipe sessions).
+        pub async fn send_input(&self, data: Vec<u8>, eof: bool) -> Result<()> {
+            self.send(agent_message::Payload::SessionInput(SessionInput {
+                session_id: self.session_id.clone(),
+                data,
+                eof,
+            })).await
+        }
+        
+        /// Change the terminal size (PTY sessions).
+        pub async fn resize(&self, cols: u32, rows: u32) -> Result<()> {
+            self.send(agent_message::Payload::SessionResize(SessionResize {
+                session_id: self.session_id.clone(),
+                cols,
+                rows,
+            })).await
+        }
+        
+        /// Hang up (or kill with `force`) the session's processes.
+        pub async fn close(&self, force: bool) -> Result<()> {
+            self.send(agent_message::Payload::CloseSession(CloseSessionRequest {
+                session_id: self.session_id.clone(),
+                force,
+            })).await
+        }
+        
+        async fn send(&self, payload: agent_message::Payload) -> Result<()> {
+            let message = AgentMessage {
+                message_id: Uuid::new_v4().to_string(),
+                timestamp: Some(current_timestamp()),
+                payload: Some(payload),
+            };
+            let mut writer = self.writer.lock().await;
+            write_message(&mut *writer, &message).await
+        }
+    }
+    
+    impl Drop for AgentSession {
+        fn drop(&mut self) {
+            self.sessions.lock().unwrap_or_else(|e| e.into_inner()).remove(&self.session_id);
+            if self.exited {
+                return;
+            }
+            
+            debug!(vm_id = %self.vm_id, session_id = %self.session_id, "Killing abandoned agent session");
+            let writer = self.writer.clone();
+            let message = AgentMessage {
+                message_id: Uuid::new_v4().to_string(),
+                timestamp: Some(current_timestamp()),
+                payload: Some(agent_message::Payload::CloseSession(CloseSessionRequest {
+                    session_id: self.session_id.clone(),
+                    force: true,
+                })),
+            };
+            if let Ok(handle) = tokio::runtime::Handle::try_current() {
+                handle.spawn(async move {
+                    let mut writer = writer.lock().await;
+                    let _ = write_message(&mut *writer, &message).await;
+                });
+            }
+        }
+    }
+    
+    /// Deliver session output to its `AgentSession`.
+    async fn route_session_event(sessions: &SessionMap, session_id: &str, event: SessionEvent, vm_id: &str) {
+        let tx = sessions.lock().unwrap_or_else(|e| e.into_inner()).get(session_id).cloned();
+        let Some(tx) = tx else {
+            debug!(vm_id = %vm_id, session_id = %session_id, "Output for unknown session");
+            return;
+        };
+        
+        if let Err(e) = tx.send_timeout(event, SESSION_SEND_TIMEOUT).await {
+            warn!(vm_id = %vm_id, session_id = %session_id, error = %e, "Session consumer stalled, dropping session");
+            sessions.lock().unwrap_or_else(|e| e.into_inner()).remove(session_id);
+        }
+    }
+
+    /// Handle incoming responses from the agent.
+    /// Takes ownership of the reader half of the split stream.
+    /// 
+    /// This task runs continuously, reading messages from the agent and either:
+    /// - Processing AgentReady/Telemetry/Error events
+    /// - Routing responses to pending requests
+    /// 
+    /// The task exits when:
+    /// - The socket is closed (Ok(None) from read)
+    /// - A read error occurs
+    /// - The socket EOF is reached
+    async fn response_handler(
+        mut reader: ReadHalf<UnixStream>,
+        pending: Arc<Mutex<HashMap<String, PendingRequest>>>,
+        sessions: SessionMap,
+        streams: StreamMap,
+        telemetry_tx: Option<mpsc::Sender<TelemetryReport>>,
+        agent_ready_tx: Option<mpsc::Sender<AgentReadyEvent>>,
+        vm_id: String,
+    ) -> Result<()> {
+        info!(vm_id = %vm_id, "Response handler started - now listening for messages from guest agent");
+        
+        let mut message_count: u64 = 0;
+        
+        loop {
+            // Read directly from the reader - no mutex needed since we own this half
+            debug!(vm_id = %vm_id, message_count = message_count, "Waiting for next message from agent");
+            
+            let message = match read_message::<_, AgentMessage>(&mut reader).await {
+                Ok(Some(msg)) => {
+                    message_count += 1;
+                    debug!(vm_id = %vm_id, message_count = message_count, "Read message successfully");
+                    msg
+                }
+                Ok(None) => {
+                    info!(vm_id = %vm_id, message_count = message_count, 
+                          "Agent connection closed by peer (EOF) after {} messages", message_count);
+                    break;
+                }
+                Err(e) => {
+                    error!(vm_id = %vm_id, message_count = message_count, error = %e, 
+                           "Failed to read message from agent - handler exiting");
+                    return Err(e);
+                }
+            };
+
+            let message_id = message.message_id.clone();
+            debug!(vm_id = %vm_id, message_id = %message_id, "Received message from agent");
+
+            match &message.payload {
+                Some(agent_message::Payload::Telemetry(report)) => {
+                    debug!(vm_id = %vm_id, "Received telemetry report");
+                    if let Some(ref tx) = telemetry_tx {
+                        let _ = tx.send(report.clone()).await;
+                    }
+                    continue;
+                }
+                Some(agent_message::Payload::AgentReady(ready)) => {
+                    info!(
+                        vm_id = %vm_id,
+                        version = %ready.version,
+                        hostname = %ready.hostname,
+                        os = %ready.os_name,
+                        kernel = %ready.kernel_version,
+                        ips = ?ready.ip_addresses,
+                        "Agent ready event received"
+                    );
+                    // Forward to service to update agent cache
+                    if let Some(ref tx) = agent_ready_tx {
+                        if let Err(e) = tx.send(ready.clone()).await {
+                            warn!(vm_id = %vm_id, error = %e, "Failed to forward AgentReadyEvent");
+                        }
+                    }
+                    continue;
+                }
+                Some(agent_message::Payload::SessionOutput(output)) => {
+                    route_session_event(&sessions, &output.session_id, SessionEvent::Output(output.clone()), &vm_id).await;
+                    continue;
+                }
+                Some(agent_message::Payload::SessionExited(exited)) => {
+                    debug!(vm_id = %vm_id, session_id = %exited.session_id, exit_code = exited.exit_code, "Agent session exited");
+                    route_session_event(&sessions, &exited.session_id, SessionEvent::Exited(exited.clone()), &vm_id).await;
+                    continue;
+                }
+                Some(agent_message::Payload::StreamData(frame)) => {
+                    let routes = streams.lock().unwrap_or_else(|e| e.into_inner());
+                    match routes.get(&frame.stream_id) {
+                        Some(route) => {
+                            if !frame.data.is_empty() {
+                                let _ = route.events.send(StreamEvent::Data(frame.data.clone()));
+                            }
+                            if frame.eof {
+                                let _ = route.events.send(StreamEvent::Eof);
+                            }
+                        }
+                        None => debug!(vm_id = %vm_id, stream_id = %frame.stream_id, "Data for unknown stream"),
+                    }
+                    continue;
+                }
+                Some(agent_message::Payload::StreamWindow(update)) => {
+                    if let Some(route) = streams.lock().unwrap_or_else(|e| e.into_inner()).get(&update.stream_id) {
+                        route.credit.add_permits(update.increment as usize);
+                    }
+                    continue;
+                }
+                Some(agent_message::Payload::StreamClose(frame)) => {
+                    debug!(vm_id = %vm_id, stream_id = %frame.stream_id, error = %frame.error, "Agent stream closed by guest");
+                    if let Some(route) = streams.lock().unwrap_or_else(|e| e.into_inner()).remove(&frame.stream_id) {
+                        route.credit.close();
+                        let _ = route.events.send(StreamEvent::Closed(frame.error.clone()));
+                    }
+                    continue;
+                }
+                Some(agent_message::Payload::Error(err)) => {
+                    error!(
+                        vm_id = %vm_id,
+                        code = %err.code,
+                        message = %err.message,
+                        "Agent error"
+                    );
+                    continue;
+                }
+                Some(agent_message::Payload::Pong(pong)) => {
+                    debug!(
+                        vm_id = %vm_id,
+                        message_id = %message_id,
+                        version = %pong.version,
+                        "Received pong response"
+                    );
+                }
+                _ => {
+                    debug!(vm_id = %vm_id, message_id = %message_id, "Received response message");
+                }
+            }
+
+            // Route response to waiting request
+            let pending_req = {
+                let mut pending = pending.lock().await;
+                pending.remove(&message_id)
+            };
+
+            if let Some(req) = pending_req {
+                debug!(vm_id = %vm_id, message_id = %message_id, "Routing response to pending request");
+                let _ = req.response_tx.send(message);
+            } else {
+                debug!(vm_id = %vm_id, message_id = %message_id, "No pending request for message");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Magic header for protocol framing: "QTX1" (Quantix Protocol v1)
+    /// This allows the reader to resync if it receives garbage/stale data.
+    const MAGIC_HEADER: [u8; 4] = [0x51, 0x54, 0x58, 0x01]; // Q=0x51, T=0x54, X=0x58, 1=0x01
+    
+    /// Maximum bytes to scan when resyncing before giving up
+    const MAX_RESYNC_BYTES: usize = 64 * 1024; // 64KB
+
+    /// Read a magic-header-prefixed protobuf message from any async reader.
+    /// 
+    /// The protocol format is:
+    /// - 4 bytes: Magic header "QTX1" (0x51 0x54 0x58 0x01)
+    /// - 4 bytes: Message length (big-endian u32)
+    /// - N bytes: Protobuf payload
+    ///
+    /// If garbage data is encountered, this function will scan for the magic header,
+    /// discarding invalid bytes until a valid message is found. This prevents the
+    /// deadlock scenario where:
+    /// 1. Host connects after guest sent data
+    /// 2. Host reads garbage as a huge "length" (e.g., 50,000)
+    /// 3. Host blocks forever waiting for 50,000 bytes
+    /// 4. Guest's buffer fills up and writes timeout
+    async fn read_message<R, M>(reader: &mut R) -> Result<Option<M>>
+    where
+        R: AsyncReadExt + Unpin,
+        M: Message + Default,
+    {
+        // Track total bytes scanned across ALL resync attempts in this function call
+        let mut total_resync_bytes: usize = 0;
+        
+        loop {
+            // Reset per-iteration resync counter (CRITICAL FIX: was not being reset before)
+            let mut resync_bytes_this_iteration: usize = 0;
+            
+            // 1. Scan for Magic Header (Resync Mechanism)
+            let mut header_byte = [0u8; 1];
+            let mut match_count: usize = 0;
+
+            while match_count < 4 {
+                match reader.read(&mut header_byte).await {
+                    Ok(0) => {
+                        // EOF
+                        if total_resync_bytes > 0 {
+                            debug!(total_bytes_scanned = total_resync_bytes, "EOF reached during resync");
+                        }
+                        return Ok(None);
+                    }
+                    Ok(_) => {}
+                    Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                        return Ok(None);
+                    }
+                    Err(e) => {
+                        return Err(e).context("Failed to read magic header");
+                    }
+                }
+
+                // Count EVERY byte we read (CRITICAL FIX: was only counting mismatches)
+                resync_bytes_this_iteration += 1;
+
+                if header_byte[0] == MAGIC_HEADER[match_count] {
+                    match_count += 1;
+                } else {
+                    // Mismatch - log progress periodically
                     if resync_bytes_this_iteration > 0 && resync_bytes_this_iteration % 1000 == 0 {
                         warn!(
                             bytes_scanned_this_attempt = resync_bytes_this_iteration,
@@ -1078,6 +2077,48 @@ mod unix_impl {
         }
     }
 
+    /// Connection counters of a port forward.
+    #[derive(Default)]
+    struct ForwardStats {
+        active: AtomicU32,
+        total: AtomicU64,
+        bytes_to_guest: AtomicU64,
+        bytes_from_guest: AtomicU64,
+    }
+
+    /// A running port forward. Dropping it stops the listener and all of
+    /// its connections.
+    struct PortForward {
+        info: PortForwardInfo,
+        stats: Arc<ForwardStats>,
+        task: tokio::task::JoinHandle<()>,
+    }
+    
+    impl PortForward {
+        fn snapshot(&self) -> PortForwardInfo {
+            let mut info = self.info.clone();
+            info.active_connections = self.stats.active.load(Ordering::Relaxed);
+            info.total_connections = self.stats.total.load(Ordering::Relaxed);
+            info.bytes_to_guest = self.stats.bytes_to_guest.load(Ordering::Relaxed);
+            info.bytes_from_guest = self.stats.bytes_from_guest.load(Ordering::Relaxed);
+            info
+        }
+    }
+    
+    impl Drop for PortForward {
+        fn drop(&mut self) {
+            self.task.abort();
+            if self.info.listen.starts_with('/') {
+                let _ = std::fs::remove_file(&self.info.listen);
+            }
+        }
+    }
+    
+    enum ForwardListener {
+        Tcp(TcpListener),
+        Unix(UnixListener),
+    }
+
     /// Manager for tracking agent connections across multiple VMs.
     pub struct AgentManager {
         clients: Mutex<HashMap<String, AgentClient>>,
@@ -1168,6 +2209,330 @@ mod unix_impl {
             Self::new().0
         }
     }
+
+    /// Port forwards from node-local listeners into VMs.
+    pub struct PortForwardRegistry {
+        socket_dir: PathBuf,
+        forwards: Mutex<HashMap<String, PortForward>>,
+    }
+
+    impl PortForwardRegistry {
+        /// Registry whose Unix socket listeners live in `socket_dir`.
+        pub fn new(socket_dir: impl Into<PathBuf>) -> Self {
+            Self {
+                socket_dir: socket_dir.into(),
+                forwards: Mutex::new(HashMap::new()),
+            }
+        }
+
+        /// Start forwarding a node-local listener to a port inside a VM.
+        ///
+        /// Every accepted connection becomes its own stream on the VM's agent
+        /// channel, resolved through `source` at accept time.
+        pub async fn add_port_forward(
+            &self,
+            vm_id: &str,
+            spec: PortForwardSpec,
+            source: StreamOpenerSource,
+        ) -> Result<PortForwardInfo> {
+            let listen = spec.validate(&self.socket_dir)?;
+
+            let mut forwards = self.forwards.lock().await;
+            if forwards.values().filter(|f| f.info.vm_id == vm_id).count() >= MAX_PORT_FORWARDS_PER_VM {
+                return Err(anyhow!("Too many port forwards for VM (maximum {})", MAX_PORT_FORWARDS_PER_VM));
+            }
+
+            let (listener, listen) = match listen {
+                ForwardListen::Unix(path) => {
+                    std::fs::create_dir_all(&self.socket_dir)
+                        .with_context(|| format!("Failed to create {}", self.socket_dir.display()))?;
+                    std::fs::set_permissions(&self.socket_dir, std::os::unix::fs::PermissionsExt::from_mode(0o700))
+                        .with_context(|| format!("Failed to restrict {}", self.socket_dir.display()))?;
+                    if path.symlink_metadata().is_ok() {
+                        return Err(anyhow!("Socket path already exists: {}", path.display()));
+                    }
+                    let listener = UnixListener::bind(&path)
+                        .with_context(|| format!("Failed to listen on {}", path.display()))?;
+                    (ForwardListener::Unix(listener), path.to_string_lossy().into_owned())
+                }
+                ForwardListen::Tcp(addr) => {
+                    let listener = TcpListener::bind(addr)
+                        .await
+                        .with_context(|| format!("Failed to listen on {}", addr))?;
+                    let local = listener.local_addr()?;
+                    (ForwardListener::Tcp(listener), local.to_string())
+                }
+            };
+
+            let info = PortForwardInfo {
+                id: Uuid::new_v4().to_string(),
+                vm_id: vm_id.to_string(),
+                listen,
+                target_host: spec.target_host,
+                target_port: spec.target_port,
+                active_connections: 0,
+                total_connections: 0,
+                bytes_to_guest: 0,
+                bytes_from_guest: 0,
+                created_at: chrono::Utc::now(),
+            };
+            let stats = Arc::new(ForwardStats::default());
+            let task = tokio::spawn(run_port_forward(listener, info.clone(), source, stats.clone()));
+
+            info!(
+                vm_id = %vm_id,
+                forward_id = %info.id,
+                listen = %info.listen,
+                target = %format!("{}:{}", info.target_host, info.target_port),
+                "Port forward started"
+            );
+
+            forwards.insert(info.id.clone(), PortForward { info: info.clone(), stats, task });
+            Ok(info)
+        }
+
+        /// List port forwards, optionally only those of one VM.
+        pub async fn list_port_forwards(&self, vm_id: Option<&str>) -> Vec<PortForwardInfo> {
+            let forwards = self.forwards.lock().await;
+            let mut list: Vec<PortForwardInfo> = forwards
+                .values()
+                .filter(|f| vm_id.is_none_or(|id| f.info.vm_id == id))
+                .map(PortForward::snapshot)
+                .collect();
+            list.sort_by_key(|f| f.created_at);
+            list
+        }
+
+        /// Stop a port forward and close its connections.
+        pub async fn remove_port_forward(&self, forward_id: &str) -> Option<PortForwardInfo> {
+            let forward = self.forwards.lock().await.remove(forward_id)?;
+            info!(vm_id = %forward.info.vm_id, forward_id = %forward_id, "Port forward stopped");
+            Some(forward.snapshot())
+        }
+
+        /// Stop all port forwards of a VM (VM deleted).
+        pub async fn remove_port_forwards(&self, vm_id: &str) -> usize {
+            let mut forwards = self.forwards.lock().await;
+            let before = forwards.len();
+            forwards.retain(|_, f| f.info.vm_id != vm_id);
+            before - forwards.len()
+        }
+    }
+
+    /// Accept loop of a port forward. Connections run in a JoinSet so they
+    /// end with the forward.
+    async fn run_port_forward(
+        listener: ForwardListener,
+        info: PortForwardInfo,
+        source: StreamOpenerSource,
+        stats: Arc<ForwardStats>,
+    ) {
+        let mut connections = tokio::task::JoinSet::new();
+        loop {
+            tokio::select! {
+                accepted = async {
+                    match &listener {
+                        ForwardListener::Tcp(l) => l.accept().await.map(|(s, peer)| {
+                            let _ = s.set_nodelay(true);
+                            (ForwardSocket::Tcp(s), peer.to_string())
+                        }),
+                        ForwardListener::Unix(l) => l.accept().await.map(|(s, _)| (ForwardSocket::Unix(s), "unix".to_string())),
+                    }
+                } => {
+                    match accepted {
+                        Ok((socket, peer)) => {
+                            debug!(forward_id = %info.id, peer = %peer, "Port forward connection accepted");
+                            let source = source.clone();
+                            let stats = stats.clone();
+                            let info = info.clone();
+                            connections.spawn(async move {
+                                match socket {
+                                    ForwardSocket::Tcp(s) => forward_connection(s, &info, source, &stats).await,
+                                    ForwardSocket::Unix(s) => forward_connection(s, &info, source, &stats).await,
+                                }
+                            });
+                        }
+                        Err(e) => {
+                            warn!(forward_id = %info.id, error = %e, "Port forward accept failed");
+                            tokio::time::sleep(Duration::from_millis(100)).await;
+                        }
+                    }
+                }
+                Some(_) = connections.join_next(), if !connections.is_empty() => {}
+            }
+        }
+    }
+    
+    /// Socket accepted by a port forward.
+    enum ForwardSocket {
+        Tcp(tokio::net::TcpStream),
+        Unix(UnixStream),
+    }
+
+    /// Carry one accepted connection to the guest.
+    async fn forward_connection<S>(socket: S, info: &PortForwardInfo, source: StreamOpenerSource, stats: &ForwardStats)
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        stats.active.fetch_add(1, Ordering::Relaxed);
+        stats.total.fetch_add(1, Ordering::Relaxed);
+
+        let result = async {
+            let opener = source().await?;
+            let stream = opener.open(&info.target_host, info.target_port).await?;
+            stream.forward(socket).await
+        }
+        .await;
+
+        match result {
+            Ok((sent, received)) => {
+                stats.bytes_to_guest.fetch_add(sent, Ordering::Relaxed);
+                stats.bytes_from_guest.fetch_add(received, Ordering::Relaxed);
+            }
+            Err(e) => {
+                debug!(vm_id = %info.vm_id, forward_id = %info.id, error = %e, "Port forward connection failed");
+            }
+        }
+        stats.active.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    impl Default for PortForwardRegistry {
+        fn default() -> Self {
+            Self::new(PORT_FORWARD_SOCKET_DIR)
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use limiquantix_proto::agent::OpenStreamResponse;
+
+        /// Minimal guest: accepts one stream and echoes its data.
+        async fn echo_guest(guest: UnixStream) {
+            let (mut rd, mut wr) = tokio::io::split(guest);
+            while let Ok(Some(msg)) = read_message::<_, AgentMessage>(&mut rd).await {
+                let reply = |payload| AgentMessage {
+                    message_id: msg.message_id.clone(),
+                    timestamp: None,
+                    payload: Some(payload),
+                };
+                let replies = match msg.payload.clone() {
+                    Some(agent_message::Payload::OpenStream(req)) => vec![reply(
+                        agent_message::Payload::OpenStreamResponse(OpenStreamResponse {
+                            success: true,
+                            stream_id: req.stream_id,
+                            error: String::new(),
+                            initial_window: 8,
+                        }),
+                    )],
+                    Some(agent_message::Payload::StreamData(frame)) => vec![
+                        reply(agent_message::Payload::StreamWindow(StreamWindowUpdate {
+                            stream_id: frame.stream_id.clone(),
+                            increment: frame.data.len() as u32,
+                        })),
+                        reply(agent_message::Payload::StreamData(frame)),
+                    ],
+                    _ => vec![],
+                };
+                for r in replies {
+                    write_message(&mut wr, &r).await.unwrap();
+                }
+            }
+        }
+
+        #[tokio::test]
+        async fn test_port_forward_echo() {
+            let (host, guest) = UnixStream::pair().unwrap();
+            tokio::spawn(echo_guest(guest));
+            let mut client = AgentClient::new("test-vm");
+            client.setup_connection(host).await.unwrap();
+
+            let manager = PortForwardRegistry::default();
+            let opener = client.stream_opener().unwrap();
+            let source: StreamOpenerSource = Arc::new(move || {
+                let opener = opener.clone();
+                Box::pin(async move { Ok(opener) })
+            });
+            let spec = PortForwardSpec {
+                listen: "127.0.0.1:0".to_string(),
+                target_host: "127.0.0.1".to_string(),
+                target_port: 22,
+            };
+            let forward = manager.add_port_forward("test-vm", spec, source).await.unwrap();
+
+            // More data than the 8-byte window, so credit must be replenished
+            let payload: Vec<u8> = (0..100u8).collect();
+            let mut conn = tokio::net::TcpStream::connect(&forward.listen).await.unwrap();
+            conn.write_all(&payload).await.unwrap();
+            conn.shutdown().await.unwrap();
+            let mut echoed = Vec::new();
+            tokio::time::timeout(Duration::from_secs(5), conn.read_to_end(&mut echoed))
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(echoed, payload);
+
+            assert_eq!(manager.list_port_forwards(Some("test-vm")).await.len(), 1);
+            assert!(manager.remove_port_forward(&forward.id).await.is_some());
+            assert!(manager.list_port_forwards(None).await.is_empty());
+        }
+
+        #[test]
+        fn test_port_forward_spec_is_local() {
+            let dir = std::path::Path::new(PORT_FORWARD_SOCKET_DIR);
+            let spec = |listen: &str, target: &str| PortForwardSpec {
+                listen: listen.to_string(),
+                target_host: target.to_string(),
+                target_port: 22,
+            };
+
+            assert_eq!(
+                spec("[::1]:2222", "localhost").validate(dir).unwrap(),
+                ForwardListen::Tcp("[::1]:2222".parse().unwrap()),
+            );
+            assert!(spec("0.0.0.0:2222", "127.0.0.1").validate(dir).is_err());
+            assert!(spec("127.0.0.1:0", "10.0.0.5").validate(dir).is_err());
+
+            let socket = format!("{}/ssh.sock", PORT_FORWARD_SOCKET_DIR);
+            assert!(spec(&socket, "127.0.0.1").validate(dir).is_ok());
+            assert!(spec("/etc/ssh.sock", "127.0.0.1").validate(dir).is_err());
+            assert!(spec(&format!("{}/../x.sock", PORT_FORWARD_SOCKET_DIR), "127.0.0.1").validate(dir).is_err());
+        }
+
+        #[tokio::test]
+        async fn test_missing_capability_is_unsupported() {
+            let (host, guest) = UnixStream::pair().unwrap();
+            // Old agent: reports capabilities but cannot control services
+            tokio::spawn(async move {
+                let (mut rd, mut wr) = tokio::io::split(guest);
+                while let Ok(Some(msg)) = read_message::<_, AgentMessage>(&mut rd).await {
+                    if let Some(agent_message::Payload::GetCapabilities(_)) = msg.payload {
+                        let reply = AgentMessage {
+                            message_id: msg.message_id,
+                            timestamp: None,
+                            payload: Some(agent_message::Payload::GetCapabilitiesResponse(GetCapabilitiesResponse {
+                                version: "0.1.0".to_string(),
+                                capabilities: vec!["execute".to_string(), "service_list".to_string()],
+                                ..Default::default()
+                            })),
+                        };
+                        write_message(&mut wr, &reply).await.unwrap();
+                    }
+                }
+            });
+            let mut client = AgentClient::new("test-vm");
+            client.setup_connection(host).await.unwrap();
+
+            client.require_capability("service_list").await.unwrap();
+            let err = client
+                .service_control(ServiceControlRequest { name: "spooler".to_string(), action: 2 })
+                .await
+                .unwrap_err();
+            let unsupported = err.downcast_ref::<UnsupportedCapability>().expect("unsupported capability error");
+            assert_eq!(unsupported.capability, "service_control");
+            assert_eq!(unsupported.agent_version, "0.1.0");
+        }
+    }
 }
 
 // ============================================================================
@@ -1184,6 +2549,7 @@ mod stub_impl {
     use tracing::warn;
 
     /// Guest Agent Client stub for non-Unix platforms.
+    #[derive(Clone)]
     pub struct AgentClient {
         vm_id: String,
         socket_path: PathBuf,
@@ -1255,6 +2621,7 @@ mod stub_impl {
             _mount_points: Vec<String>,
             _timeout_seconds: u32,
             _run_pre_freeze_scripts: bool,
+            _skip_applications: bool,
         ) -> Result<QuiesceFilesystemsResponse> {
             Err(anyhow!("Guest agent not supported on this platform"))
         }
@@ -1270,6 +2637,180 @@ mod stub_impl {
         pub async fn sync_time(&self, _force: bool) -> Result<SyncTimeResponse> {
             Err(anyhow!("Guest agent not supported on this platform"))
         }
+
+        pub async fn open_session(&self, _req: OpenSessionRequest) -> Result<AgentSession> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn get_capabilities(&self) -> Result<GetCapabilitiesResponse> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn require_capability(&self, _capability: &str) -> Result<()> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn list_processes(&self, _req: ListProcessesRequest) -> Result<ListProcessesResponse> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn kill_process(&self, _pid: u32, _signal: i32) -> Result<()> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn list_services(&self, _req: ListServicesRequest) -> Result<ListServicesResponse> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn service_control(&self, _req: ServiceControlRequest) -> Result<ServiceControlResponse> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn get_hardware_info(&self, _req: GetHardwareInfoRequest) -> Result<GetHardwareInfoResponse> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn list_installed_software(&self, _req: ListInstalledSoftwareRequest) -> Result<ListInstalledSoftwareResponse> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn reset_password(&self, _req: ResetPasswordRequest) -> Result<()> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn list_users(&self, _req: ListUsersRequest) -> Result<ListUsersResponse> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn user_account(&self, _req: UserAccountRequest) -> Result<UserAccountResponse> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn list_login_sessions(&self, _req: ListLoginSessionsRequest) -> Result<ListLoginSessionsResponse> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn list_updates(&self, _req: ListUpdatesRequest) -> Result<ListUpdatesResponse> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn apply_updates(&self, _req: ApplyUpdatesRequest) -> Result<ApplyUpdatesResponse> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn configure_network(&self, _req: ConfigureNetworkRequest) -> Result<()> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn clipboard_get(&self, _req: ClipboardGetRequest) -> Result<ClipboardGetResponse> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn clipboard_update(&self, _req: ClipboardUpdateRequest) -> Result<()> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn display_resize(&self, _req: DisplayResizeRequest) -> Result<DisplayResizeResponse> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn create_directory(&self, _req: CreateDirectoryRequest) -> Result<()> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn delete_file(&self, _req: FileDeleteRequest) -> Result<()> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn stat_file(&self, _path: &str) -> Result<FileStatResponse> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn tree_upload(&self, _req: TreeUploadRequest) -> Result<TreeUploadResponse> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn tree_download(&self, _req: TreeDownloadRequest) -> Result<TreeDownloadResponse> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn grow_filesystems(&self, _req: GrowFilesystemsRequest) -> Result<GrowFilesystemsResponse> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn trim_filesystems(&self, _req: TrimFilesystemsRequest) -> Result<TrimFilesystemsResponse> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn update_agent(&self, _binary: &[u8], _signature: &[u8], _target_version: &str) -> Result<AgentUpdateResponse> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub fn stream_opener(&self) -> Result<AgentStreamOpener> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn open_stream(&self, _target_host: &str, _target_port: u16) -> Result<AgentStream> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+    }
+
+    /// Stream opener stub for non-Unix platforms.
+    #[derive(Clone)]
+    pub struct AgentStreamOpener {
+        _marker: std::marker::PhantomData<()>,
+    }
+
+    impl AgentStreamOpener {
+        pub fn is_connected(&self) -> bool {
+            false
+        }
+
+        pub async fn open(&self, _target_host: &str, _target_port: u16) -> Result<AgentStream> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+    }
+
+    /// Multiplexed stream stub for non-Unix platforms.
+    pub struct AgentStream {
+        stream_id: String,
+    }
+
+    impl AgentStream {
+        pub fn id(&self) -> &str {
+            &self.stream_id
+        }
+
+        pub async fn forward<S>(self, _socket: S) -> Result<(u64, u64)> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+    }
+
+    /// Interactive session stub for non-Unix platforms.
+    pub struct AgentSession {
+        session_id: String,
+    }
+
+    impl AgentSession {
+        pub fn id(&self) -> &str {
+            &self.session_id
+        }
+
+        pub async fn recv(&mut self) -> Option<SessionEvent> {
+            None
+        }
+
+        pub async fn send_input(&self, _data: Vec<u8>, _eof: bool) -> Result<()> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn resize(&self, _cols: u32, _rows: u32) -> Result<()> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn close(&self, _force: bool) -> Result<()> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
     }
 
     /// Agent Manager stub for non-Unix platforms.
@@ -1314,6 +2855,46 @@ mod stub_impl {
             Self::new().0
         }
     }
+
+    /// Port forward registry stub for non-Unix platforms.
+    pub struct PortForwardRegistry {
+        _marker: std::marker::PhantomData<()>,
+    }
+
+    impl PortForwardRegistry {
+        pub fn new(_socket_dir: impl Into<PathBuf>) -> Self {
+            Self {
+                _marker: std::marker::PhantomData,
+            }
+        }
+
+        pub async fn add_port_forward(
+            &self,
+            _vm_id: &str,
+            _spec: PortForwardSpec,
+            _source: StreamOpenerSource,
+        ) -> Result<PortForwardInfo> {
+            Err(anyhow!("Guest agent not supported on this platform"))
+        }
+
+        pub async fn list_port_forwards(&self, _vm_id: Option<&str>) -> Vec<PortForwardInfo> {
+            Vec::new()
+        }
+
+        pub async fn remove_port_forward(&self, _forward_id: &str) -> Option<PortForwardInfo> {
+            None
+        }
+
+        pub async fn remove_port_forwards(&self, _vm_id: &str) -> usize {
+            0
+        }
+    }
+
+    impl Default for PortForwardRegistry {
+        fn default() -> Self {
+            Self::new(PORT_FORWARD_SOCKET_DIR)
+        }
+    }
 }
 
 // ============================================================================
@@ -1321,7 +2902,7 @@ mod stub_impl {
 // ============================================================================
 
 #[cfg(unix)]
-pub use unix_impl::{AgentClient, AgentManager};
+pub use unix_impl::{AgentClient, AgentManager, AgentSession, AgentStreamOpener, PortForwardRegistry};
 
 #[cfg(not(unix))]
-pub use stub_impl::{AgentClient, AgentManager};
+pub use stub_impl::{AgentClient, AgentManager, AgentSession, AgentStreamOpener, PortForwardRegistry};
@@ -0,0 +1,435 @@
+//! Packet Capture - bounded tcpdump sessions on VM NICs.
+//!
+//! Lets operators capture a VM's traffic through the API instead of
+//! SSHing to the host as root:
+//! - One capture per NIC at a time, at most `MAX_RUNNING` on the host
+//! - Each capture stops at its time, size or packet limit, or on request;
+//!   tcpdump streams pcap records to the daemon, which writes whole records
+//!   to the file and stops before one would exceed the size limit
+//! - The pcap of the last capture per NIC stays in
+//!   `/var/lib/limiquantix/captures` until the next one (or VM deletion)
+//! - Starting a capture is recorded as a security event, since it exposes
+//!   tenant traffic
+//!
+//! NICs are resolved to taps through libvirt (`virsh domiflist`); the tap of
+//! an OVS-attached NIC is also its OVS port name.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use limiquantix_hypervisor::network::capture::{self, CaptureOptions};
+use serde::Serialize;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::{oneshot, Mutex};
+use tracing::{info, warn};
+
+use crate::event_store::{emit_event, Event, EventCategory, EventLevel};
+
+/// Directory holding capture files
+const CAPTURE_DIR: &str = "/var/lib/limiquantix/captures";
+
+/// Concurrent captures per host
+const MAX_RUNNING: usize = 4;
+
+/// pcap global header size
+const PCAP_HEADER_LEN: usize = 24;
+
+/// pcap per-record header size
+const PCAP_RECORD_HEADER_LEN: usize = 16;
+
+/// Capture lifecycle state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptureState {
+    Running,
+    Completed,
+    Failed,
+}
+
+/// A capture session.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureInfo {
+    pub vm_id: String,
+    /// Tap / OVS port captured on
+    pub interface: String,
+    pub mac_address: String,
+    pub options: CaptureOptions,
+    pub state: CaptureState,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// Size of the pcap file
+    pub bytes: u64,
+    /// Why the capture ended ("time limit", "size limit", "packet limit", "stopped")
+    pub stop_reason: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Errors from the capture manager.
+#[derive(Debug, thiserror::Error)]
+pub enum CaptureError {
+    #[error("{0}")]
+    NotFound(String),
+    
+    #[error("{0}")]
+    Conflict(String),
+    
+    #[error("{0:#}")]
+    Invalid(anyhow::Error),
+    
+    #[error("{0:#}")]
+    Failed(anyhow::Error),
+}
+
+struct CaptureEntry {
+    info: CaptureInfo,
+    stop: Option<oneshot::Sender<()>>,
+}
+
+/// Capture sessions, keyed by (VM ID, interface).
+pub struct CaptureManager {
+    captures: Mutex<HashMap<(String, String), CaptureEntry>>,
+    dir: PathBuf,
+}
+
+impl CaptureManager {
+    fn new(dir: PathBuf) -> Self {
+        Self {
+            captures: Mutex::new(HashMap::new()),
+            dir,
+        }
+    }
+    
+    fn file_path(&self, vm_id: &str, interface: &str) -> PathBuf {
+        self.dir.join(format!("{}-{}.pcap", vm_id, interface))
+    }
+    
+    /// Find the capture key for a NIC given as tap name, MAC or index.
+    async fn find(&self, vm_id: &str, nic: &str) -> Option<(String, String)> {
+        let captures = self.captures.lock().await;
+        captures.iter()
+            .find(|((vm, iface), entry)| {
+                vm == vm_id && (iface == nic || entry.info.mac_address.eq_ignore_ascii_case(nic))
+            })
+            .map(|(key, _)| key.clone())
+    }
+    
+    /// Start a capture on a running VM's NIC.
+    pub async fn start(&'static self, vm_id: &str, nic: &str, options: CaptureOptions) -> Result<CaptureInfo, CaptureError> {
+        options.validate().map_err(CaptureError::Invalid)?;
+        let (interface, mac_address) = resolve_nic(vm_id, nic).await?;
+        let key = (vm_id.to_string(), interface.clone());
+        
+        // Held until the capture is registered, so concurrent starts cannot
+        // both pass the limits
+        let mut captures = self.captures.lock().await;
+        if captures.get(&key).is_some_and(|c| c.info.state == CaptureState::Running) {
+            return Err(CaptureError::Conflict(format!("A capture is already running on {}", interface)));
+        }
+        let running = captures.values().filter(|c| c.info.state == CaptureState::Running).count();
+        if running >= MAX_RUNNING {
+            return Err(CaptureError::Conflict(format!("{} captures are already running on this host", running)));
+        }
+        
+        let check = options.clone();
+        tokio::task::spawn_blocking(move || capture::compile_filter(&check)).await
+            .map_err(|e| CaptureError::Failed(e.into()))?
+            .map_err(CaptureError::Invalid)?;
+        
+        tokio::fs::create_dir_all(&self.dir).await.map_err(|e| CaptureError::Failed(e.into()))?;
+        let path = self.file_path(vm_id, &interface);
+        let file = tokio::fs::File::create(&path).await
+            .map_err(|e| CaptureError::Failed(anyhow::anyhow!("Failed to create {}: {}", path.display(), e)))?;
+        
+        let child = tokio::process::Command::new("tcpdump")
+            .args(capture::tcpdump_args(&interface, &options))
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| CaptureError::Failed(anyhow::anyhow!("Failed to start tcpdump: {}", e)))?;
+        
+        let info = CaptureInfo {
+            vm_id: vm_id.to_string(),
+            interface: interface.clone(),
+            mac_address,
+            options: options.clone(),
+            state: CaptureState::Running,
+            started_at: Utc::now(),
+            finished_at: None,
+            bytes: 0,
+            stop_reason: None,
+            error: None,
+        };
+        let (stop_tx, stop_rx) = oneshot::channel();
+        captures.insert(key.clone(), CaptureEntry {
+            info: info.clone(),
+            stop: Some(stop_tx),
+        });
+        drop(captures);
+        
+        info!(vm_id = %vm_id, interface = %interface, filter = ?options.filter, "Packet capture started");
+        emit_event(Event::new(
+            EventLevel::Info,
+            EventCategory::Security,
+            format!(
+                "Packet capture started on VM {} interface {} (filter: {})",
+                vm_id, interface, options.filter.as_deref().unwrap_or("none")
+            ),
+            "capture",
+        ));
+        
+        tokio::spawn(self.supervise(key, child, file, options, stop_rx));
+        Ok(info)
+    }
+    
+    /// Enforce the limits of a running capture and record how it ended.
+    async fn supervise(
+        &'static self,
+        key: (String, String),
+        mut child: tokio::process::Child,
+        mut file: tokio::fs::File,
+        options: CaptureOptions,
+        mut stop_rx: oneshot::Receiver<()>,
+    ) {
+        let deadline = tokio::time::sleep(Duration::from_secs(options.max_duration_secs.into()));
+        tokio::pin!(deadline);
+        
+        let Some(mut stdout) = child.stdout.take() else {
+            warn!(vm_id = %key.0, interface = %key.1, "tcpdump has no output pipe");
+            return;
+        };
+        let (mut reason, copied) = tokio::select! {
+            copied = copy_pcap(&mut stdout, &mut file, options.max_bytes) => match copied {
+                Ok(true) => (Some("size limit"), None),
+                // End of stream: tcpdump exited on its own
+                Ok(false) => (None, Some(Ok(()))),
+                Err(e) => (None, Some(Err(e))),
+            },
+            _ = &mut deadline => (Some("time limit"), None),
+            _ = &mut stop_rx => (Some("stopped"), None),
+        };
+        drop(stdout);
+        
+        let status = if copied.is_some() {
+            child.wait().await.ok()
+        } else {
+            // SIGINT lets tcpdump exit cleanly
+            if let Some(pid) = child.id() {
+                // SAFETY: signalling our own child process
+                unsafe { libc::kill(pid as libc::pid_t, libc::SIGINT) };
+            }
+            match tokio::time::timeout(Duration::from_secs(5), child.wait()).await {
+                Ok(status) => status.ok(),
+                Err(_) => {
+                    let _ = child.kill().await;
+                    None
+                }
+            }
+        };
+        let _ = file.flush().await;
+        
+        if reason.is_none() && options.max_packets.is_some() && status.is_some_and(|s| s.success()) {
+            reason = Some("packet limit");
+        }
+        
+        let mut stderr = String::new();
+        if let Some(mut pipe) = child.stderr.take() {
+            let _ = pipe.read_to_string(&mut stderr).await;
+        }
+        
+        // tcpdump exits with 0 on SIGINT; anything else before a limit is an error
+        let write_error = match copied {
+            Some(Err(e)) => Some(format!("Failed to write capture file: {}", e)),
+            _ => None,
+        };
+        let failed = write_error.is_some() || (reason.is_none() && !status.is_some_and(|s| s.success()));
+        let error = failed.then(|| {
+            write_error.unwrap_or_else(|| {
+                stderr.lines()
+                    .filter(|l| !l.starts_with("listening on") && !l.contains("packets "))
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            })
+        });
+        
+        let bytes = file_size(&self.file_path(&key.0, &key.1)).await;
+        if let Some(entry) = self.captures.lock().await.get_mut(&key) {
+            entry.info.state = if failed { CaptureState::Failed } else { CaptureState::Completed };
+            entry.info.finished_at = Some(Utc::now());
+            entry.info.bytes = bytes;
+            entry.info.stop_reason = reason.map(str::to_string);
+            entry.info.error = error.clone();
+            entry.stop = None;
+        }
+        
+        match error {
+            Some(e) => warn!(vm_id = %key.0, interface = %key.1, error = %e, "Packet capture failed"),
+            None => info!(vm_id = %key.0, interface = %key.1, bytes = bytes, reason = ?reason, "Packet capture finished"),
+        }
+    }
+    
+    /// State of the last capture on a NIC.
+    pub async fn status(&self, vm_id: &str, nic: &str) -> Result<CaptureInfo, CaptureError> {
+        let key = self.find(vm_id, nic).await
+            .ok_or_else(|| CaptureError::NotFound(format!("No capture for NIC {} of VM {}", nic, vm_id)))?;
+        let mut info = self.captures.lock().await.get(&key)
+            .map(|c| c.info.clone())
+            .ok_or_else(|| CaptureError::NotFound(format!("No capture for NIC {} of VM {}", nic, vm_id)))?;
+        if info.state == CaptureState::Running {
+            info.bytes = file_size(&self.file_path(&key.0, &key.1)).await;
+        }
+        Ok(info)
+    }
+    
+    /// Stop a running capture (the file is kept).
+    pub async fn stop(&self, vm_id: &str, nic: &str) -> Result<CaptureInfo, CaptureError> {
+        let key = self.find(vm_id, nic).await
+            .ok_or_else(|| CaptureError::NotFound(format!("No capture for NIC {} of VM {}", nic, vm_id)))?;
+        let mut captures = self.captures.lock().await;
+        let entry = captures.get_mut(&key)
+            .ok_or_else(|| CaptureError::NotFound(format!("No capture for NIC {} of VM {}", nic, vm_id)))?;
+        match entry.stop.take() {
+            Some(stop) => {
+                let _ = stop.send(());
+                Ok(entry.info.clone())
+            }
+            None => Err(CaptureError::Conflict(format!("Capture on {} is not running", key.1))),
+        }
+    }
+    
+    /// pcap file of the last capture on a NIC (may still be growing).
+    pub async fn file(&self, vm_id: &str, nic: &str) -> Result<(PathBuf, CaptureInfo), CaptureError> {
+        let info = self.status(vm_id, nic).await?;
+        let path = self.file_path(vm_id, &info.interface);
+        if !path.exists() {
+            return Err(CaptureError::NotFound(format!("Capture on {} has no data", info.interface)));
+        }
+        Ok((path, info))
+    }
+    
+    /// Stop captures of a deleted VM and remove their files.
+    pub async fn remove_vm(&self, vm_id: &str) {
+        let mut captures = self.captures.lock().await;
+        let keys: Vec<_> = captures.keys().filter(|(vm, _)| vm == vm_id).cloned().collect();
+        for key in keys {
+            if let Some(mut entry) = captures.remove(&key) {
+                if let Some(stop) = entry.stop.take() {
+                    let _ = stop.send(());
+                }
+            }
+            let _ = tokio::fs::remove_file(self.file_path(&key.0, &key.1)).await;
+        }
+    }
+}
+
+async fn file_size(path: &std::path::Path) -> u64 {
+    tokio::fs::metadata(path).await.map(|m| m.len()).unwrap_or(0)
+}
+
+/// Copy a pcap stream record by record, stopping before a record would
+/// make the output larger than `max_bytes`.
+///
+/// Returns true if the size limit was reached, false at the end of the stream.
+async fn copy_pcap<R, W>(reader: &mut R, writer: &mut W, max_bytes: u64) -> std::io::Result<bool>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut header = [0u8; PCAP_HEADER_LEN];
+    if let Err(e) = reader.read_exact(&mut header).await {
+        return match e.kind() {
+            std::io::ErrorKind::UnexpectedEof => Ok(false),
+            _ => Err(e),
+        };
+    }
+    // Magic 0xa1b2c3d4 (µs) or 0xa1b23c4d (ns) in the writer's byte order
+    let little_endian = header[..4] == [0xd4, 0xc3, 0xb2, 0xa1] || header[..4] == [0x4d, 0x3c, 0xb2, 0xa1];
+    writer.write_all(&header).await?;
+    let mut written = PCAP_HEADER_LEN as u64;
+    
+    let mut record = [0u8; PCAP_RECORD_HEADER_LEN];
+    let mut data = Vec::new();
+    loop {
+        match reader.read_exact(&mut record).await {
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(false),
+            Err(e) => return Err(e),
+        }
+        let len_bytes = [record[8], record[9], record[10], record[11]];
+        let captured = if little_endian { u32::from_le_bytes(len_bytes) } else { u32::from_be_bytes(len_bytes) };
+        
+        let size = (PCAP_RECORD_HEADER_LEN as u64) + u64::from(captured);
+        if written + size > max_bytes {
+            return Ok(true);
+        }
+        
+        data.resize(captured as usize, 0);
+        match reader.read_exact(&mut data).await {
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(false),
+            Err(e) => return Err(e),
+        }
+        writer.write_all(&record).await?;
+        writer.write_all(&data).await?;
+        written += size;
+    }
+}
+
+/// Resolve a NIC (tap name, MAC address or 0-based index) of a running VM to (tap, MAC).
+pub(crate) async fn resolve_nic(vm_id: &str, nic: &str) -> Result<(String, String), CaptureError> {
+    let taps = crate::firewall::vm_taps(vm_id).await;
+    if taps.is_empty() {
+        return Err(CaptureError::NotFound(format!("VM {} is not running or has no network interfaces", vm_id)));
+    }
+    
+    let by_index = nic.parse::<usize>().ok().and_then(|i| taps.get(i));
+    by_index
+        .or_else(|| taps.iter().find(|(tap, mac)| tap == nic || mac.eq_ignore_ascii_case(nic)))
+        .cloned()
+        .ok_or_else(|| CaptureError::NotFound(format!("VM {} has no NIC {}", vm_id, nic)))
+}
+
+/// Global capture manager
+static CAPTURES: std::sync::OnceLock<CaptureManager> = std::sync::OnceLock::new();
+
+/// Get the global capture manager.
+pub fn captures() -> &'static CaptureManager {
+    CAPTURES.get_or_init(|| CaptureManager::new(PathBuf::from(CAPTURE_DIR)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn record(len: u8) -> Vec<u8> {
+        let mut record = vec![0u8; PCAP_RECORD_HEADER_LEN];
+        record[8] = len;
+        record[12] = len;
+        record.extend(std::iter::repeat_n(0xab, len as usize));
+        record
+    }
+    
+    #[tokio::test]
+    async fn test_copy_pcap_stops_at_record_boundary() {
+        let mut stream = vec![0xd4, 0xc3, 0xb2, 0xa1];
+        stream.resize(PCAP_HEADER_LEN, 0);
+        for _ in 0..3 {
+            stream.extend(record(100));
+        }
+        
+        // Room for the header and two records only
+        let mut out = Vec::new();
+        let limited = copy_pcap(&mut stream.as_slice(), &mut out, 24 + 2 * 116 + 50).await.unwrap();
+        assert!(limited);
+        assert_eq!(out.len(), 24 + 2 * 116);
+        
+        let mut out = Vec::new();
+        let limited = copy_pcap(&mut stream.as_slice(), &mut out, 1 << 20).await.unwrap();
+        assert!(!limited);
+        assert_eq!(out, stream);
+    }
+}
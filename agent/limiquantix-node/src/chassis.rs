@@ -4,7 +4,12 @@
 //! - Initial chassis registration with OVN Southbound DB
 //! - Bridge mappings for external networks (VLAN/provider networks)
 //! - Encapsulation configuration (Geneve/VXLAN)
+//! - OVS bonds (redundant uplinks) on the integration and provider bridges,
+//!   saved and re-applied at startup, rolled back if the management address
+//!   or default gateway is lost
 //! - Periodic health checks of OVN controller connectivity
+//! - Overlay health: per-tunnel BFD state, flapping, encapsulation MTU and
+//!   ovn-controller recompute latency, reported as network events
 //!
 //! # Architecture
 //!
@@ -21,13 +26,41 @@
 //! ```
 
 use std::collections::HashMap;
+use std::net::{IpAddr, Ipv6Addr};
+use std::path::{Path, PathBuf};
 use std::process::Command;
-// Duration used for timeout configurations (currently not needed but kept for future use)
+use std::sync::RwLock;
+use std::time::Duration;
 use anyhow::{Context, Result, bail};
 use chrono::{DateTime, Utc};
-use tracing::{info, warn, debug, instrument};
+use tracing::{info, warn, debug, error, instrument};
 use serde::{Deserialize, Serialize};
 
+use crate::event_store::{emit_event, Event, EventCategory, EventLevel};
+
+/// Saved OVS bonds on Quantix-OS (persistent config partition)
+const BONDS_PERSISTENT_PATH: &str = "/quantix/network/ovs-bonds.json";
+
+/// Saved OVS bonds on other distributions
+const BONDS_FALLBACK_PATH: &str = "/etc/limiquantix/ovs-bonds.json";
+
+/// Per-packet overhead of Geneve over IPv4 as configured by OVN
+/// (outer IPv4 + UDP + Geneve header with OVN's option, plus inner Ethernet).
+const GENEVE_OVERHEAD: u32 = 58;
+/// Per-packet overhead of VXLAN over IPv4 (incl. inner Ethernet).
+const VXLAN_OVERHEAD: u32 = 50;
+/// Extra outer-header bytes when tunnelling over IPv6 (40-byte header instead of 20).
+const IPV6_EXTRA_OVERHEAD: u32 = 20;
+
+/// BFD flaps between two health checks that count as flapping.
+const FLAP_THRESHOLD: u64 = 3;
+
+/// ovn-controller flow recompute time (ms) considered slow.
+const SLOW_RECOMPUTE_MS: f64 = 1000.0;
+
+/// How often the health monitor runs.
+const HEALTH_INTERVAL: Duration = Duration::from_secs(30);
+
 /// OVN encapsulation type.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -56,6 +89,113 @@ impl std::fmt::Display for EncapType {
     }
 }
 
+/// OVS bond mode.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OvsBondMode {
+    /// One member carries traffic, the others take over on failure
+    #[default]
+    ActiveBackup,
+    /// Balance by source MAC and VLAN (no switch configuration needed)
+    BalanceSlb,
+    /// Balance by L3/L4 headers (requires LACP)
+    BalanceTcp,
+}
+
+impl std::fmt::Display for OvsBondMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OvsBondMode::ActiveBackup => write!(f, "active-backup"),
+            OvsBondMode::BalanceSlb => write!(f, "balance-slb"),
+            OvsBondMode::BalanceTcp => write!(f, "balance-tcp"),
+        }
+    }
+}
+
+/// LACP negotiation for an OVS bond.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LacpMode {
+    /// No LACP (static bond)
+    #[default]
+    Off,
+    /// Send LACPDUs
+    Active,
+    /// Only answer LACPDUs
+    Passive,
+}
+
+impl std::fmt::Display for LacpMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LacpMode::Off => write!(f, "off"),
+            LacpMode::Active => write!(f, "active"),
+            LacpMode::Passive => write!(f, "passive"),
+        }
+    }
+}
+
+/// An OVS bond port on the integration bridge or a provider bridge.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OvsBondConfig {
+    /// Bond port name (e.g. "bond-ex")
+    pub name: String,
+
+    /// Bridge the bond is attached to
+    pub bridge: String,
+
+    /// Member NICs (at least two)
+    pub members: Vec<String>,
+
+    #[serde(default)]
+    pub mode: OvsBondMode,
+
+    #[serde(default)]
+    pub lacp: LacpMode,
+
+    /// Send LACPDUs every second instead of every 30 seconds
+    #[serde(default)]
+    pub lacp_fast: bool,
+
+    /// Link monitoring interval in milliseconds (None = carrier detection)
+    #[serde(default)]
+    pub miimon: Option<u32>,
+}
+
+impl OvsBondConfig {
+    /// Check the bond settings.
+    pub fn validate(&self) -> Result<()> {
+        if self.members.len() < 2 {
+            bail!("OVS bond {} needs at least two members", self.name);
+        }
+        if self.mode == OvsBondMode::BalanceTcp && self.lacp == LacpMode::Off {
+            bail!("OVS bond {} uses balance-tcp, which requires LACP", self.name);
+        }
+        if self.lacp_fast && self.lacp == LacpMode::Off {
+            bail!("OVS bond {} sets a fast LACP rate without LACP", self.name);
+        }
+        Ok(())
+    }
+
+    /// Port column settings for `ovs-vsctl add-bond` / `set Port`.
+    fn port_settings(&self) -> Vec<String> {
+        let mut settings = vec![
+            format!("bond_mode={}", self.mode),
+            format!("lacp={}", self.lacp),
+            format!("other_config:lacp-time={}", if self.lacp_fast { "fast" } else { "slow" }),
+        ];
+        match self.miimon {
+            Some(interval) => {
+                settings.push("other_config:bond-detect-mode=miimon".to_string());
+                settings.push(format!("other_config:bond-miimon-interval={}", interval));
+            }
+            None => settings.push("other_config:bond-detect-mode=carrier".to_string()),
+        }
+        settings
+    }
+}
+
 /// Configuration for the chassis manager.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChassisConfig {
@@ -83,6 +223,10 @@ pub struct ChassisConfig {
     #[serde(default = "default_integration_bridge")]
     pub integration_bridge: String,
     
+    /// OVS bonds (uplinks) on the integration or provider bridges
+    #[serde(default)]
+    pub bonds: Vec<OvsBondConfig>,
+    
     /// Enable DPDK for high-performance networking
     #[serde(default)]
     pub enable_dpdk: bool,
@@ -105,6 +249,7 @@ impl Default for ChassisConfig {
             ovn_nb_address: None,
             bridge_mappings: HashMap::new(),
             integration_bridge: default_integration_bridge(),
+            bonds: Vec::new(),
             enable_dpdk: false,
             hostname: None,
         }
@@ -141,10 +286,66 @@ pub struct ChassisHealth {
     /// Bridge mappings configured
     pub bridge_mappings: Vec<String>,
     
+    /// Overlay tunnels to other chassis
+    #[serde(default)]
+    pub tunnels: Vec<TunnelHealth>,
+    
+    /// MTU of the interface carrying the encapsulation IP
+    #[serde(default)]
+    pub underlay_mtu: Option<u32>,
+    
+    /// Largest MTU of a VM port on the integration bridge
+    #[serde(default)]
+    pub max_tenant_mtu: Option<u32>,
+    
+    /// VM ports have a larger MTU than the underlay can carry after encapsulation
+    #[serde(default)]
+    pub mtu_mismatch: bool,
+    
+    /// Average ovn-controller flow recompute time (ms, short-term)
+    #[serde(default)]
+    pub recompute_avg_ms: Option<f64>,
+    
+    /// Maximum ovn-controller flow recompute time (ms)
+    #[serde(default)]
+    pub recompute_max_ms: Option<f64>,
+    
     /// Last health check time
     pub last_check: DateTime<Utc>,
 }
 
+/// Health of one overlay tunnel (OVS tunnel interface on br-int).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TunnelHealth {
+    /// Tunnel port name (e.g. "ovn-node2-0")
+    pub name: String,
+    /// geneve, vxlan or stt
+    pub encap_type: String,
+    /// Remote chassis name
+    pub remote_chassis: String,
+    /// Remote encapsulation IP
+    pub remote_ip: String,
+    /// Whether BFD runs on this tunnel (OVN enables it for HA chassis groups)
+    pub bfd_enabled: bool,
+    /// BFD session state: up, down, init, admin_down
+    pub bfd_state: Option<String>,
+    /// BFD considers the tunnel usable
+    pub bfd_forwarding: Option<bool>,
+    /// BFD flap counter since the session was created
+    pub flap_count: u64,
+    /// Interface link state
+    pub link_state: String,
+}
+
+impl TunnelHealth {
+    /// Whether the tunnel is known to be broken.
+    pub fn is_down(&self) -> bool {
+        self.link_state == "down"
+            || (self.bfd_enabled && self.bfd_state.as_deref().is_some_and(|s| s != "up"))
+            || self.bfd_forwarding == Some(false)
+    }
+}
+
 impl Default for ChassisHealth {
     fn default() -> Self {
         Self {
@@ -157,6 +358,12 @@ impl Default for ChassisHealth {
             encap_ip: String::new(),
             chassis_id: String::new(),
             bridge_mappings: Vec::new(),
+            tunnels: Vec::new(),
+            underlay_mtu: None,
+            max_tenant_mtu: None,
+            mtu_mismatch: false,
+            recompute_avg_ms: None,
+            recompute_max_ms: None,
             last_check: Utc::now(),
         }
     }
@@ -191,18 +398,43 @@ impl ChassisManager {
         let config = ChassisConfig {
             chassis_id: hostname.clone(),
             hostname: Some(hostname),
+            encap_ip: crate::registration::detect_management_ip().unwrap_or_default(),
             ..Default::default()
         };
 
         Ok(Self::new(config))
     }
 
+    /// Create a chassis manager that knows the bridges already configured in OVS
+    /// (`ovn-bridge` and `ovn-bridge-mappings` external IDs).
+    pub fn from_ovs() -> Result<Self> {
+        let mut manager = Self::from_env()?;
+        let ids = manager.get_external_ids()?;
+
+        if let Some(bridge) = ids.get("ovn-bridge").filter(|b| !b.is_empty()) {
+            manager.config.integration_bridge = bridge.clone();
+        }
+        if let Some(mappings) = ids.get("ovn-bridge-mappings") {
+            manager.config.bridge_mappings = mappings.split(',')
+                .filter_map(|m| m.split_once(':'))
+                .map(|(network, bridge)| (network.trim().to_string(), bridge.trim().to_string()))
+                .collect();
+        }
+
+        Ok(manager)
+    }
+
     /// Initialize OVS/OVN on this node.
     ///
     /// This sets up the integration bridge and configures OVS external_ids
     /// so that ovn-controller can connect to the Southbound DB.
     #[instrument(skip(self))]
     pub fn initialize(&mut self) -> Result<()> {
+        if !self.config.encap_ip.is_empty() {
+            self.config.encap_ip = normalize_encap_ip(&self.config.encap_ip)?;
+        }
+        self.config.ovn_sb_address = normalize_ovn_remote(&self.config.ovn_sb_address);
+
         info!(
             chassis_id = %self.config.chassis_id,
             encap_ip = %self.config.encap_ip,
@@ -224,6 +456,11 @@ impl ChassisManager {
             self.configure_bridge_mapping(phys_net, bridge)?;
         }
 
+        // Attach bonded uplinks
+        for bond in &self.config.bonds.clone() {
+            self.configure_bond(bond)?;
+        }
+
         // Verify OVN controller is running
         self.ensure_ovn_controller_running()?;
 
@@ -408,6 +645,99 @@ impl ChassisManager {
         Ok(())
     }
 
+    /// Add (or update) an OVS bond on the integration bridge or a mapped provider bridge.
+    ///
+    /// Changing the members of an existing bond recreates its port.
+    pub fn add_bond(&mut self, bond: OvsBondConfig) -> Result<()> {
+        bond.validate()?;
+
+        let managed = bond.bridge == self.config.integration_bridge
+            || self.config.bridge_mappings.values().any(|b| *b == bond.bridge);
+        if !managed {
+            bail!(
+                "Bridge {} is not the integration bridge or a provider bridge; add a bridge mapping first",
+                bond.bridge
+            );
+        }
+
+        let previous = self.config.bonds.iter().position(|b| b.name == bond.name)
+            .map(|i| self.config.bonds.remove(i));
+        if let Some(ref old) = previous {
+            if old.bridge != bond.bridge || old.members != bond.members {
+                self.delete_bond_port(&old.name)?;
+            }
+        }
+
+        self.configure_bond(&bond)?;
+        self.config.bonds.push(bond);
+        Ok(())
+    }
+
+    /// Configured OVS bonds.
+    pub fn bonds(&self) -> &[OvsBondConfig] {
+        &self.config.bonds
+    }
+
+    /// Remove an OVS bond.
+    pub fn remove_bond(&mut self, name: &str) -> Result<()> {
+        self.config.bonds.retain(|b| b.name != name);
+        self.delete_bond_port(name)
+    }
+
+    /// Create the bond port if missing and apply its settings.
+    #[instrument(skip(self, bond), fields(bond = %bond.name, bridge = %bond.bridge))]
+    fn configure_bond(&self, bond: &OvsBondConfig) -> Result<()> {
+        info!(members = ?bond.members, mode = %bond.mode, lacp = %bond.lacp, "Configuring OVS bond");
+
+        self.ensure_bridge(&bond.bridge)?;
+
+        let settings = bond.port_settings();
+        let mut args = vec!["--may-exist", "add-bond", bond.bridge.as_str(), bond.name.as_str()];
+        args.extend(bond.members.iter().map(String::as_str));
+        args.extend(settings.iter().map(String::as_str));
+
+        let output = Command::new("ovs-vsctl")
+            .args(&args)
+            .output()
+            .context("Failed to add OVS bond")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!("Failed to add OVS bond {}: {}", bond.name, stderr);
+        }
+
+        // --may-exist leaves an existing port untouched, so apply the settings explicitly
+        let mut args = vec!["set", "Port", bond.name.as_str()];
+        args.extend(settings.iter().map(String::as_str));
+
+        let output = Command::new("ovs-vsctl")
+            .args(&args)
+            .output()
+            .context("Failed to configure OVS bond")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!("Failed to configure OVS bond {}: {}", bond.name, stderr);
+        }
+
+        Ok(())
+    }
+
+    /// Delete a bond port from whichever bridge it is on.
+    fn delete_bond_port(&self, name: &str) -> Result<()> {
+        let output = Command::new("ovs-vsctl")
+            .args(["--if-exists", "del-port", name])
+            .output()
+            .context("Failed to delete OVS bond")?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!("Failed to delete OVS bond {}: {}", name, stderr);
+        }
+
+        Ok(())
+    }
+
     /// Ensure OVN controller is running.
     fn ensure_ovn_controller_running(&self) -> Result<()> {
         // Check if ovn-controller is active
@@ -510,18 +840,98 @@ impl ChassisManager {
             health.ovn_connected = ids.get("ovn-remote").is_some();
         }
 
+        if health.br_int_exists {
+            match self.tunnel_health() {
+                Ok(tunnels) => health.tunnels = tunnels,
+                Err(e) => debug!(error = %e, "Failed to read tunnel state"),
+            }
+            health.max_tenant_mtu = self.max_tenant_mtu();
+        }
+        if !health.encap_ip.is_empty() {
+            health.underlay_mtu = underlay_mtu(&health.encap_ip);
+        }
+        if let (Some(underlay), Some(tenant)) = (health.underlay_mtu, health.max_tenant_mtu) {
+            let mut overhead = match self.config.encap_type {
+                EncapType::Vxlan => VXLAN_OVERHEAD,
+                _ => GENEVE_OVERHEAD,
+            };
+            if health.encap_ip.parse::<IpAddr>().is_ok_and(|ip| ip.is_ipv6()) {
+                overhead += IPV6_EXTRA_OVERHEAD;
+            }
+            health.mtu_mismatch = tenant + overhead > underlay;
+        }
+        if health.ovn_controller_running {
+            if let Ok(output) = Command::new("ovn-appctl")
+                .args(["-t", "ovn-controller", "stopwatch/show", "flow-generation"])
+                .output()
+            {
+                if output.status.success() {
+                    let (avg, max) = parse_stopwatch(&String::from_utf8_lossy(&output.stdout));
+                    health.recompute_avg_ms = avg;
+                    health.recompute_max_ms = max;
+                }
+            }
+        }
+
         debug!(
             ovs = health.ovs_available,
             ovn = health.ovn_controller_running,
             br_int = health.br_int_exists,
             ports = health.br_int_port_count,
+            tunnels = health.tunnels.len(),
             "Health check complete"
         );
 
+        for (level, message) in health_changes(self.last_health.as_ref(), &health) {
+            emit_event(Event::new(level, EventCategory::Network, message, "ovn-chassis"));
+        }
+
         self.last_health = Some(health.clone());
         Ok(health)
     }
 
+    /// Read the tunnel interfaces on the integration bridge.
+    fn tunnel_health(&self) -> Result<Vec<TunnelHealth>> {
+        let interfaces = Command::new("ovs-vsctl")
+            .args([
+                "--format=json",
+                "--columns=name,type,options,bfd,bfd_status,link_state",
+                "list", "Interface",
+            ])
+            .output()
+            .context("Failed to list OVS interfaces")?;
+        if !interfaces.status.success() {
+            bail!("ovs-vsctl list Interface failed: {}", String::from_utf8_lossy(&interfaces.stderr));
+        }
+
+        // OVN records the remote chassis on the tunnel Port
+        let ports = Command::new("ovs-vsctl")
+            .args(["--format=json", "--columns=name,external_ids", "list", "Port"])
+            .output()
+            .context("Failed to list OVS ports")?;
+
+        parse_tunnels(
+            &String::from_utf8_lossy(&interfaces.stdout),
+            &String::from_utf8_lossy(&ports.stdout),
+        )
+    }
+
+    /// Largest MTU among the VM ports on the integration bridge.
+    fn max_tenant_mtu(&self) -> Option<u32> {
+        let output = Command::new("ovs-vsctl")
+            .args(["list-ifaces", &self.config.integration_bridge])
+            .output()
+            .ok()?;
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            // Tunnel and patch ports have no meaningful MTU here
+            .filter(|name| !name.is_empty() && !name.starts_with("ovn-") && !name.starts_with("patch-"))
+            .filter(|name| *name != self.config.integration_bridge)
+            .filter_map(|name| std::fs::read_to_string(format!("/sys/class/net/{}/mtu", name)).ok())
+            .filter_map(|mtu| mtu.trim().parse().ok())
+            .max()
+    }
+
     /// Get OVS external_ids.
     fn get_external_ids(&self) -> Result<HashMap<String, String>> {
         let output = Command::new("ovs-vsctl")
@@ -575,9 +985,10 @@ impl ChassisManager {
     /// Update the encapsulation IP.
     #[instrument(skip(self))]
     pub fn set_encap_ip(&mut self, ip: &str) -> Result<()> {
+        let ip = normalize_encap_ip(ip)?;
         info!(old_ip = %self.config.encap_ip, new_ip = %ip, "Updating encap IP");
         
-        self.config.encap_ip = ip.to_string();
+        self.config.encap_ip = ip.clone();
 
         let output = Command::new("ovs-vsctl")
             .args([
@@ -598,9 +1009,10 @@ impl ChassisManager {
     /// Update the OVN Southbound address.
     #[instrument(skip(self))]
     pub fn set_ovn_sb_address(&mut self, address: &str) -> Result<()> {
+        let address = normalize_ovn_remote(address);
         info!(old = %self.config.ovn_sb_address, new = %address, "Updating OVN SB address");
         
-        self.config.ovn_sb_address = address.to_string();
+        self.config.ovn_sb_address = address.clone();
 
         let output = Command::new("ovs-vsctl")
             .args([
@@ -619,6 +1031,470 @@ impl ChassisManager {
     }
 }
 
+/// Validate an encap IP and return it in canonical form.
+///
+/// Accepts IPv4 and IPv6 (optionally in brackets, e.g. "[fd00::1]").
+fn normalize_encap_ip(ip: &str) -> Result<String> {
+    let trimmed = ip.trim().trim_start_matches('[').trim_end_matches(']');
+    let addr: IpAddr = trimmed.parse()
+        .with_context(|| format!("Invalid encap IP '{}'", ip))?;
+    Ok(addr.to_string())
+}
+
+/// Bracket IPv6 hosts in an OVSDB remote list.
+///
+/// OVN expects "tcp:[fd00::1]:6642"; a bare "tcp:fd00::1:6642" is ambiguous,
+/// so the last colon is taken as the port separator.
+fn normalize_ovn_remote(remote: &str) -> String {
+    remote.split(',')
+        .map(|entry| {
+            let entry = entry.trim();
+            let Some((proto, rest)) = entry.split_once(':') else {
+                return entry.to_string();
+            };
+            if rest.starts_with('[') || rest.matches(':').count() < 2 {
+                return entry.to_string();
+            }
+            match rest.rsplit_once(':') {
+                Some((host, port)) if host.parse::<Ipv6Addr>().is_ok() && port.parse::<u16>().is_ok() => {
+                    format!("{}:[{}]:{}", proto, host, port)
+                }
+                _ => match rest.parse::<Ipv6Addr>() {
+                    Ok(host) => format!("{}:[{}]", proto, host),
+                    Err(_) => entry.to_string(),
+                },
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// MTU of the interface that owns `encap_ip`.
+fn underlay_mtu(encap_ip: &str) -> Option<u32> {
+    let encap_ip: IpAddr = encap_ip.parse().ok()?;
+    let output = Command::new("ip").args(["-j", "addr", "show"]).output().ok()?;
+    let links: serde_json::Value = serde_json::from_slice(&output.stdout).ok()?;
+    links.as_array()?
+        .iter()
+        .find(|link| {
+            link["addr_info"].as_array()
+                .is_some_and(|addrs| addrs.iter().any(|a| {
+                    a["local"].as_str().and_then(|l| l.parse::<IpAddr>().ok()) == Some(encap_ip)
+                }))
+        })
+        .and_then(|link| link["mtu"].as_u64())
+        .map(|mtu| mtu as u32)
+}
+
+/// OVSDB map value (`["map", [[k, v], ...]]`) as a HashMap of strings.
+fn ovsdb_map(value: &serde_json::Value) -> HashMap<String, String> {
+    value.get(1)
+        .and_then(|pairs| pairs.as_array())
+        .map(|pairs| {
+            pairs.iter()
+                .filter_map(|pair| {
+                    let key = pair.get(0)?.as_str()?;
+                    let value = pair.get(1)?;
+                    let value = value.as_str().map(String::from).unwrap_or_else(|| value.to_string());
+                    Some((key.to_string(), value))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn ovsdb_rows(output: &str) -> Vec<Vec<serde_json::Value>> {
+    serde_json::from_str::<serde_json::Value>(output)
+        .ok()
+        .and_then(|table| table["data"].as_array().cloned())
+        .map(|rows| rows.iter().filter_map(|r| r.as_array().cloned()).collect())
+        .unwrap_or_default()
+}
+
+/// Parse tunnel interfaces from `list Interface` and `list Port` JSON output.
+fn parse_tunnels(interfaces: &str, ports: &str) -> Result<Vec<TunnelHealth>> {
+    let chassis_of: HashMap<String, String> = ovsdb_rows(ports)
+        .into_iter()
+        .filter_map(|row| {
+            let name = row.first()?.as_str()?.to_string();
+            // "node2@10.0.0.2" (newer OVN) or just "node2"
+            let chassis = ovsdb_map(row.get(1)?).get("ovn-chassis-id")?
+                .split('@').next()?.to_string();
+            Some((name, chassis))
+        })
+        .collect();
+
+    let mut tunnels = Vec::new();
+    for row in ovsdb_rows(interfaces) {
+        let (Some(name), Some(kind)) = (row.first().and_then(|v| v.as_str()), row.get(1).and_then(|v| v.as_str())) else {
+            continue;
+        };
+        if !matches!(kind, "geneve" | "vxlan" | "stt") {
+            continue;
+        }
+        let options = row.get(2).map(ovsdb_map).unwrap_or_default();
+        let bfd = row.get(3).map(ovsdb_map).unwrap_or_default();
+        let bfd_status = row.get(4).map(ovsdb_map).unwrap_or_default();
+        let link_state = row.get(5)
+            .and_then(|v| v.as_str())
+            .unwrap_or("unknown")
+            .to_string();
+
+        tunnels.push(TunnelHealth {
+            name: name.to_string(),
+            encap_type: kind.to_string(),
+            remote_chassis: chassis_of.get(name).cloned().unwrap_or_default(),
+            remote_ip: options.get("remote_ip").cloned().unwrap_or_default(),
+            bfd_enabled: bfd.get("enable").is_some_and(|v| v == "true"),
+            bfd_state: bfd_status.get("state").cloned(),
+            bfd_forwarding: bfd_status.get("forwarding").map(|v| v == "true"),
+            flap_count: bfd_status.get("flap_count").and_then(|v| v.parse().ok()).unwrap_or(0),
+            link_state,
+        });
+    }
+    tunnels.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(tunnels)
+}
+
+/// Parse short-term average and maximum (ms) from `stopwatch/show`.
+///
+/// ```text
+/// Statistics for 'flow-generation'
+///   Total samples: 152
+///   Maximum: 231 msec
+///   Minimum: 0 msec
+///   95th percentile: 12.0 msec
+///   Short term average: 3.4 msec
+///   Long term average: 2.1 msec
+/// ```
+fn parse_stopwatch(output: &str) -> (Option<f64>, Option<f64>) {
+    let value = |label: &str| {
+        output.lines()
+            .find_map(|line| line.trim().strip_prefix(label))
+            .and_then(|rest| rest.split_whitespace().next())
+            .and_then(|v| v.parse().ok())
+    };
+    (value("Short term average:"), value("Maximum:"))
+}
+
+/// Degradations (and recoveries) between two health checks. The first check
+/// is compared with a healthy chassis, so problems present at startup are
+/// reported too.
+fn health_changes(previous: Option<&ChassisHealth>, current: &ChassisHealth) -> Vec<(EventLevel, String)> {
+    let mut changes = Vec::new();
+
+    let first_check = previous.is_none();
+    // ovn-controller is only expected on a chassis configured for OVN
+    let healthy = ChassisHealth {
+        ovn_controller_running: current.ovn_connected,
+        ..Default::default()
+    };
+    let previous = previous.unwrap_or(&healthy);
+
+    if previous.ovn_controller_running && !current.ovn_controller_running {
+        changes.push((EventLevel::Error, "ovn-controller is not running".to_string()));
+    }
+
+    let before: HashMap<&str, &TunnelHealth> = previous.tunnels.iter().map(|t| (t.name.as_str(), t)).collect();
+    let peer = |t: &TunnelHealth| {
+        if t.remote_chassis.is_empty() {
+            t.remote_ip.clone()
+        } else {
+            format!("{} ({})", t.remote_chassis, t.remote_ip)
+        }
+    };
+
+    for tunnel in &current.tunnels {
+        let old = before.get(tunnel.name.as_str());
+        if old.is_none() && !first_check {
+            changes.push((EventLevel::Info, format!("Tunnel to chassis {} added", peer(tunnel))));
+        }
+
+        // New tunnels count as up before, so one that comes up broken is reported
+        let was_down = old.is_some_and(|old| old.is_down());
+        if tunnel.is_down() && !was_down {
+            changes.push((
+                EventLevel::Error,
+                format!(
+                    "{} tunnel to chassis {} is down (BFD {})",
+                    tunnel.encap_type, peer(tunnel), tunnel.bfd_state.as_deref().unwrap_or("n/a")
+                ),
+            ));
+        } else if !tunnel.is_down() && was_down {
+            changes.push((EventLevel::Info, format!("Tunnel to chassis {} recovered", peer(tunnel))));
+        }
+
+        if let Some(old) = old {
+            let flaps = tunnel.flap_count.saturating_sub(old.flap_count);
+            if flaps >= FLAP_THRESHOLD {
+                changes.push((
+                    EventLevel::Warning,
+                    format!("Tunnel to chassis {} is flapping ({} BFD flaps since last check)", peer(tunnel), flaps),
+                ));
+            }
+        }
+    }
+    for old in &previous.tunnels {
+        if !current.tunnels.iter().any(|t| t.name == old.name) {
+            changes.push((EventLevel::Warning, format!("Tunnel to chassis {} removed", peer(old))));
+        }
+    }
+
+    if current.mtu_mismatch && !previous.mtu_mismatch {
+        changes.push((
+            EventLevel::Warning,
+            format!(
+                "VM port MTU {} does not fit the underlay MTU {} after encapsulation; large packets between hosts will be dropped",
+                current.max_tenant_mtu.unwrap_or(0), current.underlay_mtu.unwrap_or(0)
+            ),
+        ));
+    }
+
+    let slow = |h: &ChassisHealth| h.recompute_avg_ms.is_some_and(|ms| ms >= SLOW_RECOMPUTE_MS);
+    if slow(current) && !slow(previous) {
+        changes.push((
+            EventLevel::Warning,
+            format!("ovn-controller flow recompute is slow ({:.0} ms average)", current.recompute_avg_ms.unwrap_or(0.0)),
+        ));
+    }
+
+    changes
+}
+
+// =============================================================================
+// Saved OVS bonds
+// =============================================================================
+
+/// Serializes OVS bond changes (load, apply, verify, save)
+static BOND_LOCK: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+fn bonds_path() -> PathBuf {
+    if Path::new("/quantix").exists() {
+        PathBuf::from(BONDS_PERSISTENT_PATH)
+    } else {
+        PathBuf::from(BONDS_FALLBACK_PATH)
+    }
+}
+
+fn load_bonds(path: &Path) -> Result<Vec<OvsBondConfig>> {
+    match std::fs::read(path) {
+        Ok(data) => serde_json::from_slice(&data).with_context(|| format!("Failed to parse {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+fn save_bonds(path: &Path, bonds: &[OvsBondConfig]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, serde_json::to_vec_pretty(bonds)?)
+        .with_context(|| format!("Failed to write {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("Failed to replace {}", path.display()))
+}
+
+/// Whether an OVS port exists on any bridge.
+fn port_exists(name: &str) -> bool {
+    Command::new("ovs-vsctl")
+        .args(["port-to-br", name])
+        .output()
+        .map(|o| o.status.success())
+        .unwrap_or(false)
+}
+
+/// Check that the node is still reachable after a bond change.
+fn verify_uplinks() -> Result<()> {
+    let management = crate::registration::detect_management_ip().and_then(|ip| ip.parse().ok());
+    limiquantix_hypervisor::network::host::verify_connectivity(crate::host_network::VERIFY_TIMEOUT, management)
+}
+
+/// A chassis manager loaded with the saved bonds.
+fn bond_manager() -> Result<ChassisManager> {
+    let mut manager = ChassisManager::from_ovs()?;
+    manager.config.bonds = load_bonds(&bonds_path())?;
+    Ok(manager)
+}
+
+/// Run `change` against the saved bonds; keep and save the result only if
+/// the node stays reachable, otherwise restore the previous bond.
+fn change_bond(name: &str, change: impl FnOnce(&mut ChassisManager) -> Result<()>) -> Result<()> {
+    let mut manager = bond_manager()?;
+    let previous = manager.bonds().iter().find(|b| b.name == name).cloned();
+
+    let failure = match change(&mut manager) {
+        Err(e) => Some(e),
+        Ok(()) => verify_uplinks().err(),
+    };
+
+    if let Some(e) = failure {
+        warn!(bond = %name, error = %e, "Rolling back OVS bond change");
+        let rollback = match previous {
+            Some(old) => manager.add_bond(old),
+            None => manager.remove_bond(name),
+        };
+        return match rollback {
+            Ok(()) => Err(e.context("OVS bond change rolled back")),
+            Err(r) => Err(e.context(format!("Rollback incomplete: {:#}", r))),
+        };
+    }
+
+    save_bonds(&bonds_path(), manager.bonds())
+}
+
+/// Saved OVS bonds.
+pub async fn list_bonds() -> Result<Vec<OvsBondConfig>> {
+    tokio::task::spawn_blocking(|| load_bonds(&bonds_path())).await?
+}
+
+/// Add or update an OVS bond and save it.
+///
+/// The change is rolled back if the management address or default gateway
+/// becomes unreachable.
+pub async fn apply_bond(bond: OvsBondConfig) -> Result<()> {
+    let _guard = BOND_LOCK.lock().await;
+    tokio::task::spawn_blocking(move || {
+        let name = bond.name.clone();
+        change_bond(&name, |manager| manager.add_bond(bond))
+    }).await?
+}
+
+/// Remove a saved OVS bond (rolled back like `apply_bond`).
+pub async fn delete_bond(name: String) -> Result<()> {
+    let _guard = BOND_LOCK.lock().await;
+    tokio::task::spawn_blocking(move || {
+        if !load_bonds(&bonds_path())?.iter().any(|b| b.name == name) {
+            bail!("OVS bond {} not found", name);
+        }
+        change_bond(&name, |manager| manager.remove_bond(&name))
+    }).await?
+}
+
+/// Re-create saved OVS bonds at startup.
+///
+/// Bonds that were missing are removed again if the node loses its
+/// management address or gateway afterwards.
+pub async fn restore_bonds() {
+    let _guard = BOND_LOCK.lock().await;
+    let result = tokio::task::spawn_blocking(|| -> Result<usize> {
+        if !has_ovs() || load_bonds(&bonds_path())?.is_empty() {
+            return Ok(0);
+        }
+        let manager = bond_manager()?;
+        let missing: Vec<&OvsBondConfig> = manager.bonds().iter().filter(|b| !port_exists(&b.name)).collect();
+        if missing.is_empty() {
+            return Ok(0);
+        }
+
+        let failure = missing.iter()
+            .try_for_each(|bond| manager.configure_bond(bond))
+            .and_then(|_| verify_uplinks())
+            .err();
+        if let Some(e) = failure {
+            for bond in &missing {
+                if let Err(r) = manager.delete_bond_port(&bond.name) {
+                    warn!(bond = %bond.name, error = %r, "Failed to remove restored OVS bond");
+                }
+            }
+            return Err(e.context("Restored OVS bonds rolled back"));
+        }
+        Ok(missing.len())
+    }).await;
+
+    match result {
+        Ok(Ok(0)) => {}
+        Ok(Ok(restored)) => info!(bonds = restored, "Restored saved OVS bonds"),
+        Ok(Err(e)) => emit_event(Event::new(
+            EventLevel::Error,
+            EventCategory::Network,
+            format!("Failed to restore saved OVS bonds: {:#}", e),
+            "network",
+        )),
+        Err(e) => warn!(error = %e, "OVS bond restore task failed"),
+    }
+}
+
+/// Whether `ovs-vsctl` is installed.
+fn has_ovs() -> bool {
+    std::env::var_os("PATH")
+        .is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir.join("ovs-vsctl").exists()))
+}
+
+/// Latest result of the background health monitor
+static LATEST_HEALTH: RwLock<Option<ChassisHealth>> = RwLock::new(None);
+
+/// Latest chassis health from the background monitor.
+pub fn latest_health() -> Option<ChassisHealth> {
+    LATEST_HEALTH.read().ok().and_then(|h| h.clone())
+}
+
+/// Run periodic chassis health checks on hosts with OVS, emitting network
+/// events when the overlay degrades.
+pub fn spawn_health_monitor() {
+    if !has_ovs() {
+        debug!("ovs-vsctl not found, chassis health monitor disabled");
+        return;
+    }
+
+    tokio::spawn(async {
+        let mut manager = match ChassisManager::from_env() {
+            Ok(manager) => manager,
+            Err(e) => {
+                warn!(error = %e, "Chassis health monitor disabled");
+                return;
+            }
+        };
+        let mut interval = tokio::time::interval(HEALTH_INTERVAL);
+        loop {
+            interval.tick().await;
+            let result = tokio::task::spawn_blocking(move || {
+                let result = manager.health_check();
+                (manager, result)
+            }).await;
+            let (checked, result) = match result {
+                Ok(checked) => checked,
+                Err(e) => {
+                    // The manager was lost with the task: start over from the
+                    // last known health so existing problems are not reported again
+                    error!(error = %e, "Chassis health check task failed");
+                    emit_event(Event::new(
+                        EventLevel::Error,
+                        EventCategory::Network,
+                        format!("Chassis health check failed unexpectedly: {}", e),
+                        "ovn-chassis",
+                    ));
+                    match ChassisManager::from_env() {
+                        Ok(mut restarted) => {
+                            restarted.last_health = latest_health();
+                            manager = restarted;
+                        }
+                        Err(e) => {
+                            error!(error = %e, "Chassis health monitor stopped");
+                            emit_event(Event::new(
+                                EventLevel::Error,
+                                EventCategory::Network,
+                                format!("Chassis health monitor stopped: {}", e),
+                                "ovn-chassis",
+                            ));
+                            return;
+                        }
+                    }
+                    continue;
+                }
+            };
+            manager = checked;
+            match result {
+                Ok(health) => {
+                    if let Ok(mut latest) = LATEST_HEALTH.write() {
+                        *latest = Some(health);
+                    }
+                }
+                Err(e) => warn!(error = %e, "Chassis health check failed"),
+            }
+        }
+    });
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -638,6 +1514,71 @@ mod tests {
         assert!(!config.enable_dpdk);
     }
 
+    #[test]
+    fn test_ipv6_encap_and_remote() {
+        assert_eq!(normalize_encap_ip("[fd00:0::1]").unwrap(), "fd00::1");
+        assert_eq!(normalize_encap_ip("10.0.0.5").unwrap(), "10.0.0.5");
+        assert!(normalize_encap_ip("node1").is_err());
+
+        assert_eq!(normalize_ovn_remote("tcp:10.0.0.1:6642"), "tcp:10.0.0.1:6642");
+        assert_eq!(normalize_ovn_remote("tcp:fd00::1:6642"), "tcp:[fd00::1]:6642");
+        assert_eq!(normalize_ovn_remote("ssl:[fd00::1]:6642"), "ssl:[fd00::1]:6642");
+        assert_eq!(
+            normalize_ovn_remote("tcp:10.0.0.1:6642,tcp:fd00::2:6642"),
+            "tcp:10.0.0.1:6642,tcp:[fd00::2]:6642"
+        );
+    }
+
+    #[test]
+    fn test_ovs_bond_settings() {
+        let mut bond = OvsBondConfig {
+            name: "bond-ex".to_string(),
+            bridge: "br-ex".to_string(),
+            members: vec!["eth0".to_string(), "eth1".to_string()],
+            mode: OvsBondMode::BalanceTcp,
+            lacp: LacpMode::Off,
+            lacp_fast: false,
+            miimon: Some(100),
+        };
+        assert!(bond.validate().is_err());
+
+        bond.lacp = LacpMode::Active;
+        bond.lacp_fast = true;
+        bond.validate().unwrap();
+        assert_eq!(
+            bond.port_settings(),
+            vec![
+                "bond_mode=balance-tcp",
+                "lacp=active",
+                "other_config:lacp-time=fast",
+                "other_config:bond-detect-mode=miimon",
+                "other_config:bond-miimon-interval=100",
+            ]
+        );
+
+        let mut manager = ChassisManager::new(ChassisConfig::default());
+        assert!(manager.add_bond(bond).is_err());
+    }
+
+    #[test]
+    fn test_saved_bonds_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ovs-bonds.json");
+        assert!(load_bonds(&path).unwrap().is_empty());
+
+        let bond: OvsBondConfig = serde_json::from_value(serde_json::json!({
+            "name": "bond-ex",
+            "bridge": "br-ex",
+            "members": ["eth0", "eth1"],
+            "mode": "balance-slb",
+            "lacpFast": false,
+        })).unwrap();
+        assert_eq!(bond.lacp, LacpMode::Off);
+
+        save_bonds(&path, std::slice::from_ref(&bond)).unwrap();
+        assert_eq!(load_bonds(&path).unwrap(), vec![bond]);
+    }
+
     #[test]
     fn test_chassis_health_default() {
         let health = ChassisHealth::default();
@@ -645,4 +1586,71 @@ mod tests {
         assert!(!health.ovn_controller_running);
         assert!(!health.br_int_exists);
     }
+
+    #[test]
+    fn test_parse_tunnels() {
+        let interfaces = r#"{"data":[
+            ["ovn-node2-0","geneve",["map",[["remote_ip","10.0.0.2"],["key","flow"]]],["map",[["enable","true"]]],["map",[["forwarding","false"],["state","down"],["flap_count","7"]]],"up"],
+            ["vnet0","",["map",[]],["map",[]],["map",[]],"up"]
+        ],"headings":[]}"#;
+        let ports = r#"{"data":[["ovn-node2-0",["map",[["ovn-chassis-id","node2@10.0.0.2"]]]]],"headings":[]}"#;
+
+        let tunnels = parse_tunnels(interfaces, ports).unwrap();
+        assert_eq!(tunnels.len(), 1);
+        assert_eq!(tunnels[0].remote_chassis, "node2");
+        assert_eq!(tunnels[0].remote_ip, "10.0.0.2");
+        assert_eq!(tunnels[0].flap_count, 7);
+        assert!(tunnels[0].is_down());
+    }
+
+    #[test]
+    fn test_health_changes() {
+        let tunnel = TunnelHealth {
+            name: "ovn-node2-0".to_string(),
+            encap_type: "geneve".to_string(),
+            remote_chassis: "node2".to_string(),
+            remote_ip: "10.0.0.2".to_string(),
+            bfd_enabled: true,
+            bfd_state: Some("up".to_string()),
+            bfd_forwarding: Some(true),
+            flap_count: 1,
+            link_state: "up".to_string(),
+        };
+        let previous = ChassisHealth {
+            ovn_controller_running: true,
+            tunnels: vec![tunnel.clone()],
+            ..Default::default()
+        };
+        assert!(health_changes(Some(&previous), &previous).is_empty());
+
+        let mut current = previous.clone();
+        current.tunnels[0].bfd_state = Some("down".to_string());
+        current.tunnels[0].flap_count = 5;
+        current.mtu_mismatch = true;
+        current.recompute_avg_ms = Some(1500.0);
+        let changes = health_changes(Some(&previous), &current);
+        assert_eq!(changes.len(), 4);
+        assert_eq!(changes[0].0, EventLevel::Error);
+        assert!(changes[0].1.contains("node2 (10.0.0.2)"));
+
+        // A tunnel that is added while down is reported as down
+        let mut added = previous.clone();
+        added.tunnels.push(TunnelHealth { name: "ovn-node3-0".to_string(), ..current.tunnels[0].clone() });
+        let changes = health_changes(Some(&previous), &added);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].0, EventLevel::Info);
+        assert_eq!(changes[1].0, EventLevel::Error);
+
+        // Problems present at the first check are reported, healthy state is not
+        assert!(health_changes(None, &previous).is_empty());
+        let mut startup = current.clone();
+        startup.ovn_connected = true;
+        startup.ovn_controller_running = false;
+        let changes = health_changes(None, &startup);
+        assert_eq!(changes.len(), 4);
+        assert!(changes.iter().all(|(level, _)| *level != EventLevel::Info));
+        assert!(changes[0].1.contains("ovn-controller"));
+
+        assert_eq!(parse_stopwatch("  Maximum: 231 msec\n  Short term average: 3.4 msec\n"), (Some(3.4), Some(231.0)));
+    }
 }
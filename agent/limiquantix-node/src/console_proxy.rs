@@ -0,0 +1,211 @@
+//! Console Proxy - Authenticated VNC over WebSocket.
+//!
+//! QEMU's VNC servers listen on loopback only, so remote clients (the Host UI,
+//! qvmc) cannot reach them directly on a standalone host. The node's HTTP
+//! server bridges RFB over a WebSocket to the loopback port instead.
+//!
+//! Access is granted with console tickets:
+//! 1. The client requests `POST /api/v1/vms/:id/console/ticket`
+//! 2. It connects to `/api/v1/vms/:id/console/ws?ticket=...` within the TTL
+//! 3. The ticket is consumed on connect and cannot be replayed
+//!
+//! The agent shell WebSocket (`/api/v1/vms/:id/agent/shell`) and the serial
+//! console WebSocket (`/api/v1/vms/:id/serial`) use the same tickets, issued
+//! by `POST /api/v1/vms/:id/agent/shell/ticket` and
+//! `POST /api/v1/vms/:id/serial/ticket`.
+//!
+//! Design Decisions:
+//! - Tickets are bound to a single VM and expire quickly (30s)
+//! - Tickets live in memory only; a daemon restart invalidates them
+//! - The bridge is byte-transparent: RFB framing and VNC auth are left to the
+//!   client (noVNC speaks RFB over the `binary` WebSocket subprotocol)
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use axum::extract::ws::{Message, WebSocket};
+use chrono::{DateTime, Utc};
+use futures::{SinkExt, StreamExt};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tracing::{debug, info, warn};
+
+/// How long a console ticket stays valid
+pub const TICKET_TTL: Duration = Duration::from_secs(30);
+
+/// A single-use console access ticket.
+#[derive(Debug, Clone)]
+pub struct ConsoleTicket {
+    /// Opaque ticket value
+    pub ticket: String,
+    /// VM the ticket grants access to
+    pub vm_id: String,
+    /// Expiry time
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Reasons a ticket can be rejected.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TicketError {
+    #[error("Console ticket is invalid or already used")]
+    Invalid,
+    
+    #[error("Console ticket has expired")]
+    Expired,
+    
+    #[error("Console ticket was issued for a different VM")]
+    WrongVm,
+}
+
+/// In-memory store of outstanding console tickets.
+pub struct ConsoleTicketStore {
+    tickets: Mutex<HashMap<String, ConsoleTicket>>,
+    ttl: Duration,
+}
+
+impl ConsoleTicketStore {
+    /// Create a store issuing tickets with the given TTL.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            tickets: Mutex::new(HashMap::new()),
+            ttl,
+        }
+    }
+    
+    /// Issue a new ticket for a VM.
+    pub fn issue(&self, vm_id: &str) -> ConsoleTicket {
+        use rand::Rng;
+        
+        let bytes: [u8; 32] = rand::thread_rng().gen();
+        let ticket = ConsoleTicket {
+            ticket: hex::encode(bytes),
+            vm_id: vm_id.to_string(),
+            expires_at: Utc::now() + chrono::Duration::from_std(self.ttl).unwrap_or_default(),
+        };
+        
+        let mut tickets = self.tickets.lock().unwrap();
+        let now = Utc::now();
+        tickets.retain(|_, t| t.expires_at > now);
+        tickets.insert(ticket.ticket.clone(), ticket.clone());
+        
+        ticket
+    }
+    
+    /// Consume a ticket for a VM. The ticket is removed whether or not it is valid.
+    pub fn redeem(&self, ticket: &str, vm_id: &str) -> Result<(), TicketError> {
+        let entry = self.tickets.lock().unwrap()
+            .remove(ticket)
+            .ok_or(TicketError::Invalid)?;
+        
+        if entry.expires_at <= Utc::now() {
+            return Err(TicketError::Expired);
+        }
+        if entry.vm_id != vm_id {
+            return Err(TicketError::WrongVm);
+        }
+        
+        Ok(())
+    }
+}
+
+/// Global console ticket store (shared by the HTTP and HTTPS servers)
+static CONSOLE_TICKETS: std::sync::OnceLock<ConsoleTicketStore> = std::sync::OnceLock::new();
+
+/// Get the global console ticket store.
+pub fn console_tickets() -> &'static ConsoleTicketStore {
+    CONSOLE_TICKETS.get_or_init(|| ConsoleTicketStore::new(TICKET_TTL))
+}
+
+/// Bridge a WebSocket to a VNC server until either side closes.
+pub async fn proxy_vnc(socket: WebSocket, vm_id: String, addr: SocketAddr) {
+    let tcp = match TcpStream::connect(addr).await {
+        Ok(tcp) => tcp,
+        Err(e) => {
+            warn!(vm_id = %vm_id, addr = %addr, error = %e, "Failed to connect to VNC server");
+            let mut socket = socket;
+            let _ = socket.send(Message::Close(None)).await;
+            return;
+        }
+    };
+    let _ = tcp.set_nodelay(true);
+    
+    info!(vm_id = %vm_id, addr = %addr, "Console proxy connected");
+    
+    let (mut tcp_read, mut tcp_write) = tcp.into_split();
+    let (mut ws_tx, mut ws_rx) = socket.split();
+    
+    // VNC -> client
+    let vnc_to_ws = async {
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            match tcp_read.read(&mut buf).await {
+                Ok(0) | Err(_) => break,
+                Ok(n) => {
+                    if ws_tx.send(Message::Binary(buf[..n].to_vec())).await.is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+        let _ = ws_tx.send(Message::Close(None)).await;
+    };
+    
+    // Client -> VNC
+    let ws_to_vnc = async {
+        while let Some(Ok(msg)) = ws_rx.next().await {
+            let data = match msg {
+                Message::Binary(data) => data,
+                Message::Text(text) => text.into_bytes(),
+                Message::Close(_) => break,
+                // Pings are answered by the WebSocket layer
+                _ => continue,
+            };
+            if tcp_write.write_all(&data).await.is_err() {
+                break;
+            }
+        }
+        let _ = tcp_write.shutdown().await;
+    };
+    
+    tokio::select! {
+        _ = vnc_to_ws => {}
+        _ = ws_to_vnc => {}
+    }
+    
+    debug!(vm_id = %vm_id, "Console proxy closed");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_ticket_single_use() {
+        let store = ConsoleTicketStore::new(TICKET_TTL);
+        let ticket = store.issue("vm-1");
+        
+        assert_eq!(ticket.ticket.len(), 64);
+        assert_eq!(store.redeem(&ticket.ticket, "vm-1"), Ok(()));
+        assert_eq!(store.redeem(&ticket.ticket, "vm-1"), Err(TicketError::Invalid));
+    }
+    
+    #[test]
+    fn test_ticket_bound_to_vm() {
+        let store = ConsoleTicketStore::new(TICKET_TTL);
+        let ticket = store.issue("vm-1");
+        
+        assert_eq!(store.redeem(&ticket.ticket, "vm-2"), Err(TicketError::WrongVm));
+        // Consumed even on mismatch
+        assert_eq!(store.redeem(&ticket.ticket, "vm-1"), Err(TicketError::Invalid));
+    }
+    
+    #[test]
+    fn test_ticket_expiry() {
+        let store = ConsoleTicketStore::new(Duration::ZERO);
+        let ticket = store.issue("vm-1");
+        
+        assert_eq!(store.redeem(&ticket.ticket, "vm-1"), Err(TicketError::Expired));
+    }
+}
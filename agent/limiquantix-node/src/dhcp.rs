@@ -0,0 +1,447 @@
+//! DHCP Server - address management for VMs on standalone host bridges.
+//!
+//! Without OVN there is nobody handing out addresses on a plain Linux
+//! bridge. The node runs one small DHCPv4 server per configured bridge:
+//! - Configs (range, gateway, DNS, static leases) and the lease table are
+//!   stored in `/var/lib/limiquantix/dhcp.json`, so leases survive restarts
+//! - Each server listens on UDP 67 bound to its bridge (`SO_BINDTODEVICE`)
+//!   and waits for the bridge to appear if it doesn't exist yet
+//! - Leases are keyed on the NIC MAC, which lets the node report VM
+//!   addresses without a guest agent
+//!
+//! The protocol and IPAM logic live in `limiquantix_hypervisor::network::dhcp`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::path::PathBuf;
+
+use limiquantix_hypervisor::network::dhcp::{self, DhcpConfig, DhcpLease, DhcpPacket, DhcpPool, MessageType, StaticLease};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::event_store::{emit_event, Event, EventCategory, EventLevel};
+
+/// Where configs and leases are stored
+const STATE_PATH: &str = "/var/lib/limiquantix/dhcp.json";
+
+/// How long to wait before retrying to bind a server
+const BIND_RETRY: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Persisted DHCP state.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DhcpState {
+    #[serde(default)]
+    configs: BTreeMap<String, DhcpConfig>,
+    /// Bridge -> dynamic leases
+    #[serde(default)]
+    leases: BTreeMap<String, Vec<DhcpLease>>,
+}
+
+/// A bridge's DHCP config with its runtime status.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DhcpServerStatus {
+    #[serde(flatten)]
+    pub config: DhcpConfig,
+    /// Server task is running (it may still be waiting for the bridge)
+    pub running: bool,
+    pub active_leases: usize,
+}
+
+/// Errors from the DHCP manager.
+#[derive(Debug, thiserror::Error)]
+pub enum DhcpError {
+    #[error("No DHCP configuration for bridge {0}")]
+    NotFound(String),
+    
+    #[error("{0:#}")]
+    Invalid(anyhow::Error),
+    
+    #[error("{0:#}")]
+    Failed(anyhow::Error),
+}
+
+/// Per-bridge DHCP servers and their lease tables.
+pub struct DhcpManager {
+    /// Bridge -> pool
+    pools: Mutex<BTreeMap<String, DhcpPool>>,
+    servers: Mutex<HashMap<String, JoinHandle<()>>>,
+    path: PathBuf,
+}
+
+fn now() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+impl DhcpManager {
+    fn new(path: PathBuf) -> Self {
+        let state: DhcpState = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|e| {
+                warn!(path = %path.display(), error = %e, "Failed to parse DHCP state, starting empty");
+                DhcpState::default()
+            }),
+            Err(_) => DhcpState::default(),
+        };
+        
+        let mut leases = state.leases;
+        let pools = state.configs.into_iter()
+            .map(|(bridge, config)| {
+                let pool = DhcpPool::new(config, leases.remove(&bridge).unwrap_or_default());
+                (bridge, pool)
+            })
+            .collect();
+        
+        Self {
+            pools: Mutex::new(pools),
+            servers: Mutex::new(HashMap::new()),
+            path,
+        }
+    }
+    
+    /// Start the servers of all enabled bridges (at daemon startup).
+    pub async fn start_all(&'static self) {
+        let bridges: Vec<String> = self.pools.lock().await.values()
+            .filter(|p| p.config().enabled)
+            .map(|p| p.config().bridge.clone())
+            .collect();
+        for bridge in bridges {
+            self.restart_server(&bridge, true).await;
+        }
+    }
+    
+    /// All DHCP configs.
+    pub async fn list(&self) -> Vec<DhcpServerStatus> {
+        let pools = self.pools.lock().await;
+        let servers = self.servers.lock().await;
+        pools.values().map(|pool| Self::status(pool, &servers)).collect()
+    }
+    
+    /// DHCP config of a bridge.
+    pub async fn get(&self, bridge: &str) -> Result<DhcpServerStatus, DhcpError> {
+        let pools = self.pools.lock().await;
+        let servers = self.servers.lock().await;
+        pools.get(bridge)
+            .map(|pool| Self::status(pool, &servers))
+            .ok_or_else(|| DhcpError::NotFound(bridge.to_string()))
+    }
+    
+    fn status(pool: &DhcpPool, servers: &HashMap<String, JoinHandle<()>>) -> DhcpServerStatus {
+        let config = pool.config().clone();
+        let running = servers.get(&config.bridge).is_some_and(|h| !h.is_finished());
+        DhcpServerStatus {
+            active_leases: pool.active_leases(now()).len(),
+            config,
+            running,
+        }
+    }
+    
+    /// Create or replace a bridge's config, keeping leases that still fit, and (re)start its server.
+    pub async fn put_config(&'static self, config: DhcpConfig) -> Result<DhcpServerStatus, DhcpError> {
+        config.validate().map_err(DhcpError::Invalid)?;
+        let bridge = config.bridge.clone();
+        
+        {
+            let mut pools = self.pools.lock().await;
+            let leases: Vec<DhcpLease> = pools.get(&bridge)
+                .map(|p| p.dynamic_leases().cloned().collect())
+                .unwrap_or_default();
+            info!(bridge = %bridge, subnet = %config.subnet, enabled = config.enabled, "Saving DHCP configuration");
+            pools.insert(bridge.clone(), DhcpPool::new(config.clone(), leases));
+            self.save(&pools).await?;
+        }
+        
+        self.restart_server(&bridge, config.enabled).await;
+        self.get(&bridge).await
+    }
+    
+    /// Stop serving a bridge and forget its leases.
+    pub async fn delete_config(&self, bridge: &str) -> Result<(), DhcpError> {
+        let mut pools = self.pools.lock().await;
+        if pools.remove(bridge).is_none() {
+            return Err(DhcpError::NotFound(bridge.to_string()));
+        }
+        if let Some(handle) = self.servers.lock().await.remove(bridge) {
+            handle.abort();
+        }
+        info!(bridge = %bridge, "Removed DHCP configuration");
+        self.save(&pools).await
+    }
+    
+    /// Lease table of a bridge (static and active dynamic leases).
+    pub async fn leases(&self, bridge: &str) -> Result<Vec<DhcpLease>, DhcpError> {
+        self.pools.lock().await.get(bridge)
+            .map(|pool| pool.active_leases(now()))
+            .ok_or_else(|| DhcpError::NotFound(bridge.to_string()))
+    }
+    
+    /// Add or replace the static lease of a MAC.
+    pub async fn set_static_lease(&'static self, bridge: &str, lease: StaticLease) -> Result<DhcpServerStatus, DhcpError> {
+        let mut config = self.get(bridge).await?.config;
+        config.static_leases.retain(|s| !s.mac_address.eq_ignore_ascii_case(&lease.mac_address));
+        config.static_leases.push(lease);
+        self.put_config(config).await
+    }
+    
+    /// Remove the static lease of a MAC.
+    pub async fn remove_static_lease(&'static self, bridge: &str, mac: &str) -> Result<DhcpServerStatus, DhcpError> {
+        let mut config = self.get(bridge).await?.config;
+        let before = config.static_leases.len();
+        config.static_leases.retain(|s| !s.mac_address.eq_ignore_ascii_case(mac));
+        if config.static_leases.len() == before {
+            return Err(DhcpError::NotFound(format!("{} (static lease {})", bridge, mac)));
+        }
+        self.put_config(config).await
+    }
+    
+    /// Addresses leased to a running VM's NICs.
+    pub async fn addresses_for_vm(&self, vm_id: &str) -> Vec<String> {
+        if self.pools.lock().await.is_empty() {
+            return Vec::new();
+        }
+        
+        let macs: Vec<String> = crate::firewall::vm_taps(vm_id).await
+            .into_iter()
+            .map(|(_, mac)| mac)
+            .collect();
+        let now = now();
+        let pools = self.pools.lock().await;
+        macs.iter()
+            .flat_map(|mac| pools.values().filter_map(move |p| p.address_of(mac, now)))
+            .map(|addr| addr.to_string())
+            .collect()
+    }
+    
+    /// Active leases of all bridges by (lowercase) MAC, for listing many VMs at once.
+    pub async fn addresses_by_mac(&self) -> HashMap<String, Vec<String>> {
+        let now = now();
+        let mut addresses: HashMap<String, Vec<String>> = HashMap::new();
+        for pool in self.pools.lock().await.values() {
+            for lease in pool.active_leases(now) {
+                addresses.entry(lease.mac_address.to_lowercase())
+                    .or_default()
+                    .push(lease.ip_address.to_string());
+            }
+        }
+        addresses
+    }
+    
+    async fn restart_server(&'static self, bridge: &str, enabled: bool) {
+        let mut servers = self.servers.lock().await;
+        if let Some(handle) = servers.remove(bridge) {
+            handle.abort();
+        }
+        if enabled {
+            let name = bridge.to_string();
+            servers.insert(bridge.to_string(), tokio::spawn(self.serve(name)));
+        }
+    }
+    
+    /// Server loop of one bridge.
+    async fn serve(&'static self, bridge: String) {
+        let mut reported = false;
+        let socket = loop {
+            match bind_socket(&bridge).and_then(tokio::net::UdpSocket::from_std) {
+                Ok(socket) => break socket,
+                Err(e) => {
+                    if !reported {
+                        warn!(bridge = %bridge, error = %e, "Cannot listen for DHCP, retrying");
+                        emit_event(Event::new(
+                            EventLevel::Warning,
+                            EventCategory::Network,
+                            format!("DHCP server on {} cannot start: {}", bridge, e),
+                            "dhcp",
+                        ));
+                        reported = true;
+                    }
+                    tokio::time::sleep(BIND_RETRY).await;
+                }
+            }
+        };
+        info!(bridge = %bridge, "DHCP server listening");
+        
+        let mut buf = [0u8; 1500];
+        loop {
+            let len = match socket.recv_from(&mut buf).await {
+                Ok((len, _)) => len,
+                Err(e) => {
+                    warn!(bridge = %bridge, error = %e, "DHCP receive failed");
+                    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                    continue;
+                }
+            };
+            let request = match DhcpPacket::parse(&buf[..len]) {
+                Ok(packet) => packet,
+                Err(e) => {
+                    debug!(bridge = %bridge, error = %e, "Ignoring malformed DHCP packet");
+                    continue;
+                }
+            };
+            
+            let reply = self.handle(&bridge, &request).await;
+            if let Some(reply) = reply {
+                let target = reply_target(&request, &reply);
+                if let Err(e) = socket.send_to(&reply.to_bytes(), target).await {
+                    warn!(bridge = %bridge, target = %target, error = %e, "Failed to send DHCP reply");
+                }
+            }
+        }
+    }
+    
+    async fn handle(&self, bridge: &str, request: &DhcpPacket) -> Option<DhcpPacket> {
+        let mut pools = self.pools.lock().await;
+        let pool = pools.get_mut(bridge)?;
+        let (reply, changed) = pool.handle(request, now());
+        
+        let mac = request.client_mac();
+        match (request.message_type(), reply.as_ref().and_then(|r| r.message_type())) {
+            (_, Some(MessageType::Ack)) if !reply.as_ref()?.yiaddr.is_unspecified() => {
+                info!(bridge = %bridge, mac = %mac, ip = %reply.as_ref()?.yiaddr, "DHCP lease bound");
+            }
+            (_, Some(MessageType::Nak)) => {
+                debug!(bridge = %bridge, mac = %mac, requested = ?request.requested_ip(), "DHCP request refused");
+            }
+            (Some(MessageType::Discover), None) => {
+                warn!(bridge = %bridge, mac = %mac, "DHCP pool exhausted");
+            }
+            (Some(MessageType::Decline), _) => {
+                warn!(bridge = %bridge, mac = %mac, ip = ?request.requested_ip(), "Client declined address (in use elsewhere)");
+            }
+            _ => {}
+        }
+        
+        if changed {
+            if let Err(e) = self.save(&pools).await {
+                warn!(bridge = %bridge, error = %e, "Failed to save DHCP leases");
+            }
+        }
+        reply
+    }
+    
+    async fn save(&self, pools: &BTreeMap<String, DhcpPool>) -> Result<(), DhcpError> {
+        let state = DhcpState {
+            configs: pools.iter().map(|(b, p)| (b.clone(), p.config().clone())).collect(),
+            leases: pools.iter().map(|(b, p)| (b.clone(), p.dynamic_leases().cloned().collect())).collect(),
+        };
+        let data = serde_json::to_vec_pretty(&state).map_err(|e| DhcpError::Failed(e.into()))?;
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(|e| DhcpError::Failed(e.into()))?;
+        }
+        let tmp = self.path.with_extension("tmp");
+        tokio::fs::write(&tmp, data).await.map_err(|e| DhcpError::Failed(e.into()))?;
+        tokio::fs::rename(&tmp, &self.path).await.map_err(|e| DhcpError::Failed(e.into()))
+    }
+}
+
+/// Where to send a reply (RFC 2131 section 4.1).
+fn reply_target(request: &DhcpPacket, reply: &DhcpPacket) -> SocketAddrV4 {
+    if !request.giaddr.is_unspecified() {
+        return SocketAddrV4::new(request.giaddr, dhcp::SERVER_PORT);
+    }
+    if !request.ciaddr.is_unspecified() && reply.message_type() != Some(MessageType::Nak) {
+        return SocketAddrV4::new(request.ciaddr, dhcp::CLIENT_PORT);
+    }
+    // The client has no address yet; unicasting to yiaddr would need a static ARP entry
+    SocketAddrV4::new(Ipv4Addr::BROADCAST, dhcp::CLIENT_PORT)
+}
+
+/// Open a UDP socket on port 67 that only sees traffic of `bridge`.
+///
+/// Several bridges (and libvirt's dnsmasq on virbr0) can listen on port 67
+/// at the same time because every socket is bound to its device.
+fn bind_socket(bridge: &str) -> std::io::Result<std::net::UdpSocket> {
+    use std::os::fd::{AsRawFd, FromRawFd};
+    
+    fn check(ret: libc::c_int) -> std::io::Result<()> {
+        if ret < 0 {
+            Err(std::io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+    
+    // SAFETY: plain socket syscalls on a descriptor we own; the UdpSocket
+    // takes ownership immediately so it is closed on every error path.
+    unsafe {
+        let fd = libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC, 0);
+        check(fd)?;
+        let socket = std::net::UdpSocket::from_raw_fd(fd);
+        let fd = socket.as_raw_fd();
+        
+        let one: libc::c_int = 1;
+        for option in [libc::SO_REUSEADDR, libc::SO_BROADCAST] {
+            check(libc::setsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                option,
+                &one as *const libc::c_int as *const libc::c_void,
+                std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+            ))?;
+        }
+        check(libc::setsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_BINDTODEVICE,
+            bridge.as_ptr() as *const libc::c_void,
+            bridge.len() as libc::socklen_t,
+        ))?;
+        
+        let addr = libc::sockaddr_in {
+            sin_family: libc::AF_INET as libc::sa_family_t,
+            sin_port: dhcp::SERVER_PORT.to_be(),
+            sin_addr: libc::in_addr { s_addr: libc::INADDR_ANY },
+            sin_zero: [0; 8],
+        };
+        check(libc::bind(
+            fd,
+            &addr as *const libc::sockaddr_in as *const libc::sockaddr,
+            std::mem::size_of::<libc::sockaddr_in>() as libc::socklen_t,
+        ))?;
+        
+        Ok(socket)
+    }
+}
+
+/// Global DHCP manager
+static DHCP: std::sync::OnceLock<DhcpManager> = std::sync::OnceLock::new();
+
+/// Get the global DHCP manager.
+pub fn dhcp() -> &'static DhcpManager {
+    DHCP.get_or_init(|| DhcpManager::new(PathBuf::from(STATE_PATH)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_reply_target() {
+        let mut request = DhcpPacket {
+            op: 1,
+            xid: 1,
+            secs: 0,
+            flags: 0,
+            ciaddr: Ipv4Addr::UNSPECIFIED,
+            yiaddr: Ipv4Addr::UNSPECIFIED,
+            siaddr: Ipv4Addr::UNSPECIFIED,
+            giaddr: Ipv4Addr::UNSPECIFIED,
+            chaddr: [0x52, 0x54, 0, 0, 0, 1],
+            options: vec![(53, vec![MessageType::Request as u8])],
+        };
+        let mut reply = request.clone();
+        reply.options = vec![(53, vec![MessageType::Ack as u8])];
+        
+        assert_eq!(reply_target(&request, &reply), SocketAddrV4::new(Ipv4Addr::BROADCAST, 68));
+        
+        request.ciaddr = Ipv4Addr::new(192, 168, 50, 100);
+        assert_eq!(reply_target(&request, &reply), SocketAddrV4::new(request.ciaddr, 68));
+        
+        reply.options = vec![(53, vec![MessageType::Nak as u8])];
+        assert_eq!(reply_target(&request, &reply), SocketAddrV4::new(Ipv4Addr::BROADCAST, 68));
+        
+        request.giaddr = Ipv4Addr::new(10, 0, 0, 1);
+        assert_eq!(reply_target(&request, &reply), SocketAddrV4::new(request.giaddr, 67));
+    }
+}
@@ -0,0 +1,422 @@
+//! Security Group Firewall - nftables enforcement for bridge-attached NICs.
+//!
+//! VMs attached to OVN get security groups as OVN ACLs. VMs on plain Linux
+//! bridges (`NicConfig.bridge`) are filtered here instead:
+//! - Security groups and per-VM port bindings (MAC, addresses, groups) are
+//!   stored in `/var/lib/limiquantix/firewall.json`
+//! - `sync()` resolves each bound MAC to the tap libvirt created for it
+//!   (`virsh domiflist`) and replaces the nftables table atomically
+//! - Ports come from the API or from the control plane's `NetworkPortConfig`
+//!   (`port_security_enabled`, `security_group_ids`) for non-OVN ports
+//! - Sync runs at startup (reboot), when a VM starts or stops, and after
+//!   every change through the API; a VM whose rules cannot be installed is
+//!   not left running
+//!
+//! Taps are renumbered whenever VMs restart, which is why rules are always
+//! rebuilt from the bindings rather than patched.
+
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use limiquantix_hypervisor::network::firewall::{self, PortFilter, SecurityGroup};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+use crate::event_store::{emit_event, Event, EventCategory, EventLevel};
+
+/// Where security groups and port bindings are stored
+const STATE_PATH: &str = "/var/lib/limiquantix/firewall.json";
+
+/// Security settings of one VM NIC.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PortSecurity {
+    /// Guest MAC address (identifies the NIC)
+    pub mac_address: String,
+    /// Guest addresses allowed as source (empty = no IP anti-spoofing)
+    #[serde(default)]
+    pub ip_addresses: Vec<String>,
+    /// Enforce MAC/IP anti-spoofing
+    #[serde(default = "default_true")]
+    pub port_security_enabled: bool,
+    #[serde(default)]
+    pub security_group_ids: Vec<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Persisted firewall state.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FirewallState {
+    #[serde(default)]
+    security_groups: BTreeMap<String, SecurityGroup>,
+    /// VM ID -> NIC bindings
+    #[serde(default)]
+    vm_ports: BTreeMap<String, Vec<PortSecurity>>,
+}
+
+/// Result of a sync.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncResult {
+    /// Taps with rules installed
+    pub filtered_taps: Vec<String>,
+    /// Bound NICs without a tap (VM not running)
+    pub inactive_ports: usize,
+    /// The ruleset that was loaded
+    pub ruleset: String,
+}
+
+/// Errors from the firewall manager.
+#[derive(Debug, thiserror::Error)]
+pub enum FirewallError {
+    #[error("Security group {0} not found")]
+    NotFound(String),
+    
+    #[error("Security group {0} is still used by VM {1}")]
+    InUse(String, String),
+    
+    #[error("{0:#}")]
+    Invalid(anyhow::Error),
+    
+    #[error("{0:#}")]
+    Failed(anyhow::Error),
+}
+
+/// Security group store and nftables synchronizer.
+pub struct FirewallManager {
+    state: Mutex<FirewallState>,
+    path: PathBuf,
+}
+
+impl FirewallManager {
+    fn new(path: PathBuf) -> Self {
+        let state = match std::fs::read(&path) {
+            Ok(data) => serde_json::from_slice(&data).unwrap_or_else(|e| {
+                warn!(path = %path.display(), error = %e, "Failed to parse firewall state, starting empty");
+                FirewallState::default()
+            }),
+            Err(_) => FirewallState::default(),
+        };
+        
+        Self {
+            state: Mutex::new(state),
+            path,
+        }
+    }
+    
+    /// All security groups.
+    pub async fn list_groups(&self) -> Vec<SecurityGroup> {
+        self.state.lock().await.security_groups.values().cloned().collect()
+    }
+    
+    /// A security group by ID.
+    pub async fn get_group(&self, id: &str) -> Result<SecurityGroup, FirewallError> {
+        self.state.lock().await.security_groups.get(id)
+            .cloned()
+            .ok_or_else(|| FirewallError::NotFound(id.to_string()))
+    }
+    
+    /// Create or replace a security group and re-sync.
+    pub async fn put_group(&self, group: SecurityGroup) -> Result<SyncResult, FirewallError> {
+        group.validate().map_err(FirewallError::Invalid)?;
+        
+        let mut state = self.state.lock().await;
+        for rule in &group.rules {
+            if let Some(ref remote) = rule.remote_security_group_id {
+                if *remote != group.id && !state.security_groups.contains_key(remote) {
+                    return Err(FirewallError::NotFound(remote.clone()));
+                }
+            }
+        }
+        
+        info!(group_id = %group.id, rules = group.rules.len(), "Saving security group");
+        state.security_groups.insert(group.id.clone(), group);
+        self.save(&state).await?;
+        self.sync_locked(&state).await
+    }
+    
+    /// Delete an unused security group.
+    pub async fn delete_group(&self, id: &str) -> Result<(), FirewallError> {
+        let mut state = self.state.lock().await;
+        if !state.security_groups.contains_key(id) {
+            return Err(FirewallError::NotFound(id.to_string()));
+        }
+        if let Some((vm_id, _)) = state.vm_ports.iter()
+            .find(|(_, ports)| ports.iter().any(|p| p.security_group_ids.iter().any(|g| g == id)))
+        {
+            return Err(FirewallError::InUse(id.to_string(), vm_id.clone()));
+        }
+        if let Some(other) = state.security_groups.values()
+            .find(|g| g.id != id && g.rules.iter().any(|r| r.remote_security_group_id.as_deref() == Some(id)))
+        {
+            return Err(FirewallError::Invalid(anyhow::anyhow!(
+                "Security group {} is referenced by rules of {}", id, other.name
+            )));
+        }
+        
+        state.security_groups.remove(id);
+        self.save(&state).await
+    }
+    
+    /// Security settings of a VM's NICs.
+    pub async fn vm_ports(&self, vm_id: &str) -> Vec<PortSecurity> {
+        self.state.lock().await.vm_ports.get(vm_id).cloned().unwrap_or_default()
+    }
+    
+    /// Replace the security settings of a VM's NICs and re-sync.
+    pub async fn set_vm_ports(&self, vm_id: &str, ports: Vec<PortSecurity>) -> Result<SyncResult, FirewallError> {
+        let mut state = self.state.lock().await;
+        for port in &ports {
+            validate_port(&state, port)?;
+        }
+        
+        info!(vm_id = %vm_id, nics = ports.len(), "Setting VM port security");
+        if ports.is_empty() {
+            state.vm_ports.remove(vm_id);
+        } else {
+            state.vm_ports.insert(vm_id.to_string(), ports);
+        }
+        self.save(&state).await?;
+        self.sync_locked(&state).await
+    }
+    
+    /// Add or replace the settings of one NIC (matched by MAC) and re-sync.
+    pub async fn bind_port(&self, vm_id: &str, port: PortSecurity) -> Result<SyncResult, FirewallError> {
+        let mut state = self.state.lock().await;
+        validate_port(&state, &port)?;
+        
+        info!(vm_id = %vm_id, mac = %port.mac_address, groups = ?port.security_group_ids, "Binding port security");
+        let ports = state.vm_ports.entry(vm_id.to_string()).or_default();
+        ports.retain(|p| !p.mac_address.eq_ignore_ascii_case(&port.mac_address));
+        ports.push(port);
+        self.save(&state).await?;
+        self.sync_locked(&state).await
+    }
+    
+    /// Remove the settings of one NIC and re-sync.
+    pub async fn unbind_port(&self, vm_id: &str, mac_address: &str) -> Result<(), FirewallError> {
+        let mut state = self.state.lock().await;
+        let Some(ports) = state.vm_ports.get_mut(vm_id) else {
+            return Ok(());
+        };
+        ports.retain(|p| !p.mac_address.eq_ignore_ascii_case(mac_address));
+        if ports.is_empty() {
+            state.vm_ports.remove(vm_id);
+        }
+        self.save(&state).await?;
+        self.sync_locked(&state).await.map(|_| ())
+    }
+    
+    /// Sync after a VM started and check that every bound NIC of the VM is
+    /// filtered. An error means the VM must not keep running.
+    pub async fn sync_vm(&self, vm_id: &str) -> Result<(), FirewallError> {
+        let state = self.state.lock().await;
+        let Some(ports) = state.vm_ports.get(vm_id) else {
+            // Nothing to enforce for this VM; other VMs' rules are best effort
+            drop(state);
+            self.sync_logged().await;
+            return Ok(());
+        };
+        
+        let result = self.sync_locked(&state).await?;
+        let taps = vm_taps(vm_id).await;
+        for port in ports {
+            let filtered = taps.iter()
+                .any(|(tap, mac)| mac.eq_ignore_ascii_case(&port.mac_address) && result.filtered_taps.contains(tap));
+            if !filtered {
+                return Err(FirewallError::Failed(anyhow::anyhow!(
+                    "NIC {} has no tap to filter", port.mac_address
+                )));
+            }
+        }
+        Ok(())
+    }
+    
+    /// Forget a deleted VM.
+    pub async fn remove_vm(&self, vm_id: &str) {
+        let mut state = self.state.lock().await;
+        if state.vm_ports.remove(vm_id).is_some() {
+            if let Err(e) = self.save(&state).await {
+                warn!(vm_id = %vm_id, error = %e, "Failed to save firewall state");
+            }
+            if let Err(e) = self.sync_locked(&state).await {
+                warn!(vm_id = %vm_id, error = %e, "Failed to sync firewall rules");
+            }
+        }
+    }
+    
+    /// Rebuild and load the ruleset for the running VMs.
+    pub async fn sync(&self) -> Result<SyncResult, FirewallError> {
+        let state = self.state.lock().await;
+        self.sync_locked(&state).await
+    }
+    
+    /// Sync, reporting failures as events (for background triggers).
+    pub async fn sync_logged(&self) {
+        if let Err(e) = self.sync().await {
+            emit_event(Event::new(
+                EventLevel::Error,
+                EventCategory::Security,
+                format!("Failed to apply security group rules: {}", e),
+                "firewall",
+            ));
+        }
+    }
+    
+    async fn sync_locked(&self, state: &FirewallState) -> Result<SyncResult, FirewallError> {
+        let mut filters = Vec::new();
+        let mut inactive_ports = 0;
+        
+        for (vm_id, ports) in &state.vm_ports {
+            let taps = vm_taps(vm_id).await;
+            for port in ports {
+                let tap = taps.iter()
+                    .find(|(_, mac)| mac.eq_ignore_ascii_case(&port.mac_address))
+                    .map(|(tap, _)| tap.clone());
+                let Some(tap) = tap else {
+                    inactive_ports += 1;
+                    continue;
+                };
+                filters.push(PortFilter {
+                    tap,
+                    mac_address: port.mac_address.clone(),
+                    ip_addresses: port.ip_addresses.clone(),
+                    port_security_enabled: port.port_security_enabled,
+                    security_group_ids: port.security_group_ids.clone(),
+                });
+            }
+        }
+        
+        let ruleset = firewall::render_ruleset(&state.security_groups, &filters)
+            .map_err(FirewallError::Invalid)?;
+        
+        // Nothing to enforce and nothing to clean up on hosts without nftables
+        let nft_missing = which_nft().is_none();
+        if filters.is_empty() && nft_missing {
+            debug!("nft not installed and no filtered ports, skipping sync");
+        } else {
+            let script = ruleset.clone();
+            tokio::task::spawn_blocking(move || firewall::apply_ruleset(&script)).await
+                .map_err(|e| FirewallError::Failed(e.into()))?
+                .map_err(FirewallError::Failed)?;
+        }
+        
+        let filtered_taps: Vec<String> = filters.into_iter().map(|f| f.tap).collect();
+        debug!(taps = ?filtered_taps, inactive = inactive_ports, "Security group rules synced");
+        
+        Ok(SyncResult {
+            filtered_taps,
+            inactive_ports,
+            ruleset,
+        })
+    }
+    
+    async fn save(&self, state: &FirewallState) -> Result<(), FirewallError> {
+        let data = serde_json::to_vec_pretty(state).map_err(|e| FirewallError::Failed(e.into()))?;
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(|e| FirewallError::Failed(e.into()))?;
+        }
+        let tmp = self.path.with_extension("tmp");
+        tokio::fs::write(&tmp, data).await.map_err(|e| FirewallError::Failed(e.into()))?;
+        tokio::fs::rename(&tmp, &self.path).await.map_err(|e| FirewallError::Failed(e.into()))
+    }
+}
+
+/// Check a NIC binding against the stored security groups.
+fn validate_port(state: &FirewallState, port: &PortSecurity) -> Result<(), FirewallError> {
+    firewall::validate_mac(&port.mac_address).map_err(FirewallError::Invalid)?;
+    for addr in &port.ip_addresses {
+        let plain = addr.split('/').next().unwrap_or_default();
+        plain.parse::<std::net::IpAddr>()
+            .map_err(|_| FirewallError::Invalid(anyhow::anyhow!("Invalid address {}", addr)))?;
+    }
+    if let Some(missing) = port.security_group_ids.iter().find(|g| !state.security_groups.contains_key(*g)) {
+        return Err(FirewallError::NotFound(missing.clone()));
+    }
+    Ok(())
+}
+
+fn which_nft() -> Option<PathBuf> {
+    std::env::var_os("PATH")
+        .into_iter()
+        .flat_map(|paths| std::env::split_paths(&paths).collect::<Vec<_>>())
+        .map(|dir| dir.join("nft"))
+        .find(|p| p.exists())
+}
+
+/// Tap devices and guest MACs of a running VM (empty if it is not running).
+pub(crate) async fn vm_taps(vm_id: &str) -> Vec<(String, String)> {
+    vm_interfaces(vm_id).await
+        .into_iter()
+        .filter_map(|(tap, mac)| Some((tap?, mac)))
+        .collect()
+}
+
+/// Interfaces of a VM, running or not, as (tap, guest MAC) pairs. The tap
+/// is only known while the VM runs.
+pub(crate) async fn vm_interfaces(vm_id: &str) -> Vec<(Option<String>, String)> {
+    let output = match tokio::process::Command::new("virsh")
+        .args(["domiflist", vm_id])
+        .output()
+        .await
+    {
+        Ok(o) if o.status.success() => o,
+        _ => return Vec::new(),
+    };
+    
+    parse_domiflist(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parse `virsh domiflist` output into (tap, MAC) pairs.
+///
+/// ```text
+///  Interface   Type     Source   Model    MAC
+/// -------------------------------------------------------
+///  vnet3       bridge   br0      virtio   52:54:00:12:34:56
+/// ```
+fn parse_domiflist(output: &str) -> Vec<(Option<String>, String)> {
+    output.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let (tap, mac) = (fields.first()?, fields.last()?);
+            // Header and separator
+            if fields.len() < 5 || *tap == "Interface" || firewall::validate_mac(mac).is_err() {
+                return None;
+            }
+            // "-" for interfaces of stopped VMs
+            let tap = (*tap != "-").then(|| tap.to_string());
+            Some((tap, mac.to_lowercase()))
+        })
+        .collect()
+}
+
+/// Global firewall manager
+static FIREWALL: std::sync::OnceLock<Arc<FirewallManager>> = std::sync::OnceLock::new();
+
+/// Get the global firewall manager.
+pub fn firewall() -> &'static Arc<FirewallManager> {
+    FIREWALL.get_or_init(|| Arc::new(FirewallManager::new(PathBuf::from(STATE_PATH))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_domiflist() {
+        let output = " Interface   Type      Source    Model    MAC\n\
+                      -------------------------------------------------------------\n \
+                      vnet3       bridge    br0       virtio   52:54:00:AB:CD:EF\n \
+                      -           network   default   virtio   52:54:00:11:22:33\n";
+        assert_eq!(parse_domiflist(output), vec![
+            (Some("vnet3".to_string()), "52:54:00:ab:cd:ef".to_string()),
+            (None, "52:54:00:11:22:33".to_string()),
+        ]);
+    }
+}
@@ -0,0 +1,218 @@
+//! Guest Event Monitor - Reports watchdog firings and guest panics.
+//!
+//! The state watcher polls domain state, which cannot see a watchdog reset
+//! or a panic followed by an automatic restart: the VM is "running" before
+//! and after. This module subscribes to libvirt's domain event stream through
+//! `virsh event --loop` and turns these device events into VM events in the
+//! `EventStore`.
+//!
+//! Start and stop lifecycle events also trigger a security group sync, since
+//! libvirt hands out new tap devices whenever a VM starts.
+//!
+//! Design Decisions:
+//! - `virsh` subprocess instead of libvirt callbacks (the `virt` crate has no
+//!   domain event bindings, and we already shell out to virsh elsewhere)
+//! - The subprocess is restarted with a delay if it exits (libvirtd restart)
+//! - Domains are reported by name; names are resolved to UUIDs via the
+//!   hypervisor so events attach to the right VM resource
+
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+use tracing::{debug, info, instrument, warn};
+
+use limiquantix_hypervisor::Hypervisor;
+
+use crate::event_store::{emit_event, Event, EventLevel};
+
+/// Delay before restarting `virsh event` after it exits.
+const RESTART_DELAY: Duration = Duration::from_secs(5);
+
+/// A guest device event parsed from the libvirt event stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GuestDeviceEvent {
+    /// The watchdog fired and QEMU performed `action`.
+    Watchdog { domain: String, action: String },
+    /// The guest reported a kernel panic / bugcheck through the panic device.
+    Panicked { domain: String },
+}
+
+impl GuestDeviceEvent {
+    /// Get the libvirt domain name from the event.
+    pub fn domain(&self) -> &str {
+        match self {
+            GuestDeviceEvent::Watchdog { domain, .. } => domain,
+            GuestDeviceEvent::Panicked { domain } => domain,
+        }
+    }
+}
+
+/// Guest Event Monitor - Streams libvirt device events into the event store.
+pub struct GuestEventMonitor {
+    /// Hypervisor backend for resolving domain names to VM IDs
+    hypervisor: Arc<dyn Hypervisor>,
+    /// Libvirt connection URI passed to virsh
+    libvirt_uri: Option<String>,
+}
+
+impl GuestEventMonitor {
+    /// Create a new GuestEventMonitor.
+    pub fn new(hypervisor: Arc<dyn Hypervisor>, libvirt_uri: Option<String>) -> Self {
+        Self { hypervisor, libvirt_uri }
+    }
+    
+    /// Run the monitor loop (never returns).
+    #[instrument(skip(self))]
+    pub async fn run(&self) {
+        info!("Starting guest event monitor");
+        
+        loop {
+            if let Err(e) = self.stream_events().await {
+                warn!(error = %e, "Guest event stream failed");
+            }
+            
+            debug!(delay_secs = RESTART_DELAY.as_secs(), "Restarting guest event stream");
+            tokio::time::sleep(RESTART_DELAY).await;
+        }
+    }
+    
+    /// Spawn `virsh event` and process lines until it exits.
+    async fn stream_events(&self) -> anyhow::Result<()> {
+        let mut cmd = Command::new("virsh");
+        if let Some(uri) = &self.libvirt_uri {
+            cmd.arg("-c").arg(uri);
+        }
+        cmd.args(["event", "--all", "--loop"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true);
+        
+        let mut child = cmd.spawn()?;
+        let stdout = child.stdout.take()
+            .ok_or_else(|| anyhow::anyhow!("virsh event has no stdout"))?;
+        let mut lines = BufReader::new(stdout).lines();
+        
+        while let Some(line) = lines.next_line().await? {
+            if let Some(event) = parse_virsh_event(&line) {
+                self.report(event).await;
+            }
+            if changes_taps(&line) {
+                tokio::spawn(async {
+                    crate::firewall::firewall().sync_logged().await;
+                });
+            }
+        }
+        
+        let status = child.wait().await?;
+        Err(anyhow::anyhow!("virsh event exited: {}", status))
+    }
+    
+    /// Emit a VM event for a guest device event.
+    async fn report(&self, event: GuestDeviceEvent) {
+        let vm_id = self.resolve_vm_id(event.domain()).await;
+        
+        let (level, message, details) = match &event {
+            GuestDeviceEvent::Watchdog { domain, action } => (
+                EventLevel::Warning,
+                format!("Watchdog fired on VM '{}' (action: {})", domain, action),
+                serde_json::json!({ "device": "watchdog", "action": action }),
+            ),
+            GuestDeviceEvent::Panicked { domain } => (
+                EventLevel::Error,
+                format!("Guest OS panic reported by VM '{}'", domain),
+                serde_json::json!({ "device": "panic" }),
+            ),
+        };
+        
+        emit_event(Event::vm_event(level, &vm_id, message).with_details(details));
+    }
+    
+    /// Resolve a libvirt domain name to the VM UUID (falls back to the name).
+    async fn resolve_vm_id(&self, domain: &str) -> String {
+        match self.hypervisor.list_vms().await {
+            Ok(vms) => vms.into_iter()
+                .find(|vm| vm.name == domain)
+                .map(|vm| vm.id)
+                .unwrap_or_else(|| domain.to_string()),
+            Err(_) => domain.to_string(),
+        }
+    }
+}
+
+/// Parse a line of `virsh event` output.
+///
+/// Relevant formats:
+/// - `event 'watchdog' for domain 'NAME': reset`
+/// - `event 'lifecycle' for domain 'NAME': Crashed Panicked`
+///
+/// Lines may carry a `--timestamp` prefix, which is ignored.
+pub fn parse_virsh_event(line: &str) -> Option<GuestDeviceEvent> {
+    let start = line.find("event '")?;
+    let rest = &line[start + 7..];
+    let kind_end = rest.find('\'')?;
+    let kind = &rest[..kind_end];
+    
+    let rest = &rest[kind_end..];
+    let domain_start = rest.find("for domain '")? + 12;
+    let rest = &rest[domain_start..];
+    let domain_end = rest.rfind("': ")?;
+    let domain = rest[..domain_end].to_string();
+    let detail = rest[domain_end + 3..].trim();
+    
+    match kind {
+        "watchdog" => Some(GuestDeviceEvent::Watchdog {
+            domain,
+            action: detail.to_string(),
+        }),
+        "lifecycle" if detail.starts_with("Crashed") && detail.contains("Panicked") => {
+            Some(GuestDeviceEvent::Panicked { domain })
+        }
+        _ => None,
+    }
+}
+
+/// Whether a `virsh event` line is a VM start or stop (taps created or removed).
+pub fn changes_taps(line: &str) -> bool {
+    line.contains("event 'lifecycle' for domain '")
+        && (line.contains("': Started ") || line.contains("': Stopped "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_parse_watchdog_event() {
+        let event = parse_virsh_event("event 'watchdog' for domain 'web-01': reset");
+        assert_eq!(event, Some(GuestDeviceEvent::Watchdog {
+            domain: "web-01".to_string(),
+            action: "reset".to_string(),
+        }));
+    }
+    
+    #[test]
+    fn test_parse_panic_event_with_timestamp() {
+        let event = parse_virsh_event(
+            "2026-03-01 10:00:00.123+0000: event 'lifecycle' for domain 'db': Crashed Panicked"
+        );
+        assert_eq!(event, Some(GuestDeviceEvent::Panicked { domain: "db".to_string() }));
+    }
+    
+    #[test]
+    fn test_ignore_other_events() {
+        assert_eq!(parse_virsh_event("event 'lifecycle' for domain 'db': Started Booted"), None);
+        assert_eq!(parse_virsh_event("event 'reboot' for domain 'db'"), None);
+        assert_eq!(parse_virsh_event("events received: 3"), None);
+    }
+    
+    #[test]
+    fn test_lifecycle_changes_taps() {
+        assert!(changes_taps("event 'lifecycle' for domain 'db': Started Booted"));
+        assert!(changes_taps("event 'lifecycle' for domain 'db': Stopped Destroyed"));
+        assert!(!changes_taps("event 'lifecycle' for domain 'db': Suspended Paused"));
+        assert!(!changes_taps("event 'watchdog' for domain 'db': reset"));
+    }
+}
@@ -0,0 +1,260 @@
+//! Host Firewall - Source restrictions for management-plane services.
+//!
+//! The rules themselves are rendered by
+//! `limiquantix_hypervisor::network::host_firewall`. Restricting the web UI
+//! or SSH can lock out the client making the change, so remote changes use
+//! the same safe-apply scheme as host network changes:
+//! 1. The new ruleset is loaded; the previous configuration is kept
+//! 2. The change stays pending for `confirm_timeout`
+//! 3. The client confirms it through the new rules (`POST /settings/firewall/confirm`);
+//!    the HTTP handlers refuse changes and confirmations from clients the new
+//!    rules would block
+//! 4. If no confirmation arrives in time, the previous rules are restored
+//!
+//! Confirmed configurations are saved and loaded again when the node starts.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use limiquantix_hypervisor::network::firewall;
+use limiquantix_hypervisor::network::host_firewall::{render_host_ruleset, HostFirewallConfig};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::event_store::{emit_event, Event, EventCategory, EventLevel};
+
+/// Default confirm window for remote changes
+pub const DEFAULT_CONFIRM_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Persistent config partition on Quantix-OS
+const PERSISTENT_DIR: &str = "/quantix/firewall";
+
+/// Saved configuration on other distributions
+const FALLBACK_PATH: &str = "/etc/limiquantix/host-firewall.json";
+
+/// Where the committed host firewall configuration is saved.
+fn persist_path() -> PathBuf {
+    if Path::new("/quantix").exists() {
+        Path::new(PERSISTENT_DIR).join("host-firewall.json")
+    } else {
+        PathBuf::from(FALLBACK_PATH)
+    }
+}
+
+/// Errors from host firewall changes.
+#[derive(Debug, thiserror::Error)]
+pub enum HostFirewallError {
+    #[error("Another firewall change ({0}) is waiting for confirmation")]
+    Pending(String),
+    
+    #[error("No pending firewall change with ID {0}")]
+    NotPending(String),
+    
+    #[error("{0:#}")]
+    Invalid(anyhow::Error),
+    
+    #[error("{0:#}")]
+    Failed(anyhow::Error),
+}
+
+struct PendingChange {
+    id: String,
+    /// Restored when the change is rolled back
+    previous: HostFirewallConfig,
+    deadline: DateTime<Utc>,
+}
+
+struct HostFirewallState {
+    /// Configuration currently loaded into nftables
+    current: HostFirewallConfig,
+    pending: Option<PendingChange>,
+}
+
+/// Serializes host firewall changes and tracks the pending one.
+pub struct HostFirewallManager {
+    state: Mutex<HostFirewallState>,
+}
+
+impl HostFirewallManager {
+    fn new() -> Self {
+        Self {
+            state: Mutex::new(HostFirewallState {
+                current: HostFirewallConfig::default(),
+                pending: None,
+            }),
+        }
+    }
+    
+    /// Configuration currently in effect.
+    pub async fn current(&self) -> HostFirewallConfig {
+        self.state.lock().await.current.clone()
+    }
+    
+    /// Load `config` into nftables.
+    ///
+    /// With a `confirm_timeout`, the previous rules are restored unless
+    /// `confirm` is called before the deadline. Returns the transaction ID
+    /// and deadline of the pending change.
+    pub async fn apply(
+        self: &Arc<Self>,
+        config: HostFirewallConfig,
+        confirm_timeout: Option<Duration>,
+    ) -> Result<Option<(String, DateTime<Utc>)>, HostFirewallError> {
+        let mut state = self.state.lock().await;
+        if let Some(ref p) = state.pending {
+            return Err(HostFirewallError::Pending(p.id.clone()));
+        }
+        
+        load(&config).await?;
+        let previous = std::mem::replace(&mut state.current, config.clone());
+        
+        let timeout = match confirm_timeout {
+            Some(t) if !t.is_zero() && previous != config => t,
+            _ => {
+                persist(&config).await;
+                return Ok(None);
+            }
+        };
+        
+        let id = uuid::Uuid::new_v4().to_string();
+        let deadline = Utc::now() + chrono::Duration::from_std(timeout).unwrap_or_default();
+        state.pending = Some(PendingChange {
+            id: id.clone(),
+            previous,
+            deadline,
+        });
+        
+        info!(transaction_id = %id, timeout_secs = timeout.as_secs(), "Host firewall change applied, waiting for confirmation");
+        
+        let manager = self.clone();
+        let timer_id = id.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(timeout).await;
+            match manager.rollback_if_pending(&timer_id).await {
+                Ok(true) => {
+                    emit_event(Event::new(
+                        EventLevel::Warning,
+                        EventCategory::Security,
+                        "Host firewall change was not confirmed in time and has been rolled back",
+                        "firewall",
+                    ));
+                }
+                Ok(false) => {}
+                Err(e) => warn!(transaction_id = %timer_id, error = %e, "Automatic firewall rollback failed"),
+            }
+        });
+        
+        Ok(Some((id, deadline)))
+    }
+    
+    /// Keep a pending change.
+    pub async fn confirm(&self, transaction_id: &str) -> Result<(), HostFirewallError> {
+        let mut state = self.state.lock().await;
+        if state.pending.take_if(|p| p.id == transaction_id).is_none() {
+            return Err(HostFirewallError::NotPending(transaction_id.to_string()));
+        }
+        
+        info!(transaction_id = %transaction_id, "Host firewall change confirmed");
+        emit_event(Event::new(
+            EventLevel::Info,
+            EventCategory::Security,
+            "Host firewall configuration changed",
+            "firewall",
+        ));
+        persist(&state.current).await;
+        Ok(())
+    }
+    
+    /// Roll back the pending change now.
+    pub async fn rollback(&self, transaction_id: &str) -> Result<(), HostFirewallError> {
+        match self.rollback_if_pending(transaction_id).await? {
+            true => Ok(()),
+            false => Err(HostFirewallError::NotPending(transaction_id.to_string())),
+        }
+    }
+    
+    /// ID and deadline of the pending change, if any.
+    pub async fn pending(&self) -> Option<(String, DateTime<Utc>)> {
+        self.state.lock().await.pending.as_ref().map(|p| (p.id.clone(), p.deadline))
+    }
+    
+    async fn rollback_if_pending(&self, transaction_id: &str) -> Result<bool, HostFirewallError> {
+        let mut state = self.state.lock().await;
+        let Some(p) = state.pending.take_if(|p| p.id == transaction_id) else {
+            return Ok(false);
+        };
+        
+        warn!(transaction_id = %transaction_id, "Rolling back host firewall change");
+        load(&p.previous).await?;
+        state.current = p.previous;
+        Ok(true)
+    }
+}
+
+/// Render and load `config` in one nftables transaction.
+async fn load(config: &HostFirewallConfig) -> Result<(), HostFirewallError> {
+    let ruleset = render_host_ruleset(config).map_err(HostFirewallError::Invalid)?;
+    tokio::task::spawn_blocking(move || firewall::apply_ruleset(&ruleset)).await
+        .map_err(|e| HostFirewallError::Failed(e.into()))?
+        .map_err(HostFirewallError::Failed)
+}
+
+/// Load the saved host firewall configuration at startup.
+pub async fn restore_persisted() {
+    let path = persist_path();
+    let config: HostFirewallConfig = match tokio::fs::read(&path).await {
+        Ok(data) => match serde_json::from_slice(&data) {
+            Ok(config) => config,
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "Failed to parse saved host firewall configuration");
+                return;
+            }
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return,
+        Err(e) => {
+            warn!(path = %path.display(), error = %e, "Failed to load saved host firewall configuration");
+            return;
+        }
+    };
+    
+    match host_firewall().apply(config, None).await {
+        Ok(_) => info!("Restored saved host firewall configuration"),
+        Err(e) => {
+            emit_event(Event::new(
+                EventLevel::Error,
+                EventCategory::Security,
+                format!("Failed to restore saved host firewall configuration: {}", e),
+                "firewall",
+            ));
+        }
+    }
+}
+
+/// Save a committed configuration (failures are logged, the rules stay loaded).
+async fn persist(config: &HostFirewallConfig) {
+    let path = persist_path();
+    let result = async {
+        let data = serde_json::to_vec_pretty(config)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let tmp = path.with_extension("tmp");
+        tokio::fs::write(&tmp, data).await?;
+        tokio::fs::rename(&tmp, &path).await?;
+        anyhow::Ok(())
+    }.await;
+    
+    if let Err(e) = result {
+        warn!(error = %format!("{:#}", e), "Failed to save host firewall configuration");
+    }
+}
+
+/// Global host firewall manager (shared by the HTTP and HTTPS servers)
+static HOST_FIREWALL: std::sync::OnceLock<Arc<HostFirewallManager>> = std::sync::OnceLock::new();
+
+/// Get the global host firewall manager.
+pub fn host_firewall() -> &'static Arc<HostFirewallManager> {
+    HOST_FIREWALL.get_or_init(|| Arc::new(HostFirewallManager::new()))
+}
@@ -0,0 +1,313 @@
+//! Host Network Transactions - Apply, confirm or roll back.
+//!
+//! Host network changes made from the web UI can cut off the client that
+//! made them. Every change therefore goes through a `NetworkTransaction`
+//! (see `limiquantix_hypervisor::network::host`) and, for remote clients,
+//! a confirm window:
+//! 1. The change is applied; failures, a lost management address and an
+//!    unreachable gateway roll back immediately
+//! 2. The transaction stays pending for `confirm_timeout`
+//! 3. The client confirms it over the new configuration (`POST /network/config/confirm`)
+//! 4. If no confirmation arrives in time, the previous state is restored
+//!
+//! Only one transaction can be pending at a time.
+//!
+//! Committed configurations are saved and re-applied when the node starts,
+//! so bonds, VLANs and bridges survive a reboot.
+
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use limiquantix_hypervisor::network::host::{self, HostNetworkConfig, NetworkChange, NetworkTransaction};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::event_store::{emit_event, Event, EventCategory, EventLevel};
+
+/// How long to wait for the management address and default gateway after applying a change
+pub const VERIFY_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Default confirm window for remote changes
+pub const DEFAULT_CONFIRM_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Persistent config partition on Quantix-OS
+const PERSISTENT_DIR: &str = "/quantix/network";
+
+/// Saved configuration on other distributions
+const FALLBACK_PATH: &str = "/etc/limiquantix/host-network.json";
+
+/// Where the committed host network configuration is saved.
+fn persist_path() -> PathBuf {
+    if Path::new("/quantix").exists() {
+        Path::new(PERSISTENT_DIR).join("host-network.json")
+    } else {
+        PathBuf::from(FALLBACK_PATH)
+    }
+}
+
+/// Errors from host network transactions.
+#[derive(Debug, thiserror::Error)]
+pub enum HostNetworkError {
+    #[error("Another network change ({0}) is waiting for confirmation")]
+    Pending(String),
+    
+    #[error("No pending network change with ID {0}")]
+    NotPending(String),
+    
+    #[error("{0:#}")]
+    Failed(anyhow::Error),
+}
+
+/// Result of an applied change.
+#[derive(Debug, Clone)]
+pub struct AppliedChange {
+    /// Transaction ID (empty when nothing needed confirming)
+    pub transaction_id: String,
+    /// Changes that were applied
+    pub changes: Vec<NetworkChange>,
+    /// Deadline for confirmation (None = already committed)
+    pub confirm_deadline: Option<DateTime<Utc>>,
+}
+
+struct PendingTransaction {
+    id: String,
+    transaction: NetworkTransaction,
+    /// Saved once the change is confirmed
+    desired: HostNetworkConfig,
+    deadline: DateTime<Utc>,
+}
+
+/// Serializes host network changes and tracks the pending transaction.
+pub struct HostNetworkManager {
+    pending: Mutex<Option<PendingTransaction>>,
+}
+
+impl HostNetworkManager {
+    fn new() -> Self {
+        Self {
+            pending: Mutex::new(None),
+        }
+    }
+    
+    /// Capture the current host network configuration.
+    pub async fn current(&self) -> Result<HostNetworkConfig, HostNetworkError> {
+        tokio::task::spawn_blocking(host::capture).await
+            .map_err(|e| HostNetworkError::Failed(e.into()))?
+            .map_err(HostNetworkError::Failed)
+    }
+    
+    /// Compute the changes needed to reach `desired` without applying them.
+    pub async fn plan(&self, desired: &HostNetworkConfig) -> Result<Vec<NetworkChange>, HostNetworkError> {
+        desired.validate().map_err(HostNetworkError::Failed)?;
+        let current = self.current().await?;
+        desired.validate_against(&current).map_err(HostNetworkError::Failed)?;
+        Ok(host::plan(&current, desired))
+    }
+    
+    /// Apply `desired`.
+    ///
+    /// With a `confirm_timeout`, the change is rolled back unless `confirm`
+    /// is called before the deadline. With a `management` address, the
+    /// change is rolled back right away if that address is lost.
+    pub async fn apply(
+        self: &Arc<Self>,
+        desired: HostNetworkConfig,
+        confirm_timeout: Option<Duration>,
+        management: Option<IpAddr>,
+    ) -> Result<AppliedChange, HostNetworkError> {
+        // Held for the whole apply so changes never interleave
+        let mut pending = self.pending.lock().await;
+        if let Some(ref p) = *pending {
+            return Err(HostNetworkError::Pending(p.id.clone()));
+        }
+        
+        let target = desired.clone();
+        let result = tokio::task::spawn_blocking(move || NetworkTransaction::apply(&target, VERIFY_TIMEOUT, management)).await
+            .map_err(|e| HostNetworkError::Failed(e.into()))?;
+        
+        let transaction = match result {
+            Ok(t) => t,
+            Err(e) => {
+                emit_event(Event::new(
+                    EventLevel::Error,
+                    EventCategory::Network,
+                    format!("Host network change failed: {:#}", e),
+                    "network",
+                ));
+                return Err(HostNetworkError::Failed(e));
+            }
+        };
+        
+        let changes = transaction.changes.clone();
+        let timeout = match confirm_timeout {
+            Some(t) if !t.is_zero() && !changes.is_empty() => t,
+            _ => {
+                if !changes.is_empty() {
+                    persist(&desired).await;
+                }
+                return Ok(AppliedChange {
+                    transaction_id: String::new(),
+                    changes,
+                    confirm_deadline: None,
+                });
+            }
+        };
+        
+        let id = uuid::Uuid::new_v4().to_string();
+        let deadline = Utc::now() + chrono::Duration::from_std(timeout).unwrap_or_default();
+        *pending = Some(PendingTransaction {
+            id: id.clone(),
+            transaction,
+            desired,
+            deadline,
+        });
+        
+        info!(transaction_id = %id, timeout_secs = timeout.as_secs(), "Network change applied, waiting for confirmation");
+        
+        let manager = self.clone();
+        let timer_id = id.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(timeout).await;
+            match manager.rollback_if_pending(&timer_id).await {
+                Ok(true) => {
+                    emit_event(Event::new(
+                        EventLevel::Warning,
+                        EventCategory::Network,
+                        "Host network change was not confirmed in time and has been rolled back",
+                        "network",
+                    ));
+                }
+                Ok(false) => {}
+                Err(e) => warn!(transaction_id = %timer_id, error = %e, "Automatic network rollback failed"),
+            }
+        });
+        
+        Ok(AppliedChange {
+            transaction_id: id,
+            changes,
+            confirm_deadline: Some(deadline),
+        })
+    }
+    
+    /// Keep a pending change.
+    pub async fn confirm(&self, transaction_id: &str) -> Result<(), HostNetworkError> {
+        let mut pending = self.pending.lock().await;
+        let Some(p) = pending.take_if(|p| p.id == transaction_id) else {
+            return Err(HostNetworkError::NotPending(transaction_id.to_string()));
+        };
+        
+        info!(transaction_id = %transaction_id, "Network change confirmed");
+        persist(&p.desired).await;
+        Ok(())
+    }
+    
+    /// Roll back the pending change now.
+    pub async fn rollback(&self, transaction_id: &str) -> Result<(), HostNetworkError> {
+        match self.rollback_if_pending(transaction_id).await? {
+            true => Ok(()),
+            false => Err(HostNetworkError::NotPending(transaction_id.to_string())),
+        }
+    }
+    
+    /// ID and deadline of the pending change, if any.
+    pub async fn pending(&self) -> Option<(String, DateTime<Utc>)> {
+        self.pending.lock().await.as_ref().map(|p| (p.id.clone(), p.deadline))
+    }
+    
+    async fn rollback_if_pending(&self, transaction_id: &str) -> Result<bool, HostNetworkError> {
+        let mut pending = self.pending.lock().await;
+        let Some(p) = pending.take_if(|p| p.id == transaction_id) else {
+            return Ok(false);
+        };
+        
+        warn!(transaction_id = %transaction_id, "Rolling back network change");
+        tokio::task::spawn_blocking(move || p.transaction.rollback()).await
+            .map_err(|e| HostNetworkError::Failed(e.into()))?
+            .map_err(HostNetworkError::Failed)?;
+        
+        Ok(true)
+    }
+}
+
+/// Re-apply the saved host network configuration at startup.
+///
+/// Skipped when the console has written a newer interface configuration
+/// since the last save, so changes made locally are not reverted.
+pub async fn restore_persisted() {
+    let path = persist_path();
+    let saved = match tokio::task::spawn_blocking({
+        let path = path.clone();
+        move || HostNetworkConfig::load(&path)
+    }).await {
+        Ok(Ok(Some(config))) => config,
+        Ok(Ok(None)) => return,
+        Ok(Err(e)) => {
+            warn!(path = %path.display(), error = %e, "Failed to load saved host network configuration");
+            return;
+        }
+        Err(e) => {
+            warn!(error = %e, "Failed to load saved host network configuration");
+            return;
+        }
+    };
+    
+    if console_config_is_newer(&path) {
+        warn!(path = %path.display(), "Console network configuration is newer, not restoring saved host network configuration");
+        return;
+    }
+    
+    // There is no client to confirm at boot; roll back if the restored
+    // configuration loses the management address or the gateway
+    let management = crate::registration::detect_management_ip().and_then(|ip| ip.parse().ok());
+    match host_network().apply(saved, None, management).await {
+        Ok(applied) if applied.changes.is_empty() => {}
+        Ok(applied) => {
+            info!(changes = applied.changes.len(), "Restored saved host network configuration");
+        }
+        Err(e) => {
+            emit_event(Event::new(
+                EventLevel::Error,
+                EventCategory::Network,
+                format!("Failed to restore saved host network configuration: {}", e),
+                "network",
+            ));
+        }
+    }
+}
+
+/// Whether a console (TUI) interface config is newer than `saved`.
+fn console_config_is_newer(saved: &Path) -> bool {
+    let modified = |p: &Path| std::fs::metadata(p).and_then(|m| m.modified()).ok();
+    let Some(saved_at) = modified(saved) else { return false };
+    
+    std::fs::read_dir(PERSISTENT_DIR)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().is_some_and(|ext| ext == "conf"))
+        .any(|p| modified(&p).is_some_and(|t| t > saved_at))
+}
+
+/// Save a committed configuration (failures are logged, the change stays applied).
+async fn persist(config: &HostNetworkConfig) {
+    let config = config.clone();
+    let path = persist_path();
+    let result = tokio::task::spawn_blocking(move || config.save(&path)).await;
+    match result {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => warn!(error = %format!("{:#}", e), "Failed to save host network configuration"),
+        Err(e) => warn!(error = %e, "Failed to save host network configuration"),
+    }
+}
+
+/// Global host network manager (shared by the HTTP and HTTPS servers)
+static HOST_NETWORK: std::sync::OnceLock<Arc<HostNetworkManager>> = std::sync::OnceLock::new();
+
+/// Get the global host network manager.
+pub fn host_network() -> &'static Arc<HostNetworkManager> {
+    HOST_NETWORK.get_or_init(|| Arc::new(HostNetworkManager::new()))
+}
@@ -21,7 +21,7 @@ use tokio::io::AsyncWriteExt;
 use axum::{
     Router,
     routing::{get, post},
-    extract::{Path, State, Multipart, Query, DefaultBodyLimit, ws::{Message, WebSocket}},
+    extract::{ConnectInfo, Path, State, Multipart, Query, DefaultBodyLimit, ws::{Message, WebSocket}},
     http::{StatusCode, header, Method, Uri, HeaderMap},
     response::{IntoResponse, Response, Json, Redirect},
     body::Body,
@@ -61,6 +61,8 @@ pub struct AppState {
     pub update_manager: Arc<UpdateManager>,
     /// ISO Manager for ISO file tracking and sync
     pub iso_manager: Arc<crate::iso_manager::IsoManager>,
+    /// Port this server listens on (checked against host firewall changes)
+    pub listen_port: u16,
 }
 
 // ============================================================================
@@ -308,6 +310,8 @@ struct VmResponse {
     memory_used_bytes: u64,
     memory_total_bytes: u64,
     guest_agent: Option<GuestAgentInfo>,
+    /// Guest addresses (from the agent, or from node DHCP leases)
+    ip_addresses: Vec<String>,
     disks: Vec<DiskSpecResponse>,
 }
 
@@ -331,6 +335,8 @@ struct GuestAgentInfo {
     os_name: String,
     hostname: String,
     ip_addresses: Vec<String>,
+    /// Guest reports that installed updates need a reboot
+    reboot_required: bool,
 }
 
 #[derive(Serialize)]
@@ -461,6 +467,8 @@ struct VolumeResponse {
     format: String,
     path: String,
     attached_to: Option<String>,
+    /// Space actually used on the pool
+    allocation_bytes: u64,
 }
 
 #[derive(Serialize)]
@@ -530,6 +538,10 @@ struct DiskSpecRequest {
     bootable: Option<bool>,
     /// Storage pool to create the disk in (e.g., "SSD-local01", "nfs-xxx")
     pool_id: Option<String>,
+    /// "unmap" to pass guest TRIM through to the image (default: "ignore")
+    discard: Option<String>,
+    /// "on", "unmap" or "off" (default)
+    detect_zeroes: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -576,6 +588,55 @@ struct SnapshotListResponse {
     snapshots: Vec<SnapshotResponse>,
 }
 
+// ============================================================================
+// Template Types
+// ============================================================================
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TemplateResponse {
+    template_id: String,
+    name: String,
+    description: String,
+    source_vm_id: String,
+    created_at: String,
+    vcpus: u32,
+    memory_mib: u64,
+    disk_count: u32,
+    linked_clone_vm_ids: Vec<String>,
+    generalized: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TemplateListResponse {
+    templates: Vec<TemplateResponse>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct ConvertToTemplateBody {
+    name: Option<String>,
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CloneVmBody {
+    name: String,
+    /// "full" (default) or "linked"
+    clone_type: Option<String>,
+    vm_id: Option<String>,
+    start_on_create: Option<bool>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CloneVmResponse {
+    vm_id: String,
+    message: String,
+}
+
 // ============================================================================
 // Cluster Types
 // ============================================================================
@@ -800,10 +861,18 @@ struct NetworkInterfaceList {
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct ConfigureInterfaceRequest {
+    /// Obtain an IPv4 address with DHCP
+    #[serde(default)]
     dhcp: bool,
+    /// Static IPv4 or IPv6 address; replaces the static addresses of the same family
     ip_address: Option<String>,
+    /// Dotted netmask or prefix length (IPv6: prefix length only)
     netmask: Option<String>,
     gateway: Option<String>,
+    /// SLAAC, DHCPv6 or off (None = unchanged)
+    ipv6_autoconf: Option<limiquantix_hypervisor::network::host::Ipv6Autoconf>,
+    /// Seconds to wait for confirmation before rolling back (0 = no confirmation)
+    confirm_timeout_secs: Option<u64>,
 }
 
 #[derive(Deserialize)]
@@ -811,6 +880,231 @@ struct ConfigureInterfaceRequest {
 struct CreateBridgeRequest {
     name: String,
     interfaces: Vec<String>,  // Physical interfaces to add to bridge
+    /// Seconds to wait for confirmation before rolling back (0 = no confirmation)
+    confirm_timeout_secs: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApplyNetworkConfigRequest {
+    config: limiquantix_hypervisor::HostNetworkConfig,
+    /// Seconds to wait for confirmation before rolling back (0 = no confirmation)
+    confirm_timeout_secs: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NetworkTransactionRequest {
+    transaction_id: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HostNetworkConfigResponse {
+    config: limiquantix_hypervisor::HostNetworkConfig,
+    /// Change waiting for confirmation, if any
+    pending_transaction_id: Option<String>,
+    confirm_deadline: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NetworkPlanResponse {
+    changes: Vec<limiquantix_hypervisor::NetworkChange>,
+    /// Human-readable description of each change
+    summary: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NetworkApplyResponse {
+    /// Empty when the change did not need confirmation
+    transaction_id: String,
+    /// Human-readable description of each applied change
+    changes: Vec<String>,
+    /// Confirm before this time or the change is rolled back
+    confirm_deadline: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HostFirewallResponse {
+    config: limiquantix_hypervisor::HostFirewallConfig,
+    /// Change waiting for confirmation, if any
+    pending_transaction_id: Option<String>,
+    confirm_deadline: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateHostFirewallRequest {
+    config: limiquantix_hypervisor::HostFirewallConfig,
+    /// Seconds to wait for confirmation before rolling back (0 = no confirmation)
+    confirm_timeout_secs: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateBondRequest {
+    name: String,
+    /// Bonding mode ("active-backup", "802.3ad", "balance-alb", ...)
+    mode: String,
+    /// Member NICs; their addresses move to the bond
+    members: Vec<String>,
+    miimon: Option<u32>,
+    /// LACPDU rate for 802.3ad ("slow" or "fast")
+    lacp_rate: Option<String>,
+    xmit_hash_policy: Option<String>,
+    /// Preferred member for active-backup
+    primary: Option<String>,
+    /// Seconds to wait for confirmation before rolling back (0 = no confirmation)
+    confirm_timeout_secs: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateVlanRequest {
+    /// Parent link (NIC, bond or bridge)
+    parent: String,
+    vlan_id: u16,
+    /// Link name (default: "<parent>.<vlan_id>")
+    name: Option<String>,
+    /// Static addresses in CIDR notation
+    #[serde(default)]
+    addresses: Vec<String>,
+    #[serde(default)]
+    dhcp: bool,
+    /// Seconds to wait for confirmation before rolling back (0 = no confirmation)
+    confirm_timeout_secs: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeleteNetworkLinkQuery {
+    /// Seconds to wait for confirmation before rolling back (0 = no confirmation)
+    confirm_timeout_secs: Option<u64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BridgeList {
+    bridges: Vec<limiquantix_hypervisor::network::host::BridgeConfig>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BondList {
+    bonds: Vec<limiquantix_hypervisor::network::host::BondConfig>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OvsBondList {
+    bonds: Vec<crate::chassis::OvsBondConfig>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VlanList {
+    vlans: Vec<limiquantix_hypervisor::network::host::VlanConfig>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MirrorList {
+    mirrors: Vec<limiquantix_hypervisor::MirrorSession>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateMirrorRequest {
+    name: String,
+    /// OVS port to mirror; alternatively give `vmId` and `nic`
+    source_port: Option<String>,
+    vm_id: Option<String>,
+    /// NIC of `vmId`: tap name, MAC address or index
+    nic: Option<String>,
+    /// Analyzer port receiving the copies
+    output_port: String,
+    #[serde(default)]
+    direction: limiquantix_hypervisor::MirrorDirection,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MacRegistryResponse {
+    pool: limiquantix_hypervisor::MacPoolConfig,
+    allocations: Vec<limiquantix_hypervisor::MacAllocation>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MacConflictList {
+    conflicts: Vec<limiquantix_hypervisor::MacConflict>,
+}
+
+/// Bandwidth limits from the VM's point of view; 0 means unlimited
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NetworkPortQosLimits {
+    #[serde(default)]
+    ingress_rate_kbps: u64,
+    #[serde(default)]
+    egress_rate_kbps: u64,
+    #[serde(default)]
+    ingress_burst_kb: u64,
+    #[serde(default)]
+    egress_burst_kb: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NetworkPortStatus {
+    port_id: String,
+    /// OVS interface, if the VM is running
+    ovs_port_name: Option<String>,
+    /// Limits enforced by OVS right now
+    effective_qos: Option<NetworkPortQosLimits>,
+    rx_bytes: u64,
+    tx_bytes: u64,
+    rx_packets: u64,
+    tx_packets: u64,
+    rx_dropped: u64,
+    tx_dropped: u64,
+    qos_dropped: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DhcpServerList {
+    servers: Vec<crate::dhcp::DhcpServerStatus>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DhcpLeaseList {
+    leases: Vec<limiquantix_hypervisor::DhcpLease>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StaticLeaseRequest {
+    ip_address: std::net::Ipv4Addr,
+    hostname: Option<String>,
+    vm_id: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SecurityGroupList {
+    security_groups: Vec<limiquantix_hypervisor::SecurityGroup>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VmPortSecurity {
+    /// One entry per NIC, matched by MAC address
+    ports: Vec<crate::firewall::PortSecurity>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -865,6 +1159,7 @@ pub async fn run_http_server(
         storage,
         update_manager,
         iso_manager,
+        listen_port: http_addr.port(),
     });
 
     // Build the application router
@@ -873,7 +1168,7 @@ pub async fn run_http_server(
     info!(address = %http_addr, "Starting HTTP server for Web UI");
     
     let listener = tokio::net::TcpListener::bind(http_addr).await?;
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
 
     Ok(())
 }
@@ -914,6 +1209,7 @@ pub async fn run_https_server(
         storage,
         update_manager,
         iso_manager,
+        listen_port: https_addr.port(),
     });
 
     // Build the application router
@@ -934,7 +1230,7 @@ pub async fn run_https_server(
     );
     
     axum_server::bind_rustls(https_addr, rustls_config)
-        .serve(app.into_make_service())
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .await?;
 
     Ok(())
@@ -971,21 +1267,72 @@ fn build_app_router(state: Arc<AppState>, webui_path: &PathBuf) -> Router {
         .route("/vms/:vm_id/pause", post(pause_vm))
         .route("/vms/:vm_id/resume", post(resume_vm))
         .route("/vms/:vm_id/console", get(get_vm_console))
+        .route("/vms/:vm_id/console/ticket", post(create_console_ticket))
+        .route("/vms/:vm_id/console/ws", get(console_proxy_ws))
+        .route("/vms/:vm_id/serial/ticket", post(create_serial_console_ticket))
+        .route("/vms/:vm_id/serial", get(serial_console_ws))
         .route("/vms/:vm_id/logs", get(get_vm_logs))
         .route("/vms/:vm_id/snapshots", get(list_snapshots))
         .route("/vms/:vm_id/snapshots", post(create_snapshot))
         .route("/vms/:vm_id/snapshots/:snapshot_id", axum::routing::delete(delete_snapshot))
         .route("/vms/:vm_id/snapshots/:snapshot_id/revert", post(revert_snapshot))
+        // Templates & clones
+        .route("/vms/:vm_id/template", post(convert_vm_to_template))
+        .route("/vms/:vm_id/clone", post(clone_vm))
+        .route("/templates", get(list_templates))
+        .route("/templates/:template_id", axum::routing::delete(delete_template))
+        .route("/templates/:template_id/clone", post(clone_template))
+        // OVA import / export
+        .route("/vms/:vm_id/export", post(export_vm_ova))
+        .route("/ova/import", post(import_ova))
+        .route("/ova/jobs/:job_id", get(get_ova_job))
         // Quantix Agent endpoints (advanced agent)
         .route("/vms/:vm_id/agent/ping", get(ping_quantix_agent))
         .route("/vms/:vm_id/agent/install", post(install_quantix_agent))
         .route("/vms/:vm_id/agent/update", post(update_quantix_agent))
         .route("/vms/:vm_id/agent/refresh", post(refresh_quantix_agent))
         .route("/vms/:vm_id/agent/logs", get(get_agent_logs))
+        .route("/vms/:vm_id/agent/shell/ticket", post(create_agent_shell_ticket))
+        .route("/vms/:vm_id/agent/shell", get(agent_shell_ws))
+        .route("/vms/:vm_id/agent/port-forwards", get(list_agent_port_forwards).post(create_agent_port_forward))
+        .route("/vms/:vm_id/agent/port-forwards/:forward_id", axum::routing::delete(delete_agent_port_forward))
+        .route("/vms/:vm_id/agent/capabilities", get(get_agent_capabilities))
+        .route("/vms/:vm_id/agent/processes", get(list_guest_processes))
+        .route("/vms/:vm_id/agent/processes/:pid/kill", post(kill_guest_process))
+        .route("/vms/:vm_id/agent/services", get(list_guest_services))
+        .route("/vms/:vm_id/agent/services/:service/:action", post(control_guest_service))
+        .route("/vms/:vm_id/agent/hardware", get(get_guest_hardware))
+        .route("/vms/:vm_id/agent/software", get(list_guest_software))
+        .route("/vms/:vm_id/agent/password", post(reset_guest_password))
+        .route("/vms/:vm_id/agent/users", get(list_guest_users).post(create_guest_user))
+        .route("/vms/:vm_id/agent/users/:username", axum::routing::delete(delete_guest_user))
+        .route("/vms/:vm_id/agent/users/:username/groups", axum::routing::put(set_guest_user_groups))
+        .route("/vms/:vm_id/agent/users/:username/lock", post(lock_guest_user))
+        .route("/vms/:vm_id/agent/users/:username/unlock", post(unlock_guest_user))
+        .route("/vms/:vm_id/agent/users/:username/ssh-keys", post(add_guest_user_ssh_key).delete(remove_guest_user_ssh_key))
+        .route("/vms/:vm_id/agent/users/:username/logoff", post(logoff_guest_user))
+        .route("/vms/:vm_id/agent/sessions", get(list_guest_login_sessions))
+        .route("/vms/:vm_id/agent/network", post(configure_guest_network))
+        .route("/vms/:vm_id/agent/clipboard", get(get_guest_clipboard).put(set_guest_clipboard))
+        .route("/vms/:vm_id/agent/display/resize", post(resize_guest_display))
+        .route("/vms/:vm_id/agent/time/sync", post(sync_guest_time))
+        .route("/vms/:vm_id/agent/filesystems/quiesce", post(quiesce_guest_filesystems))
+        .route("/vms/:vm_id/agent/filesystems/thaw", post(thaw_guest_filesystems))
+        .route("/vms/:vm_id/agent/filesystems/grow", post(grow_guest_filesystems))
+        .route("/vms/:vm_id/agent/filesystems/trim", post(trim_guest_filesystems))
+        .route("/vms/:vm_id/agent/updates", get(list_guest_updates))
+        .route("/vms/:vm_id/agent/updates/apply", post(apply_guest_updates))
+        .route("/vms/:vm_id/agent/patch-compliance", get(get_guest_patch_compliance))
+        .route("/patch-compliance", get(list_patch_compliance))
         .route("/vms/:vm_id/agent/shutdown", post(agent_shutdown))
         .route("/vms/:vm_id/agent/reboot", post(agent_reboot))
         .route("/vms/:vm_id/agent/files/list", get(list_guest_files))
         .route("/vms/:vm_id/agent/files/read", get(read_guest_file))
+        .route("/vms/:vm_id/agent/files", axum::routing::delete(delete_guest_file))
+        .route("/vms/:vm_id/agent/files/mkdir", post(create_guest_directory))
+        .route("/vms/:vm_id/agent/files/stat", get(stat_guest_file))
+        .route("/vms/:vm_id/agent/files/tree/upload", post(upload_guest_tree))
+        .route("/vms/:vm_id/agent/files/tree/download", post(download_guest_tree))
         .route("/vms/:vm_id/execute", post(execute_in_guest))
         // Agent ISO installation endpoint
         .route("/vms/:vm_id/cdrom/mount-agent-iso", post(mount_agent_iso))
@@ -1004,6 +1351,7 @@ fn build_app_router(state: Arc<AppState>, webui_path: &PathBuf) -> Router {
         .route("/storage/pools/:pool_id/volumes", get(list_volumes))
         .route("/storage/pools/:pool_id/volumes", post(create_volume))
         .route("/storage/pools/:pool_id/volumes/:volume_id", axum::routing::delete(delete_volume))
+        .route("/storage/pools/:pool_id/trim-schedule", get(get_pool_trim_schedule).put(set_pool_trim_schedule))
         .route("/storage/images", get(list_images))
         // ISO management endpoints
         .route("/images", get(list_isos))
@@ -1026,7 +1374,36 @@ fn build_app_router(state: Arc<AppState>, webui_path: &PathBuf) -> Router {
         .route("/network/interfaces", get(list_network_interfaces))
         .route("/network/interfaces/:name", get(get_network_interface))
         .route("/network/interfaces/:name/configure", post(configure_network_interface))
-        .route("/network/bridges", post(create_bridge))
+        .route("/network/bridges", get(list_bridges).post(create_bridge))
+        .route("/network/bridges/:name", axum::routing::delete(delete_bridge))
+        .route("/network/bonds", get(list_bonds).post(create_bond))
+        .route("/network/bonds/:name", axum::routing::delete(delete_bond))
+        .route("/network/ovs-bonds", get(list_ovs_bonds).post(apply_ovs_bond))
+        .route("/network/ovs-bonds/:name", axum::routing::delete(delete_ovs_bond))
+        .route("/network/vlans", get(list_vlans).post(create_vlan))
+        .route("/network/vlans/:name", axum::routing::delete(delete_vlan))
+        .route("/network/config", get(get_host_network_config))
+        .route("/network/config/plan", post(plan_host_network_config))
+        .route("/network/config/apply", post(apply_host_network_config))
+        .route("/network/config/confirm", post(confirm_host_network_config))
+        .route("/network/config/rollback", post(rollback_host_network_config))
+        .route("/network/mirrors", get(list_mirrors).post(create_mirror))
+        .route("/network/mirrors/:name", axum::routing::delete(delete_mirror))
+        .route("/vms/:vm_id/nics/:nic/capture", get(download_capture).post(start_capture).delete(stop_capture))
+        .route("/vms/:vm_id/nics/:nic/capture/status", get(get_capture_status))
+        .route("/network/ovn/health", get(get_ovn_health))
+        .route("/network/ports/:port_id/qos", axum::routing::put(update_network_port_qos))
+        .route("/network/macs", get(get_mac_registry))
+        .route("/network/macs/pool", axum::routing::put(put_mac_pool))
+        .route("/network/macs/conflicts", get(list_mac_conflicts))
+        .route("/network/dhcp", get(list_dhcp_servers))
+        .route("/network/dhcp/:bridge", get(get_dhcp_server).put(put_dhcp_server).delete(delete_dhcp_server))
+        .route("/network/dhcp/:bridge/leases", get(list_dhcp_leases))
+        .route("/network/dhcp/:bridge/static-leases/:mac", axum::routing::put(set_static_lease).delete(delete_static_lease))
+        .route("/firewall/security-groups", get(list_security_groups).post(create_security_group))
+        .route("/firewall/security-groups/:id", get(get_security_group).put(update_security_group).delete(delete_security_group))
+        .route("/firewall/sync", post(sync_firewall))
+        .route("/vms/:vm_id/security", get(get_vm_port_security).put(set_vm_port_security))
         .route("/network/dns", get(get_dns_config))
         .route("/network/dns", post(set_dns_config))
         .route("/network/hostname", get(get_hostname))
@@ -1052,6 +1429,10 @@ fn build_app_router(state: Arc<AppState>, webui_path: &PathBuf) -> Router {
         // Settings endpoints
         .route("/settings", get(get_settings))
         .route("/settings", post(update_settings))
+        .route("/settings/firewall", get(get_host_firewall))
+        .route("/settings/firewall", axum::routing::put(update_host_firewall))
+        .route("/settings/firewall/confirm", post(confirm_host_firewall))
+        .route("/settings/firewall/rollback", post(rollback_host_firewall))
         .route("/settings/services", get(list_services))
         .route("/settings/services/:name/restart", post(restart_service))
         // Certificate management endpoints
@@ -1125,8 +1506,11 @@ pub async fn run_redirect_server(redirect_addr: SocketAddr, https_port: u16) {
                 .get(header::HOST)
                 .and_then(|h| h.to_str().ok())
                 .map(|h| {
-                    // Remove port from host if present
-                    h.split(':').next().unwrap_or(h).to_string()
+                    // Remove port from host if present ("[fd00::1]:80" keeps its brackets)
+                    match h.strip_prefix('[').and_then(|rest| rest.split_once(']')) {
+                        Some((addr, _)) => format!("[{}]", addr),
+                        None => h.split(':').next().unwrap_or(h).to_string(),
+                    }
                 })
                 .unwrap_or_else(|| "localhost".to_string());
             
@@ -2710,7 +3094,9 @@ async fn list_vms(
                         os_name: ga.os_name,
                         hostname: ga.hostname,
                         ip_addresses: ga.ip_addresses,
+                        reboot_required: ga.reboot_required,
                     }),
+                    ip_addresses: vm.ip_addresses,
                     disks: vm.disks.into_iter().map(|d| DiskSpecResponse {
                         id: d.id,
                         path: d.path,
@@ -2759,7 +3145,9 @@ async fn get_vm(
                     os_name: ga.os_name,
                     hostname: ga.hostname,
                     ip_addresses: ga.ip_addresses,
+                    reboot_required: ga.reboot_required,
                 }),
+                ip_addresses: vm.ip_addresses,
                 disks: vm.disks.into_iter().map(|d| DiskSpecResponse {
                     id: d.id,
                     path: d.path,
@@ -2956,94 +3344,357 @@ async fn get_vm_console(
     }
 }
 
-/// Response type for VM logs
+/// Response for a console ticket request
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
-struct VmLogsResponse {
-    /// VM ID
+struct ConsoleTicketResponse {
+    /// Single-use ticket value
+    ticket: String,
+    /// VM the ticket is bound to
     vm_id: String,
-    /// VM name (for display)
-    vm_name: String,
-    /// QEMU log content (last N lines)
-    qemu_log: String,
-    /// Log file path
-    log_path: String,
-    /// Total log file size in bytes
-    log_size_bytes: u64,
-    /// Number of lines returned
-    lines_returned: usize,
-    /// Whether the log was truncated
-    truncated: bool,
-    /// Timestamp of last modification
-    last_modified: Option<String>,
+    /// Ticket expiry (RFC 3339)
+    expires_at: String,
+    /// WebSocket path to connect to (includes the ticket)
+    websocket_path: String,
+    /// VNC password, if the display requires one
+    password: String,
 }
 
-/// GET /api/v1/vms/:vm_id/logs - Get VM QEMU logs for troubleshooting
-/// 
-/// Query parameters:
-/// - lines: Number of lines to return (default: 100, max: 1000)
-/// 
-/// Returns the QEMU log file content from /var/log/libvirt/qemu/{vm_name}.log
-async fn get_vm_logs(
+/// POST /api/v1/vms/:vm_id/console/ticket - Issue a short-lived console ticket
+async fn create_console_ticket(
     State(state): State<Arc<AppState>>,
     Path(vm_id): Path<String>,
-    Query(params): Query<std::collections::HashMap<String, String>>,
-) -> Result<Json<VmLogsResponse>, (StatusCode, Json<ApiError>)> {
-    use tokio::fs;
-    use tokio::io::{AsyncBufReadExt, BufReader};
+) -> Result<Json<ConsoleTicketResponse>, (StatusCode, Json<ApiError>)> {
+    let console = state.service.hypervisor().get_console(&vm_id).await
+        .map_err(|e| (StatusCode::NOT_FOUND, Json(ApiError::new("get_console_failed", &e.to_string()))))?;
     
-    // Get the VM to find its name by listing all VMs and finding by ID or name
-    let vms = state.service.hypervisor().list_vms().await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiError::new("list_vms_failed", &format!("Failed to list VMs: {}", e))),
-        )
-    })?;
+    if console.console_type != limiquantix_hypervisor::ConsoleType::Vnc {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ApiError::new("unsupported_console", "Only VNC consoles can be proxied")),
+        ));
+    }
     
-    // Find VM by ID or name
-    let vm = vms.iter()
-        .find(|v| v.id == vm_id || v.name == vm_id)
-        .ok_or_else(|| {
-            (
-                StatusCode::NOT_FOUND,
-                Json(ApiError::new("vm_not_found", &format!("VM not found: {}", vm_id))),
-            )
+    let ticket = crate::console_proxy::console_tickets().issue(&vm_id);
+    
+    info!(vm_id = %vm_id, expires_at = %ticket.expires_at, "Issued console ticket");
+    
+    Ok(Json(ConsoleTicketResponse {
+        websocket_path: format!("/api/v1/vms/{}/console/ws?ticket={}", vm_id, ticket.ticket),
+        expires_at: ticket.expires_at.to_rfc3339(),
+        ticket: ticket.ticket,
+        vm_id,
+        password: console.password.unwrap_or_default(),
+    }))
+}
+
+/// Query parameters for the console WebSocket
+#[derive(Deserialize)]
+struct ConsoleProxyQuery {
+    ticket: String,
+}
+
+/// GET /api/v1/vms/:vm_id/console/ws - VNC over WebSocket (requires a console ticket)
+async fn console_proxy_ws(
+    ws: axum::extract::WebSocketUpgrade,
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+    Query(params): Query<ConsoleProxyQuery>,
+) -> Result<axum::response::Response, (StatusCode, Json<ApiError>)> {
+    crate::console_proxy::console_tickets()
+        .redeem(&params.ticket, &vm_id)
+        .map_err(|e| {
+            warn!(vm_id = %vm_id, error = %e, "Rejected console ticket");
+            (StatusCode::UNAUTHORIZED, Json(ApiError::new("invalid_ticket", &e.to_string())))
         })?;
     
-    let vm_name = &vm.name;
-    let log_path = format!("/var/log/libvirt/qemu/{}.log", vm_name);
+    // Connect to the hypervisor's view of the display (loopback), not the
+    // management IP that get_console advertises to remote clients
+    let console = state.service.hypervisor().get_console(&vm_id).await
+        .map_err(|e| (StatusCode::NOT_FOUND, Json(ApiError::new("get_console_failed", &e.to_string()))))?;
     
-    // Parse lines parameter (default: 100, max: 1000)
-    let max_lines: usize = params
-        .get("lines")
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(100)
-        .min(1000);
+    let ip = match console.host.parse::<std::net::IpAddr>() {
+        Ok(ip) if !ip.is_unspecified() => ip,
+        _ => std::net::IpAddr::from([127, 0, 0, 1]),
+    };
+    let addr = SocketAddr::new(ip, console.port);
     
-    // Check if log file exists
-    let metadata = match fs::metadata(&log_path).await {
-        Ok(m) => m,
+    Ok(ws
+        .protocols(["binary"])
+        .on_upgrade(move |socket| crate::console_proxy::proxy_vnc(socket, vm_id, addr)))
+}
+
+/// Response for a serial console ticket request
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SerialConsoleTicketResponse {
+    /// Single-use ticket value
+    ticket: String,
+    /// VM the ticket is bound to
+    vm_id: String,
+    /// Ticket expiry (RFC 3339)
+    expires_at: String,
+    /// WebSocket path to connect to (includes the ticket; append the other
+    /// query parameters)
+    websocket_path: String,
+}
+
+/// POST /api/v1/vms/:vm_id/serial/ticket - Issue a short-lived serial console ticket
+async fn create_serial_console_ticket(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+) -> Result<Json<SerialConsoleTicketResponse>, (StatusCode, Json<ApiError>)> {
+    match state.service.hypervisor().vm_exists(&vm_id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return Err((StatusCode::NOT_FOUND, Json(ApiError::new("not_found", "VM not found"))));
+        }
         Err(e) => {
-            if e.kind() == std::io::ErrorKind::NotFound {
-                return Ok(Json(VmLogsResponse {
-                    vm_id: vm_id.clone(),
-                    vm_name: vm_name.clone(),
-                    qemu_log: String::new(),
-                    log_path,
-                    log_size_bytes: 0,
-                    lines_returned: 0,
-                    truncated: false,
-                    last_modified: None,
-                }));
-            }
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(ApiError::new("log_read_error", &format!("Failed to read log metadata: {}", e))),
-            ));
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, Json(ApiError::new("serial_console_failed", &e.to_string()))));
         }
-    };
+    }
     
-    let log_size_bytes = metadata.len();
+    let ticket = crate::console_proxy::console_tickets().issue(&vm_id);
+    
+    info!(vm_id = %vm_id, expires_at = %ticket.expires_at, "Issued serial console ticket");
+    
+    Ok(Json(SerialConsoleTicketResponse {
+        websocket_path: format!("/api/v1/vms/{}/serial?ticket={}", vm_id, ticket.ticket),
+        expires_at: ticket.expires_at.to_rfc3339(),
+        ticket: ticket.ticket,
+        vm_id,
+    }))
+}
+
+/// Query parameters for the serial console WebSocket
+#[derive(Deserialize)]
+struct SerialConsoleQuery {
+    /// Single-use ticket from `POST .../serial/ticket`
+    ticket: String,
+    /// Guest serial port (0 = console)
+    #[serde(default)]
+    port: u32,
+    /// Request write access (only one writer per port)
+    #[serde(default)]
+    write: bool,
+}
+
+/// GET /api/v1/vms/:vm_id/serial - Attach to the VM serial console (WebSocket)
+///
+/// Requires a serial console ticket. Output is sent as binary frames, starting with the scrollback buffer.
+/// Control messages (attach status) are sent as JSON text frames.
+/// Input frames are forwarded to the guest only for the writer connection.
+async fn serial_console_ws(
+    ws: axum::extract::WebSocketUpgrade,
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+    Query(params): Query<SerialConsoleQuery>,
+) -> Result<axum::response::Response, (StatusCode, Json<ApiError>)> {
+    crate::console_proxy::console_tickets()
+        .redeem(&params.ticket, &vm_id)
+        .map_err(|e| {
+            warn!(vm_id = %vm_id, error = %e, "Rejected serial console ticket");
+            (StatusCode::UNAUTHORIZED, Json(ApiError::new("invalid_ticket", &e.to_string())))
+        })?;
+    
+    match state.service.hypervisor().vm_exists(&vm_id).await {
+        Ok(true) => {}
+        Ok(false) => {
+            return Err((StatusCode::NOT_FOUND, Json(ApiError::new("not_found", "VM not found"))));
+        }
+        Err(e) => {
+            return Err((StatusCode::INTERNAL_SERVER_ERROR, Json(ApiError::new("serial_console_failed", &e.to_string()))));
+        }
+    }
+    
+    // The viewer detaches when dropped, including when the upgrade fails
+    let viewer = crate::serial_console::serial_consoles()
+        .attach(&vm_id, params.port)
+        .await
+        .map_err(|e| (StatusCode::CONFLICT, Json(ApiError::new("serial_console_unavailable", &e.to_string()))))?;
+    
+    Ok(ws.on_upgrade(move |socket| handle_serial_console(socket, viewer, params.write)))
+}
+
+/// Handle a serial console WebSocket connection
+async fn handle_serial_console(
+    mut socket: WebSocket,
+    viewer: crate::serial_console::SerialViewer,
+    want_write: bool,
+) {
+    use tokio::sync::broadcast::error::RecvError;
+    
+    let session = &viewer.session;
+    let conn_id = &viewer.conn_id;
+    let viewers = viewer.viewers;
+    
+    let Some((scrollback, mut output)) = session.subscribe() else {
+        let _ = socket.send(Message::Close(None)).await;
+        return;
+    };
+    
+    let writer = want_write && session.try_acquire_writer(conn_id);
+    
+    info!(
+        vm_id = %session.vm_id,
+        port = session.port,
+        writer = writer,
+        viewers = viewers,
+        "Serial console WebSocket connected"
+    );
+    
+    let status = serde_json::json!({
+        "type": "attached",
+        "vmId": session.vm_id,
+        "port": session.port,
+        "pty": session.pty_path,
+        "writer": writer,
+        "viewers": viewers,
+    });
+    
+    let mut open = socket.send(Message::Text(status.to_string())).await.is_ok();
+    if open && !scrollback.is_empty() {
+        open = socket.send(Message::Binary(scrollback)).await.is_ok();
+    }
+    if !open {
+        return;
+    }
+    
+    loop {
+        tokio::select! {
+            chunk = output.recv() => {
+                match chunk {
+                    Ok(data) => {
+                        if socket.send(Message::Binary(data)).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        debug!(vm_id = %session.vm_id, skipped = skipped, "Serial console viewer lagged");
+                    }
+                    Err(RecvError::Closed) => {
+                        // PTY closed (VM stopped)
+                        let _ = socket.send(Message::Close(None)).await;
+                        break;
+                    }
+                }
+            }
+            msg = socket.recv() => {
+                let input = match msg {
+                    Some(Ok(Message::Binary(data))) => data,
+                    Some(Ok(Message::Text(text))) => text.into_bytes(),
+                    Some(Ok(Message::Ping(data))) => {
+                        if socket.send(Message::Pong(data)).await.is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                    Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                    Some(Ok(_)) => continue,
+                };
+                
+                // Viewers are read-only; their input is dropped
+                if writer {
+                    if let Err(e) = session.send_input(conn_id, input).await {
+                        warn!(vm_id = %session.vm_id, error = %e, "Serial console input failed");
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    
+    info!(vm_id = %session.vm_id, port = session.port, "Serial console WebSocket closed");
+}
+
+/// Response type for VM logs
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VmLogsResponse {
+    /// VM ID
+    vm_id: String,
+    /// VM name (for display)
+    vm_name: String,
+    /// QEMU log content (last N lines)
+    qemu_log: String,
+    /// Log file path
+    log_path: String,
+    /// Total log file size in bytes
+    log_size_bytes: u64,
+    /// Number of lines returned
+    lines_returned: usize,
+    /// Whether the log was truncated
+    truncated: bool,
+    /// Timestamp of last modification
+    last_modified: Option<String>,
+}
+
+/// GET /api/v1/vms/:vm_id/logs - Get VM QEMU logs for troubleshooting
+/// 
+/// Query parameters:
+/// - lines: Number of lines to return (default: 100, max: 1000)
+/// 
+/// Returns the QEMU log file content from /var/log/libvirt/qemu/{vm_name}.log
+async fn get_vm_logs(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+    Query(params): Query<std::collections::HashMap<String, String>>,
+) -> Result<Json<VmLogsResponse>, (StatusCode, Json<ApiError>)> {
+    use tokio::fs;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    
+    // Get the VM to find its name by listing all VMs and finding by ID or name
+    let vms = state.service.hypervisor().list_vms().await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiError::new("list_vms_failed", &format!("Failed to list VMs: {}", e))),
+        )
+    })?;
+    
+    // Find VM by ID or name
+    let vm = vms.iter()
+        .find(|v| v.id == vm_id || v.name == vm_id)
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiError::new("vm_not_found", &format!("VM not found: {}", vm_id))),
+            )
+        })?;
+    
+    let vm_name = &vm.name;
+    let log_path = format!("/var/log/libvirt/qemu/{}.log", vm_name);
+    
+    // Parse lines parameter (default: 100, max: 1000)
+    let max_lines: usize = params
+        .get("lines")
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(100)
+        .min(1000);
+    
+    // Check if log file exists
+    let metadata = match fs::metadata(&log_path).await {
+        Ok(m) => m,
+        Err(e) => {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                return Ok(Json(VmLogsResponse {
+                    vm_id: vm_id.clone(),
+                    vm_name: vm_name.clone(),
+                    qemu_log: String::new(),
+                    log_path,
+                    log_size_bytes: 0,
+                    lines_returned: 0,
+                    truncated: false,
+                    last_modified: None,
+                }));
+            }
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ApiError::new("log_read_error", &format!("Failed to read log metadata: {}", e))),
+            ));
+        }
+    };
+    
+    let log_size_bytes = metadata.len();
     let last_modified = metadata.modified().ok().map(|t| {
         chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339()
     });
@@ -3111,7 +3762,7 @@ async fn create_vm(
     use tonic::Request;
     use limiquantix_proto::{
         NodeDaemonService, CreateVmOnNodeRequest, VmSpec, DiskSpec, NicSpec,
-        DiskBus, DiskFormat, NicModel, CloudInitConfig,
+        DiskBus, DiskFormat, DiskDiscard, DiskDetectZeroes, NicModel, CloudInitConfig,
     };
     
     // Generate VM ID - must be a valid UUID for libvirt
@@ -3129,6 +3780,15 @@ async fn create_vm(
             Some("raw") => DiskFormat::Raw.into(),
             _ => DiskFormat::Qcow2.into(),
         };
+        let discard = match d.discard.as_deref() {
+            Some("unmap") => DiskDiscard::Unmap.into(),
+            _ => DiskDiscard::Ignore.into(),
+        };
+        let detect_zeroes = match d.detect_zeroes.as_deref() {
+            Some("on") => DiskDetectZeroes::On.into(),
+            Some("unmap") => DiskDetectZeroes::Unmap.into(),
+            _ => DiskDetectZeroes::Off.into(),
+        };
         DiskSpec {
             id: d.id.clone(),
             path: String::new(),
@@ -3141,6 +3801,8 @@ async fn create_vm(
             throughput_mbps: 0,
             backing_file: d.backing_file.clone().unwrap_or_default(),
             pool_id: d.pool_id.clone().unwrap_or_default(),
+            discard,
+            detect_zeroes,
         }
     }).collect();
     
@@ -3203,6 +3865,7 @@ async fn create_vm(
                 memory_used_bytes: 0,
                 memory_total_bytes: request.memory_mib * 1024 * 1024,
                 guest_agent: None,
+                ip_addresses: vec![],
                 disks: request.disks.iter().map(|d| DiskSpecResponse {
                     id: d.id.clone(),
                     path: String::new(), // Path unknown until started/inspected, but usually auto-generated
@@ -4004,7 +4667,7 @@ struct UpdateAgentResponse {
 async fn update_quantix_agent(
     State(state): State<Arc<AppState>>,
     Path(vm_id): Path<String>,
-    Json(_request): Json<UpdateAgentRequest>,
+    Json(request): Json<UpdateAgentRequest>,
 ) -> Result<Json<UpdateAgentResponse>, (StatusCode, Json<ApiError>)> {
     info!(vm_id = %vm_id, "Updating Quantix Agent");
     
@@ -4056,8 +4719,8 @@ async fn update_quantix_agent(
     info!(vm_id = %vm_id_canonical, current_version = %current_version, "Current agent version detected");
     
     // Step 1: Download the latest agent binary
-    let agent_binary = match download_latest_agent_binary().await {
-        Ok(binary) => binary,
+    let release = match download_latest_agent_binary().await {
+        Ok(release) => release,
         Err(e) => {
             error!(vm_id = %vm_id_canonical, error = %e, "Failed to download agent binary");
             return Ok(Json(UpdateAgentResponse {
@@ -4069,7 +4732,8 @@ async fn update_quantix_agent(
         }
     };
     
-    info!(vm_id = %vm_id_canonical, size = agent_binary.len(), "Downloaded agent binary for update");
+    let agent_binary = release.binary;
+    info!(vm_id = %vm_id_canonical, size = agent_binary.len(), signed = release.signature.is_some(), "Downloaded agent binary for update");
     
     // Step 2: Get the agent client and transfer the binary
     if let Err(e) = state.service.get_agent_client(&vm_id_canonical).await {
@@ -4094,6 +4758,34 @@ async fn update_quantix_agent(
         }
     };
     
+    // Agents that verify signatures take the binary through the update
+    // protocol and roll themselves back if the new one fails. Older agents
+    // can't verify anything and are upgraded with the install script.
+    if client.require_capability("signed_update").await.is_ok() {
+        let Some(signature) = release.signature else {
+            return Ok(Json(UpdateAgentResponse {
+                success: false,
+                message: "No signature found for the agent binary".to_string(),
+                new_version: None,
+                error: Some("The agent only accepts signed updates".to_string()),
+            }));
+        };
+        
+        info!(vm_id = %vm_id_canonical, "Sending signed agent update");
+        let result = client.update_agent(&agent_binary, &signature, &request.target_version).await;
+        drop(agents);
+        
+        return match result {
+            Ok(resp) => Ok(Json(wait_for_agent_update(&state, vm, &current_version, &resp.new_version).await)),
+            Err(e) => Ok(Json(UpdateAgentResponse {
+                success: false,
+                message: "Agent rejected the update".to_string(),
+                new_version: None,
+                error: Some(format!("{:#}", e)),
+            })),
+        };
+    }
+    
     // Step 3: Transfer the new binary to /tmp/quantix-kvm-agent.new
     info!(vm_id = %vm_id_canonical, "Transferring new agent binary to VM");
     if let Err(e) = client.write_file("/tmp/quantix-kvm-agent.new", &agent_binary, 0o755).await {
@@ -4214,8 +4906,78 @@ echo "[Quantix] Agent upgraded successfully!"
     }))
 }
 
-/// Downloads the latest agent binary from the update server or local storage.
-async fn download_latest_agent_binary() -> anyhow::Result<Vec<u8>> {
+/// How long a signed update may take to come back healthy; the agent rolls
+/// back after 2 minutes, plus time for the restart.
+const AGENT_UPDATE_DEADLINE: std::time::Duration = std::time::Duration::from_secs(150);
+
+/// Wait for the agent to reconnect running `new_version`, or report that it
+/// was rolled back.
+async fn wait_for_agent_update(
+    state: &Arc<AppState>,
+    vm: &limiquantix_hypervisor::VmInfo,
+    old_version: &str,
+    new_version: &str,
+) -> UpdateAgentResponse {
+    use crate::event_store::{emit_event, Event, EventCategory, EventLevel};
+    
+    info!(vm_id = %vm.id, new_version = %new_version, "Waiting for updated agent to reconnect");
+    let started = std::time::Instant::now();
+    let mut seen_version = None;
+    
+    while started.elapsed() < AGENT_UPDATE_DEADLINE {
+        tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+        let _ = state.service.get_agent_client(&vm.id).await;
+        
+        let info = state.service.get_agent_info(&vm.id).await;
+        seen_version = info.filter(|i| i.connected).map(|i| i.version);
+        if seen_version.as_deref() == Some(new_version) {
+            info!(vm_id = %vm.id, old_version = %old_version, new_version = %new_version, "Agent updated successfully");
+            emit_event(Event::new(
+                EventLevel::Info,
+                EventCategory::Vm,
+                format!("Guest agent in VM {} updated from {} to {}", vm.name, old_version, new_version),
+                "agent",
+            ).with_resource(vm.id.clone()));
+            
+            return UpdateAgentResponse {
+                success: true,
+                message: format!("Agent updated from {} to {}", old_version, new_version),
+                new_version: Some(new_version.to_string()),
+                error: None,
+            };
+        }
+    }
+    
+    let error = match seen_version.as_deref() {
+        Some(v) if v == old_version => format!("The new agent did not report healthy and was rolled back to {}", v),
+        Some(v) => format!("Agent reconnected with unexpected version {}", v),
+        None => "The agent did not reconnect after the update".to_string(),
+    };
+    warn!(vm_id = %vm.id, new_version = %new_version, error = %error, "Agent update failed");
+    emit_event(Event::new(
+        EventLevel::Warning,
+        EventCategory::Vm,
+        format!("Guest agent update to {} in VM {} failed: {}", new_version, vm.name, error),
+        "agent",
+    ).with_resource(vm.id.clone()));
+    
+    UpdateAgentResponse {
+        success: false,
+        message: format!("Agent update to {} failed", new_version),
+        new_version: seen_version,
+        error: Some(error),
+    }
+}
+
+/// Agent binary for an update, with its detached signature if one was found.
+struct AgentRelease {
+    binary: Vec<u8>,
+    signature: Option<Vec<u8>>,
+}
+
+/// Downloads the latest agent binary (and `<binary>.sig`) from the update
+/// server or local storage.
+async fn download_latest_agent_binary() -> anyhow::Result<AgentRelease> {
     // First try local paths
     let agent_paths = [
         "/data/share/quantix-agent/quantix-kvm-agent-linux-amd64",
@@ -4226,7 +4988,8 @@ async fn download_latest_agent_binary() -> anyhow::Result<Vec<u8>> {
     for path in &agent_paths {
         if let Ok(data) = tokio::fs::read(path).await {
             info!(path = %path, size = data.len(), "Using local agent binary for update");
-            return Ok(data);
+            let signature = tokio::fs::read(format!("{}.sig", path)).await.ok();
+            return Ok(AgentRelease { binary: data, signature });
         }
     }
     
@@ -4246,7 +5009,12 @@ async fn download_latest_agent_binary() -> anyhow::Result<Vec<u8>> {
             .map_err(|e| anyhow::anyhow!("Failed to read response: {}", e))?;
         
         info!(size = bytes.len(), "Downloaded agent binary from update server");
-        return Ok(bytes.to_vec());
+        
+        let signature = match reqwest::get(format!("{}.sig", url)).await {
+            Ok(response) if response.status().is_success() => response.bytes().await.ok().map(|b| b.to_vec()),
+            _ => None,
+        };
+        return Ok(AgentRelease { binary: bytes.to_vec(), signature });
     }
     
     Err(anyhow::anyhow!("Could not find agent binary locally or from update server"))
@@ -4892,452 +5660,2777 @@ async fn get_agent_logs(
     }
 }
 
-/// Response for shutdown/reboot
+/// Response for an agent shell ticket request
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
-struct AgentShutdownResponse {
-    success: bool,
-    message: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    error: Option<String>,
+struct AgentShellTicketResponse {
+    /// Single-use ticket value
+    ticket: String,
+    /// VM the ticket is bound to
+    vm_id: String,
+    /// Ticket expiry (RFC 3339)
+    expires_at: String,
+    /// WebSocket path to connect to (includes the ticket; append the other
+    /// query parameters)
+    websocket_path: String,
 }
 
-/// POST /api/v1/vms/:vm_id/agent/shutdown - Graceful shutdown via agent
-async fn agent_shutdown(
+/// POST /api/v1/vms/:vm_id/agent/shell/ticket - Issue a short-lived shell ticket
+async fn create_agent_shell_ticket(
+    Path(vm_id): Path<String>,
+) -> Json<AgentShellTicketResponse> {
+    let ticket = crate::console_proxy::console_tickets().issue(&vm_id);
+    
+    info!(vm_id = %vm_id, expires_at = %ticket.expires_at, "Issued agent shell ticket");
+    
+    Json(AgentShellTicketResponse {
+        websocket_path: format!("/api/v1/vms/{}/agent/shell?ticket={}", vm_id, ticket.ticket),
+        expires_at: ticket.expires_at.to_rfc3339(),
+        ticket: ticket.ticket,
+        vm_id,
+    })
+}
+
+/// Query parameters for the agent shell WebSocket
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AgentShellQuery {
+    /// Single-use ticket from `POST .../agent/shell/ticket`
+    ticket: String,
+    /// Guest user (default: root)
+    #[serde(default)]
+    user: String,
+    /// Command to run instead of the login shell
+    #[serde(default)]
+    command: String,
+    /// Initial terminal size
+    #[serde(default = "default_shell_cols")]
+    cols: u32,
+    #[serde(default = "default_shell_rows")]
+    rows: u32,
+    /// TERM for the session
+    #[serde(default)]
+    term: String,
+}
+
+fn default_shell_cols() -> u32 { 80 }
+fn default_shell_rows() -> u32 { 24 }
+
+/// Control messages from the shell client (JSON text frames)
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum AgentShellControl {
+    Resize { cols: u32, rows: u32 },
+    Close {
+        #[serde(default)]
+        force: bool,
+    },
+}
+
+/// GET /api/v1/vms/:vm_id/agent/shell - Interactive shell in the guest via the agent (WebSocket)
+///
+/// Requires a shell ticket. Works without guest networking or SSH. Terminal output is sent as binary
+/// frames; binary frames from the client are terminal input. Text frames carry
+/// JSON control messages: `{"type":"resize","cols":..,"rows":..}` and
+/// `{"type":"close","force":false}` from the client, `opened` and `exited`
+/// from the server.
+async fn agent_shell_ws(
+    ws: axum::extract::WebSocketUpgrade,
     State(state): State<Arc<AppState>>,
     Path(vm_id): Path<String>,
-) -> Result<Json<AgentShutdownResponse>, (StatusCode, Json<ApiError>)> {
-    info!(vm_id = %vm_id, "Initiating graceful shutdown via agent");
+    Query(params): Query<AgentShellQuery>,
+) -> Result<axum::response::Response, (StatusCode, Json<ApiError>)> {
+    use crate::event_store::{emit_event, Event, EventCategory, EventLevel};
     
-    // Verify VM exists and is running
-    let vms = state.service.hypervisor().list_vms().await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiError::new("list_vms_failed", &format!("Failed to list VMs: {}", e))),
-        )
-    })?;
+    crate::console_proxy::console_tickets()
+        .redeem(&params.ticket, &vm_id)
+        .map_err(|e| {
+            warn!(vm_id = %vm_id, error = %e, "Rejected agent shell ticket");
+            (StatusCode::UNAUTHORIZED, Json(ApiError::new("invalid_ticket", &e.to_string())))
+        })?;
     
+    let vms = state.service.hypervisor().list_vms().await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiError::new("list_vms_failed", &e.to_string()))))?;
     let vm = vms.iter()
         .find(|v| v.id == vm_id || v.name == vm_id)
-        .ok_or_else(|| {
-            (
-                StatusCode::NOT_FOUND,
-                Json(ApiError::new("vm_not_found", &format!("VM not found: {}", vm_id))),
-            )
-        })?;
+        .ok_or_else(|| (StatusCode::NOT_FOUND, Json(ApiError::new("vm_not_found", &format!("VM not found: {}", vm_id)))))?;
     
     if vm.state != limiquantix_hypervisor::types::VmState::Running {
-        return Ok(Json(AgentShutdownResponse {
-            success: false,
-            message: "VM is not running".to_string(),
-            error: Some(format!("VM is not running (state: {:?})", vm.state)),
-        }));
+        return Err((StatusCode::CONFLICT, Json(ApiError::new("vm_not_running", &format!("VM is not running (state: {:?})", vm.state)))));
     }
     
-    // Discover socket path and connect to agent (robust socket discovery)
-    if let Err(e) = discover_and_connect_agent(&state, &vm.id, &vm.name).await {
-        return Ok(Json(AgentShutdownResponse {
-            success: false,
-            message: "Failed to connect to agent".to_string(),
-            error: Some(format!("{}", e)),
-        }));
+    discover_and_connect_agent(&state, &vm.id, &vm.name).await
+        .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, Json(ApiError::new("agent_unavailable", &e))))?;
+    
+    let request = limiquantix_proto::agent::OpenSessionRequest {
+        command: params.command.clone(),
+        run_as_user: params.user.clone(),
+        pty: true,
+        cols: params.cols,
+        rows: params.rows,
+        term: params.term.clone(),
+        ..Default::default()
+    };
+    
+    // Open the session before upgrading so failures are plain HTTP errors
+    let session = {
+        let agents = state.service.agent_manager().await;
+        let client = agents.get(&vm.id)
+            .ok_or_else(|| (StatusCode::SERVICE_UNAVAILABLE, Json(ApiError::new("agent_unavailable", "Agent client not found after connection"))))?;
+        client.open_session(request).await.map_err(|e| match e.downcast_ref::<crate::agent_client::UnsupportedCapability>() {
+            Some(_) => agent_api_error(e),
+            None => (StatusCode::BAD_GATEWAY, Json(ApiError::new("session_failed", &e.to_string()))),
+        })?
+    };
+    
+    let user = if params.user.is_empty() { "root".to_string() } else { params.user };
+    emit_event(Event::new(
+        EventLevel::Info,
+        EventCategory::Security,
+        format!("Agent shell opened on VM {} as {}", vm.name, user),
+        "agent",
+    ).with_resource(vm.id.clone()));
+    
+    let vm_id = vm.id.clone();
+    Ok(ws.on_upgrade(move |socket| handle_agent_shell(socket, session, vm_id)))
+}
+
+/// Bridge an agent session and a shell WebSocket
+async fn handle_agent_shell(
+    mut socket: WebSocket,
+    mut session: crate::agent_client::AgentSession,
+    vm_id: String,
+) {
+    use crate::agent_client::SessionEvent;
+    
+    let opened = serde_json::json!({ "type": "opened", "sessionId": session.id() });
+    if socket.send(Message::Text(opened.to_string())).await.is_err() {
+        return;
     }
     
-    let agents = state.service.agent_manager().await;
-    if let Some(client) = agents.get(&vm.id) {
-        match client.shutdown(false).await {
-            Ok(result) => {
-                if result.accepted {
-                    Ok(Json(AgentShutdownResponse {
-                        success: true,
-                        message: "Graceful shutdown signal sent to guest".to_string(),
-                        error: None,
-                    }))
-                } else {
-                    Ok(Json(AgentShutdownResponse {
-                        success: false,
-                        message: "Shutdown was rejected by guest agent".to_string(),
-                        error: Some(result.error),
-                    }))
+    let mut exit_code = None;
+    loop {
+        tokio::select! {
+            event = session.recv() => {
+                match event {
+                    Some(SessionEvent::Output(output)) => {
+                        if socket.send(Message::Binary(output.data)).await.is_err() {
+                            break;
+                        }
+                    }
+                    Some(SessionEvent::Exited(exited)) => {
+                        exit_code = Some(exited.exit_code);
+                        let msg = serde_json::json!({
+                            "type": "exited",
+                            "exitCode": exited.exit_code,
+                            "signal": exited.signal,
+                            "error": exited.error,
+                        });
+                        let _ = socket.send(Message::Text(msg.to_string())).await;
+                        let _ = socket.send(Message::Close(None)).await;
+                        break;
+                    }
+                    None => {
+                        // Agent connection lost
+                        let _ = socket.send(Message::Close(None)).await;
+                        break;
+                    }
                 }
             }
-            Err(e) => {
-                Ok(Json(AgentShutdownResponse {
-                    success: false,
-                    message: "Failed to send shutdown signal".to_string(),
-                    error: Some(format!("{}", e)),
-                }))
+            msg = socket.recv() => {
+                let result = match msg {
+                    Some(Ok(Message::Binary(data))) => session.send_input(data, false).await,
+                    Some(Ok(Message::Text(text))) => match serde_json::from_str::<AgentShellControl>(&text) {
+                        Ok(AgentShellControl::Resize { cols, rows }) => session.resize(cols, rows).await,
+                        Ok(AgentShellControl::Close { force }) => session.close(force).await,
+                        // Plain text is terminal input
+                        Err(_) => session.send_input(text.into_bytes(), false).await,
+                    },
+                    Some(Ok(Message::Ping(data))) => {
+                        if socket.send(Message::Pong(data)).await.is_err() {
+                            break;
+                        }
+                        continue;
+                    }
+                    Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                    Some(Ok(_)) => continue,
+                };
+                if let Err(e) = result {
+                    warn!(vm_id = %vm_id, error = %e, "Agent shell input failed");
+                    break;
+                }
             }
         }
-    } else {
-        Ok(Json(AgentShutdownResponse {
-            success: false,
-            message: "Agent client not found after connection".to_string(),
-            error: None,
-        }))
     }
+    
+    // Dropping the session kills the shell if it is still running
+    info!(vm_id = %vm_id, session_id = %session.id(), exit_code = ?exit_code, "Agent shell closed");
 }
 
-/// POST /api/v1/vms/:vm_id/agent/reboot - Graceful reboot via agent
-async fn agent_reboot(
+/// Request to forward a node-local listener to a port inside a VM
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreatePortForwardRequest {
+    /// Loopback "address:port" or a socket path in /run/limiquantix/port-forwards
+    /// (default: 127.0.0.1 on a free port)
+    #[serde(default = "default_forward_listen")]
+    listen: String,
+    /// Loopback address inside the guest to connect to
+    #[serde(default = "default_forward_target_host")]
+    target_host: String,
+    target_port: u16,
+}
+
+fn default_forward_listen() -> String { "127.0.0.1:0".to_string() }
+fn default_forward_target_host() -> String { "127.0.0.1".to_string() }
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PortForwardListResponse {
+    forwards: Vec<crate::agent_client::PortForwardInfo>,
+}
+
+/// GET /api/v1/vms/:vm_id/agent/port-forwards - List port forwards of a VM
+async fn list_agent_port_forwards(
     State(state): State<Arc<AppState>>,
     Path(vm_id): Path<String>,
-) -> Result<Json<AgentShutdownResponse>, (StatusCode, Json<ApiError>)> {
-    info!(vm_id = %vm_id, "Initiating graceful reboot via agent");
-    
-    // Verify VM exists and is running
-    let vms = state.service.hypervisor().list_vms().await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiError::new("list_vms_failed", &format!("Failed to list VMs: {}", e))),
-        )
-    })?;
+) -> Json<PortForwardListResponse> {
+    let forwards = state.service.port_forwards().list_port_forwards(Some(&vm_id)).await;
+    Json(PortForwardListResponse { forwards })
+}
+
+/// POST /api/v1/vms/:vm_id/agent/port-forwards - Forward a node-local port into the guest
+///
+/// Connections to the listener are carried over the agent channel as
+/// multiplexed streams, so the guest needs no network path to the node.
+async fn create_agent_port_forward(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+    Json(request): Json<CreatePortForwardRequest>,
+) -> Result<(StatusCode, Json<crate::agent_client::PortForwardInfo>), (StatusCode, Json<ApiError>)> {
+    use crate::agent_client::{PortForwardSpec, StreamOpenerSource};
+    use crate::event_store::{emit_event, Event, EventCategory, EventLevel};
     
+    let vms = state.service.hypervisor().list_vms().await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiError::new("list_vms_failed", &e.to_string()))))?;
     let vm = vms.iter()
         .find(|v| v.id == vm_id || v.name == vm_id)
-        .ok_or_else(|| {
-            (
-                StatusCode::NOT_FOUND,
-                Json(ApiError::new("vm_not_found", &format!("VM not found: {}", vm_id))),
-            )
-        })?;
+        .ok_or_else(|| (StatusCode::NOT_FOUND, Json(ApiError::new("vm_not_found", &format!("VM not found: {}", vm_id)))))?;
+    
+    if request.target_port == 0 {
+        return Err((StatusCode::BAD_REQUEST, Json(ApiError::new("invalid_request", "targetPort is required"))));
+    }
+    
+    // Resolve the agent per connection, so the forward survives agent restarts
+    let source: StreamOpenerSource = {
+        let state = state.clone();
+        let vm_id = vm.id.clone();
+        let vm_name = vm.name.clone();
+        Arc::new(move || {
+            let state = state.clone();
+            let vm_id = vm_id.clone();
+            let vm_name = vm_name.clone();
+            Box::pin(async move {
+                discover_and_connect_agent(&state, &vm_id, &vm_name).await
+                    .map_err(|e| anyhow::anyhow!(e))?;
+                let agents = state.service.agent_manager().await;
+                let client = agents.get(&vm_id)
+                    .ok_or_else(|| anyhow::anyhow!("Agent client not found after connection"))?;
+                client.require_capability("port_forward").await?;
+                client.stream_opener()
+            })
+        })
+    };
+    
+    let spec = PortForwardSpec {
+        listen: request.listen,
+        target_host: request.target_host,
+        target_port: request.target_port,
+    };
+    let forward = state.service.port_forwards().add_port_forward(&vm.id, spec, source).await
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ApiError::new("port_forward_failed", &format!("{:#}", e)))))?;
+    
+    emit_event(Event::new(
+        EventLevel::Info,
+        EventCategory::Security,
+        format!("Port forward {} -> {}:{} opened on VM {}", forward.listen, forward.target_host, forward.target_port, vm.name),
+        "agent",
+    ).with_resource(vm.id.clone()));
+    
+    Ok((StatusCode::CREATED, Json(forward)))
+}
+
+/// DELETE /api/v1/vms/:vm_id/agent/port-forwards/:forward_id - Stop a port forward
+async fn delete_agent_port_forward(
+    State(state): State<Arc<AppState>>,
+    Path((vm_id, forward_id)): Path<(String, String)>,
+) -> Result<StatusCode, (StatusCode, Json<ApiError>)> {
+    let forwards = state.service.port_forwards();
+    let owned = forwards.list_port_forwards(Some(&vm_id)).await.iter().any(|f| f.id == forward_id);
+    if !owned || forwards.remove_port_forward(&forward_id).await.is_none() {
+        return Err((StatusCode::NOT_FOUND, Json(ApiError::new("not_found", &format!("Port forward not found: {}", forward_id)))));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Resolve a running VM and make sure its guest agent is connected.
+async fn connect_vm_agent(
+    state: &Arc<AppState>,
+    vm_id: &str,
+) -> Result<limiquantix_hypervisor::VmInfo, (StatusCode, Json<ApiError>)> {
+    let vms = state.service.hypervisor().list_vms().await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiError::new("list_vms_failed", &e.to_string()))))?;
+    let vm = vms.into_iter()
+        .find(|v| v.id == vm_id || v.name == vm_id)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, Json(ApiError::new("vm_not_found", &format!("VM not found: {}", vm_id)))))?;
     
     if vm.state != limiquantix_hypervisor::types::VmState::Running {
-        return Ok(Json(AgentShutdownResponse {
-            success: false,
-            message: "VM is not running".to_string(),
-            error: Some(format!("VM is not running (state: {:?})", vm.state)),
-        }));
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ApiError::new("vm_not_running", &format!("VM is not running (state: {:?})", vm.state))),
+        ));
     }
     
-    // Discover socket path and connect to agent (robust socket discovery)
-    if let Err(e) = discover_and_connect_agent(&state, &vm.id, &vm.name).await {
-        return Ok(Json(AgentShutdownResponse {
-            success: false,
-            message: "Failed to connect to agent".to_string(),
-            error: Some(format!("{}", e)),
-        }));
+    discover_and_connect_agent(state, &vm.id, &vm.name).await
+        .map_err(|e| (StatusCode::SERVICE_UNAVAILABLE, Json(ApiError::new("agent_unavailable", &e))))?;
+    Ok(vm)
+}
+
+/// Look up the connected agent client of a VM.
+fn agent_for<'a>(
+    agents: &'a HashMap<String, crate::agent_client::AgentClient>,
+    vm_id: &str,
+) -> Result<&'a crate::agent_client::AgentClient, (StatusCode, Json<ApiError>)> {
+    agents.get(vm_id).ok_or_else(|| {
+        (StatusCode::SERVICE_UNAVAILABLE, Json(ApiError::new("agent_unavailable", "Agent client not found after connection")))
+    })
+}
+
+/// Map a guest agent error to an API error. Agents that are too old for an
+/// operation get 501 so the UI can tell them apart from failures.
+fn agent_api_error(e: anyhow::Error) -> (StatusCode, Json<ApiError>) {
+    match e.downcast_ref::<crate::agent_client::UnsupportedCapability>() {
+        Some(unsupported) => (StatusCode::NOT_IMPLEMENTED, Json(ApiError::new("agent_unsupported", &unsupported.to_string()))),
+        None => (StatusCode::BAD_GATEWAY, Json(ApiError::new("agent_error", &format!("{:#}", e)))),
     }
+}
+
+fn rfc3339(t: Option<prost_types::Timestamp>) -> Option<String> {
+    t.and_then(|t| chrono::DateTime::from_timestamp(t.seconds, t.nanos as u32)).map(|dt| dt.to_rfc3339())
+}
+
+fn non_empty(s: String) -> Option<String> {
+    if s.is_empty() { None } else { Some(s) }
+}
+
+/// Emit a security event for a change made inside a guest.
+fn emit_guest_event(vm: &limiquantix_hypervisor::VmInfo, message: String) {
+    use crate::event_store::{emit_event, Event, EventCategory, EventLevel};
     
+    emit_event(Event::new(EventLevel::Info, EventCategory::Security, message, "agent").with_resource(vm.id.clone()));
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GuestCapabilitiesResponse {
+    version: String,
+    os: String,
+    architecture: String,
+    capabilities: Vec<String>,
+    features: HashMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    build_time: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    build_commit: Option<String>,
+}
+
+/// GET /api/v1/vms/:vm_id/agent/capabilities - Capabilities of the guest agent
+async fn get_agent_capabilities(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+) -> Result<Json<GuestCapabilitiesResponse>, (StatusCode, Json<ApiError>)> {
+    let vm = connect_vm_agent(&state, &vm_id).await?;
     let agents = state.service.agent_manager().await;
-    if let Some(client) = agents.get(&vm.id) {
-        match client.shutdown(true).await {
-            Ok(result) => {
-                if result.accepted {
-                    Ok(Json(AgentShutdownResponse {
-                        success: true,
-                        message: "Graceful reboot signal sent to guest".to_string(),
-                        error: None,
-                    }))
-                } else {
-                    Ok(Json(AgentShutdownResponse {
-                        success: false,
-                        message: "Reboot was rejected by guest agent".to_string(),
-                        error: Some(result.error),
-                    }))
-                }
-            }
-            Err(e) => {
-                Ok(Json(AgentShutdownResponse {
-                    success: false,
-                    message: "Failed to send reboot signal".to_string(),
-                    error: Some(format!("{}", e)),
-                }))
-            }
-        }
-    } else {
-        Ok(Json(AgentShutdownResponse {
-            success: false,
-            message: "Agent client not found after connection".to_string(),
-            error: None,
-        }))
-    }
+    let caps = agent_for(&agents, &vm.id)?.get_capabilities().await.map_err(agent_api_error)?;
+    
+    Ok(Json(GuestCapabilitiesResponse {
+        version: caps.version,
+        os: caps.os,
+        architecture: caps.architecture,
+        capabilities: caps.capabilities,
+        features: caps.features.into_iter().collect(),
+        build_time: non_empty(caps.build_time),
+        build_commit: non_empty(caps.build_commit),
+    }))
 }
 
-/// Query parameters for file listing
+/// Query parameters for process and software listings
 #[derive(Deserialize)]
-struct ListFilesQuery {
-    /// Path to list (default: /)
-    #[serde(default = "default_path")]
-    path: String,
-    /// Include hidden files
+#[serde(rename_all = "camelCase")]
+struct GuestListQuery {
+    /// Name filter (default: all)
     #[serde(default)]
-    include_hidden: bool,
-}
-
-fn default_path() -> String {
-    "/".to_string()
+    filter: String,
+    /// Maximum entries (0 = unlimited)
+    #[serde(default)]
+    max_entries: u32,
 }
 
-/// Directory entry response
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
-struct DirectoryEntryResponse {
+struct GuestProcessResponse {
+    pid: u32,
+    ppid: u32,
     name: String,
-    path: String,
-    is_directory: bool,
-    is_symlink: bool,
-    size_bytes: u64,
-    mode: u32,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    modified_at: Option<String>,
+    command_line: String,
+    user: String,
+    cpu_percent: f64,
+    memory_bytes: u64,
+    state: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    owner: Option<String>,
+    started_at: Option<String>,
+    thread_count: u32,
     #[serde(skip_serializing_if = "Option::is_none")]
-    group: Option<String>,
+    working_directory: Option<String>,
 }
 
-/// Response for file listing
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
-struct ListFilesResponse {
-    success: bool,
-    path: String,
-    entries: Vec<DirectoryEntryResponse>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    error: Option<String>,
+struct GuestProcessListResponse {
+    processes: Vec<GuestProcessResponse>,
 }
 
-/// GET /api/v1/vms/:vm_id/agent/files/list - List directory contents in the guest
-async fn list_guest_files(
+/// GET /api/v1/vms/:vm_id/agent/processes - List processes in the guest
+async fn list_guest_processes(
     State(state): State<Arc<AppState>>,
     Path(vm_id): Path<String>,
-    Query(params): Query<ListFilesQuery>,
-) -> Result<Json<ListFilesResponse>, (StatusCode, Json<ApiError>)> {
-    info!(vm_id = %vm_id, path = %params.path, "Listing directory in guest");
+    Query(params): Query<GuestListQuery>,
+) -> Result<Json<GuestProcessListResponse>, (StatusCode, Json<ApiError>)> {
+    use limiquantix_proto::agent::ListProcessesRequest;
     
-    // Verify VM exists and is running
-    let vms = state.service.hypervisor().list_vms().await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiError::new("list_vms_failed", &format!("Failed to list VMs: {}", e))),
-        )
-    })?;
+    let vm = connect_vm_agent(&state, &vm_id).await?;
+    let agents = state.service.agent_manager().await;
+    let result = agent_for(&agents, &vm.id)?
+        .list_processes(ListProcessesRequest {
+            filter: params.filter,
+            include_threads: false,
+            max_entries: params.max_entries,
+        })
+        .await
+        .map_err(agent_api_error)?;
     
-    let vm = vms.iter()
-        .find(|v| v.id == vm_id || v.name == vm_id)
-        .ok_or_else(|| {
-            (
-                StatusCode::NOT_FOUND,
-                Json(ApiError::new("vm_not_found", &format!("VM not found: {}", vm_id))),
-            )
-        })?;
-    
-    if vm.state != limiquantix_hypervisor::types::VmState::Running {
-        return Ok(Json(ListFilesResponse {
-            success: false,
-            path: params.path.clone(),
-            entries: vec![],
-            error: Some(format!("VM is not running (state: {:?})", vm.state)),
-        }));
-    }
-    
-    // Discover socket path and connect to agent (robust socket discovery)
-    if let Err(e) = discover_and_connect_agent(&state, &vm.id, &vm.name).await {
-        return Ok(Json(ListFilesResponse {
-            success: false,
-            path: params.path,
-            entries: vec![],
-            error: Some(format!("Failed to connect to agent: {}", e)),
-        }));
-    }
+    let processes = result.processes.into_iter()
+        .map(|p| GuestProcessResponse {
+            pid: p.pid,
+            ppid: p.ppid,
+            name: p.name,
+            command_line: p.command_line,
+            user: p.user,
+            cpu_percent: p.cpu_percent,
+            memory_bytes: p.memory_bytes,
+            state: p.state,
+            started_at: rfc3339(p.started_at),
+            thread_count: p.thread_count,
+            working_directory: non_empty(p.working_directory),
+        })
+        .collect();
+    Ok(Json(GuestProcessListResponse { processes }))
+}
+
+/// Request to signal a guest process
+#[derive(Deserialize)]
+struct KillGuestProcessRequest {
+    /// Signal number (default: SIGTERM; ignored on Windows)
+    #[serde(default = "default_kill_signal")]
+    signal: i32,
+}
+
+fn default_kill_signal() -> i32 { 15 }
+
+/// POST /api/v1/vms/:vm_id/agent/processes/:pid/kill - Signal a guest process
+async fn kill_guest_process(
+    State(state): State<Arc<AppState>>,
+    Path((vm_id, pid)): Path<(String, u32)>,
+    request: Option<Json<KillGuestProcessRequest>>,
+) -> Result<StatusCode, (StatusCode, Json<ApiError>)> {
+    let signal = request.map(|Json(r)| r.signal).unwrap_or_else(default_kill_signal);
     
+    let vm = connect_vm_agent(&state, &vm_id).await?;
     let agents = state.service.agent_manager().await;
-    if let Some(client) = agents.get(&vm.id) {
-        match client.list_directory(&params.path, params.include_hidden).await {
-            Ok(result) => {
-                let entries: Vec<DirectoryEntryResponse> = result.entries
-                    .into_iter()
-                    .map(|e| DirectoryEntryResponse {
-                        name: e.name,
-                        path: e.path,
-                        is_directory: e.is_directory,
-                        is_symlink: e.is_symlink,
-                        size_bytes: e.size_bytes,
-                        mode: e.mode,
-                        modified_at: e.modified_at.map(|t| {
-                            chrono::DateTime::from_timestamp(t.seconds, t.nanos as u32)
-                                .map(|dt| dt.to_rfc3339())
-                                .unwrap_or_default()
-                        }),
-                        owner: if e.owner.is_empty() { None } else { Some(e.owner) },
-                        group: if e.group.is_empty() { None } else { Some(e.group) },
-                    })
-                    .collect();
-                
-                Ok(Json(ListFilesResponse {
-                    success: true,
-                    path: params.path,
-                    entries,
-                    error: None,
-                }))
-            }
-            Err(e) => {
-                Ok(Json(ListFilesResponse {
-                    success: false,
-                    path: params.path,
-                    entries: vec![],
-                    error: Some(format!("Failed to list directory: {}", e)),
-                }))
-            }
-        }
-    } else {
-        Ok(Json(ListFilesResponse {
-            success: false,
-            path: params.path,
-            entries: vec![],
-            error: Some("Agent client not found after connection".to_string()),
-        }))
-    }
+    agent_for(&agents, &vm.id)?.kill_process(pid, signal).await.map_err(agent_api_error)?;
+    
+    info!(vm_id = %vm.id, pid = pid, signal = signal, "Signalled guest process");
+    emit_guest_event(&vm, format!("Process {} signalled ({}) in VM {}", pid, signal, vm.name));
+    Ok(StatusCode::NO_CONTENT)
 }
 
-/// Query parameters for file reading
+/// Query parameters for service listing
 #[derive(Deserialize)]
-struct ReadFileQuery {
-    /// Path to the file to read
-    path: String,
+#[serde(rename_all = "camelCase")]
+struct GuestServicesQuery {
+    #[serde(default)]
+    filter: String,
+    #[serde(default)]
+    running_only: bool,
 }
 
-/// Response for file reading
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
-struct ReadFileResponse {
-    success: bool,
-    path: String,
-    /// Base64-encoded file content
-    #[serde(skip_serializing_if = "Option::is_none")]
-    content: Option<String>,
-    /// File size in bytes
+struct GuestServiceResponse {
+    name: String,
+    display_name: String,
+    state: String,
+    start_type: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    size_bytes: Option<u64>,
+    description: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    error: Option<String>,
+    pid: Option<u32>,
+    memory_bytes: u64,
 }
 
-/// GET /api/v1/vms/:vm_id/agent/files/read - Read a file from the guest
-async fn read_guest_file(
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GuestServiceListResponse {
+    services: Vec<GuestServiceResponse>,
+}
+
+/// GET /api/v1/vms/:vm_id/agent/services - List services (systemd units or Windows services)
+async fn list_guest_services(
     State(state): State<Arc<AppState>>,
     Path(vm_id): Path<String>,
-    Query(params): Query<ReadFileQuery>,
-) -> Result<Json<ReadFileResponse>, (StatusCode, Json<ApiError>)> {
-    info!(vm_id = %vm_id, path = %params.path, "Reading file from guest");
-    
-    // Verify VM exists and is running
-    let vms = state.service.hypervisor().list_vms().await.map_err(|e| {
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ApiError::new("list_vms_failed", &format!("Failed to list VMs: {}", e))),
-        )
-    })?;
+    Query(params): Query<GuestServicesQuery>,
+) -> Result<Json<GuestServiceListResponse>, (StatusCode, Json<ApiError>)> {
+    use limiquantix_proto::agent::ListServicesRequest;
     
-    let vm = vms.iter()
-        .find(|v| v.id == vm_id || v.name == vm_id)
-        .ok_or_else(|| {
-            (
-                StatusCode::NOT_FOUND,
-                Json(ApiError::new("vm_not_found", &format!("VM not found: {}", vm_id))),
-            )
-        })?;
+    let vm = connect_vm_agent(&state, &vm_id).await?;
+    let agents = state.service.agent_manager().await;
+    let result = agent_for(&agents, &vm.id)?
+        .list_services(ListServicesRequest { filter: params.filter, running_only: params.running_only })
+        .await
+        .map_err(agent_api_error)?;
+    
+    let services = result.services.into_iter()
+        .map(|s| GuestServiceResponse {
+            name: s.name,
+            display_name: s.display_name,
+            state: s.state,
+            start_type: s.start_type,
+            description: non_empty(s.description),
+            pid: if s.pid == 0 { None } else { Some(s.pid) },
+            memory_bytes: s.memory_bytes,
+        })
+        .collect();
+    Ok(Json(GuestServiceListResponse { services }))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GuestServiceControlResponse {
+    name: String,
+    action: String,
+    new_state: String,
+}
+
+/// POST /api/v1/vms/:vm_id/agent/services/:service/:action - Start, stop, restart,
+/// enable, disable or query a guest service
+async fn control_guest_service(
+    State(state): State<Arc<AppState>>,
+    Path((vm_id, service, action)): Path<(String, String, String)>,
+) -> Result<Json<GuestServiceControlResponse>, (StatusCode, Json<ApiError>)> {
+    use limiquantix_proto::agent::{ServiceAction, ServiceControlRequest};
+    
+    let service_action = match action.as_str() {
+        "start" => ServiceAction::Start,
+        "stop" => ServiceAction::Stop,
+        "restart" => ServiceAction::Restart,
+        "enable" => ServiceAction::Enable,
+        "disable" => ServiceAction::Disable,
+        "status" => ServiceAction::Status,
+        _ => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ApiError::new("invalid_action", &format!("Unknown service action: {}", action))),
+            ));
+        }
+    };
     
-    if vm.state != limiquantix_hypervisor::types::VmState::Running {
-        return Ok(Json(ReadFileResponse {
-            success: false,
-            path: params.path.clone(),
-            content: None,
-            size_bytes: None,
-            error: Some(format!("VM is not running (state: {:?})", vm.state)),
-        }));
-    }
+    let vm = connect_vm_agent(&state, &vm_id).await?;
+    let agents = state.service.agent_manager().await;
+    let result = agent_for(&agents, &vm.id)?
+        .service_control(ServiceControlRequest { name: service.clone(), action: service_action as i32 })
+        .await
+        .map_err(agent_api_error)?;
     
-    // Discover socket path and connect to agent (robust socket discovery)
-    if let Err(e) = discover_and_connect_agent(&state, &vm.id, &vm.name).await {
-        return Ok(Json(ReadFileResponse {
-            success: false,
-            path: params.path,
-            content: None,
-            size_bytes: None,
-            error: Some(format!("Failed to connect to agent: {}", e)),
-        }));
+    if service_action != ServiceAction::Status {
+        info!(vm_id = %vm.id, service = %service, action = %action, "Controlled guest service");
+        emit_guest_event(&vm, format!("Service {} {} in VM {}", service, action, vm.name));
     }
     
-    let agents = state.service.agent_manager().await;
-    if let Some(client) = agents.get(&vm.id) {
-        match client.read_file(&params.path).await {
-            Ok(data) => {
-                use base64::Engine;
-                let content = base64::engine::general_purpose::STANDARD.encode(&data);
-                
-                Ok(Json(ReadFileResponse {
-                    success: true,
-                    path: params.path,
-                    content: Some(content),
-                    size_bytes: Some(data.len() as u64),
-                    error: None,
-                }))
-            }
-            Err(e) => {
-                Ok(Json(ReadFileResponse {
-                    success: false,
-                    path: params.path,
-                    content: None,
-                    size_bytes: None,
-                    error: Some(format!("Failed to read file: {}", e)),
-                }))
-            }
-        }
-    } else {
-        Ok(Json(ReadFileResponse {
-            success: false,
-            path: params.path,
-            content: None,
-            size_bytes: None,
-            error: Some("Agent client not found after connection".to_string()),
-        }))
-    }
+    Ok(Json(GuestServiceControlResponse { name: service, action, new_state: result.new_state }))
 }
 
-/// Request body for command execution
-#[derive(Deserialize)]
+#[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
-struct ExecuteCommandRequest {
-    /// Command to execute
-    command: String,
-    /// Timeout in seconds (default: 30, max: 30 for sync execution)
-    #[serde(default = "default_execute_timeout")]
-    timeout_seconds: u32,
-    /// Wait for command to complete (always true for this endpoint)
-    #[serde(default = "default_true")]
-    wait_for_exit: bool,
+struct GuestCpuResponse {
+    model: String,
+    vendor: String,
+    architecture: String,
+    sockets: u32,
+    cores: u32,
+    threads: u32,
+    frequency_mhz: f64,
+    l1_cache_bytes: u64,
+    l2_cache_bytes: u64,
+    l3_cache_bytes: u64,
+    flags: Vec<String>,
 }
 
-fn default_execute_timeout() -> u32 {
-    30
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GuestMemoryResponse {
+    total_bytes: u64,
+    available_bytes: u64,
+    memory_type: String,
+    speed_mhz: u32,
+    dimm_count: u32,
 }
 
-fn default_true() -> bool {
-    true
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GuestPartitionResponse {
+    device: String,
+    mount_point: String,
+    filesystem: String,
+    size_bytes: u64,
+    used_bytes: u64,
+    label: String,
 }
 
-/// Response for command execution
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
-struct ExecuteCommandResponse {
-    success: bool,
-    exit_code: i32,
-    stdout: String,
-    stderr: String,
-    timed_out: bool,
-    duration_ms: u64,
+struct GuestDiskResponse {
+    device: String,
+    model: String,
+    serial: String,
+    size_bytes: u64,
+    disk_type: String,
+    interface: String,
+    is_virtual: bool,
+    partitions: Vec<GuestPartitionResponse>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GuestNetworkAdapterResponse {
+    name: String,
+    mac_address: String,
+    adapter_type: String,
+    speed_mbps: u32,
+    is_virtual: bool,
+    driver: String,
+    pci_address: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GuestBiosResponse {
+    vendor: String,
+    version: String,
+    release_date: String,
+    system_manufacturer: String,
+    system_product: String,
+    system_serial: String,
+    system_uuid: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GuestOsResponse {
+    name: String,
+    version: String,
+    build: String,
+    kernel: String,
+    architecture: String,
+    hostname: String,
+    domain: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    error: Option<String>,
+    install_date: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_boot: Option<String>,
 }
 
-/// POST /api/v1/vms/:vm_id/execute - Execute a command in the guest VM
-/// 
-/// WARNING: This endpoint has a maximum timeout of 30 seconds for synchronous execution.
-/// Long-running scripts may timeout but continue running in the guest. For long operations,
-/// check the VM console or use asynchronous execution (future feature).
-async fn execute_in_guest(
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GuestGpuResponse {
+    name: String,
+    vendor: String,
+    driver_version: String,
+    vram_bytes: u64,
+    pci_address: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GuestHardwareResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cpu: Option<GuestCpuResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    memory: Option<GuestMemoryResponse>,
+    disks: Vec<GuestDiskResponse>,
+    network_adapters: Vec<GuestNetworkAdapterResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bios: Option<GuestBiosResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    os: Option<GuestOsResponse>,
+    gpus: Vec<GuestGpuResponse>,
+}
+
+impl From<limiquantix_proto::agent::HardwareInfo> for GuestHardwareResponse {
+    fn from(hw: limiquantix_proto::agent::HardwareInfo) -> Self {
+        Self {
+            cpu: hw.cpu.map(|c| GuestCpuResponse {
+                model: c.model,
+                vendor: c.vendor,
+                architecture: c.architecture,
+                sockets: c.sockets,
+                cores: c.cores,
+                threads: c.threads,
+                frequency_mhz: c.frequency_mhz,
+                l1_cache_bytes: c.l1_cache_bytes,
+                l2_cache_bytes: c.l2_cache_bytes,
+                l3_cache_bytes: c.l3_cache_bytes,
+                flags: c.flags,
+            }),
+            memory: hw.memory.map(|m| GuestMemoryResponse {
+                total_bytes: m.total_bytes,
+                available_bytes: m.available_bytes,
+                memory_type: m.memory_type,
+                speed_mhz: m.speed_mhz,
+                dimm_count: m.dimm_count,
+            }),
+            disks: hw.disks.into_iter()
+                .map(|d| GuestDiskResponse {
+                    device: d.device,
+                    model: d.model,
+                    serial: d.serial,
+                    size_bytes: d.size_bytes,
+                    disk_type: d.disk_type,
+                    interface: d.interface,
+                    is_virtual: d.is_virtual,
+                    partitions: d.partitions.into_iter()
+                        .map(|p| GuestPartitionResponse {
+                            device: p.device,
+                            mount_point: p.mount_point,
+                            filesystem: p.filesystem,
+                            size_bytes: p.size_bytes,
+                            used_bytes: p.used_bytes,
+                            label: p.label,
+                        })
+                        .collect(),
+                })
+                .collect(),
+            network_adapters: hw.network_adapters.into_iter()
+                .map(|n| GuestNetworkAdapterResponse {
+                    name: n.name,
+                    mac_address: n.mac_address,
+                    adapter_type: n.adapter_type,
+                    speed_mbps: n.speed_mbps,
+                    is_virtual: n.is_virtual,
+                    driver: n.driver,
+                    pci_address: n.pci_address,
+                })
+                .collect(),
+            bios: hw.bios.map(|b| GuestBiosResponse {
+                vendor: b.vendor,
+                version: b.version,
+                release_date: b.release_date,
+                system_manufacturer: b.system_manufacturer,
+                system_product: b.system_product,
+                system_serial: b.system_serial,
+                system_uuid: b.system_uuid,
+            }),
+            os: hw.os.map(|o| GuestOsResponse {
+                name: o.name,
+                version: o.version,
+                build: o.build,
+                kernel: o.kernel,
+                architecture: o.architecture,
+                hostname: o.hostname,
+                domain: o.domain,
+                install_date: rfc3339(o.install_date),
+                last_boot: rfc3339(o.last_boot),
+            }),
+            gpus: hw.gpus.into_iter()
+                .map(|g| GuestGpuResponse {
+                    name: g.name,
+                    vendor: g.vendor,
+                    driver_version: g.driver_version,
+                    vram_bytes: g.vram_bytes,
+                    pci_address: g.pci_address,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// GET /api/v1/vms/:vm_id/agent/hardware - Hardware inventory as seen by the guest
+async fn get_guest_hardware(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+) -> Result<Json<GuestHardwareResponse>, (StatusCode, Json<ApiError>)> {
+    use limiquantix_proto::agent::GetHardwareInfoRequest;
+    
+    let vm = connect_vm_agent(&state, &vm_id).await?;
+    let agents = state.service.agent_manager().await;
+    let result = agent_for(&agents, &vm.id)?
+        .get_hardware_info(GetHardwareInfoRequest {
+            include_cpu_details: true,
+            include_disk_details: true,
+            include_network_details: true,
+        })
+        .await
+        .map_err(agent_api_error)?;
+    
+    Ok(Json(result.hardware.unwrap_or_default().into()))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GuestSoftwareResponse {
+    name: String,
+    version: String,
+    publisher: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    install_date: Option<String>,
+    size_bytes: u64,
+    package_type: String,
+    architecture: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GuestSoftwareListResponse {
+    software: Vec<GuestSoftwareResponse>,
+    total_count: u32,
+}
+
+/// GET /api/v1/vms/:vm_id/agent/software - List installed software in the guest
+async fn list_guest_software(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+    Query(params): Query<GuestListQuery>,
+) -> Result<Json<GuestSoftwareListResponse>, (StatusCode, Json<ApiError>)> {
+    use limiquantix_proto::agent::ListInstalledSoftwareRequest;
+    
+    let vm = connect_vm_agent(&state, &vm_id).await?;
+    let agents = state.service.agent_manager().await;
+    let result = agent_for(&agents, &vm.id)?
+        .list_installed_software(ListInstalledSoftwareRequest { filter: params.filter, max_entries: params.max_entries })
+        .await
+        .map_err(agent_api_error)?;
+    
+    let software = result.software.into_iter()
+        .map(|s| GuestSoftwareResponse {
+            name: s.name,
+            version: s.version,
+            publisher: s.publisher,
+            install_date: rfc3339(s.install_date),
+            size_bytes: s.size_bytes,
+            package_type: s.package_type,
+            architecture: s.architecture,
+        })
+        .collect();
+    Ok(Json(GuestSoftwareListResponse { software, total_count: result.total_count }))
+}
+
+/// Request to reset a guest user's password
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ResetGuestPasswordRequest {
+    username: String,
+    password: String,
+    /// Force a password change at next login
+    #[serde(default)]
+    expire: bool,
+}
+
+/// POST /api/v1/vms/:vm_id/agent/password - Reset a guest user's password
+async fn reset_guest_password(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+    Json(request): Json<ResetGuestPasswordRequest>,
+) -> Result<StatusCode, (StatusCode, Json<ApiError>)> {
+    use limiquantix_proto::agent::ResetPasswordRequest;
+    
+    if request.username.is_empty() || request.password.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, Json(ApiError::new("invalid_request", "username and password are required"))));
+    }
+    
+    let vm = connect_vm_agent(&state, &vm_id).await?;
+    let agents = state.service.agent_manager().await;
+    agent_for(&agents, &vm.id)?
+        .reset_password(ResetPasswordRequest {
+            username: request.username.clone(),
+            new_password: request.password,
+            expire: request.expire,
+        })
+        .await
+        .map_err(agent_api_error)?;
+    
+    info!(vm_id = %vm.id, username = %request.username, "Reset guest password");
+    emit_guest_event(&vm, format!("Password of user {} reset in VM {}", request.username, vm.name));
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Request to apply a network configuration in the guest
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ConfigureGuestNetworkRequest {
+    /// Netplan YAML
+    netplan_config: String,
+    #[serde(default = "default_true")]
+    apply_now: bool,
+}
+
+/// POST /api/v1/vms/:vm_id/agent/network - Write (and apply) the guest's Netplan configuration
+async fn configure_guest_network(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+    Json(request): Json<ConfigureGuestNetworkRequest>,
+) -> Result<StatusCode, (StatusCode, Json<ApiError>)> {
+    use limiquantix_proto::agent::ConfigureNetworkRequest;
+    
+    if request.netplan_config.trim().is_empty() {
+        return Err((StatusCode::BAD_REQUEST, Json(ApiError::new("invalid_request", "netplanConfig is required"))));
+    }
+    
+    let vm = connect_vm_agent(&state, &vm_id).await?;
+    let agents = state.service.agent_manager().await;
+    agent_for(&agents, &vm.id)?
+        .configure_network(ConfigureNetworkRequest {
+            netplan_config: request.netplan_config,
+            apply_now: request.apply_now,
+        })
+        .await
+        .map_err(agent_api_error)?;
+    
+    emit_guest_event(&vm, format!("Network configuration updated in VM {}", vm.name));
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Query parameters for guest user listing
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GuestUsersQuery {
+    /// Include system accounts
+    #[serde(default)]
+    include_system: bool,
+    /// Only this user
+    #[serde(default)]
+    username: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GuestUserResponse {
+    username: String,
+    /// UID (Linux) or SID (Windows)
+    id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    full_name: Option<String>,
+    home: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    shell: Option<String>,
+    groups: Vec<String>,
+    locked: bool,
+    system: bool,
+    ssh_authorized_keys: Vec<String>,
+}
+
+impl From<limiquantix_proto::agent::GuestUser> for GuestUserResponse {
+    fn from(u: limiquantix_proto::agent::GuestUser) -> Self {
+        Self {
+            username: u.username,
+            id: u.id,
+            full_name: non_empty(u.full_name),
+            home: u.home,
+            shell: non_empty(u.shell),
+            groups: u.groups,
+            locked: u.locked,
+            system: u.system,
+            ssh_authorized_keys: u.ssh_authorized_keys,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GuestUserListResponse {
+    users: Vec<GuestUserResponse>,
+}
+
+/// GET /api/v1/vms/:vm_id/agent/users - List local accounts in the guest
+async fn list_guest_users(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+    Query(params): Query<GuestUsersQuery>,
+) -> Result<Json<GuestUserListResponse>, (StatusCode, Json<ApiError>)> {
+    use limiquantix_proto::agent::ListUsersRequest;
+    
+    let vm = connect_vm_agent(&state, &vm_id).await?;
+    let agents = state.service.agent_manager().await;
+    let result = agent_for(&agents, &vm.id)?
+        .list_users(ListUsersRequest { include_system: params.include_system, username: params.username })
+        .await
+        .map_err(agent_api_error)?;
+    
+    Ok(Json(GuestUserListResponse { users: result.users.into_iter().map(Into::into).collect() }))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GuestUserAccountResponse {
+    /// The account after the change (absent after a delete)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<GuestUserResponse>,
+    sessions_ended: u32,
+}
+
+/// Apply an account change in the guest (the agent writes it to its audit
+/// log) and record it as a security event.
+async fn apply_guest_user_account(
+    state: &Arc<AppState>,
+    vm_id: &str,
+    request: limiquantix_proto::agent::UserAccountRequest,
+    change: &str,
+) -> Result<Json<GuestUserAccountResponse>, (StatusCode, Json<ApiError>)> {
+    if request.username.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, Json(ApiError::new("invalid_request", "username is required"))));
+    }
+    
+    let username = request.username.clone();
+    let vm = connect_vm_agent(state, vm_id).await?;
+    let agents = state.service.agent_manager().await;
+    let result = agent_for(&agents, &vm.id)?
+        .user_account(request)
+        .await
+        .map_err(agent_api_error)?;
+    
+    info!(vm_id = %vm.id, username = %username, change = %change, "Changed guest user account");
+    emit_guest_event(&vm, format!("User {} {} in VM {}", username, change, vm.name));
+    Ok(Json(GuestUserAccountResponse {
+        user: result.user.map(Into::into),
+        sessions_ended: result.sessions_ended,
+    }))
+}
+
+/// Request to create a guest user
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateGuestUserRequest {
+    username: String,
+    /// Initial password (omit for SSH key logins only)
+    #[serde(default)]
+    password: String,
+    #[serde(default)]
+    full_name: String,
+    /// Login shell (Linux)
+    #[serde(default)]
+    shell: String,
+    /// Supplementary groups
+    #[serde(default)]
+    groups: Vec<String>,
+    /// OpenSSH public key to authorize
+    #[serde(default)]
+    ssh_public_key: String,
+}
+
+/// POST /api/v1/vms/:vm_id/agent/users - Create a local account in the guest
+async fn create_guest_user(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+    Json(request): Json<CreateGuestUserRequest>,
+) -> Result<(StatusCode, Json<GuestUserAccountResponse>), (StatusCode, Json<ApiError>)> {
+    use limiquantix_proto::agent::{UserAccountAction, UserAccountRequest};
+    
+    let account = UserAccountRequest {
+        action: UserAccountAction::Create as i32,
+        username: request.username,
+        password: request.password,
+        full_name: request.full_name,
+        shell: request.shell,
+        groups: request.groups,
+        ssh_public_key: request.ssh_public_key,
+        ..Default::default()
+    };
+    let response = apply_guest_user_account(&state, &vm_id, account, "created").await?;
+    Ok((StatusCode::CREATED, response))
+}
+
+/// Query parameters for guest user deletion
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeleteGuestUserQuery {
+    /// Also delete the home directory / profile
+    #[serde(default)]
+    remove_home: bool,
+}
+
+/// DELETE /api/v1/vms/:vm_id/agent/users/:username - Delete a guest account,
+/// ending its sessions first
+async fn delete_guest_user(
+    State(state): State<Arc<AppState>>,
+    Path((vm_id, username)): Path<(String, String)>,
+    Query(params): Query<DeleteGuestUserQuery>,
+) -> Result<Json<GuestUserAccountResponse>, (StatusCode, Json<ApiError>)> {
+    use limiquantix_proto::agent::{UserAccountAction, UserAccountRequest};
+    
+    let account = UserAccountRequest {
+        action: UserAccountAction::Delete as i32,
+        username,
+        remove_home: params.remove_home,
+        ..Default::default()
+    };
+    apply_guest_user_account(&state, &vm_id, account, "deleted").await
+}
+
+/// Request to replace a guest user's supplementary groups
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetGuestUserGroupsRequest {
+    groups: Vec<String>,
+}
+
+/// PUT /api/v1/vms/:vm_id/agent/users/:username/groups - Replace a guest user's group memberships
+async fn set_guest_user_groups(
+    State(state): State<Arc<AppState>>,
+    Path((vm_id, username)): Path<(String, String)>,
+    Json(request): Json<SetGuestUserGroupsRequest>,
+) -> Result<Json<GuestUserAccountResponse>, (StatusCode, Json<ApiError>)> {
+    use limiquantix_proto::agent::{UserAccountAction, UserAccountRequest};
+    
+    let change = format!("added to groups [{}]", request.groups.join(", "));
+    let account = UserAccountRequest {
+        action: UserAccountAction::SetGroups as i32,
+        username,
+        groups: request.groups,
+        ..Default::default()
+    };
+    apply_guest_user_account(&state, &vm_id, account, &change).await
+}
+
+/// POST /api/v1/vms/:vm_id/agent/users/:username/lock - Block password and SSH key logins
+async fn lock_guest_user(
+    State(state): State<Arc<AppState>>,
+    Path((vm_id, username)): Path<(String, String)>,
+) -> Result<Json<GuestUserAccountResponse>, (StatusCode, Json<ApiError>)> {
+    use limiquantix_proto::agent::{UserAccountAction, UserAccountRequest};
+    
+    let account = UserAccountRequest { action: UserAccountAction::Lock as i32, username, ..Default::default() };
+    apply_guest_user_account(&state, &vm_id, account, "locked").await
+}
+
+/// POST /api/v1/vms/:vm_id/agent/users/:username/unlock - Allow logins again
+async fn unlock_guest_user(
+    State(state): State<Arc<AppState>>,
+    Path((vm_id, username)): Path<(String, String)>,
+) -> Result<Json<GuestUserAccountResponse>, (StatusCode, Json<ApiError>)> {
+    use limiquantix_proto::agent::{UserAccountAction, UserAccountRequest};
+    
+    let account = UserAccountRequest { action: UserAccountAction::Unlock as i32, username, ..Default::default() };
+    apply_guest_user_account(&state, &vm_id, account, "unlocked").await
+}
+
+/// An OpenSSH public key line
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GuestSshKeyRequest {
+    public_key: String,
+}
+
+/// POST /api/v1/vms/:vm_id/agent/users/:username/ssh-keys - Authorize an SSH key for a guest user
+async fn add_guest_user_ssh_key(
+    State(state): State<Arc<AppState>>,
+    Path((vm_id, username)): Path<(String, String)>,
+    Json(request): Json<GuestSshKeyRequest>,
+) -> Result<Json<GuestUserAccountResponse>, (StatusCode, Json<ApiError>)> {
+    use limiquantix_proto::agent::{UserAccountAction, UserAccountRequest};
+    
+    let account = UserAccountRequest {
+        action: UserAccountAction::AddSshKey as i32,
+        username,
+        ssh_public_key: request.public_key,
+        ..Default::default()
+    };
+    apply_guest_user_account(&state, &vm_id, account, "was given an SSH key").await
+}
+
+/// DELETE /api/v1/vms/:vm_id/agent/users/:username/ssh-keys - Revoke an SSH key of a guest user
+async fn remove_guest_user_ssh_key(
+    State(state): State<Arc<AppState>>,
+    Path((vm_id, username)): Path<(String, String)>,
+    Json(request): Json<GuestSshKeyRequest>,
+) -> Result<Json<GuestUserAccountResponse>, (StatusCode, Json<ApiError>)> {
+    use limiquantix_proto::agent::{UserAccountAction, UserAccountRequest};
+    
+    let account = UserAccountRequest {
+        action: UserAccountAction::RemoveSshKey as i32,
+        username,
+        ssh_public_key: request.public_key,
+        ..Default::default()
+    };
+    apply_guest_user_account(&state, &vm_id, account, "had an SSH key revoked").await
+}
+
+/// Query parameters for logging off a guest user
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LogoffGuestUserQuery {
+    /// Only end this session
+    #[serde(default)]
+    session_id: String,
+}
+
+/// POST /api/v1/vms/:vm_id/agent/users/:username/logoff - Force-end a guest user's sessions
+async fn logoff_guest_user(
+    State(state): State<Arc<AppState>>,
+    Path((vm_id, username)): Path<(String, String)>,
+    Query(params): Query<LogoffGuestUserQuery>,
+) -> Result<Json<GuestUserAccountResponse>, (StatusCode, Json<ApiError>)> {
+    use limiquantix_proto::agent::{UserAccountAction, UserAccountRequest};
+    
+    let account = UserAccountRequest {
+        action: UserAccountAction::Logoff as i32,
+        username,
+        session_id: params.session_id,
+        ..Default::default()
+    };
+    apply_guest_user_account(&state, &vm_id, account, "logged off").await
+}
+
+/// Query parameters for login session listing
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GuestSessionsQuery {
+    #[serde(default)]
+    username: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GuestLoginSessionResponse {
+    session_id: String,
+    username: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tty: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remote_host: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    login_time: Option<String>,
+    state: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GuestLoginSessionListResponse {
+    sessions: Vec<GuestLoginSessionResponse>,
+}
+
+/// GET /api/v1/vms/:vm_id/agent/sessions - List logged-in sessions in the guest
+async fn list_guest_login_sessions(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+    Query(params): Query<GuestSessionsQuery>,
+) -> Result<Json<GuestLoginSessionListResponse>, (StatusCode, Json<ApiError>)> {
+    use limiquantix_proto::agent::ListLoginSessionsRequest;
+    
+    let vm = connect_vm_agent(&state, &vm_id).await?;
+    let agents = state.service.agent_manager().await;
+    let result = agent_for(&agents, &vm.id)?
+        .list_login_sessions(ListLoginSessionsRequest { username: params.username })
+        .await
+        .map_err(agent_api_error)?;
+    
+    let sessions = result.sessions.into_iter()
+        .map(|s| GuestLoginSessionResponse {
+            session_id: s.session_id,
+            username: s.username,
+            tty: non_empty(s.tty),
+            remote_host: non_empty(s.remote_host),
+            login_time: rfc3339(s.login_time),
+            state: s.state,
+        })
+        .collect();
+    Ok(Json(GuestLoginSessionListResponse { sessions }))
+}
+
+/// Clipboard content; text is sent as-is, anything else base64-encoded
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GuestClipboard {
+    /// text, image, files or html
+    #[serde(default = "default_clipboard_type")]
+    r#type: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    mime_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    data_base64: Option<String>,
+}
+
+fn default_clipboard_type() -> String { "text".to_string() }
+
+fn clipboard_type_name(t: limiquantix_proto::agent::ClipboardType) -> &'static str {
+    use limiquantix_proto::agent::ClipboardType;
+    match t {
+        ClipboardType::Text => "text",
+        ClipboardType::Image => "image",
+        ClipboardType::Files => "files",
+        ClipboardType::Html => "html",
+    }
+}
+
+/// GET /api/v1/vms/:vm_id/agent/clipboard - Read the guest clipboard
+async fn get_guest_clipboard(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+) -> Result<Json<GuestClipboard>, (StatusCode, Json<ApiError>)> {
+    use base64::Engine;
+    use limiquantix_proto::agent::{ClipboardGetRequest, ClipboardType};
+    
+    let vm = connect_vm_agent(&state, &vm_id).await?;
+    let agents = state.service.agent_manager().await;
+    let result = agent_for(&agents, &vm.id)?
+        .clipboard_get(ClipboardGetRequest { preferred_type: ClipboardType::Text as i32 })
+        .await
+        .map_err(agent_api_error)?;
+    
+    let clipboard_type = result.r#type();
+    let (text, data_base64) = match clipboard_type {
+        ClipboardType::Text | ClipboardType::Html => (Some(String::from_utf8_lossy(&result.data).into_owned()), None),
+        _ => (None, Some(base64::engine::general_purpose::STANDARD.encode(&result.data))),
+    };
+    Ok(Json(GuestClipboard {
+        r#type: clipboard_type_name(clipboard_type).to_string(),
+        mime_type: result.mime_type,
+        text,
+        data_base64,
+    }))
+}
+
+/// PUT /api/v1/vms/:vm_id/agent/clipboard - Replace the guest clipboard
+async fn set_guest_clipboard(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+    Json(request): Json<GuestClipboard>,
+) -> Result<StatusCode, (StatusCode, Json<ApiError>)> {
+    use base64::Engine;
+    use limiquantix_proto::agent::{ClipboardType, ClipboardUpdateRequest};
+    
+    let clipboard_type = match request.r#type.as_str() {
+        "text" => ClipboardType::Text,
+        "image" => ClipboardType::Image,
+        "files" => ClipboardType::Files,
+        "html" => ClipboardType::Html,
+        other => {
+            return Err((StatusCode::BAD_REQUEST, Json(ApiError::new("invalid_request", &format!("Unknown clipboard type: {}", other)))));
+        }
+    };
+    let data = match (request.text, request.data_base64) {
+        (Some(text), _) => text.into_bytes(),
+        (None, Some(encoded)) => base64::engine::general_purpose::STANDARD.decode(encoded)
+            .map_err(|e| (StatusCode::BAD_REQUEST, Json(ApiError::new("invalid_request", &format!("Invalid base64 data: {}", e)))))?,
+        (None, None) => {
+            return Err((StatusCode::BAD_REQUEST, Json(ApiError::new("invalid_request", "text or dataBase64 is required"))));
+        }
+    };
+    
+    let vm = connect_vm_agent(&state, &vm_id).await?;
+    let agents = state.service.agent_manager().await;
+    agent_for(&agents, &vm.id)?
+        .clipboard_update(ClipboardUpdateRequest {
+            r#type: clipboard_type as i32,
+            data,
+            mime_type: request.mime_type,
+        })
+        .await
+        .map_err(agent_api_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Request to resize the guest display
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ResizeGuestDisplayRequest {
+    width: u32,
+    height: u32,
+    #[serde(default)]
+    dpi: u32,
+    #[serde(default)]
+    display_id: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ResizeGuestDisplayResponse {
+    width: u32,
+    height: u32,
+}
+
+/// POST /api/v1/vms/:vm_id/agent/display/resize - Change the guest display resolution
+async fn resize_guest_display(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+    Json(request): Json<ResizeGuestDisplayRequest>,
+) -> Result<Json<ResizeGuestDisplayResponse>, (StatusCode, Json<ApiError>)> {
+    use limiquantix_proto::agent::DisplayResizeRequest;
+    
+    if request.width == 0 || request.height == 0 {
+        return Err((StatusCode::BAD_REQUEST, Json(ApiError::new("invalid_request", "width and height are required"))));
+    }
+    
+    let vm = connect_vm_agent(&state, &vm_id).await?;
+    let agents = state.service.agent_manager().await;
+    let result = agent_for(&agents, &vm.id)?
+        .display_resize(DisplayResizeRequest {
+            width: request.width,
+            height: request.height,
+            dpi: request.dpi,
+            display_id: request.display_id,
+        })
+        .await
+        .map_err(agent_api_error)?;
+    
+    Ok(Json(ResizeGuestDisplayResponse { width: result.actual_width, height: result.actual_height }))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GuestTimeSyncResponse {
+    offset_seconds: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    time_source: Option<String>,
+}
+
+/// POST /api/v1/vms/:vm_id/agent/time/sync - Synchronise the guest clock
+async fn sync_guest_time(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+) -> Result<Json<GuestTimeSyncResponse>, (StatusCode, Json<ApiError>)> {
+    let vm = connect_vm_agent(&state, &vm_id).await?;
+    let agents = state.service.agent_manager().await;
+    let result = agent_for(&agents, &vm.id)?.sync_time(true).await.map_err(agent_api_error)?;
+    if !result.success {
+        return Err((StatusCode::BAD_GATEWAY, Json(ApiError::new("agent_error", &result.error))));
+    }
+    
+    Ok(Json(GuestTimeSyncResponse { offset_seconds: result.offset_seconds, time_source: non_empty(result.time_source) }))
+}
+
+/// Request to freeze guest filesystems
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct QuiesceGuestFilesystemsRequest {
+    /// Mount points to freeze (empty = all writable filesystems)
+    #[serde(default)]
+    mount_points: Vec<String>,
+    /// Auto-thaw after this many seconds (0 = agent default)
+    #[serde(default)]
+    timeout_seconds: u32,
+    #[serde(default)]
+    run_pre_freeze_scripts: bool,
+    /// Skip the database quiesce providers configured in the guest
+    #[serde(default)]
+    skip_applications: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FrozenFilesystemResponse {
+    mount_point: String,
+    device: String,
+    filesystem: String,
+    frozen: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ApplicationQuiesceResponse {
+    provider: String,
+    success: bool,
+    duration_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    detail: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl From<limiquantix_proto::agent::ApplicationQuiesceResult> for ApplicationQuiesceResponse {
+    fn from(app: limiquantix_proto::agent::ApplicationQuiesceResult) -> Self {
+        Self {
+            provider: app.provider,
+            success: app.success,
+            duration_ms: app.duration_ms,
+            detail: non_empty(app.detail),
+            error: non_empty(app.error),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct QuiesceGuestFilesystemsResponse {
+    success: bool,
+    /// Pass to the thaw request
+    quiesce_token: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    auto_thaw_at: Option<String>,
+    frozen: Vec<FrozenFilesystemResponse>,
+    applications: Vec<ApplicationQuiesceResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// POST /api/v1/vms/:vm_id/agent/filesystems/quiesce - Freeze guest filesystems
+///
+/// The guest thaws them on its own after the timeout if no thaw request arrives.
+async fn quiesce_guest_filesystems(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+    request: Option<Json<QuiesceGuestFilesystemsRequest>>,
+) -> Result<Json<QuiesceGuestFilesystemsResponse>, (StatusCode, Json<ApiError>)> {
+    let Json(request) = request.unwrap_or_default();
+    let vm = connect_vm_agent(&state, &vm_id).await?;
+    let agents = state.service.agent_manager().await;
+    let result = agent_for(&agents, &vm.id)?
+        .quiesce_filesystems(request.mount_points, request.timeout_seconds, request.run_pre_freeze_scripts, request.skip_applications)
+        .await
+        .map_err(agent_api_error)?;
+    
+    if result.success {
+        emit_guest_event(&vm, format!("Quiesced guest filesystems ({} frozen)", result.frozen.len()));
+    }
+    
+    Ok(Json(QuiesceGuestFilesystemsResponse {
+        success: result.success,
+        quiesce_token: result.quiesce_token,
+        auto_thaw_at: rfc3339(result.auto_thaw_at),
+        frozen: result.frozen.into_iter().map(|fs| FrozenFilesystemResponse {
+            mount_point: fs.mount_point,
+            device: fs.device,
+            filesystem: fs.filesystem,
+            frozen: fs.frozen,
+            error: non_empty(fs.error),
+        }).collect(),
+        applications: result.applications.into_iter().map(Into::into).collect(),
+        error: non_empty(result.error),
+    }))
+}
+
+/// Request to thaw guest filesystems
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct ThawGuestFilesystemsRequest {
+    /// Token from the quiesce response
+    #[serde(default)]
+    quiesce_token: String,
+    #[serde(default)]
+    run_post_thaw_scripts: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ThawGuestFilesystemsResponse {
+    success: bool,
+    thawed_mount_points: Vec<String>,
+    frozen_duration_ms: u64,
+    applications: Vec<ApplicationQuiesceResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// POST /api/v1/vms/:vm_id/agent/filesystems/thaw - Thaw frozen guest filesystems
+async fn thaw_guest_filesystems(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+    request: Option<Json<ThawGuestFilesystemsRequest>>,
+) -> Result<Json<ThawGuestFilesystemsResponse>, (StatusCode, Json<ApiError>)> {
+    let Json(request) = request.unwrap_or_default();
+    let vm = connect_vm_agent(&state, &vm_id).await?;
+    let agents = state.service.agent_manager().await;
+    let result = agent_for(&agents, &vm.id)?
+        .thaw_filesystems(non_empty(request.quiesce_token), request.run_post_thaw_scripts)
+        .await
+        .map_err(agent_api_error)?;
+    
+    Ok(Json(ThawGuestFilesystemsResponse {
+        success: result.success,
+        thawed_mount_points: result.thawed_mount_points,
+        frozen_duration_ms: result.frozen_duration_ms,
+        applications: result.applications.into_iter().map(Into::into).collect(),
+        error: non_empty(result.error),
+    }))
+}
+
+/// Request to grow guest filesystems
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct GrowGuestFilesystemsRequest {
+    /// Mount points or drive letters (empty = all)
+    #[serde(default)]
+    mount_points: Vec<String>,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GrownFilesystemResponse {
+    mount_point: String,
+    device: String,
+    filesystem: String,
+    steps: Vec<String>,
+    size_before_bytes: u64,
+    size_after_bytes: u64,
+    grown: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GrowGuestFilesystemsResponse {
+    success: bool,
+    filesystems: Vec<GrownFilesystemResponse>,
+}
+
+/// POST /api/v1/vms/:vm_id/agent/filesystems/grow - Grow guest partitions and filesystems to fill resized disks
+async fn grow_guest_filesystems(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+    request: Option<Json<GrowGuestFilesystemsRequest>>,
+) -> Result<Json<GrowGuestFilesystemsResponse>, (StatusCode, Json<ApiError>)> {
+    use limiquantix_proto::agent::GrowFilesystemsRequest;
+    
+    let Json(request) = request.unwrap_or_default();
+    let vm = connect_vm_agent(&state, &vm_id).await?;
+    let agents = state.service.agent_manager().await;
+    let result = agent_for(&agents, &vm.id)?
+        .grow_filesystems(GrowFilesystemsRequest { mount_points: request.mount_points, dry_run: request.dry_run })
+        .await
+        .map_err(agent_api_error)?;
+    
+    let grown: Vec<&str> = result.filesystems.iter().filter(|fs| fs.grown).map(|fs| fs.mount_point.as_str()).collect();
+    if !grown.is_empty() {
+        emit_guest_event(&vm, format!("Grew guest filesystems: {}", grown.join(", ")));
+    }
+    
+    Ok(Json(GrowGuestFilesystemsResponse {
+        success: result.success,
+        filesystems: result.filesystems.into_iter().map(|fs| GrownFilesystemResponse {
+            mount_point: fs.mount_point,
+            device: fs.device,
+            filesystem: fs.filesystem,
+            steps: fs.steps,
+            size_before_bytes: fs.size_before_bytes,
+            size_after_bytes: fs.size_after_bytes,
+            grown: fs.grown,
+            error: non_empty(fs.error),
+        }).collect(),
+    }))
+}
+
+/// Request to trim guest filesystems
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct TrimGuestFilesystemsRequest {
+    /// Mount points or drive letters (empty = all)
+    #[serde(default)]
+    mount_points: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TrimmedFilesystemResponse {
+    mount_point: String,
+    trimmed_bytes: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct VolumeReclaimResponse {
+    pool_id: String,
+    volume_id: String,
+    path: String,
+    allocation_before_bytes: u64,
+    allocation_bytes: u64,
+    reclaimed_bytes: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TrimGuestFilesystemsResponse {
+    success: bool,
+    filesystems: Vec<TrimmedFilesystemResponse>,
+    /// Allocation change of the VM's pool volumes
+    volumes: Vec<VolumeReclaimResponse>,
+    reclaimed_bytes: u64,
+}
+
+/// POST /api/v1/vms/:vm_id/agent/filesystems/trim - Discard unused guest blocks and report the pool space reclaimed
+async fn trim_guest_filesystems(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+    request: Option<Json<TrimGuestFilesystemsRequest>>,
+) -> Result<Json<TrimGuestFilesystemsResponse>, (StatusCode, Json<ApiError>)> {
+    use tonic::Request;
+    use limiquantix_proto::{NodeDaemonService, TrimGuestFilesystemsRequest as ProtoRequest};
+    use limiquantix_proto::agent::TrimFilesystemsRequest;
+    
+    let Json(request) = request.unwrap_or_default();
+    let vm = connect_vm_agent(&state, &vm_id).await?;
+    let result = state.service.trim_guest_filesystems(Request::new(ProtoRequest {
+        vm_id: vm.id.clone(),
+        request: Some(TrimFilesystemsRequest { mount_points: request.mount_points }),
+    })).await
+        .map_err(|e| {
+            let status = match e.code() {
+                tonic::Code::Unimplemented => StatusCode::NOT_IMPLEMENTED,
+                tonic::Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+                tonic::Code::FailedPrecondition => StatusCode::CONFLICT,
+                _ => StatusCode::BAD_GATEWAY,
+            };
+            (status, Json(ApiError::new("trim_failed", e.message())))
+        })?
+        .into_inner();
+    
+    if result.reclaimed_bytes > 0 {
+        emit_guest_event(&vm, format!("Trimmed guest filesystems, {} bytes reclaimed", result.reclaimed_bytes));
+    }
+    
+    let guest = result.guest.unwrap_or_default();
+    Ok(Json(TrimGuestFilesystemsResponse {
+        success: guest.success,
+        filesystems: guest.filesystems.into_iter().map(|fs| TrimmedFilesystemResponse {
+            mount_point: fs.mount_point,
+            trimmed_bytes: fs.trimmed_bytes,
+            error: non_empty(fs.error),
+        }).collect(),
+        volumes: result.volumes.into_iter().filter_map(|v| {
+            let volume = v.volume?;
+            Some(VolumeReclaimResponse {
+                pool_id: volume.pool_id,
+                volume_id: volume.volume_id,
+                path: volume.path,
+                allocation_before_bytes: v.allocation_before_bytes,
+                allocation_bytes: volume.allocation_bytes,
+                reclaimed_bytes: v.reclaimed_bytes,
+            })
+        }).collect(),
+        reclaimed_bytes: result.reclaimed_bytes,
+    }))
+}
+
+/// Map guest patching gRPC errors to HTTP status codes
+fn patch_error(code: &str, e: tonic::Status) -> (StatusCode, Json<ApiError>) {
+    let status = match e.code() {
+        tonic::Code::NotFound => StatusCode::NOT_FOUND,
+        tonic::Code::Unimplemented => StatusCode::NOT_IMPLEMENTED,
+        tonic::Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::BAD_GATEWAY,
+    };
+    (status, Json(ApiError::new(code, e.message())))
+}
+
+/// Query parameters for the pending update listing
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GuestUpdatesQuery {
+    /// Refresh the package metadata first
+    #[serde(default)]
+    refresh: bool,
+    /// Only security updates
+    #[serde(default)]
+    security_only: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AvailableUpdateResponse {
+    /// Pass this in `packages` to install only selected updates
+    id: String,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    current_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    available_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+    security: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    severity: Option<String>,
+    advisories: Vec<String>,
+    reboot_expected: bool,
+}
+
+impl From<limiquantix_proto::agent::AvailableUpdate> for AvailableUpdateResponse {
+    fn from(u: limiquantix_proto::agent::AvailableUpdate) -> Self {
+        Self {
+            id: u.id,
+            name: u.name,
+            current_version: non_empty(u.current_version),
+            available_version: non_empty(u.available_version),
+            source: non_empty(u.source),
+            security: u.security,
+            severity: non_empty(u.severity),
+            advisories: u.advisories,
+            reboot_expected: u.reboot_expected,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GuestUpdateListResponse {
+    package_manager: String,
+    updates: Vec<AvailableUpdateResponse>,
+    /// False if the package manager has no security metadata (apk)
+    security_classified: bool,
+    reboot_required: bool,
+}
+
+/// GET /api/v1/vms/:vm_id/agent/updates - List pending OS updates in the guest
+async fn list_guest_updates(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+    Query(params): Query<GuestUpdatesQuery>,
+) -> Result<Json<GuestUpdateListResponse>, (StatusCode, Json<ApiError>)> {
+    use tonic::Request;
+    use limiquantix_proto::{NodeDaemonService, ListGuestUpdatesRequest};
+    use limiquantix_proto::agent::ListUpdatesRequest;
+    
+    let vm = connect_vm_agent(&state, &vm_id).await?;
+    let result = state.service.list_guest_updates(Request::new(ListGuestUpdatesRequest {
+        vm_id: vm.id,
+        request: Some(ListUpdatesRequest { refresh: params.refresh, security_only: params.security_only }),
+    })).await
+        .map_err(|e| patch_error("list_updates_failed", e))?
+        .into_inner();
+    
+    Ok(Json(GuestUpdateListResponse {
+        package_manager: result.package_manager,
+        updates: result.updates.into_iter().map(Into::into).collect(),
+        security_classified: result.security_classified,
+        reboot_required: result.reboot_required,
+    }))
+}
+
+/// Request to install pending updates in the guest
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct ApplyGuestUpdatesRequest {
+    /// Only install security updates
+    #[serde(default)]
+    security_only: bool,
+    /// Update IDs from the listing (empty = all pending)
+    #[serde(default)]
+    packages: Vec<String>,
+    /// "never" (default), "if_required" or "always"
+    #[serde(default)]
+    reboot_policy: Option<String>,
+    /// Delay between the end of the run and the reboot
+    #[serde(default)]
+    reboot_delay_seconds: u32,
+    /// Abort the package manager after this long (default: 3600)
+    #[serde(default)]
+    timeout_seconds: u32,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ApplyGuestUpdatesResponse {
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+    package_manager: String,
+    installed: Vec<AvailableUpdateResponse>,
+    failed: Vec<String>,
+    reboot_required: bool,
+    reboot_scheduled: bool,
+    /// Tail of the package manager output
+    output: String,
+    duration_ms: u64,
+}
+
+/// POST /api/v1/vms/:vm_id/agent/updates/apply - Install pending OS updates in the guest
+async fn apply_guest_updates(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+    request: Option<Json<ApplyGuestUpdatesRequest>>,
+) -> Result<Json<ApplyGuestUpdatesResponse>, (StatusCode, Json<ApiError>)> {
+    use tonic::Request;
+    use limiquantix_proto::{NodeDaemonService, ApplyGuestUpdatesRequest as ProtoRequest};
+    use limiquantix_proto::agent::{ApplyRebootPolicy, ApplyUpdatesRequest};
+    
+    let Json(request) = request.unwrap_or_default();
+    let reboot_policy = match request.reboot_policy.as_deref().unwrap_or("never") {
+        "never" => ApplyRebootPolicy::Never,
+        "if_required" => ApplyRebootPolicy::IfRequired,
+        "always" => ApplyRebootPolicy::Always,
+        other => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ApiError::new("invalid_request", &format!("Unknown reboot policy: {} (expected never, if_required or always)", other))),
+            ));
+        }
+    };
+    
+    let vm = connect_vm_agent(&state, &vm_id).await?;
+    let result = state.service.apply_guest_updates(Request::new(ProtoRequest {
+        vm_id: vm.id.clone(),
+        request: Some(ApplyUpdatesRequest {
+            security_only: request.security_only,
+            packages: request.packages,
+            reboot_policy: reboot_policy as i32,
+            reboot_delay_seconds: request.reboot_delay_seconds,
+            timeout_seconds: request.timeout_seconds,
+        }),
+    })).await
+        .map_err(|e| patch_error("apply_updates_failed", e))?
+        .into_inner();
+    
+    if !result.installed.is_empty() {
+        let reboot = if result.reboot_scheduled {
+            ", reboot scheduled"
+        } else if result.reboot_required {
+            ", reboot required"
+        } else {
+            ""
+        };
+        emit_guest_event(&vm, format!("Installed {} guest OS update(s) with {}{}", result.installed.len(), result.package_manager, reboot));
+    }
+    
+    Ok(Json(ApplyGuestUpdatesResponse {
+        success: result.success,
+        error: non_empty(result.error),
+        package_manager: result.package_manager,
+        installed: result.installed.into_iter().map(Into::into).collect(),
+        failed: result.failed,
+        reboot_required: result.reboot_required,
+        reboot_scheduled: result.reboot_scheduled,
+        output: result.output,
+        duration_ms: result.duration_ms,
+    }))
+}
+
+/// Query parameters for patch compliance
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PatchComplianceQuery {
+    /// Scan guests with a connected agent again instead of using the last scan
+    #[serde(default)]
+    rescan: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GuestPatchComplianceResponse {
+    vm_id: String,
+    vm_name: String,
+    /// "unknown", "compliant", "security_updates_pending" or "reboot_required"
+    status: String,
+    agent_connected: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_scan_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    package_manager: Option<String>,
+    security_updates: u32,
+    other_updates: u32,
+    security_classified: bool,
+    reboot_required: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_apply_at: Option<String>,
+    last_apply_succeeded: bool,
+    last_apply_installed: u32,
+    last_apply_failed: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl From<limiquantix_proto::GuestPatchCompliance> for GuestPatchComplianceResponse {
+    fn from(c: limiquantix_proto::GuestPatchCompliance) -> Self {
+        use limiquantix_proto::GuestPatchStatus;
+        
+        let status = match GuestPatchStatus::try_from(c.status).unwrap_or(GuestPatchStatus::Unknown) {
+            GuestPatchStatus::Unknown => "unknown",
+            GuestPatchStatus::Compliant => "compliant",
+            GuestPatchStatus::SecurityUpdatesPending => "security_updates_pending",
+            GuestPatchStatus::RebootRequired => "reboot_required",
+        };
+        Self {
+            vm_id: c.vm_id,
+            vm_name: c.vm_name,
+            status: status.to_string(),
+            agent_connected: c.agent_connected,
+            last_scan_at: non_empty(c.last_scan_at),
+            package_manager: non_empty(c.package_manager),
+            security_updates: c.security_updates,
+            other_updates: c.other_updates,
+            security_classified: c.security_classified,
+            reboot_required: c.reboot_required,
+            last_apply_at: non_empty(c.last_apply_at),
+            last_apply_succeeded: c.last_apply_succeeded,
+            last_apply_installed: c.last_apply_installed,
+            last_apply_failed: c.last_apply_failed,
+            error: non_empty(c.error),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PatchComplianceListResponse {
+    vms: Vec<GuestPatchComplianceResponse>,
+    /// VMs with security updates pending
+    security_updates_pending: u32,
+    /// VMs waiting for a reboot
+    reboot_required: u32,
+}
+
+/// GET /api/v1/vms/:vm_id/agent/patch-compliance - Patch compliance of a VM (stopped VMs report their last scan)
+async fn get_guest_patch_compliance(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+    Query(params): Query<PatchComplianceQuery>,
+) -> Result<Json<GuestPatchComplianceResponse>, (StatusCode, Json<ApiError>)> {
+    use tonic::Request;
+    use limiquantix_proto::{NodeDaemonService, GetGuestPatchComplianceRequest};
+    
+    let vms = state.service.hypervisor().list_vms().await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiError::new("list_vms_failed", &e.to_string()))))?;
+    let vm = vms.into_iter()
+        .find(|v| v.id == vm_id || v.name == vm_id)
+        .ok_or_else(|| (StatusCode::NOT_FOUND, Json(ApiError::new("vm_not_found", &format!("VM not found: {}", vm_id)))))?;
+    
+    // Without an agent the last recorded scan is reported
+    if vm.state == limiquantix_hypervisor::types::VmState::Running {
+        if let Err(e) = discover_and_connect_agent(&state, &vm.id, &vm.name).await {
+            debug!(vm_id = %vm.id, error = %e, "No agent for patch compliance scan");
+        }
+    }
+    
+    let result = state.service.get_guest_patch_compliance(Request::new(GetGuestPatchComplianceRequest {
+        vm_id: vm.id,
+        rescan: params.rescan,
+    })).await
+        .map_err(|e| patch_error("patch_compliance_failed", e))?
+        .into_inner();
+    
+    Ok(Json(result.into()))
+}
+
+/// GET /api/v1/patch-compliance - Patch compliance of every VM on this node
+async fn list_patch_compliance(
+    State(state): State<Arc<AppState>>,
+    Query(params): Query<PatchComplianceQuery>,
+) -> Result<Json<PatchComplianceListResponse>, (StatusCode, Json<ApiError>)> {
+    use tonic::Request;
+    use limiquantix_proto::{NodeDaemonService, ListGuestPatchComplianceRequest, GuestPatchStatus};
+    
+    let result = state.service.list_guest_patch_compliance(Request::new(ListGuestPatchComplianceRequest {
+        rescan: params.rescan,
+    })).await
+        .map_err(|e| patch_error("patch_compliance_failed", e))?
+        .into_inner();
+    
+    let count = |status: GuestPatchStatus| result.vms.iter().filter(|c| c.status == status as i32).count() as u32;
+    let security_updates_pending = count(GuestPatchStatus::SecurityUpdatesPending);
+    let reboot_required = count(GuestPatchStatus::RebootRequired);
+    
+    Ok(Json(PatchComplianceListResponse {
+        vms: result.vms.into_iter().map(Into::into).collect(),
+        security_updates_pending,
+        reboot_required,
+    }))
+}
+
+/// Request to create a directory in the guest
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateGuestDirectoryRequest {
+    path: String,
+    #[serde(default = "default_true")]
+    create_parents: bool,
+    /// Unix permissions (default: 0755)
+    #[serde(default)]
+    mode: u32,
+}
+
+/// POST /api/v1/vms/:vm_id/agent/files/mkdir - Create a directory in the guest
+async fn create_guest_directory(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+    Json(request): Json<CreateGuestDirectoryRequest>,
+) -> Result<StatusCode, (StatusCode, Json<ApiError>)> {
+    use limiquantix_proto::agent::CreateDirectoryRequest;
+    
+    let vm = connect_vm_agent(&state, &vm_id).await?;
+    let agents = state.service.agent_manager().await;
+    agent_for(&agents, &vm.id)?
+        .create_directory(CreateDirectoryRequest {
+            path: request.path,
+            create_parents: request.create_parents,
+            mode: request.mode,
+        })
+        .await
+        .map_err(agent_api_error)?;
+    Ok(StatusCode::CREATED)
+}
+
+/// Query parameters for deleting a guest file
+#[derive(Deserialize)]
+struct DeleteGuestFileQuery {
+    path: String,
+    #[serde(default)]
+    recursive: bool,
+}
+
+/// DELETE /api/v1/vms/:vm_id/agent/files?path=...&recursive=... - Delete a file or directory in the guest
+async fn delete_guest_file(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+    Query(params): Query<DeleteGuestFileQuery>,
+) -> Result<StatusCode, (StatusCode, Json<ApiError>)> {
+    use limiquantix_proto::agent::FileDeleteRequest;
+    
+    let vm = connect_vm_agent(&state, &vm_id).await?;
+    let agents = state.service.agent_manager().await;
+    agent_for(&agents, &vm.id)?
+        .delete_file(FileDeleteRequest { path: params.path.clone(), recursive: params.recursive })
+        .await
+        .map_err(agent_api_error)?;
+    
+    info!(vm_id = %vm.id, path = %params.path, recursive = params.recursive, "Deleted guest file");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Query parameters for stat
+#[derive(Deserialize)]
+struct StatGuestFileQuery {
+    path: String,
+}
+
+/// GET /api/v1/vms/:vm_id/agent/files/stat?path=... - Stat a file in the guest
+async fn stat_guest_file(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+    Query(params): Query<StatGuestFileQuery>,
+) -> Result<Json<DirectoryEntryResponse>, (StatusCode, Json<ApiError>)> {
+    let vm = connect_vm_agent(&state, &vm_id).await?;
+    let agents = state.service.agent_manager().await;
+    let result = agent_for(&agents, &vm.id)?.stat_file(&params.path).await.map_err(agent_api_error)?;
+    let e = result.entry
+        .ok_or_else(|| (StatusCode::NOT_FOUND, Json(ApiError::new("not_found", &format!("File not found: {}", params.path)))))?;
+    
+    Ok(Json(DirectoryEntryResponse {
+        name: e.name,
+        path: e.path,
+        is_directory: e.is_directory,
+        is_symlink: e.is_symlink,
+        size_bytes: e.size_bytes,
+        mode: e.mode,
+        modified_at: rfc3339(e.modified_at),
+        owner: non_empty(e.owner),
+        group: non_empty(e.group),
+    }))
+}
+
+/// Request to copy a directory tree between the node and a guest
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GuestTreeTransferRequest {
+    /// Upload: node path below the transfer directory; download: guest path
+    source: String,
+    /// Upload: guest directory; download: node directory below the transfer directory
+    destination: String,
+    #[serde(default)]
+    overwrite: bool,
+    /// Reuse the ID of an interrupted upload to resume it
+    #[serde(default)]
+    transfer_id: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GuestTreeTransferResponse {
+    transfer_id: String,
+    files: u64,
+    directories: u64,
+    symlinks: u64,
+    bytes: u64,
+    archive_sha256: String,
+}
+
+impl GuestTreeTransferResponse {
+    fn new(transfer_id: String, summary: limiquantix_proto::agent::TreeTransferSummary) -> Self {
+        Self {
+            transfer_id,
+            files: summary.files,
+            directories: summary.directories,
+            symlinks: summary.symlinks,
+            bytes: summary.bytes,
+            archive_sha256: summary.archive_sha256,
+        }
+    }
+}
+
+/// Resolve the node side of a tree transfer, confined to the transfer directory.
+fn node_transfer_path(path: &str) -> Result<PathBuf, (StatusCode, Json<ApiError>)> {
+    let root = crate::tree_transfer::transfer_root().map_err(|e| {
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiError::new("transfer_dir_failed", &e.to_string())))
+    })?;
+    crate::tree_transfer::confine_path(&root, path)
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ApiError::new("invalid_path", &e.to_string()))))
+}
+
+/// Connect the VM's agent for a transfer and check it supports tree transfers.
+async fn prepare_tree_transfer(
+    state: &Arc<AppState>,
+    vm_id: &str,
+) -> Result<limiquantix_hypervisor::VmInfo, (StatusCode, Json<ApiError>)> {
+    let vm = connect_vm_agent(state, vm_id).await?;
+    let agents = state.service.agent_manager().await;
+    agent_for(&agents, &vm.id)?.require_capability("tree_transfer").await.map_err(agent_api_error)?;
+    Ok(vm)
+}
+
+/// POST /api/v1/vms/:vm_id/agent/files/tree/upload - Copy a directory tree from the node's transfer directory into the guest
+async fn upload_guest_tree(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+    Json(request): Json<GuestTreeTransferRequest>,
+) -> Result<Json<GuestTreeTransferResponse>, (StatusCode, Json<ApiError>)> {
+    let source = node_transfer_path(&request.source)?;
+    if !source.exists() {
+        return Err((StatusCode::NOT_FOUND, Json(ApiError::new("not_found", &format!("Source not found: {}", request.source)))));
+    }
+    let vm = prepare_tree_transfer(&state, &vm_id).await?;
+    let transfer_id = request.transfer_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    
+    // Resolve the agent per chunk so the transfer can resume after a reconnect
+    let send = |req| {
+        let state = state.clone();
+        let vm_id = vm.id.clone();
+        let vm_name = vm.name.clone();
+        async move {
+            discover_and_connect_agent(&state, &vm_id, &vm_name).await
+                .map_err(|e| anyhow::anyhow!(e))?;
+            let agents = state.service.agent_manager().await;
+            agents.get(&vm_id)
+                .ok_or_else(|| anyhow::anyhow!("Agent client not found after connection"))?
+                .tree_upload(req)
+                .await
+        }
+    };
+    let summary = crate::tree_transfer::upload_tree(&transfer_id, source, &request.destination, request.overwrite, send)
+        .await
+        .map_err(agent_api_error)?;
+    
+    info!(vm_id = %vm.id, transfer_id = %transfer_id, files = summary.files, bytes = summary.bytes, "Uploaded directory tree to guest");
+    emit_guest_event(&vm, format!(
+        "Uploaded {} files ({} bytes) from {} to {}",
+        summary.files, summary.bytes, request.source, request.destination
+    ));
+    Ok(Json(GuestTreeTransferResponse::new(transfer_id, summary)))
+}
+
+/// POST /api/v1/vms/:vm_id/agent/files/tree/download - Copy a guest directory tree into the node's transfer directory
+async fn download_guest_tree(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+    Json(request): Json<GuestTreeTransferRequest>,
+) -> Result<Json<GuestTreeTransferResponse>, (StatusCode, Json<ApiError>)> {
+    let destination = node_transfer_path(&request.destination)?;
+    let vm = prepare_tree_transfer(&state, &vm_id).await?;
+    let transfer_id = request.transfer_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+    
+    let send = |req| {
+        let state = state.clone();
+        let vm_id = vm.id.clone();
+        let vm_name = vm.name.clone();
+        async move {
+            discover_and_connect_agent(&state, &vm_id, &vm_name).await
+                .map_err(|e| anyhow::anyhow!(e))?;
+            let agents = state.service.agent_manager().await;
+            agents.get(&vm_id)
+                .ok_or_else(|| anyhow::anyhow!("Agent client not found after connection"))?
+                .tree_download(req)
+                .await
+        }
+    };
+    let summary = crate::tree_transfer::download_tree(&transfer_id, &request.source, destination, request.overwrite, send)
+        .await
+        .map_err(agent_api_error)?;
+    
+    info!(vm_id = %vm.id, transfer_id = %transfer_id, files = summary.files, bytes = summary.bytes, "Downloaded directory tree from guest");
+    emit_guest_event(&vm, format!(
+        "Downloaded {} files ({} bytes) from {} to {}",
+        summary.files, summary.bytes, request.source, request.destination
+    ));
+    Ok(Json(GuestTreeTransferResponse::new(transfer_id, summary)))
+}
+
+/// Response for shutdown/reboot
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AgentShutdownResponse {
+    success: bool,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// POST /api/v1/vms/:vm_id/agent/shutdown - Graceful shutdown via agent
+async fn agent_shutdown(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+) -> Result<Json<AgentShutdownResponse>, (StatusCode, Json<ApiError>)> {
+    info!(vm_id = %vm_id, "Initiating graceful shutdown via agent");
+    
+    // Verify VM exists and is running
+    let vms = state.service.hypervisor().list_vms().await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiError::new("list_vms_failed", &format!("Failed to list VMs: {}", e))),
+        )
+    })?;
+    
+    let vm = vms.iter()
+        .find(|v| v.id == vm_id || v.name == vm_id)
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiError::new("vm_not_found", &format!("VM not found: {}", vm_id))),
+            )
+        })?;
+    
+    if vm.state != limiquantix_hypervisor::types::VmState::Running {
+        return Ok(Json(AgentShutdownResponse {
+            success: false,
+            message: "VM is not running".to_string(),
+            error: Some(format!("VM is not running (state: {:?})", vm.state)),
+        }));
+    }
+    
+    // Discover socket path and connect to agent (robust socket discovery)
+    if let Err(e) = discover_and_connect_agent(&state, &vm.id, &vm.name).await {
+        return Ok(Json(AgentShutdownResponse {
+            success: false,
+            message: "Failed to connect to agent".to_string(),
+            error: Some(format!("{}", e)),
+        }));
+    }
+    
+    let agents = state.service.agent_manager().await;
+    if let Some(client) = agents.get(&vm.id) {
+        match client.shutdown(false).await {
+            Ok(result) => {
+                if result.accepted {
+                    Ok(Json(AgentShutdownResponse {
+                        success: true,
+                        message: "Graceful shutdown signal sent to guest".to_string(),
+                        error: None,
+                    }))
+                } else {
+                    Ok(Json(AgentShutdownResponse {
+                        success: false,
+                        message: "Shutdown was rejected by guest agent".to_string(),
+                        error: Some(result.error),
+                    }))
+                }
+            }
+            Err(e) => {
+                Ok(Json(AgentShutdownResponse {
+                    success: false,
+                    message: "Failed to send shutdown signal".to_string(),
+                    error: Some(format!("{}", e)),
+                }))
+            }
+        }
+    } else {
+        Ok(Json(AgentShutdownResponse {
+            success: false,
+            message: "Agent client not found after connection".to_string(),
+            error: None,
+        }))
+    }
+}
+
+/// POST /api/v1/vms/:vm_id/agent/reboot - Graceful reboot via agent
+async fn agent_reboot(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+) -> Result<Json<AgentShutdownResponse>, (StatusCode, Json<ApiError>)> {
+    info!(vm_id = %vm_id, "Initiating graceful reboot via agent");
+    
+    // Verify VM exists and is running
+    let vms = state.service.hypervisor().list_vms().await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiError::new("list_vms_failed", &format!("Failed to list VMs: {}", e))),
+        )
+    })?;
+    
+    let vm = vms.iter()
+        .find(|v| v.id == vm_id || v.name == vm_id)
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiError::new("vm_not_found", &format!("VM not found: {}", vm_id))),
+            )
+        })?;
+    
+    if vm.state != limiquantix_hypervisor::types::VmState::Running {
+        return Ok(Json(AgentShutdownResponse {
+            success: false,
+            message: "VM is not running".to_string(),
+            error: Some(format!("VM is not running (state: {:?})", vm.state)),
+        }));
+    }
+    
+    // Discover socket path and connect to agent (robust socket discovery)
+    if let Err(e) = discover_and_connect_agent(&state, &vm.id, &vm.name).await {
+        return Ok(Json(AgentShutdownResponse {
+            success: false,
+            message: "Failed to connect to agent".to_string(),
+            error: Some(format!("{}", e)),
+        }));
+    }
+    
+    let agents = state.service.agent_manager().await;
+    if let Some(client) = agents.get(&vm.id) {
+        match client.shutdown(true).await {
+            Ok(result) => {
+                if result.accepted {
+                    Ok(Json(AgentShutdownResponse {
+                        success: true,
+                        message: "Graceful reboot signal sent to guest".to_string(),
+                        error: None,
+                    }))
+                } else {
+                    Ok(Json(AgentShutdownResponse {
+                        success: false,
+                        message: "Reboot was rejected by guest agent".to_string(),
+                        error: Some(result.error),
+                    }))
+                }
+            }
+            Err(e) => {
+                Ok(Json(AgentShutdownResponse {
+                    success: false,
+                    message: "Failed to send reboot signal".to_string(),
+                    error: Some(format!("{}", e)),
+                }))
+            }
+        }
+    } else {
+        Ok(Json(AgentShutdownResponse {
+            success: false,
+            message: "Agent client not found after connection".to_string(),
+            error: None,
+        }))
+    }
+}
+
+/// Query parameters for file listing
+#[derive(Deserialize)]
+struct ListFilesQuery {
+    /// Path to list (default: /)
+    #[serde(default = "default_path")]
+    path: String,
+    /// Include hidden files
+    #[serde(default)]
+    include_hidden: bool,
+}
+
+fn default_path() -> String {
+    "/".to_string()
+}
+
+/// Directory entry response
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DirectoryEntryResponse {
+    name: String,
+    path: String,
+    is_directory: bool,
+    is_symlink: bool,
+    size_bytes: u64,
+    mode: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    modified_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    owner: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    group: Option<String>,
+}
+
+/// Response for file listing
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ListFilesResponse {
+    success: bool,
+    path: String,
+    entries: Vec<DirectoryEntryResponse>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// GET /api/v1/vms/:vm_id/agent/files/list - List directory contents in the guest
+async fn list_guest_files(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+    Query(params): Query<ListFilesQuery>,
+) -> Result<Json<ListFilesResponse>, (StatusCode, Json<ApiError>)> {
+    info!(vm_id = %vm_id, path = %params.path, "Listing directory in guest");
+    
+    // Verify VM exists and is running
+    let vms = state.service.hypervisor().list_vms().await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiError::new("list_vms_failed", &format!("Failed to list VMs: {}", e))),
+        )
+    })?;
+    
+    let vm = vms.iter()
+        .find(|v| v.id == vm_id || v.name == vm_id)
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiError::new("vm_not_found", &format!("VM not found: {}", vm_id))),
+            )
+        })?;
+    
+    if vm.state != limiquantix_hypervisor::types::VmState::Running {
+        return Ok(Json(ListFilesResponse {
+            success: false,
+            path: params.path.clone(),
+            entries: vec![],
+            error: Some(format!("VM is not running (state: {:?})", vm.state)),
+        }));
+    }
+    
+    // Discover socket path and connect to agent (robust socket discovery)
+    if let Err(e) = discover_and_connect_agent(&state, &vm.id, &vm.name).await {
+        return Ok(Json(ListFilesResponse {
+            success: false,
+            path: params.path,
+            entries: vec![],
+            error: Some(format!("Failed to connect to agent: {}", e)),
+        }));
+    }
+    
+    let agents = state.service.agent_manager().await;
+    if let Some(client) = agents.get(&vm.id) {
+        match client.list_directory(&params.path, params.include_hidden).await {
+            Ok(result) => {
+                let entries: Vec<DirectoryEntryResponse> = result.entries
+                    .into_iter()
+                    .map(|e| DirectoryEntryResponse {
+                        name: e.name,
+                        path: e.path,
+                        is_directory: e.is_directory,
+                        is_symlink: e.is_symlink,
+                        size_bytes: e.size_bytes,
+                        mode: e.mode,
+                        modified_at: e.modified_at.map(|t| {
+                            chrono::DateTime::from_timestamp(t.seconds, t.nanos as u32)
+                                .map(|dt| dt.to_rfc3339())
+                                .unwrap_or_default()
+                        }),
+                        owner: if e.owner.is_empty() { None } else { Some(e.owner) },
+                        group: if e.group.is_empty() { None } else { Some(e.group) },
+                    })
+                    .collect();
+                
+                Ok(Json(ListFilesResponse {
+                    success: true,
+                    path: params.path,
+                    entries,
+                    error: None,
+                }))
+            }
+            Err(e) => {
+                Ok(Json(ListFilesResponse {
+                    success: false,
+                    path: params.path,
+                    entries: vec![],
+                    error: Some(format!("Failed to list directory: {}", e)),
+                }))
+            }
+        }
+    } else {
+        Ok(Json(ListFilesResponse {
+            success: false,
+            path: params.path,
+            entries: vec![],
+            error: Some("Agent client not found after connection".to_string()),
+        }))
+    }
+}
+
+/// Query parameters for file reading
+#[derive(Deserialize)]
+struct ReadFileQuery {
+    /// Path to the file to read
+    path: String,
+}
+
+/// Response for file reading
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReadFileResponse {
+    success: bool,
+    path: String,
+    /// Base64-encoded file content
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+    /// File size in bytes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// GET /api/v1/vms/:vm_id/agent/files/read - Read a file from the guest
+async fn read_guest_file(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+    Query(params): Query<ReadFileQuery>,
+) -> Result<Json<ReadFileResponse>, (StatusCode, Json<ApiError>)> {
+    info!(vm_id = %vm_id, path = %params.path, "Reading file from guest");
+    
+    // Verify VM exists and is running
+    let vms = state.service.hypervisor().list_vms().await.map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ApiError::new("list_vms_failed", &format!("Failed to list VMs: {}", e))),
+        )
+    })?;
+    
+    let vm = vms.iter()
+        .find(|v| v.id == vm_id || v.name == vm_id)
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                Json(ApiError::new("vm_not_found", &format!("VM not found: {}", vm_id))),
+            )
+        })?;
+    
+    if vm.state != limiquantix_hypervisor::types::VmState::Running {
+        return Ok(Json(ReadFileResponse {
+            success: false,
+            path: params.path.clone(),
+            content: None,
+            size_bytes: None,
+            error: Some(format!("VM is not running (state: {:?})", vm.state)),
+        }));
+    }
+    
+    // Discover socket path and connect to agent (robust socket discovery)
+    if let Err(e) = discover_and_connect_agent(&state, &vm.id, &vm.name).await {
+        return Ok(Json(ReadFileResponse {
+            success: false,
+            path: params.path,
+            content: None,
+            size_bytes: None,
+            error: Some(format!("Failed to connect to agent: {}", e)),
+        }));
+    }
+    
+    let agents = state.service.agent_manager().await;
+    if let Some(client) = agents.get(&vm.id) {
+        match client.read_file(&params.path).await {
+            Ok(data) => {
+                use base64::Engine;
+                let content = base64::engine::general_purpose::STANDARD.encode(&data);
+                
+                Ok(Json(ReadFileResponse {
+                    success: true,
+                    path: params.path,
+                    content: Some(content),
+                    size_bytes: Some(data.len() as u64),
+                    error: None,
+                }))
+            }
+            Err(e) => {
+                Ok(Json(ReadFileResponse {
+                    success: false,
+                    path: params.path,
+                    content: None,
+                    size_bytes: None,
+                    error: Some(format!("Failed to read file: {}", e)),
+                }))
+            }
+        }
+    } else {
+        Ok(Json(ReadFileResponse {
+            success: false,
+            path: params.path,
+            content: None,
+            size_bytes: None,
+            error: Some("Agent client not found after connection".to_string()),
+        }))
+    }
+}
+
+/// Request body for command execution
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExecuteCommandRequest {
+    /// Command to execute
+    command: String,
+    /// Timeout in seconds (default: 30, max: 30 for sync execution)
+    #[serde(default = "default_execute_timeout")]
+    timeout_seconds: u32,
+    /// Wait for command to complete (always true for this endpoint)
+    #[serde(default = "default_true")]
+    wait_for_exit: bool,
+}
+
+fn default_execute_timeout() -> u32 {
+    30
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Response for command execution
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExecuteCommandResponse {
+    success: bool,
+    exit_code: i32,
+    stdout: String,
+    stderr: String,
+    timed_out: bool,
+    duration_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// POST /api/v1/vms/:vm_id/execute - Execute a command in the guest VM
+/// 
+/// WARNING: This endpoint has a maximum timeout of 30 seconds for synchronous execution.
+/// Long-running scripts may timeout but continue running in the guest. For long operations,
+/// check the VM console or use asynchronous execution (future feature).
+async fn execute_in_guest(
     State(state): State<Arc<AppState>>,
     Path(vm_id): Path<String>,
     Json(request): Json<ExecuteCommandRequest>,
@@ -6575,6 +9668,165 @@ async fn revert_snapshot(
     }
 }
 
+// ============================================================================
+// Template & Clone Endpoints
+// ============================================================================
+
+fn template_to_response(t: limiquantix_proto::TemplateInfoResponse) -> TemplateResponse {
+    TemplateResponse {
+        template_id: t.template_id,
+        name: t.name,
+        description: t.description,
+        source_vm_id: t.source_vm_id,
+        created_at: t.created_at.map(|ts| {
+            chrono::DateTime::from_timestamp(ts.seconds, ts.nanos as u32)
+                .map(|dt| dt.to_rfc3339())
+                .unwrap_or_default()
+        }).unwrap_or_default(),
+        vcpus: t.vcpus,
+        memory_mib: t.memory_mib,
+        disk_count: t.disk_count,
+        linked_clone_vm_ids: t.linked_clone_vm_ids,
+        generalized: t.generalized,
+    }
+}
+
+/// Map template/clone gRPC errors to HTTP status codes
+fn template_error(code: &str, e: tonic::Status) -> (StatusCode, Json<ApiError>) {
+    let status = match e.code() {
+        tonic::Code::NotFound => StatusCode::NOT_FOUND,
+        tonic::Code::InvalidArgument => StatusCode::BAD_REQUEST,
+        // VM not stopped, or template still has linked clones
+        tonic::Code::FailedPrecondition => StatusCode::CONFLICT,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, Json(ApiError::new(code, e.message())))
+}
+
+/// GET /api/v1/templates - List VM templates
+async fn list_templates(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<TemplateListResponse>, (StatusCode, Json<ApiError>)> {
+    use tonic::Request;
+    use limiquantix_proto::NodeDaemonService;
+    
+    let response = state.service.list_templates(Request::new(())).await
+        .map_err(|e| template_error("list_templates_failed", e))?;
+    
+    let templates = response.into_inner().templates.into_iter()
+        .map(template_to_response)
+        .collect();
+    
+    Ok(Json(TemplateListResponse { templates }))
+}
+
+/// POST /api/v1/vms/:vm_id/template - Create a template from a stopped VM
+async fn convert_vm_to_template(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+    body: Option<Json<ConvertToTemplateBody>>,
+) -> Result<Json<TemplateResponse>, (StatusCode, Json<ApiError>)> {
+    use tonic::Request;
+    use limiquantix_proto::{NodeDaemonService, ConvertToTemplateRequest};
+    
+    let body = body.map(|Json(b)| b).unwrap_or_default();
+    
+    info!(vm_id = %vm_id, "Creating template from VM via HTTP API");
+    
+    let response = state.service.convert_to_template(Request::new(ConvertToTemplateRequest {
+        vm_id: vm_id.clone(),
+        name: body.name.unwrap_or_default(),
+        description: body.description.unwrap_or_default(),
+    })).await.map_err(|e| {
+        error!(error = %e, vm_id = %vm_id, "Failed to create template");
+        template_error("convert_to_template_failed", e)
+    })?;
+    
+    Ok(Json(template_to_response(response.into_inner())))
+}
+
+/// DELETE /api/v1/templates/:template_id - Delete a template
+async fn delete_template(
+    State(state): State<Arc<AppState>>,
+    Path(template_id): Path<String>,
+) -> Result<StatusCode, (StatusCode, Json<ApiError>)> {
+    use tonic::Request;
+    use limiquantix_proto::{NodeDaemonService, TemplateIdRequest};
+    
+    state.service.delete_template(Request::new(TemplateIdRequest { template_id: template_id.clone() })).await
+        .map_err(|e| {
+            warn!(error = %e, template_id = %template_id, "Failed to delete template");
+            template_error("delete_template_failed", e)
+        })?;
+    
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /api/v1/templates/:template_id/clone - Create a VM from a template
+async fn clone_template(
+    State(state): State<Arc<AppState>>,
+    Path(template_id): Path<String>,
+    Json(body): Json<CloneVmBody>,
+) -> Result<Json<CloneVmResponse>, (StatusCode, Json<ApiError>)> {
+    do_clone_vm(&state, String::new(), template_id, body).await
+}
+
+/// POST /api/v1/vms/:vm_id/clone - Full clone of a stopped VM
+async fn clone_vm(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+    Json(body): Json<CloneVmBody>,
+) -> Result<Json<CloneVmResponse>, (StatusCode, Json<ApiError>)> {
+    do_clone_vm(&state, vm_id, String::new(), body).await
+}
+
+async fn do_clone_vm(
+    state: &AppState,
+    source_vm_id: String,
+    template_id: String,
+    body: CloneVmBody,
+) -> Result<Json<CloneVmResponse>, (StatusCode, Json<ApiError>)> {
+    use tonic::Request;
+    use limiquantix_proto::{NodeDaemonService, CloneVmRequest, CloneType};
+    
+    let clone_type = match body.clone_type.as_deref() {
+        None | Some("full") => CloneType::Full,
+        Some("linked") => CloneType::Linked,
+        Some(other) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ApiError::new("invalid_clone_type", &format!("Unknown clone type '{}'", other))),
+            ));
+        }
+    };
+    
+    info!(
+        source_vm_id = %source_vm_id,
+        template_id = %template_id,
+        name = %body.name,
+        clone_type = ?clone_type,
+        "Cloning VM via HTTP API"
+    );
+    
+    let response = state.service.clone_vm(Request::new(CloneVmRequest {
+        source_vm_id,
+        template_id,
+        name: body.name,
+        vm_id: body.vm_id.unwrap_or_default(),
+        clone_type: clone_type as i32,
+        start_on_create: body.start_on_create.unwrap_or(false),
+    })).await.map_err(|e| {
+        error!(error = %e, "Failed to clone VM");
+        template_error("clone_vm_failed", e)
+    })?;
+    
+    let created = response.into_inner();
+    Ok(Json(CloneVmResponse {
+        vm_id: created.vm_id,
+        message: created.message,
+    }))
+}
+
 // ============================================================================
 // Storage API Handlers
 // ============================================================================
@@ -6785,6 +10037,7 @@ async fn list_volumes(
                     format: vol.format,
                     path: vol.path,
                     attached_to: if vol.attached_to.is_empty() { None } else { Some(vol.attached_to) },
+                    allocation_bytes: vol.allocation_bytes,
                 }
             }).collect();
             
@@ -6827,6 +10080,7 @@ async fn create_volume(
                 format: vol.format,
                 path: vol.path,
                 attached_to: if vol.attached_to.is_empty() { None } else { Some(vol.attached_to) },
+                allocation_bytes: vol.allocation_bytes,
             }))
         }
         Err(e) => {
@@ -6864,6 +10118,77 @@ async fn delete_volume(
     }
 }
 
+/// Trim schedule of a storage pool
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PoolTrimScheduleResponse {
+    pool_id: String,
+    enabled: bool,
+    /// Hours between runs (0 = weekly)
+    interval_hours: u32,
+    last_run_at: Option<String>,
+    last_reclaimed_bytes: u64,
+}
+
+impl From<limiquantix_proto::PoolTrimSchedule> for PoolTrimScheduleResponse {
+    fn from(s: limiquantix_proto::PoolTrimSchedule) -> Self {
+        Self {
+            pool_id: s.pool_id,
+            enabled: s.enabled,
+            interval_hours: s.interval_hours,
+            last_run_at: non_empty(s.last_run_at),
+            last_reclaimed_bytes: s.last_reclaimed_bytes,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SetPoolTrimScheduleRequest {
+    enabled: bool,
+    #[serde(default)]
+    interval_hours: u32,
+}
+
+/// GET /api/v1/storage/pools/:pool_id/trim-schedule - Get the periodic guest trim schedule of a pool
+async fn get_pool_trim_schedule(
+    State(state): State<Arc<AppState>>,
+    Path(pool_id): Path<String>,
+) -> Result<Json<PoolTrimScheduleResponse>, (StatusCode, Json<ApiError>)> {
+    use tonic::Request;
+    use limiquantix_proto::{NodeDaemonService, StoragePoolIdRequest};
+    
+    let schedule = state.service.get_pool_trim_schedule(Request::new(StoragePoolIdRequest { pool_id })).await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiError::new("get_trim_schedule_failed", e.message()))))?;
+    Ok(Json(schedule.into_inner().into()))
+}
+
+/// PUT /api/v1/storage/pools/:pool_id/trim-schedule - Enable, disable or change the periodic guest trim of a pool
+async fn set_pool_trim_schedule(
+    State(state): State<Arc<AppState>>,
+    Path(pool_id): Path<String>,
+    Json(request): Json<SetPoolTrimScheduleRequest>,
+) -> Result<Json<PoolTrimScheduleResponse>, (StatusCode, Json<ApiError>)> {
+    use tonic::Request;
+    use limiquantix_proto::{NodeDaemonService, PoolTrimSchedule};
+    
+    let schedule = state.service.set_pool_trim_schedule(Request::new(PoolTrimSchedule {
+        pool_id,
+        enabled: request.enabled,
+        interval_hours: request.interval_hours,
+        ..Default::default()
+    })).await
+        .map_err(|e| {
+            let status = if e.code() == tonic::Code::NotFound {
+                StatusCode::NOT_FOUND
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            };
+            (status, Json(ApiError::new("set_trim_schedule_failed", e.message())))
+        })?;
+    Ok(Json(schedule.into_inner().into()))
+}
+
 /// GET /api/v1/storage/images - List ISO images
 async fn list_images(
     State(state): State<Arc<AppState>>,
@@ -7908,6 +11233,247 @@ async fn get_conversion_status(
     }
 }
 
+// ============================================================================
+// OVA Import / Export
+// ============================================================================
+
+/// Request body for OVA import
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImportOvaRequest {
+    /// Path to the .ova (or unpacked .ovf) on this host
+    source_path: String,
+    /// Storage pool for the converted disks (default path if omitted)
+    pool_id: Option<String>,
+    /// VM name (defaults to the name in the OVF)
+    name: Option<String>,
+    /// Bridge to connect all NICs to
+    bridge: Option<String>,
+    /// Keep the source MAC addresses
+    keep_mac_addresses: Option<bool>,
+    /// Start the VM once imported
+    start_on_create: Option<bool>,
+}
+
+/// Request body for OVA export
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportOvaRequest {
+    /// Destination .ova file, or an existing directory
+    dest_path: String,
+}
+
+/// Response for a started OVA job
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OvaJobResponse {
+    job_id: String,
+    message: String,
+}
+
+/// Status of an OVA import/export job
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct OvaJob {
+    job_id: String,
+    /// "import" or "export"
+    kind: String,
+    /// "running", "completed", "failed"
+    status: String,
+    /// Imported VM ID, or exported VM ID
+    vm_id: Option<String>,
+    /// Source OVA (import) or destination OVA (export)
+    path: String,
+    error_message: Option<String>,
+    started_at: String,
+    completed_at: Option<String>,
+}
+
+/// How long finished OVA jobs stay queryable
+const OVA_JOB_RETENTION: chrono::Duration = chrono::Duration::hours(24);
+
+/// Thread-safe storage for OVA jobs
+static OVA_JOBS: std::sync::OnceLock<std::sync::Mutex<std::collections::HashMap<String, OvaJob>>> = std::sync::OnceLock::new();
+
+fn get_ova_jobs() -> &'static std::sync::Mutex<std::collections::HashMap<String, OvaJob>> {
+    OVA_JOBS.get_or_init(|| std::sync::Mutex::new(std::collections::HashMap::new()))
+}
+
+fn start_ova_job(kind: &str, path: &str, vm_id: Option<String>) -> String {
+    let job_id = uuid::Uuid::new_v4().to_string();
+    let job = OvaJob {
+        job_id: job_id.clone(),
+        kind: kind.to_string(),
+        status: "running".to_string(),
+        vm_id,
+        path: path.to_string(),
+        error_message: None,
+        started_at: chrono::Utc::now().to_rfc3339(),
+        completed_at: None,
+    };
+    
+    let mut jobs = get_ova_jobs().lock().unwrap();
+    // Drop finished jobs nobody has polled for a while
+    let cutoff = chrono::Utc::now() - OVA_JOB_RETENTION;
+    jobs.retain(|_, j| {
+        j.completed_at.as_deref()
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+            .map(|t| t > cutoff)
+            .unwrap_or(true)
+    });
+    jobs.insert(job_id.clone(), job);
+    job_id
+}
+
+fn finish_ova_job(job_id: &str, result: Result<Option<String>, String>) {
+    let mut jobs = get_ova_jobs().lock().unwrap();
+    if let Some(job) = jobs.get_mut(job_id) {
+        match result {
+            Ok(vm_id) => {
+                job.status = "completed".to_string();
+                if vm_id.is_some() {
+                    job.vm_id = vm_id;
+                }
+            }
+            Err(e) => {
+                job.status = "failed".to_string();
+                job.error_message = Some(e);
+            }
+        }
+        job.completed_at = Some(chrono::Utc::now().to_rfc3339());
+    }
+}
+
+/// POST /api/v1/ova/import - Import a VM from an OVA
+async fn import_ova(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<ImportOvaRequest>,
+) -> Result<Json<OvaJobResponse>, (StatusCode, Json<ApiError>)> {
+    use crate::event_store::{emit_event, Event, EventLevel};
+    use limiquantix_hypervisor::OvaImportOptions;
+    
+    if !std::path::Path::new(&request.source_path).exists() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::new("source_not_found", "Source file does not exist")),
+        ));
+    }
+    
+    let vms_root = state.service.pool_vms_root(request.pool_id.as_deref().unwrap_or("")).await
+        .map_err(|e| template_error("import_failed", e))?;
+    
+    let options = OvaImportOptions {
+        name: request.name.filter(|n| !n.is_empty()),
+        vm_id: None,
+        bridge: request.bridge.filter(|b| !b.is_empty()),
+        keep_mac_addresses: request.keep_mac_addresses.unwrap_or(false),
+    };
+    let start = request.start_on_create.unwrap_or(false);
+    
+    let job_id = start_ova_job("import", &request.source_path, None);
+    info!(job_id = %job_id, source = %request.source_path, vms_root = %vms_root.display(), "Starting OVA import");
+    
+    let service = state.service.clone();
+    let source = PathBuf::from(&request.source_path);
+    let job = job_id.clone();
+    tokio::spawn(async move {
+        let imported = tokio::task::spawn_blocking(move || {
+            limiquantix_hypervisor::ovf::import_ova(&source, &vms_root, &options)
+        }).await;
+        
+        let result = match imported {
+            Ok(Ok(config)) => {
+                let name = config.name.clone();
+                match service.create_imported_vm(config, start).await {
+                    Ok(vm_id) => {
+                        emit_event(Event::vm_event(
+                            EventLevel::Info,
+                            &vm_id,
+                            format!("VM '{}' imported from OVA", name),
+                        ));
+                        Ok(Some(vm_id))
+                    }
+                    Err(e) => Err(e.message().to_string()),
+                }
+            }
+            Ok(Err(e)) => Err(e.to_string()),
+            Err(e) => Err(format!("Import task failed: {}", e)),
+        };
+        
+        if let Err(ref e) = result {
+            error!(job_id = %job, error = %e, "OVA import failed");
+        }
+        finish_ova_job(&job, result);
+    });
+    
+    Ok(Json(OvaJobResponse {
+        job_id,
+        message: "Import started".to_string(),
+    }))
+}
+
+/// POST /api/v1/vms/:vm_id/export - Export a stopped VM as an OVA
+async fn export_vm_ova(
+    State(state): State<Arc<AppState>>,
+    Path(vm_id): Path<String>,
+    Json(request): Json<ExportOvaRequest>,
+) -> Result<Json<OvaJobResponse>, (StatusCode, Json<ApiError>)> {
+    let config = state.service.stopped_vm_config(&vm_id).await
+        .map_err(|e| template_error("export_failed", e))?;
+    
+    let mut dest = PathBuf::from(&request.dest_path);
+    if dest.is_dir() {
+        dest = dest.join(format!("{}.ova", config.name));
+    }
+    if dest.exists() {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ApiError::new("dest_exists", &format!("{} already exists", dest.display()))),
+        ));
+    }
+    
+    let dest_str = dest.to_string_lossy().into_owned();
+    let job_id = start_ova_job("export", &dest_str, Some(vm_id.clone()));
+    info!(job_id = %job_id, vm_id = %vm_id, dest = %dest_str, "Starting OVA export");
+    
+    let job = job_id.clone();
+    tokio::spawn(async move {
+        let exported = tokio::task::spawn_blocking(move || {
+            limiquantix_hypervisor::ovf::export_ova(&config, &dest)
+        }).await;
+        
+        let result = match exported {
+            Ok(Ok(())) => Ok(None),
+            Ok(Err(e)) => Err(e.to_string()),
+            Err(e) => Err(format!("Export task failed: {}", e)),
+        };
+        
+        if let Err(ref e) = result {
+            error!(job_id = %job, error = %e, "OVA export failed");
+        }
+        finish_ova_job(&job, result);
+    });
+    
+    Ok(Json(OvaJobResponse {
+        job_id,
+        message: format!("Export to {} started", dest_str),
+    }))
+}
+
+/// GET /api/v1/ova/jobs/:job_id - Get OVA import/export job status
+async fn get_ova_job(
+    Path(job_id): Path<String>,
+) -> Result<Json<OvaJob>, (StatusCode, Json<ApiError>)> {
+    get_ova_jobs().lock().unwrap()
+        .get(&job_id)
+        .cloned()
+        .map(Json)
+        .ok_or_else(|| (
+            StatusCode::NOT_FOUND,
+            Json(ApiError::new("job_not_found", "OVA job not found")),
+        ))
+}
+
 // ============================================================================
 // ISO Management Handlers
 // ============================================================================
@@ -8670,97 +12236,919 @@ async fn get_network_interface(
     }
 }
 
+fn network_error(e: crate::host_network::HostNetworkError) -> (StatusCode, Json<ApiError>) {
+    use crate::host_network::HostNetworkError;
+    
+    let (status, code) = match e {
+        HostNetworkError::Pending(_) => (StatusCode::CONFLICT, "network_change_pending"),
+        HostNetworkError::NotPending(_) => (StatusCode::NOT_FOUND, "transaction_not_found"),
+        HostNetworkError::Failed(_) => (StatusCode::INTERNAL_SERVER_ERROR, "network_change_failed"),
+    };
+    (status, Json(ApiError::new(code, &e.to_string())))
+}
+
+fn invalid_network_config(e: anyhow::Error) -> (StatusCode, Json<ApiError>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(ApiError::new("invalid_network_config", &format!("{:#}", e))),
+    )
+}
+
+fn confirm_timeout(secs: Option<u64>) -> Option<std::time::Duration> {
+    Some(secs.map(std::time::Duration::from_secs).unwrap_or(crate::host_network::DEFAULT_CONFIRM_TIMEOUT))
+}
+
+/// Apply a desired host network config and describe the result.
+async fn apply_network_config(
+    desired: limiquantix_hypervisor::HostNetworkConfig,
+    timeout: Option<std::time::Duration>,
+) -> Result<Json<NetworkApplyResponse>, (StatusCode, Json<ApiError>)> {
+    desired.validate().map_err(invalid_network_config)?;
+    
+    // The address the UI and control plane reach this node on must survive the change
+    let management = crate::registration::detect_management_ip().and_then(|ip| ip.parse().ok());
+    let applied = crate::host_network::host_network().apply(desired, timeout, management).await
+        .map_err(network_error)?;
+    
+    Ok(Json(NetworkApplyResponse {
+        transaction_id: applied.transaction_id,
+        changes: applied.changes.iter().map(|c| c.to_string()).collect(),
+        confirm_deadline: applied.confirm_deadline.map(|d| d.to_rfc3339()),
+    }))
+}
+
+/// GET /api/v1/network/config - Current host network configuration
+async fn get_host_network_config() -> Result<Json<HostNetworkConfigResponse>, (StatusCode, Json<ApiError>)> {
+    let manager = crate::host_network::host_network();
+    let config = manager.current().await.map_err(network_error)?;
+    let pending = manager.pending().await;
+    
+    Ok(Json(HostNetworkConfigResponse {
+        config,
+        pending_transaction_id: pending.as_ref().map(|(id, _)| id.clone()),
+        confirm_deadline: pending.map(|(_, deadline)| deadline.to_rfc3339()),
+    }))
+}
+
+/// POST /api/v1/network/config/plan - Show the changes a config would make
+async fn plan_host_network_config(
+    Json(desired): Json<limiquantix_hypervisor::HostNetworkConfig>,
+) -> Result<Json<NetworkPlanResponse>, (StatusCode, Json<ApiError>)> {
+    use crate::host_network::HostNetworkError;
+    
+    let changes = crate::host_network::host_network().plan(&desired).await
+        .map_err(|e| match e {
+            HostNetworkError::Failed(e) => invalid_network_config(e),
+            e => network_error(e),
+        })?;
+    
+    Ok(Json(NetworkPlanResponse {
+        summary: changes.iter().map(|c| c.to_string()).collect(),
+        changes,
+    }))
+}
+
+/// POST /api/v1/network/config/apply - Apply a host network config
+async fn apply_host_network_config(
+    Json(request): Json<ApplyNetworkConfigRequest>,
+) -> Result<Json<NetworkApplyResponse>, (StatusCode, Json<ApiError>)> {
+    info!("Applying host network configuration");
+    apply_network_config(request.config, confirm_timeout(request.confirm_timeout_secs)).await
+}
+
+/// POST /api/v1/network/config/confirm - Keep a pending network change
+async fn confirm_host_network_config(
+    Json(request): Json<NetworkTransactionRequest>,
+) -> Result<StatusCode, (StatusCode, Json<ApiError>)> {
+    crate::host_network::host_network().confirm(&request.transaction_id).await
+        .map_err(network_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /api/v1/network/config/rollback - Revert a pending network change now
+async fn rollback_host_network_config(
+    Json(request): Json<NetworkTransactionRequest>,
+) -> Result<StatusCode, (StatusCode, Json<ApiError>)> {
+    crate::host_network::host_network().rollback(&request.transaction_id).await
+        .map_err(network_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn host_firewall_error(e: crate::host_firewall::HostFirewallError) -> (StatusCode, Json<ApiError>) {
+    use crate::host_firewall::HostFirewallError;
+    
+    let (status, code) = match e {
+        HostFirewallError::Pending(_) => (StatusCode::CONFLICT, "firewall_change_pending"),
+        HostFirewallError::NotPending(_) => (StatusCode::NOT_FOUND, "transaction_not_found"),
+        HostFirewallError::Invalid(_) => (StatusCode::BAD_REQUEST, "invalid_firewall_config"),
+        HostFirewallError::Failed(_) => (StatusCode::INTERNAL_SERVER_ERROR, "firewall_change_failed"),
+    };
+    (status, Json(ApiError::new(code, &e.to_string())))
+}
+
+/// Current host firewall configuration and pending change.
+async fn host_firewall_response() -> HostFirewallResponse {
+    let manager = crate::host_firewall::host_firewall();
+    let pending = manager.pending().await;
+    
+    HostFirewallResponse {
+        config: manager.current().await,
+        pending_transaction_id: pending.as_ref().map(|(id, _)| id.clone()),
+        confirm_deadline: pending.map(|(_, deadline)| deadline.to_rfc3339()),
+    }
+}
+
+/// GET /api/v1/settings/firewall - Management service firewall
+async fn get_host_firewall() -> Json<HostFirewallResponse> {
+    Json(host_firewall_response().await)
+}
+
+/// Refuse firewall changes that would block the requesting client from
+/// this server.
+fn check_firewall_client(
+    config: &limiquantix_hypervisor::network::HostFirewallConfig,
+    client: SocketAddr,
+    port: u16,
+    status: StatusCode,
+) -> Result<(), (StatusCode, Json<ApiError>)> {
+    let ip = client.ip().to_canonical();
+    if config.allows(ip, port) {
+        return Ok(());
+    }
+    Err((status, Json(ApiError::new(
+        "client_blocked",
+        &format!("The host firewall rules block {} from port {}", ip, port),
+    ))))
+}
+
+/// PUT /api/v1/settings/firewall - Replace the management service firewall
+///
+/// The change is rolled back unless confirmed within `confirmTimeoutSecs`
+/// (default 60, 0 = apply without confirmation). Rules that would block the
+/// requesting client from this server are refused.
+async fn update_host_firewall(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Json(request): Json<UpdateHostFirewallRequest>,
+) -> Result<Json<HostFirewallResponse>, (StatusCode, Json<ApiError>)> {
+    check_firewall_client(&request.config, client, state.listen_port, StatusCode::BAD_REQUEST)?;
+    
+    let timeout = request.confirm_timeout_secs
+        .map(std::time::Duration::from_secs)
+        .unwrap_or(crate::host_firewall::DEFAULT_CONFIRM_TIMEOUT);
+    
+    info!(enabled = request.config.enabled, "Applying host firewall configuration");
+    crate::host_firewall::host_firewall().apply(request.config, Some(timeout)).await
+        .map_err(host_firewall_error)?;
+    Ok(Json(host_firewall_response().await))
+}
+
+/// POST /api/v1/settings/firewall/confirm - Keep a pending firewall change
+///
+/// Established connections survive the new rules, so the confirming client
+/// is checked against them explicitly.
+async fn confirm_host_firewall(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(client): ConnectInfo<SocketAddr>,
+    Json(request): Json<NetworkTransactionRequest>,
+) -> Result<StatusCode, (StatusCode, Json<ApiError>)> {
+    let manager = crate::host_firewall::host_firewall();
+    check_firewall_client(&manager.current().await, client, state.listen_port, StatusCode::FORBIDDEN)?;
+    
+    manager.confirm(&request.transaction_id).await
+        .map_err(host_firewall_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /api/v1/settings/firewall/rollback - Revert a pending firewall change now
+async fn rollback_host_firewall(
+    Json(request): Json<NetworkTransactionRequest>,
+) -> Result<StatusCode, (StatusCode, Json<ApiError>)> {
+    crate::host_firewall::host_firewall().rollback(&request.transaction_id).await
+        .map_err(host_firewall_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
 /// Configure a network interface
 async fn configure_network_interface(
     State(_state): State<Arc<AppState>>,
     Path(name): Path<String>,
     Json(config): Json<ConfigureInterfaceRequest>,
-) -> Result<Json<NetworkInterface>, (StatusCode, Json<ApiError>)> {
-    use std::process::Command;
+) -> Result<Json<NetworkApplyResponse>, (StatusCode, Json<ApiError>)> {
+    use limiquantix_hypervisor::network::host::{netmask_to_prefix, LinkConfig, RouteConfig};
+    
+    info!(interface = %name, dhcp = config.dhcp, ipv6_autoconf = ?config.ipv6_autoconf, "Configuring network interface");
     
-    info!(interface = %name, dhcp = config.dhcp, "Configuring network interface");
+    let mut desired = crate::host_network::host_network().current().await
+        .map_err(network_error)?;
     
-    // Clone the IP address before the match to use later
-    let configured_ip = config.ip_address.clone();
+    let mut link = desired.links.iter()
+        .find(|l| l.name == name)
+        .cloned()
+        .unwrap_or_else(|| LinkConfig::new(&name));
+    link.up = true;
+    
+    if let Some(mode) = config.ipv6_autoconf {
+        link.ipv6_autoconf = Some(mode);
+    }
     
     if config.dhcp {
-        // Configure for DHCP
-        let _ = Command::new("ip")
-            .args(&["link", "set", &name, "up"])
-            .output();
-        
-        // Start DHCP client (simplified - in production use proper DHCP client)
-        info!(interface = %name, "Configured for DHCP");
-    } else if let (Some(ip), Some(netmask)) = (config.ip_address, config.netmask) {
-        // Configure static IP
-        let _ = Command::new("ip")
-            .args(&["addr", "add", &format!("{}/{}", ip, netmask), "dev", &name])
-            .output();
-        
-        let _ = Command::new("ip")
-            .args(&["link", "set", &name, "up"])
-            .output();
+        link.dhcp = true;
+        link.addresses.retain(|a| a.contains(':'));
+    } else if let Some(ip) = config.ip_address {
+        // The UI may send "10.0.0.5/24" with a separate netmask; the netmask wins
+        let (addr, cidr_prefix) = match ip.split_once('/') {
+            Some((addr, prefix)) => (addr.to_string(), Some(prefix.to_string())),
+            None => (ip, None),
+        };
+        let parsed: std::net::IpAddr = addr.parse()
+            .map_err(|_| invalid_network_config(anyhow::anyhow!("Invalid IP address {}", addr)))?;
+        let ipv6 = parsed.is_ipv6();
+        let prefix = match config.netmask.or(cidr_prefix) {
+            Some(mask) if ipv6 => mask.parse::<u8>().ok().filter(|p| *p <= 128)
+                .ok_or_else(|| invalid_network_config(anyhow::anyhow!("Invalid IPv6 prefix length {}", mask)))?,
+            Some(mask) => netmask_to_prefix(&mask).map_err(invalid_network_config)?,
+            None => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(ApiError::new("invalid_network_config", "A netmask is required for a static address")),
+                ));
+            }
+        };
         
-        if let Some(gateway) = config.gateway {
-            let _ = Command::new("ip")
-                .args(&["route", "add", "default", "via", &gateway])
-                .output();
+        // Replace the static addresses of the same family, keep the other
+        if !ipv6 {
+            link.dhcp = false;
         }
+        link.addresses.retain(|a| a.contains(':') != ipv6);
+        link.addresses.push(format!("{}/{}", parsed, prefix));
         
-        info!(interface = %name, ip = %ip, "Configured with static IP");
+        if let Some(gateway) = config.gateway.filter(|g| !g.is_empty()) {
+            desired.routes.retain(|r| {
+                r.destination != "default" || r.gateway.as_deref().map(|g| g.contains(':')) != Some(ipv6)
+            });
+            desired.routes.push(RouteConfig {
+                destination: "default".to_string(),
+                gateway: Some(gateway),
+                dev: Some(name.clone()),
+                metric: None,
+            });
+        }
     }
     
-    // Return updated interface info
-    Ok(Json(NetworkInterface {
-        name: name.clone(),
-        mac_address: "00:00:00:00:00:00".to_string(),
-        interface_type: "ethernet".to_string(),
-        state: "up".to_string(),
-        ip_addresses: configured_ip.map(|ip| vec![ip]).unwrap_or_default(),
-        mtu: 1500,
-        speed_mbps: Some(1000),
-    }))
+    desired.links.retain(|l| l.name != name);
+    desired.links.push(link);
+    
+    apply_network_config(desired, confirm_timeout(config.confirm_timeout_secs)).await
 }
 
 /// Create a network bridge
+///
+/// Addresses, DHCP and routes of the enslaved interfaces move to the bridge,
+/// so bridging the management NIC keeps the host reachable. Ports can be
+/// NICs, bonds or VLANs.
 async fn create_bridge(
     State(_state): State<Arc<AppState>>,
     Json(request): Json<CreateBridgeRequest>,
-) -> Result<Json<NetworkInterface>, (StatusCode, Json<ApiError>)> {
-    use std::process::Command;
+) -> Result<Json<NetworkApplyResponse>, (StatusCode, Json<ApiError>)> {
+    use limiquantix_hypervisor::network::host::BridgeConfig;
     
     info!(bridge = %request.name, interfaces = ?request.interfaces, "Creating network bridge");
     
-    // Create bridge
-    let _ = Command::new("ip")
-        .args(&["link", "add", "name", &request.name, "type", "bridge"])
-        .output();
+    let mut desired = crate::host_network::host_network().current().await
+        .map_err(network_error)?;
     
-    // Add interfaces to bridge
-    for iface in &request.interfaces {
-        let _ = Command::new("ip")
-            .args(&["link", "set", iface, "master", &request.name])
-            .output();
+    if desired.bridges.iter().any(|b| b.name == request.name) {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ApiError::new("bridge_exists", &format!("Bridge {} already exists", request.name))),
+        ));
     }
     
-    // Bring bridge up
-    let _ = Command::new("ip")
-        .args(&["link", "set", &request.name, "up"])
-        .output();
+    desired.bridges.push(BridgeConfig {
+        name: request.name.clone(),
+        ports: request.interfaces.clone(),
+        stp: false,
+    });
+    desired.move_addressing(&request.interfaces, &request.name);
+    
+    apply_network_config(desired, confirm_timeout(request.confirm_timeout_secs)).await
+}
+
+/// GET /api/v1/network/bridges - List Linux bridges
+async fn list_bridges() -> Result<Json<BridgeList>, (StatusCode, Json<ApiError>)> {
+    let current = crate::host_network::host_network().current().await
+        .map_err(network_error)?;
+    Ok(Json(BridgeList { bridges: current.bridges }))
+}
+
+/// DELETE /api/v1/network/bridges/:name - Delete a bridge, returning its addresses to the first port
+async fn delete_bridge(
+    Path(name): Path<String>,
+    Query(params): Query<DeleteNetworkLinkQuery>,
+) -> Result<Json<NetworkApplyResponse>, (StatusCode, Json<ApiError>)> {
+    info!(bridge = %name, "Deleting network bridge");
+    delete_virtual_link(&name, params.confirm_timeout_secs, |c| c.bridges.iter().any(|b| b.name == name)).await
+}
+
+/// GET /api/v1/network/bonds - List bonds
+async fn list_bonds() -> Result<Json<BondList>, (StatusCode, Json<ApiError>)> {
+    let current = crate::host_network::host_network().current().await
+        .map_err(network_error)?;
+    Ok(Json(BondList { bonds: current.bonds }))
+}
+
+/// POST /api/v1/network/bonds - Create a bond from physical NICs
+///
+/// Addresses, DHCP and routes of the members move to the bond.
+async fn create_bond(
+    Json(request): Json<CreateBondRequest>,
+) -> Result<Json<NetworkApplyResponse>, (StatusCode, Json<ApiError>)> {
+    use limiquantix_hypervisor::network::host::BondConfig;
     
-    info!(bridge = %request.name, "Bridge created successfully");
+    info!(bond = %request.name, mode = %request.mode, members = ?request.members, "Creating bond");
     
-    Ok(Json(NetworkInterface {
-        name: request.name.clone(),
-        mac_address: "00:00:00:00:00:00".to_string(),
-        interface_type: "bridge".to_string(),
-        state: "up".to_string(),
-        ip_addresses: vec![],
-        mtu: 1500,
-        speed_mbps: None,
+    if request.members.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(ApiError::new("invalid_network_config", "A bond needs at least one member")),
+        ));
+    }
+    
+    let mut desired = crate::host_network::host_network().current().await
+        .map_err(network_error)?;
+    
+    if desired.bonds.iter().any(|b| b.name == request.name) {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ApiError::new("bond_exists", &format!("Bond {} already exists", request.name))),
+        ));
+    }
+    
+    desired.bonds.push(BondConfig {
+        miimon: request.miimon.or(Some(100)),
+        lacp_rate: request.lacp_rate,
+        xmit_hash_policy: request.xmit_hash_policy,
+        primary: request.primary,
+        ..BondConfig::new(&request.name, &request.mode, request.members.clone())
+    });
+    desired.move_addressing(&request.members, &request.name);
+    
+    apply_network_config(desired, confirm_timeout(request.confirm_timeout_secs)).await
+}
+
+/// DELETE /api/v1/network/bonds/:name - Delete a bond, returning its addresses to the first member
+async fn delete_bond(
+    Path(name): Path<String>,
+    Query(params): Query<DeleteNetworkLinkQuery>,
+) -> Result<Json<NetworkApplyResponse>, (StatusCode, Json<ApiError>)> {
+    info!(bond = %name, "Deleting bond");
+    delete_virtual_link(&name, params.confirm_timeout_secs, |c| c.bonds.iter().any(|b| b.name == name)).await
+}
+
+fn ovs_bond_error(e: anyhow::Error) -> (StatusCode, Json<ApiError>) {
+    let message = format!("{:#}", e);
+    let status = if message.contains("not found") { StatusCode::NOT_FOUND } else { StatusCode::INTERNAL_SERVER_ERROR };
+    (status, Json(ApiError::new("ovs_bond_failed", &message)))
+}
+
+/// GET /api/v1/network/ovs-bonds - List saved OVS bonds
+async fn list_ovs_bonds() -> Result<Json<OvsBondList>, (StatusCode, Json<ApiError>)> {
+    let bonds = crate::chassis::list_bonds().await.map_err(ovs_bond_error)?;
+    Ok(Json(OvsBondList { bonds }))
+}
+
+/// POST /api/v1/network/ovs-bonds - Add or update an OVS bond on the integration or a provider bridge
+///
+/// The bond is saved and re-created at startup. The change is rolled back if
+/// the management address or default gateway stops answering.
+async fn apply_ovs_bond(
+    Json(bond): Json<crate::chassis::OvsBondConfig>,
+) -> Result<Json<crate::chassis::OvsBondConfig>, (StatusCode, Json<ApiError>)> {
+    bond.validate().map_err(invalid_network_config)?;
+    
+    info!(bond = %bond.name, bridge = %bond.bridge, members = ?bond.members, "Applying OVS bond");
+    crate::chassis::apply_bond(bond.clone()).await.map_err(ovs_bond_error)?;
+    Ok(Json(bond))
+}
+
+/// DELETE /api/v1/network/ovs-bonds/:name - Remove an OVS bond
+async fn delete_ovs_bond(
+    Path(name): Path<String>,
+) -> Result<StatusCode, (StatusCode, Json<ApiError>)> {
+    info!(bond = %name, "Deleting OVS bond");
+    crate::chassis::delete_bond(name).await.map_err(ovs_bond_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /api/v1/network/vlans - List VLAN sub-interfaces
+async fn list_vlans() -> Result<Json<VlanList>, (StatusCode, Json<ApiError>)> {
+    let current = crate::host_network::host_network().current().await
+        .map_err(network_error)?;
+    Ok(Json(VlanList { vlans: current.vlans }))
+}
+
+/// POST /api/v1/network/vlans - Create a VLAN sub-interface
+async fn create_vlan(
+    Json(request): Json<CreateVlanRequest>,
+) -> Result<Json<NetworkApplyResponse>, (StatusCode, Json<ApiError>)> {
+    use limiquantix_hypervisor::network::host::{LinkConfig, VlanConfig};
+    
+    let name = request.name.unwrap_or_else(|| format!("{}.{}", request.parent, request.vlan_id));
+    info!(vlan = %name, parent = %request.parent, id = request.vlan_id, "Creating VLAN interface");
+    
+    let mut desired = crate::host_network::host_network().current().await
+        .map_err(network_error)?;
+    
+    if desired.vlans.iter().any(|v| v.name == name) {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ApiError::new("vlan_exists", &format!("VLAN interface {} already exists", name))),
+        ));
+    }
+    
+    desired.vlans.push(VlanConfig {
+        name: name.clone(),
+        parent: request.parent,
+        id: request.vlan_id,
+    });
+    desired.links.push(LinkConfig {
+        addresses: request.addresses,
+        dhcp: request.dhcp,
+        ..LinkConfig::new(&name)
+    });
+    
+    apply_network_config(desired, confirm_timeout(request.confirm_timeout_secs)).await
+}
+
+/// DELETE /api/v1/network/vlans/:name - Delete a VLAN sub-interface
+async fn delete_vlan(
+    Path(name): Path<String>,
+    Query(params): Query<DeleteNetworkLinkQuery>,
+) -> Result<Json<NetworkApplyResponse>, (StatusCode, Json<ApiError>)> {
+    info!(vlan = %name, "Deleting VLAN interface");
+    delete_virtual_link(&name, params.confirm_timeout_secs, |c| c.vlans.iter().any(|v| v.name == name)).await
+}
+
+/// Remove a bridge, bond or VLAN (`exists` checks that it is the expected kind).
+async fn delete_virtual_link(
+    name: &str,
+    confirm_timeout_secs: Option<u64>,
+    exists: impl Fn(&limiquantix_hypervisor::HostNetworkConfig) -> bool,
+) -> Result<Json<NetworkApplyResponse>, (StatusCode, Json<ApiError>)> {
+    let mut desired = crate::host_network::host_network().current().await
+        .map_err(network_error)?;
+    
+    if !exists(&desired) {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ApiError::new("not_found", &format!("{} not found", name))),
+        ));
+    }
+    desired.remove_virtual_link(name).map_err(|e| (
+        StatusCode::CONFLICT,
+        Json(ApiError::new("link_in_use", &format!("{:#}", e))),
+    ))?;
+    
+    apply_network_config(desired, confirm_timeout(confirm_timeout_secs)).await
+}
+
+// ============================================================================
+// Packet Capture & Port Mirror Handlers
+// ============================================================================
+
+fn capture_error(e: crate::capture::CaptureError) -> (StatusCode, Json<ApiError>) {
+    use crate::capture::CaptureError;
+    
+    let (status, code) = match e {
+        CaptureError::NotFound(_) => (StatusCode::NOT_FOUND, "capture_not_found"),
+        CaptureError::Conflict(_) => (StatusCode::CONFLICT, "capture_conflict"),
+        CaptureError::Invalid(_) => (StatusCode::BAD_REQUEST, "invalid_capture"),
+        CaptureError::Failed(_) => (StatusCode::INTERNAL_SERVER_ERROR, "capture_failed"),
+    };
+    (status, Json(ApiError::new(code, &e.to_string())))
+}
+
+/// POST /api/v1/vms/:vm_id/nics/:nic/capture - Start a bounded packet capture
+async fn start_capture(
+    Path((vm_id, nic)): Path<(String, String)>,
+    body: Option<Json<limiquantix_hypervisor::CaptureOptions>>,
+) -> Result<(StatusCode, Json<crate::capture::CaptureInfo>), (StatusCode, Json<ApiError>)> {
+    let options = body.map(|Json(o)| o).unwrap_or_default();
+    crate::capture::captures().start(&vm_id, &nic, options).await
+        .map(|info| (StatusCode::ACCEPTED, Json(info)))
+        .map_err(capture_error)
+}
+
+/// GET /api/v1/vms/:vm_id/nics/:nic/capture/status - State of the last capture
+async fn get_capture_status(
+    Path((vm_id, nic)): Path<(String, String)>,
+) -> Result<Json<crate::capture::CaptureInfo>, (StatusCode, Json<ApiError>)> {
+    crate::capture::captures().status(&vm_id, &nic).await
+        .map(Json)
+        .map_err(capture_error)
+}
+
+/// DELETE /api/v1/vms/:vm_id/nics/:nic/capture - Stop a running capture
+async fn stop_capture(
+    Path((vm_id, nic)): Path<(String, String)>,
+) -> Result<Json<crate::capture::CaptureInfo>, (StatusCode, Json<ApiError>)> {
+    crate::capture::captures().stop(&vm_id, &nic).await
+        .map(Json)
+        .map_err(capture_error)
+}
+
+/// GET /api/v1/vms/:vm_id/nics/:nic/capture - Download the pcap of the last capture
+async fn download_capture(
+    Path((vm_id, nic)): Path<(String, String)>,
+) -> Result<Response, (StatusCode, Json<ApiError>)> {
+    use tokio::io::AsyncReadExt;
+    
+    let (path, info) = crate::capture::captures().file(&vm_id, &nic).await.map_err(capture_error)?;
+    let file = tokio::fs::File::open(&path).await
+        .map_err(|e| capture_error(crate::capture::CaptureError::Failed(e.into())))?;
+    
+    // Stream in chunks; captures can be up to 1 GiB
+    let stream = futures::stream::unfold(Some(file), |file| async move {
+        let mut file = file?;
+        let mut buf = vec![0u8; 64 * 1024];
+        match file.read(&mut buf).await {
+            Ok(0) => None,
+            Ok(n) => {
+                buf.truncate(n);
+                Some((Ok(axum::body::Bytes::from(buf)), Some(file)))
+            }
+            Err(e) => Some((Err(e), None)),
+        }
+    });
+    
+    let filename = format!("{}-{}.pcap", vm_id, info.interface);
+    Ok((
+        StatusCode::OK,
+        [
+            (header::CONTENT_TYPE, "application/vnd.tcpdump.pcap".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename={}", filename)),
+        ],
+        Body::from_stream(stream),
+    ).into_response())
+}
+
+/// GET /api/v1/network/mirrors - List OVS port mirror sessions
+async fn list_mirrors(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<MirrorList>, (StatusCode, Json<ApiError>)> {
+    let service = state.service.clone();
+    tokio::task::spawn_blocking(move || service.list_mirrors_internal()).await
+        .map_err(|e| e.to_string())
+        .and_then(|r| r)
+        .map(|mirrors| Json(MirrorList { mirrors }))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiError::new("mirror_list_failed", &e))))
+}
+
+/// POST /api/v1/network/mirrors - Mirror a port's traffic to an analyzer port
+async fn create_mirror(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<CreateMirrorRequest>,
+) -> Result<(StatusCode, Json<limiquantix_hypervisor::MirrorSession>), (StatusCode, Json<ApiError>)> {
+    use crate::event_store::{emit_event, Event, EventCategory, EventLevel};
+    
+    let source_port = match (request.source_port, request.vm_id, request.nic) {
+        (Some(port), _, _) => port,
+        (None, Some(vm_id), Some(nic)) => crate::capture::resolve_nic(&vm_id, &nic).await
+            .map_err(capture_error)?
+            .0,
+        _ => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(ApiError::new("invalid_mirror", "Either sourcePort or vmId and nic are required")),
+            ));
+        }
+    };
+    
+    let service = state.service.clone();
+    let (name, output_port, direction) = (request.name, request.output_port, request.direction);
+    let mirror = tokio::task::spawn_blocking(move || service.create_mirror_internal(&name, &source_port, &output_port, direction)).await
+        .map_err(|e| e.to_string())
+        .and_then(|r| r)
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ApiError::new("mirror_create_failed", &e))))?;
+    
+    // Mirrors expose tenant traffic, so they are audited like captures
+    emit_event(Event::new(
+        EventLevel::Info,
+        EventCategory::Security,
+        format!("Port mirror {} created: {} -> {}", mirror.name, mirror.source_port, mirror.output_port),
+        "capture",
+    ));
+    Ok((StatusCode::CREATED, Json(mirror)))
+}
+
+/// DELETE /api/v1/network/mirrors/:name - Remove a port mirror session
+async fn delete_mirror(
+    State(state): State<Arc<AppState>>,
+    Path(name): Path<String>,
+) -> Result<StatusCode, (StatusCode, Json<ApiError>)> {
+    let service = state.service.clone();
+    tokio::task::spawn_blocking(move || service.delete_mirror_internal(&name)).await
+        .map_err(|e| e.to_string())
+        .and_then(|r| r)
+        .map(|_| StatusCode::NO_CONTENT)
+        .map_err(|e| {
+            let status = if e.contains("not found") { StatusCode::NOT_FOUND } else { StatusCode::INTERNAL_SERVER_ERROR };
+            (status, Json(ApiError::new("mirror_delete_failed", &e)))
+        })
+}
+
+// ============================================================================
+// MAC Allocation Handlers
+// ============================================================================
+
+/// GET /api/v1/network/macs - MAC pool and allocated addresses
+async fn get_mac_registry(
+    State(state): State<Arc<AppState>>,
+) -> Json<MacRegistryResponse> {
+    let macs = state.service.mac_allocator();
+    Json(MacRegistryResponse {
+        pool: macs.pool().await,
+        allocations: macs.allocations().await,
+    })
+}
+
+/// PUT /api/v1/network/macs/pool - Change the prefix/range for new MACs
+async fn put_mac_pool(
+    State(state): State<Arc<AppState>>,
+    Json(pool): Json<limiquantix_hypervisor::MacPoolConfig>,
+) -> Result<Json<limiquantix_hypervisor::MacPoolConfig>, (StatusCode, Json<ApiError>)> {
+    state.service.mac_allocator().set_pool(pool).await
+        .map_err(|e| (StatusCode::BAD_REQUEST, Json(ApiError::new("invalid_mac_pool", &format!("{:#}", e)))))?;
+    Ok(Json(state.service.mac_allocator().pool().await))
+}
+
+/// GET /api/v1/network/macs/conflicts - Scan local VMs for duplicate MACs
+async fn list_mac_conflicts(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<MacConflictList>, (StatusCode, Json<ApiError>)> {
+    state.service.check_mac_conflicts().await
+        .map(|conflicts| Json(MacConflictList { conflicts }))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiError::new("mac_scan_failed", &e))))
+}
+
+// ============================================================================
+// Network Port QoS Handlers
+// ============================================================================
+
+/// PUT /api/v1/network/ports/:port_id/qos - Change a port's bandwidth limits (live)
+async fn update_network_port_qos(
+    State(state): State<Arc<AppState>>,
+    Path(port_id): Path<String>,
+    Json(request): Json<NetworkPortQosLimits>,
+) -> Result<Json<NetworkPortStatus>, (StatusCode, Json<ApiError>)> {
+    let qos = limiquantix_hypervisor::NetworkPortQoS {
+        ingress_rate_kbps: request.ingress_rate_kbps,
+        egress_rate_kbps: request.egress_rate_kbps,
+        ingress_burst_kb: request.ingress_burst_kb,
+        egress_burst_kb: request.egress_burst_kb,
+    };
+    
+    let info = state.service.update_network_port_qos_internal(&port_id, Some(qos)).await
+        .map_err(|e| {
+            let status = if e.code() == tonic::Code::NotFound {
+                StatusCode::NOT_FOUND
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            };
+            (status, Json(ApiError::new("port_qos_failed", e.message())))
+        })?;
+    
+    Ok(Json(NetworkPortStatus {
+        port_id: info.port_id,
+        ovs_port_name: info.ovs_port_name,
+        effective_qos: info.effective_qos.map(|q| NetworkPortQosLimits {
+            ingress_rate_kbps: q.ingress_rate_kbps,
+            egress_rate_kbps: q.egress_rate_kbps,
+            ingress_burst_kb: q.ingress_burst_kb,
+            egress_burst_kb: q.egress_burst_kb,
+        }),
+        rx_bytes: info.rx_bytes,
+        tx_bytes: info.tx_bytes,
+        rx_packets: info.rx_packets,
+        tx_packets: info.tx_packets,
+        rx_dropped: info.rx_dropped,
+        tx_dropped: info.tx_dropped,
+        qos_dropped: info.qos_dropped,
     }))
 }
 
+// ============================================================================
+// OVN Chassis Health Handlers
+// ============================================================================
+
+/// GET /api/v1/network/ovn/health - Latest overlay (tunnel, BFD, MTU) health
+async fn get_ovn_health() -> Result<Json<crate::chassis::ChassisHealth>, (StatusCode, Json<ApiError>)> {
+    crate::chassis::latest_health().map(Json).ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ApiError::new("ovn_health_unavailable", "No chassis health check has completed on this host")),
+        )
+    })
+}
+
+// ============================================================================
+// DHCP Handlers
+// ============================================================================
+
+fn dhcp_error(e: crate::dhcp::DhcpError) -> (StatusCode, Json<ApiError>) {
+    use crate::dhcp::DhcpError;
+    
+    let (status, code) = match e {
+        DhcpError::NotFound(_) => (StatusCode::NOT_FOUND, "dhcp_not_configured"),
+        DhcpError::Invalid(_) => (StatusCode::BAD_REQUEST, "invalid_dhcp_config"),
+        DhcpError::Failed(_) => (StatusCode::INTERNAL_SERVER_ERROR, "dhcp_failed"),
+    };
+    (status, Json(ApiError::new(code, &e.to_string())))
+}
+
+/// GET /api/v1/network/dhcp - List DHCP servers
+async fn list_dhcp_servers() -> Json<DhcpServerList> {
+    Json(DhcpServerList {
+        servers: crate::dhcp::dhcp().list().await,
+    })
+}
+
+/// GET /api/v1/network/dhcp/:bridge - Get a bridge's DHCP configuration
+async fn get_dhcp_server(
+    Path(bridge): Path<String>,
+) -> Result<Json<crate::dhcp::DhcpServerStatus>, (StatusCode, Json<ApiError>)> {
+    crate::dhcp::dhcp().get(&bridge).await
+        .map(Json)
+        .map_err(dhcp_error)
+}
+
+/// PUT /api/v1/network/dhcp/:bridge - Configure and (re)start DHCP on a bridge
+async fn put_dhcp_server(
+    Path(bridge): Path<String>,
+    Json(mut config): Json<limiquantix_hypervisor::DhcpConfig>,
+) -> Result<Json<crate::dhcp::DhcpServerStatus>, (StatusCode, Json<ApiError>)> {
+    if !std::path::Path::new("/sys/class/net").join(&bridge).exists() {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ApiError::new("bridge_not_found", &format!("Bridge {} does not exist", bridge))),
+        ));
+    }
+    
+    config.bridge = bridge;
+    crate::dhcp::dhcp().put_config(config).await
+        .map(Json)
+        .map_err(dhcp_error)
+}
+
+/// DELETE /api/v1/network/dhcp/:bridge - Stop DHCP on a bridge
+async fn delete_dhcp_server(
+    Path(bridge): Path<String>,
+) -> Result<StatusCode, (StatusCode, Json<ApiError>)> {
+    crate::dhcp::dhcp().delete_config(&bridge).await.map_err(dhcp_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /api/v1/network/dhcp/:bridge/leases - Lease table of a bridge
+async fn list_dhcp_leases(
+    Path(bridge): Path<String>,
+) -> Result<Json<DhcpLeaseList>, (StatusCode, Json<ApiError>)> {
+    crate::dhcp::dhcp().leases(&bridge).await
+        .map(|leases| Json(DhcpLeaseList { leases }))
+        .map_err(dhcp_error)
+}
+
+/// PUT /api/v1/network/dhcp/:bridge/static-leases/:mac - Pin an address to a NIC
+async fn set_static_lease(
+    Path((bridge, mac)): Path<(String, String)>,
+    Json(request): Json<StaticLeaseRequest>,
+) -> Result<Json<crate::dhcp::DhcpServerStatus>, (StatusCode, Json<ApiError>)> {
+    let lease = limiquantix_hypervisor::StaticLease {
+        mac_address: mac.to_lowercase(),
+        ip_address: request.ip_address,
+        hostname: request.hostname,
+        vm_id: request.vm_id,
+    };
+    crate::dhcp::dhcp().set_static_lease(&bridge, lease).await
+        .map(Json)
+        .map_err(dhcp_error)
+}
+
+/// DELETE /api/v1/network/dhcp/:bridge/static-leases/:mac - Remove a static lease
+async fn delete_static_lease(
+    Path((bridge, mac)): Path<(String, String)>,
+) -> Result<Json<crate::dhcp::DhcpServerStatus>, (StatusCode, Json<ApiError>)> {
+    crate::dhcp::dhcp().remove_static_lease(&bridge, &mac).await
+        .map(Json)
+        .map_err(dhcp_error)
+}
+
+// ============================================================================
+// Security Group Handlers
+// ============================================================================
+
+fn firewall_error(e: crate::firewall::FirewallError) -> (StatusCode, Json<ApiError>) {
+    use crate::firewall::FirewallError;
+    
+    let (status, code) = match e {
+        FirewallError::NotFound(_) => (StatusCode::NOT_FOUND, "security_group_not_found"),
+        FirewallError::InUse(..) => (StatusCode::CONFLICT, "security_group_in_use"),
+        FirewallError::Invalid(_) => (StatusCode::BAD_REQUEST, "invalid_security_group"),
+        FirewallError::Failed(_) => (StatusCode::INTERNAL_SERVER_ERROR, "firewall_failed"),
+    };
+    (status, Json(ApiError::new(code, &e.to_string())))
+}
+
+/// GET /api/v1/firewall/security-groups - List security groups
+async fn list_security_groups() -> Json<SecurityGroupList> {
+    Json(SecurityGroupList {
+        security_groups: crate::firewall::firewall().list_groups().await,
+    })
+}
+
+/// POST /api/v1/firewall/security-groups - Create a security group
+async fn create_security_group(
+    Json(mut group): Json<limiquantix_hypervisor::SecurityGroup>,
+) -> Result<(StatusCode, Json<limiquantix_hypervisor::SecurityGroup>), (StatusCode, Json<ApiError>)> {
+    if group.id.is_empty() {
+        group.id = uuid::Uuid::new_v4().to_string();
+    }
+    for rule in group.rules.iter_mut().filter(|r| r.id.is_empty()) {
+        rule.id = uuid::Uuid::new_v4().to_string();
+    }
+    
+    let manager = crate::firewall::firewall();
+    if manager.get_group(&group.id).await.is_ok() {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(ApiError::new("security_group_exists", &format!("Security group {} already exists", group.id))),
+        ));
+    }
+    
+    manager.put_group(group.clone()).await.map_err(firewall_error)?;
+    Ok((StatusCode::CREATED, Json(group)))
+}
+
+/// GET /api/v1/firewall/security-groups/:id - Get a security group
+async fn get_security_group(
+    Path(id): Path<String>,
+) -> Result<Json<limiquantix_hypervisor::SecurityGroup>, (StatusCode, Json<ApiError>)> {
+    crate::firewall::firewall().get_group(&id).await
+        .map(Json)
+        .map_err(firewall_error)
+}
+
+/// PUT /api/v1/firewall/security-groups/:id - Replace a security group's rules
+async fn update_security_group(
+    Path(id): Path<String>,
+    Json(mut group): Json<limiquantix_hypervisor::SecurityGroup>,
+) -> Result<Json<limiquantix_hypervisor::SecurityGroup>, (StatusCode, Json<ApiError>)> {
+    let manager = crate::firewall::firewall();
+    manager.get_group(&id).await.map_err(firewall_error)?;
+    
+    group.id = id;
+    for rule in group.rules.iter_mut().filter(|r| r.id.is_empty()) {
+        rule.id = uuid::Uuid::new_v4().to_string();
+    }
+    
+    manager.put_group(group.clone()).await.map_err(firewall_error)?;
+    Ok(Json(group))
+}
+
+/// DELETE /api/v1/firewall/security-groups/:id - Delete an unused security group
+async fn delete_security_group(
+    Path(id): Path<String>,
+) -> Result<StatusCode, (StatusCode, Json<ApiError>)> {
+    crate::firewall::firewall().delete_group(&id).await.map_err(firewall_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /api/v1/firewall/sync - Rebuild the nftables rules for running VMs
+async fn sync_firewall() -> Result<Json<crate::firewall::SyncResult>, (StatusCode, Json<ApiError>)> {
+    crate::firewall::firewall().sync().await
+        .map(Json)
+        .map_err(firewall_error)
+}
+
+/// GET /api/v1/vms/:vm_id/security - Security settings of a VM's NICs
+async fn get_vm_port_security(
+    Path(vm_id): Path<String>,
+) -> Json<VmPortSecurity> {
+    Json(VmPortSecurity {
+        ports: crate::firewall::firewall().vm_ports(&vm_id).await,
+    })
+}
+
+/// PUT /api/v1/vms/:vm_id/security - Set anti-spoofing and security groups for a VM's NICs
+async fn set_vm_port_security(
+    Path(vm_id): Path<String>,
+    Json(request): Json<VmPortSecurity>,
+) -> Result<Json<crate::firewall::SyncResult>, (StatusCode, Json<ApiError>)> {
+    crate::firewall::firewall().set_vm_ports(&vm_id, request.ports).await
+        .map(Json)
+        .map_err(firewall_error)
+}
+
 /// Get DNS configuration
 async fn get_dns_config(
     State(_state): State<Arc<AppState>>,
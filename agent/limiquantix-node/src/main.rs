@@ -20,20 +20,31 @@ use clap::Parser;
 use tracing::{info, error};
 
 mod agent_client;
+mod capture;
 mod chassis;
 mod cli;
 mod config;
+mod console_proxy;
+mod dhcp;
 mod event_store;
+mod firewall;
+mod guest_events;
+mod host_firewall;
+mod host_network;
 mod http_server;
 mod iso_manager;
+mod patch_compliance;
 mod registration;
 mod server;
+mod serial_console;
 mod service;
 mod state_watcher;
 mod tls;
+mod tree_transfer;
+mod trim_scheduler;
 pub mod update;
 
-pub use chassis::{ChassisConfig, ChassisHealth, ChassisManager};
+pub use chassis::{ChassisConfig, ChassisHealth, ChassisManager, LacpMode, OvsBondConfig, OvsBondMode};
 
 pub use agent_client::{AgentClient, AgentManager};
 
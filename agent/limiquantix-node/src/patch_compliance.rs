@@ -0,0 +1,330 @@
+//! Patch Compliance - Last guest update scan and update run per VM.
+//!
+//! Every pending-update listing and update run through the guest agent is
+//! recorded here, so the node can report patch compliance for VMs whose
+//! agent is not reachable right now, stopped VMs included. While an agent is
+//! connected, its telemetry provides the reboot-required flag instead of the
+//! recorded one (see `NodeDaemonServiceImpl::guest_patch_compliance`).
+//!
+//! Records are saved and loaded again when the node starts.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use limiquantix_proto::agent::{ApplyUpdatesResponse, ListUpdatesResponse};
+use limiquantix_proto::{GuestPatchCompliance, GuestPatchStatus};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tracing::warn;
+
+/// Persistent config partition on Quantix-OS
+const PERSISTENT_DIR: &str = "/quantix/storage";
+
+/// Saved records on other distributions
+const FALLBACK_PATH: &str = "/etc/limiquantix/patch-compliance.json";
+
+/// Age after which a scan no longer says anything about compliance
+const MAX_SCAN_AGE_DAYS: i64 = 7;
+
+/// Where the patch records are saved.
+fn persist_path() -> PathBuf {
+    if Path::new("/quantix").exists() {
+        Path::new(PERSISTENT_DIR).join("patch-compliance.json")
+    } else {
+        PathBuf::from(FALLBACK_PATH)
+    }
+}
+
+/// Result of a pending-update scan.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatchScan {
+    pub at: DateTime<Utc>,
+    pub package_manager: String,
+    pub security_updates: u32,
+    pub other_updates: u32,
+    pub security_classified: bool,
+}
+
+/// Result of an update run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatchRun {
+    pub at: DateTime<Utc>,
+    pub succeeded: bool,
+    pub installed: u32,
+    pub failed: u32,
+}
+
+/// Patch state of one VM.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatchRecord {
+    pub vm_id: String,
+    #[serde(default)]
+    pub last_scan: Option<PatchScan>,
+    #[serde(default)]
+    pub last_run: Option<PatchRun>,
+    /// Reboot flag reported by the last scan or run
+    #[serde(default)]
+    pub reboot_required: bool,
+}
+
+impl PatchRecord {
+    fn new(vm_id: &str) -> Self {
+        Self {
+            vm_id: vm_id.to_string(),
+            last_scan: None,
+            last_run: None,
+            reboot_required: false,
+        }
+    }
+
+    /// Compliance status at `now`. `reboot_required` is the live telemetry
+    /// flag if the agent is connected, otherwise the recorded one is used.
+    /// A scan older than `MAX_SCAN_AGE_DAYS`, or one whose package manager
+    /// can't tell security updates apart while updates are pending, gives
+    /// `Unknown`.
+    pub fn status(&self, reboot_required: Option<bool>, now: DateTime<Utc>) -> GuestPatchStatus {
+        let Some(scan) = &self.last_scan else {
+            return GuestPatchStatus::Unknown;
+        };
+        if self.needs_scan(now) {
+            GuestPatchStatus::Unknown
+        } else if scan.security_updates > 0 {
+            GuestPatchStatus::SecurityUpdatesPending
+        } else if !scan.security_classified && scan.other_updates > 0 {
+            GuestPatchStatus::Unknown
+        } else if reboot_required.unwrap_or(self.reboot_required) {
+            GuestPatchStatus::RebootRequired
+        } else {
+            GuestPatchStatus::Compliant
+        }
+    }
+
+    /// Whether the VM was never scanned or its last scan is stale.
+    pub fn needs_scan(&self, now: DateTime<Utc>) -> bool {
+        self.last_scan.as_ref()
+            .is_none_or(|scan| now - scan.at > chrono::Duration::days(MAX_SCAN_AGE_DAYS))
+    }
+
+    pub fn to_proto(&self, reboot_required: Option<bool>) -> GuestPatchCompliance {
+        let scan = self.last_scan.as_ref();
+        let run = self.last_run.as_ref();
+        GuestPatchCompliance {
+            vm_id: self.vm_id.clone(),
+            status: self.status(reboot_required, Utc::now()) as i32,
+            last_scan_at: scan.map(|s| s.at.to_rfc3339()).unwrap_or_default(),
+            package_manager: scan.map(|s| s.package_manager.clone()).unwrap_or_default(),
+            security_updates: scan.map_or(0, |s| s.security_updates),
+            other_updates: scan.map_or(0, |s| s.other_updates),
+            security_classified: scan.is_some_and(|s| s.security_classified),
+            reboot_required: reboot_required.unwrap_or(self.reboot_required),
+            last_apply_at: run.map(|r| r.at.to_rfc3339()).unwrap_or_default(),
+            last_apply_succeeded: run.is_some_and(|r| r.succeeded),
+            last_apply_installed: run.map_or(0, |r| r.installed),
+            last_apply_failed: run.map_or(0, |r| r.failed),
+            ..Default::default()
+        }
+    }
+}
+
+/// Keeps the per-VM patch records.
+pub struct PatchComplianceStore {
+    path: PathBuf,
+    records: Mutex<HashMap<String, PatchRecord>>,
+}
+
+impl PatchComplianceStore {
+    /// Load the records saved at `path` (none if the file does not exist).
+    fn load(path: PathBuf) -> Self {
+        let records = match std::fs::read(&path) {
+            Ok(data) => match serde_json::from_slice::<Vec<PatchRecord>>(&data) {
+                Ok(list) => list.into_iter().map(|r| (r.vm_id.clone(), r)).collect(),
+                Err(e) => {
+                    warn!(path = %path.display(), error = %e, "Failed to parse saved patch records");
+                    HashMap::new()
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "Failed to load saved patch records");
+                HashMap::new()
+            }
+        };
+
+        Self {
+            path,
+            records: Mutex::new(records),
+        }
+    }
+
+    /// Record of a VM (empty if it was never scanned).
+    pub async fn get(&self, vm_id: &str) -> PatchRecord {
+        self.records.lock().await.get(vm_id).cloned()
+            .unwrap_or_else(|| PatchRecord::new(vm_id))
+    }
+
+    /// Record a full (not security-only) pending-update listing.
+    pub async fn record_scan(&self, vm_id: &str, at: DateTime<Utc>, scan: &ListUpdatesResponse) {
+        let security_updates = scan.updates.iter().filter(|u| u.security).count() as u32;
+
+        let mut records = self.records.lock().await;
+        let record = records.entry(vm_id.to_string())
+            .or_insert_with(|| PatchRecord::new(vm_id));
+        record.last_scan = Some(PatchScan {
+            at,
+            package_manager: scan.package_manager.clone(),
+            security_updates,
+            other_updates: scan.updates.len() as u32 - security_updates,
+            security_classified: scan.security_classified,
+        });
+        record.reboot_required = scan.reboot_required;
+        self.persist(&records).await;
+    }
+
+    /// Record an update run. The installed updates are taken off the last
+    /// scan, so the summary stays current without listing again.
+    pub async fn record_run(&self, vm_id: &str, at: DateTime<Utc>, run: &ApplyUpdatesResponse) {
+        let installed_security = run.installed.iter().filter(|u| u.security).count() as u32;
+        let installed_other = run.installed.len() as u32 - installed_security;
+
+        let mut records = self.records.lock().await;
+        let record = records.entry(vm_id.to_string())
+            .or_insert_with(|| PatchRecord::new(vm_id));
+        if let Some(scan) = &mut record.last_scan {
+            scan.security_updates = scan.security_updates.saturating_sub(installed_security);
+            scan.other_updates = scan.other_updates.saturating_sub(installed_other);
+        }
+        record.last_run = Some(PatchRun {
+            at,
+            succeeded: run.success,
+            installed: run.installed.len() as u32,
+            failed: run.failed.len() as u32,
+        });
+        record.reboot_required = run.reboot_required;
+        self.persist(&records).await;
+    }
+
+    /// Forget a deleted VM.
+    pub async fn remove(&self, vm_id: &str) {
+        let mut records = self.records.lock().await;
+        if records.remove(vm_id).is_some() {
+            self.persist(&records).await;
+        }
+    }
+
+    /// Save the records (failures are logged, the records stay in memory).
+    async fn persist(&self, records: &HashMap<String, PatchRecord>) {
+        let mut list: Vec<&PatchRecord> = records.values().collect();
+        list.sort_by(|a, b| a.vm_id.cmp(&b.vm_id));
+
+        let result = async {
+            let data = serde_json::to_vec_pretty(&list)?;
+            if let Some(parent) = self.path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            let tmp = self.path.with_extension("tmp");
+            tokio::fs::write(&tmp, data).await?;
+            tokio::fs::rename(&tmp, &self.path).await?;
+            anyhow::Ok(())
+        }.await;
+
+        if let Err(e) = result {
+            warn!(error = %format!("{:#}", e), "Failed to save patch records");
+        }
+    }
+}
+
+/// Global patch compliance store
+static PATCH_COMPLIANCE: std::sync::OnceLock<Arc<PatchComplianceStore>> = std::sync::OnceLock::new();
+
+/// Get the global patch compliance store.
+pub fn patch_compliance() -> &'static Arc<PatchComplianceStore> {
+    PATCH_COMPLIANCE.get_or_init(|| Arc::new(PatchComplianceStore::load(persist_path())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use limiquantix_proto::agent::AvailableUpdate;
+
+    fn update(name: &str, security: bool) -> AvailableUpdate {
+        AvailableUpdate {
+            id: name.to_string(),
+            name: name.to_string(),
+            security,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_patch_status() {
+        let now = Utc::now();
+        let mut record = PatchRecord::new("vm-1");
+        assert_eq!(record.status(None, now), GuestPatchStatus::Unknown);
+
+        record.last_scan = Some(PatchScan {
+            at: now,
+            package_manager: "apt".to_string(),
+            security_updates: 0,
+            other_updates: 3,
+            security_classified: true,
+        });
+        assert_eq!(record.status(None, now), GuestPatchStatus::Compliant);
+
+        record.reboot_required = true;
+        assert_eq!(record.status(None, now), GuestPatchStatus::RebootRequired);
+        assert_eq!(record.status(Some(false), now), GuestPatchStatus::Compliant, "telemetry wins");
+
+        record.last_scan.as_mut().unwrap().security_updates = 1;
+        assert_eq!(record.status(Some(true), now), GuestPatchStatus::SecurityUpdatesPending);
+
+        // A stale scan says nothing
+        let later = now + chrono::Duration::days(MAX_SCAN_AGE_DAYS + 1);
+        assert_eq!(record.status(Some(true), later), GuestPatchStatus::Unknown);
+
+        // Pending updates that can't be classified may be security updates
+        let scan = record.last_scan.as_mut().unwrap();
+        scan.security_updates = 0;
+        scan.security_classified = false;
+        assert_eq!(record.status(Some(false), now), GuestPatchStatus::Unknown);
+        record.last_scan.as_mut().unwrap().other_updates = 0;
+        assert_eq!(record.status(Some(false), now), GuestPatchStatus::Compliant);
+    }
+
+    #[tokio::test]
+    async fn test_records_persist_across_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("patch-compliance.json");
+
+        let store = PatchComplianceStore::load(path.clone());
+        let scan = ListUpdatesResponse {
+            success: true,
+            package_manager: "dnf".to_string(),
+            updates: vec![update("openssl-libs.x86_64", true), update("bash.x86_64", false), update("vim.x86_64", false)],
+            security_classified: true,
+            ..Default::default()
+        };
+        store.record_scan("vm-1", Utc::now(), &scan).await;
+
+        let run = ApplyUpdatesResponse {
+            success: true,
+            installed: vec![update("openssl-libs.x86_64", true)],
+            reboot_required: true,
+            ..Default::default()
+        };
+        store.record_run("vm-1", Utc::now(), &run).await;
+
+        let reloaded = PatchComplianceStore::load(path);
+        let compliance = reloaded.get("vm-1").await.to_proto(None);
+        assert_eq!(compliance.status, GuestPatchStatus::RebootRequired as i32);
+        assert_eq!(compliance.security_updates, 0);
+        assert_eq!(compliance.other_updates, 2);
+        assert_eq!(compliance.package_manager, "dnf");
+        assert!(compliance.last_apply_succeeded);
+        assert_eq!(compliance.last_apply_installed, 1);
+
+        reloaded.remove("vm-1").await;
+        assert_eq!(reloaded.get("vm-1").await, PatchRecord::new("vm-1"));
+    }
+}
@@ -142,7 +142,7 @@ impl RegistrationClient {
         let current_ip = detect_management_ip().unwrap_or_else(|| self.management_ip.clone());
         let request = serde_json::json!({
             "hostname": self.hostname,
-            "managementIp": format!("{}:9090", current_ip),
+            "managementIp": format!("{}:9090", url_host(&current_ip)),
             "labels": self.labels,
             "role": {
                 "compute": true,
@@ -1014,83 +1014,25 @@ impl RegistrationClient {
 /// 2. Bonded/team interfaces (bond*, team*)
 /// 3. Any other non-virtual interface
 /// 4. Fallback to any non-loopback IP
+///
+/// On dual-stack interfaces IPv4 is preferred; IPv6-only hosts get their
+/// global (or unique local) IPv6 address.
 pub fn detect_management_ip() -> Option<String> {
     debug!("Detecting management IP address...");
     
-    if let Ok(interfaces) = local_ip_address::list_afinet_netifas() {
-        debug!("Found {} network interfaces", interfaces.len());
-        
-        // Collect all valid (non-loopback, non-link-local) IPv4 addresses with interface names
-        let mut candidates: Vec<(String, std::net::Ipv4Addr, i32)> = Vec::new();
-        
-        for (name, ip) in interfaces {
-            // Skip loopback
-            if ip.is_loopback() {
-                continue;
-            }
-            
-            if let std::net::IpAddr::V4(ipv4) = ip {
-                // Skip link-local (169.254.x.x)
-                if ipv4.is_link_local() {
-                    continue;
-                }
-                
-                // Skip private addresses that are commonly used for VMs (192.168.122.x = libvirt default)
-                // But still allow other 192.168.x.x networks
-                let octets = ipv4.octets();
-                if octets[0] == 192 && octets[1] == 168 && octets[2] == 122 {
-                    // This is the libvirt default bridge, skip it
-                    debug!(interface = %name, ip = %ipv4, "Skipping libvirt default bridge network");
-                    continue;
-                }
-                
-                // Calculate priority based on interface name
-                let priority = get_interface_priority(&name);
-                
-                debug!(
-                    interface = %name,
-                    ip = %ipv4,
-                    priority = priority,
-                    "Found network interface candidate"
-                );
-                
-                candidates.push((name, ipv4, priority));
-            }
-        }
-        
-        // Sort by priority (higher is better)
-        candidates.sort_by(|a, b| b.2.cmp(&a.2));
-        
-        if candidates.is_empty() {
-            warn!("No valid network interface candidates found");
-            // Log all interfaces for debugging
-            if let Ok(all_ifaces) = local_ip_address::list_afinet_netifas() {
-                for (name, ip) in all_ifaces {
-                    warn!(interface = %name, ip = %ip, "Available interface (filtered out)");
-                }
-            }
-        } else {
-            debug!("Found {} candidate interfaces", candidates.len());
-            for (name, ip, priority) in &candidates {
-                debug!(interface = %name, ip = %ip, priority = %priority, "Candidate");
-            }
-        }
-        
-        if let Some((name, ip, _)) = candidates.first() {
-            info!(
-                interface = %name,
-                ip = %ip,
-                "Selected management IP"
-            );
-            return Some(ip.to_string());
-        }
-    } else {
-        warn!("Failed to list network interfaces");
+    if let Some((name, ip)) = management_ip_candidates().into_iter().next() {
+        info!(
+            interface = %name,
+            ip = %ip,
+            "Selected management IP"
+        );
+        return Some(ip.to_string());
     }
     
     // Fallback: try local_ip_address crate's default detection
     debug!("Trying fallback IP detection");
     let fallback = local_ip_address::local_ip()
+        .or_else(|_| local_ip_address::local_ipv6())
         .ok()
         .map(|ip| {
             info!(ip = %ip, "Using fallback IP detection");
@@ -1104,6 +1046,97 @@ pub fn detect_management_ip() -> Option<String> {
     fallback
 }
 
+/// All usable management addresses (IPv4 and IPv6), best first.
+pub fn detect_management_ips() -> Vec<std::net::IpAddr> {
+    management_ip_candidates().into_iter().map(|(_, ip)| ip).collect()
+}
+
+/// Non-loopback, non-link-local addresses with their interface, best first.
+fn management_ip_candidates() -> Vec<(String, std::net::IpAddr)> {
+    let interfaces = match local_ip_address::list_afinet_netifas() {
+        Ok(interfaces) => interfaces,
+        Err(_) => {
+            warn!("Failed to list network interfaces");
+            return Vec::new();
+        }
+    };
+    debug!("Found {} network interfaces", interfaces.len());
+    
+    // (interface, address, priority)
+    let mut candidates: Vec<(String, std::net::IpAddr, i32)> = Vec::new();
+    
+    for (name, ip) in &interfaces {
+        if !is_management_candidate(ip) {
+            continue;
+        }
+        
+        // Skip private addresses that are commonly used for VMs (192.168.122.x = libvirt default)
+        // But still allow other 192.168.x.x networks
+        if let std::net::IpAddr::V4(ipv4) = ip {
+            let octets = ipv4.octets();
+            if octets[0] == 192 && octets[1] == 168 && octets[2] == 122 {
+                // This is the libvirt default bridge, skip it
+                debug!(interface = %name, ip = %ipv4, "Skipping libvirt default bridge network");
+                continue;
+            }
+        }
+        
+        // Calculate priority based on interface name
+        let priority = get_interface_priority(name);
+        
+        debug!(
+            interface = %name,
+            ip = %ip,
+            priority = priority,
+            "Found network interface candidate"
+        );
+        
+        candidates.push((name.clone(), *ip, priority));
+    }
+    
+    // Sort by priority (higher is better), then IPv4 before IPv6
+    candidates.sort_by_key(|(_, ip, priority)| (std::cmp::Reverse(*priority), ip.is_ipv6()));
+    
+    if candidates.is_empty() {
+        warn!("No valid network interface candidates found");
+        // Log all interfaces for debugging
+        for (name, ip) in &interfaces {
+            warn!(interface = %name, ip = %ip, "Available interface (filtered out)");
+        }
+    } else {
+        debug!("Found {} candidate interfaces", candidates.len());
+        for (name, ip, priority) in &candidates {
+            debug!(interface = %name, ip = %ip, priority = %priority, "Candidate");
+        }
+    }
+    
+    candidates.into_iter().map(|(name, ip, _)| (name, ip)).collect()
+}
+
+/// Whether `ip` can identify this host to other hosts.
+fn is_management_candidate(ip: &std::net::IpAddr) -> bool {
+    match ip {
+        // Skip link-local (169.254.x.x)
+        std::net::IpAddr::V4(ipv4) => !ipv4.is_loopback() && !ipv4.is_link_local() && !ipv4.is_unspecified(),
+        // Skip link-local (fe80::/10) and IPv4-mapped addresses
+        std::net::IpAddr::V6(ipv6) => {
+            !ipv6.is_loopback()
+                && !ipv6.is_unspecified()
+                && !ipv6.is_multicast()
+                && (ipv6.segments()[0] & 0xffc0) != 0xfe80
+                && ipv6.to_ipv4_mapped().is_none()
+        }
+    }
+}
+
+/// Format `host` for use in a URL or `host:port` pair (IPv6 in brackets).
+pub fn url_host(host: &str) -> String {
+    match host.parse::<std::net::Ipv6Addr>() {
+        Ok(ipv6) => format!("[{}]", ipv6),
+        Err(_) => host.to_string(),
+    }
+}
+
 /// Get priority for an interface based on its name.
 /// Higher priority = more likely to be the management interface.
 fn get_interface_priority(name: &str) -> i32 {
@@ -1278,6 +1311,22 @@ async fn get_qcow2_virtual_size(path: &std::path::Path) -> Option<u64> {
 mod tests {
     use super::*;
     
+    #[test]
+    fn test_management_candidates() {
+        let candidate = |ip: &str| is_management_candidate(&ip.parse().unwrap());
+        assert!(candidate("10.0.0.5"));
+        assert!(candidate("2001:db8::5"));
+        assert!(candidate("fd00::5"));
+        assert!(!candidate("fe80::1"));
+        assert!(!candidate("::1"));
+        assert!(!candidate("169.254.1.1"));
+        assert!(!candidate("::ffff:10.0.0.5"));
+        
+        assert_eq!(url_host("2001:db8::5"), "[2001:db8::5]");
+        assert_eq!(url_host("10.0.0.5"), "10.0.0.5");
+        assert_eq!(url_host("node1.example.com"), "node1.example.com");
+    }
+    
     #[test]
     fn test_detect_management_ip() {
         // Should return some IP (not necessarily the "right" one in tests)
@@ -0,0 +1,475 @@
+//! Serial Console Sessions - Shared access to a VM's PTY serial port.
+//!
+//! Every VM is defined with a PTY-backed serial console (`build_console` in
+//! the domain XML builder). This module attaches to that PTY and fans its
+//! output out to any number of WebSocket viewers:
+//! - One session per (VM, serial port), opened on first attach
+//! - Output is broadcast to all viewers and kept in a scrollback buffer so
+//!   late joiners see the boot log / last prompt
+//! - Only one connection at a time may write to the guest
+//!
+//! Design Decisions:
+//! - The PTY is opened directly (like libvirt's console stream does) and put
+//!   into raw mode so the host line discipline doesn't echo or translate bytes
+//! - PTY I/O runs on blocking threads; a PTY slave is not pollable by tokio's
+//!   file API in a portable way
+//! - A session closes when QEMU closes the PTY (VM stopped) or when the last
+//!   viewer detaches; the next attach re-reads the domain XML because the PTY
+//!   path changes on every boot
+
+use std::collections::{HashMap, VecDeque};
+use std::io::{Read, Write};
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::AsRawFd;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::{broadcast, mpsc};
+use tracing::{debug, info, warn};
+
+/// Scrollback kept per session (bytes)
+const SCROLLBACK_BYTES: usize = 64 * 1024;
+
+/// Buffered output chunks per viewer before it starts lagging
+const OUTPUT_CHANNEL_CAPACITY: usize = 256;
+
+/// Buffered input chunks from the writer
+const INPUT_CHANNEL_CAPACITY: usize = 64;
+
+/// How often the reader thread checks whether the session was closed (ms)
+const READ_POLL_MS: i32 = 250;
+
+/// Fixed-size byte ring buffer holding recent console output.
+#[derive(Debug)]
+pub struct ScrollbackBuffer {
+    data: VecDeque<u8>,
+    capacity: usize,
+}
+
+impl ScrollbackBuffer {
+    /// Create a buffer that keeps the last `capacity` bytes.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            data: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+    
+    /// Append output, discarding the oldest bytes if over capacity.
+    pub fn push(&mut self, bytes: &[u8]) {
+        let bytes = if bytes.len() > self.capacity {
+            &bytes[bytes.len() - self.capacity..]
+        } else {
+            bytes
+        };
+        
+        let overflow = (self.data.len() + bytes.len()).saturating_sub(self.capacity);
+        self.data.drain(..overflow);
+        self.data.extend(bytes);
+    }
+    
+    /// Copy out the buffered bytes (oldest first).
+    pub fn snapshot(&self) -> Vec<u8> {
+        self.data.iter().copied().collect()
+    }
+}
+
+/// An open serial console attached to one VM serial port.
+pub struct SerialSession {
+    /// VM UUID
+    pub vm_id: String,
+    /// Guest serial port index
+    pub port: u32,
+    /// Host PTY path
+    pub pty_path: String,
+    /// Output fan-out (None once the PTY is closed)
+    output_tx: Mutex<Option<broadcast::Sender<Vec<u8>>>>,
+    /// Input to the guest (None once the PTY is closed)
+    input_tx: Mutex<Option<mpsc::Sender<Vec<u8>>>>,
+    /// Recent output for late joiners
+    scrollback: Mutex<ScrollbackBuffer>,
+    /// Connection ID currently holding write access
+    writer: Mutex<Option<String>>,
+    /// Number of attached connections
+    viewers: Mutex<usize>,
+    /// Set when the PTY is closed
+    closed: AtomicBool,
+}
+
+impl SerialSession {
+    /// Subscribe to output. Returns the scrollback and a receiver for
+    /// everything after it, taken atomically so no bytes are lost or doubled.
+    pub fn subscribe(&self) -> Option<(Vec<u8>, broadcast::Receiver<Vec<u8>>)> {
+        let scrollback = self.scrollback.lock().ok()?;
+        let rx = self.output_tx.lock().ok()?.as_ref()?.subscribe();
+        Some((scrollback.snapshot(), rx))
+    }
+    
+    /// Register an attached connection; returns the new viewer count, or
+    /// `None` if the session is already closed.
+    fn add_viewer(&self) -> Option<usize> {
+        let mut viewers = self.viewers.lock().ok()?;
+        if self.is_closed() {
+            return None;
+        }
+        *viewers += 1;
+        Some(*viewers)
+    }
+    
+    /// Unregister a connection and release write access if it held it.
+    /// The last viewer to leave closes the session.
+    fn remove_viewer(&self, conn_id: &str) {
+        if let Ok(mut writer) = self.writer.lock() {
+            if writer.as_deref() == Some(conn_id) {
+                *writer = None;
+            }
+        }
+        
+        let Ok(mut viewers) = self.viewers.lock() else {
+            return;
+        };
+        *viewers = viewers.saturating_sub(1);
+        if *viewers == 0 && !self.is_closed() {
+            info!(vm_id = %self.vm_id, port = self.port, "Last serial console viewer detached, closing session");
+            self.close();
+        }
+    }
+    
+    /// Try to become the single writer.
+    pub fn try_acquire_writer(&self, conn_id: &str) -> bool {
+        match self.writer.lock() {
+            Ok(mut writer) if writer.is_none() => {
+                *writer = Some(conn_id.to_string());
+                true
+            }
+            Ok(writer) => writer.as_deref() == Some(conn_id),
+            Err(_) => false,
+        }
+    }
+    
+    /// Send input to the guest if `conn_id` holds write access.
+    pub async fn send_input(&self, conn_id: &str, data: Vec<u8>) -> anyhow::Result<()> {
+        let is_writer = self.writer.lock()
+            .map(|w| w.as_deref() == Some(conn_id))
+            .unwrap_or(false);
+        if !is_writer {
+            return Err(anyhow::anyhow!("Connection is read-only"));
+        }
+        
+        let tx = self.input_tx.lock()
+            .map_err(|_| anyhow::anyhow!("Lock poisoned"))?
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("Serial console is closed"))?;
+        
+        tx.send(data).await
+            .map_err(|_| anyhow::anyhow!("Serial console is closed"))
+    }
+    
+    /// Check if the PTY has been closed.
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::SeqCst)
+    }
+    
+    /// Record output from the guest.
+    fn publish(&self, bytes: &[u8]) {
+        if let Ok(mut scrollback) = self.scrollback.lock() {
+            scrollback.push(bytes);
+            if let Ok(tx) = self.output_tx.lock() {
+                if let Some(tx) = tx.as_ref() {
+                    // No receivers is fine - output still lands in scrollback
+                    let _ = tx.send(bytes.to_vec());
+                }
+            }
+        }
+    }
+    
+    /// Mark closed and drop the channels so viewers see end-of-stream.
+    fn close(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+        if let Ok(mut tx) = self.output_tx.lock() {
+            tx.take();
+        }
+        if let Ok(mut tx) = self.input_tx.lock() {
+            tx.take();
+        }
+    }
+}
+
+/// An attached connection. Dropping it detaches from the session.
+pub struct SerialViewer {
+    pub session: Arc<SerialSession>,
+    /// Connection ID used for write access
+    pub conn_id: String,
+    /// Viewer count including this one, at attach time
+    pub viewers: usize,
+}
+
+impl Drop for SerialViewer {
+    fn drop(&mut self) {
+        self.session.remove_viewer(&self.conn_id);
+    }
+}
+
+/// Registry of open serial console sessions.
+pub struct SerialConsoleManager {
+    sessions: tokio::sync::Mutex<HashMap<(String, u32), Arc<SerialSession>>>,
+}
+
+impl SerialConsoleManager {
+    /// Create an empty manager.
+    pub fn new() -> Self {
+        Self {
+            sessions: tokio::sync::Mutex::new(HashMap::new()),
+        }
+    }
+    
+    /// Attach to a VM serial port, opening the PTY if no live session exists.
+    pub async fn attach(&self, vm_id: &str, port: u32) -> anyhow::Result<SerialViewer> {
+        let mut sessions = self.sessions.lock().await;
+        let key = (vm_id.to_string(), port);
+        let conn_id = uuid::Uuid::new_v4().to_string();
+        
+        if let Some(session) = sessions.get(&key) {
+            if let Some(viewers) = session.add_viewer() {
+                return Ok(SerialViewer { session: session.clone(), conn_id, viewers });
+            }
+        }
+        sessions.remove(&key);
+        
+        let xml = dump_domain_xml(vm_id).await?;
+        let pty_path = parse_pty_path(&xml, port).ok_or_else(|| anyhow::anyhow!(
+            "VM has no active PTY on serial port {} (is it running?)", port
+        ))?;
+        
+        let session = open_session(vm_id, port, &pty_path)?;
+        let viewers = session.add_viewer()
+            .ok_or_else(|| anyhow::anyhow!("Serial console closed while attaching"))?;
+        sessions.insert(key, session.clone());
+        
+        Ok(SerialViewer { session, conn_id, viewers })
+    }
+}
+
+impl Default for SerialConsoleManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Global serial console manager (shared by the HTTP and HTTPS servers)
+static SERIAL_CONSOLES: std::sync::OnceLock<SerialConsoleManager> = std::sync::OnceLock::new();
+
+/// Get the global serial console manager.
+pub fn serial_consoles() -> &'static SerialConsoleManager {
+    SERIAL_CONSOLES.get_or_init(SerialConsoleManager::new)
+}
+
+/// Open the PTY and start the I/O threads.
+fn open_session(vm_id: &str, port: u32, pty_path: &str) -> anyhow::Result<Arc<SerialSession>> {
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(libc::O_NOCTTY)
+        .open(pty_path)
+        .map_err(|e| anyhow::anyhow!("Failed to open {}: {}", pty_path, e))?;
+    
+    set_raw_mode(&file)
+        .map_err(|e| anyhow::anyhow!("Failed to set raw mode on {}: {}", pty_path, e))?;
+    
+    let mut reader = file.try_clone()?;
+    let mut writer = file;
+    
+    let (output_tx, _) = broadcast::channel(OUTPUT_CHANNEL_CAPACITY);
+    let (input_tx, mut input_rx) = mpsc::channel::<Vec<u8>>(INPUT_CHANNEL_CAPACITY);
+    
+    let session = Arc::new(SerialSession {
+        vm_id: vm_id.to_string(),
+        port,
+        pty_path: pty_path.to_string(),
+        output_tx: Mutex::new(Some(output_tx)),
+        input_tx: Mutex::new(Some(input_tx)),
+        scrollback: Mutex::new(ScrollbackBuffer::new(SCROLLBACK_BYTES)),
+        writer: Mutex::new(None),
+        viewers: Mutex::new(0),
+        closed: AtomicBool::new(false),
+    });
+    
+    info!(vm_id = %vm_id, port = port, pty = %pty_path, "Serial console session opened");
+    
+    // Reader: PTY -> scrollback + viewers
+    let reader_session = session.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut buf = [0u8; 4096];
+        while !reader_session.is_closed() {
+            match wait_readable(&reader, READ_POLL_MS) {
+                Ok(false) => continue,
+                Ok(true) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    debug!(vm_id = %reader_session.vm_id, error = %e, "Serial console poll failed");
+                    break;
+                }
+            }
+            match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => reader_session.publish(&buf[..n]),
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    // EIO is expected when QEMU closes the master side
+                    debug!(vm_id = %reader_session.vm_id, error = %e, "Serial console read ended");
+                    break;
+                }
+            }
+        }
+        
+        info!(vm_id = %reader_session.vm_id, port = reader_session.port, "Serial console session closed");
+        reader_session.close();
+    });
+    
+    // Writer: single writer connection -> PTY
+    let writer_vm_id = vm_id.to_string();
+    tokio::task::spawn_blocking(move || {
+        while let Some(data) = input_rx.blocking_recv() {
+            if let Err(e) = writer.write_all(&data) {
+                warn!(vm_id = %writer_vm_id, error = %e, "Failed to write to serial console");
+                break;
+            }
+        }
+    });
+    
+    Ok(session)
+}
+
+/// Wait up to `timeout_ms` for the PTY to become readable (or hung up).
+fn wait_readable(file: &std::fs::File, timeout_ms: i32) -> std::io::Result<bool> {
+    let mut fds = libc::pollfd {
+        fd: file.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    
+    // SAFETY: fds points to one valid pollfd for the duration of the call.
+    match unsafe { libc::poll(&mut fds, 1, timeout_ms) } {
+        -1 => Err(std::io::Error::last_os_error()),
+        0 => Ok(false),
+        _ => Ok(true),
+    }
+}
+
+/// Put a terminal file descriptor into raw mode.
+fn set_raw_mode(file: &std::fs::File) -> std::io::Result<()> {
+    let fd = file.as_raw_fd();
+    
+    // SAFETY: fd is a valid open descriptor owned by `file`, and termios is
+    // fully initialized by tcgetattr before being modified.
+    unsafe {
+        let mut termios: libc::termios = std::mem::zeroed();
+        if libc::tcgetattr(fd, &mut termios) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        libc::cfmakeraw(&mut termios);
+        if libc::tcsetattr(fd, libc::TCSANOW, &termios) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    
+    Ok(())
+}
+
+/// Get the live domain XML via virsh (accepts the UUID).
+async fn dump_domain_xml(vm_id: &str) -> anyhow::Result<String> {
+    let output = tokio::process::Command::new("virsh")
+        .args(["dumpxml", vm_id])
+        .output()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to run virsh dumpxml: {}", e))?;
+    
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "virsh dumpxml failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Find the host PTY path of a running domain's serial port.
+///
+/// Looks for:
+/// ```xml
+/// <serial type='pty'>
+///   <source path='/dev/pts/3'/>
+///   <target type='isa-serial' port='0'>
+/// ```
+pub fn parse_pty_path(xml: &str, port: u32) -> Option<String> {
+    let port_attr = format!("port='{}'", port);
+    
+    for section in xml.split("<serial type='pty'").skip(1) {
+        let section = &section[..section.find("</serial>")?];
+        
+        let target_start = section.find("<target")?;
+        let target = &section[target_start..];
+        let target = &target[..target.find('>')?];
+        if !target.contains(&port_attr) {
+            continue;
+        }
+        
+        let path_start = section.find("<source path='")? + 14;
+        let rest = &section[path_start..];
+        let path_end = rest.find('\'')?;
+        return Some(rest[..path_end].to_string());
+    }
+    
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_scrollback_keeps_tail() {
+        let mut buffer = ScrollbackBuffer::new(8);
+        buffer.push(b"hello ");
+        buffer.push(b"world");
+        assert_eq!(buffer.snapshot(), b"lo world");
+        
+        buffer.push(b"0123456789");
+        assert_eq!(buffer.snapshot(), b"23456789");
+        assert_eq!(buffer.snapshot().len(), 8);
+    }
+    
+    #[test]
+    fn test_parse_pty_path() {
+        let xml = r#"
+    <serial type='pty'>
+      <source path='/dev/pts/3'/>
+      <target type='isa-serial' port='0'>
+        <model name='isa-serial'/>
+      </target>
+      <alias name='serial0'/>
+    </serial>
+    <serial type='pty'>
+      <source path='/dev/pts/4'/>
+      <target type='isa-serial' port='1'>
+        <model name='isa-serial'/>
+      </target>
+    </serial>
+    <console type='pty' tty='/dev/pts/3'>
+      <source path='/dev/pts/3'/>
+      <target type='serial' port='0'/>
+    </console>
+"#;
+        assert_eq!(parse_pty_path(xml, 0), Some("/dev/pts/3".to_string()));
+        assert_eq!(parse_pty_path(xml, 1), Some("/dev/pts/4".to_string()));
+        assert_eq!(parse_pty_path(xml, 2), None);
+    }
+    
+    #[test]
+    fn test_parse_pty_path_stopped_domain() {
+        // Inactive XML has no source path yet
+        let xml = "<serial type='pty'>\n  <target port='0'/>\n</serial>";
+        assert_eq!(parse_pty_path(xml, 0), None);
+    }
+}
@@ -12,8 +12,9 @@ use limiquantix_telemetry::TelemetryCollector;
 
 use crate::config::{Config, HypervisorBackend};
 use crate::event_store::{init_event_store, emit_event, Event, EventLevel, EventCategory};
+use crate::guest_events::GuestEventMonitor;
 use crate::http_server;
-use crate::registration::{RegistrationClient, detect_management_ip};
+use crate::registration::{RegistrationClient, detect_management_ip, url_host};
 use crate::service::NodeDaemonServiceImpl;
 use crate::state_watcher::StateWatcher;
 use crate::update::UpdateManager;
@@ -31,6 +32,27 @@ pub async fn run(config: Config) -> Result<()> {
         "qx-node"
     ));
     
+    // Re-create bonds, VLANs, bridges and OVS bonds saved by earlier network
+    // changes, then start the DHCP servers on them
+    tokio::spawn(async {
+        crate::host_network::restore_persisted().await;
+        crate::chassis::restore_bonds().await;
+        crate::dhcp::dhcp().start_all().await;
+    });
+    
+    // Restrict management services to the saved allowed sources
+    tokio::spawn(async {
+        crate::host_firewall::restore_persisted().await;
+    });
+    
+    // Reload security group rules for VMs that are already running
+    tokio::spawn(async {
+        crate::firewall::firewall().sync_logged().await;
+    });
+    
+    // Watch OVN tunnels, BFD and MTU on hosts that are part of the overlay
+    crate::chassis::spawn_health_monitor();
+    
     // Initialize hypervisor backend
     let hypervisor: Arc<dyn Hypervisor> = match config.hypervisor.backend {
         HypervisorBackend::Mock => {
@@ -98,6 +120,14 @@ pub async fn run(config: Config) -> Result<()> {
         }
     }
     
+    // Report watchdog firings and guest panics as VM events
+    if config.hypervisor.backend == HypervisorBackend::Libvirt && limiquantix_hypervisor::libvirt::is_available() {
+        let monitor = GuestEventMonitor::new(hypervisor.clone(), config.hypervisor.libvirt_uri.clone());
+        tokio::spawn(async move {
+            monitor.run().await;
+        });
+    }
+    
     // Initialize telemetry collector
     let telemetry = Arc::new(TelemetryCollector::new());
     
@@ -165,9 +195,15 @@ pub async fn run(config: Config) -> Result<()> {
     // Start background agent connection manager for proactive agent connections
     service.start_agent_connection_manager();
     
+    // Watch for duplicate MACs across local domains
+    service.start_mac_conflict_monitor();
+    
+    // Periodically trim guest filesystems on pools with a trim schedule
+    service.start_trim_scheduler();
+    
     // Parse gRPC listen address
-    let grpc_addr: std::net::SocketAddr = config.server.listen_address.parse()
-        .map_err(|e| anyhow::anyhow!("Invalid gRPC listen address: {}", e))?;
+    let grpc_addr = dual_stack(config.server.listen_address.parse()
+        .map_err(|e| anyhow::anyhow!("Invalid gRPC listen address: {}", e))?);
     
     info!(
         grpc_address = %grpc_addr,
@@ -226,15 +262,15 @@ pub async fn run(config: Config) -> Result<()> {
     
     let webui_path = PathBuf::from(&config.server.http.webui_path);
     let tls_config = config.server.http.tls.clone();
-    let host = if management_ip == "0.0.0.0" { "localhost" } else { &management_ip };
+    let host = if management_ip == "0.0.0.0" { "localhost".to_string() } else { url_host(&management_ip) };
     
     // Track server handles for cleanup
     let mut server_handles = Vec::new();
     
     // Start HTTP server on port 8080 (if enabled - default)
     if config.server.http.enabled {
-        let http_addr: std::net::SocketAddr = config.server.http.listen_address.parse()
-            .map_err(|e| anyhow::anyhow!("Invalid HTTP listen address: {}", e))?;
+        let http_addr = dual_stack(config.server.http.listen_address.parse()
+            .map_err(|e| anyhow::anyhow!("Invalid HTTP listen address: {}", e))?);
         
         let webui_path_http = webui_path.clone();
         let tls_config_http = tls_config.clone();
@@ -264,8 +300,8 @@ pub async fn run(config: Config) -> Result<()> {
     
     // Start HTTPS server on port 8443 (if TLS enabled)
     if tls_config.enabled {
-        let https_addr: std::net::SocketAddr = tls_config.listen_address.parse()
-            .map_err(|e| anyhow::anyhow!("Invalid HTTPS listen address: {}", e))?;
+        let https_addr = dual_stack(tls_config.listen_address.parse()
+            .map_err(|e| anyhow::anyhow!("Invalid HTTPS listen address: {}", e))?);
         
         let webui_path_https = webui_path.clone();
         let tls_config_https = tls_config.clone();
@@ -285,7 +321,7 @@ pub async fn run(config: Config) -> Result<()> {
         
         // Start HTTP→HTTPS redirect server if enabled
         if tls_config.redirect_http {
-            let redirect_addr = std::net::SocketAddr::from(([0, 0, 0, 0], tls_config.redirect_port));
+            let redirect_addr = dual_stack(std::net::SocketAddr::from(([0, 0, 0, 0], tls_config.redirect_port)));
             let https_port = https_addr.port();
             
             info!(
@@ -330,6 +366,19 @@ pub async fn run(config: Config) -> Result<()> {
     Ok(())
 }
 
+/// Listen on both address families when the host has IPv6.
+///
+/// An unspecified IPv4 address (`0.0.0.0`) becomes `[::]`, which also
+/// accepts IPv4 clients as mapped addresses on Linux. Explicit addresses are
+/// kept as configured.
+fn dual_stack(addr: std::net::SocketAddr) -> std::net::SocketAddr {
+    if addr.ip() == std::net::Ipv4Addr::UNSPECIFIED && std::path::Path::new("/proc/net/if_inet6").exists() {
+        std::net::SocketAddr::from((std::net::Ipv6Addr::UNSPECIFIED, addr.port()))
+    } else {
+        addr
+    }
+}
+
 /// Initialize agent ISO paths on startup.
 /// 
 /// This ensures that:
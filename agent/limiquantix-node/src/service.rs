@@ -15,13 +15,18 @@ use tracing::{info, debug, warn, error, instrument};
 
 use limiquantix_hypervisor::{
     Hypervisor, VmConfig, VmState, DiskConfig, NicConfig, CdromConfig,
-    DiskBus, DiskFormat, NicModel, StorageManager, Firmware, BootDevice,
+    DiskBus, DiskDiscard, DiskDetectZeroes, DiskFormat, NicModel, StorageManager, Firmware, BootDevice,
     // Network/OVS types
-    OvsPortManager, NetworkPortConfig,
+    OvsPortManager, NetworkPortConfig, NetworkPortQoS,
     // Storage types
     PoolType, PoolConfig, VolumeSource, LocalConfig,
     // Cloud-init
     CloudInitConfig, CloudInitGenerator,
+    // Templates & clones
+    CloneOptions, CloneType, HypervisorError, TemplateInfo, TemplateManager, VmConfigStore,
+    config_from_domain_xml, refresh_from_domain,
+    // MAC allocation
+    MacAllocator, MacConflict,
 };
 use limiquantix_telemetry::TelemetryCollector;
 use limiquantix_proto::{
@@ -44,7 +49,7 @@ use limiquantix_proto::{
     InitStoragePoolRequest, StoragePoolIdRequest, StoragePoolInfoResponse,
     ListStoragePoolsResponse, CreateVolumeRequest, VolumeIdRequest,
     ResizeVolumeRequest, CloneVolumeRequest, VolumeAttachInfoResponse,
-    CreateVolumeSnapshotRequest, StoragePoolType,
+    CreateVolumeSnapshotRequest, StoragePoolType, PoolTrimSchedule,
     // Storage pool file listing types
     ListStoragePoolFilesRequest, ListStoragePoolFilesResponse, StoragePoolFileEntry,
     // Volume listing types
@@ -56,11 +61,28 @@ use limiquantix_proto::{
     SyncTimeRequest, SyncTimeResponse,
     // CD-ROM media change
     ChangeMediaRequest,
+    // Network port QoS
+    NetworkPortQos, NetworkPortStatusResponse, UpdateNetworkPortQosRequest,
+    // Templates & clones
+    ConvertToTemplateRequest, TemplateInfoResponse, ListTemplatesResponse,
+    TemplateIdRequest, CloneVmRequest,
+    // Guest agent passthrough
+    ListGuestProcessesRequest, KillGuestProcessRequest, ListGuestServicesRequest,
+    ControlGuestServiceRequest, GetGuestHardwareInfoRequest, ListGuestSoftwareRequest,
+    ResetGuestPasswordRequest, ConfigureGuestNetworkRequest, GetGuestClipboardRequest,
+    SetGuestClipboardRequest, ResizeGuestDisplayRequest, ListGuestDirectoryRequest,
+    CreateGuestDirectoryRequest, DeleteGuestFileRequest, StatGuestFileRequest,
+    GrowGuestFilesystemsRequest, TrimGuestFilesystemsRequest, TrimGuestFilesystemsResponse, VolumeReclaim,
+    ListGuestUpdatesRequest, ApplyGuestUpdatesRequest, GetGuestPatchComplianceRequest,
+    ListGuestPatchComplianceRequest, ListGuestPatchComplianceResponse, GuestPatchCompliance,
 };
 // Agent types (from guest agent protocol - used by AgentClient)
 use limiquantix_proto::agent::TelemetryReport;
 
-use crate::agent_client::AgentClient;
+use crate::agent_client::{AgentClient, PortForwardRegistry, UnsupportedCapability};
+use crate::trim_scheduler::trim_scheduler;
+use crate::patch_compliance::patch_compliance;
+use limiquantix_proto::agent as guest;
 
 /// Cached guest agent info for a VM
 #[derive(Debug, Clone, Default)]
@@ -97,6 +119,14 @@ pub struct NodeDaemonServiceImpl {
     network_ports: Arc<RwLock<HashMap<String, NetworkPortConfig>>>,
     /// Trigger for immediate state watcher poll (after mutations)
     poll_trigger: Arc<RwLock<Option<mpsc::Sender<()>>>>,
+    /// Stored VM configurations (source for templates and clones)
+    vm_configs: VmConfigStore,
+    /// VM templates and linked clone tracking
+    templates: Arc<TemplateManager>,
+    /// MAC address registry for VM NICs
+    macs: Arc<MacAllocator>,
+    /// Port forwards into VMs over the agent channel
+    port_forwards: Arc<PortForwardRegistry>,
 }
 
 impl NodeDaemonServiceImpl {
@@ -122,18 +152,28 @@ impl NodeDaemonServiceImpl {
             }
         }
         
+        let storage = Arc::new(StorageManager::new());
+        let templates = Arc::new(TemplateManager::new(
+            limiquantix_hypervisor::template::DEFAULT_TEMPLATE_PATH,
+            storage.clone(),
+        ));
+        
         Self {
             node_id,
             hostname,
             management_ip,
             hypervisor,
             telemetry,
-            storage: Arc::new(StorageManager::new()),
+            storage,
             ovs_manager: OvsPortManager::new(),
             agent_manager: Arc::new(RwLock::new(HashMap::new())),
             agent_cache: Arc::new(RwLock::new(HashMap::new())),
             network_ports: Arc::new(RwLock::new(HashMap::new())),
             poll_trigger: Arc::new(RwLock::new(None)),
+            vm_configs: VmConfigStore::default(),
+            templates,
+            macs: Arc::new(MacAllocator::default()),
+            port_forwards: Arc::new(PortForwardRegistry::default()),
         }
     }
     
@@ -173,6 +213,41 @@ impl NodeDaemonServiceImpl {
         tracing::info!("Storage auto-detection complete");
     }
     
+    /// Start the background MAC conflict monitor.
+    ///
+    /// Scans the local domains for duplicate MACs (clones, imports, domains
+    /// defined by hand) and reports each one as a warning event.
+    pub fn start_mac_conflict_monitor(&self) {
+        let hypervisor = self.hypervisor.clone();
+        let macs = self.macs.clone();
+        
+        tokio::spawn(async move {
+            const SCAN_INTERVAL_SECS: u64 = 600;
+            
+            let mut interval = tokio::time::interval(Duration::from_secs(SCAN_INTERVAL_SECS));
+            // Report each duplicate once (again if it goes away and comes back)
+            let mut reported: std::collections::HashSet<MacConflict> = std::collections::HashSet::new();
+            
+            loop {
+                interval.tick().await;
+                
+                let conflicts = match scan_mac_conflicts(&hypervisor, &macs).await {
+                    Ok(conflicts) => conflicts,
+                    Err(e) => {
+                        warn!(error = %e, "MAC conflict scan failed");
+                        continue;
+                    }
+                };
+                
+                let current: std::collections::HashSet<MacConflict> = conflicts.into_iter().collect();
+                for conflict in current.difference(&reported) {
+                    report_mac_conflict(conflict);
+                }
+                reported = current;
+            }
+        });
+    }
+    
     /// Start the background agent connection manager.
     /// 
     /// This background task proactively maintains agent connections for running VMs:
@@ -826,6 +901,28 @@ impl NodeDaemonServiceImpl {
         self.ovs_manager.get_status().map_err(|e| e.to_string())
     }
     
+    /// List OVS port mirror sessions
+    pub fn list_mirrors_internal(&self) -> Result<Vec<limiquantix_hypervisor::MirrorSession>, String> {
+        self.ovs_manager.list_mirrors().map_err(|e| format!("{:#}", e))
+    }
+    
+    /// Mirror a port's traffic to an analyzer port
+    pub fn create_mirror_internal(
+        &self,
+        name: &str,
+        source_port: &str,
+        output_port: &str,
+        direction: limiquantix_hypervisor::MirrorDirection,
+    ) -> Result<limiquantix_hypervisor::MirrorSession, String> {
+        self.ovs_manager.create_mirror(name, source_port, output_port, direction)
+            .map_err(|e| format!("{:#}", e))
+    }
+    
+    /// Remove a port mirror session
+    pub fn delete_mirror_internal(&self, name: &str) -> Result<(), String> {
+        self.ovs_manager.delete_mirror(name).map_err(|e| format!("{:#}", e))
+    }
+    
     /// Configure a network port for a VM
     pub async fn configure_network_port_internal(
         &self,
@@ -836,6 +933,18 @@ impl NodeDaemonServiceImpl {
         // Configure the port in OVS
         let result = self.ovs_manager.configure_port(&config).map_err(|e| e.to_string())?;
         
+        // OVN enforces security groups as ACLs; other ports are filtered on the node
+        if config.ovn_port_name.is_empty() {
+            let port = crate::firewall::PortSecurity {
+                mac_address: config.mac_address.clone(),
+                ip_addresses: config.ip_addresses.clone(),
+                port_security_enabled: config.port_security_enabled,
+                security_group_ids: config.security_group_ids.clone(),
+            };
+            crate::firewall::firewall().bind_port(&config.vm_id, port).await
+                .map_err(|e| e.to_string())?;
+        }
+        
         // Store in cache for later reference
         {
             let mut ports = self.network_ports.write().await;
@@ -852,9 +961,11 @@ impl NodeDaemonServiceImpl {
         vm_id: &str,
     ) -> Result<(), String> {
         // Remove from cache
-        {
-            let mut ports = self.network_ports.write().await;
-            ports.remove(port_id);
+        let removed = self.network_ports.write().await.remove(port_id);
+        if let Some(config) = removed.filter(|c| c.ovn_port_name.is_empty()) {
+            if let Err(e) = crate::firewall::firewall().unbind_port(vm_id, &config.mac_address).await {
+                warn!(port_id = %port_id, error = %e, "Failed to remove port security rules");
+            }
         }
         
         // Delete from OVS
@@ -880,6 +991,186 @@ impl NodeDaemonServiceImpl {
         ports.iter().map(|(k, v)| (k.clone(), v.clone())).collect()
     }
     
+    /// MAC address registry for VM NICs
+    pub fn mac_allocator(&self) -> &Arc<MacAllocator> {
+        &self.macs
+    }
+    
+    /// Check the local domains for duplicate MACs now
+    pub async fn check_mac_conflicts(&self) -> Result<Vec<MacConflict>, String> {
+        scan_mac_conflicts(&self.hypervisor, &self.macs).await
+    }
+    
+    /// Give every NIC of a new VM a registered MAC, reporting collisions
+    async fn assign_macs(&self, config: &mut VmConfig) -> Result<(), Status> {
+        let conflicts = self.macs.assign(config).await
+            .map_err(|e| Status::resource_exhausted(format!("Failed to allocate MAC address: {:#}", e)))?;
+        for conflict in &conflicts {
+            report_mac_conflict(conflict);
+        }
+        Ok(())
+    }
+    
+    /// Change a network port's QoS, enforcing it right away if the VM is running.
+    /// The limits are also stored in the VM's domain definition so they are
+    /// reapplied whenever the VM starts.
+    pub async fn update_network_port_qos_internal(
+        &self,
+        port_id: &str,
+        qos: Option<NetworkPortQoS>,
+    ) -> Result<limiquantix_hypervisor::NetworkPortInfo, Status> {
+        let mut config = self.network_ports.read().await
+            .get(port_id)
+            .cloned()
+            .ok_or_else(|| Status::not_found(format!("Network port {} not found", port_id)))?;
+        config.qos = qos.filter(|q| !q.is_unlimited());
+        
+        // A VM defined later gets the limits from the port's interface XML
+        let defined = self.hypervisor.vm_exists(&config.vm_id).await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        if defined {
+            self.ovs_manager.persist_qos(&config.vm_id, &config.mac_address, config.qos.as_ref())
+                .map_err(|e| Status::internal(format!("Failed to store QoS in the VM definition: {:#}", e)))?;
+        }
+        
+        let configured = self.ovs_manager.configure_port(&config)
+            .map_err(|e| Status::internal(format!("Failed to apply QoS: {:#}", e)))?;
+        let status = self.ovs_manager.get_port_status(port_id, &config.ovn_port_name).ok().flatten();
+        
+        info!(
+            port_id = %port_id,
+            qos = ?config.qos,
+            live = configured.ovs_port_name.is_some(),
+            "Network port QoS updated"
+        );
+        self.network_ports.write().await.insert(port_id.to_string(), config);
+        
+        Ok(status.unwrap_or(configured))
+    }
+    
+    // =========================================================================
+    // OVA Import / Export
+    // =========================================================================
+    
+    /// Directory that holds VM disk folders for a pool (`<mount>/vms`).
+    ///
+    /// An empty pool ID uses the default path, like CreateVM.
+    pub async fn pool_vms_root(&self, pool_id: &str) -> Result<std::path::PathBuf, Status> {
+        if pool_id.is_empty() {
+            return Ok(std::path::PathBuf::from("/data/limiquantix/vms").join("vms"));
+        }
+        
+        let pool = self.storage.list_pools().await
+            .into_iter()
+            .find(|p| p.pool_id == pool_id)
+            .ok_or_else(|| Status::not_found(format!("Storage pool '{}' not found", pool_id)))?;
+        
+        let mount_path = pool.mount_path
+            .ok_or_else(|| Status::failed_precondition(format!(
+                "Storage pool '{}' has no mount path configured", pool_id
+            )))?;
+        
+        Ok(std::path::PathBuf::from(mount_path).join("vms"))
+    }
+    
+    /// Create a VM from an imported configuration whose disks already exist.
+    ///
+    /// On failure the imported disk folder is removed.
+    pub async fn create_imported_vm(&self, mut config: VmConfig, start: bool) -> Result<String, Status> {
+        self.assign_macs(&mut config).await?;
+        
+        let created_id = match self.hypervisor.create_vm(config.clone()).await {
+            Ok(id) => id,
+            Err(e) => {
+                error!(vm_id = %config.id, error = %e, "Failed to create imported VM");
+                let _ = self.macs.release_vm(&config.id).await;
+                if let Some(folder) = config.disks.first().and_then(|d| std::path::Path::new(&d.path).parent()) {
+                    let _ = std::fs::remove_dir_all(folder);
+                }
+                return Err(Status::internal(format!("Failed to create VM: {}", e)));
+            }
+        };
+        
+        if let Err(e) = self.vm_configs.save(&config) {
+            warn!(vm_id = %created_id, error = %e, "Failed to store VM config");
+        }
+        
+        if start {
+            if let Err(e) = self.hypervisor.start_vm(&created_id).await {
+                warn!(vm_id = %created_id, error = %e, "Imported VM created but failed to start");
+            }
+        }
+        
+        self.trigger_immediate_poll().await;
+        
+        info!(vm_id = %created_id, name = %config.name, "Imported VM created");
+        Ok(created_id)
+    }
+    
+    /// Load the stored configuration of a stopped VM (for export).
+    pub async fn stopped_vm_config(&self, vm_id: &str) -> Result<VmConfig, Status> {
+        self.require_stopped(vm_id).await?;
+        self.vm_config(vm_id).await
+    }
+    
+    /// Stored configuration of a VM. VMs defined before the config store
+    /// existed have theirs backfilled from the domain XML.
+    async fn vm_config(&self, vm_id: &str) -> Result<VmConfig, Status> {
+        match self.vm_configs.load(vm_id) {
+            Ok(config) => return Ok(config),
+            Err(HypervisorError::VmNotFound(_)) => {}
+            Err(e) => return Err(Status::internal(e.to_string())),
+        }
+        
+        let xml = domain_xml(vm_id).await
+            .map_err(|e| Status::failed_precondition(format!("No stored configuration for VM {}: {}", vm_id, e)))?;
+        let config = config_from_domain_xml(&xml)
+            .map_err(|e| Status::internal(format!("Failed to read domain XML of VM {}: {}", vm_id, e)))?;
+        
+        if let Err(e) = self.vm_configs.save(&config) {
+            warn!(vm_id = %vm_id, error = %e, "Failed to store backfilled VM config");
+        }
+        info!(vm_id = %vm_id, disks = config.disks.len(), nics = config.nics.len(), "Backfilled VM config from domain XML");
+        Ok(config)
+    }
+    
+    /// Re-read the devices of a VM into its stored configuration after a
+    /// device change. Failures are logged; the stored config is then stale
+    /// until the next change.
+    async fn refresh_vm_config(&self, vm_id: &str) {
+        let result = async {
+            let stored = self.vm_config(vm_id).await?;
+            let xml = domain_xml(vm_id).await.map_err(Status::unavailable)?;
+            let live = config_from_domain_xml(&xml).map_err(|e| Status::internal(e.to_string()))?;
+            self.vm_configs.save(&refresh_from_domain(&stored, live))
+                .map_err(|e| Status::internal(e.to_string()))
+        }.await;
+        
+        if let Err(e) = result {
+            warn!(vm_id = %vm_id, error = %e.message(), "Failed to refresh stored VM config");
+        }
+    }
+    
+    /// Record a disk's new size in the stored configuration.
+    async fn record_disk_size(&self, vm_id: &str, path: &str, size_bytes: u64) {
+        const GIB: u64 = 1024 * 1024 * 1024;
+        
+        let mut config = match self.vm_config(vm_id).await {
+            Ok(config) => config,
+            Err(e) => {
+                warn!(vm_id = %vm_id, error = %e.message(), "Failed to update stored disk size");
+                return;
+            }
+        };
+        let Some(disk) = config.disks.iter_mut().find(|d| d.path == path) else {
+            return;
+        };
+        disk.size_gib = size_bytes.div_ceil(GIB);
+        if let Err(e) = self.vm_configs.save(&config) {
+            warn!(vm_id = %vm_id, error = %e, "Failed to update stored disk size");
+        }
+    }
+    
     /// Get or create an agent client for a VM
     /// Returns Ok(()) if connected, Err(Status) if connection failed
     pub async fn get_agent_client(&self, vm_id: &str) -> Result<(), Status> {
@@ -987,6 +1278,338 @@ impl NodeDaemonServiceImpl {
         self.agent_manager.read().await
     }
     
+    /// Port forwards from node-local listeners into VMs
+    pub fn port_forwards(&self) -> &PortForwardRegistry {
+        &self.port_forwards
+    }
+    
+    /// Connect the VM's agent if needed and return a handle to it. The handle
+    /// shares the connection, so long agent calls don't hold the agent map
+    /// lock that every other agent operation needs.
+    async fn connected_agent(&self, vm_id: &str) -> Result<AgentClient, Status> {
+        self.get_agent_client(vm_id).await?;
+        self.agent_manager.read().await.get(vm_id)
+            .cloned()
+            .ok_or_else(|| Status::unavailable(format!("No agent connection for VM {}", vm_id)))
+    }
+    
+    /// Quiesce a VM through its guest agent before a disk-only snapshot.
+    /// Returns the quiesce token, or `None` if the VM has no agent and libvirt
+    /// should quiesce through qemu-ga instead.
+    async fn quiesce_for_snapshot(&self, vm_id: &str) -> Result<Option<String>, Status> {
+        let Ok(agent) = self.connected_agent(vm_id).await else {
+            return Ok(None);
+        };
+        
+        let resp = agent.quiesce_filesystems(Vec::new(), 0, true, false).await
+            .map_err(agent_status)?;
+        for app in &resp.applications {
+            info!(vm_id = %vm_id, provider = %app.provider, success = app.success, error = %app.error, "Application quiesced for snapshot");
+        }
+        if !resp.success {
+            return Err(Status::failed_precondition(format!("Guest quiesce failed: {}", resp.error)));
+        }
+        
+        Ok(Some(resp.quiesce_token))
+    }
+    
+    /// Thaw a VM quiesced by [`Self::quiesce_for_snapshot`]. The guest also
+    /// thaws on its own after the quiesce timeout, so failures are logged.
+    async fn thaw_after_snapshot(&self, vm_id: &str, token: String) {
+        let Some(agent) = self.agent_manager.read().await.get(vm_id).cloned() else {
+            warn!(vm_id = %vm_id, "Agent disconnected before thaw, guest will auto-thaw");
+            return;
+        };
+        
+        match agent.thaw_filesystems(Some(token), true).await {
+            Ok(resp) => {
+                if resp.success {
+                    info!(vm_id = %vm_id, frozen_duration_ms = resp.frozen_duration_ms, "Guest thawed after snapshot");
+                } else {
+                    warn!(vm_id = %vm_id, error = %resp.error, "Guest thaw after snapshot failed");
+                }
+                for app in resp.applications.iter().filter(|a| !a.success) {
+                    warn!(vm_id = %vm_id, provider = %app.provider, error = %app.error, "Application release after snapshot failed");
+                }
+            }
+            Err(e) => warn!(vm_id = %vm_id, error = %e, "Guest thaw after snapshot failed"),
+        }
+    }
+    
+    /// After a live disk resize, let the guest agent grow the partitions and
+    /// filesystems on the resized disk. The guest identifies that disk by its
+    /// new capacity in a dry run; only those mount points are grown. The disk
+    /// resize already succeeded, so failures here are only reported as events.
+    async fn grow_guest_filesystems_after_resize(&self, vm_id: &str, disk_size_bytes: u64) {
+        use crate::event_store::{emit_event, Event, EventCategory, EventLevel};
+        
+        let event = |level, message: String| {
+            emit_event(Event::new(level, EventCategory::Storage, message, "agent").with_resource(vm_id.to_string()));
+        };
+        
+        let agent = match self.connected_agent(vm_id).await {
+            Ok(agent) => agent,
+            Err(e) => {
+                info!(vm_id = %vm_id, error = %e.message(), "No guest agent to grow filesystems after disk resize");
+                event(EventLevel::Info, "Disk resized; the guest has no agent, so its partition and filesystem must be grown manually".to_string());
+                return;
+            }
+        };
+        
+        // The hypervisor rounds the size up to whole sectors
+        let sectors = disk_size_bytes.div_ceil(512);
+        let plan = agent.grow_filesystems(guest::GrowFilesystemsRequest { dry_run: true, ..Default::default() }).await;
+        let mount_points: Vec<String> = match plan {
+            Ok(plan) => plan.filesystems.into_iter()
+                .filter(|fs| fs.disk_size_bytes.iter().any(|size| size.div_ceil(512) == sectors))
+                .map(|fs| fs.mount_point)
+                .collect(),
+            Err(e) => {
+                warn!(vm_id = %vm_id, error = %e, "Failed to find guest filesystems on the resized disk");
+                event(EventLevel::Warning, format!("Disk resized but the guest filesystems were not grown: {}", e));
+                return;
+            }
+        };
+        if mount_points.is_empty() {
+            info!(vm_id = %vm_id, "No guest filesystem found on the resized disk");
+            event(EventLevel::Info, "Disk resized; no mounted guest filesystem was found on it to grow".to_string());
+            return;
+        }
+        
+        match agent.grow_filesystems(guest::GrowFilesystemsRequest { mount_points, dry_run: false }).await {
+            Ok(resp) => {
+                for fs in &resp.filesystems {
+                    if !fs.error.is_empty() {
+                        warn!(vm_id = %vm_id, mount_point = %fs.mount_point, error = %fs.error, "Failed to grow guest filesystem");
+                        event(EventLevel::Warning, format!("Failed to grow guest filesystem {}: {}", fs.mount_point, fs.error));
+                    } else if fs.grown {
+                        info!(vm_id = %vm_id, mount_point = %fs.mount_point, size = fs.size_after_bytes, "Grew guest filesystem");
+                        event(EventLevel::Info, format!(
+                            "Guest filesystem {} grown from {} to {} bytes",
+                            fs.mount_point, fs.size_before_bytes, fs.size_after_bytes
+                        ));
+                    }
+                }
+            }
+            Err(e) => {
+                warn!(vm_id = %vm_id, error = %e, "Failed to grow guest filesystems after disk resize");
+                event(EventLevel::Warning, format!("Disk resized but the guest filesystems were not grown: {}", e));
+            }
+        }
+    }
+    
+    /// Volumes in any known pool whose path is one of `paths`, with the ID
+    /// of the pool holding them.
+    async fn volumes_by_path(&self, paths: &[String]) -> Vec<(String, limiquantix_hypervisor::storage::VolumeInfo)> {
+        let mut found = Vec::new();
+        for pool in self.storage.list_pools().await {
+            match self.storage.list_volumes(&pool.pool_id).await {
+                Ok(volumes) => found.extend(
+                    volumes.into_iter()
+                        .filter(|v| paths.contains(&v.path))
+                        .map(|v| (pool.pool_id.clone(), v)),
+                ),
+                Err(e) => debug!(pool_id = %pool.pool_id, error = %e, "Failed to list pool volumes"),
+            }
+        }
+        found
+    }
+    
+    /// Trim a VM's filesystems through its guest agent and measure how much
+    /// allocation its volumes gave back to their pools. Refused when no disk
+    /// passes discards to its image (`discard='unmap'`): the guest's discards
+    /// would be dropped and nothing reclaimed.
+    async fn trim_vm_filesystems(
+        &self,
+        vm_id: &str,
+        request: guest::TrimFilesystemsRequest,
+    ) -> Result<TrimGuestFilesystemsResponse, Status> {
+        let status = self.hypervisor.get_vm_status(vm_id).await
+            .map_err(|e| Status::not_found(e.to_string()))?;
+        if !status.disks.iter().any(|d| d.discard == DiskDiscard::Unmap) {
+            return Err(Status::failed_precondition(
+                "No disk of the VM has discard enabled (discard='unmap'); trimming would not reclaim space",
+            ));
+        }
+        let disks: Vec<String> = status.disks.into_iter().map(|d| d.path).collect();
+        let before = self.volumes_by_path(&disks).await;
+        
+        let guest = self.connected_agent(vm_id).await?
+            .trim_filesystems(request).await.map_err(agent_status)?;
+        
+        let volumes: Vec<VolumeReclaim> = self.volumes_by_path(&disks).await.into_iter()
+            .filter_map(|(pool_id, volume)| {
+                let (_, previous) = before.iter().find(|(_, v)| v.path == volume.path)?;
+                Some(VolumeReclaim {
+                    allocation_before_bytes: previous.allocation,
+                    reclaimed_bytes: previous.allocation.saturating_sub(volume.allocation),
+                    volume: Some(VolumeInfoResponse {
+                        volume_id: volume.name,
+                        pool_id,
+                        size_bytes: volume.capacity,
+                        format: volume.format.unwrap_or_else(|| "qcow2".to_string()),
+                        path: volume.path,
+                        attached_to: vm_id.to_string(),
+                        allocation_bytes: volume.allocation,
+                    }),
+                })
+            })
+            .collect();
+        let reclaimed_bytes = volumes.iter().map(|v| v.reclaimed_bytes).sum();
+        
+        info!(vm_id = %vm_id, volumes = volumes.len(), reclaimed_bytes, "Guest filesystems trimmed");
+        Ok(TrimGuestFilesystemsResponse {
+            guest: Some(guest),
+            volumes,
+            reclaimed_bytes,
+        })
+    }
+    
+    /// List a guest's pending updates. Full listings are recorded for the
+    /// patch compliance summary.
+    async fn scan_guest_updates(&self, vm_id: &str, request: guest::ListUpdatesRequest) -> Result<guest::ListUpdatesResponse, Status> {
+        let security_only = request.security_only;
+        let resp = self.connected_agent(vm_id).await?
+            .list_updates(request).await.map_err(agent_status)?;
+        
+        // A security-only listing says nothing about the other updates
+        if !security_only {
+            patch_compliance().record_scan(vm_id, chrono::Utc::now(), &resp).await;
+        }
+        Ok(resp)
+    }
+    
+    /// Patch compliance of a VM. Guests with a connected agent are scanned
+    /// when asked to or when their last scan is missing or stale; the reboot
+    /// flag comes from their latest telemetry.
+    async fn guest_patch_compliance(&self, vm_id: &str, vm_name: &str, rescan: bool) -> GuestPatchCompliance {
+        let connected = self.agent_manager.read().await.get(vm_id).is_some_and(|c| c.is_connected());
+        
+        let mut error = String::new();
+        if connected && (rescan || patch_compliance().get(vm_id).await.needs_scan(chrono::Utc::now())) {
+            if let Err(e) = self.scan_guest_updates(vm_id, guest::ListUpdatesRequest::default()).await {
+                debug!(vm_id = %vm_id, error = %e.message(), "Patch compliance scan failed");
+                error = e.message().to_string();
+            }
+        }
+        
+        let telemetry_reboot = if connected {
+            self.agent_cache.read().await.get(vm_id)
+                .and_then(|c| c.last_telemetry.as_ref())
+                .map(|t| t.reboot_required)
+        } else {
+            None
+        };
+        
+        let mut compliance = patch_compliance().get(vm_id).await.to_proto(telemetry_reboot);
+        compliance.vm_name = vm_name.to_string();
+        compliance.agent_connected = connected;
+        compliance.error = error;
+        compliance
+    }
+    
+    /// Start the background trim scheduler.
+    /// 
+    /// Pools with an enabled trim schedule (see `crate::trim_scheduler`) get
+    /// the filesystems of their running VMs trimmed when the schedule is due.
+    pub fn start_trim_scheduler(self: &Arc<Self>) {
+        let service = self.clone();
+        
+        tokio::spawn(async move {
+            const CHECK_INTERVAL_SECS: u64 = 900;
+            
+            let mut interval = tokio::time::interval(Duration::from_secs(CHECK_INTERVAL_SECS));
+            
+            loop {
+                interval.tick().await;
+                
+                for pool_id in trim_scheduler().due(chrono::Utc::now()).await {
+                    let reclaimed = service.trim_pool(&pool_id).await;
+                    trim_scheduler().record_run(&pool_id, chrono::Utc::now(), reclaimed).await;
+                }
+            }
+        });
+    }
+    
+    /// Trim every running VM with a disk in the pool. VMs whose disks in the
+    /// pool don't pass discards are skipped. Returns the allocation the pool
+    /// got back.
+    async fn trim_pool(&self, pool_id: &str) -> u64 {
+        use crate::event_store::{emit_event, Event, EventCategory, EventLevel};
+        
+        let paths: Vec<String> = match self.storage.list_volumes(pool_id).await {
+            Ok(volumes) => volumes.into_iter().map(|v| v.path).collect(),
+            Err(e) => {
+                warn!(pool_id = %pool_id, error = %e, "Scheduled trim: failed to list pool volumes");
+                return 0;
+            }
+        };
+        let vms = match self.hypervisor.list_vms().await {
+            Ok(vms) => vms,
+            Err(e) => {
+                warn!(pool_id = %pool_id, error = %e, "Scheduled trim: failed to list VMs");
+                return 0;
+            }
+        };
+        
+        let mut reclaimed = 0;
+        let mut trimmed = 0;
+        let mut skipped = Vec::new();
+        let mut no_discard = Vec::new();
+        for vm in vms.into_iter().filter(|vm| vm.state == VmState::Running) {
+            let Ok(status) = self.hypervisor.get_vm_status(&vm.id).await else {
+                continue;
+            };
+            let pool_disks: Vec<_> = status.disks.iter().filter(|d| paths.contains(&d.path)).collect();
+            if pool_disks.is_empty() {
+                continue;
+            }
+            if !pool_disks.iter().any(|d| d.discard == DiskDiscard::Unmap) {
+                debug!(pool_id = %pool_id, vm_id = %vm.id, "Scheduled trim: skipping VM without discard on its disks");
+                no_discard.push(vm.name);
+                continue;
+            }
+            
+            match self.trim_vm_filesystems(&vm.id, guest::TrimFilesystemsRequest::default()).await {
+                Ok(resp) => {
+                    trimmed += 1;
+                    reclaimed += resp.volumes.iter()
+                        .filter(|v| v.volume.as_ref().is_some_and(|vol| vol.pool_id == pool_id))
+                        .map(|v| v.reclaimed_bytes)
+                        .sum::<u64>();
+                }
+                Err(e) => {
+                    debug!(pool_id = %pool_id, vm_id = %vm.id, error = %e.message(), "Scheduled trim: skipping VM");
+                    skipped.push(vm.name);
+                }
+            }
+        }
+        
+        info!(
+            pool_id = %pool_id,
+            vms = trimmed,
+            skipped = skipped.len(),
+            no_discard = no_discard.len(),
+            reclaimed_bytes = reclaimed,
+            "Scheduled trim finished"
+        );
+        let mut message = format!("Scheduled trim of pool {}: {} VMs trimmed, {} bytes reclaimed", pool_id, trimmed, reclaimed);
+        if !skipped.is_empty() {
+            message.push_str(&format!("; not trimmed (no agent or trim failed): {}", skipped.join(", ")));
+        }
+        if !no_discard.is_empty() {
+            message.push_str(&format!("; not trimmed (discard disabled on their disks): {}", no_discard.join(", ")));
+        }
+        emit_event(Event::new(
+            if skipped.is_empty() && no_discard.is_empty() { EventLevel::Info } else { EventLevel::Warning },
+            EventCategory::Storage,
+            message,
+            "storage",
+        ).with_resource(pool_id.to_string()));
+        
+        reclaimed
+    }
+    
     /// Update cached agent info from telemetry
     async fn update_agent_cache(&self, vm_id: &str, telemetry: &TelemetryReport) {
         let mut cache = self.agent_cache.write().await;
@@ -1118,6 +1741,7 @@ impl NodeDaemonServiceImpl {
                     "file_write".to_string(),
                     "shutdown".to_string(),
                 ],
+                reboot_required: info.last_telemetry.as_ref().is_some_and(|t| t.reboot_required),
                 last_seen: info.last_seen.map(|instant| {
                     let duration = instant.elapsed();
                     let now = std::time::SystemTime::now();
@@ -1172,6 +1796,63 @@ impl NodeDaemonServiceImpl {
         }
     }
     
+    fn convert_disk_discard(discard: i32) -> DiskDiscard {
+        match discard {
+            1 => DiskDiscard::Unmap,
+            _ => DiskDiscard::Ignore,
+        }
+    }
+    
+    fn convert_disk_detect_zeroes(detect_zeroes: i32) -> DiskDetectZeroes {
+        match detect_zeroes {
+            1 => DiskDetectZeroes::On,
+            2 => DiskDetectZeroes::Unmap,
+            _ => DiskDetectZeroes::Off,
+        }
+    }
+    
+    /// Map template/clone errors to gRPC status codes.
+    fn template_error_status(e: HypervisorError) -> Status {
+        match e {
+            HypervisorError::InvalidState(msg) => Status::failed_precondition(msg),
+            HypervisorError::InvalidConfig(msg) => Status::invalid_argument(msg),
+            HypervisorError::VmNotFound(msg) => Status::not_found(msg),
+            e => Status::internal(e.to_string()),
+        }
+    }
+    
+    fn template_to_response(template: TemplateInfo) -> TemplateInfoResponse {
+        TemplateInfoResponse {
+            template_id: template.id,
+            name: template.name,
+            description: template.description,
+            source_vm_id: template.source_vm_id,
+            created_at: Some(prost_types::Timestamp {
+                seconds: template.created_at.timestamp(),
+                nanos: template.created_at.timestamp_subsec_nanos() as i32,
+            }),
+            vcpus: template.config.cpu.total_vcpus(),
+            memory_mib: template.config.memory.size_mib,
+            disk_count: template.disks.len() as u32,
+            linked_clone_vm_ids: template.linked_clones.into_iter().map(|c| c.vm_id).collect(),
+            generalized: template.generalized,
+        }
+    }
+    
+    /// Fail unless the VM is stopped (templates and clones need consistent disks).
+    async fn require_stopped(&self, vm_id: &str) -> Result<(), Status> {
+        let status = self.hypervisor.get_vm_status(vm_id).await
+            .map_err(|e| Status::not_found(e.to_string()))?;
+        
+        if status.state != VmState::Stopped {
+            return Err(Status::failed_precondition(format!(
+                "VM must be stopped (current state: {:?})", status.state
+            )));
+        }
+        
+        Ok(())
+    }
+    
     fn convert_disk_format(format: i32) -> DiskFormat {
         match format {
             0 => DiskFormat::Qcow2,
@@ -1363,6 +2044,8 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
                 format,
                 readonly: disk_spec.readonly,
                 bootable: disk_spec.bootable,
+                discard: Self::convert_disk_discard(disk_spec.discard),
+                detect_zeroes: Self::convert_disk_detect_zeroes(disk_spec.detect_zeroes),
                 backing_file: backing_file.clone(),
                 ..Default::default()
             };
@@ -1942,10 +2625,18 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
             "Creating VM in hypervisor"
         );
         
+        self.assign_macs(&mut config).await?;
+        let stored_config = config.clone();
+        
         match self.hypervisor.create_vm(config).await {
             Ok(created_id) => {
                 info!(vm_id = %created_id, "VM created successfully in libvirt");
                 
+                // Keep the config for templates/clones (non-fatal)
+                if let Err(e) = self.vm_configs.save(&stored_config) {
+                    warn!(vm_id = %created_id, error = %e, "Failed to store VM config");
+                }
+                
                 // Trigger immediate state watcher poll to push update to control plane
                 self.trigger_immediate_poll().await;
                 
@@ -1957,6 +2648,7 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
             }
             Err(e) => {
                 error!(vm_id = %vm_uuid, error = %e, "Failed to create VM in hypervisor");
+                let _ = self.macs.release_vm(&stored_config.id).await;
                 Err(Status::internal(format!("Failed to create VM: {}", e)))
             }
         }
@@ -1982,6 +2674,19 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
         self.hypervisor.start_vm(vm_id).await
             .map_err(|e| Status::internal(e.to_string()))?;
         
+        // The VM has new tap devices; filter them before the guest is up and
+        // never leave a VM with security groups running unfiltered
+        if let Err(e) = crate::firewall::firewall().sync_vm(vm_id).await {
+            error!(vm_id = %vm_id, error = %e, "Failed to apply security group rules, stopping VM");
+            if let Err(stop) = self.hypervisor.force_stop_vm(vm_id).await {
+                error!(vm_id = %vm_id, error = %stop, "Failed to stop unfiltered VM");
+            }
+            crate::firewall::firewall().sync_logged().await;
+            return Err(Status::failed_precondition(format!(
+                "VM not started: failed to apply security group rules: {}", e
+            )));
+        }
+        
         // Trigger immediate state watcher poll to push update to control plane
         self.trigger_immediate_poll().await;
         
@@ -2085,6 +2790,16 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
         self.hypervisor.delete_vm(vm_id).await
             .map_err(|e| Status::internal(e.to_string()))?;
         
+        self.vm_configs.remove(vm_id);
+        self.templates.forget_clone(vm_id).await;
+        if let Err(e) = self.macs.release_vm(vm_id).await {
+            warn!(vm_id = %vm_id, error = %e, "Failed to release MAC addresses");
+        }
+        crate::firewall::firewall().remove_vm(vm_id).await;
+        crate::capture::captures().remove_vm(vm_id).await;
+        self.port_forwards.remove_port_forwards(vm_id).await;
+        patch_compliance().remove(vm_id).await;
+        
         // Legacy cleanup: Also check the old default VM directory (for backwards compatibility)
         // New VMs are stored in datastore paths like /var/lib/limiquantix/mnt/nfs-{pool}/vms/{name}_{uuid}/
         // but older VMs might still be in /var/lib/limiquantix/vms/{vm_id}/
@@ -2121,6 +2836,14 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
             let _ = self.get_agent_client(vm_id).await;
         }
         
+        // Prefer what the agent reports; fall back to our own DHCP leases
+        let mut ip_addresses = guest_agent.as_ref()
+            .map(|a| a.ip_addresses.clone())
+            .unwrap_or_default();
+        if ip_addresses.is_empty() && status.state == VmState::Running {
+            ip_addresses = crate::dhcp::dhcp().addresses_for_vm(vm_id).await;
+        }
+        
         Ok(Response::new(VmStatusResponse {
             vm_id: status.id,
             name: status.name,
@@ -2136,6 +2859,7 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
             memory_total_bytes: status.memory_max_bytes,
             started_at: None,
             guest_agent,
+            ip_addresses,
             disks: status.disks.into_iter().map(|d| DiskSpec {
                 id: d.id,
                 path: d.path,
@@ -2157,6 +2881,15 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
                 throughput_mbps: 0,
                 backing_file: d.backing_file.unwrap_or_default(),
                 pool_id: String::new(), // Pool ID not tracked for existing VMs
+                discard: match d.discard {
+                    limiquantix_hypervisor::DiskDiscard::Ignore => limiquantix_proto::DiskDiscard::Ignore.into(),
+                    limiquantix_hypervisor::DiskDiscard::Unmap => limiquantix_proto::DiskDiscard::Unmap.into(),
+                },
+                detect_zeroes: match d.detect_zeroes {
+                    limiquantix_hypervisor::DiskDetectZeroes::Off => limiquantix_proto::DiskDetectZeroes::Off.into(),
+                    limiquantix_hypervisor::DiskDetectZeroes::On => limiquantix_proto::DiskDetectZeroes::On.into(),
+                    limiquantix_hypervisor::DiskDetectZeroes::Unmap => limiquantix_proto::DiskDetectZeroes::Unmap.into(),
+                },
             }).collect(),
         }))
     }
@@ -2169,8 +2902,21 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
         let vms = self.hypervisor.list_vms().await
             .map_err(|e| Status::internal(e.to_string()))?;
         
-        let responses: Vec<VmStatusResponse> = vms.into_iter().map(|vm| {
-            VmStatusResponse {
+        // One lease snapshot for all VMs; NIC MACs come from the stored configs
+        let leases = crate::dhcp::dhcp().addresses_by_mac().await;
+        
+        let mut responses = Vec::with_capacity(vms.len());
+        for vm in vms {
+            let ip_addresses = match self.vm_configs.load(&vm.id) {
+                Ok(config) if vm.state == VmState::Running && !leases.is_empty() => config.nics.iter()
+                    .filter_map(|nic| nic.mac_address.as_deref())
+                    .filter_map(|mac| leases.get(&mac.to_lowercase()))
+                    .flatten()
+                    .cloned()
+                    .collect(),
+                _ => Vec::new(),
+            };
+            responses.push(VmStatusResponse {
                 vm_id: vm.id,
                 name: vm.name,
                 state: Self::map_vm_state(vm.state),
@@ -2179,9 +2925,10 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
                 memory_total_bytes: 0,
                 started_at: None,
                 guest_agent: None,
+                ip_addresses,
                 disks: vec![],
-            }
-        }).collect();
+            });
+        }
         
         debug!(count = responses.len(), "Listed VMs");
         
@@ -2216,7 +2963,10 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
             host,
             port: console.port as u32,
             password: console.password.unwrap_or_default(),
-            websocket_path: console.websocket_path.unwrap_or_default(),
+            // Without a backend-provided path, advertise the node's own proxy
+            // (connect with a ticket from POST .../console/ticket)
+            websocket_path: console.websocket_path
+                .unwrap_or_else(|| format!("/api/v1/vms/{}/console/ws", vm_id)),
         }))
     }
     
@@ -2325,6 +3075,15 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
         
         let req = request.into_inner();
         
+        // Disk-only snapshots of VMs with our agent quiesce through it, so the
+        // guest's database providers run too; otherwise libvirt quiesces
+        // through qemu-ga (memory snapshots must not capture a frozen guest)
+        let quiesce_token = if req.quiesce && req.disk_only {
+            self.quiesce_for_snapshot(&req.vm_id).await?
+        } else {
+            None
+        };
+        
         // Convert proto request to snapshot options
         // disk_only is the inverse of include_memory
         let options = limiquantix_hypervisor::types::CreateSnapshotOptions {
@@ -2332,11 +3091,14 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
             description: req.description,
             include_memory: !req.disk_only,
             live: !req.disk_only, // Use live mode when including memory
-            quiesce: req.quiesce,
+            quiesce: req.quiesce && quiesce_token.is_none(),
         };
         
-        let snapshot = self.hypervisor.create_snapshot(&req.vm_id, &options).await
-            .map_err(|e| Status::internal(e.to_string()))?;
+        let result = self.hypervisor.create_snapshot(&req.vm_id, &options).await;
+        if let Some(token) = quiesce_token {
+            self.thaw_after_snapshot(&req.vm_id, token).await;
+        }
+        let snapshot = result.map_err(|e| Status::internal(e.to_string()))?;
         
         info!(
             snapshot_id = %snapshot.id,
@@ -2417,13 +3179,134 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
         Ok(Response::new(ListSnapshotsResponse { snapshots: responses }))
     }
     
-    type StreamMetricsStream = Pin<Box<dyn Stream<Item = Result<NodeMetrics, Status>> + Send>>;
-    
-    #[instrument(skip(self, request))]
-    async fn stream_metrics(
+    #[instrument(skip(self, request), fields(vm_id = %request.get_ref().vm_id))]
+    async fn convert_to_template(
         &self,
-        request: Request<StreamMetricsRequest>,
-    ) -> Result<Response<Self::StreamMetricsStream>, Status> {
+        request: Request<ConvertToTemplateRequest>,
+    ) -> Result<Response<TemplateInfoResponse>, Status> {
+        let req = request.into_inner();
+        
+        self.require_stopped(&req.vm_id).await?;
+        
+        let config = self.vm_config(&req.vm_id).await?;
+        
+        let name = if req.name.is_empty() { config.name.clone() } else { req.name };
+        
+        let template = self.templates.create_template(&config, &name, &req.description).await
+            .map_err(Self::template_error_status)?;
+        
+        info!(template_id = %template.id, name = %template.name, "VM converted to template");
+        Ok(Response::new(Self::template_to_response(template)))
+    }
+    
+    async fn list_templates(
+        &self,
+        _request: Request<()>,
+    ) -> Result<Response<ListTemplatesResponse>, Status> {
+        let templates = self.templates.list_templates().await
+            .into_iter()
+            .map(Self::template_to_response)
+            .collect();
+        
+        Ok(Response::new(ListTemplatesResponse { templates }))
+    }
+    
+    #[instrument(skip(self, request), fields(template_id = %request.get_ref().template_id))]
+    async fn delete_template(
+        &self,
+        request: Request<TemplateIdRequest>,
+    ) -> Result<Response<()>, Status> {
+        let template_id = request.into_inner().template_id;
+        
+        self.templates.delete_template(&template_id).await
+            .map_err(Self::template_error_status)?;
+        
+        Ok(Response::new(()))
+    }
+    
+    #[instrument(skip(self, request), fields(name = %request.get_ref().name))]
+    async fn clone_vm(
+        &self,
+        request: Request<CloneVmRequest>,
+    ) -> Result<Response<CreateVmOnNodeResponse>, Status> {
+        let req = request.into_inner();
+        
+        if req.name.is_empty() {
+            return Err(Status::invalid_argument("Clone name is required"));
+        }
+        
+        let vm_id = if req.vm_id.is_empty() {
+            None
+        } else {
+            Some(uuid::Uuid::parse_str(&req.vm_id)
+                .map_err(|_| Status::invalid_argument(format!("VM ID must be a valid UUID, got: {}", req.vm_id)))?
+                .to_string())
+        };
+        
+        let options = CloneOptions {
+            name: req.name.clone(),
+            clone_type: match req.clone_type {
+                1 => CloneType::Linked,
+                _ => CloneType::Full,
+            },
+            vm_id,
+        };
+        
+        let config = match (req.template_id.is_empty(), req.source_vm_id.is_empty()) {
+            (false, true) => {
+                self.templates.clone_from_template(&req.template_id, &options, &self.macs).await
+                    .map_err(Self::template_error_status)?
+            }
+            (true, false) => {
+                self.require_stopped(&req.source_vm_id).await?;
+                let source = self.vm_config(&req.source_vm_id).await?;
+                self.templates.clone_from_vm(&source, &options, &self.macs).await
+                    .map_err(Self::template_error_status)?
+            }
+            _ => {
+                return Err(Status::invalid_argument(
+                    "Exactly one of template_id or source_vm_id must be set"
+                ));
+            }
+        };
+        
+        let created_id = match self.hypervisor.create_vm(config.clone()).await {
+            Ok(id) => id,
+            Err(e) => {
+                error!(vm_id = %config.id, error = %e, "Failed to create cloned VM");
+                let _ = self.macs.release_vm(&config.id).await;
+                self.templates.discard_clone(&config).await;
+                return Err(Status::internal(format!("Failed to create VM: {}", e)));
+            }
+        };
+        
+        if let Err(e) = self.vm_configs.save(&config) {
+            warn!(vm_id = %created_id, error = %e, "Failed to store VM config");
+        }
+        
+        if req.start_on_create {
+            if let Err(e) = self.hypervisor.start_vm(&created_id).await {
+                warn!(vm_id = %created_id, error = %e, "Cloned VM created but failed to start");
+            }
+        }
+        
+        self.trigger_immediate_poll().await;
+        
+        info!(vm_id = %created_id, clone_type = ?options.clone_type, "VM cloned");
+        Ok(Response::new(CreateVmOnNodeResponse {
+            vm_id: created_id,
+            created: true,
+            message: "VM cloned successfully".to_string(),
+        }))
+    }
+    
+    type StreamMetricsStream = Pin<Box<dyn Stream<Item = Result<NodeMetrics, Status>> + Send>>;
+    
+    #[instrument(skip(self, request))]
+    async fn stream_metrics(
+        &self,
+        request: Request<StreamMetricsRequest>,
+    ) -> Result<Response<Self::StreamMetricsStream>, Status> {
         let interval_secs = request.into_inner().interval_seconds.max(1);
         
         let (tx, rx) = mpsc::channel(10);
@@ -2574,11 +3457,7 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
         info!(vm_id = %req.vm_id, command = %req.command, "Executing command in guest");
         
         // Ensure agent is connected
-        self.get_agent_client(&req.vm_id).await?;
-        
-        let agents = self.agent_manager.read().await;
-        let client = agents.get(&req.vm_id)
-            .ok_or_else(|| Status::unavailable("Agent not connected"))?;
+        let client = self.connected_agent(&req.vm_id).await?;
         
         // Execute the command
         let timeout = if req.timeout_seconds > 0 { req.timeout_seconds } else { 60 };
@@ -2625,11 +3504,7 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
         info!(vm_id = %req.vm_id, path = %req.path, "Reading file from guest");
         
         // Ensure agent is connected
-        self.get_agent_client(&req.vm_id).await?;
-        
-        let agents = self.agent_manager.read().await;
-        let client = agents.get(&req.vm_id)
-            .ok_or_else(|| Status::unavailable("Agent not connected"))?;
+        let client = self.connected_agent(&req.vm_id).await?;
         
         match client.read_file(&req.path).await {
             Ok(data) => {
@@ -2665,11 +3540,7 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
         info!(vm_id = %req.vm_id, path = %req.path, size = req.data.len(), "Writing file to guest");
         
         // Ensure agent is connected
-        self.get_agent_client(&req.vm_id).await?;
-        
-        let agents = self.agent_manager.read().await;
-        let client = agents.get(&req.vm_id)
-            .ok_or_else(|| Status::unavailable("Agent not connected"))?;
+        let client = self.connected_agent(&req.vm_id).await?;
         
         match client.write_file(&req.path, &req.data, req.mode).await {
             Ok(()) => {
@@ -2700,11 +3571,7 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
         info!(vm_id = %req.vm_id, reboot = req.reboot, "Requesting guest shutdown");
         
         // Ensure agent is connected
-        self.get_agent_client(&req.vm_id).await?;
-        
-        let agents = self.agent_manager.read().await;
-        let client = agents.get(&req.vm_id)
-            .ok_or_else(|| Status::unavailable("Agent not connected"))?;
+        let client = self.connected_agent(&req.vm_id).await?;
         
         match client.shutdown(req.reboot).await {
             Ok(response) => {
@@ -2872,6 +3739,7 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
         
         self.storage.destroy_pool(&req.pool_id).await
             .map_err(|e| Status::internal(format!("Failed to destroy pool: {}", e)))?;
+        trim_scheduler().remove(&req.pool_id).await;
         
         Ok(Response::new(()))
     }
@@ -3100,6 +3968,7 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
                 format: v.format.unwrap_or_else(|| "qcow2".to_string()),
                 path: v.path,
                 attached_to: String::new(), // TODO: Track attachments
+                allocation_bytes: v.allocation,
             }
         }).collect();
         
@@ -3136,6 +4005,7 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
             format: "qcow2".to_string(),
             path: attach_info.path,
             attached_to: String::new(),
+            allocation_bytes: 0,
         }))
     }
     
@@ -3159,11 +4029,47 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
         request: Request<ResizeVolumeRequest>,
     ) -> Result<Response<()>, Status> {
         let req = request.into_inner();
-        info!(pool_id = %req.pool_id, volume_id = %req.volume_id, new_size = req.new_size_bytes, "Resizing volume");
+        info!(pool_id = %req.pool_id, volume_id = %req.volume_id, new_size = req.new_size_bytes, vm_id = %req.vm_id, "Resizing volume");
+        
+        // A running VM holds the image open, so it has to be resized through
+        // the hypervisor, which also tells the guest about the new capacity.
+        let running = !req.vm_id.is_empty()
+            && self.hypervisor.get_vm_status(&req.vm_id).await
+                .map(|status| status.state == VmState::Running)
+                .unwrap_or(false);
+        
+        if !running {
+            self.storage.resize_volume(&req.pool_id, &req.volume_id, req.new_size_bytes).await
+                .map_err(|e| Status::internal(format!("Failed to resize volume: {}", e)))?;
+            if !req.vm_id.is_empty() {
+                if let Ok(attach_info) = self.storage.get_attach_info(&req.pool_id, &req.volume_id).await {
+                    self.record_disk_size(&req.vm_id, &attach_info.path, req.new_size_bytes).await;
+                }
+            }
+            return Ok(Response::new(()));
+        }
         
-        self.storage.resize_volume(&req.pool_id, &req.volume_id, req.new_size_bytes).await
-            .map_err(|e| Status::internal(format!("Failed to resize volume: {}", e)))?;
+        let attach_info = self.storage.get_attach_info(&req.pool_id, &req.volume_id).await
+            .map_err(|e| Status::internal(format!("Failed to get volume info: {}", e)))?;
         
+        // QEMU truncates the image on a smaller size, destroying guest data
+        let current_size = self.volumes_by_path(std::slice::from_ref(&attach_info.path)).await
+            .into_iter()
+            .map(|(_, volume)| volume.capacity)
+            .next()
+            .ok_or_else(|| Status::internal(format!("Failed to determine the size of {}", attach_info.path)))?;
+        if req.new_size_bytes < current_size {
+            return Err(Status::invalid_argument(format!(
+                "Cannot shrink a disk in use by a running VM ({} to {} bytes)",
+                current_size, req.new_size_bytes
+            )));
+        }
+        
+        self.hypervisor.resize_disk(&req.vm_id, &attach_info.path, req.new_size_bytes).await
+            .map_err(|e| Status::internal(format!("Failed to resize disk: {}", e)))?;
+        self.record_disk_size(&req.vm_id, &attach_info.path, req.new_size_bytes).await;
+        
+        self.grow_guest_filesystems_after_resize(&req.vm_id, req.new_size_bytes).await;
         Ok(Response::new(()))
     }
     
@@ -3212,6 +4118,32 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
         Ok(Response::new(()))
     }
     
+    #[instrument(skip(self, request), fields(pool_id = %request.get_ref().pool_id))]
+    async fn get_pool_trim_schedule(
+        &self,
+        request: Request<StoragePoolIdRequest>,
+    ) -> Result<Response<PoolTrimSchedule>, Status> {
+        let req = request.into_inner();
+        Ok(Response::new(trim_scheduler().get(&req.pool_id).await.to_proto()))
+    }
+    
+    #[instrument(skip(self, request), fields(pool_id = %request.get_ref().pool_id))]
+    async fn set_pool_trim_schedule(
+        &self,
+        request: Request<PoolTrimSchedule>,
+    ) -> Result<Response<PoolTrimSchedule>, Status> {
+        let req = request.into_inner();
+        if req.pool_id.is_empty() {
+            return Err(Status::invalid_argument("pool_id is required"));
+        }
+        self.storage.get_pool_info_or_discover(&req.pool_id).await
+            .map_err(|e| Status::not_found(format!("Pool not found: {}", e)))?;
+        
+        info!(pool_id = %req.pool_id, enabled = req.enabled, interval_hours = req.interval_hours, "Setting pool trim schedule");
+        let schedule = trim_scheduler().set(&req.pool_id, req.enabled, req.interval_hours).await;
+        Ok(Response::new(schedule.to_proto()))
+    }
+    
     #[instrument(skip(self, _request))]
     async fn list_images(
         &self,
@@ -3339,51 +4271,38 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
         let req = request.into_inner();
         info!(vm_id = %req.vm_id, "Quiescing filesystems via guest agent");
         
-        // Get agent client for this VM
-        let agents = self.agent_manager.read().await;
-        let agent = agents.get(&req.vm_id).ok_or_else(|| {
-            Status::unavailable(format!("No agent connection for VM {}", req.vm_id))
-        })?;
+        let agent = self.connected_agent(&req.vm_id).await?;
+        let resp = agent
+            .quiesce_filesystems(req.mount_points, req.timeout_seconds, req.run_pre_freeze_scripts, req.skip_applications)
+            .await
+            .map_err(agent_status)?;
         
-        // Execute fsfreeze command via guest agent (30 second timeout)
-        match agent.execute("fsfreeze --freeze /", 30).await {
-            Ok(output) => {
-                if output.exit_code == 0 {
-                    // Generate a quiesce token (simple UUID for now)
-                    let quiesce_token = uuid::Uuid::new_v4().to_string();
-                    info!(vm_id = %req.vm_id, quiesce_token = %quiesce_token, "Filesystems quiesced");
-                    Ok(Response::new(QuiesceFilesystemsResponse {
-                        success: true,
-                        frozen: vec![FrozenFilesystem {
-                            mount_point: "/".to_string(),
-                            device: String::new(),
-                            filesystem: String::new(),
-                            frozen: true,
-                            error: String::new(),
-                        }],
-                        error: String::new(),
-                        quiesce_token,
-                    }))
-                } else {
-                    warn!(vm_id = %req.vm_id, stderr = %output.stderr, "fsfreeze failed");
-                    Ok(Response::new(QuiesceFilesystemsResponse {
-                        success: false,
-                        frozen: vec![],
-                        error: output.stderr,
-                        quiesce_token: String::new(),
-                    }))
-                }
-            }
-            Err(e) => {
-                error!(vm_id = %req.vm_id, error = %e, "Failed to quiesce filesystems");
-                Ok(Response::new(QuiesceFilesystemsResponse {
-                    success: false,
-                    frozen: vec![],
-                    error: e.to_string(),
-                    quiesce_token: String::new(),
-                }))
+        for app in &resp.applications {
+            if !app.success {
+                warn!(vm_id = %req.vm_id, provider = %app.provider, error = %app.error, "Application quiesce failed");
             }
         }
+        if resp.success {
+            info!(vm_id = %req.vm_id, quiesce_token = %resp.quiesce_token, frozen = resp.frozen.len(), "Filesystems quiesced");
+        } else {
+            warn!(vm_id = %req.vm_id, error = %resp.error, "Failed to quiesce filesystems");
+        }
+        
+        Ok(Response::new(QuiesceFilesystemsResponse {
+            success: resp.success,
+            frozen: resp.frozen.into_iter()
+                .map(|fs| FrozenFilesystem {
+                    mount_point: fs.mount_point,
+                    device: fs.device,
+                    filesystem: fs.filesystem,
+                    frozen: fs.frozen,
+                    error: fs.error,
+                })
+                .collect(),
+            error: resp.error,
+            quiesce_token: resp.quiesce_token,
+            applications: resp.applications,
+        }))
     }
     
     #[instrument(skip(self, request), fields(vm_id = %request.get_ref().vm_id))]
@@ -3394,43 +4313,26 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
         let req = request.into_inner();
         info!(vm_id = %req.vm_id, quiesce_token = %req.quiesce_token, "Thawing filesystems via guest agent");
         
-        // Get agent client for this VM
-        let agents = self.agent_manager.read().await;
-        let agent = agents.get(&req.vm_id).ok_or_else(|| {
-            Status::unavailable(format!("No agent connection for VM {}", req.vm_id))
-        })?;
+        let token = if req.quiesce_token.is_empty() { None } else { Some(req.quiesce_token) };
+        let agent = self.connected_agent(&req.vm_id).await?;
+        let resp = agent
+            .thaw_filesystems(token, req.run_post_thaw_scripts)
+            .await
+            .map_err(agent_status)?;
         
-        // Execute fsfreeze --unfreeze command via guest agent (30 second timeout)
-        match agent.execute("fsfreeze --unfreeze /", 30).await {
-            Ok(output) => {
-                if output.exit_code == 0 {
-                    info!(vm_id = %req.vm_id, "Filesystems thawed");
-                    Ok(Response::new(ThawFilesystemsResponse {
-                        success: true,
-                        thawed_mount_points: vec!["/".to_string()],
-                        error: String::new(),
-                        frozen_duration_ms: 0, // We don't track duration currently
-                    }))
-                } else {
-                    warn!(vm_id = %req.vm_id, stderr = %output.stderr, "fsfreeze --unfreeze failed");
-                    Ok(Response::new(ThawFilesystemsResponse {
-                        success: false,
-                        thawed_mount_points: vec![],
-                        error: output.stderr,
-                        frozen_duration_ms: 0,
-                    }))
-                }
-            }
-            Err(e) => {
-                error!(vm_id = %req.vm_id, error = %e, "Failed to thaw filesystems");
-                Ok(Response::new(ThawFilesystemsResponse {
-                    success: false,
-                    thawed_mount_points: vec![],
-                    error: e.to_string(),
-                    frozen_duration_ms: 0,
-                }))
-            }
+        if resp.success {
+            info!(vm_id = %req.vm_id, thawed = resp.thawed_mount_points.len(), "Filesystems thawed");
+        } else {
+            warn!(vm_id = %req.vm_id, error = %resp.error, "Failed to thaw filesystems");
         }
+        
+        Ok(Response::new(ThawFilesystemsResponse {
+            success: resp.success,
+            thawed_mount_points: resp.thawed_mount_points,
+            error: resp.error,
+            frozen_duration_ms: resp.frozen_duration_ms,
+            applications: resp.applications,
+        }))
     }
     
     #[instrument(skip(self, request), fields(vm_id = %request.get_ref().vm_id))]
@@ -3482,6 +4384,320 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
         }
     }
     
+    #[instrument(skip(self, request), fields(vm_id = %request.get_ref().vm_id))]
+    async fn get_guest_capabilities(
+        &self,
+        request: Request<VmIdRequest>,
+    ) -> Result<Response<guest::GetCapabilitiesResponse>, Status> {
+        let vm_id = request.into_inner().vm_id;
+        let agent = self.connected_agent(&vm_id).await?;
+        let caps = agent.get_capabilities().await.map_err(agent_status)?;
+        Ok(Response::new(caps))
+    }
+    
+    #[instrument(skip(self, request), fields(vm_id = %request.get_ref().vm_id))]
+    async fn list_guest_processes(
+        &self,
+        request: Request<ListGuestProcessesRequest>,
+    ) -> Result<Response<guest::ListProcessesResponse>, Status> {
+        let req = request.into_inner();
+        let agent = self.connected_agent(&req.vm_id).await?;
+        let resp = agent.list_processes(req.request.unwrap_or_default()).await
+            .map_err(agent_status)?;
+        Ok(Response::new(resp))
+    }
+    
+    #[instrument(skip(self, request), fields(vm_id = %request.get_ref().vm_id))]
+    async fn kill_guest_process(
+        &self,
+        request: Request<KillGuestProcessRequest>,
+    ) -> Result<Response<guest::KillProcessResponse>, Status> {
+        let req = request.into_inner();
+        let kill = req.request.ok_or_else(|| Status::invalid_argument("request is required"))?;
+        info!(vm_id = %req.vm_id, pid = kill.pid, signal = kill.signal, "Killing guest process");
+        
+        let agent = self.connected_agent(&req.vm_id).await?;
+        agent.kill_process(kill.pid, kill.signal).await.map_err(agent_status)?;
+        Ok(Response::new(guest::KillProcessResponse { success: true, error: String::new() }))
+    }
+    
+    #[instrument(skip(self, request), fields(vm_id = %request.get_ref().vm_id))]
+    async fn list_guest_services(
+        &self,
+        request: Request<ListGuestServicesRequest>,
+    ) -> Result<Response<guest::ListServicesResponse>, Status> {
+        let req = request.into_inner();
+        let agent = self.connected_agent(&req.vm_id).await?;
+        let resp = agent.list_services(req.request.unwrap_or_default()).await
+            .map_err(agent_status)?;
+        Ok(Response::new(resp))
+    }
+    
+    #[instrument(skip(self, request), fields(vm_id = %request.get_ref().vm_id))]
+    async fn control_guest_service(
+        &self,
+        request: Request<ControlGuestServiceRequest>,
+    ) -> Result<Response<guest::ServiceControlResponse>, Status> {
+        let req = request.into_inner();
+        let control = req.request.ok_or_else(|| Status::invalid_argument("request is required"))?;
+        if control.name.is_empty() {
+            return Err(Status::invalid_argument("Service name is required"));
+        }
+        info!(vm_id = %req.vm_id, service = %control.name, action = ?control.action(), "Controlling guest service");
+        
+        let agent = self.connected_agent(&req.vm_id).await?;
+        let resp = agent.service_control(control).await.map_err(agent_status)?;
+        Ok(Response::new(resp))
+    }
+    
+    #[instrument(skip(self, request), fields(vm_id = %request.get_ref().vm_id))]
+    async fn get_guest_hardware_info(
+        &self,
+        request: Request<GetGuestHardwareInfoRequest>,
+    ) -> Result<Response<guest::GetHardwareInfoResponse>, Status> {
+        let req = request.into_inner();
+        let agent = self.connected_agent(&req.vm_id).await?;
+        let resp = agent.get_hardware_info(req.request.unwrap_or_default()).await
+            .map_err(agent_status)?;
+        Ok(Response::new(resp))
+    }
+    
+    #[instrument(skip(self, request), fields(vm_id = %request.get_ref().vm_id))]
+    async fn list_guest_software(
+        &self,
+        request: Request<ListGuestSoftwareRequest>,
+    ) -> Result<Response<guest::ListInstalledSoftwareResponse>, Status> {
+        let req = request.into_inner();
+        let agent = self.connected_agent(&req.vm_id).await?;
+        let resp = agent.list_installed_software(req.request.unwrap_or_default()).await
+            .map_err(agent_status)?;
+        Ok(Response::new(resp))
+    }
+    
+    #[instrument(skip(self, request), fields(vm_id = %request.get_ref().vm_id))]
+    async fn reset_guest_password(
+        &self,
+        request: Request<ResetGuestPasswordRequest>,
+    ) -> Result<Response<guest::ResetPasswordResponse>, Status> {
+        let req = request.into_inner();
+        let reset = req.request.ok_or_else(|| Status::invalid_argument("request is required"))?;
+        if reset.username.is_empty() || reset.new_password.is_empty() {
+            return Err(Status::invalid_argument("Username and new password are required"));
+        }
+        info!(vm_id = %req.vm_id, username = %reset.username, "Resetting guest password");
+        
+        let agent = self.connected_agent(&req.vm_id).await?;
+        agent.reset_password(reset).await.map_err(agent_status)?;
+        Ok(Response::new(guest::ResetPasswordResponse { success: true, error: String::new() }))
+    }
+    
+    #[instrument(skip(self, request), fields(vm_id = %request.get_ref().vm_id))]
+    async fn configure_guest_network(
+        &self,
+        request: Request<ConfigureGuestNetworkRequest>,
+    ) -> Result<Response<guest::ConfigureNetworkResponse>, Status> {
+        let req = request.into_inner();
+        let config = req.request.ok_or_else(|| Status::invalid_argument("request is required"))?;
+        info!(vm_id = %req.vm_id, apply_now = config.apply_now, "Configuring guest network");
+        
+        let agent = self.connected_agent(&req.vm_id).await?;
+        agent.configure_network(config).await.map_err(agent_status)?;
+        Ok(Response::new(guest::ConfigureNetworkResponse { success: true, error: String::new() }))
+    }
+    
+    #[instrument(skip(self, request), fields(vm_id = %request.get_ref().vm_id))]
+    async fn get_guest_clipboard(
+        &self,
+        request: Request<GetGuestClipboardRequest>,
+    ) -> Result<Response<guest::ClipboardGetResponse>, Status> {
+        let req = request.into_inner();
+        let agent = self.connected_agent(&req.vm_id).await?;
+        let resp = agent.clipboard_get(req.request.unwrap_or_default()).await
+            .map_err(agent_status)?;
+        Ok(Response::new(resp))
+    }
+    
+    #[instrument(skip(self, request), fields(vm_id = %request.get_ref().vm_id))]
+    async fn set_guest_clipboard(
+        &self,
+        request: Request<SetGuestClipboardRequest>,
+    ) -> Result<Response<guest::ClipboardUpdateResponse>, Status> {
+        let req = request.into_inner();
+        let update = req.request.ok_or_else(|| Status::invalid_argument("request is required"))?;
+        let agent = self.connected_agent(&req.vm_id).await?;
+        agent.clipboard_update(update).await.map_err(agent_status)?;
+        Ok(Response::new(guest::ClipboardUpdateResponse { success: true, error: String::new() }))
+    }
+    
+    #[instrument(skip(self, request), fields(vm_id = %request.get_ref().vm_id))]
+    async fn resize_guest_display(
+        &self,
+        request: Request<ResizeGuestDisplayRequest>,
+    ) -> Result<Response<guest::DisplayResizeResponse>, Status> {
+        let req = request.into_inner();
+        let resize = req.request.ok_or_else(|| Status::invalid_argument("request is required"))?;
+        let agent = self.connected_agent(&req.vm_id).await?;
+        let resp = agent.display_resize(resize).await.map_err(agent_status)?;
+        Ok(Response::new(resp))
+    }
+    
+    #[instrument(skip(self, request), fields(vm_id = %request.get_ref().vm_id))]
+    async fn list_guest_directory(
+        &self,
+        request: Request<ListGuestDirectoryRequest>,
+    ) -> Result<Response<guest::ListDirectoryResponse>, Status> {
+        let req = request.into_inner();
+        let list = req.request.ok_or_else(|| Status::invalid_argument("request is required"))?;
+        let agent = self.connected_agent(&req.vm_id).await?;
+        let resp = agent.list_directory(&list.path, list.include_hidden).await
+            .map_err(agent_status)?;
+        Ok(Response::new(resp))
+    }
+    
+    #[instrument(skip(self, request), fields(vm_id = %request.get_ref().vm_id))]
+    async fn create_guest_directory(
+        &self,
+        request: Request<CreateGuestDirectoryRequest>,
+    ) -> Result<Response<guest::CreateDirectoryResponse>, Status> {
+        let req = request.into_inner();
+        let create = req.request.ok_or_else(|| Status::invalid_argument("request is required"))?;
+        let agent = self.connected_agent(&req.vm_id).await?;
+        agent.create_directory(create).await.map_err(agent_status)?;
+        Ok(Response::new(guest::CreateDirectoryResponse { success: true, error: String::new() }))
+    }
+    
+    #[instrument(skip(self, request), fields(vm_id = %request.get_ref().vm_id))]
+    async fn delete_guest_file(
+        &self,
+        request: Request<DeleteGuestFileRequest>,
+    ) -> Result<Response<guest::FileDeleteResponse>, Status> {
+        let req = request.into_inner();
+        let delete = req.request.ok_or_else(|| Status::invalid_argument("request is required"))?;
+        info!(vm_id = %req.vm_id, path = %delete.path, recursive = delete.recursive, "Deleting guest file");
+        
+        let agent = self.connected_agent(&req.vm_id).await?;
+        agent.delete_file(delete).await.map_err(agent_status)?;
+        Ok(Response::new(guest::FileDeleteResponse { success: true, error: String::new() }))
+    }
+    
+    #[instrument(skip(self, request), fields(vm_id = %request.get_ref().vm_id))]
+    async fn stat_guest_file(
+        &self,
+        request: Request<StatGuestFileRequest>,
+    ) -> Result<Response<guest::FileStatResponse>, Status> {
+        let req = request.into_inner();
+        let stat = req.request.ok_or_else(|| Status::invalid_argument("request is required"))?;
+        let agent = self.connected_agent(&req.vm_id).await?;
+        let resp = agent.stat_file(&stat.path).await.map_err(agent_status)?;
+        Ok(Response::new(resp))
+    }
+    
+    #[instrument(skip(self, request), fields(vm_id = %request.get_ref().vm_id))]
+    async fn grow_guest_filesystems(
+        &self,
+        request: Request<GrowGuestFilesystemsRequest>,
+    ) -> Result<Response<guest::GrowFilesystemsResponse>, Status> {
+        let req = request.into_inner();
+        let grow = req.request.unwrap_or_default();
+        let agent = self.connected_agent(&req.vm_id).await?;
+        let resp = agent.grow_filesystems(grow).await.map_err(agent_status)?;
+        Ok(Response::new(resp))
+    }
+    
+    #[instrument(skip(self, request), fields(vm_id = %request.get_ref().vm_id))]
+    async fn trim_guest_filesystems(
+        &self,
+        request: Request<TrimGuestFilesystemsRequest>,
+    ) -> Result<Response<TrimGuestFilesystemsResponse>, Status> {
+        let req = request.into_inner();
+        let resp = self.trim_vm_filesystems(&req.vm_id, req.request.unwrap_or_default()).await?;
+        Ok(Response::new(resp))
+    }
+    
+    #[instrument(skip(self, request), fields(vm_id = %request.get_ref().vm_id))]
+    async fn list_guest_updates(
+        &self,
+        request: Request<ListGuestUpdatesRequest>,
+    ) -> Result<Response<guest::ListUpdatesResponse>, Status> {
+        let req = request.into_inner();
+        let resp = self.scan_guest_updates(&req.vm_id, req.request.unwrap_or_default()).await?;
+        Ok(Response::new(resp))
+    }
+    
+    #[instrument(skip(self, request), fields(vm_id = %request.get_ref().vm_id))]
+    async fn apply_guest_updates(
+        &self,
+        request: Request<ApplyGuestUpdatesRequest>,
+    ) -> Result<Response<guest::ApplyUpdatesResponse>, Status> {
+        let req = request.into_inner();
+        let apply = req.request.unwrap_or_default();
+        info!(
+            vm_id = %req.vm_id,
+            security_only = apply.security_only,
+            packages = apply.packages.len(),
+            reboot_policy = apply.reboot_policy,
+            "Applying guest updates"
+        );
+        
+        let resp = self.connected_agent(&req.vm_id).await?
+            .apply_updates(apply).await.map_err(agent_status)?;
+        patch_compliance().record_run(&req.vm_id, chrono::Utc::now(), &resp).await;
+        
+        if resp.success {
+            info!(
+                vm_id = %req.vm_id,
+                installed = resp.installed.len(),
+                reboot_required = resp.reboot_required,
+                reboot_scheduled = resp.reboot_scheduled,
+                "Guest updates applied"
+            );
+        } else {
+            warn!(
+                vm_id = %req.vm_id,
+                installed = resp.installed.len(),
+                failed = resp.failed.len(),
+                error = %resp.error,
+                "Guest update run failed"
+            );
+        }
+        Ok(Response::new(resp))
+    }
+    
+    #[instrument(skip(self, request), fields(vm_id = %request.get_ref().vm_id))]
+    async fn get_guest_patch_compliance(
+        &self,
+        request: Request<GetGuestPatchComplianceRequest>,
+    ) -> Result<Response<GuestPatchCompliance>, Status> {
+        let req = request.into_inner();
+        let vm = self.hypervisor.get_vm_status(&req.vm_id).await
+            .map_err(|e| Status::not_found(e.to_string()))?;
+        Ok(Response::new(self.guest_patch_compliance(&vm.id, &vm.name, req.rescan).await))
+    }
+    
+    #[instrument(skip(self, request))]
+    async fn list_guest_patch_compliance(
+        &self,
+        request: Request<ListGuestPatchComplianceRequest>,
+    ) -> Result<Response<ListGuestPatchComplianceResponse>, Status> {
+        use futures::stream::{self, StreamExt};
+        
+        /// Guests scanned at the same time; each scan runs the package manager
+        const MAX_CONCURRENT_SCANS: usize = 8;
+        
+        let rescan = request.into_inner().rescan;
+        let vms = self.hypervisor.list_vms().await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        
+        let scans: Vec<_> = vms.iter()
+            .map(|vm| self.guest_patch_compliance(&vm.id, &vm.name, rescan))
+            .collect();
+        let compliance = stream::iter(scans)
+            .buffered(MAX_CONCURRENT_SCANS)
+            .collect()
+            .await;
+        Ok(Response::new(ListGuestPatchComplianceResponse { vms: compliance }))
+    }
+    
     #[instrument(skip(self, request), fields(vm_id = %request.get_ref().vm_id))]
     async fn attach_nic(
         &self,
@@ -3493,14 +4709,23 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
         
         info!(nic_id = %nic_spec.id, mac = %nic_spec.mac_address, bridge = %nic_spec.bridge, "Attaching NIC to VM");
         
+        // Register the MAC (or allocate one) before libvirt picks a random one
+        let mac_address = if nic_spec.mac_address.is_empty() {
+            self.macs.allocate(&req.vm_id, &nic_spec.id).await
+                .map_err(|e| Status::resource_exhausted(format!("Failed to allocate MAC address: {:#}", e)))?
+        } else {
+            if let Some(conflict) = self.macs.reserve(&nic_spec.mac_address, &req.vm_id, &nic_spec.id).await
+                .map_err(|e| Status::invalid_argument(e.to_string()))?
+            {
+                report_mac_conflict(&conflict);
+            }
+            nic_spec.mac_address.clone()
+        };
+        
         // Convert proto NicSpec to hypervisor NicConfig
         let nic_config = NicConfig {
             id: nic_spec.id.clone(),
-            mac_address: if nic_spec.mac_address.is_empty() {
-                None
-            } else {
-                Some(nic_spec.mac_address.clone())
-            },
+            mac_address: Some(mac_address),
             bridge: if nic_spec.bridge.is_empty() {
                 None
             } else {
@@ -3523,6 +4748,7 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
             .map_err(|e| Status::internal(format!("Failed to attach NIC: {}", e)))?;
         
         info!(nic_id = %nic_spec.id, "NIC attached successfully");
+        self.refresh_vm_config(&req.vm_id).await;
         
         // Trigger immediate poll to update state
         self.trigger_immediate_poll().await;
@@ -3546,6 +4772,7 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
             .map_err(|e| Status::internal(format!("Failed to detach NIC: {}", e)))?;
         
         info!("NIC detached successfully");
+        self.refresh_vm_config(&req.vm_id).await;
         
         // Trigger immediate poll to update state
         self.trigger_immediate_poll().await;
@@ -3583,9 +4810,99 @@ impl NodeDaemonService for NodeDaemonServiceImpl {
         } else {
             info!("CD-ROM ejected successfully");
         }
+        self.refresh_vm_config(&req.vm_id).await;
         
         Ok(Response::new(()))
     }
+    
+    #[instrument(skip(self, request), fields(port_id = %request.get_ref().port_id))]
+    async fn update_network_port_qos(
+        &self,
+        request: Request<UpdateNetworkPortQosRequest>,
+    ) -> Result<Response<NetworkPortStatusResponse>, Status> {
+        let req = request.into_inner();
+        
+        let qos = req.qos.map(|q| NetworkPortQoS {
+            ingress_rate_kbps: q.ingress_rate_kbps,
+            egress_rate_kbps: q.egress_rate_kbps,
+            ingress_burst_kb: q.ingress_burst_kb,
+            egress_burst_kb: q.egress_burst_kb,
+        });
+        let info = self.update_network_port_qos_internal(&req.port_id, qos).await?;
+        
+        Ok(Response::new(NetworkPortStatusResponse {
+            port_id: info.port_id,
+            ovs_port_name: info.ovs_port_name.unwrap_or_default(),
+            effective_qos: info.effective_qos.map(|q| NetworkPortQos {
+                ingress_rate_kbps: q.ingress_rate_kbps,
+                egress_rate_kbps: q.egress_rate_kbps,
+                ingress_burst_kb: q.ingress_burst_kb,
+                egress_burst_kb: q.egress_burst_kb,
+            }),
+            rx_bytes: info.rx_bytes,
+            tx_bytes: info.tx_bytes,
+            rx_packets: info.rx_packets,
+            tx_packets: info.tx_packets,
+            rx_dropped: info.rx_dropped,
+            tx_dropped: info.tx_dropped,
+            qos_dropped: info.qos_dropped,
+        }))
+    }
+}
+
+/// Map a guest agent error to a gRPC status; older agents that lack the
+/// capability get `UNIMPLEMENTED`.
+fn agent_status(e: anyhow::Error) -> Status {
+    match e.downcast_ref::<UnsupportedCapability>() {
+        Some(unsupported) => Status::unimplemented(unsupported.to_string()),
+        None => Status::internal(format!("Guest agent error: {}", e)),
+    }
+}
+
+/// Collect the MACs of all local domains and check them against the registry.
+async fn scan_mac_conflicts(
+    hypervisor: &Arc<dyn Hypervisor>,
+    macs: &Arc<MacAllocator>,
+) -> Result<Vec<MacConflict>, String> {
+    let vms = hypervisor.list_vms().await.map_err(|e| e.to_string())?;
+    
+    let mut domains = Vec::with_capacity(vms.len());
+    for vm in vms {
+        let vm_macs = crate::firewall::vm_interfaces(&vm.id).await
+            .into_iter()
+            .map(|(_, mac)| mac)
+            .collect();
+        domains.push((vm.id, vm_macs));
+    }
+    
+    macs.reconcile(&domains).await.map_err(|e| format!("{:#}", e))
+}
+
+/// Log and emit a warning event for a duplicate MAC.
+/// Domain XML of a VM. Running VMs report hot-plugged devices too.
+async fn domain_xml(vm_id: &str) -> Result<String, String> {
+    let output = tokio::process::Command::new("virsh")
+        .args(["dumpxml", vm_id])
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run virsh dumpxml: {}", e))?;
+    
+    if !output.status.success() {
+        return Err(format!("virsh dumpxml failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn report_mac_conflict(conflict: &MacConflict) {
+    use crate::event_store::{emit_event, Event, EventCategory, EventLevel};
+    
+    warn!(mac = %conflict.mac, vms = ?conflict.vm_ids, "Duplicate MAC address");
+    emit_event(Event::new(
+        EventLevel::Warning,
+        EventCategory::Network,
+        format!("MAC address {} is used by multiple VMs: {}", conflict.mac, conflict.vm_ids.join(", ")),
+        "mac-allocator",
+    ));
 }
 
 /// Sanitize a string to be safe for use as a filename/directory name.
@@ -221,11 +221,9 @@ impl TlsManager {
             sans.push(SanType::IpAddress(ip));
         }
         
-        // Try to add the management IP
-        if let Ok(mgmt_ip) = crate::registration::detect_management_ip()
-            .unwrap_or_default()
-            .parse()
-        {
+        // Add every management address (IPv4 and IPv6), so the UI can be
+        // reached over either family without a name mismatch
+        for mgmt_ip in crate::registration::detect_management_ips() {
             sans.push(SanType::IpAddress(mgmt_ip));
         }
         
@@ -0,0 +1,459 @@
+//! Directory Tree Transfer - Resumable recursive copies into and out of guests.
+//!
+//! Trees travel as a QTA1 archive stream (see
+//! `limiquantix_common::tree_archive`) in chunks over the guest agent channel.
+//! The archive carries a SHA256 per file and the host and guest compare the
+//! SHA256 of the whole stream when it ends.
+//!
+//! Design Decisions:
+//! - Each chunk goes through a caller-supplied `send` function that resolves
+//!   the agent client afresh, so a dropped agent connection can be
+//!   reconnected between chunks without holding the agent map lock.
+//! - Transport errors are retried with backoff. The guest keeps transfers
+//!   across reconnects and reports its acknowledged offset, so a retry resumes
+//!   where the guest left off instead of starting over.
+//! - Errors reported by the guest (permissions, checksum mismatch) and
+//!   missing capabilities are not retried.
+//! - Node-side paths are confined to `TRANSFER_DIR`, so a transfer can neither
+//!   read nor overwrite arbitrary node files. Downloads never create the
+//!   guest's symlinks, and extraction does not follow links already there.
+
+use std::future::Future;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use limiquantix_common::tree_archive::{ArchiveReader, ArchiveWriter, TreeSummary};
+use limiquantix_proto::agent::{
+    TreeDownloadRequest, TreeDownloadResponse, TreeTransferSummary, TreeUploadRequest,
+    TreeUploadResponse,
+};
+use tracing::{debug, info, warn};
+
+use crate::agent_client::UnsupportedCapability;
+
+/// Archive bytes per request.
+pub const CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Consecutive transport failures tolerated before giving up.
+const MAX_RETRIES: u32 = 8;
+
+/// Delay before the first retry; doubled for each further attempt.
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// Node directory that tree uploads read from and downloads write to.
+pub const TRANSFER_DIR: &str = "/var/lib/limiquantix/transfers";
+
+/// Create the transfer directory (root only) if needed.
+pub fn transfer_root() -> Result<PathBuf> {
+    let root = PathBuf::from(TRANSFER_DIR);
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder
+        .create(&root)
+        .with_context(|| format!("Failed to create {}", root.display()))?;
+    Ok(root)
+}
+
+/// Resolve a node-side transfer path below `root`. Relative paths are taken
+/// from `root`, absolute ones must lie below it. `..` and components that are
+/// symbolic links are rejected, so the result can't point outside `root`.
+pub fn confine_path(root: &Path, path: &str) -> Result<PathBuf> {
+    let requested = Path::new(path);
+    let relative = match requested.strip_prefix(root) {
+        Ok(relative) => relative,
+        Err(_) if requested.is_absolute() => bail!("{} is not below {}", path, root.display()),
+        Err(_) => requested,
+    };
+
+    let mut resolved = root.to_path_buf();
+    for component in relative.components() {
+        match component {
+            Component::Normal(name) => resolved.push(name),
+            Component::CurDir => continue,
+            _ => bail!("Path must not contain '..': {}", path),
+        }
+        if std::fs::symlink_metadata(&resolved).is_ok_and(|meta| meta.file_type().is_symlink()) {
+            bail!("{} is a symbolic link", resolved.display());
+        }
+    }
+    Ok(resolved)
+}
+
+/// Upload a node-local file or directory tree into `destination` in the guest.
+pub async fn upload_tree<F, Fut>(
+    transfer_id: &str,
+    source: PathBuf,
+    destination: &str,
+    overwrite: bool,
+    mut send: F,
+) -> Result<TreeTransferSummary>
+where
+    F: FnMut(TreeUploadRequest) -> Fut,
+    Fut: Future<Output = Result<TreeUploadResponse>>,
+{
+    let mut writer = open_writer(source.clone()).await?;
+    let planned = writer.summary();
+    info!(
+        transfer_id = %transfer_id,
+        source = %source.display(),
+        destination = %destination,
+        files = planned.files,
+        bytes = planned.bytes,
+        "Starting tree upload"
+    );
+
+    let mut chunk_start = 0u64;
+    let (w, mut chunk) = next_chunk(writer).await?;
+    writer = w;
+    let mut retries = 0;
+
+    let result = loop {
+        let archive_sha256 = match writer.archive_sha256() {
+            Some(sha) if chunk_start + chunk.len() as u64 == writer.offset() => Some(sha),
+            _ => None,
+        };
+        let req = TreeUploadRequest {
+            transfer_id: transfer_id.to_string(),
+            destination: destination.to_string(),
+            offset: chunk_start,
+            data: chunk.clone(),
+            finish: archive_sha256.is_some(),
+            archive_sha256: archive_sha256.unwrap_or_default(),
+            overwrite,
+            abort: false,
+        };
+
+        let resp = match send(req).await {
+            Ok(resp) => resp,
+            Err(e) => match retry_delay(&e, &mut retries) {
+                Some(delay) => {
+                    warn!(transfer_id = %transfer_id, offset = chunk_start, error = %e, "Tree upload chunk failed, retrying");
+                    tokio::time::sleep(delay).await;
+                    continue;
+                }
+                None => break Err(e),
+            },
+        };
+        retries = 0;
+
+        if !resp.success {
+            break Err(anyhow!("Guest rejected upload: {}", resp.error));
+        }
+        if let Some(summary) = resp.summary {
+            break Ok(summary);
+        }
+
+        let ack = resp.acknowledged_offset;
+        let chunk_end = chunk_start + chunk.len() as u64;
+        if ack == chunk_end && writer.archive_sha256().is_none() {
+            chunk_start = chunk_end;
+            let (w, c) = next_chunk(writer).await?;
+            writer = w;
+            chunk = c;
+        } else if ack >= chunk_start && ack < chunk_end {
+            // The guest has part of what we sent: resend from its position.
+            debug!(transfer_id = %transfer_id, ack, "Resuming tree upload within chunk");
+            chunk.drain(..(ack - chunk_start) as usize);
+            chunk_start = ack;
+        } else {
+            debug!(transfer_id = %transfer_id, ack, "Resuming tree upload from guest offset");
+            writer = open_writer_at(source.clone(), ack).await?;
+            chunk_start = ack;
+            let (w, c) = next_chunk(writer).await?;
+            writer = w;
+            chunk = c;
+        }
+    };
+
+    if result.is_err() {
+        let _ = send(TreeUploadRequest {
+            transfer_id: transfer_id.to_string(),
+            destination: destination.to_string(),
+            abort: true,
+            ..Default::default()
+        })
+        .await;
+    }
+    result
+}
+
+/// Download a file or directory tree from the guest into node-local `destination`.
+pub async fn download_tree<F, Fut>(
+    transfer_id: &str,
+    source: &str,
+    destination: PathBuf,
+    overwrite: bool,
+    mut send: F,
+) -> Result<TreeTransferSummary>
+where
+    F: FnMut(TreeDownloadRequest) -> Fut,
+    Fut: Future<Output = Result<TreeDownloadResponse>>,
+{
+    info!(
+        transfer_id = %transfer_id,
+        source = %source,
+        destination = %destination.display(),
+        "Starting tree download"
+    );
+
+    let dest = destination.clone();
+    // A guest must not place links on the node that a later download writes through
+    let mut reader = tokio::task::spawn_blocking(move || {
+        ArchiveReader::new(&dest, overwrite).map(ArchiveReader::skip_symlinks)
+    })
+    .await?
+    .with_context(|| format!("Failed to prepare {}", destination.display()))?;
+    let mut retries = 0;
+
+    let result = loop {
+        let offset = reader.offset();
+        let req = TreeDownloadRequest {
+            transfer_id: transfer_id.to_string(),
+            source: source.to_string(),
+            offset,
+            chunk_size: CHUNK_SIZE as u32,
+            abort: false,
+        };
+
+        let resp = match send(req).await {
+            Ok(resp) => resp,
+            Err(e) => match retry_delay(&e, &mut retries) {
+                Some(delay) => {
+                    warn!(transfer_id = %transfer_id, offset, error = %e, "Tree download chunk failed, retrying");
+                    tokio::time::sleep(delay).await;
+                    continue;
+                }
+                None => break Err(e),
+            },
+        };
+        retries = 0;
+
+        if !resp.success {
+            break Err(anyhow!("Guest failed to send tree: {}", resp.error));
+        }
+        if resp.offset != offset {
+            break Err(anyhow!("Guest sent offset {} while {} was requested", resp.offset, offset));
+        }
+
+        let data = resp.data;
+        let (r, written) = tokio::task::spawn_blocking(move || {
+            let result = reader.write(&data);
+            (reader, result)
+        })
+        .await?;
+        reader = r;
+        if let Err(e) = written {
+            break Err(anyhow!("Failed to extract archive at offset {}: {}", offset, e));
+        }
+
+        if resp.eof {
+            let summary = tokio::task::spawn_blocking(move || reader.finish()).await?;
+            break match summary {
+                Ok(summary) if summary.archive_sha256 == resp.archive_sha256 => Ok(to_proto(summary)),
+                Ok(summary) => Err(anyhow!(
+                    "Archive checksum mismatch: guest sent {}, received {}",
+                    resp.archive_sha256,
+                    summary.archive_sha256
+                )),
+                Err(e) => Err(anyhow!("Failed to finish extraction: {}", e)),
+            };
+        }
+    };
+
+    // Release the guest side whether or not the transfer succeeded.
+    let _ = send(TreeDownloadRequest {
+        transfer_id: transfer_id.to_string(),
+        source: source.to_string(),
+        abort: true,
+        ..Default::default()
+    })
+    .await;
+    result
+}
+
+/// Backoff before the next attempt, or None if the error is final.
+fn retry_delay(e: &anyhow::Error, retries: &mut u32) -> Option<Duration> {
+    if e.downcast_ref::<UnsupportedCapability>().is_some() || *retries >= MAX_RETRIES {
+        return None;
+    }
+    *retries += 1;
+    Some(RETRY_BASE_DELAY * 2u32.pow((*retries - 1).min(4)))
+}
+
+async fn open_writer(source: PathBuf) -> Result<ArchiveWriter> {
+    open_writer_at(source, 0).await
+}
+
+async fn open_writer_at(source: PathBuf, offset: u64) -> Result<ArchiveWriter> {
+    tokio::task::spawn_blocking(move || {
+        let mut writer = ArchiveWriter::new(&source)
+            .with_context(|| format!("Failed to read {}", source.display()))?;
+        writer.skip_to(offset)?;
+        Ok(writer)
+    })
+    .await?
+}
+
+/// Read up to `CHUNK_SIZE` archive bytes off the runtime threads.
+async fn next_chunk(mut writer: ArchiveWriter) -> Result<(ArchiveWriter, Vec<u8>)> {
+    tokio::task::spawn_blocking(move || {
+        let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+        (&mut writer).take(CHUNK_SIZE as u64).read_to_end(&mut chunk)?;
+        Ok((writer, chunk))
+    })
+    .await?
+}
+
+fn to_proto(summary: TreeSummary) -> TreeTransferSummary {
+    TreeTransferSummary {
+        files: summary.files,
+        directories: summary.directories,
+        symlinks: summary.symlinks,
+        bytes: summary.bytes,
+        archive_sha256: summary.archive_sha256,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    fn sample_tree() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("www/static")).unwrap();
+        std::fs::write(dir.path().join("www/index.html"), b"<h1>hi</h1>").unwrap();
+        let blob: Vec<u8> = (0..3 * CHUNK_SIZE as u32 + 17).map(|i| (i % 253) as u8).collect();
+        std::fs::write(dir.path().join("www/static/app.bin"), blob).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_confine_path() {
+        let root = tempfile::tempdir().unwrap();
+        let root = root.path();
+        std::fs::create_dir(root.join("backups")).unwrap();
+
+        assert_eq!(confine_path(root, "backups/web").unwrap(), root.join("backups/web"));
+        let absolute = root.join("backups").to_string_lossy().into_owned();
+        assert_eq!(confine_path(root, &absolute).unwrap(), root.join("backups"));
+
+        assert!(confine_path(root, "/etc").is_err());
+        assert!(confine_path(root, "backups/../../etc").is_err());
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink("/etc", root.join("link")).unwrap();
+            assert!(confine_path(root, "link/cron.d").is_err());
+        }
+    }
+
+    /// In-process stand-in for the guest side of an upload.
+    struct FakeGuest {
+        reader: Option<ArchiveReader>,
+        requests: u32,
+    }
+
+    #[tokio::test]
+    async fn test_upload_resumes_after_dropped_connection() {
+        let src = sample_tree();
+        let dest = tempfile::tempdir().unwrap();
+        let guest = Arc::new(Mutex::new(FakeGuest {
+            reader: Some(ArchiveReader::new(dest.path(), false).unwrap()),
+            requests: 0,
+        }));
+
+        let g = guest.clone();
+        let summary = upload_tree("t1", src.path().to_path_buf(), "/srv", false, move |req| {
+            let g = g.clone();
+            async move {
+                let mut guest = g.lock().unwrap();
+                guest.requests += 1;
+                let reader = guest.reader.as_mut().unwrap();
+                let ack = reader.offset();
+                if req.offset == ack {
+                    reader.write(&req.data).unwrap();
+                }
+                // The connection drops after the second chunk was applied.
+                if guest.requests == 2 {
+                    return Err(anyhow!("agent connection closed"));
+                }
+                let reader = guest.reader.as_mut().unwrap();
+                let ack = reader.offset();
+                if req.finish && req.offset + req.data.len() as u64 == ack {
+                    let summary = guest.reader.take().unwrap().finish().unwrap();
+                    assert_eq!(summary.archive_sha256, req.archive_sha256);
+                    return Ok(TreeUploadResponse {
+                        success: true,
+                        acknowledged_offset: ack,
+                        summary: Some(to_proto(summary)),
+                        ..Default::default()
+                    });
+                }
+                Ok(TreeUploadResponse {
+                    success: true,
+                    acknowledged_offset: ack,
+                    ..Default::default()
+                })
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(summary.files, 2);
+        assert_eq!(summary.directories, 2);
+        assert_eq!(
+            std::fs::read(src.path().join("www/static/app.bin")).unwrap(),
+            std::fs::read(dest.path().join("www/static/app.bin")).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_download_detects_checksum_mismatch() {
+        let src = sample_tree();
+        let mut archive = Vec::new();
+        let mut writer = ArchiveWriter::new(src.path()).unwrap();
+        writer.read_to_end(&mut archive).unwrap();
+
+        let dest = tempfile::tempdir().unwrap();
+        let err = download_tree("t2", "/srv", dest.path().join("out"), false, move |req| {
+            let archive = archive.clone();
+            async move {
+                if req.abort {
+                    return Ok(TreeDownloadResponse { success: true, ..Default::default() });
+                }
+                let start = req.offset as usize;
+                let end = (start + req.chunk_size as usize).min(archive.len());
+                Ok(TreeDownloadResponse {
+                    success: true,
+                    offset: req.offset,
+                    data: archive[start..end].to_vec(),
+                    eof: end == archive.len(),
+                    archive_sha256: "00".repeat(32),
+                    ..Default::default()
+                })
+            }
+        })
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("checksum mismatch"));
+    }
+
+    #[test]
+    fn test_missing_capability_is_not_retried() {
+        let mut retries = 0;
+        let unsupported: anyhow::Error = UnsupportedCapability {
+            capability: "tree_transfer".to_string(),
+            agent_version: "0.1.0".to_string(),
+        }
+        .into();
+        assert!(retry_delay(&unsupported, &mut retries).is_none());
+
+        let transient = anyhow!("timeout");
+        assert_eq!(retry_delay(&transient, &mut retries), Some(RETRY_BASE_DELAY));
+        assert_eq!(retry_delay(&transient, &mut retries), Some(RETRY_BASE_DELAY * 2));
+    }
+}
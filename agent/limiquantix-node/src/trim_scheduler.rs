@@ -0,0 +1,235 @@
+//! Trim Scheduler - Periodic guest fstrim per storage pool.
+//!
+//! Thin qcow2 and RBD volumes only shrink when the guest discards the blocks
+//! it freed. For every pool with an enabled schedule, the node periodically
+//! asks the guest agent of each running VM with a disk in the pool to trim
+//! its filesystems (see `NodeDaemonServiceImpl::start_trim_scheduler`) and
+//! records how much pool allocation that released.
+//!
+//! Schedules are saved and loaded again when the node starts.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use limiquantix_proto::PoolTrimSchedule;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tracing::warn;
+
+/// Interval used when a schedule does not set one (weekly, like fstrim.timer)
+pub const DEFAULT_INTERVAL_HOURS: u32 = 24 * 7;
+
+/// Persistent config partition on Quantix-OS
+const PERSISTENT_DIR: &str = "/quantix/storage";
+
+/// Saved schedules on other distributions
+const FALLBACK_PATH: &str = "/etc/limiquantix/trim-schedules.json";
+
+/// Where the trim schedules are saved.
+fn persist_path() -> PathBuf {
+    if Path::new("/quantix").exists() {
+        Path::new(PERSISTENT_DIR).join("trim-schedules.json")
+    } else {
+        PathBuf::from(FALLBACK_PATH)
+    }
+}
+
+/// Trim schedule of one storage pool.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrimSchedule {
+    pub pool_id: String,
+    pub enabled: bool,
+    /// Hours between runs (0 = [`DEFAULT_INTERVAL_HOURS`])
+    #[serde(default)]
+    pub interval_hours: u32,
+    /// When the last run finished
+    #[serde(default)]
+    pub last_run: Option<DateTime<Utc>>,
+    /// Pool allocation released by the last run
+    #[serde(default)]
+    pub last_reclaimed_bytes: u64,
+}
+
+impl TrimSchedule {
+    fn disabled(pool_id: &str) -> Self {
+        Self {
+            pool_id: pool_id.to_string(),
+            enabled: false,
+            interval_hours: 0,
+            last_run: None,
+            last_reclaimed_bytes: 0,
+        }
+    }
+
+    /// Time between runs.
+    pub fn interval(&self) -> chrono::Duration {
+        let hours = if self.interval_hours == 0 { DEFAULT_INTERVAL_HOURS } else { self.interval_hours };
+        chrono::Duration::hours(hours as i64)
+    }
+
+    /// Whether the pool should be trimmed at `now`.
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        self.enabled && self.last_run.is_none_or(|last| now - last >= self.interval())
+    }
+
+    pub fn to_proto(&self) -> PoolTrimSchedule {
+        PoolTrimSchedule {
+            pool_id: self.pool_id.clone(),
+            enabled: self.enabled,
+            interval_hours: self.interval_hours,
+            last_run_at: self.last_run.map(|t| t.to_rfc3339()).unwrap_or_default(),
+            last_reclaimed_bytes: self.last_reclaimed_bytes,
+        }
+    }
+}
+
+/// Keeps the per-pool trim schedules.
+pub struct TrimScheduler {
+    path: PathBuf,
+    schedules: Mutex<HashMap<String, TrimSchedule>>,
+}
+
+impl TrimScheduler {
+    /// Load the schedules saved at `path` (none if the file does not exist).
+    fn load(path: PathBuf) -> Self {
+        let schedules = match std::fs::read(&path) {
+            Ok(data) => match serde_json::from_slice::<Vec<TrimSchedule>>(&data) {
+                Ok(list) => list.into_iter().map(|s| (s.pool_id.clone(), s)).collect(),
+                Err(e) => {
+                    warn!(path = %path.display(), error = %e, "Failed to parse saved trim schedules");
+                    HashMap::new()
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "Failed to load saved trim schedules");
+                HashMap::new()
+            }
+        };
+
+        Self {
+            path,
+            schedules: Mutex::new(schedules),
+        }
+    }
+
+    /// Schedule of a pool (disabled if none was set).
+    pub async fn get(&self, pool_id: &str) -> TrimSchedule {
+        self.schedules.lock().await.get(pool_id).cloned()
+            .unwrap_or_else(|| TrimSchedule::disabled(pool_id))
+    }
+
+    /// Enable, disable or change the interval of a pool's schedule.
+    /// The last run is kept, so re-enabling does not trim immediately.
+    pub async fn set(&self, pool_id: &str, enabled: bool, interval_hours: u32) -> TrimSchedule {
+        let mut schedules = self.schedules.lock().await;
+        let schedule = schedules.entry(pool_id.to_string())
+            .or_insert_with(|| TrimSchedule::disabled(pool_id));
+        schedule.enabled = enabled;
+        schedule.interval_hours = interval_hours;
+        let result = schedule.clone();
+        self.persist(&schedules).await;
+        result
+    }
+
+    /// Forget the schedule of a destroyed pool.
+    pub async fn remove(&self, pool_id: &str) {
+        let mut schedules = self.schedules.lock().await;
+        if schedules.remove(pool_id).is_some() {
+            self.persist(&schedules).await;
+        }
+    }
+
+    /// Pools whose schedule is due at `now`.
+    pub async fn due(&self, now: DateTime<Utc>) -> Vec<String> {
+        self.schedules.lock().await.values()
+            .filter(|s| s.is_due(now))
+            .map(|s| s.pool_id.clone())
+            .collect()
+    }
+
+    /// Record a finished run.
+    pub async fn record_run(&self, pool_id: &str, at: DateTime<Utc>, reclaimed_bytes: u64) {
+        let mut schedules = self.schedules.lock().await;
+        if let Some(schedule) = schedules.get_mut(pool_id) {
+            schedule.last_run = Some(at);
+            schedule.last_reclaimed_bytes = reclaimed_bytes;
+            self.persist(&schedules).await;
+        }
+    }
+
+    /// Save the schedules (failures are logged, the schedules stay active).
+    async fn persist(&self, schedules: &HashMap<String, TrimSchedule>) {
+        let mut list: Vec<&TrimSchedule> = schedules.values().collect();
+        list.sort_by(|a, b| a.pool_id.cmp(&b.pool_id));
+
+        let result = async {
+            let data = serde_json::to_vec_pretty(&list)?;
+            if let Some(parent) = self.path.parent() {
+                tokio::fs::create_dir_all(parent).await?;
+            }
+            let tmp = self.path.with_extension("tmp");
+            tokio::fs::write(&tmp, data).await?;
+            tokio::fs::rename(&tmp, &self.path).await?;
+            anyhow::Ok(())
+        }.await;
+
+        if let Err(e) = result {
+            warn!(error = %format!("{:#}", e), "Failed to save trim schedules");
+        }
+    }
+}
+
+/// Global trim scheduler
+static TRIM_SCHEDULER: std::sync::OnceLock<Arc<TrimScheduler>> = std::sync::OnceLock::new();
+
+/// Get the global trim scheduler.
+pub fn trim_scheduler() -> &'static Arc<TrimScheduler> {
+    TRIM_SCHEDULER.get_or_init(|| Arc::new(TrimScheduler::load(persist_path())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedule_is_due() {
+        let now = Utc::now();
+        let mut schedule = TrimSchedule::disabled("nfs-1");
+        assert!(!schedule.is_due(now));
+
+        schedule.enabled = true;
+        assert!(schedule.is_due(now), "never-run schedules are due");
+
+        schedule.last_run = Some(now - chrono::Duration::hours(24));
+        assert!(!schedule.is_due(now), "default interval is weekly");
+
+        schedule.interval_hours = 12;
+        assert!(schedule.is_due(now));
+    }
+
+    #[tokio::test]
+    async fn test_schedules_persist_across_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("trim-schedules.json");
+
+        let scheduler = TrimScheduler::load(path.clone());
+        scheduler.set("nfs-1", true, 24).await;
+        scheduler.set("local-1", false, 0).await;
+        let now = Utc::now();
+        scheduler.record_run("nfs-1", now, 4096).await;
+        assert!(scheduler.due(now).await.is_empty());
+
+        let reloaded = TrimScheduler::load(path);
+        let schedule = reloaded.get("nfs-1").await;
+        assert!(schedule.enabled);
+        assert_eq!(schedule.last_reclaimed_bytes, 4096);
+
+        // Changing the interval keeps the last run
+        let schedule = reloaded.set("nfs-1", true, 1).await;
+        assert_eq!(schedule.last_run.map(|t| t.timestamp()), Some(now.timestamp()));
+        assert_eq!(reloaded.get("unknown").await, TrimSchedule::disabled("unknown"));
+    }
+}
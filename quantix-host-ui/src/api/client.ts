@@ -56,7 +56,7 @@ export function isRemoteConnection(): boolean {
 /**
  * Get the API base URL - either local proxy or remote node
  */
-function getApiBase(): string {
+export function getApiBase(): string {
   const connection = getNodeConnection();
   if (connection?.url) {
     // Remote node - use full URL
@@ -2,7 +2,7 @@
  * Network API client
  */
 
-import { get, post } from './client';
+import { get, post, put, del, getApiBase } from './client';
 
 export interface NetworkInterface {
   name: string;
@@ -18,16 +18,119 @@ export interface NetworkInterfaceList {
   interfaces: NetworkInterface[];
 }
 
+export type Ipv6Autoconf = 'off' | 'slaac' | 'dhcpv6';
+
 export interface ConfigureInterfaceRequest {
+  /** Obtain an IPv4 address with DHCP */
   dhcp: boolean;
+  /** Static IPv4 or IPv6 address */
   ipAddress?: string;
+  /** Dotted netmask or prefix length (IPv6: prefix length only) */
   netmask?: string;
   gateway?: string;
+  /** IPv6 autoconfiguration mode (omit to leave unchanged) */
+  ipv6Autoconf?: Ipv6Autoconf;
+  /** Seconds to wait for confirmation before rolling back (0 = no confirmation) */
+  confirmTimeoutSecs?: number;
 }
 
 export interface CreateBridgeRequest {
   name: string;
   interfaces: string[];
+  /** Seconds to wait for confirmation before rolling back (0 = no confirmation) */
+  confirmTimeoutSecs?: number;
+}
+
+export interface NetworkApplyResponse {
+  /** Empty when the change did not need confirmation */
+  transactionId: string;
+  /** Human-readable description of each applied change */
+  changes: string[];
+  /** Confirm before this time or the change is rolled back */
+  confirmDeadline?: string;
+}
+
+export interface LinkConfig {
+  name: string;
+  addresses: string[];
+  dhcp: boolean;
+  ipv6Autoconf?: Ipv6Autoconf;
+  mtu?: number;
+  up: boolean;
+}
+
+export interface BridgeConfig {
+  name: string;
+  ports: string[];
+  stp: boolean;
+}
+
+export type BondMode =
+  | 'balance-rr'
+  | 'active-backup'
+  | 'balance-xor'
+  | 'broadcast'
+  | '802.3ad'
+  | 'balance-tlb'
+  | 'balance-alb';
+
+export interface BondConfig {
+  name: string;
+  mode: BondMode;
+  members: string[];
+  miimon?: number;
+  lacpRate?: 'slow' | 'fast';
+  xmitHashPolicy?: string;
+  primary?: string;
+}
+
+export interface CreateBondRequest extends BondConfig {
+  /** Seconds to wait for confirmation before rolling back (0 = no confirmation) */
+  confirmTimeoutSecs?: number;
+}
+
+export interface CreateVlanRequest {
+  parent: string;
+  vlanId: number;
+  /** Defaults to "<parent>.<vlanId>" */
+  name?: string;
+  addresses?: string[];
+  dhcp?: boolean;
+  /** Seconds to wait for confirmation before rolling back (0 = no confirmation) */
+  confirmTimeoutSecs?: number;
+}
+
+export interface VlanConfig {
+  name: string;
+  parent: string;
+  id: number;
+}
+
+export interface RouteConfig {
+  destination: string;
+  gateway?: string;
+  dev?: string;
+  metric?: number;
+}
+
+export interface HostNetworkConfig {
+  links: LinkConfig[];
+  bridges: BridgeConfig[];
+  bonds: BondConfig[];
+  vlans: VlanConfig[];
+  routes: RouteConfig[];
+  dns: DnsConfig;
+}
+
+export interface HostNetworkConfigResponse {
+  config: HostNetworkConfig;
+  pendingTransactionId?: string;
+  confirmDeadline?: string;
+}
+
+export interface NetworkPlanResponse {
+  changes: Record<string, unknown>[];
+  summary: string[];
 }
 
 export interface DnsConfig {
@@ -39,6 +142,85 @@ export interface HostnameConfig {
   hostname: string;
 }
 
+export type MirrorDirection = 'both' | 'ingress' | 'egress';
+
+export interface MirrorSession {
+  name: string;
+  bridge: string;
+  sourcePort: string;
+  outputPort: string;
+  direction: MirrorDirection;
+  txPackets: number;
+  txBytes: number;
+}
+
+export interface CreateMirrorRequest {
+  name: string;
+  /** OVS port to mirror; alternatively vmId + nic */
+  sourcePort?: string;
+  vmId?: string;
+  nic?: string;
+  outputPort: string;
+  direction?: MirrorDirection;
+}
+
+export interface CaptureOptions {
+  /** BPF filter, e.g. "tcp port 443" */
+  filter?: string;
+  maxBytes?: number;
+  maxDurationSecs?: number;
+  maxPackets?: number;
+  snaplen?: number;
+}
+
+export interface CaptureInfo {
+  vmId: string;
+  interface: string;
+  macAddress: string;
+  options: Required<Omit<CaptureOptions, 'filter' | 'maxPackets'>> & Pick<CaptureOptions, 'filter' | 'maxPackets'>;
+  state: 'running' | 'completed' | 'failed';
+  startedAt: string;
+  finishedAt?: string;
+  bytes: number;
+  stopReason?: string;
+  error?: string;
+}
+
+export interface StaticLease {
+  macAddress: string;
+  ipAddress: string;
+  hostname?: string;
+  vmId?: string;
+}
+
+export interface DhcpConfig {
+  bridge: string;
+  enabled: boolean;
+  subnet: string;
+  serverAddress?: string;
+  rangeStart: string;
+  rangeEnd: string;
+  gateway?: string;
+  dnsServers: string[];
+  domainName?: string;
+  leaseTimeSecs: number;
+  staticLeases: StaticLease[];
+}
+
+export interface DhcpServerStatus extends DhcpConfig {
+  running: boolean;
+  activeLeases: number;
+}
+
+export interface DhcpLease {
+  macAddress: string;
+  ipAddress: string;
+  hostname?: string;
+  /** Unix time; 0 for static leases */
+  expiresAt: number;
+  isStatic: boolean;
+}
+
 // Network interface operations
 export async function listNetworkInterfaces(): Promise<NetworkInterfaceList> {
   return get<NetworkInterfaceList>('/network/interfaces');
@@ -51,12 +233,140 @@ export async function getNetworkInterface(name: string): Promise<NetworkInterfac
 export async function configureNetworkInterface(
   name: string,
   config: ConfigureInterfaceRequest
-): Promise<NetworkInterface> {
-  return post<NetworkInterface>(`/network/interfaces/${name}/configure`, config);
+): Promise<NetworkApplyResponse> {
+  return post<NetworkApplyResponse>(`/network/interfaces/${name}/configure`, config);
+}
+
+export async function createBridge(request: CreateBridgeRequest): Promise<NetworkApplyResponse> {
+  return post<NetworkApplyResponse>('/network/bridges', request);
+}
+
+export async function listBridges(): Promise<{ bridges: BridgeConfig[] }> {
+  return get<{ bridges: BridgeConfig[] }>('/network/bridges');
+}
+
+export async function deleteBridge(name: string, confirmTimeoutSecs?: number): Promise<NetworkApplyResponse> {
+  return del<NetworkApplyResponse>(`/network/bridges/${name}${timeoutQuery(confirmTimeoutSecs)}`);
+}
+
+// Bond operations
+export async function listBonds(): Promise<{ bonds: BondConfig[] }> {
+  return get<{ bonds: BondConfig[] }>('/network/bonds');
+}
+
+export async function createBond(request: CreateBondRequest): Promise<NetworkApplyResponse> {
+  return post<NetworkApplyResponse>('/network/bonds', request);
+}
+
+export async function deleteBond(name: string, confirmTimeoutSecs?: number): Promise<NetworkApplyResponse> {
+  return del<NetworkApplyResponse>(`/network/bonds/${name}${timeoutQuery(confirmTimeoutSecs)}`);
+}
+
+// VLAN operations
+export async function listVlans(): Promise<{ vlans: VlanConfig[] }> {
+  return get<{ vlans: VlanConfig[] }>('/network/vlans');
+}
+
+export async function createVlan(request: CreateVlanRequest): Promise<NetworkApplyResponse> {
+  return post<NetworkApplyResponse>('/network/vlans', request);
+}
+
+export async function deleteVlan(name: string, confirmTimeoutSecs?: number): Promise<NetworkApplyResponse> {
+  return del<NetworkApplyResponse>(`/network/vlans/${name}${timeoutQuery(confirmTimeoutSecs)}`);
+}
+
+function timeoutQuery(confirmTimeoutSecs?: number): string {
+  return confirmTimeoutSecs === undefined ? '' : `?confirmTimeoutSecs=${confirmTimeoutSecs}`;
+}
+
+// Host network transactions
+export async function getHostNetworkConfig(): Promise<HostNetworkConfigResponse> {
+  return get<HostNetworkConfigResponse>('/network/config');
+}
+
+export async function planHostNetworkConfig(config: HostNetworkConfig): Promise<NetworkPlanResponse> {
+  return post<NetworkPlanResponse>('/network/config/plan', config);
+}
+
+export async function applyHostNetworkConfig(
+  config: HostNetworkConfig,
+  confirmTimeoutSecs?: number
+): Promise<NetworkApplyResponse> {
+  return post<NetworkApplyResponse>('/network/config/apply', { config, confirmTimeoutSecs });
+}
+
+export async function confirmNetworkChange(transactionId: string): Promise<void> {
+  return post<void>('/network/config/confirm', { transactionId });
+}
+
+export async function rollbackNetworkChange(transactionId: string): Promise<void> {
+  return post<void>('/network/config/rollback', { transactionId });
+}
+
+// Port mirrors
+export async function listMirrors(): Promise<{ mirrors: MirrorSession[] }> {
+  return get<{ mirrors: MirrorSession[] }>('/network/mirrors');
+}
+
+export async function createMirror(request: CreateMirrorRequest): Promise<MirrorSession> {
+  return post<MirrorSession>('/network/mirrors', request);
+}
+
+export async function deleteMirror(name: string): Promise<void> {
+  return del<void>(`/network/mirrors/${encodeURIComponent(name)}`);
+}
+
+// Packet capture on VM NICs (nic = tap name, MAC address or index)
+function capturePath(vmId: string, nic: string): string {
+  return `/vms/${encodeURIComponent(vmId)}/nics/${encodeURIComponent(nic)}/capture`;
+}
+
+export async function startCapture(vmId: string, nic: string, options: CaptureOptions = {}): Promise<CaptureInfo> {
+  return post<CaptureInfo>(capturePath(vmId, nic), options);
+}
+
+export async function getCaptureStatus(vmId: string, nic: string): Promise<CaptureInfo> {
+  return get<CaptureInfo>(`${capturePath(vmId, nic)}/status`);
+}
+
+export async function stopCapture(vmId: string, nic: string): Promise<CaptureInfo> {
+  return del<CaptureInfo>(capturePath(vmId, nic));
+}
+
+/** URL of the pcap download (use as a link href) */
+export function captureDownloadUrl(vmId: string, nic: string): string {
+  return `${getApiBase()}${capturePath(vmId, nic)}`;
+}
+
+// DHCP operations
+export async function listDhcpServers(): Promise<{ servers: DhcpServerStatus[] }> {
+  return get<{ servers: DhcpServerStatus[] }>('/network/dhcp');
+}
+
+export async function putDhcpServer(config: DhcpConfig): Promise<DhcpServerStatus> {
+  return put<DhcpServerStatus>(`/network/dhcp/${encodeURIComponent(config.bridge)}`, config);
+}
+
+export async function deleteDhcpServer(bridge: string): Promise<void> {
+  return del<void>(`/network/dhcp/${encodeURIComponent(bridge)}`);
+}
+
+export async function listDhcpLeases(bridge: string): Promise<{ leases: DhcpLease[] }> {
+  return get<{ leases: DhcpLease[] }>(`/network/dhcp/${encodeURIComponent(bridge)}/leases`);
+}
+
+export async function setStaticLease(bridge: string, lease: StaticLease): Promise<DhcpServerStatus> {
+  const { macAddress, ...body } = lease;
+  return put<DhcpServerStatus>(
+    `/network/dhcp/${encodeURIComponent(bridge)}/static-leases/${encodeURIComponent(macAddress)}`,
+    body
+  );
 }
 
-export async function createBridge(request: CreateBridgeRequest): Promise<NetworkInterface> {
-  return post<NetworkInterface>('/network/bridges', request);
+export async function deleteStaticLease(bridge: string, macAddress: string): Promise<DhcpServerStatus> {
+  return del<DhcpServerStatus>(
+    `/network/dhcp/${encodeURIComponent(bridge)}/static-leases/${encodeURIComponent(macAddress)}`
+  );
 }
 
 // DNS operations
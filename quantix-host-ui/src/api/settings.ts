@@ -2,7 +2,7 @@
  * Settings API - Endpoints for system configuration
  */
 
-import { get, post } from './client';
+import { get, post, put } from './client';
 
 /**
  * Node settings response
@@ -188,4 +188,75 @@ export async function enableSsh(request: EnableSshRequest): Promise<SshStatus> {
  */
 export async function disableSsh(): Promise<SshStatus> {
   return post<SshStatus>('/settings/ssh/disable');
-}
\ No newline at end of file
+}
+// ============================================================================
+// Host Firewall
+// ============================================================================
+
+export type ManagementService = 'web-ui' | 'grpc' | 'ssh' | 'vnc' | 'migration';
+
+/**
+ * Access rule for one management service
+ */
+export interface FirewallServiceRule {
+  service: ManagementService;
+  /** TCP ports or ranges ("8443", "5900-5999") */
+  ports: string[];
+  /** false closes the service to all networks */
+  enabled: boolean;
+  /** Allowed source addresses or CIDRs (empty = any) */
+  allowedSources: string[];
+}
+
+export interface HostFirewallConfig {
+  /** false removes the firewall (all ports open) */
+  enabled: boolean;
+  services: FirewallServiceRule[];
+}
+
+/**
+ * Host firewall configuration and pending change
+ */
+export interface HostFirewall {
+  config: HostFirewallConfig;
+  pendingTransactionId?: string;
+  /** Confirm before this time or the change is rolled back */
+  confirmDeadline?: string;
+}
+
+/**
+ * Update host firewall request
+ */
+export interface UpdateHostFirewallRequest {
+  config: HostFirewallConfig;
+  /** Seconds to wait for confirmation before rolling back (default 60, 0 = none) */
+  confirmTimeoutSecs?: number;
+}
+
+/**
+ * Get the host firewall configuration
+ */
+export async function getHostFirewall(): Promise<HostFirewall> {
+  return get<HostFirewall>('/settings/firewall');
+}
+
+/**
+ * Apply a host firewall configuration (rolled back unless confirmed)
+ */
+export async function updateHostFirewall(request: UpdateHostFirewallRequest): Promise<HostFirewall> {
+  return put<HostFirewall>('/settings/firewall', request);
+}
+
+/**
+ * Keep a pending host firewall change
+ */
+export async function confirmHostFirewall(transactionId: string): Promise<void> {
+  return post('/settings/firewall/confirm', { transactionId });
+}
+
+/**
+ * Revert a pending host firewall change now
+ */
+export async function rollbackHostFirewall(transactionId: string): Promise<void> {
+  return post('/settings/firewall/rollback', { transactionId });
+}
@@ -57,6 +57,8 @@ export interface VirtualMachine {
   memoryTotalBytes: number;
   startedAt?: string;
   guestAgent?: GuestAgentInfo;
+  /** Guest addresses (from the agent, or from node DHCP leases) */
+  ipAddresses?: string[];
   disks?: DiskSpec[];
 }
 
@@ -155,6 +157,38 @@ export interface Snapshot {
   parentId?: string;
 }
 
+// ============================================================================
+// Guest Agent Types
+// ============================================================================
+
+export interface GuestCapabilities {
+  version: string;
+  os: string;
+  architecture: string;
+  capabilities: string[];
+  features: Record<string, string>;
+  buildTime?: string;
+  buildCommit?: string;
+}
+
+export interface GuestService {
+  name: string;
+  displayName: string;
+  state: string;
+  startType: string;
+  description?: string;
+  pid?: number;
+  memoryBytes: number;
+}
+
+export type GuestServiceAction = 'start' | 'stop' | 'restart' | 'enable' | 'disable' | 'status';
+
+export interface GuestServiceControlResult {
+  name: string;
+  action: GuestServiceAction;
+  newState: string;
+}
+
 // ============================================================================
 // Storage Types
 // ============================================================================
@@ -7,7 +7,11 @@ import type {
   VirtualMachine, 
   CreateVmRequest, 
   ConsoleInfo, 
-  Snapshot 
+  Snapshot,
+  GuestCapabilities,
+  GuestService,
+  GuestServiceAction,
+  GuestServiceControlResult,
 } from './types';
 
 /**
@@ -145,3 +149,38 @@ export async function revertSnapshot(vmId: string, snapshotId: string): Promise<
 export async function deleteSnapshot(vmId: string, snapshotId: string): Promise<void> {
   return del(`/vms/${vmId}/snapshots/${snapshotId}`);
 }
+
+// ============================================================================
+// Guest Agent Operations
+// ============================================================================
+
+/**
+ * Get the capabilities of the VM's guest agent
+ */
+export async function getGuestCapabilities(vmId: string): Promise<GuestCapabilities> {
+  return get<GuestCapabilities>(`/vms/${vmId}/agent/capabilities`);
+}
+
+/**
+ * List services in the guest (systemd units or Windows services)
+ */
+export async function listGuestServices(vmId: string, filter = '', runningOnly = false): Promise<GuestService[]> {
+  const params = new URLSearchParams({ filter, runningOnly: String(runningOnly) });
+  const response = await get<{ services: GuestService[] }>(`/vms/${vmId}/agent/services?${params}`);
+  return response.services;
+}
+
+/**
+ * Start, stop, restart, enable or disable a guest service
+ *
+ * Fails with status 501 if the guest agent is too old to manage services.
+ */
+export async function controlGuestService(
+  vmId: string,
+  service: string,
+  action: GuestServiceAction
+): Promise<GuestServiceControlResult> {
+  return post<GuestServiceControlResult>(
+    `/vms/${vmId}/agent/services/${encodeURIComponent(service)}/${action}`
+  );
+}
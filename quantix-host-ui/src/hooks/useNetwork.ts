@@ -11,6 +11,8 @@ const NETWORK_KEYS = {
   all: ['network'] as const,
   interfaces: () => [...NETWORK_KEYS.all, 'interfaces'] as const,
   interface: (name: string) => [...NETWORK_KEYS.interfaces(), name] as const,
+  bonds: () => [...NETWORK_KEYS.all, 'bonds'] as const,
+  vlans: () => [...NETWORK_KEYS.all, 'vlans'] as const,
   dns: () => [...NETWORK_KEYS.all, 'dns'] as const,
   hostname: () => [...NETWORK_KEYS.all, 'hostname'] as const,
 };
@@ -39,7 +41,7 @@ export function useConfigureInterface() {
 
   return useMutation({
     mutationFn: ({ name, config }: { name: string; config: networkApi.ConfigureInterfaceRequest }) =>
-      networkApi.configureNetworkInterface(name, config),
+      applyAndConfirm(networkApi.configureNetworkInterface(name, config)),
     onSuccess: (_, variables) => {
       queryClient.invalidateQueries({ queryKey: NETWORK_KEYS.interfaces() });
       queryClient.invalidateQueries({ queryKey: NETWORK_KEYS.interface(variables.name) });
@@ -56,10 +58,10 @@ export function useCreateBridge() {
   const queryClient = useQueryClient();
 
   return useMutation({
-    mutationFn: networkApi.createBridge,
-    onSuccess: (data) => {
+    mutationFn: (request: networkApi.CreateBridgeRequest) => applyAndConfirm(networkApi.createBridge(request)),
+    onSuccess: (_, variables) => {
       queryClient.invalidateQueries({ queryKey: NETWORK_KEYS.interfaces() });
-      toast.success(`Bridge ${data.name} created successfully`);
+      toast.success(`Bridge ${variables.name} created successfully`);
     },
     onError: (error: Error) => {
       toast.error(`Failed to create bridge: ${error.message}`);
@@ -67,6 +69,91 @@ export function useCreateBridge() {
   });
 }
 
+// Apply a change and confirm it once the node answers over the new configuration
+async function applyAndConfirm(apply: Promise<networkApi.NetworkApplyResponse>) {
+  const result = await apply;
+  if (result.transactionId) {
+    await networkApi.confirmNetworkChange(result.transactionId);
+  }
+  return result;
+}
+
+// Bonds
+export function useBonds() {
+  return useQuery({
+    queryKey: NETWORK_KEYS.bonds(),
+    queryFn: networkApi.listBonds,
+  });
+}
+
+export function useCreateBond() {
+  const queryClient = useQueryClient();
+
+  return useMutation({
+    mutationFn: (request: networkApi.CreateBondRequest) => applyAndConfirm(networkApi.createBond(request)),
+    onSuccess: (_, variables) => {
+      queryClient.invalidateQueries({ queryKey: NETWORK_KEYS.all });
+      toast.success(`Bond ${variables.name} created successfully`);
+    },
+    onError: (error: Error) => {
+      toast.error(`Failed to create bond: ${error.message}`);
+    },
+  });
+}
+
+export function useDeleteBond() {
+  const queryClient = useQueryClient();
+
+  return useMutation({
+    mutationFn: (name: string) => applyAndConfirm(networkApi.deleteBond(name)),
+    onSuccess: (_, name) => {
+      queryClient.invalidateQueries({ queryKey: NETWORK_KEYS.all });
+      toast.success(`Bond ${name} deleted`);
+    },
+    onError: (error: Error) => {
+      toast.error(`Failed to delete bond: ${error.message}`);
+    },
+  });
+}
+
+// VLAN sub-interfaces
+export function useVlans() {
+  return useQuery({
+    queryKey: NETWORK_KEYS.vlans(),
+    queryFn: networkApi.listVlans,
+  });
+}
+
+export function useCreateVlan() {
+  const queryClient = useQueryClient();
+
+  return useMutation({
+    mutationFn: (request: networkApi.CreateVlanRequest) => applyAndConfirm(networkApi.createVlan(request)),
+    onSuccess: (_, variables) => {
+      queryClient.invalidateQueries({ queryKey: NETWORK_KEYS.all });
+      toast.success(`VLAN ${variables.vlanId} on ${variables.parent} created successfully`);
+    },
+    onError: (error: Error) => {
+      toast.error(`Failed to create VLAN: ${error.message}`);
+    },
+  });
+}
+
+export function useDeleteVlan() {
+  const queryClient = useQueryClient();
+
+  return useMutation({
+    mutationFn: (name: string) => applyAndConfirm(networkApi.deleteVlan(name)),
+    onSuccess: (_, name) => {
+      queryClient.invalidateQueries({ queryKey: NETWORK_KEYS.all });
+      toast.success(`VLAN interface ${name} deleted`);
+    },
+    onError: (error: Error) => {
+      toast.error(`Failed to delete VLAN: ${error.message}`);
+    },
+  });
+}
+
 // DNS configuration
 export function useDnsConfig() {
   return useQuery({
@@ -15,8 +15,10 @@ import {
   createSnapshot,
   revertSnapshot,
   deleteSnapshot,
+  listGuestServices,
+  controlGuestService,
 } from '@/api/vm';
-import type { CreateVmRequest } from '@/api/types';
+import type { CreateVmRequest, GuestServiceAction } from '@/api/types';
 import { toast } from '@/lib/toast';
 
 /**
@@ -260,3 +262,35 @@ export function useVMSnapshotOps(vmId: string) {
 
   return { create, revert, remove };
 }
+
+/**
+ * Hook to list services inside a VM via the guest agent
+ */
+export function useGuestServices(vmId: string, enabled = true) {
+  return useQuery({
+    queryKey: ['vms', vmId, 'guest-services'],
+    queryFn: () => listGuestServices(vmId),
+    staleTime: 10_000,
+    enabled: !!vmId && enabled,
+    retry: false,
+  });
+}
+
+/**
+ * Hook to control a service inside a VM via the guest agent
+ */
+export function useControlGuestService(vmId: string) {
+  const queryClient = useQueryClient();
+
+  return useMutation({
+    mutationFn: ({ service, action }: { service: string; action: GuestServiceAction }) =>
+      controlGuestService(vmId, service, action),
+    onSuccess: (result) => {
+      queryClient.invalidateQueries({ queryKey: ['vms', vmId, 'guest-services'] });
+      toast.success(`Service ${result.name}: ${result.newState || result.action}`);
+    },
+    onError: (error: Error) => {
+      toast.error(`Failed to control service: ${error.message}`);
+    },
+  });
+}
@@ -81,6 +81,8 @@ struct App {
     update_available: Option<String>,
     /// Last time we checked for updates
     last_update_check: std::time::Instant,
+    /// Host firewall screen state
+    firewall: FirewallState,
 }
 
 /// Static IP configuration
@@ -159,6 +161,43 @@ impl Default for ClusterConfig {
     }
 }
 
+/// Host firewall configuration as returned by `GET /api/v1/settings/firewall`
+#[derive(Default, Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HostFirewallConfig {
+    enabled: bool,
+    services: Vec<FirewallServiceRule>,
+}
+
+/// Access rule for one management service
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FirewallServiceRule {
+    /// "web-ui", "grpc", "ssh", "vnc" or "migration"
+    service: String,
+    ports: Vec<String>,
+    /// false closes the service to all networks
+    enabled: bool,
+    /// Allowed source addresses/CIDRs (empty = any)
+    allowed_sources: Vec<String>,
+}
+
+/// Host firewall screen state
+#[derive(Default, Clone)]
+struct FirewallState {
+    /// Configuration being edited (None until loaded)
+    config: Option<HostFirewallConfig>,
+    /// Remote change waiting for confirmation
+    pending_transaction_id: Option<String>,
+    confirm_deadline: Option<String>,
+    /// Selected row (0 = firewall on/off, then one row per service)
+    selected: usize,
+    /// Allowed sources being typed for the selected service
+    editing: Option<String>,
+    /// Edits not yet applied
+    dirty: bool,
+}
+
 /// Application screens
 #[derive(Clone, Copy, PartialEq, Debug)]
 enum Screen {
@@ -174,6 +213,7 @@ enum Screen {
     Auth,
     FactoryReset,
     Shell,
+    Firewall,
 }
 
 impl App {
@@ -239,6 +279,7 @@ impl App {
             last_cluster_refresh: std::time::Instant::now(),
             update_available: None,
             last_update_check: std::time::Instant::now(),
+            firewall: FirewallState::default(),
         }
     }
 
@@ -284,6 +325,7 @@ impl App {
             ("Refresh Display", "F5"),
             ("Restart Management Services", "F6"),
             ("View System Logs", "F7"),
+            ("Configure Host Firewall", "F8"),
             ("Reset to Factory Defaults", "F9"),
             ("Shutdown / Reboot", "F10"),
         ]
@@ -454,8 +496,9 @@ fn handle_input(app: &mut App, key: KeyCode, modifiers: KeyModifiers) {
             KeyCode::F(5) => handle_menu_action(app, 4),  // Refresh
             KeyCode::F(6) => handle_menu_action(app, 5),  // Restart Services
             KeyCode::F(7) => handle_menu_action(app, 6),  // Logs
-            KeyCode::F(9) => handle_menu_action(app, 7),  // Factory Reset
-            KeyCode::F(10) => handle_menu_action(app, 8), // Shutdown
+            KeyCode::F(8) => handle_menu_action(app, 7),  // Firewall
+            KeyCode::F(9) => handle_menu_action(app, 8),  // Factory Reset
+            KeyCode::F(10) => handle_menu_action(app, 9), // Shutdown
             _ => {}
         },
         Screen::Network => match key {
@@ -466,6 +509,10 @@ fn handle_input(app: &mut App, key: KeyCode, modifiers: KeyModifiers) {
                 app.success_message = Some("Running DHCP on all interfaces...".to_string());
                 run_dhcp_all();
             }
+            KeyCode::Char('6') => {
+                app.success_message = Some("Enabling IPv6 autoconfiguration on all interfaces...".to_string());
+                run_ipv6_autoconf_all();
+            }
             KeyCode::Char('r') | KeyCode::Char('R') => {
                 app.success_message = Some("Restarting network service...".to_string());
                 restart_network();
@@ -498,6 +545,7 @@ fn handle_input(app: &mut App, key: KeyCode, modifiers: KeyModifiers) {
         Screen::WiFi => handle_wifi_input(app, key),
         Screen::Ssh => handle_ssh_input(app, key),
         Screen::Cluster => handle_cluster_input(app, key),
+        Screen::Firewall => handle_firewall_input(app, key),
         Screen::FactoryReset => match key {
             KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('n') | KeyCode::Char('N') => {
                 app.screen = Screen::Main;
@@ -578,10 +626,16 @@ fn handle_menu_action(app: &mut App, index: usize) {
         }
         6 => app.screen = Screen::Diagnostics,
         7 => {
+            // Go to Host Firewall screen with the current configuration
+            app.firewall = FirewallState::default();
+            load_firewall(app);
+            app.screen = Screen::Firewall;
+        }
+        8 => {
             // Go to Factory Reset confirmation screen
             app.screen = Screen::FactoryReset;
         }
-        8 => app.screen = Screen::Power,
+        9 => app.screen = Screen::Power,
         _ => {}
     }
 }
@@ -716,8 +770,8 @@ fn handle_static_ip_input(app: &mut App, key: KeyCode) {
             }
         }
         KeyCode::Char(c) => {
-            // Only allow valid IP characters
-            if c.is_ascii_digit() || c == '.' {
+            // Only allow valid IPv4/IPv6 characters
+            if c.is_ascii_hexdigit() || c == '.' || c == ':' {
                 match app.input_field_index {
                     1 => app.static_ip_config.ip_address.push(c),
                     2 => app.static_ip_config.netmask.push(c),
@@ -742,6 +796,10 @@ fn handle_static_ip_input(app: &mut App, key: KeyCode) {
                 app.error_message = Some("IP address is required".to_string());
             } else if !is_valid_ip(&app.static_ip_config.ip_address) {
                 app.error_message = Some("Invalid IP address format".to_string());
+            } else if !app.static_ip_config.gateway.is_empty()
+                && !same_family(&app.static_ip_config.ip_address, &app.static_ip_config.gateway)
+            {
+                app.error_message = Some("Gateway must be the same IP version as the address".to_string());
             } else {
                 match apply_static_ip(&app.static_ip_config) {
                     Ok(_) => {
@@ -835,82 +893,92 @@ fn handle_wifi_input(app: &mut App, key: KeyCode) {
 }
 
 fn is_valid_ip(ip: &str) -> bool {
-    let parts: Vec<&str> = ip.split('.').collect();
-    if parts.len() != 4 {
-        return false;
-    }
-    for part in parts {
-        match part.parse::<u8>() {
-            Ok(_) => continue,
-            Err(_) => return false,
-        }
+    ip.parse::<std::net::IpAddr>().is_ok()
+}
+
+fn is_ipv6(ip: &str) -> bool {
+    ip.parse::<std::net::Ipv6Addr>().is_ok()
+}
+
+fn same_family(a: &str, b: &str) -> bool {
+    match (a.parse::<std::net::IpAddr>(), b.parse::<std::net::IpAddr>()) {
+        (Ok(a), Ok(b)) => a.is_ipv6() == b.is_ipv6(),
+        _ => false,
     }
-    true
 }
 
-fn apply_static_ip(config: &StaticIpConfig) -> Result<()> {
+/// Apply the static configuration to the running system and verify it.
+fn configure_static_ip(config: &StaticIpConfig) -> Result<()> {
     use std::process::Stdio;
     
-    // CRITICAL: Kill any DHCP clients first, otherwise they will override our static IP
-    // Kill udhcpc for this specific interface
-    let _ = std::process::Command::new("pkill")
-        .args(["-f", &format!("udhcpc.*{}", config.interface)])
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .output();
+    let ipv6 = is_ipv6(&config.ip_address);
+    let family = if ipv6 { "-6" } else { "-4" };
     
-    // Kill all udhcpc processes (nuclear option)
-    let _ = std::process::Command::new("killall")
-        .args(["udhcpc"])
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .output();
+    if ipv6 {
+        // Only the DHCPv6 client and SLAAC compete with a static IPv6 address;
+        // IPv4 (DHCP or static) on the same interface is left alone
+        let _ = std::process::Command::new("pkill")
+            .args(["-f", &format!("udhcpc6.*{}", config.interface)])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .output();
+        let _ = std::process::Command::new("pkill")
+            .args(["-f", &format!("dhclient -6.*{}", config.interface)])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .output();
+        set_ipv6_sysctl(&config.interface, "autoconf", "0");
+        set_ipv6_sysctl(&config.interface, "accept_ra", "0");
+    } else {
+        // CRITICAL: Kill any DHCP clients first, otherwise they will override our static IP
+        // Kill udhcpc for this specific interface
+        let _ = std::process::Command::new("pkill")
+            .args(["-f", &format!("udhcpc .*{}", config.interface)])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .output();
     
-    // Kill dhclient too
-    let _ = std::process::Command::new("pkill")
-        .args(["-f", &format!("dhclient.*{}", config.interface)])
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .output();
+        // Kill all udhcpc processes (nuclear option)
+        let _ = std::process::Command::new("killall")
+            .args(["udhcpc"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .output();
+        
+        // Kill dhclient too
+        let _ = std::process::Command::new("pkill")
+            .args(["-f", &format!("dhclient -[^6].*{}", config.interface)])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .output();
+    }
     
     // Wait for DHCP to die
     std::thread::sleep(std::time::Duration::from_millis(500));
     
-    // Flush existing IP on interface
-    let _ = std::process::Command::new("ip")
-        .args(["addr", "flush", "dev", &config.interface])
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .output();
+    // Flush existing addresses of this family (IPv6 link-local stays)
+    if ipv6 {
+        run_ip(&["-6", "addr", "flush", "dev", &config.interface, "scope", "global"])?;
+    } else {
+        run_ip(&["-4", "addr", "flush", "dev", &config.interface])?;
+    }
     
     // Bring interface up
-    let _ = std::process::Command::new("ip")
-        .args(["link", "set", &config.interface, "up"])
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .output();
+    run_ip(&["link", "set", &config.interface, "up"])?;
     
-    // Calculate CIDR prefix from netmask
-    let prefix = netmask_to_cidr(&config.netmask);
+    // Calculate CIDR prefix from netmask (or prefix length)
+    let prefix = netmask_to_cidr(&config.netmask, ipv6);
     let ip_cidr = format!("{}/{}", config.ip_address, prefix);
     
     // Add the IP address
-    let result = std::process::Command::new("ip")
-        .args(["addr", "add", &ip_cidr, "dev", &config.interface])
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .output()?;
-    
-    if !result.status.success() {
-        return Err(anyhow::anyhow!("Failed to set IP address"));
-    }
+    run_ip(&[family, "addr", "add", &ip_cidr, "dev", &config.interface])?;
     
     // Set default gateway if provided
     if !config.gateway.is_empty() {
         // Remove ALL existing default routes first
         loop {
             let del_result = std::process::Command::new("ip")
-                .args(["route", "del", "default"])
+                .args([family, "route", "del", "default"])
                 .stdout(Stdio::null())
                 .stderr(Stdio::null())
                 .output();
@@ -924,36 +992,29 @@ fn apply_static_ip(config: &StaticIpConfig) -> Result<()> {
         std::thread::sleep(std::time::Duration::from_millis(100));
         
         // Add new default route with device specified
-        let route_result = std::process::Command::new("ip")
-            .args(["route", "add", "default", "via", &config.gateway, "dev", &config.interface])
-            .output();
-        
-        if let Ok(output) = route_result {
-            if !output.status.success() {
-                // Log the error but continue - route might already exist
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                tracing::warn!("Failed to add default route: {}", stderr);
-            }
-        }
+        run_ip(&[family, "route", "replace", "default", "via", &config.gateway, "dev", &config.interface])?;
     }
     
     // Set DNS if provided
     if !config.dns.is_empty() {
         let resolv_content = format!("nameserver {}\n", config.dns);
-        let _ = std::fs::write("/etc/resolv.conf", resolv_content);
+        std::fs::write("/etc/resolv.conf", resolv_content)?;
     }
     
     // Verify the configuration was applied
     std::thread::sleep(std::time::Duration::from_millis(500));
     
-    // Check if IP was assigned
+    // Check if IP was assigned (`ip` prints IPv6 addresses in canonical form)
+    let canonical = config.ip_address.parse::<std::net::IpAddr>()
+        .map(|ip| ip.to_string())
+        .unwrap_or_else(|_| config.ip_address.clone());
     let verify_ip = std::process::Command::new("ip")
-        .args(["addr", "show", "dev", &config.interface])
+        .args([family, "addr", "show", "dev", &config.interface])
         .output();
     
     if let Ok(output) = verify_ip {
         let stdout = String::from_utf8_lossy(&output.stdout);
-        if !stdout.contains(&config.ip_address) {
+        if !stdout.contains(&format!(" {}/", canonical)) {
             tracing::error!("IP address {} not found on interface {}", config.ip_address, config.interface);
             return Err(anyhow::anyhow!("IP address was not applied correctly"));
         }
@@ -961,27 +1022,198 @@ fn apply_static_ip(config: &StaticIpConfig) -> Result<()> {
     
     // Check if route was added
     let verify_route = std::process::Command::new("ip")
-        .args(["route", "show", "default"])
+        .args([family, "route", "show", "default"])
         .output();
     
     if let Ok(output) = verify_route {
         let stdout = String::from_utf8_lossy(&output.stdout);
-        if !config.gateway.is_empty() && !stdout.contains(&config.gateway) {
-            tracing::warn!("Default route via {} not found, but continuing", config.gateway);
+        let gateway = config.gateway.parse::<std::net::IpAddr>()
+            .map(|ip| ip.to_string())
+            .unwrap_or_else(|_| config.gateway.clone());
+        if !config.gateway.is_empty() && !stdout.contains(&gateway) {
+            return Err(anyhow::anyhow!("Default route via {} was not applied", config.gateway));
         }
     }
     
+    // The gateway must answer, otherwise the host would drop off the network
+    if !config.gateway.is_empty() && !wait_for_reachable(&config.gateway, 5) {
+        return Err(anyhow::anyhow!("Gateway {} is not reachable", config.gateway));
+    }
+    
+    Ok(())
+}
+    
+
+/// Network state of an interface before a change, used to undo it.
+struct NetworkSnapshot {
+    interface: String,
+    /// Whether this snapshot covers IPv6 rather than IPv4
+    ipv6: bool,
+    /// Global addresses of that family in CIDR form
+    addresses: Vec<String>,
+    /// `ip route show default` lines
+    default_routes: Vec<String>,
+    resolv_conf: Option<String>,
+    /// DHCP (IPv4) or autoconfiguration (IPv6) was in use
+    dhcp: bool,
+}
+
+impl NetworkSnapshot {
+    fn capture(interface: &str, ipv6: bool) -> Self {
+        let family = if ipv6 { "-6" } else { "-4" };
+        let mut addresses = Vec::new();
+        let mut dhcp = false;
+        if let Ok(output) = std::process::Command::new("ip")
+            .args(["-o", family, "addr", "show", "dev", interface, "scope", "global"])
+            .output()
+        {
+            for line in String::from_utf8_lossy(&output.stdout).lines() {
+                let mut fields = line.split_whitespace();
+                if fields.any(|f| f == "inet" || f == "inet6") {
+                    if let Some(cidr) = fields.next() {
+                        addresses.push(cidr.to_string());
+                    }
+                }
+                dhcp |= line.contains(" dynamic");
+            }
+        }
+        
+        let default_routes = std::process::Command::new("ip")
+            .args([family, "route", "show", "default"])
+            .output()
+            .map(|o| {
+                String::from_utf8_lossy(&o.stdout)
+                    .lines()
+                    .map(|l| l.trim().to_string())
+                    .filter(|l| !l.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        
+        Self {
+            interface: interface.to_string(),
+            ipv6,
+            addresses,
+            default_routes,
+            resolv_conf: std::fs::read_to_string("/etc/resolv.conf").ok(),
+            dhcp,
+        }
+    }
+    
+    /// Put the interface back the way it was (best effort).
+    fn restore(&self) {
+        tracing::warn!("Restoring previous network configuration of {}", self.interface);
+        
+        let family = if self.ipv6 { "-6" } else { "-4" };
+        let _ = run_ip(&[family, "addr", "flush", "dev", &self.interface, "scope", "global"]);
+        let _ = run_ip(&["link", "set", &self.interface, "up"]);
+        
+        if self.dhcp && self.ipv6 {
+            // Addresses come back with the next router advertisement
+            set_ipv6_sysctl(&self.interface, "accept_ra", "2");
+            set_ipv6_sysctl(&self.interface, "autoconf", "1");
+        } else if self.dhcp {
+            let _ = std::process::Command::new("udhcpc")
+                .args(["-i", &self.interface, "-b", "-q"])
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .spawn();
+        } else {
+            for addr in &self.addresses {
+                if let Err(e) = run_ip(&[family, "addr", "add", addr, "dev", &self.interface]) {
+                    tracing::error!("Failed to restore address {}: {}", addr, e);
+                }
+            }
+        }
+        
+        while run_ip(&[family, "route", "del", "default"]).is_ok() {}
+        for route in &self.default_routes {
+            let mut args = vec![family, "route", "add"];
+            args.extend(route.split_whitespace());
+            if let Err(e) = run_ip(&args) {
+                tracing::error!("Failed to restore route '{}': {}", route, e);
+            }
+        }
+        
+        if let Some(ref content) = self.resolv_conf {
+            let _ = std::fs::write("/etc/resolv.conf", content);
+        }
+    }
+}
+
+/// Set `net.ipv6.conf.<interface>.<key>` (best effort).
+fn set_ipv6_sysctl(interface: &str, key: &str, value: &str) {
+    let path = format!("/proc/sys/net/ipv6/conf/{}/{}", interface, key);
+    if let Err(e) = std::fs::write(&path, value) {
+        tracing::warn!("Failed to set {}: {}", path, e);
+    }
+}
+
+/// Run `ip` and turn a non-zero exit into an error carrying its stderr.
+fn run_ip(args: &[&str]) -> Result<()> {
+    let output = std::process::Command::new("ip").args(args).output()?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "ip {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Ping `address` until it answers or `attempts` pings have failed.
+fn wait_for_reachable(address: &str, attempts: u32) -> bool {
+    (0..attempts).any(|_| {
+        std::process::Command::new("ping")
+            .args(["-c", "1", "-W", "2", address])
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .status()
+            .map(|s| s.success())
+            .unwrap_or(false)
+    })
+}
+
+fn apply_static_ip(config: &StaticIpConfig) -> Result<()> {
+    use std::process::Stdio;
+    
+    // Remember the working configuration so a bad address or gateway
+    // doesn't leave the host unreachable
+    let ipv6 = is_ipv6(&config.ip_address);
+    let snapshot = NetworkSnapshot::capture(&config.interface, ipv6);
+    if let Err(e) = configure_static_ip(config) {
+        tracing::error!("Static IP configuration failed: {}", e);
+        snapshot.restore();
+        return Err(e);
+    }
+    
     // Save to Quantix network config directory for current session
     // The quantix-network service reads from /etc/quantix/network/
     let config_dir = "/etc/quantix/network";
     let _ = std::fs::create_dir_all(config_dir);
     
-    // Save interface-specific config
-    let interface_config = format!(
-        "# Static IP configuration for {}\n# Generated by Quantix Console\nIP_ADDRESS={}\nNETMASK={}\nGATEWAY={}\nDNS={}\n",
-        config.interface, config.ip_address, config.netmask, config.gateway, config.dns
-    );
+    // Save interface-specific config. IPv4 and IPv6 settings share the
+    // file, so only the keys of the configured family are replaced.
+    let updates: Vec<(&str, String)> = if ipv6 {
+        vec![
+            ("IPV6_MODE", "static".to_string()),
+            ("IPV6_ADDRESS", config.ip_address.clone()),
+            ("IPV6_PREFIX", netmask_to_cidr(&config.netmask, true).to_string()),
+            ("IPV6_GATEWAY", config.gateway.clone()),
+            ("DNS", config.dns.clone()),
+        ]
+    } else {
+        vec![
+            ("IP_ADDRESS", config.ip_address.clone()),
+            ("NETMASK", config.netmask.clone()),
+            ("GATEWAY", config.gateway.clone()),
+            ("DNS", config.dns.clone()),
+        ]
+    };
     let interface_config_path = format!("{}/{}.conf", config_dir, config.interface);
+    let existing = std::fs::read_to_string(&interface_config_path).unwrap_or_default();
+    let interface_config = merge_network_config(&existing, &config.interface, &updates);
     let _ = std::fs::write(&interface_config_path, &interface_config);
     
     // Also save as global static.conf (applies to any active interface)
@@ -1001,11 +1233,10 @@ fn apply_static_ip(config: &StaticIpConfig) -> Result<()> {
     }
     
     // Also save to /etc/network/interfaces for legacy compatibility
-    let interfaces_content = format!(
-        "auto lo\niface lo inet loopback\n\nauto {}\niface {} inet static\n    address {}\n    netmask {}\n    gateway {}\n",
-        config.interface, config.interface, config.ip_address, config.netmask, config.gateway
+    let _ = std::fs::write(
+        "/etc/network/interfaces",
+        legacy_interfaces(&config.interface, &interface_config),
     );
-    let _ = std::fs::write("/etc/network/interfaces", interfaces_content);
     
     // Restart node daemon to bind to new IP (do this in background)
     // This ensures the web UI is accessible at the new IP address
@@ -1020,7 +1251,68 @@ fn apply_static_ip(config: &StaticIpConfig) -> Result<()> {
     Ok(())
 }
 
-fn netmask_to_cidr(netmask: &str) -> u8 {
+/// Replace `updates` in a `KEY=value` network config, keeping other keys.
+fn merge_network_config(existing: &str, interface: &str, updates: &[(&str, String)]) -> String {
+    let mut out = format!("# Static IP configuration for {}\n# Generated by Quantix Console\n", interface);
+    for line in existing.lines() {
+        let key = line.split('=').next().unwrap_or("").trim();
+        if line.starts_with('#') || line.trim().is_empty() || updates.iter().any(|(k, _)| *k == key) {
+            continue;
+        }
+        out.push_str(line);
+        out.push('\n');
+    }
+    for (key, value) in updates {
+        out.push_str(&format!("{}={}\n", key, value));
+    }
+    out
+}
+
+/// `/etc/network/interfaces` stanzas for a merged network config.
+fn legacy_interfaces(interface: &str, config: &str) -> String {
+    let get = |key: &str| {
+        config.lines()
+            .find_map(|l| l.strip_prefix(key).and_then(|v| v.strip_prefix('=')))
+            .unwrap_or("")
+            .to_string()
+    };
+    
+    let mut out = format!("auto lo\niface lo inet loopback\n\nauto {}\n", interface);
+    let address = get("IP_ADDRESS");
+    if address.is_empty() {
+        out.push_str(&format!("iface {} inet dhcp\n", interface));
+    } else {
+        out.push_str(&format!("iface {} inet static\n    address {}\n    netmask {}\n", interface, address, get("NETMASK")));
+        let gateway = get("GATEWAY");
+        if !gateway.is_empty() {
+            out.push_str(&format!("    gateway {}\n", gateway));
+        }
+    }
+    
+    let address6 = get("IPV6_ADDRESS");
+    if get("IPV6_MODE") == "static" && !address6.is_empty() {
+        out.push_str(&format!("\niface {} inet6 static\n    address {}\n    netmask {}\n", interface, address6, get("IPV6_PREFIX")));
+        let gateway6 = get("IPV6_GATEWAY");
+        if !gateway6.is_empty() {
+            out.push_str(&format!("    gateway {}\n", gateway6));
+        }
+    } else if get("IPV6_MODE") != "off" {
+        out.push_str(&format!("\niface {} inet6 auto\n", interface));
+    }
+    out
+}
+
+/// Prefix length for a netmask. A plain number is taken as the prefix
+/// itself, which is the only form IPv6 accepts.
+fn netmask_to_cidr(netmask: &str, ipv6: bool) -> u8 {
+    let max = if ipv6 { 128 } else { 32 };
+    if let Ok(prefix) = netmask.trim().trim_start_matches('/').parse::<u8>() {
+        return prefix.min(max);
+    }
+    if ipv6 {
+        return 64; // Default to /64
+    }
+    
     let parts: Vec<u8> = netmask
         .split('.')
         .filter_map(|s| s.parse().ok())
@@ -1188,6 +1480,7 @@ fn ui(f: &mut Frame, app: &App) {
         Screen::WiFi => render_wifi_screen(f, app, chunks[1]),
         Screen::Ssh => render_ssh_screen(f, app, chunks[1]),
         Screen::Cluster => render_cluster_screen(f, app, chunks[1]),
+        Screen::Firewall => render_firewall_screen(f, app, chunks[1]),
         Screen::Diagnostics => render_diagnostics_screen(f, app, chunks[1]),
         Screen::Power => render_power_screen(f, chunks[1]),
         Screen::FactoryReset => render_factory_reset_screen(f, chunks[1]),
@@ -1455,6 +1748,7 @@ fn render_network_screen(f: &mut Frame, area: Rect) {
     lines.push(Line::from(Span::styled("Actions:", Style::default().fg(Color::Cyan))));
     lines.push(Line::from(""));
     lines.push(Line::from("  D - Run DHCP on all interfaces"));
+    lines.push(Line::from("  6 - Enable IPv6 autoconfiguration (SLAAC/DHCPv6)"));
     lines.push(Line::from("  R - Restart network service"));
     lines.push(Line::from("  S - Set static IP (manual entry)"));
     lines.push(Line::from("  W - Configure WiFi"));
@@ -1480,7 +1774,7 @@ fn render_static_ip_screen(f: &mut Frame, app: &App, area: Rect) {
     let fields = [
         ("Interface:", &app.static_ip_config.interface, true),  // true = selector
         ("IP Address:", &app.static_ip_config.ip_address, false),
-        ("Netmask:", &app.static_ip_config.netmask, false),
+        ("Netmask/Pfx:", &app.static_ip_config.netmask, false),
         ("Gateway:", &app.static_ip_config.gateway, false),
         ("DNS Server:", &app.static_ip_config.dns, false),
     ];
@@ -1524,7 +1818,7 @@ fn render_static_ip_screen(f: &mut Frame, app: &App, area: Rect) {
     }
     
     lines.push(Line::from(""));
-    lines.push(Line::from(Span::styled("Type IP address (numbers and dots only)", Style::default().fg(Color::DarkGray))));
+    lines.push(Line::from(Span::styled("IPv4 (192.168.1.10, netmask or /24) or IPv6 (fd00::10, prefix 64)", Style::default().fg(Color::DarkGray))));
     
     let text = Paragraph::new(lines)
         .block(Block::default().borders(Borders::ALL).title("Static IP"))
@@ -1748,15 +2042,18 @@ fn get_network_interfaces() -> Vec<(String, String, String)> {
 }
 
 fn get_interface_ip(iface: &str) -> String {
-    if let Ok(output) = std::process::Command::new("ip")
-        .args(["-4", "addr", "show", iface])
-        .output()
-    {
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        for line in stdout.lines() {
-            if line.contains("inet ") {
-                if let Some(ip) = line.split_whitespace().nth(1) {
-                    return ip.to_string();
+    // Prefer IPv4, fall back to a global IPv6 address
+    for (family, marker) in [("-4", "inet "), ("-6", "inet6 ")] {
+        if let Ok(output) = std::process::Command::new("ip")
+            .args([family, "addr", "show", iface, "scope", "global"])
+            .output()
+        {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            for line in stdout.lines() {
+                if line.contains(marker) {
+                    if let Some(ip) = line.split_whitespace().nth(1) {
+                        return ip.to_string();
+                    }
                 }
             }
         }
@@ -2236,6 +2533,245 @@ fn leave_cluster_api() -> Result<(), String> {
     }
 }
 
+fn handle_firewall_input(app: &mut App, key: KeyCode) {
+    // Typing allowed sources for the selected service
+    if let Some(ref mut buffer) = app.firewall.editing {
+        match key {
+            KeyCode::Esc => app.firewall.editing = None,
+            KeyCode::Backspace => {
+                buffer.pop();
+            }
+            KeyCode::Char(c) if c.is_ascii_hexdigit() || ".:/, ".contains(c) => buffer.push(c),
+            KeyCode::Enter => {
+                let sources: Vec<String> = buffer
+                    .split([',', ' '])
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+                    .collect();
+                let index = app.firewall.selected - 1;
+                if let Some(rule) = app.firewall.config.as_mut().and_then(|c| c.services.get_mut(index)) {
+                    rule.allowed_sources = sources;
+                    app.firewall.dirty = true;
+                }
+                app.firewall.editing = None;
+            }
+            _ => {}
+        }
+        return;
+    }
+    
+    let rows = app.firewall.config.as_ref().map(|c| c.services.len() + 1).unwrap_or(0);
+    match key {
+        KeyCode::Esc | KeyCode::Char('q') => {
+            app.screen = Screen::Main;
+        }
+        KeyCode::Up | KeyCode::Char('k') => {
+            app.firewall.selected = app.firewall.selected.saturating_sub(1);
+        }
+        KeyCode::Down | KeyCode::Char('j') if app.firewall.selected + 1 < rows => {
+            app.firewall.selected += 1;
+        }
+        KeyCode::Char(' ') => {
+            let selected = app.firewall.selected;
+            if let Some(config) = app.firewall.config.as_mut() {
+                if selected == 0 {
+                    config.enabled = !config.enabled;
+                } else if let Some(rule) = config.services.get_mut(selected - 1) {
+                    rule.enabled = !rule.enabled;
+                }
+                app.firewall.dirty = true;
+            }
+        }
+        KeyCode::Enter | KeyCode::Char('e') | KeyCode::Char('E') => {
+            let index = app.firewall.selected.checked_sub(1);
+            if let Some(rule) = index.and_then(|i| app.firewall.config.as_ref().and_then(|c| c.services.get(i))) {
+                app.firewall.editing = Some(rule.allowed_sources.join(", "));
+            }
+        }
+        KeyCode::Char('a') | KeyCode::Char('A') => {
+            let Some(config) = app.firewall.config.clone() else { return };
+            app.set_status("Applying host firewall...");
+            // The local console is not affected by the firewall, so no confirmation is needed
+            let body = serde_json::json!({ "config": config, "confirmTimeoutSecs": 0 });
+            match firewall_api("PUT", "/settings/firewall", Some(body)) {
+                Ok(_) => {
+                    app.firewall.dirty = false;
+                    app.success_message = Some("Host firewall applied".to_string());
+                }
+                Err(e) => app.error_message = Some(format!("Failed to apply firewall: {}", e)),
+            }
+        }
+        KeyCode::Char('c') | KeyCode::Char('C') => {
+            let Some(id) = app.firewall.pending_transaction_id.clone() else { return };
+            match firewall_api("POST", "/settings/firewall/confirm", Some(serde_json::json!({ "transactionId": id }))) {
+                Ok(_) => {
+                    app.success_message = Some("Pending firewall change confirmed".to_string());
+                    load_firewall(app);
+                }
+                Err(e) => app.error_message = Some(format!("Failed to confirm: {}", e)),
+            }
+        }
+        KeyCode::Char('r') | KeyCode::Char('R') => {
+            let Some(id) = app.firewall.pending_transaction_id.clone() else { return };
+            match firewall_api("POST", "/settings/firewall/rollback", Some(serde_json::json!({ "transactionId": id }))) {
+                Ok(_) => {
+                    app.success_message = Some("Pending firewall change rolled back".to_string());
+                    load_firewall(app);
+                }
+                Err(e) => app.error_message = Some(format!("Failed to roll back: {}", e)),
+            }
+        }
+        KeyCode::Char('l') | KeyCode::Char('L') => load_firewall(app),
+        _ => {}
+    }
+}
+
+/// Load the host firewall configuration from the node daemon
+fn load_firewall(app: &mut App) {
+    match firewall_api("GET", "/settings/firewall", None) {
+        Ok(response) => {
+            app.firewall.config = serde_json::from_value(response["config"].clone()).ok();
+            app.firewall.pending_transaction_id = response["pendingTransactionId"].as_str().map(String::from);
+            app.firewall.confirm_deadline = response["confirmDeadline"].as_str().map(String::from);
+            app.firewall.dirty = false;
+            if app.firewall.config.is_none() {
+                app.error_message = Some("Unexpected firewall configuration from node daemon".to_string());
+            }
+        }
+        Err(e) => app.error_message = Some(format!("Failed to load firewall: {}", e)),
+    }
+}
+
+/// Call a host firewall endpoint of the local node daemon
+fn firewall_api(method: &str, path: &str, body: Option<serde_json::Value>) -> Result<serde_json::Value, String> {
+    use std::process::Stdio;
+    
+    let url = format!("https://127.0.0.1:8443/api/v1{}", path);
+    let mut args = vec!["-s".to_string(), "-k".to_string(), "--max-time".to_string(), "10".to_string(), "-X".to_string(), method.to_string()];
+    if let Some(body) = body {
+        args.extend(["-H".to_string(), "Content-Type: application/json".to_string(), "-d".to_string(), body.to_string()]);
+    }
+    args.push(url);
+    
+    let output = std::process::Command::new("curl")
+        .args(&args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|e| format!("Failed to connect to node daemon: {}", e))?;
+    if !output.status.success() {
+        return Err("API request failed - is the node daemon running?".to_string());
+    }
+    
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if stdout.trim().is_empty() {
+        return Ok(serde_json::Value::Null);
+    }
+    let value: serde_json::Value = serde_json::from_str(&stdout)
+        .map_err(|_| format!("Unexpected response: {}", stdout.chars().take(100).collect::<String>()))?;
+    if let Some(message) = value.get("error").and(value.get("message")).and_then(|m| m.as_str()) {
+        return Err(message.to_string());
+    }
+    Ok(value)
+}
+
+fn render_firewall_screen(f: &mut Frame, app: &App, area: Rect) {
+    let mut lines = vec![
+        Line::from(Span::styled("🛡 Host Firewall", Style::default().add_modifier(Modifier::BOLD))),
+        Line::from(""),
+        Line::from(Span::styled("Restrict which networks can reach the management services.", Style::default().fg(Color::DarkGray))),
+        Line::from(Span::styled("Services without allowed sources are open to all networks.", Style::default().fg(Color::DarkGray))),
+        Line::from(""),
+    ];
+    
+    if let Some(ref id) = app.firewall.pending_transaction_id {
+        lines.push(Line::from(vec![
+            Span::styled("⚠ Pending remote change ", Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)),
+            Span::styled(
+                format!("{} - rolls back at {}", id, app.firewall.confirm_deadline.as_deref().unwrap_or("?")),
+                Style::default().fg(Color::Yellow),
+            ),
+        ]));
+        lines.push(Line::from(Span::styled("  C - Confirm it   R - Roll it back now", Style::default().fg(Color::Yellow))));
+        lines.push(Line::from(""));
+    }
+    
+    let Some(ref config) = app.firewall.config else {
+        lines.push(Line::from(Span::styled("Firewall configuration not loaded (L to retry)", Style::default().fg(Color::Red))));
+        let text = Paragraph::new(lines)
+            .block(Block::default().borders(Borders::ALL).title("Host Firewall"))
+            .wrap(Wrap { trim: true });
+        f.render_widget(text, area);
+        return;
+    };
+    
+    let row_style = |selected: bool| {
+        if selected {
+            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::White)
+        }
+    };
+    
+    let selected = app.firewall.selected == 0;
+    lines.push(Line::from(vec![
+        Span::styled(if selected { "▶ " } else { "  " }, row_style(selected)),
+        Span::styled("Firewall: ", Style::default().fg(Color::Gray)),
+        if config.enabled {
+            Span::styled("● ENABLED", Style::default().fg(Color::Green).add_modifier(Modifier::BOLD))
+        } else {
+            Span::styled("○ DISABLED (all ports open)", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))
+        },
+    ]));
+    lines.push(Line::from(""));
+    
+    for (i, rule) in config.services.iter().enumerate() {
+        let selected = app.firewall.selected == i + 1;
+        let (access, access_style) = if !rule.enabled {
+            ("closed".to_string(), Style::default().fg(Color::Red))
+        } else if rule.allowed_sources.is_empty() {
+            ("any source".to_string(), Style::default().fg(Color::Yellow))
+        } else {
+            (rule.allowed_sources.join(", "), Style::default().fg(Color::Green))
+        };
+        lines.push(Line::from(vec![
+            Span::styled(if selected { "▶ " } else { "  " }, row_style(selected)),
+            Span::styled(format!("{:<10}", rule.service), row_style(selected)),
+            Span::styled(format!("{:<22}", rule.ports.join(",")), Style::default().fg(Color::DarkGray)),
+            Span::styled(access, access_style),
+        ]));
+        if selected {
+            if let Some(ref buffer) = app.firewall.editing {
+                lines.push(Line::from(vec![
+                    Span::styled("    Allowed sources: ", Style::default().fg(Color::Gray)),
+                    Span::styled(format!("{}█", buffer), Style::default().fg(Color::Cyan)),
+                ]));
+            }
+        }
+    }
+    
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled("─".repeat(50), Style::default().fg(Color::DarkGray))));
+    if app.firewall.editing.is_some() {
+        lines.push(Line::from(Span::styled("Type addresses or CIDRs separated by commas (empty = any)", Style::default().fg(Color::DarkGray))));
+        lines.push(Line::from(Span::styled("Enter to keep, Esc to cancel", Style::default().fg(Color::Yellow))));
+    } else {
+        lines.push(Line::from("  Space - Toggle firewall / open or close service"));
+        lines.push(Line::from("  E     - Edit allowed sources"));
+        lines.push(Line::from("  A     - Apply changes"));
+        lines.push(Line::from("  L     - Reload from node daemon"));
+        if app.firewall.dirty {
+            lines.push(Line::from(Span::styled("Unapplied changes - press A to apply", Style::default().fg(Color::Yellow))));
+        }
+        lines.push(Line::from(Span::styled("Press Esc to return to main menu", Style::default().fg(Color::Yellow))));
+    }
+    
+    let text = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title("Host Firewall"))
+        .wrap(Wrap { trim: true });
+    f.render_widget(text, area);
+}
+
 fn render_diagnostics_screen(f: &mut Frame, app: &App, area: Rect) {
     let cpus = app.system.cpus();
     let cpu_count = cpus.len();
@@ -2761,6 +3297,34 @@ fn run_dhcp_all() {
     });
 }
 
+/// Accept router advertisements (SLAAC) and ask DHCPv6 for addresses
+/// and DNS on all interfaces except virtual bridges.
+fn run_ipv6_autoconf_all() {
+    use std::process::Stdio;
+    
+    std::thread::spawn(|| {
+        for iface in get_interface_names() {
+            if iface.starts_with("vir") || iface.starts_with("br-") {
+                continue;
+            }
+            // accept_ra=2 keeps RAs working when forwarding is enabled
+            set_ipv6_sysctl(&iface, "accept_ra", "2");
+            set_ipv6_sysctl(&iface, "autoconf", "1");
+            
+            let _ = std::process::Command::new("pkill")
+                .args(["-f", &format!("udhcpc6.*{}", iface)])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .output();
+            let _ = std::process::Command::new("udhcpc6")
+                .args(["-i", &iface, "-b", "-t", "2", "-T", "3", "-S"])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn();
+        }
+    });
+}
+
 fn restart_network() {
     use std::process::Stdio;
     